@@ -0,0 +1,42 @@
+"""Stand-in kernel for the RPC benchmarks (benches/rpc.rs).
+
+Speaks the real stdio protocol (handshake, then one sealed JSON-RPC frame
+per line, see reos.ipc_channel) but does no work: `initialize` reports no
+methods and every other request gets its params back as the result, so a
+round trip measures framing, encryption and the pipe and nothing else.
+
+Run with src/ on PYTHONPATH.
+"""
+
+from __future__ import annotations
+
+import json
+import sys
+
+from reos import ipc_channel
+
+
+def main() -> None:
+    hello = sys.stdin.readline()
+    rust_public = ipc_channel.parse_hello(hello.strip())
+    if rust_public is None:
+        sys.exit("expected a channel handshake")
+    channel, reply = ipc_channel.accept(rust_public)
+    sys.stdout.write(reply + "\n")
+    sys.stdout.flush()
+
+    for line in sys.stdin:
+        if not line.strip():
+            continue
+        req = json.loads(channel.open(line.strip()))
+        if req.get("method") == "initialize":
+            result = {"methods": []}
+        else:
+            result = req.get("params")
+        resp = {"jsonrpc": "2.0", "id": req.get("id"), "result": result}
+        sys.stdout.write(channel.seal(json.dumps(resp, ensure_ascii=False)) + "\n")
+        sys.stdout.flush()
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,187 @@
+//! RPC Layer Benchmarks
+//!
+//! `cargo bench --features bench` (see `benches/README.md` for baselines):
+//!
+//! - `framing`: sealing a request, and opening and parsing a response, at a
+//!   few sizes (the per-frame cost of `ipc.rs` and `json_stream.rs`)
+//! - `round_trip`: one request through the pipe to `echo_kernel.py` and
+//!   back, over the real encrypted channel
+//! - `session_check`: `SessionStore::touch` through the shared `AuthState`
+//!   from 1, 4 and 16 threads, as concurrent kernel requests do
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use reos_tauri_lib::auth::{create_session, AuthState, ClientInfo};
+use reos_tauri_lib::ipc::{Handshake, SecureChannel};
+use reos_tauri_lib::json_stream;
+
+/// Response payload sizes (bytes of row text)
+const SIZES: [usize; 3] = [64, 64 * 1024, 4 * 1024 * 1024];
+
+fn rows(bytes: usize) -> Value {
+    let count = (bytes / 100).max(1);
+    (0..count)
+        .map(|i| json!({ "id": i, "text": "x".repeat(80) }))
+        .collect()
+}
+
+fn framing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("framing");
+    for size in SIZES {
+        let result = rows(size);
+        let request =
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "bench/echo", "params": result });
+        let response = json!({ "jsonrpc": "2.0", "id": 1, "result": result }).to_string();
+        group.throughput(Throughput::Bytes(response.len() as u64));
+
+        let (mut rust, _) = SecureChannel::pair();
+        group.bench_with_input(
+            BenchmarkId::new("seal_request", size),
+            &request,
+            |b, req| b.iter(|| rust.seal_json(req)),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("open_response", size),
+            &response,
+            |b, resp| {
+                b.iter_custom(|iters| {
+                    let (mut rust, mut kernel) = SecureChannel::pair();
+                    let frames: Vec<String> =
+                        (0..iters).map(|_| kernel.seal(resp.as_bytes())).collect();
+                    let start = Instant::now();
+                    for frame in &frames {
+                        let plaintext = rust.open(frame.as_bytes()).unwrap();
+                        let mut parsed = json_stream::parse(&plaintext, 1, Some("bench")).unwrap();
+                        json_stream::materialize(&mut parsed["result"]).unwrap();
+                    }
+                    start.elapsed()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// `echo_kernel.py` behind an open channel
+struct EchoKernel {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    channel: SecureChannel,
+    next_id: u64,
+}
+
+impl EchoKernel {
+    fn start() -> Option<Self> {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let python = std::env::var("REOS_PYTHON").unwrap_or_else(|_| "python3".to_string());
+        let mut child = Command::new(python)
+            .arg(root.join("benches/echo_kernel.py"))
+            .env("PYTHONPATH", root.join("../../../src"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .ok()?;
+        let mut stdin = child.stdin.take()?;
+        let mut stdout = BufReader::new(child.stdout.take()?);
+        let handshake = Handshake::new();
+        writeln!(stdin, "{}", handshake.hello()).ok()?;
+        let mut reply = String::new();
+        stdout.read_line(&mut reply).ok()?;
+        let channel = handshake.finish(reply.trim()).ok()?;
+        Some(Self {
+            child,
+            stdin,
+            stdout,
+            channel,
+            next_id: 1,
+        })
+    }
+
+    fn request(&mut self, params: &Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request =
+            json!({ "jsonrpc": "2.0", "id": id, "method": "bench/echo", "params": params });
+        writeln!(self.stdin, "{}", self.channel.seal_json(&request)).unwrap();
+        self.stdin.flush().unwrap();
+        let mut line = String::new();
+        self.stdout.read_line(&mut line).unwrap();
+        let plaintext = self.channel.open(line.as_bytes()).unwrap();
+        let mut parsed = json_stream::parse(&plaintext, id, Some("bench")).unwrap();
+        json_stream::materialize(&mut parsed["result"]).unwrap();
+        parsed
+    }
+}
+
+impl Drop for EchoKernel {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn round_trip(c: &mut Criterion) {
+    let Some(mut kernel) = EchoKernel::start() else {
+        eprintln!("round_trip skipped: could not start benches/echo_kernel.py (set REOS_PYTHON)");
+        return;
+    };
+    let mut group = c.benchmark_group("round_trip");
+    for size in SIZES {
+        let params = rows(size);
+        group.throughput(Throughput::Bytes(params.to_string().len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &params, |b, params| {
+            b.iter(|| kernel.request(params))
+        });
+    }
+    group.finish();
+}
+
+fn session_check(c: &mut Criterion) {
+    // The store the app shares between commands, locked as requests lock it
+    let state = AuthState::new();
+    let tokens: Vec<String> = (0..64).map(|i| format!("token-{i}")).collect();
+    for (i, token) in tokens.iter().enumerate() {
+        let session = create_session(token.clone(), format!("user{i}"), ClientInfo::default());
+        state.store().insert(session);
+    }
+
+    let mut group = c.benchmark_group("session_check");
+    for threads in [1usize, 4, 16] {
+        group.bench_function(BenchmarkId::from_parameter(threads), |b| {
+            b.iter_custom(|iters| {
+                let per_thread = iters.div_ceil(threads as u64);
+                let start = Instant::now();
+                std::thread::scope(|scope| {
+                    for t in 0..threads {
+                        let state = &state;
+                        let token = &tokens[t % tokens.len()];
+                        scope.spawn(move || {
+                            for _ in 0..per_thread {
+                                state.read().touch(token).unwrap();
+                            }
+                        });
+                    }
+                });
+                // Latency of one validation as a request sees it, times `iters`
+                start
+                    .elapsed()
+                    .mul_f64(iters as f64 / per_thread.max(1) as f64)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().measurement_time(Duration::from_secs(5));
+    targets = framing, round_trip, session_check
+}
+criterion_main!(benches);
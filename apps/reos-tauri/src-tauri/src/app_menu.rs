@@ -0,0 +1,304 @@
+//! Kernel-Declared Application Menu for ReOS
+//!
+//! The kernel describes the native app menu in its `initialize` result
+//! (`menu`: sections with a `title` and `items` of `id`, `title`, optional
+//! `accelerator` and `method`) and sends a new one as an `event` push
+//! tagged `capabilities` when its features change. The sections are added
+//! to the platform's standard menu (Edit, Window, Help, and the app menu on
+//! macOS), before Window.
+//!
+//! Choosing an item asks the focused window to run it (`app-menu-action`):
+//! the window calls `app_menu_run` with its session and the item id, and
+//! the method, taken from the manifest here, goes through the same checks
+//! as `kernel_request`.
+//!
+//! Invalid items and sections, or those beyond the caps, are ignored. An
+//! accelerator that is malformed, lacks Ctrl, Cmd or Alt, is already taken,
+//! or belongs to the standard menu (copy, paste, quit, ...) is dropped and
+//! the item kept.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::menu::{Menu, MenuItem, Submenu, WINDOW_SUBMENU_ID};
+use tauri::{AppHandle, Runtime};
+
+use crate::locks::Recover;
+use crate::quick_actions::{is_valid_id, is_valid_title};
+
+/// Event asking a window to run a menu item (payload: its id)
+pub const APP_MENU_EVENT: &str = "app-menu-action";
+
+/// Menu id prefix of kernel-declared items
+pub const MENU_PREFIX: &str = "app-menu:";
+
+const CAPABILITIES_TAG: &str = "capabilities";
+
+const MAX_SECTIONS: usize = 6;
+const MAX_ITEMS: usize = 16;
+
+/// Accelerators of the standard menu
+const RESERVED_ACCELERATORS: &[&str] = &[
+    "CmdOrCtrl+A",
+    "CmdOrCtrl+C",
+    "CmdOrCtrl+H",
+    "CmdOrCtrl+M",
+    "CmdOrCtrl+Q",
+    "CmdOrCtrl+V",
+    "CmdOrCtrl+W",
+    "CmdOrCtrl+X",
+    "CmdOrCtrl+Z",
+    "CmdOrCtrl+Shift+Z",
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MenuEntry {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub accelerator: Option<String>,
+    pub method: String,
+}
+
+impl MenuEntry {
+    pub fn menu_id(&self) -> String {
+        format!("{MENU_PREFIX}{}", self.id)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MenuSection {
+    pub title: String,
+    pub items: Vec<MenuEntry>,
+}
+
+/// `accelerator` in canonical form (`CmdOrCtrl+Alt+Shift+K`), or `None` if
+/// it isn't a modifier combination plus `A`-`Z`, `0`-`9` or `F1`-`F24`
+pub fn normalize_accelerator(accelerator: &str) -> Option<String> {
+    let (mut cmd_or_ctrl, mut ctrl, mut alt, mut shift) = (false, false, false, false);
+    let mut key = None;
+    for part in accelerator.split('+').map(str::trim) {
+        match part.to_ascii_lowercase().as_str() {
+            "cmdorctrl" | "commandorcontrol" => cmd_or_ctrl = true,
+            "ctrl" | "control" => ctrl = true,
+            "alt" | "option" => alt = true,
+            "shift" => shift = true,
+            name if key.is_none() => {
+                let is_char = name.len() == 1 && name.bytes().all(|b| b.is_ascii_alphanumeric());
+                let is_function = name
+                    .strip_prefix('f')
+                    .and_then(|n| n.parse::<u8>().ok())
+                    .is_some_and(|n| (1..=24).contains(&n) && !name.starts_with("f0"));
+                if !(is_char || is_function) {
+                    return None;
+                }
+                key = Some(name.to_ascii_uppercase());
+            }
+            _ => return None,
+        }
+    }
+    if !(cmd_or_ctrl || ctrl || alt) {
+        return None;
+    }
+    let mut parts = Vec::new();
+    for (on, name) in [
+        (cmd_or_ctrl, "CmdOrCtrl"),
+        (ctrl, "Ctrl"),
+        (alt, "Alt"),
+        (shift, "Shift"),
+    ] {
+        if on {
+            parts.push(name.to_string());
+        }
+    }
+    parts.push(key?);
+    Some(parts.join("+"))
+}
+
+/// Menu sections from a manifest's `menu` value
+fn parse_sections(menu: Option<&Value>) -> Vec<MenuSection> {
+    let Some(entries) = menu.and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    let mut ids = HashSet::new();
+    let mut accelerators: HashSet<String> = RESERVED_ACCELERATORS
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+    let mut sections = Vec::new();
+    for entry in entries {
+        let Ok(section) = serde_json::from_value::<MenuSection>(entry.clone()) else {
+            continue;
+        };
+        if !is_valid_title(&section.title) {
+            continue;
+        }
+        let mut items = Vec::new();
+        for mut item in section.items {
+            if !is_valid_id(&item.id)
+                || !is_valid_title(&item.title)
+                || item.method.is_empty()
+                || !ids.insert(item.id.clone())
+            {
+                continue;
+            }
+            item.accelerator = item
+                .accelerator
+                .as_deref()
+                .and_then(normalize_accelerator)
+                .filter(|a| accelerators.insert(a.clone()));
+            items.push(item);
+            if items.len() == MAX_ITEMS {
+                break;
+            }
+        }
+        if !items.is_empty() {
+            sections.push(MenuSection {
+                title: section.title,
+                items,
+            });
+        }
+        if sections.len() == MAX_SECTIONS {
+            break;
+        }
+    }
+    sections
+}
+
+/// Menu sections from an `initialize` result
+pub fn from_manifest(result: &Value) -> Vec<MenuSection> {
+    parse_sections(result.get("menu"))
+}
+
+/// New menu sections from a kernel push event (`None` unless tagged
+/// `capabilities` with a `menu`)
+pub fn from_capabilities_event(event: &Value) -> Option<Vec<MenuSection>> {
+    let params = event.get("params")?;
+    if params.get("tag").and_then(Value::as_str) != Some(CAPABILITIES_TAG) {
+        return None;
+    }
+    let menu = params.get("menu")?;
+    Some(parse_sections(Some(menu)))
+}
+
+/// The standard menu with `sections` added before Window
+pub fn build<R: Runtime>(app: &AppHandle<R>, sections: &[MenuSection]) -> tauri::Result<Menu<R>> {
+    let menu = Menu::default(app)?;
+    let items = menu.items()?;
+    let position = items
+        .iter()
+        .position(|item| item.id().as_ref() == WINDOW_SUBMENU_ID)
+        .unwrap_or(items.len());
+    for (offset, section) in sections.iter().enumerate() {
+        let submenu = Submenu::new(app, &section.title, true)?;
+        for entry in &section.items {
+            submenu.append(&MenuItem::with_id(
+                app,
+                entry.menu_id(),
+                &entry.title,
+                true,
+                entry.accelerator.as_deref(),
+            )?)?;
+        }
+        menu.insert(&submenu, position + offset)?;
+    }
+    Ok(menu)
+}
+
+/// Menu sections of the running kernel
+#[derive(Default)]
+pub struct AppMenuState(pub Mutex<Vec<MenuSection>>);
+
+impl AppMenuState {
+    pub fn find(&self, id: &str) -> Option<MenuEntry> {
+        self.0
+            .lock_or_recover()
+            .iter()
+            .flat_map(|section| &section.items)
+            .find(|item| item.id == id)
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_normalize_accelerator() {
+        assert_eq!(
+            normalize_accelerator("shift+cmdorctrl+n").as_deref(),
+            Some("CmdOrCtrl+Shift+N")
+        );
+        assert_eq!(normalize_accelerator("Alt+F5").as_deref(), Some("Alt+F5"));
+        assert_eq!(normalize_accelerator("Shift+N"), None);
+        assert_eq!(normalize_accelerator("CmdOrCtrl+F25"), None);
+        assert_eq!(normalize_accelerator("CmdOrCtrl+Space"), None);
+        assert_eq!(normalize_accelerator("CmdOrCtrl+A+B"), None);
+        assert_eq!(normalize_accelerator("CmdOrCtrl"), None);
+    }
+
+    #[test]
+    fn test_from_manifest_filters_items_and_accelerators() {
+        let result = json!({ "menu": [
+            { "title": "Conversation", "items": [
+                { "id": "new", "title": "New", "accelerator": "CmdOrCtrl+Shift+N", "method": "conversation/start" },
+                { "id": "copy", "title": "Copy", "accelerator": "CmdOrCtrl+C", "method": "ping" },
+                { "id": "again", "title": "Again", "accelerator": "cmdorctrl+shift+n", "method": "ping" },
+                { "id": "Bad Id", "title": "Bad", "method": "ping" },
+            ] },
+            { "title": "Empty", "items": [{ "id": "new", "title": "Duplicate", "method": "ping" }] },
+            { "title": "", "items": [{ "id": "untitled", "title": "Untitled", "method": "ping" }] },
+        ] });
+        let sections = from_manifest(&result);
+        assert_eq!(sections.len(), 1);
+        let items = &sections[0].items;
+        assert_eq!(
+            items.iter().map(|i| i.id.as_str()).collect::<Vec<_>>(),
+            ["new", "copy", "again"]
+        );
+        assert_eq!(items[0].accelerator.as_deref(), Some("CmdOrCtrl+Shift+N"));
+        assert_eq!(items[1].accelerator, None);
+        assert_eq!(items[2].accelerator, None);
+        assert_eq!(items[0].menu_id(), "app-menu:new");
+        assert!(from_manifest(&json!({ "protocolVersion": "jsonrpc-2.0" })).is_empty());
+    }
+
+    #[test]
+    fn test_caps_sections_and_items() {
+        let items: Vec<Value> = (0..40)
+            .map(|i| json!({ "id": format!("i{i}"), "title": "I", "method": "ping" }))
+            .collect();
+        let sections: Vec<Value> = (0..10)
+            .map(|s| {
+                let items: Vec<Value> = items
+                    .iter()
+                    .map(|i| {
+                        let mut i = i.clone();
+                        i["id"] = json!(format!("s{s}-{}", i["id"].as_str().unwrap()));
+                        i
+                    })
+                    .collect();
+                json!({ "title": "S", "items": items })
+            })
+            .collect();
+        let parsed = from_manifest(&json!({ "menu": sections }));
+        assert_eq!(parsed.len(), MAX_SECTIONS);
+        assert!(parsed.iter().all(|s| s.items.len() == MAX_ITEMS));
+    }
+
+    #[test]
+    fn test_capabilities_event() {
+        let event = json!({ "method": "event", "params": { "tag": "capabilities", "menu": [
+            { "title": "Calendar", "items": [{ "id": "calendar", "title": "Calendar", "method": "cairn/thunderbird/status" }] },
+        ] } });
+        let sections = from_capabilities_event(&event).unwrap();
+        assert_eq!(sections[0].items[0].method, "cairn/thunderbird/status");
+        let cleared = json!({ "params": { "tag": "capabilities", "menu": [] } });
+        assert_eq!(from_capabilities_event(&cleared), Some(Vec::new()));
+        let notify = json!({ "params": { "tag": "notify", "title": "Hi" } });
+        assert_eq!(from_capabilities_event(&notify), None);
+    }
+}
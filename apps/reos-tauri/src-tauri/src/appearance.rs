@@ -0,0 +1,368 @@
+//! System Appearance Bridge for ReOS
+//!
+//! Reads the desktop's light/dark preference, accent color and text scaling
+//! so the frontend (and the kernel, for what it renders itself) can follow
+//! them. Changes are emitted to every window as `appearance-changed` and
+//! sent to the kernel as `appearance/set`.
+//!
+//! Platforms:
+//! - Linux: the XDG desktop portal's Settings (`color-scheme`,
+//!   `accent-color`, and GNOME's `text-scaling-factor`), watched through
+//!   `SettingChanged`
+//! - macOS: `defaults` (`AppleInterfaceStyle`, `AppleAccentColor`)
+//! - Windows: `Personalize\AppsUseLightTheme`, `DWM\AccentColor` and
+//!   `Accessibility\TextScaleFactor` in the registry
+//!
+//! Whatever the platform doesn't report falls back to the main window's
+//! theme, no accent and a scale of 1. Outside Linux changes are picked up
+//! when the window theme changes.
+
+use serde::Serialize;
+use std::sync::Mutex;
+
+use crate::locks::Recover;
+
+/// Event carrying the new `Appearance`
+pub const APPEARANCE_EVENT: &str = "appearance-changed";
+
+const MIN_FONT_SCALE: f64 = 0.5;
+const MAX_FONT_SCALE: f64 = 3.0;
+
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorScheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Appearance {
+    pub scheme: ColorScheme,
+    /// `#rrggbb`
+    pub accent: Option<String>,
+    /// Text size relative to the platform default
+    pub font_scale: f64,
+}
+
+impl Default for Appearance {
+    fn default() -> Self {
+        Self {
+            scheme: ColorScheme::default(),
+            accent: None,
+            font_scale: 1.0,
+        }
+    }
+}
+
+/// What the platform reported (`None` = unknown)
+#[derive(Debug, Default, PartialEq)]
+struct Detected {
+    scheme: Option<ColorScheme>,
+    accent: Option<String>,
+    font_scale: Option<f64>,
+}
+
+fn hex(r: u8, g: u8, b: u8) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// `#rrggbb` from color channels in 0..=1 (`None` if any is out of range,
+/// which the portal uses for "no accent")
+fn accent_from_unit(r: f64, g: f64, b: f64) -> Option<String> {
+    let channel = |c: f64| (0.0..=1.0).contains(&c).then(|| (c * 255.0).round() as u8);
+    Some(hex(channel(r)?, channel(g)?, channel(b)?))
+}
+
+/// Scale clamped to a sane range (1 for nonsense values)
+fn clamp_scale(scale: f64) -> f64 {
+    if scale.is_finite() && scale > 0.0 {
+        scale.clamp(MIN_FONT_SCALE, MAX_FONT_SCALE)
+    } else {
+        1.0
+    }
+}
+
+/// The current appearance, with `fallback` as the scheme if the platform
+/// doesn't report one
+pub fn detect(fallback: ColorScheme) -> Appearance {
+    let detected = platform::detect();
+    Appearance {
+        scheme: detected.scheme.unwrap_or(fallback),
+        accent: detected.accent,
+        font_scale: detected.font_scale.map_or(1.0, clamp_scale),
+    }
+}
+
+/// Call `on_change` from a background thread whenever the platform's
+/// appearance settings change
+///
+/// Best-effort: if the platform service is unavailable nothing is watched.
+pub fn watch(on_change: impl Fn() + Send + 'static) {
+    platform::watch(on_change);
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{accent_from_unit, ColorScheme, Detected};
+    use zbus::blocking::{Connection, Proxy};
+    use zbus::zvariant::{OwnedValue, Value};
+
+    const APPEARANCE: &str = "org.freedesktop.appearance";
+    const INTERFACE: &str = "org.gnome.desktop.interface";
+
+    fn settings(conn: &Connection) -> zbus::Result<Proxy<'static>> {
+        Proxy::new(
+            conn,
+            "org.freedesktop.portal.Desktop",
+            "/org/freedesktop/portal/desktop",
+            "org.freedesktop.portal.Settings",
+        )
+    }
+
+    /// `Read` wraps the value in an extra variant on older portals
+    fn read(settings: &Proxy<'_>, namespace: &str, key: &str) -> Option<Value<'static>> {
+        let value: OwnedValue = settings.call("Read", &(namespace, key)).ok()?;
+        let mut value = Value::from(value);
+        while let Value::Value(inner) = value {
+            value = *inner;
+        }
+        Some(value)
+    }
+
+    pub(super) fn scheme_from_portal(value: u32) -> Option<ColorScheme> {
+        match value {
+            1 => Some(ColorScheme::Dark),
+            2 => Some(ColorScheme::Light),
+            _ => None,
+        }
+    }
+
+    pub fn detect() -> Detected {
+        let Ok(settings) = Connection::session().and_then(|conn| settings(&conn)) else {
+            return Detected::default();
+        };
+        let scheme = read(&settings, APPEARANCE, "color-scheme")
+            .and_then(|v| u32::try_from(v).ok())
+            .and_then(scheme_from_portal);
+        let accent = read(&settings, APPEARANCE, "accent-color")
+            .and_then(|v| <(f64, f64, f64)>::try_from(v).ok())
+            .and_then(|(r, g, b)| accent_from_unit(r, g, b));
+        let font_scale =
+            read(&settings, INTERFACE, "text-scaling-factor").and_then(|v| f64::try_from(v).ok());
+        Detected {
+            scheme,
+            accent,
+            font_scale,
+        }
+    }
+
+    pub fn watch(on_change: impl Fn() + Send + 'static) {
+        let changes = Connection::session()
+            .and_then(|conn| settings(&conn))
+            .and_then(|settings| settings.receive_signal("SettingChanged"));
+        let changes = match changes {
+            Ok(changes) => changes,
+            Err(e) => {
+                tracing::warn!(error = %e, "appearance watcher unavailable");
+                return;
+            }
+        };
+        std::thread::spawn(move || {
+            for msg in changes {
+                let namespace = msg.body().deserialize::<(String, String, OwnedValue)>();
+                if matches!(namespace, Ok((ns, _, _)) if ns == APPEARANCE || ns == INTERFACE) {
+                    on_change();
+                }
+            }
+        });
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{hex, ColorScheme, Detected};
+    use std::process::Command;
+
+    fn read_global(key: &str) -> Option<String> {
+        let output = Command::new("defaults")
+            .args(["read", "-g", key])
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// System accent colors by `AppleAccentColor` (unset = blue)
+    fn accent(value: Option<i64>) -> Option<String> {
+        let (r, g, b) = match value {
+            None | Some(4) => (0x00, 0x7a, 0xff),
+            Some(-1) => (0x98, 0x98, 0x98),
+            Some(0) => (0xe0, 0x38, 0x3e),
+            Some(1) => (0xf7, 0x82, 0x1b),
+            Some(2) => (0xff, 0xc6, 0x00),
+            Some(3) => (0x62, 0xba, 0x46),
+            Some(5) => (0x95, 0x3d, 0x96),
+            Some(6) => (0xf7, 0x4f, 0x9e),
+            Some(_) => return None,
+        };
+        Some(hex(r, g, b))
+    }
+
+    pub fn detect() -> Detected {
+        // AppleInterfaceStyle is only set in dark mode
+        let scheme = match read_global("AppleInterfaceStyle").as_deref() {
+            Some("Dark") => ColorScheme::Dark,
+            _ => ColorScheme::Light,
+        };
+        let accent_color = read_global("AppleAccentColor").and_then(|v| v.parse().ok());
+        Detected {
+            scheme: Some(scheme),
+            accent: accent(accent_color),
+            font_scale: None,
+        }
+    }
+
+    pub fn watch(_on_change: impl Fn() + Send + 'static) {}
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{hex, ColorScheme, Detected};
+    use std::process::Command;
+
+    fn read_dword(key: &str, name: &str) -> Option<u32> {
+        let output = Command::new("reg")
+            .args(["query", key, "/v", name])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        super::reg_dword(&String::from_utf8_lossy(&output.stdout), name)
+    }
+
+    pub fn detect() -> Detected {
+        let light = read_dword(
+            r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+            "AppsUseLightTheme",
+        );
+        // 0xAABBGGRR
+        let accent = read_dword(r"HKCU\Software\Microsoft\Windows\DWM", "AccentColor")
+            .map(|abgr| hex(abgr as u8, (abgr >> 8) as u8, (abgr >> 16) as u8));
+        let text_scale = read_dword(r"HKCU\Software\Microsoft\Accessibility", "TextScaleFactor");
+        Detected {
+            scheme: light.map(|light| {
+                if light == 0 {
+                    ColorScheme::Dark
+                } else {
+                    ColorScheme::Light
+                }
+            }),
+            accent,
+            font_scale: text_scale.map(|percent| f64::from(percent) / 100.0),
+        }
+    }
+
+    pub fn watch(_on_change: impl Fn() + Send + 'static) {}
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+mod platform {
+    use super::Detected;
+
+    pub fn detect() -> Detected {
+        Detected::default()
+    }
+
+    pub fn watch(_on_change: impl Fn() + Send + 'static) {}
+}
+
+/// Value of the `REG_DWORD` `name` in `reg query` output
+#[cfg(any(target_os = "windows", test))]
+fn reg_dword(output: &str, name: &str) -> Option<u32> {
+    output.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        if fields.next()? != name || fields.next()? != "REG_DWORD" {
+            return None;
+        }
+        u32::from_str_radix(fields.next()?.strip_prefix("0x")?, 16).ok()
+    })
+}
+
+/// Last appearance sent to windows and the kernel
+#[derive(Default)]
+pub struct AppearanceState(pub Mutex<Appearance>);
+
+impl AppearanceState {
+    /// Store `appearance`; true if it differs from the last one
+    pub fn update(&self, appearance: Appearance) -> bool {
+        let mut current = self.0.lock_or_recover();
+        if *current == appearance {
+            return false;
+        }
+        *current = appearance;
+        true
+    }
+
+    pub fn current(&self) -> Appearance {
+        self.0.lock_or_recover().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accent_from_unit() {
+        assert_eq!(accent_from_unit(1.0, 0.5, 0.0).as_deref(), Some("#ff8000"));
+        // The portal reports "no accent" as out-of-range channels
+        assert_eq!(accent_from_unit(-1.0, -1.0, -1.0), None);
+        assert_eq!(accent_from_unit(0.2, 1.5, 0.2), None);
+    }
+
+    #[test]
+    fn test_clamp_scale() {
+        assert_eq!(clamp_scale(1.25), 1.25);
+        assert_eq!(clamp_scale(10.0), MAX_FONT_SCALE);
+        assert_eq!(clamp_scale(0.1), MIN_FONT_SCALE);
+        assert_eq!(clamp_scale(0.0), 1.0);
+        assert_eq!(clamp_scale(f64::NAN), 1.0);
+    }
+
+    #[test]
+    fn test_reg_dword() {
+        let output = "\r\nHKEY_CURRENT_USER\\Software\\Microsoft\\Windows\\DWM\r\n    \
+                      AccentColor    REG_DWORD    0xff9e5a00\r\n\r\n";
+        assert_eq!(reg_dword(output, "AccentColor"), Some(0xff9e5a00));
+        assert_eq!(reg_dword(output, "ColorPrevalence"), None);
+        assert_eq!(
+            reg_dword("    AccentColor    REG_SZ    blue", "AccentColor"),
+            None
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_portal_color_scheme() {
+        assert_eq!(platform::scheme_from_portal(1), Some(ColorScheme::Dark));
+        assert_eq!(platform::scheme_from_portal(2), Some(ColorScheme::Light));
+        assert_eq!(platform::scheme_from_portal(0), None);
+    }
+
+    #[test]
+    fn test_update_reports_changes() {
+        let state = AppearanceState::default();
+        assert!(!state.update(Appearance::default()));
+        let dark = Appearance {
+            scheme: ColorScheme::Dark,
+            ..Appearance::default()
+        };
+        assert!(state.update(dark.clone()));
+        assert!(!state.update(dark.clone()));
+        assert_eq!(state.current(), dark);
+    }
+}
@@ -0,0 +1,279 @@
+//! Authentication Audit Log
+//!
+//! Append-only JSON-lines record of authentication events, written by the
+//! Rust shell so that a compromised kernel can't erase the trail. The file
+//! lives in the app data directory and rotates by size:
+//! `auth-audit.jsonl` -> `auth-audit.jsonl.1` -> ... -> `.{AUDIT_KEEP_FILES}`.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, TryLockError};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::error;
+
+use crate::locks::Recover;
+
+/// Rotate once the active file reaches this size (5 MiB)
+pub const AUDIT_MAX_BYTES: u64 = 5 * 1024 * 1024;
+/// Number of rotated files kept next to the active one
+pub const AUDIT_KEEP_FILES: u32 = 5;
+
+/// Kinds of authentication events
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEvent {
+    LoginSuccess,
+    LoginFailure,
+    SecondFactorRequired,
+    SecondFactorFailure,
+    SecondFactorDisabled,
+    Logout,
+    SessionExpired,
+    Lockout,
+    PasswordChanged,
+    Lock,
+    Unlock,
+    UnlockFailure,
+    StepUp,
+    StepUpFailure,
+    SessionRestored,
+    SessionsRevoked,
+    MethodDenied,
+    ConsentGranted,
+    ConsentDenied,
+    Panic,
+    StateReset,
+    CommandDenied,
+    KernelRestarted,
+    UpdateInstalled,
+    DeepLinkRejected,
+    ExternalOpened,
+    ExternalOpenDenied,
+    LogLevelChanged,
+    CrashReportApproved,
+    CrashReportDeclined,
+    DiagnosticsExported,
+}
+
+impl AuditEvent {
+    /// A failed attempt to authenticate (counted in `metrics`)
+    pub fn is_auth_failure(self) -> bool {
+        matches!(
+            self,
+            AuditEvent::LoginFailure
+                | AuditEvent::SecondFactorFailure
+                | AuditEvent::UnlockFailure
+                | AuditEvent::StepUpFailure
+                | AuditEvent::Lockout
+        )
+    }
+}
+
+/// One line of the audit log
+#[derive(Serialize)]
+pub struct AuditEntry {
+    /// Unix time in milliseconds
+    pub ts: u128,
+    pub event: AuditEvent,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(event: AuditEvent) -> Self {
+        Self {
+            ts: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or(0),
+            event,
+            username: None,
+            session_id: None,
+            window: None,
+            detail: None,
+        }
+    }
+
+    pub fn username(mut self, username: &str) -> Self {
+        self.username = Some(username.to_string());
+        self
+    }
+
+    pub fn session_id(mut self, session_id: &str) -> Self {
+        self.session_id = Some(session_id.to_string());
+        self
+    }
+
+    pub fn window(mut self, label: &str) -> Self {
+        self.window = Some(label.to_string());
+        self
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// Size-rotated JSON-lines writer
+pub struct AuditLog {
+    path: Option<PathBuf>,
+    file: Option<File>,
+}
+
+impl AuditLog {
+    /// Log to `path`; `None` disables persistence (no data dir available)
+    pub fn open(path: Option<PathBuf>) -> Self {
+        Self { path, file: None }
+    }
+
+    pub fn append(&mut self, entry: &AuditEntry) -> std::io::Result<()> {
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 > AUDIT_MAX_BYTES {
+            self.file = None;
+            rotate(&path)?;
+        }
+
+        if self.file.is_none() {
+            self.file = Some(open_append(&path)?);
+        }
+        let file = self.file.as_mut().expect("audit file opened above");
+        file.write_all(&line)?;
+        file.flush()
+    }
+}
+
+/// Thread-safe audit log
+pub struct AuditState(pub Mutex<AuditLog>);
+
+impl AuditState {
+    pub fn open(path: Option<PathBuf>) -> Self {
+        Self(Mutex::new(AuditLog::open(path)))
+    }
+
+    /// Append an entry (best effort: auditing never blocks authentication)
+    pub fn record(&self, mut entry: AuditEntry) {
+        if entry.event.is_auth_failure() {
+            if let Ok(Value::String(name)) = serde_json::to_value(entry.event) {
+                crate::metrics::record_auth_failure(&name);
+            }
+        }
+        entry.detail = entry.detail.map(|detail| crate::redact::text(&detail));
+        if let Err(e) = self.0.lock_or_recover().append(&entry) {
+            error!(error = %e, "auth audit log write failed");
+        }
+    }
+
+    /// Append without waiting for the lock (panic hook: the panicking
+    /// thread may be the one holding it)
+    pub fn try_record(&self, mut entry: AuditEntry) {
+        let mut log = match self.0.try_lock() {
+            Ok(log) => log,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => return,
+        };
+        entry.detail = entry.detail.map(|detail| crate::redact::text(&detail));
+        if let Err(e) = log.append(&entry) {
+            error!(error = %e, "auth audit log write failed");
+        }
+    }
+}
+
+pub fn open_append(path: &Path) -> std::io::Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+pub fn rotated_path(path: &Path, n: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+/// Shift `path.N` -> `path.N+1` (dropping the oldest), then `path` -> `path.1`
+pub fn rotate(path: &Path) -> std::io::Result<()> {
+    let _ = fs::remove_file(rotated_path(path, AUDIT_KEEP_FILES));
+    for n in (1..AUDIT_KEEP_FILES).rev() {
+        let from = rotated_path(path, n);
+        if from.exists() {
+            fs::rename(&from, rotated_path(path, n + 1))?;
+        }
+    }
+    fs::rename(path, rotated_path(path, 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("reos-audit-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_entries_are_json_lines() {
+        let dir = temp_dir("lines");
+        let path = dir.join("auth-audit.jsonl");
+        let mut log = AuditLog::open(Some(path.clone()));
+        log.append(&AuditEntry::new(AuditEvent::LoginSuccess).username("alice"))
+            .unwrap();
+        log.append(&AuditEntry::new(AuditEvent::Logout).session_id("abcd"))
+            .unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["event"], "login_success");
+        assert_eq!(lines[0]["username"], "alice");
+        assert!(lines[1].get("username").is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rotation_shifts_files() {
+        let dir = temp_dir("rotate");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("auth-audit.jsonl");
+        fs::write(&path, "current").unwrap();
+        fs::write(rotated_path(&path, 1), "older").unwrap();
+
+        rotate(&path).unwrap();
+        assert!(!path.exists());
+        assert_eq!(
+            fs::read_to_string(rotated_path(&path, 1)).unwrap(),
+            "current"
+        );
+        assert_eq!(fs::read_to_string(rotated_path(&path, 2)).unwrap(), "older");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
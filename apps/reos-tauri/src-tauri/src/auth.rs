@@ -7,41 +7,215 @@
 //! - Frontend sends credentials to Python kernel via auth/login RPC
 //! - Python validates via PAM, derives encryption key
 //! - Python returns session token to Rust
-//! - Rust stores session token and validates on each request
+//! - Rust stores a SHA-256 hash of the token and validates on each request
 //! - Python handles encrypted storage with the derived key
+//!
+//! The store sits behind an `RwLock`: validating a request (`check`,
+//! `touch`) only takes the read lock, since recording activity goes
+//! through the session's own `Activity` cell, so requests from several
+//! windows validate in parallel. Logins, logouts, locking and cleanup take
+//! the write lock.
 
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::locks::{Recover, RecoverRw};
+use crate::password_policy::PolicyViolation;
+use crate::roles::Role;
+use crate::secret::SecretString;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Why a session token can't be used for a request
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AuthError {
+    #[error("Invalid or expired session")]
+    InvalidSession,
+    /// Reads the same as `InvalidSession` to callers, so a live, locked
+    /// token can't be told from a dead one; match the variant internally
+    #[error("Invalid or expired session")]
+    Locked,
+    #[error("Re-authentication required")]
+    ReauthRequired,
+}
+
+/// Session idle timeout in seconds (`session.idle_timeout_secs`, default 15 minutes)
+static SESSION_IDLE_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(15 * 60);
+
+/// Current session idle timeout
+pub fn idle_timeout() -> Duration {
+    Duration::from_secs(SESSION_IDLE_TIMEOUT_SECS.load(Ordering::Relaxed))
+}
+
+/// Install the configured idle timeout (at startup)
+pub fn set_idle_timeout(timeout: Duration) {
+    SESSION_IDLE_TIMEOUT_SECS.store(timeout.as_secs(), Ordering::Relaxed);
+}
+
+/// How long past the idle timeout a quick-unlock session stays resumable (8 hours)
+pub const QUICK_UNLOCK_GRACE: Duration = Duration::from_secs(8 * 60 * 60);
+
+/// Activity this recent isn't recorded again (`Session::refresh_coalesced`):
+/// chatty screens send many requests a second, and the idle timeout is
+/// minutes long
+pub const REFRESH_COALESCE: Duration = Duration::from_secs(5);
+
+/// SHA-256 of a session token (what the store keeps instead of the token)
+pub type TokenHash = [u8; 32];
+
+/// Last time a session was used, updatable through a shared reference
+/// (the store's read lock) under its own small lock
+pub struct Activity(Mutex<Instant>);
+
+impl Activity {
+    pub fn new(at: Instant) -> Self {
+        Self(Mutex::new(at))
+    }
+
+    pub fn get(&self) -> Instant {
+        *self.0.lock_or_recover()
+    }
+
+    pub fn set(&self, at: Instant) {
+        *self.0.lock_or_recover() = at;
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.get().elapsed()
+    }
 
-/// Session idle timeout (15 minutes)
-const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+    /// Set to now unless set within `window`; whether it was
+    fn bump_unless_within(&self, window: Duration) -> bool {
+        let mut at = self.0.lock_or_recover();
+        if at.elapsed() < window {
+            return false;
+        }
+        *at = Instant::now();
+        true
+    }
+}
 
 /// A user session with authentication state
 pub struct Session {
-    pub token: String,
+    pub token_hash: TokenHash,
+    /// Truncated token for logs and events (see `session_id`)
+    pub session_id: String,
     pub username: String,
     pub created_at: Instant,
-    pub last_activity: Instant,
+    /// Last time the user proved their password (login, step-up, password unlock)
+    pub authenticated_at: Instant,
+    pub last_activity: Activity,
+    /// User has a PIN or biometric: after idling out the session locks instead of dying
+    pub quick_unlock: bool,
+    /// Explicitly locked (system suspend / screen lock) regardless of activity
+    pub locked_at: Option<Instant>,
+    /// Authorization level (guest sessions have no PAM credentials or key)
+    pub role: Role,
+    /// Where the login came from (window, app build, device)
+    pub client: ClientInfo,
+    /// Consent categories the user approved for this session (see `consent.rs`)
+    pub consents: HashSet<String>,
 }
 
 impl Session {
     /// Check if session has expired due to inactivity
     pub fn is_expired(&self) -> bool {
-        self.last_activity.elapsed() > SESSION_IDLE_TIMEOUT
+        self.last_activity.elapsed() > idle_timeout()
+    }
+
+    /// Locked but still resumable with the user's PIN: either idled out with
+    /// quick unlock enabled, or explicitly locked within the grace period
+    pub fn is_locked(&self) -> bool {
+        if let Some(locked_at) = self.locked_at {
+            return locked_at.elapsed() <= QUICK_UNLOCK_GRACE;
+        }
+        self.is_expired()
+            && self.quick_unlock
+            && self.last_activity.elapsed() <= idle_timeout() + QUICK_UNLOCK_GRACE
+    }
+
+    /// Usable for requests: neither idled out nor explicitly locked
+    fn is_active(&self) -> bool {
+        !self.is_expired() && self.locked_at.is_none()
+    }
+
+    /// Time left before an active session idles out (`None` if not active)
+    pub fn idle_remaining(&self) -> Option<Duration> {
+        if !self.is_active() {
+            return None;
+        }
+        idle_timeout().checked_sub(self.last_activity.elapsed())
     }
 
     /// Update last activity timestamp
-    pub fn refresh(&mut self) {
-        self.last_activity = Instant::now();
+    pub fn refresh(&self) {
+        self.last_activity.set(Instant::now());
+    }
+
+    /// `refresh` unless already done within `REFRESH_COALESCE`; whether it
+    /// refreshed
+    pub fn refresh_coalesced(&self) -> bool {
+        self.last_activity.bump_unless_within(REFRESH_COALESCE)
+    }
+
+    /// Lock the session until it is unlocked (no-op if already locked)
+    pub fn lock(&mut self) {
+        self.locked_at.get_or_insert_with(Instant::now);
+    }
+
+    /// Resume a locked session
+    pub fn unlock(&mut self) {
+        self.locked_at = None;
+        self.refresh();
+    }
+
+    /// Loggable description of this session
+    pub fn info(&self) -> SessionInfo {
+        SessionInfo {
+            username: self.username.clone(),
+            session_id: self.session_id.clone(),
+            role: self.role,
+            client: self.client.clone(),
+        }
+    }
+}
+
+/// Client metadata captured at login, for kernel-side audit logs
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct ClientInfo {
+    /// Label of the window that logged in
+    pub window: String,
+    pub app_version: String,
+    pub hostname: String,
+    /// `std::env::consts::OS` (linux, macos, windows, ...)
+    pub platform: String,
+}
+
+impl ClientInfo {
+    /// Describe a login from `window` on this machine
+    pub fn new(window: impl Into<String>, app_version: impl Into<String>) -> Self {
+        Self {
+            window: window.into(),
+            app_version: app_version.into(),
+            hostname: gethostname::gethostname().to_string_lossy().into_owned(),
+            platform: std::env::consts::OS.to_string(),
+        }
     }
 }
 
 /// Thread-safe session store
+///
+/// Only SHA-256 hashes of tokens are kept, so a memory dump or debug print
+/// of the store doesn't yield usable bearer tokens. Presented tokens are
+/// hashed before lookup, so lookup timing reveals nothing about stored
+/// tokens either.
 pub struct SessionStore {
-    sessions: HashMap<String, Session>,
+    sessions: HashMap<TokenHash, Session>,
 }
 
 impl SessionStore {
@@ -53,36 +227,188 @@ impl SessionStore {
 
     /// Insert a new session
     pub fn insert(&mut self, session: Session) {
-        self.sessions.insert(session.token.clone(), session);
+        self.sessions.insert(session.token_hash, session);
+    }
+
+    fn find(&self, token: &str) -> Option<&Session> {
+        self.sessions.get(&hash_token(token))
     }
 
-    /// Get a session by token (if valid and not expired)
+    fn find_mut(&mut self, token: &str) -> Option<&mut Session> {
+        self.sessions.get_mut(&hash_token(token))
+    }
+
+    /// Get a session by token (if valid, not expired and not locked)
     pub fn get(&self, token: &str) -> Option<&Session> {
-        self.sessions.get(token).filter(|s| !s.is_expired())
+        self.find(token).filter(|s| s.is_active())
     }
 
-    /// Get a mutable session by token (if valid and not expired)
+    /// Get a mutable session by token (if valid, not expired and not locked)
     pub fn get_mut(&mut self, token: &str) -> Option<&mut Session> {
-        self.sessions.get_mut(token).filter(|s| !s.is_expired())
+        self.find_mut(token).filter(|s| s.is_active())
     }
 
     /// Remove a session
     pub fn remove(&mut self, token: &str) -> bool {
-        self.sessions.remove(token).is_some()
+        self.sessions.remove(&hash_token(token)).is_some()
+    }
+
+    /// Remove sessions matching `pred`, returning what was removed
+    fn evict_where(&mut self, pred: impl Fn(&Session) -> bool) -> Vec<SessionInfo> {
+        let mut evicted = Vec::new();
+        self.sessions.retain(|_, session| {
+            if pred(session) {
+                evicted.push(session.info());
+                return false;
+            }
+            true
+        });
+        evicted
+    }
+
+    /// Sessions worth keeping across a restart (active or still resumable)
+    pub fn live_sessions(&self) -> impl Iterator<Item = &Session> {
+        self.sessions
+            .values()
+            .filter(|s| s.is_active() || s.is_locked())
+    }
+
+    /// Whether any session is active (logged in and not locked)
+    pub fn any_active(&self) -> bool {
+        self.sessions.values().any(|s| s.is_active())
+    }
+
+    /// Active and locked sessions, for the status screen
+    pub fn counts(&self) -> SessionCounts {
+        let mut counts = SessionCounts::default();
+        for session in self.sessions.values() {
+            if session.is_active() {
+                counts.active += 1;
+                if session.role == Role::Guest {
+                    counts.guests += 1;
+                }
+            } else if session.is_locked() {
+                counts.locked += 1;
+            }
+        }
+        counts
+    }
+
+    /// Session info for an active session (`AuthError::Locked` for a locked one)
+    pub fn check(&self, token: &str) -> Result<SessionInfo, AuthError> {
+        match self.find(token) {
+            Some(session) if session.is_active() => Ok(session.info()),
+            Some(session) if session.is_locked() => Err(AuthError::Locked),
+            _ => Err(AuthError::InvalidSession),
+        }
+    }
+
+    /// `check` and record activity in one go (coalesced, see
+    /// `REFRESH_COALESCE`), for per-request validation; needs only the
+    /// read lock
+    pub fn touch(&self, token: &str) -> Result<Touched, AuthError> {
+        match self.find(token) {
+            Some(session) if session.is_active() => Ok(Touched {
+                refreshed: session.refresh_coalesced(),
+                authenticated_for: session.authenticated_at.elapsed(),
+                info: session.info(),
+            }),
+            Some(session) if session.is_locked() => Err(AuthError::Locked),
+            _ => Err(AuthError::InvalidSession),
+        }
+    }
+
+    /// Whether the session with this token hash is active
+    pub fn is_active_hash(&self, hash: &TokenHash) -> bool {
+        self.sessions.get(hash).is_some_and(|s| s.is_active())
+    }
+
+    /// Get a locked (idled-out, PIN-resumable) session by token
+    pub fn get_locked_mut(&mut self, token: &str) -> Option<&mut Session> {
+        self.find_mut(token).filter(|s| s.is_locked())
+    }
+
+    /// Enable or disable PIN resume for all of `username`'s sessions
+    pub fn set_quick_unlock(&mut self, username: &str, enabled: bool) {
+        for session in self.sessions.values_mut() {
+            if session.username == username {
+                session.quick_unlock = enabled;
+            }
+        }
+    }
+
+    /// Lock every active session, returning the ones that were newly locked
+    pub fn lock_all(&mut self) -> Vec<SessionInfo> {
+        self.sessions
+            .values_mut()
+            .filter(|s| s.is_active())
+            .map(|s| {
+                s.lock();
+                s.info()
+            })
+            .collect()
+    }
+
+    /// Remove every session belonging to `username`, returning the removed sessions
+    pub fn remove_user(&mut self, username: &str) -> Vec<SessionInfo> {
+        self.evict_where(|s| s.username == username)
+    }
+
+    /// Remove all expired sessions (locked sessions are kept until their grace ends)
+    ///
+    /// Returns the removed sessions so callers can audit them.
+    pub fn cleanup_expired(&mut self) -> Vec<SessionInfo> {
+        self.evict_where(|s| s.is_expired() && !s.is_locked())
     }
+}
 
-    /// Remove all expired sessions
-    pub fn cleanup_expired(&mut self) {
-        self.sessions.retain(|_, s| !s.is_expired());
+impl Default for SessionStore {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
+/// An active session `SessionStore::touch` validated
+pub struct Touched {
+    pub info: SessionInfo,
+    /// Since the user last proved their password
+    pub authenticated_for: Duration,
+    /// Activity was recorded (false when coalesced into a recent refresh)
+    pub refreshed: bool,
+}
+
+/// Session totals (`guests` is the subset of `active` with the guest role)
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SessionCounts {
+    pub active: usize,
+    pub locked: usize,
+    pub guests: usize,
+}
+
 /// Thread-safe authentication state
-pub struct AuthState(pub Arc<Mutex<SessionStore>>);
+pub struct AuthState(pub Arc<RwLock<SessionStore>>);
 
 impl AuthState {
     pub fn new() -> Self {
-        Self(Arc::new(Mutex::new(SessionStore::new())))
+        Self(Arc::new(RwLock::new(SessionStore::new())))
+    }
+
+    /// Write-lock the session store, failing closed: a poisoned store is
+    /// cleared so every session has to log in again
+    pub fn store(&self) -> RwLockWriteGuard<'_, SessionStore> {
+        self.0.write_or_reset(|store| *store = SessionStore::new())
+    }
+
+    /// Read-lock the session store (validation, lookups), failing closed
+    /// like `store`
+    pub fn read(&self) -> RwLockReadGuard<'_, SessionStore> {
+        self.0.read_or_reset(|store| *store = SessionStore::new())
+    }
+}
+
+impl Default for AuthState {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -96,13 +422,87 @@ pub struct AuthResult {
     pub username: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// PAM succeeded but a TOTP code is still required (see `auth_verify_totp`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub needs_second_factor: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge_id: Option<String>,
+    /// Machine-readable reason for some failures (set in Rust only)
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<&'static str>,
+    /// New password rejected by the password policy (set in Rust only)
+    #[serde(default, skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<PolicyViolation>,
+}
+
+impl AuthResult {
+    /// A failed login with a user-facing error message
+    pub fn failure(error: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            session_token: None,
+            username: None,
+            error: Some(error.into()),
+            needs_second_factor: false,
+            challenge_id: None,
+            error_code: None,
+            violations: Vec::new(),
+        }
+    }
+
+    /// A successful login carrying the session token
+    pub fn authenticated(session_token: String, username: String) -> Self {
+        Self {
+            success: true,
+            session_token: Some(session_token),
+            username: Some(username),
+            error: None,
+            needs_second_factor: false,
+            challenge_id: None,
+            error_code: None,
+            violations: Vec::new(),
+        }
+    }
+
+    /// PAM succeeded; the frontend must complete `challenge_id` with a TOTP code
+    pub fn second_factor_required(username: String, challenge_id: String) -> Self {
+        Self {
+            success: false,
+            session_token: None,
+            username: Some(username),
+            error: None,
+            needs_second_factor: true,
+            challenge_id: Some(challenge_id),
+            error_code: None,
+            violations: Vec::new(),
+        }
+    }
+
+    /// A failure with a stable code the UI can branch on
+    pub fn failure_code(code: &'static str, error: impl Into<String>) -> Self {
+        Self {
+            error_code: Some(code),
+            ..Self::failure(error)
+        }
+    }
+
+    /// A new password broke the password policy
+    pub fn policy_violation(violations: Vec<PolicyViolation>) -> Self {
+        let reasons: Vec<String> = violations.iter().map(|v| v.message()).collect();
+        Self {
+            violations,
+            ..Self::failure(format!("Password {}", reasons.join(", ")))
+        }
+    }
 }
 
 /// Session info for injection into RPC params
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SessionInfo {
     pub username: String,
     pub session_id: String, // Truncated token for logging (first 16 chars)
+    pub role: Role,
+    pub client: ClientInfo,
 }
 
 /// Generate a cryptographically secure session token
@@ -112,23 +512,67 @@ pub fn generate_session_token() -> String {
     hex::encode(bytes)
 }
 
+/// SHA-256 of a session token
+pub fn hash_token(token: &str) -> TokenHash {
+    Sha256::digest(token.as_bytes()).into()
+}
+
 /// Create a new session after Python kernel validates credentials
-pub fn create_session(token: String, username: String) -> Session {
+///
+/// Only the token's hash and truncated id are kept; the token itself is
+/// wiped when this returns.
+pub fn create_session(token: String, username: String, client: ClientInfo) -> Session {
+    let token = SecretString::new(token);
     let now = Instant::now();
     Session {
-        token,
+        token_hash: hash_token(token.expose()),
+        session_id: session_id(token.expose()),
         username,
         created_at: now,
-        last_activity: now,
+        authenticated_at: now,
+        last_activity: Activity::new(now),
+        quick_unlock: false,
+        locked_at: None,
+        role: Role::User,
+        client,
+        consents: HashSet::new(),
     }
 }
 
-/// Validate a session token and return session info if valid
-pub fn validate_session(store: &SessionStore, token: &str) -> Option<SessionInfo> {
-    store.get(token).map(|session| SessionInfo {
-        username: session.username.clone(),
-        session_id: token.chars().take(16).collect(),
-    })
+/// Create a guest session (no kernel-side credentials or key material)
+pub fn create_guest_session(token: String, username: String, client: ClientInfo) -> Session {
+    let mut session = create_session(token, username, client);
+    session.role = Role::Guest;
+    session
+}
+
+/// Truncated token used to identify a session in logs (first 16 chars)
+pub fn session_id(token: &str) -> String {
+    token.chars().take(16).collect()
+}
+
+/// Params with session info injected for kernel-side audit logging
+pub fn with_session_claims(params: Value, session_info: &SessionInfo) -> Value {
+    let mut enriched_params = match params {
+        Value::Object(map) => Value::Object(map),
+        Value::Null => json!({}),
+        other => json!({ "value": other }),
+    };
+
+    if let Value::Object(ref mut map) = enriched_params {
+        // Only the shell's own calls may carry a `__shell` proof
+        map.remove("__shell");
+        map.insert(
+            "__session".to_string(),
+            json!({
+                "username": session_info.username,
+                "session_id": session_info.session_id,
+                "role": session_info.role,
+                "client": session_info.client,
+            }),
+        );
+    }
+    enriched_params
 }
 
 #[cfg(test)]
@@ -145,11 +589,18 @@ mod tests {
 
     #[test]
     fn test_session_expiry() {
-        let mut session = Session {
-            token: "test".to_string(),
+        let session = Session {
+            token_hash: hash_token("test"),
+            session_id: session_id("test"),
             username: "testuser".to_string(),
             created_at: Instant::now(),
-            last_activity: Instant::now() - Duration::from_secs(20 * 60), // 20 mins ago
+            authenticated_at: Instant::now(),
+            last_activity: Activity::new(Instant::now() - Duration::from_secs(20 * 60)), // 20 mins ago
+            quick_unlock: false,
+            locked_at: None,
+            role: Role::User,
+            client: ClientInfo::default(),
+            consents: HashSet::new(),
         };
 
         assert!(session.is_expired());
@@ -161,7 +612,7 @@ mod tests {
     fn test_session_store() {
         let mut store = SessionStore::new();
         let token = generate_session_token();
-        let session = create_session(token.clone(), "testuser".to_string());
+        let session = create_session(token.clone(), "testuser".to_string(), ClientInfo::default());
 
         store.insert(session);
         assert!(store.get(&token).is_some());
@@ -169,4 +620,169 @@ mod tests {
         store.remove(&token);
         assert!(store.get(&token).is_none());
     }
+
+    #[test]
+    fn test_touch_coalesces_refreshes() {
+        let mut store = SessionStore::new();
+        let session = create_session(
+            "tok".to_string(),
+            "alice".to_string(),
+            ClientInfo::default(),
+        );
+        session
+            .last_activity
+            .set(Instant::now() - Duration::from_secs(60));
+        store.insert(session);
+
+        let touched = store.touch("tok").unwrap();
+        assert!(touched.refreshed);
+        assert_eq!(touched.info.username, "alice");
+        assert!(!store.touch("tok").unwrap().refreshed);
+        assert!(matches!(
+            store.touch("other"),
+            Err(AuthError::InvalidSession)
+        ));
+        store.lock_all();
+        assert!(matches!(store.touch("tok"), Err(AuthError::Locked)));
+    }
+
+    #[test]
+    fn test_parallel_touch_under_read_lock() {
+        let state = AuthState::new();
+        let session = create_session(
+            "tok".to_string(),
+            "alice".to_string(),
+            ClientInfo::default(),
+        );
+        session
+            .last_activity
+            .set(Instant::now() - Duration::from_secs(60));
+        state.store().insert(session);
+
+        let refreshed = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| state.read().touch("tok").unwrap().refreshed))
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .filter(|&r| r)
+                .count()
+        });
+        // Coalesced: exactly one of the concurrent requests records activity
+        assert_eq!(refreshed, 1);
+    }
+
+    #[test]
+    fn test_pin_session_locks_instead_of_expiring() {
+        let mut store = SessionStore::new();
+        let session = create_session(
+            "tok".to_string(),
+            "alice".to_string(),
+            ClientInfo::default(),
+        );
+        session
+            .last_activity
+            .set(Instant::now() - Duration::from_secs(20 * 60));
+        store.insert(session);
+
+        store.cleanup_expired();
+        assert!(store.get("tok").is_none());
+        assert!(store.get_locked_mut("tok").is_none());
+
+        let session = create_session(
+            "tok".to_string(),
+            "alice".to_string(),
+            ClientInfo::default(),
+        );
+        session
+            .last_activity
+            .set(Instant::now() - Duration::from_secs(20 * 60));
+        store.insert(session);
+        store.set_quick_unlock("alice", true);
+
+        store.cleanup_expired();
+        assert!(store.get("tok").is_none());
+        let locked = store
+            .get_locked_mut("tok")
+            .expect("session should be locked");
+        locked.refresh();
+        assert!(store.get("tok").is_some());
+    }
+
+    #[test]
+    fn test_lock_all_blocks_requests_until_unlocked() {
+        let mut store = SessionStore::new();
+        store.insert(create_session(
+            "tok".to_string(),
+            "alice".to_string(),
+            ClientInfo::default(),
+        ));
+
+        assert!(store.is_active_hash(&hash_token("tok")));
+        assert_eq!(store.lock_all().len(), 1);
+        assert!(store.lock_all().is_empty());
+        store.insert(create_guest_session(
+            "guest".to_string(),
+            "guest".to_string(),
+            ClientInfo::default(),
+        ));
+        assert_eq!(
+            store.counts(),
+            SessionCounts {
+                active: 1,
+                locked: 1,
+                guests: 1
+            }
+        );
+        assert!(store.get("tok").is_none());
+        assert!(!store.is_active_hash(&hash_token("tok")));
+        assert_eq!(store.check("tok").unwrap_err(), AuthError::Locked);
+        assert_eq!(store.check("nope").unwrap_err(), AuthError::InvalidSession);
+        // Callers can't tell a locked token from an unknown one
+        assert_eq!(
+            store.check("tok").unwrap_err().to_string(),
+            store.check("nope").unwrap_err().to_string()
+        );
+
+        // Explicit locks survive cleanup even without quick unlock
+        store.cleanup_expired();
+        store
+            .get_locked_mut("tok")
+            .expect("session should be locked")
+            .unlock();
+        assert!(store.get("tok").is_some());
+    }
+
+    #[test]
+    fn test_remove_user_sessions() {
+        let mut store = SessionStore::new();
+        for user in ["alice", "alice", "bob"] {
+            store.insert(create_session(
+                generate_session_token(),
+                user.to_string(),
+                ClientInfo::default(),
+            ));
+        }
+
+        assert_eq!(store.remove_user("alice").len(), 2);
+        assert!(store.remove_user("alice").is_empty());
+        assert_eq!(store.sessions.len(), 1);
+    }
+
+    #[test]
+    fn test_session_info_carries_client() {
+        let mut store = SessionStore::new();
+        let client = ClientInfo::new("main", "1.2.3");
+        assert_eq!(client.platform, std::env::consts::OS);
+        store.insert(create_session(
+            "tok".to_string(),
+            "alice".to_string(),
+            client,
+        ));
+
+        let info = store.check("tok").unwrap();
+        assert_eq!(info.client.window, "main");
+        assert_eq!(info.client.app_version, "1.2.3");
+    }
 }
@@ -0,0 +1,240 @@
+//! Pluggable Login Backends for ReOS
+//!
+//! `auth_login` hands the credentials to an `AuthBackend`, which proves
+//! who the user is and establishes the kernel session (and with it the
+//! encryption key). The command layer only sees the resulting
+//! `AuthResult`, so deployments can swap backends without touching it.
+//!
+//! Selected via `auth-backend.json` in the app data dir:
+//! - `{ "backend": "pam" }` (default): kernel PAM/Polkit login
+//! - `{ "backend": "ldap", "url": "ldaps://ldap.example.org",
+//!   "user_dn": "uid={username},ou=people,dc=example,dc=org" }`: kernel
+//!   binds as the user's entry (`ldap://` URLs must take StartTLS; an
+//!   optional `ca_cert` path verifies the server)
+//! - `{ "backend": "password_file", "path": "/etc/reos/passwd" }`: Argon2
+//!   hashes in a local `user:$argon2id$...` file, checked in Rust
+
+use argon2::password_hash::PasswordHash;
+use argon2::{Argon2, PasswordVerifier};
+use serde::Deserialize;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::auth::AuthResult;
+use crate::kernel::{request_shared, rpc_result, SharedKernel};
+use crate::secret::SecretString;
+
+/// Credentials presented to a backend
+pub struct LoginRequest {
+    pub username: String,
+    /// Not needed by the PAM backend (Polkit prompts on its own)
+    pub password: Option<SecretString>,
+}
+
+/// A way of authenticating users and opening kernel sessions
+///
+/// Implementations block (they talk to the kernel or the filesystem) and
+/// are called from a blocking thread.
+pub trait AuthBackend: Send + Sync {
+    /// Short name for audit logs
+    fn name(&self) -> &'static str;
+
+    /// Authenticate `request` and open a kernel session for the user
+    fn login(&self, kernel: &SharedKernel, request: &LoginRequest) -> Result<AuthResult, String>;
+}
+
+/// Default: the kernel authenticates via PAM/Polkit
+pub struct KernelPamBackend;
+
+impl AuthBackend for KernelPamBackend {
+    fn name(&self) -> &'static str {
+        "pam"
+    }
+
+    fn login(&self, kernel: &SharedKernel, request: &LoginRequest) -> Result<AuthResult, String> {
+        let response = request_shared(
+            kernel,
+            "auth/login",
+            json!({ "username": request.username }),
+        )?;
+        rpc_result(response)
+    }
+}
+
+/// The kernel authenticates by binding to an LDAP directory
+pub struct KernelLdapBackend {
+    url: String,
+    /// DN template with a `{username}` placeholder
+    user_dn: String,
+    ca_cert: Option<PathBuf>,
+}
+
+impl AuthBackend for KernelLdapBackend {
+    fn name(&self) -> &'static str {
+        "ldap"
+    }
+
+    fn login(&self, kernel: &SharedKernel, request: &LoginRequest) -> Result<AuthResult, String> {
+        let Some(password) = &request.password else {
+            return Ok(AuthResult::failure("Password required"));
+        };
+        let response = request_shared(
+            kernel,
+            "auth/login_ldap",
+            json!({
+                "username": request.username,
+                "password": password.expose(),
+                "url": self.url,
+                "user_dn": self.user_dn,
+                "ca_cert": self.ca_cert,
+            }),
+        )?;
+        rpc_result(response)
+    }
+}
+
+/// Users and Argon2 password hashes from a local file
+///
+/// Rust checks the password; the kernel is then asked to open a session
+/// for the already-verified user and derive its key from the password.
+pub struct PasswordFileBackend {
+    path: PathBuf,
+}
+
+impl PasswordFileBackend {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Check `password` against the file's entry for `username`
+    fn verify(&self, username: &str, password: &str) -> Result<bool, String> {
+        let contents = std::fs::read_to_string(&self.path)
+            .map_err(|e| format!("failed to read password file: {e}"))?;
+        Ok(verify_entry(&contents, username, password))
+    }
+}
+
+impl AuthBackend for PasswordFileBackend {
+    fn name(&self) -> &'static str {
+        "password_file"
+    }
+
+    fn login(&self, kernel: &SharedKernel, request: &LoginRequest) -> Result<AuthResult, String> {
+        let Some(password) = &request.password else {
+            return Ok(AuthResult::failure("Password required"));
+        };
+        if !self.verify(&request.username, password.expose())? {
+            return Ok(AuthResult::failure("Invalid username or password"));
+        }
+        let response = request_shared(
+            kernel,
+            "auth/login_verified",
+            json!({
+                "username": request.username,
+                "password": password.expose(),
+                "verified_by": self.name(),
+                "__shell": {},
+            }),
+        )?;
+        rpc_result(response)
+    }
+}
+
+/// Whether `contents` (`user:phc-hash` lines) has a matching entry
+fn verify_entry(contents: &str, username: &str, password: &str) -> bool {
+    contents
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| line.split_once(':'))
+        .find(|(user, _)| *user == username)
+        .and_then(|(_, hash)| PasswordHash::new(hash.trim()).ok())
+        .is_some_and(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+}
+
+/// Backend selection from `auth-backend.json`
+#[derive(Deserialize, Default)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum BackendConfig {
+    #[default]
+    Pam,
+    Ldap {
+        url: String,
+        user_dn: String,
+        #[serde(default)]
+        ca_cert: Option<PathBuf>,
+    },
+    PasswordFile {
+        path: PathBuf,
+    },
+}
+
+impl BackendConfig {
+    /// Load the selection from `path` (missing or invalid file = PAM)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn build(self) -> Arc<dyn AuthBackend> {
+        match self {
+            BackendConfig::Pam => Arc::new(KernelPamBackend),
+            BackendConfig::Ldap {
+                url,
+                user_dn,
+                ca_cert,
+            } => Arc::new(KernelLdapBackend {
+                url,
+                user_dn,
+                ca_cert,
+            }),
+            BackendConfig::PasswordFile { path } => Arc::new(PasswordFileBackend::new(path)),
+        }
+    }
+}
+
+/// The configured login backend
+pub struct AuthBackendState(pub Arc<dyn AuthBackend>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use argon2::password_hash::{rand_core::OsRng, SaltString};
+    use argon2::PasswordHasher;
+
+    #[test]
+    fn test_password_file_entries() {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default()
+            .hash_password(b"hunter2", &salt)
+            .unwrap()
+            .to_string();
+        let contents = format!("# comment\nalice:{hash}\nbob:not-a-hash\n");
+
+        assert!(verify_entry(&contents, "alice", "hunter2"));
+        assert!(!verify_entry(&contents, "alice", "wrong"));
+        assert!(!verify_entry(&contents, "bob", "anything"));
+        assert!(!verify_entry(&contents, "carol", "hunter2"));
+    }
+
+    #[test]
+    fn test_backend_config() {
+        assert_eq!(BackendConfig::load(None).build().name(), "pam");
+        let config: BackendConfig =
+            serde_json::from_str(r#"{ "backend": "password_file", "path": "/tmp/p" }"#).unwrap();
+        assert_eq!(config.build().name(), "password_file");
+        let config: BackendConfig = serde_json::from_str(
+            r#"{ "backend": "ldap", "url": "ldaps://ldap", "user_dn": "uid={username}" }"#,
+        )
+        .unwrap();
+        assert_eq!(config.build().name(), "ldap");
+        // Without a directory to bind to, it isn't an LDAP selection
+        let config: Result<BackendConfig, _> = serde_json::from_str(r#"{ "backend": "ldap" }"#);
+        assert!(config.is_err());
+    }
+}
@@ -0,0 +1,352 @@
+//! Start on Login for ReOS
+//!
+//! `autostart_enable` registers ReOS to launch when the user logs in, with
+//! `--autostart` (and `--minimized` if asked) on the command line. A
+//! minimized autostart keeps the main window hidden behind the tray, starts
+//! the kernel right away and locks any restored sessions, so the first
+//! unlock is fast but nothing is readable until then.
+//!
+//! Platforms:
+//! - Linux: XDG autostart entry (`~/.config/autostart/<identifier>.desktop`);
+//!   an AppImage registers the image itself rather than the mounted binary
+//! - macOS: launchd agent (`~/Library/LaunchAgents/<identifier>.plist`)
+//! - Windows: `HKCU\Software\Microsoft\Windows\CurrentVersion\Run` value
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Marks a launch started by the login entry
+pub const AUTOSTART_ARG: &str = "--autostart";
+/// Start hidden in the tray (only together with `AUTOSTART_ARG`)
+pub const MINIMIZED_ARG: &str = "--minimized";
+
+#[derive(Debug, Error)]
+pub enum AutostartError {
+    #[error("Cannot find the ReOS executable: {0}")]
+    Executable(String),
+    #[error("Cannot update the login entry: {0}")]
+    Io(String),
+}
+
+impl From<std::io::Error> for AutostartError {
+    fn from(e: std::io::Error) -> Self {
+        AutostartError::Io(e.to_string())
+    }
+}
+
+/// Result of `autostart_status` (and the `autostart` setting)
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AutostartStatus {
+    pub enabled: bool,
+    pub minimized: bool,
+}
+
+/// How this process was launched, from its arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutostartLaunch {
+    pub minimized: bool,
+}
+
+impl AutostartLaunch {
+    /// `Some` when started by the login entry
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let args: Vec<String> = args.into_iter().skip(1).collect();
+        args.iter().any(|a| a == AUTOSTART_ARG).then(|| Self {
+            minimized: args.iter().any(|a| a == MINIMIZED_ARG),
+        })
+    }
+}
+
+/// Path to launch at login
+pub fn executable() -> Result<PathBuf, AutostartError> {
+    #[cfg(target_os = "linux")]
+    if let Some(image) = std::env::var_os("APPIMAGE") {
+        return Ok(PathBuf::from(image));
+    }
+    std::env::current_exe().map_err(|e| AutostartError::Executable(e.to_string()))
+}
+
+fn launch_args(minimized: bool) -> Vec<&'static str> {
+    let mut args = vec![AUTOSTART_ARG];
+    if minimized {
+        args.push(MINIMIZED_ARG);
+    }
+    args
+}
+
+/// XDG desktop entry, with the path quoted per the Exec key rules
+#[cfg(any(target_os = "linux", test))]
+pub fn desktop_entry(name: &str, exe: &Path, minimized: bool) -> String {
+    let mut quoted = String::from("\"");
+    for c in exe.to_string_lossy().chars() {
+        match c {
+            '"' | '`' | '$' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '%' => quoted.push_str("%%"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    let exec = std::iter::once(quoted)
+        .chain(launch_args(minimized).into_iter().map(String::from))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    format!(
+        "[Desktop Entry]\nType=Application\nName={name}\nExec={exec}\nTerminal=false\nNoDisplay=true\nX-GNOME-Autostart-enabled=true\n"
+    )
+}
+
+#[cfg(any(target_os = "macos", test))]
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// launchd agent running `exe` once at login
+#[cfg(any(target_os = "macos", test))]
+pub fn launchd_plist(label: &str, exe: &Path, minimized: bool) -> String {
+    let arguments: String = std::iter::once(exe.to_string_lossy().into_owned())
+        .chain(launch_args(minimized).into_iter().map(String::from))
+        .map(|arg| format!("        <string>{}</string>\n", xml_escape(&arg)))
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>ProgramArguments</key>
+    <array>
+{arguments}    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#,
+        xml_escape(label)
+    )
+}
+
+/// Command line stored in the Windows `Run` key
+#[cfg(any(target_os = "windows", test))]
+pub fn run_command(exe: &Path, minimized: bool) -> String {
+    let mut command = format!("\"{}\"", exe.display());
+    for arg in launch_args(minimized) {
+        command.push(' ');
+        command.push_str(arg);
+    }
+    command
+}
+
+/// Status from the contents of an existing entry
+fn status_of(contents: &str) -> AutostartStatus {
+    AutostartStatus {
+        enabled: contents.contains(AUTOSTART_ARG),
+        minimized: contents.contains(MINIMIZED_ARG),
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::*;
+
+    fn entry_path(identifier: &str) -> Result<PathBuf, AutostartError> {
+        let config = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+            .ok_or_else(|| AutostartError::Io("no config directory".to_string()))?;
+        Ok(config
+            .join("autostart")
+            .join(format!("{identifier}.desktop")))
+    }
+
+    pub fn enable(
+        identifier: &str,
+        name: &str,
+        exe: &Path,
+        minimized: bool,
+    ) -> Result<(), AutostartError> {
+        let path = entry_path(identifier)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, desktop_entry(name, exe, minimized))?;
+        Ok(())
+    }
+
+    pub fn disable(identifier: &str) -> Result<(), AutostartError> {
+        match std::fs::remove_file(entry_path(identifier)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn status(identifier: &str) -> Result<AutostartStatus, AutostartError> {
+        let contents = std::fs::read_to_string(entry_path(identifier)?).unwrap_or_default();
+        // Desktops write Hidden=true instead of deleting a disabled entry
+        if contents.lines().any(|l| l.trim() == "Hidden=true") {
+            return Ok(AutostartStatus::default());
+        }
+        Ok(status_of(&contents))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+
+    fn agent_path(identifier: &str) -> Result<PathBuf, AutostartError> {
+        let home = std::env::var_os("HOME")
+            .ok_or_else(|| AutostartError::Io("no home directory".to_string()))?;
+        Ok(PathBuf::from(home)
+            .join("Library/LaunchAgents")
+            .join(format!("{identifier}.plist")))
+    }
+
+    pub fn enable(
+        identifier: &str,
+        _name: &str,
+        exe: &Path,
+        minimized: bool,
+    ) -> Result<(), AutostartError> {
+        let path = agent_path(identifier)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, launchd_plist(identifier, exe, minimized))?;
+        Ok(())
+    }
+
+    pub fn disable(identifier: &str) -> Result<(), AutostartError> {
+        match std::fs::remove_file(agent_path(identifier)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn status(identifier: &str) -> Result<AutostartStatus, AutostartError> {
+        let contents = std::fs::read_to_string(agent_path(identifier)?).unwrap_or_default();
+        Ok(status_of(&contents))
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::*;
+    use std::process::Command;
+
+    const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
+    fn reg(args: &[&str]) -> Result<std::process::Output, AutostartError> {
+        Ok(Command::new("reg").args(args).output()?)
+    }
+
+    pub fn enable(
+        _identifier: &str,
+        name: &str,
+        exe: &Path,
+        minimized: bool,
+    ) -> Result<(), AutostartError> {
+        let command = run_command(exe, minimized);
+        let output = reg(&[
+            "add", RUN_KEY, "/v", name, "/t", "REG_SZ", "/d", &command, "/f",
+        ])?;
+        if !output.status.success() {
+            return Err(AutostartError::Io(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn disable(_identifier: &str, name: &str) -> Result<(), AutostartError> {
+        // Fails only when the value is already gone
+        reg(&["delete", RUN_KEY, "/v", name, "/f"])?;
+        Ok(())
+    }
+
+    pub fn status(_identifier: &str, name: &str) -> Result<AutostartStatus, AutostartError> {
+        let output = reg(&["query", RUN_KEY, "/v", name])?;
+        if !output.status.success() {
+            return Ok(AutostartStatus::default());
+        }
+        Ok(status_of(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+/// Launch ReOS at login (`identifier` names the entry, `name` is shown to the user)
+pub fn enable(identifier: &str, name: &str, minimized: bool) -> Result<(), AutostartError> {
+    platform::enable(identifier, name, &executable()?, minimized)
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn disable(identifier: &str, _name: &str) -> Result<(), AutostartError> {
+    platform::disable(identifier)
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn status(identifier: &str, _name: &str) -> Result<AutostartStatus, AutostartError> {
+    platform::status(identifier)
+}
+
+#[cfg(target_os = "windows")]
+pub use platform::{disable, status};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launch_from_args() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(AutostartLaunch::from_args(args(&["reos"])), None);
+        assert_eq!(
+            AutostartLaunch::from_args(args(&["reos", "--autostart"])),
+            Some(AutostartLaunch { minimized: false })
+        );
+        assert_eq!(
+            AutostartLaunch::from_args(args(&["reos", "--autostart", "--minimized"])),
+            Some(AutostartLaunch { minimized: true })
+        );
+        // The program name itself never counts
+        assert_eq!(AutostartLaunch::from_args(args(&["--autostart"])), None);
+    }
+
+    #[test]
+    fn test_desktop_entry_quotes_exec() {
+        let entry = desktop_entry("Talking Rock", Path::new("/opt/my $apps/100%/reos"), true);
+        assert!(entry.contains("Exec=\"/opt/my \\$apps/100%%/reos\" --autostart --minimized\n"));
+        assert!(entry.contains("Name=Talking Rock\n"));
+        assert_eq!(
+            status_of(&entry),
+            AutostartStatus {
+                enabled: true,
+                minimized: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_launchd_plist_escapes_arguments() {
+        let plist = launchd_plist("dev.reos.app", Path::new("/Apps/R&D/reos"), false);
+        assert!(plist.contains("<string>/Apps/R&amp;D/reos</string>"));
+        assert!(plist.contains("<string>--autostart</string>"));
+        assert!(!plist.contains(MINIMIZED_ARG));
+    }
+
+    #[test]
+    fn test_run_command() {
+        assert_eq!(
+            run_command(Path::new(r"C:\Program Files\ReOS\reos.exe"), true),
+            r#""C:\Program Files\ReOS\reos.exe" --autostart --minimized"#
+        );
+    }
+}
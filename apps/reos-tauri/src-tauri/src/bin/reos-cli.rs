@@ -0,0 +1,484 @@
+//! Headless CLI
+//!
+//! Runs one kernel method from a script or a terminal, without the GUI:
+//!
+//! ```sh
+//! reos-cli call files/list --json '{"path": "~"}'
+//! REOS_PASSWORD=... reos-cli --user alice call notes/search --json '{"q": "x"}'
+//! printf '%s\n' "$pw" | reos-cli --user alice --password-stdin call ...
+//! ```
+//!
+//! It reads the app's own `config.toml` and data dir files (honoring
+//! `--profile` and `--portable` as the app does) and starts the configured
+//! kernel backend. A local kernel is always started over stdio for the one
+//! call, so the app's kernel and its `kernel.json` are left alone; a remote
+//! kernel is connected to as usual.
+//!
+//! With `--user`, the login goes through the configured `auth-backend.json`
+//! and the app's second factor, role mapping and `method-policy.json`, and
+//! is recorded in the auth audit log as window `cli`. It never prompts: the
+//! password comes from `--password-stdin` (first line) or `REOS_PASSWORD`,
+//! a TOTP code from `REOS_TOTP_CODE`. The default PAM backend leaves the
+//! prompt to Polkit, so unattended scripts want `ldap` or `password_file`.
+//! Consent prompts and the webview's method allowlist don't apply here.
+//!
+//! The result goes to stdout as JSON; a kernel or policy error goes to
+//! stderr as its JSON-RPC error object. Exit status: 0 success, 1 error
+//! response or failed request, 2 bad usage, 3 login failed.
+
+use reos_tauri_lib::{
+    audit, auth, auth_backend, config, kernel, kernel_backend, kernel_transport, logging,
+    method_policy, portable, profile, redact, remote_kernel, roles, secret, totp,
+};
+use serde_json::{json, Value};
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+
+use audit::{AuditEntry, AuditEvent, AuditLog};
+use auth::{ClientInfo, SessionInfo};
+use auth_backend::{BackendConfig, LoginRequest};
+use config::ConfigState;
+use kernel::{request_shared, rpc_result, SharedKernel};
+use kernel_backend::MockKernelConfig;
+use kernel_transport::Transport;
+use method_policy::MethodPolicy;
+use profile::ProfileError;
+use redact::RedactionConfig;
+use remote_kernel::RemoteKernelConfig;
+use roles::{Role, RoleMapping};
+use secret::SecretString;
+use totp::TotpManager;
+
+const USAGE: &str = "\
+usage: reos-cli [options] call <method> [--json <params>]
+
+options:
+  --json <params>     JSON params for the method (default {})
+  --user <name>       log in first (password from REOS_PASSWORD)
+  --password-stdin    read the password from the first line of stdin
+  --profile <name>    use a named profile's config and data
+  --portable          use the data dir beside the executable";
+
+const JSON_FLAG: &str = "--json";
+const USER_FLAG: &str = "--user";
+const PASSWORD_STDIN_FLAG: &str = "--password-stdin";
+const PROFILE_FLAG: &str = "--profile";
+const PORTABLE_FLAG: &str = "--portable";
+
+const PASSWORD_ENV: &str = "REOS_PASSWORD";
+const TOTP_CODE_ENV: &str = "REOS_TOTP_CODE";
+
+/// Exit statuses
+const EXIT_FAILED: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_LOGIN: i32 = 3;
+
+/// Window label of CLI logins in audit logs and kernel-side claims
+const CLIENT_WINDOW: &str = "cli";
+
+/// How long the kernel gets to exit once the call is done
+const STOP_GRACE: Duration = Duration::from_secs(5);
+
+/// For the app identifier, so the app's dirs are found
+const TAURI_CONF: &str = include_str!("../../tauri.conf.json");
+
+#[derive(Debug, Error, PartialEq, Eq)]
+enum ArgsError {
+    #[error("{0} needs a value")]
+    MissingValue(&'static str),
+    #[error("unknown option {0}")]
+    UnknownFlag(String),
+    #[error("unknown command {0:?}")]
+    UnknownCommand(String),
+    #[error("missing method")]
+    MissingMethod,
+    #[error("{JSON_FLAG}: {0}")]
+    Params(String),
+    #[error(transparent)]
+    Profile(#[from] ProfileError),
+}
+
+/// Parsed command line
+#[derive(Debug, PartialEq, Eq)]
+struct Args {
+    /// `None` for `--help`
+    method: Option<String>,
+    params: Value,
+    user: Option<String>,
+    password_stdin: bool,
+    profile: Option<String>,
+    portable: bool,
+}
+
+impl Args {
+    /// Parse `args` (program name first); the last of a repeated option wins
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut parsed = Self {
+            method: None,
+            params: json!({}),
+            user: None,
+            password_stdin: false,
+            profile: None,
+            portable: false,
+        };
+        let mut positional = Vec::new();
+        let mut args = args.into_iter().skip(1);
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let Some(flag) = [JSON_FLAG, USER_FLAG, PROFILE_FLAG]
+                .into_iter()
+                .find(|&known| known == flag)
+            else {
+                match flag {
+                    PASSWORD_STDIN_FLAG => parsed.password_stdin = true,
+                    PORTABLE_FLAG => parsed.portable = true,
+                    "-h" | "--help" => return Ok(parsed),
+                    _ if flag.starts_with('-') => return Err(ArgsError::UnknownFlag(arg)),
+                    _ => positional.push(arg),
+                }
+                continue;
+            };
+            let value = inline
+                .or_else(|| args.next())
+                .filter(|value| !value.trim().is_empty())
+                .ok_or(ArgsError::MissingValue(flag))?;
+            match flag {
+                JSON_FLAG => {
+                    parsed.params = serde_json::from_str(&value)
+                        .map_err(|e| ArgsError::Params(e.to_string()))?;
+                }
+                USER_FLAG => parsed.user = Some(value),
+                _ => parsed.profile = profile::parse_name(value)?,
+            }
+        }
+        let mut positional = positional.into_iter();
+        match positional.next().as_deref() {
+            None | Some("help") => Ok(parsed),
+            Some("call") => {
+                parsed.method = Some(positional.next().ok_or(ArgsError::MissingMethod)?);
+                match positional.next() {
+                    Some(extra) => Err(ArgsError::UnknownCommand(extra)),
+                    None => Ok(parsed),
+                }
+            }
+            Some(other) => Err(ArgsError::UnknownCommand(other.to_string())),
+        }
+    }
+}
+
+/// A session opened for the call
+struct Login {
+    token: SecretString,
+    info: SessionInfo,
+}
+
+fn main() {
+    logging::init();
+    let args = match Args::parse(std::env::args()) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+    let Some(method) = args.method.clone() else {
+        println!("{USAGE}");
+        return;
+    };
+    profile::init(args.profile.clone());
+    if let Err(e) = portable::init(args.portable) {
+        eprintln!("{e}");
+        std::process::exit(EXIT_USAGE);
+    }
+    std::process::exit(run(&args, &method));
+}
+
+/// Set up as the app would, log in if asked, make the call; the exit status
+fn run(args: &Args, method: &str) -> i32 {
+    let conf: Value = serde_json::from_str(TAURI_CONF).unwrap_or_default();
+    let identifier = conf["identifier"].as_str().unwrap_or_default();
+    let Some((data_dir, config_dir)) = profile::headless_dirs(identifier) else {
+        eprintln!("no home directory");
+        return EXIT_FAILED;
+    };
+    let config_state = ConfigState::load(Some(&config_dir), Vec::new());
+    if let Some(e) = config_state.error() {
+        eprintln!("ignoring {}: {e}", config::CONFIG_FILE);
+    }
+    let config = config_state.config();
+    kernel::set_python(config.kernel.python);
+    kernel::set_transport(match config.kernel.transport {
+        Transport::Remote => Transport::Remote,
+        _ => Transport::Stdio,
+    });
+    kernel_backend::set_backend(config.kernel.backend);
+    if profile::current().is_some() || portable::root().is_some() {
+        kernel::set_data_dir(data_dir.join("kernel"));
+    }
+    redact::init(RedactionConfig::load(Some(
+        &data_dir.join("redaction.json"),
+    )));
+    remote_kernel::init(RemoteKernelConfig::load(Some(
+        &data_dir.join("remote-kernel.json"),
+    )));
+    kernel_backend::init_mock(MockKernelConfig::load(Some(
+        &data_dir.join("kernel-mock.json"),
+    )));
+
+    let kernel: SharedKernel = Arc::new(Mutex::new(None));
+    let login = match &args.user {
+        Some(username) => match login(&kernel, &data_dir, username, args.password_stdin) {
+            Ok(login) => Some(login),
+            Err(status) => {
+                stop(&kernel);
+                return status;
+            }
+        },
+        None => None,
+    };
+    let status = call(
+        &kernel,
+        &data_dir,
+        login.as_ref(),
+        method,
+        args.params.clone(),
+    );
+    if let Some(login) = login {
+        logout(&kernel, &login.token);
+    }
+    stop(&kernel);
+    status
+}
+
+/// Log `username` in the way `auth_login` and `auth_verify_totp` do; the
+/// session, or the exit status
+fn login(
+    kernel: &SharedKernel,
+    data_dir: &Path,
+    username: &str,
+    password_stdin: bool,
+) -> Result<Login, i32> {
+    let password = if password_stdin {
+        let mut line = zeroize::Zeroizing::new(String::new());
+        if let Err(e) = std::io::stdin().lock().read_line(&mut line) {
+            eprintln!("failed to read the password: {e}");
+            return Err(EXIT_USAGE);
+        }
+        Some(SecretString::new(
+            line.trim_end_matches(['\r', '\n']).to_string(),
+        ))
+    } else {
+        std::env::var(PASSWORD_ENV).ok().map(SecretString::new)
+    };
+    let mut audit = AuditLog::open(Some(data_dir.join("auth-audit.jsonl")));
+    let mut record = |entry: AuditEntry| {
+        if let Err(e) = audit.append(&entry.window(CLIENT_WINDOW)) {
+            eprintln!("failed to write the audit log: {e}");
+        }
+    };
+
+    let backend = BackendConfig::load(Some(&data_dir.join("auth-backend.json"))).build();
+    let request = LoginRequest {
+        username: username.to_string(),
+        password,
+    };
+    let result = match backend.login(kernel, &request) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("{e}");
+            return Err(EXIT_LOGIN);
+        }
+    };
+    let (true, Some(token), Some(username)) =
+        (result.success, result.session_token, result.username)
+    else {
+        let error = result.error.unwrap_or_else(|| "login failed".to_string());
+        record(
+            AuditEntry::new(AuditEvent::LoginFailure)
+                .username(username)
+                .detail(error.clone()),
+        );
+        eprintln!("{error}");
+        return Err(EXIT_LOGIN);
+    };
+    let token = SecretString::new(token);
+
+    let mut totp = TotpManager::load(Some(data_dir.join("totp.json")));
+    if totp.is_enrolled(&username) {
+        record(AuditEntry::new(AuditEvent::SecondFactorRequired).username(&username));
+        let code = std::env::var(TOTP_CODE_ENV).unwrap_or_default();
+        // A fresh manager has no earlier challenges to expire
+        let (challenge, _) = totp.begin_challenge(token.clone(), username.clone());
+        if let Err(e) = totp.complete_challenge(&challenge, code.trim()) {
+            record(
+                AuditEntry::new(AuditEvent::SecondFactorFailure)
+                    .username(&username)
+                    .detail(e.to_string()),
+            );
+            eprintln!("second factor: {e} (code from {TOTP_CODE_ENV})");
+            logout(kernel, &token);
+            return Err(EXIT_LOGIN);
+        }
+    }
+
+    let mut session = auth::create_session(
+        token.expose().to_string(),
+        username.clone(),
+        ClientInfo::new(CLIENT_WINDOW, env!("CARGO_PKG_VERSION")),
+    );
+    session.role = resolve_role(kernel, data_dir, &username);
+    let info = session.info();
+    record(
+        AuditEntry::new(AuditEvent::LoginSuccess)
+            .username(&username)
+            .session_id(&info.session_id),
+    );
+    Ok(Login { token, info })
+}
+
+/// The user's role from their groups and `roles.json`, as at app login
+fn resolve_role(kernel: &SharedKernel, data_dir: &Path, username: &str) -> Role {
+    #[derive(serde::Deserialize)]
+    struct Groups {
+        groups: Vec<String>,
+    }
+    let response = request_shared(kernel, "auth/groups", json!({ "username": username }));
+    match response.and_then(rpc_result::<Groups>) {
+        Ok(Groups { groups }) => {
+            RoleMapping::load(Some(&data_dir.join("roles.json"))).role_for(&groups)
+        }
+        Err(e) => {
+            eprintln!("failed to resolve the role of {username} ({e}); using the user role");
+            Role::User
+        }
+    }
+}
+
+/// Make the call (within the session's role, if logged in) and print the
+/// outcome; the exit status
+fn call(
+    kernel: &SharedKernel,
+    data_dir: &Path,
+    login: Option<&Login>,
+    method: &str,
+    params: Value,
+) -> i32 {
+    let params = match login {
+        Some(login) => {
+            let policy = MethodPolicy::load(Some(&data_dir.join("method-policy.json")));
+            if let Err(denied) = policy.check(login.info.role, method) {
+                return print_error(&denied.to_response()["error"]);
+            }
+            auth::with_session_claims(params, &login.info)
+        }
+        None => params,
+    };
+    let response = match request_shared(kernel, method, params) {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("{e}");
+            return EXIT_FAILED;
+        }
+    };
+    if let Some(error) = response.get("error") {
+        return print_error(error);
+    }
+    match rpc_result::<Value>(response) {
+        Ok(result) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&result).unwrap_or_default()
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            EXIT_FAILED
+        }
+    }
+}
+
+fn print_error(error: &Value) -> i32 {
+    eprintln!(
+        "{}",
+        serde_json::to_string_pretty(error).unwrap_or_default()
+    );
+    EXIT_FAILED
+}
+
+/// Drop the kernel-side session (and its key), best effort
+fn logout(kernel: &SharedKernel, token: &SecretString) {
+    if let Some(proc) = kernel::lock_shared(kernel).as_mut() {
+        let _ = proc.request("auth/logout", json!({ "session_token": token.expose() }));
+    }
+}
+
+fn stop(kernel: &SharedKernel) {
+    if let Some(proc) = kernel::lock_shared(kernel).take() {
+        proc.shutdown(STOP_GRACE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, ArgsError> {
+        Args::parse(
+            std::iter::once("reos-cli")
+                .chain(args.iter().copied())
+                .map(String::from),
+        )
+    }
+
+    #[test]
+    fn test_call_args() {
+        let args = parse(&["call", "files/list", "--json", r#"{"path":"~"}"#]).unwrap();
+        assert_eq!(args.method.as_deref(), Some("files/list"));
+        assert_eq!(args.params, json!({ "path": "~" }));
+        assert_eq!(args.user, None);
+
+        let args = parse(&[
+            "--user=alice",
+            "--password-stdin",
+            "--profile",
+            "work",
+            "call",
+            "notes/search",
+        ])
+        .unwrap();
+        assert_eq!(args.user.as_deref(), Some("alice"));
+        assert!(args.password_stdin);
+        assert_eq!(args.profile.as_deref(), Some("work"));
+        assert_eq!(args.params, json!({}));
+    }
+
+    #[test]
+    fn test_bad_args() {
+        assert_eq!(parse(&["call"]), Err(ArgsError::MissingMethod));
+        assert_eq!(
+            parse(&["call", "a/b", "--json"]),
+            Err(ArgsError::MissingValue(JSON_FLAG))
+        );
+        assert!(matches!(
+            parse(&["call", "a/b", "--json", "{"]),
+            Err(ArgsError::Params(_))
+        ));
+        assert_eq!(
+            parse(&["list"]),
+            Err(ArgsError::UnknownCommand("list".into()))
+        );
+        assert_eq!(
+            parse(&["call", "a/b", "--verbose"]),
+            Err(ArgsError::UnknownFlag("--verbose".into()))
+        );
+        assert_eq!(parse(&["--help"]).unwrap().method, None);
+        assert_eq!(parse(&[]).unwrap().method, None);
+    }
+}
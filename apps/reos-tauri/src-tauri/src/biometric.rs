@@ -0,0 +1,406 @@
+//! Biometric Unlock for ReOS
+//!
+//! Alternative to the PIN for resuming a locked session. Biometrics never
+//! replace the initial PAM login: they can only reopen a session that the
+//! kernel already authenticated, and only within the policy's window since
+//! that password login.
+//!
+//! Platforms:
+//! - Linux: fprintd (`fprintd-list` / `fprintd-verify`)
+//! - macOS: Touch ID (LocalAuthentication, biometrics only)
+//! - Windows: Windows Hello (`UserConsentVerifier`; Hello's own PIN counts)
+//!
+//! fprintd checks the named user's fingers. Touch ID and Hello can only
+//! vouch for whoever is signed in to the OS, so there they are offered
+//! only when that account has the session's username.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::storage::write_private_file;
+
+/// How long to wait for a finger before giving up
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Error)]
+pub enum BiometricError {
+    #[error("biometric unlock is disabled by policy")]
+    Disabled,
+    #[error("biometric unlock is not supported on this platform")]
+    Unsupported,
+    #[error("no biometric enrolled for this user")]
+    NotEnrolled,
+    #[error("biometric verification failed")]
+    NoMatch,
+    #[error("biometric verification timed out")]
+    Timeout,
+    #[error("password login too old for biometric unlock, please log in again")]
+    ReauthRequired,
+    #[error("biometric service error: {0}")]
+    Service(String),
+    #[error("failed to save biometric settings: {0}")]
+    Persist(String),
+}
+
+/// Deployment policy for biometric unlock
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct BiometricPolicy {
+    /// Master switch (opt-in)
+    pub enabled: bool,
+    /// Biometric unlock is refused once the PAM login is older than this
+    #[serde(with = "duration_secs")]
+    pub max_since_password: Duration,
+}
+
+impl Default for BiometricPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_since_password: Duration::from_secs(8 * 60 * 60),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct BiometricFile {
+    #[serde(default)]
+    policy: BiometricPolicy,
+    #[serde(default)]
+    users: HashSet<String>,
+}
+
+/// Policy plus the set of users who opted in
+pub struct BiometricManager {
+    path: Option<PathBuf>,
+    policy: BiometricPolicy,
+    users: HashSet<String>,
+}
+
+impl BiometricManager {
+    /// Load settings from `path` (missing or unreadable file = defaults)
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let file = path
+            .as_ref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str::<BiometricFile>(&s).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            policy: file.policy,
+            users: file.users,
+        }
+    }
+
+    fn save(&self) -> Result<(), BiometricError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let file = BiometricFile {
+            policy: self.policy.clone(),
+            users: self.users.clone(),
+        };
+        let data =
+            serde_json::to_vec_pretty(&file).map_err(|e| BiometricError::Persist(e.to_string()))?;
+        write_private_file(path, &data).map_err(|e| BiometricError::Persist(e.to_string()))
+    }
+
+    pub fn policy(&self) -> &BiometricPolicy {
+        &self.policy
+    }
+
+    /// Whether `username` may resume locked sessions with a biometric
+    pub fn is_enabled(&self, username: &str) -> bool {
+        self.policy.enabled && self.users.contains(username)
+    }
+
+    /// Opt `username` in (requires an enrolled biometric on this machine)
+    pub fn enable(&mut self, username: &str) -> Result<(), BiometricError> {
+        if !self.policy.enabled {
+            return Err(BiometricError::Disabled);
+        }
+        if !is_enrolled(username)? {
+            return Err(BiometricError::NotEnrolled);
+        }
+        self.users.insert(username.to_string());
+        self.save()
+    }
+
+    /// Opt `username` out
+    pub fn disable(&mut self, username: &str) -> Result<(), BiometricError> {
+        self.users.remove(username);
+        self.save()
+    }
+
+    /// Check policy for a locked session whose PAM login was `since_password` ago
+    pub fn check_policy(
+        &self,
+        username: &str,
+        since_password: Duration,
+    ) -> Result<(), BiometricError> {
+        if !self.is_enabled(username) {
+            return Err(BiometricError::Disabled);
+        }
+        if since_password > self.policy.max_since_password {
+            return Err(BiometricError::ReauthRequired);
+        }
+        Ok(())
+    }
+}
+
+/// Thread-safe biometric state
+pub struct BiometricState(pub Mutex<BiometricManager>);
+
+impl BiometricState {
+    pub fn load(path: Option<PathBuf>) -> Self {
+        Self(Mutex::new(BiometricManager::load(path)))
+    }
+}
+
+/// Whether `username` has at least one biometric enrolled with the platform
+#[cfg(target_os = "linux")]
+pub fn is_enrolled(username: &str) -> Result<bool, BiometricError> {
+    let output = std::process::Command::new("fprintd-list")
+        .arg(username)
+        .output()
+        .map_err(|_| BiometricError::Unsupported)?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(output.status.success() && !stdout.contains("no fingers enrolled"))
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+pub fn is_enrolled(username: &str) -> Result<bool, BiometricError> {
+    if !is_os_user(username) {
+        return Ok(false);
+    }
+    platform::is_enrolled()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn is_enrolled(_username: &str) -> Result<bool, BiometricError> {
+    Err(BiometricError::Unsupported)
+}
+
+/// Prompt for and verify a biometric for `username` (blocks until done)
+#[cfg(target_os = "linux")]
+pub fn verify(username: &str) -> Result<(), BiometricError> {
+    use std::io::Read;
+    use std::process::{Command, Stdio};
+    use std::time::Instant;
+
+    let mut child = Command::new("fprintd-verify")
+        .arg(username)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|_| BiometricError::Unsupported)?;
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() > VERIFY_TIMEOUT => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(BiometricError::Timeout);
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(100)),
+            Err(e) => return Err(BiometricError::Service(e.to_string())),
+        }
+    };
+
+    let mut stdout = String::new();
+    if let Some(mut out) = child.stdout.take() {
+        let _ = out.read_to_string(&mut stdout);
+    }
+    if stdout.contains("verify-match") && status.success() {
+        Ok(())
+    } else if stdout.contains("No devices available") {
+        Err(BiometricError::Unsupported)
+    } else {
+        Err(BiometricError::NoMatch)
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+pub fn verify(username: &str) -> Result<(), BiometricError> {
+    if !is_os_user(username) {
+        return Err(BiometricError::NotEnrolled);
+    }
+    platform::verify()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn verify(_username: &str) -> Result<(), BiometricError> {
+    Err(BiometricError::Unsupported)
+}
+
+/// Whether `username` is the account signed in to the OS (the only one
+/// Touch ID / Hello can vouch for)
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn is_os_user(username: &str) -> bool {
+    let var = if cfg!(target_os = "windows") {
+        "USERNAME"
+    } else {
+        "USER"
+    };
+    std::env::var(var).is_ok_and(|user| {
+        if cfg!(target_os = "windows") {
+            user.eq_ignore_ascii_case(username)
+        } else {
+            user == username
+        }
+    })
+}
+
+/// Touch ID through LocalAuthentication
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{BiometricError, VERIFY_TIMEOUT};
+    use block2::RcBlock;
+    use objc2::runtime::Bool;
+    use objc2_foundation::{NSError, NSString};
+    use objc2_local_authentication::{LAContext, LAError, LAPolicy};
+    use std::sync::mpsc;
+
+    const POLICY: LAPolicy = LAPolicy::DeviceOwnerAuthenticationWithBiometrics;
+
+    pub fn is_enrolled() -> Result<bool, BiometricError> {
+        let context = unsafe { LAContext::new() };
+        match unsafe { context.canEvaluatePolicy_error(POLICY) } {
+            Ok(()) => Ok(true),
+            Err(e) if e.code() == LAError::BiometryNotEnrolled.0 => Ok(false),
+            Err(_) => Err(BiometricError::Unsupported),
+        }
+    }
+
+    pub fn verify() -> Result<(), BiometricError> {
+        let context = unsafe { LAContext::new() };
+        let (tx, rx) = mpsc::channel();
+        // Runs on a framework queue once the prompt is answered
+        let reply = RcBlock::new(move |success: Bool, error: *mut NSError| {
+            let code = unsafe { error.as_ref() }.map(|e| e.code());
+            let _ = tx.send((success.as_bool(), code));
+        });
+        let reason = NSString::from_str("unlock your session");
+        unsafe { context.evaluatePolicy_localizedReason_reply(POLICY, &reason, &reply) };
+
+        match rx.recv_timeout(VERIFY_TIMEOUT) {
+            Ok((true, _)) => Ok(()),
+            Ok((false, Some(code))) if code == LAError::BiometryNotEnrolled.0 => {
+                Err(BiometricError::NotEnrolled)
+            }
+            Ok((false, Some(code))) if code == LAError::BiometryNotAvailable.0 => {
+                Err(BiometricError::Unsupported)
+            }
+            Ok((false, _)) => Err(BiometricError::NoMatch),
+            Err(_) => {
+                // Dismisses the prompt
+                unsafe { context.invalidate() };
+                Err(BiometricError::Timeout)
+            }
+        }
+    }
+}
+
+/// Windows Hello through `UserConsentVerifier`
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{BiometricError, VERIFY_TIMEOUT};
+    use std::sync::mpsc;
+    use windows::core::HSTRING;
+    use windows::Security::Credentials::UI::{
+        UserConsentVerificationResult, UserConsentVerifier, UserConsentVerifierAvailability,
+    };
+
+    fn service(e: windows::core::Error) -> BiometricError {
+        BiometricError::Service(e.message())
+    }
+
+    pub fn is_enrolled() -> Result<bool, BiometricError> {
+        let availability = UserConsentVerifier::CheckAvailabilityAsync()
+            .and_then(|operation| operation.get())
+            .map_err(service)?;
+        match availability {
+            UserConsentVerifierAvailability::Available => Ok(true),
+            UserConsentVerifierAvailability::NotConfiguredForUser => Ok(false),
+            _ => Err(BiometricError::Unsupported),
+        }
+    }
+
+    pub fn verify() -> Result<(), BiometricError> {
+        let operation =
+            UserConsentVerifier::RequestVerificationAsync(&HSTRING::from("Unlock your session"))
+                .map_err(service)?;
+        let (tx, rx) = mpsc::channel();
+        let waiting = operation.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(waiting.get());
+        });
+        let result = match rx.recv_timeout(VERIFY_TIMEOUT) {
+            Ok(result) => result.map_err(service)?,
+            Err(_) => {
+                // Dismisses the prompt
+                let _ = operation.Cancel();
+                return Err(BiometricError::Timeout);
+            }
+        };
+        match result {
+            UserConsentVerificationResult::Verified => Ok(()),
+            UserConsentVerificationResult::NotConfiguredForUser => Err(BiometricError::NotEnrolled),
+            UserConsentVerificationResult::DeviceNotPresent
+            | UserConsentVerificationResult::DisabledByPolicy => Err(BiometricError::Unsupported),
+            UserConsentVerificationResult::DeviceBusy => {
+                Err(BiometricError::Service("device busy".to_string()))
+            }
+            _ => Err(BiometricError::NoMatch),
+        }
+    }
+}
+
+/// Serialize `Duration` as whole seconds
+mod duration_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(d.as_secs())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        u64::deserialize(d).map(Duration::from_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_gates_unlock() {
+        let mut manager = BiometricManager::load(None);
+        manager.users.insert("alice".to_string());
+        assert!(matches!(
+            manager.check_policy("alice", Duration::ZERO),
+            Err(BiometricError::Disabled)
+        ));
+
+        manager.policy.enabled = true;
+        assert!(manager
+            .check_policy("alice", Duration::from_secs(60))
+            .is_ok());
+        assert!(matches!(
+            manager.check_policy("alice", Duration::from_secs(9 * 60 * 60)),
+            Err(BiometricError::ReauthRequired)
+        ));
+        assert!(matches!(
+            manager.check_policy("bob", Duration::ZERO),
+            Err(BiometricError::Disabled)
+        ));
+    }
+}
@@ -0,0 +1,550 @@
+//! Out-of-Band Transfer of Large Kernel Results
+//!
+//! A result of hundreds of megabytes shouldn't cross the pipe as one JSON
+//! line. `initialize` offers the kernel a private directory and a size
+//! threshold; above it the kernel writes the serialized result elsewhere
+//! and answers with a reference (`reos.blob_transfer` on the Python side):
+//!
+//! - `memfd` (Linux): a sealed anonymous memory file, opened here through
+//!   `/proc/<kernel pid>/fd/<fd>` (the kernel is our child)
+//! - `file`: a file in the offered directory, unlinked once opened
+//!
+//! The data is sealed with ChaCha20-Poly1305 in `SEGMENT_SIZE` segments
+//! (nonce = segment index) under a fresh key that only travels in the
+//! encrypted response frame, so the bytes outside the pipe need no trust:
+//! a swapped, truncated or edited file fails to open or to decrypt. The
+//! reference is checked (place, kind, exact sealed size) before anything
+//! is read.
+//!
+//! The response handed on then carries a handle instead of the result
+//! (`{"__blob": {"handle", "size", "segments", "segment_size"}}`). The
+//! frontend streams it with `kernel_blob_read` (one decrypted segment per
+//! call, as raw bytes) and frees it with `kernel_blob_release`; only the
+//! session whose request produced it can read it. The shell's own calls
+//! get the parsed result back transparently (`kernel::rpc_result`).
+//! Handles not released expire after `BLOB_TTL`.
+//!
+//! `blobs.json` in the app data dir (missing or invalid file = defaults):
+//! `{ "enabled": true, "threshold_bytes": 8388608, "max_bytes": 2147483648, "mmap": false }`
+//!
+//! With `mmap`, file blobs are memory-mapped instead of read through the
+//! file: less copying, but another process of the same user that opened the
+//! file before it was unlinked could truncate it and crash the app, so
+//! it's off by default.
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::locks::Recover;
+
+/// Plaintext bytes per sealed segment (mirrors `blob_transfer.py`)
+pub const SEGMENT_SIZE: u64 = 1024 * 1024;
+
+/// Poly1305 tag after each segment
+const TAG_LEN: u64 = 16;
+
+/// Handles held at once (a new blob beyond this is refused)
+const MAX_OPEN_BLOBS: usize = 8;
+
+/// Handles never released are dropped after this long
+pub const BLOB_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// memfd name the kernel uses (`/proc/.../fd/N` links to `/memfd:<name>`)
+#[cfg(target_os = "linux")]
+const MEMFD_NAME: &str = "/memfd:reos-blob";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum BlobError {
+    #[error("invalid blob reference: {0}")]
+    Invalid(&'static str),
+    #[error("blob of {size} bytes is over the {max} byte limit")]
+    TooLarge { size: u64, max: u64 },
+    #[error("too many large results open; release one first")]
+    TooMany,
+    #[error("cannot read blob: {0}")]
+    Io(String),
+    #[error("blob data was altered")]
+    Tampered,
+    #[error("unknown or expired blob handle")]
+    UnknownHandle,
+    #[error("blob segment {0} out of range")]
+    OutOfRange(u64),
+    #[error("blob is not valid JSON: {0}")]
+    Json(String),
+}
+
+impl From<io::Error> for BlobError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BlobConfig {
+    pub enabled: bool,
+    /// Serialized results larger than this go out of band
+    pub threshold_bytes: u64,
+    /// Largest blob accepted
+    pub max_bytes: u64,
+    /// Memory-map file blobs (see the module docs)
+    pub mmap: bool,
+}
+
+impl Default for BlobConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold_bytes: 8 * 1024 * 1024,
+            max_bytes: 2 * 1024 * 1024 * 1024,
+            mmap: false,
+        }
+    }
+}
+
+impl BlobConfig {
+    /// Load from `path` (missing or invalid file = defaults)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Directory offered to the kernel and the config, once set up
+static OFFER: OnceLock<(PathBuf, BlobConfig)> = OnceLock::new();
+
+/// Create the private blob directory (clearing files left by an earlier
+/// run) and offer it to kernels started from now on; at startup
+pub fn init(dir: PathBuf, config: BlobConfig) -> io::Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+    fs::create_dir_all(&dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))?;
+    }
+    for entry in fs::read_dir(&dir)?.flatten() {
+        let _ = fs::remove_file(entry.path());
+    }
+    let _ = OFFER.set((dir, config));
+    Ok(())
+}
+
+/// `blobs` param of `initialize` (`null`: results stay in the pipe)
+pub fn offer() -> Value {
+    match OFFER.get() {
+        Some((dir, config)) => json!({
+            "dir": dir.to_string_lossy(),
+            "threshold": config.threshold_bytes,
+        }),
+        None => Value::Null,
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum BlobKind {
+    File,
+    Memfd,
+}
+
+/// `__blob` reference in a kernel response
+#[derive(Deserialize)]
+struct BlobSpec {
+    kind: BlobKind,
+    path: Option<PathBuf>,
+    fd: Option<u32>,
+    size: u64,
+    key: String,
+    encoding: String,
+}
+
+impl Drop for BlobSpec {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+fn segment_count(size: u64) -> u64 {
+    size.div_ceil(SEGMENT_SIZE)
+}
+
+/// Bytes on disk for `size` bytes of plaintext
+fn sealed_len(size: u64) -> u64 {
+    size + segment_count(size) * TAG_LEN
+}
+
+fn nonce(index: u64) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&index.to_le_bytes());
+    Nonce::from(nonce)
+}
+
+enum Backing {
+    File(File),
+    Mapped(memmap2::Mmap),
+}
+
+impl Backing {
+    fn read(&mut self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        match self {
+            Self::File(file) => {
+                let mut buf = vec![0u8; len];
+                file.seek(SeekFrom::Start(offset))?;
+                file.read_exact(&mut buf)?;
+                Ok(buf)
+            }
+            Self::Mapped(map) => {
+                let start = usize::try_from(offset).map_err(io::Error::other)?;
+                map.get(start..start + len)
+                    .map(<[u8]>::to_vec)
+                    .ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+            }
+        }
+    }
+}
+
+/// An opened blob, decrypted a segment at a time
+pub struct Blob {
+    backing: Mutex<Backing>,
+    cipher: ChaCha20Poly1305,
+    size: u64,
+    /// Session whose request produced it (`None` for the shell's own calls)
+    owner: Option<String>,
+    opened: Instant,
+}
+
+impl Blob {
+    fn new(backing: Backing, key: &[u8; 32], size: u64, owner: Option<String>) -> Self {
+        Self {
+            backing: Mutex::new(backing),
+            cipher: ChaCha20Poly1305::new(Key::from_slice(key)),
+            size,
+            owner,
+            opened: Instant::now(),
+        }
+    }
+
+    fn segments(&self) -> u64 {
+        segment_count(self.size)
+    }
+
+    /// Plaintext of segment `index`
+    pub fn segment(&self, index: u64) -> Result<Vec<u8>, BlobError> {
+        if index >= self.segments() {
+            return Err(BlobError::OutOfRange(index));
+        }
+        let plain = SEGMENT_SIZE.min(self.size - index * SEGMENT_SIZE);
+        let offset = index * (SEGMENT_SIZE + TAG_LEN);
+        let len = usize::try_from(plain + TAG_LEN).map_err(|_| BlobError::Tampered)?;
+        let sealed = self.backing.lock_or_recover().read(offset, len)?;
+        self.cipher
+            .decrypt(&nonce(index), sealed.as_slice())
+            .map_err(|_| BlobError::Tampered)
+    }
+
+    /// The whole blob, parsed
+    fn json(&self) -> Result<Value, BlobError> {
+        let mut data = Vec::with_capacity(usize::try_from(self.size).unwrap_or(0));
+        for index in 0..self.segments() {
+            data.extend_from_slice(&self.segment(index)?);
+        }
+        serde_json::from_slice(&data).map_err(|e| BlobError::Json(e.to_string()))
+    }
+}
+
+/// Open the file or memfd a reference points at
+fn open_backing(spec: &BlobSpec, dir: &Path, kernel_pid: u32) -> Result<File, BlobError> {
+    match spec.kind {
+        BlobKind::File => {
+            let path = spec.path.as_deref().ok_or(BlobError::Invalid("no path"))?;
+            if path.parent() != Some(dir) || path.extension().is_none_or(|ext| ext != "blob") {
+                return Err(BlobError::Invalid("outside the blob directory"));
+            }
+            if !fs::symlink_metadata(path)?.is_file() {
+                return Err(BlobError::Invalid("not a regular file"));
+            }
+            let file = File::open(path)?;
+            // The open handle is all that's needed from here on
+            let _ = fs::remove_file(path);
+            Ok(file)
+        }
+        #[cfg(target_os = "linux")]
+        BlobKind::Memfd => {
+            let fd = spec.fd.ok_or(BlobError::Invalid("no fd"))?;
+            let link = PathBuf::from(format!("/proc/{kernel_pid}/fd/{fd}"));
+            let target = fs::read_link(&link)?;
+            if !target.to_string_lossy().starts_with(MEMFD_NAME) {
+                return Err(BlobError::Invalid("fd is not a kernel memfd"));
+            }
+            Ok(File::open(&link)?)
+        }
+        #[cfg(not(target_os = "linux"))]
+        BlobKind::Memfd => {
+            let _ = kernel_pid;
+            Err(BlobError::Invalid("memfd is Linux-only"))
+        }
+    }
+}
+
+fn open(
+    spec: &BlobSpec,
+    dir: &Path,
+    config: &BlobConfig,
+    kernel_pid: u32,
+    owner: Option<String>,
+) -> Result<Blob, BlobError> {
+    if spec.encoding != "json" {
+        return Err(BlobError::Invalid("unknown encoding"));
+    }
+    if spec.size == 0 {
+        return Err(BlobError::Invalid("empty"));
+    }
+    if spec.size > config.max_bytes {
+        return Err(BlobError::TooLarge {
+            size: spec.size,
+            max: config.max_bytes,
+        });
+    }
+    let key = Zeroizing::new(hex::decode(spec.key.as_str()).unwrap_or_default());
+    let key: Zeroizing<[u8; 32]> = <[u8; 32]>::try_from(key.as_slice())
+        .map(Zeroizing::new)
+        .map_err(|_| BlobError::Invalid("bad key"))?;
+    let file = open_backing(spec, dir, kernel_pid)?;
+    if file.metadata()?.len() != sealed_len(spec.size) {
+        return Err(BlobError::Invalid("size mismatch"));
+    }
+    let backing = match spec.kind {
+        BlobKind::File if config.mmap => {
+            // SAFETY: the file was unlinked from our private directory right
+            // after opening; only a process that already had it open could
+            // still change its length (see the module docs)
+            Backing::Mapped(unsafe { memmap2::Mmap::map(&file)? })
+        }
+        _ => Backing::File(file),
+    };
+    Ok(Blob::new(backing, &key, spec.size, owner))
+}
+
+/// Open blobs by handle
+static BLOBS: Mutex<BTreeMap<String, Arc<Blob>>> = Mutex::new(BTreeMap::new());
+
+fn prune(blobs: &mut BTreeMap<String, Arc<Blob>>) {
+    blobs.retain(|_, blob| blob.opened.elapsed() < BLOB_TTL);
+}
+
+fn insert(blob: Blob) -> Result<Value, BlobError> {
+    let mut blobs = BLOBS.lock_or_recover();
+    prune(&mut blobs);
+    if blobs.len() >= MAX_OPEN_BLOBS {
+        return Err(BlobError::TooMany);
+    }
+    let mut bytes = [0u8; 16];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    let handle = hex::encode(bytes);
+    let reference = json!({ "__blob": {
+        "handle": handle,
+        "size": blob.size,
+        "segments": blob.segments(),
+        "segment_size": SEGMENT_SIZE,
+    }});
+    blobs.insert(handle, Arc::new(blob));
+    Ok(reference)
+}
+
+/// Open the blob a kernel response references (`__blob`) and keep it; the
+/// handle reference that replaces the result
+pub fn receive(spec: Value, kernel_pid: u32, owner: Option<String>) -> Result<Value, BlobError> {
+    let (dir, config) = OFFER.get().ok_or(BlobError::Invalid("none offered"))?;
+    let spec: BlobSpec =
+        serde_json::from_value(spec).map_err(|_| BlobError::Invalid("malformed"))?;
+    insert(open(&spec, dir, config, kernel_pid, owner)?)
+}
+
+/// Handle of a result that was moved out of band
+pub fn handle_of(result: &Value) -> Option<&str> {
+    result.get("__blob")?.get("handle")?.as_str()
+}
+
+fn get(handle: &str, owner: &str) -> Result<Arc<Blob>, BlobError> {
+    let mut blobs = BLOBS.lock_or_recover();
+    prune(&mut blobs);
+    blobs
+        .get(handle)
+        .filter(|blob| blob.owner.as_deref() == Some(owner))
+        .cloned()
+        .ok_or(BlobError::UnknownHandle)
+}
+
+/// Segment `index` of a blob produced for session `owner`
+pub fn read(handle: &str, owner: &str, index: u64) -> Result<Vec<u8>, BlobError> {
+    get(handle, owner)?.segment(index)
+}
+
+/// Free a blob produced for session `owner`; whether it existed
+pub fn release(handle: &str, owner: &str) -> bool {
+    let mut blobs = BLOBS.lock_or_recover();
+    let owned = blobs
+        .get(handle)
+        .is_some_and(|blob| blob.owner.as_deref() == Some(owner));
+    owned && blobs.remove(handle).is_some()
+}
+
+/// Parse and free a blob, for the shell's own calls
+pub fn take_json(handle: &str) -> Result<Value, BlobError> {
+    let blob = BLOBS
+        .lock_or_recover()
+        .remove(handle)
+        .ok_or(BlobError::UnknownHandle)?;
+    blob.json()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seal(data: &[u8], key: &[u8; 32]) -> Vec<u8> {
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+        data.chunks(SEGMENT_SIZE as usize)
+            .enumerate()
+            .flat_map(|(i, chunk)| cipher.encrypt(&nonce(i as u64), chunk).unwrap())
+            .collect()
+    }
+
+    fn blob_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("reos-blobs-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn spec(path: &Path, size: u64, key: &[u8; 32]) -> BlobSpec {
+        BlobSpec {
+            kind: BlobKind::File,
+            path: Some(path.to_path_buf()),
+            fd: None,
+            size,
+            key: hex::encode(key),
+            encoding: "json".into(),
+        }
+    }
+
+    #[test]
+    fn test_file_blob_round_trip() {
+        let dir = blob_dir("round-trip");
+        let data = serde_json::to_vec(&json!({ "rows": vec!["x".repeat(1000); 1500] })).unwrap();
+        let key = [7u8; 32];
+        let path = dir.join("a.blob");
+        for mmap in [false, true] {
+            fs::write(&path, seal(&data, &key)).unwrap();
+            let config = BlobConfig {
+                mmap,
+                ..BlobConfig::default()
+            };
+            let blob = open(
+                &spec(&path, data.len() as u64, &key),
+                &dir,
+                &config,
+                0,
+                None,
+            )
+            .unwrap();
+            assert!(!path.exists(), "unlinked once opened");
+            assert_eq!(blob.segments(), 2);
+            assert_eq!(
+                blob.segment(1).unwrap().len(),
+                data.len() - SEGMENT_SIZE as usize
+            );
+            assert_eq!(blob.json().unwrap()["rows"][0], json!("x".repeat(1000)));
+            assert_eq!(blob.segment(2), Err(BlobError::OutOfRange(2)));
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_tampered_or_misplaced_blobs_rejected() {
+        let dir = blob_dir("tampered");
+        let config = BlobConfig::default();
+        let key = [9u8; 32];
+        let data = b"\"hello\"";
+        let path = dir.join("b.blob");
+
+        let mut sealed = seal(data, &key);
+        sealed[2] ^= 1;
+        fs::write(&path, &sealed).unwrap();
+        let blob = open(
+            &spec(&path, data.len() as u64, &key),
+            &dir,
+            &config,
+            0,
+            None,
+        )
+        .unwrap();
+        assert_eq!(blob.segment(0), Err(BlobError::Tampered));
+
+        // Truncated: the sealed size no longer matches
+        fs::write(&path, &seal(data, &key)[..10]).unwrap();
+        let opened = open(
+            &spec(&path, data.len() as u64, &key),
+            &dir,
+            &config,
+            0,
+            None,
+        );
+        assert_eq!(opened.err(), Some(BlobError::Invalid("size mismatch")));
+
+        let outside = std::env::temp_dir().join("c.blob");
+        let opened = open(&spec(&outside, 7, &key), &dir, &config, 0, None);
+        assert_eq!(
+            opened.err(),
+            Some(BlobError::Invalid("outside the blob directory"))
+        );
+        let opened = open(
+            &spec(&path, config.max_bytes + 1, &key),
+            &dir,
+            &config,
+            0,
+            None,
+        );
+        assert!(matches!(opened, Err(BlobError::TooLarge { .. })));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_handles_belong_to_their_session() {
+        let dir = blob_dir("handles");
+        let key = [3u8; 32];
+        let path = dir.join("d.blob");
+        fs::write(&path, seal(b"[1,2,3]", &key)).unwrap();
+        let blob = open(
+            &spec(&path, 7, &key),
+            &dir,
+            &BlobConfig::default(),
+            0,
+            Some("s1".into()),
+        )
+        .unwrap();
+        let reference = insert(blob).unwrap();
+        let handle = handle_of(&reference).unwrap().to_string();
+        assert_eq!(read(&handle, "s2", 0), Err(BlobError::UnknownHandle));
+        assert_eq!(read(&handle, "s1", 0).unwrap(), b"[1,2,3]");
+        assert!(!release(&handle, "s2"));
+        assert!(release(&handle, "s1"));
+        assert_eq!(take_json(&handle), Err(BlobError::UnknownHandle));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,218 @@
+//! Command-Line Flags
+//!
+//! For packagers and admins pinning behavior from `.desktop` files and
+//! scripts. Values are given as `--flag value` or `--flag=value`:
+//!
+//! - `--kernel-path <python>`: kernel interpreter (`kernel.python`)
+//! - `--log-level <level>`: default log level, over `RUST_LOG` too
+//! - `--profile <name>`: run a separate profile (see `profile.rs`)
+//! - `--start-locked`: sessions restored at startup come up locked
+//! - `--portable`: keep everything beside the executable (see `portable.rs`)
+//! - `--headless-check`: print a JSON readiness report without opening a
+//!   window and exit (status 0 if ready, 1 if not)
+//!
+//! Flags win over `REOS_*` variables and `config.toml`. Other arguments
+//! (autostart flags, `reos://` links) are left to their own modules.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::config::{ConfigState, Override};
+use crate::first_run::{Setup, SetupStatus};
+use crate::health::{self, ConfigProblem, DiskSpace};
+use crate::logging;
+use crate::portable;
+use crate::profile::{self, ProfileError};
+
+const KERNEL_PATH_FLAG: &str = "--kernel-path";
+const LOG_LEVEL_FLAG: &str = "--log-level";
+const PROFILE_FLAG: &str = "--profile";
+const START_LOCKED_FLAG: &str = "--start-locked";
+const HEADLESS_CHECK_FLAG: &str = "--headless-check";
+const PORTABLE_FLAG: &str = "--portable";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CliError {
+    #[error("{0} needs a value")]
+    MissingValue(&'static str),
+    #[error("{LOG_LEVEL_FLAG}: unknown level {0:?}")]
+    Level(String),
+    #[error(transparent)]
+    Profile(#[from] ProfileError),
+}
+
+/// Parsed flags
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CliArgs {
+    pub kernel_path: Option<PathBuf>,
+    pub log_level: Option<String>,
+    /// `None` for the default profile
+    pub profile: Option<String>,
+    pub start_locked: bool,
+    pub headless_check: bool,
+    pub portable: bool,
+}
+
+impl CliArgs {
+    /// Parse `args` (program name first); the last of a repeated flag wins
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter().skip(1);
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let Some(flag) = [KERNEL_PATH_FLAG, LOG_LEVEL_FLAG, PROFILE_FLAG]
+                .into_iter()
+                .find(|&known| known == flag)
+            else {
+                match flag {
+                    START_LOCKED_FLAG => parsed.start_locked = true,
+                    HEADLESS_CHECK_FLAG => parsed.headless_check = true,
+                    PORTABLE_FLAG => parsed.portable = true,
+                    _ => {}
+                }
+                continue;
+            };
+            let value = inline
+                .or_else(|| args.next())
+                .filter(|value| !value.trim().is_empty())
+                .ok_or(CliError::MissingValue(flag))?;
+            match flag {
+                KERNEL_PATH_FLAG => parsed.kernel_path = Some(PathBuf::from(value)),
+                LOG_LEVEL_FLAG => {
+                    if !logging::is_level(&value) {
+                        return Err(CliError::Level(value));
+                    }
+                    parsed.log_level = Some(value);
+                }
+                _ => parsed.profile = profile::parse_name(value)?,
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Config keys the flags set, on top of `REOS_*` variables
+    pub fn overrides(&self) -> Vec<Override> {
+        let mut overrides = Vec::new();
+        if let Some(path) = &self.kernel_path {
+            overrides.push(Override::new(
+                KERNEL_PATH_FLAG,
+                "kernel.python",
+                &path.to_string_lossy(),
+            ));
+        }
+        if let Some(level) = &self.log_level {
+            overrides.push(Override::new(LOG_LEVEL_FLAG, "logging.level", level));
+        }
+        overrides
+    }
+}
+
+/// `--headless-check` report
+#[derive(Serialize)]
+pub struct HeadlessReport {
+    pub ok: bool,
+    pub profile: &'static str,
+    /// Portable data dir, if running portable
+    pub portable: Option<&'static Path>,
+    pub config_file: Option<PathBuf>,
+    /// Why `config.toml` would be ignored
+    pub config_error: Option<String>,
+    pub overrides: Vec<Override>,
+    pub config_problems: Vec<ConfigProblem>,
+    pub disk: Option<DiskSpace>,
+    pub setup: SetupStatus,
+}
+
+impl HeadlessReport {
+    /// Check what a normal start would use, from the app's data and config
+    /// dirs (already scoped to the profile)
+    pub fn check(args: &CliArgs, data_dir: &Path, config_dir: &Path) -> Self {
+        let config = ConfigState::load(Some(config_dir), args.overrides());
+        let config_error = config.error().map(|e| e.to_string());
+        // The kernel probe must use the interpreter a start would
+        crate::kernel::set_python(config.config().kernel.python);
+        let setup = Setup {
+            data_dir: data_dir.to_path_buf(),
+            config_dir: config_dir.to_path_buf(),
+        }
+        .status();
+        let config_problems = health::config_problems(data_dir);
+        let disk = DiskSpace::check(data_dir);
+        let ok = setup.complete
+            && config_error.is_none()
+            && config.overrides.iter().all(|o| o.error.is_none())
+            && config_problems.is_empty()
+            && !disk.as_ref().is_some_and(|disk| disk.low);
+        Self {
+            ok,
+            profile: profile::name(),
+            portable: portable::root(),
+            config_file: config.path.clone(),
+            config_error,
+            overrides: config.overrides.clone(),
+            config_problems,
+            disk,
+            setup,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliArgs, CliError> {
+        CliArgs::parse(
+            std::iter::once("reos")
+                .chain(args.iter().copied())
+                .map(str::to_string),
+        )
+    }
+
+    #[test]
+    fn test_flags_in_both_forms() {
+        let args = parse(&[
+            "--kernel-path",
+            "/opt/py/bin/python3",
+            "--log-level=debug",
+            "--profile=work",
+            "--start-locked",
+            "--portable",
+            "--autostart",
+            "reos://open",
+        ])
+        .unwrap();
+        assert_eq!(args.kernel_path, Some(PathBuf::from("/opt/py/bin/python3")));
+        assert_eq!(args.log_level.as_deref(), Some("debug"));
+        assert_eq!(args.profile.as_deref(), Some("work"));
+        assert!(args.start_locked && args.portable && !args.headless_check);
+        let keys: Vec<_> = args.overrides().iter().map(|o| o.key).collect();
+        assert_eq!(keys, vec!["kernel.python", "logging.level"]);
+        assert_eq!(parse(&[]), Ok(CliArgs::default()));
+    }
+
+    #[test]
+    fn test_bad_flag_values_rejected() {
+        assert_eq!(
+            parse(&["--log-level"]),
+            Err(CliError::MissingValue(LOG_LEVEL_FLAG))
+        );
+        assert_eq!(
+            parse(&["--kernel-path="]),
+            Err(CliError::MissingValue(KERNEL_PATH_FLAG))
+        );
+        assert_eq!(
+            parse(&["--log-level", "loud"]),
+            Err(CliError::Level("loud".into()))
+        );
+        assert!(matches!(
+            parse(&["--profile", "../x"]),
+            Err(CliError::Profile(_))
+        ));
+        assert_eq!(parse(&["--profile", "default"]).unwrap().profile, None);
+    }
+}
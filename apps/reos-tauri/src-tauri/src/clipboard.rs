@@ -0,0 +1,277 @@
+//! Clipboard Bridge for ReOS
+//!
+//! `clipboard_write` / `clipboard_read` move plain text only, capped at
+//! `max_bytes`, and sanitize it both ways: control characters and
+//! invisible or bidi-override characters (which make pasted text read
+//! differently from what runs) are dropped, line endings normalized, and
+//! tracking parameters (`utm_*`, `fbclid`, ...) stripped from URLs.
+//!
+//! `clipboard_copy_secret` copies a vault entry from Rust, so the secret
+//! never reaches the webview, and clears it after `clear_secrets_after_secs`
+//! unless something else was copied over it in the meantime.
+//!
+//! Configurable via `clipboard.json` in the app data dir:
+//! `{ "max_bytes": 65536, "clear_secrets_after_secs": 20 }` (0 = never clear)
+//!
+//! The system clipboard is reached through arboard on Linux (X11, or
+//! Wayland compositors with the data-control protocol), macOS and Windows.
+//! It's kept open while ReOS runs and let go on exit, when X11 hands the
+//! last copied text to the clipboard manager.
+
+use crate::locks::Recover;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use thiserror::Error;
+use url::Url;
+
+const DEFAULT_MAX_BYTES: usize = 1024 * 1024;
+const DEFAULT_CLEAR_SECRETS_AFTER_SECS: u64 = 30;
+
+/// Query parameters that only track where a link was shared
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "igshid", "mc_cid",
+    "mc_eid", "_hsenc", "_hsmtk", "mkt_tok", "ref_src",
+];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ClipboardError {
+    #[error("Clipboard text is larger than {0} bytes")]
+    TooLarge(usize),
+    #[error("Clipboard unavailable: {0}")]
+    Unavailable(String),
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct ClipboardConfig {
+    pub max_bytes: usize,
+    /// Clear copied secrets after this long (0 = never)
+    pub clear_secrets_after_secs: u64,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_BYTES,
+            clear_secrets_after_secs: DEFAULT_CLEAR_SECRETS_AFTER_SECS,
+        }
+    }
+}
+
+impl ClipboardConfig {
+    /// Load the config from `path` (missing or invalid file = defaults)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// Sanitize `text` and check it against `max_bytes`
+    pub fn prepare(&self, text: &str) -> Result<String, ClipboardError> {
+        if text.len() > self.max_bytes {
+            return Err(ClipboardError::TooLarge(self.max_bytes));
+        }
+        Ok(sanitize(text))
+    }
+
+    pub fn clear_secrets_after(&self) -> Option<Duration> {
+        (self.clear_secrets_after_secs > 0)
+            .then(|| Duration::from_secs(self.clear_secrets_after_secs))
+    }
+}
+
+/// Characters that render as nothing or reorder text
+fn is_invisible(c: char) -> bool {
+    matches!(c,
+        '\u{200B}'..='\u{200F}'
+        | '\u{202A}'..='\u{202E}'
+        | '\u{2060}'..='\u{2064}'
+        | '\u{2066}'..='\u{2069}'
+        | '\u{FEFF}'
+        | '\u{00AD}')
+}
+
+/// Plain, visible text with tracking parameters removed from URLs
+pub fn sanitize(text: &str) -> String {
+    let cleaned: String = text
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .chars()
+        .filter(|&c| (!c.is_control() || c == '\n' || c == '\t') && !is_invisible(c))
+        .collect();
+    let mut out = String::with_capacity(cleaned.len());
+    let mut rest = cleaned.as_str();
+    // Rebuild token by token so whitespace is kept exactly
+    while !rest.is_empty() {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let (token, tail) = rest.split_at(end);
+        out.push_str(&strip_tracking(token));
+        let ws_end = tail
+            .find(|c: char| !c.is_whitespace())
+            .unwrap_or(tail.len());
+        out.push_str(&tail[..ws_end]);
+        rest = &tail[ws_end..];
+    }
+    out
+}
+
+/// `token` without tracking query parameters, if it's an http(s) URL
+fn strip_tracking(token: &str) -> String {
+    let Ok(mut url) = Url::parse(token) else {
+        return token.to_string();
+    };
+    if !matches!(url.scheme(), "http" | "https") || url.query().is_none() {
+        return token.to_string();
+    }
+    let kept: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| {
+            let key = key.to_ascii_lowercase();
+            !key.starts_with("utm_") && !TRACKING_PARAMS.contains(&key.as_str())
+        })
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    if kept.len() == url.query_pairs().count() {
+        return token.to_string();
+    }
+    if kept.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(kept);
+    }
+    url.to_string()
+}
+
+fn digest(text: &str) -> [u8; 32] {
+    Sha256::digest(text.as_bytes()).into()
+}
+
+/// Config, the system clipboard and the secret currently on it (by hash)
+pub struct ClipboardState {
+    pub config: ClipboardConfig,
+    secret: Mutex<Option<(u64, [u8; 32])>>,
+    /// Opened on first use
+    system: Mutex<Option<arboard::Clipboard>>,
+}
+
+impl ClipboardState {
+    pub fn new(config: ClipboardConfig) -> Self {
+        Self {
+            config,
+            secret: Mutex::new(None),
+            system: Mutex::new(None),
+        }
+    }
+
+    /// Run `f` with the system clipboard (blocking)
+    fn with_system<T>(
+        &self,
+        f: impl FnOnce(&mut arboard::Clipboard) -> Result<T, arboard::Error>,
+    ) -> Result<T, ClipboardError> {
+        let unavailable = |e: arboard::Error| ClipboardError::Unavailable(e.to_string());
+        let mut system = self.system.lock_or_recover();
+        let clipboard = match system.as_mut() {
+            Some(clipboard) => clipboard,
+            None => system.insert(arboard::Clipboard::new().map_err(unavailable)?),
+        };
+        f(clipboard).map_err(unavailable)
+    }
+
+    /// Clipboard text (`None` if the clipboard holds no text)
+    pub fn read(&self) -> Result<Option<String>, ClipboardError> {
+        self.with_system(|clipboard| match clipboard.get_text() {
+            Ok(text) => Ok(Some(text)),
+            Err(arboard::Error::ContentNotAvailable) => Ok(None),
+            Err(e) => Err(e),
+        })
+    }
+
+    pub fn write(&self, text: &str) -> Result<(), ClipboardError> {
+        self.with_system(|clipboard| clipboard.set_text(text))
+    }
+
+    pub fn clear(&self) -> Result<(), ClipboardError> {
+        self.with_system(|clipboard| clipboard.clear())
+    }
+
+    /// Let go of the system clipboard (on exit)
+    pub fn close(&self) {
+        self.system.lock_or_recover().take();
+    }
+
+    /// Remember that `text` (a secret) was copied; returns a ticket for
+    /// `should_clear`
+    pub fn copied_secret(&self, text: &str) -> u64 {
+        let mut secret = self.secret.lock_or_recover();
+        let ticket = secret.map_or(1, |(ticket, _)| ticket + 1);
+        *secret = Some((ticket, digest(text)));
+        ticket
+    }
+
+    /// Whether the clipboard, now holding `current`, still has the secret
+    /// copied under `ticket`
+    pub fn should_clear(&self, ticket: u64, current: Option<&str>) -> bool {
+        let mut secret = self.secret.lock_or_recover();
+        let still_ours = matches!(*secret, Some((t, hash)) if t == ticket
+            && current.is_some_and(|text| digest(text) == hash));
+        if secret.is_some_and(|(t, _)| t == ticket) {
+            *secret = None;
+        }
+        still_ours
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_strips_invisible_and_control_chars() {
+        assert_eq!(sanitize("a\u{202E}b\u{200B}c\r\nd\u{0007}\te"), "abc\nd\te");
+    }
+
+    #[test]
+    fn test_sanitize_strips_tracking_params() {
+        assert_eq!(
+            sanitize("see https://example.com/post?id=7&utm_source=x&fbclid=abc  now"),
+            "see https://example.com/post?id=7  now"
+        );
+        assert_eq!(
+            sanitize("https://example.com/?utm_medium=email"),
+            "https://example.com/"
+        );
+        // Untouched when there's nothing to strip
+        assert_eq!(
+            sanitize("https://example.com/a?b=c%20d"),
+            "https://example.com/a?b=c%20d"
+        );
+    }
+
+    #[test]
+    fn test_prepare_enforces_size_cap() {
+        let config = ClipboardConfig {
+            max_bytes: 4,
+            ..ClipboardConfig::default()
+        };
+        assert_eq!(config.prepare("abcd"), Ok("abcd".to_string()));
+        assert_eq!(config.prepare("abcde"), Err(ClipboardError::TooLarge(4)));
+    }
+
+    #[test]
+    fn test_secret_cleared_only_if_still_on_clipboard() {
+        let state = ClipboardState::new(ClipboardConfig::default());
+        let ticket = state.copied_secret("sk-live");
+        assert!(state.should_clear(ticket, Some("sk-live")));
+
+        let ticket = state.copied_secret("sk-live");
+        assert!(!state.should_clear(ticket, Some("something else")));
+
+        let old = state.copied_secret("one");
+        let new = state.copied_secret("two");
+        assert!(!state.should_clear(old, Some("one")));
+        assert!(state.should_clear(new, Some("two")));
+    }
+}
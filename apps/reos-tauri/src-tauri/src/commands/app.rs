@@ -0,0 +1,534 @@
+//! Application Commands
+//!
+//! Configuration, first-run setup, settings, logging, metrics, health,
+//! crash reports and state reset.
+
+use super::autostart::autostart_names;
+use crate::shell::kernel::KernelState;
+use crate::shell::sessions::{admin_session, require_step_up, user_session};
+use crate::shell::tray::refresh_tray;
+use crate::shell::window::check_window_nonce;
+use reos_tauri_lib::appearance::{Appearance, AppearanceState};
+use reos_tauri_lib::audit::{AuditEntry, AuditEvent, AuditState};
+use reos_tauri_lib::auth::AuthState;
+use reos_tauri_lib::autostart::AutostartStatus;
+use reos_tauri_lib::cli::{CliArgs, HeadlessReport};
+use reos_tauri_lib::config::{
+    AppConfig, ConfigLocation, ConfigState, Settings, SETTINGS_CHANGED_EVENT,
+};
+use reos_tauri_lib::crash_report::{CrashReport, CrashReportError, CrashReportState, CrashSummary};
+use reos_tauri_lib::features::{FeatureStatus, FeaturesState};
+use reos_tauri_lib::first_run::{Setup, SetupStatus, SetupStep, StepStatus};
+use reos_tauri_lib::health::{ConfigProblem, HealthReport, KernelReport};
+use reos_tauri_lib::locks::Recover;
+use reos_tauri_lib::logging::{Levels, LogConfig};
+use reos_tauri_lib::metrics::Snapshot;
+use reos_tauri_lib::migrations::MigrationState;
+use reos_tauri_lib::notifications::{Category, Notification};
+use reos_tauri_lib::power::{Battery, PowerState};
+use reos_tauri_lib::profile::ProfilePaths;
+use reos_tauri_lib::rate_limit::{FrontendLogLimitState, RequestRateLimitState};
+use reos_tauri_lib::request_history::RequestHistory;
+use reos_tauri_lib::step_up::StepUpState;
+use reos_tauri_lib::{
+    auth, autostart, config, crash_report, features, kernel, kernel_backend, logging, metrics,
+    notifications, profile, redact, request_history, telemetry,
+};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+use tracing::{error, warn};
+
+/// Where `config.toml` is looked for, whether it exists and why it is being
+/// ignored, if it is
+#[tauri::command]
+pub fn config_path(state: State<'_, ConfigState>) -> ConfigLocation {
+    ConfigLocation::of(&state)
+}
+
+/// Feature flags as they apply to the session's user (guests included)
+#[tauri::command]
+pub fn features_get(
+    auth_state: State<'_, AuthState>,
+    config_state: State<'_, ConfigState>,
+    features_state: State<'_, FeaturesState>,
+    session_token: String,
+) -> Result<Vec<FeatureStatus>, String> {
+    let username = {
+        let store = auth_state.read();
+        store
+            .check(&session_token)
+            .map_err(|e| e.to_string())?
+            .username
+    };
+    let kernel = features_state.0.lock_or_recover().clone();
+    Ok(features::resolve(
+        &kernel,
+        &config_state.config().features,
+        &username,
+    ))
+}
+
+pub fn first_run_setup(app: &AppHandle) -> Result<Setup, String> {
+    let path = app.path();
+    Ok(Setup {
+        data_dir: path.profile_data_dir().map_err(|e| e.to_string())?,
+        config_dir: path.profile_config_dir().map_err(|e| e.to_string())?,
+    })
+}
+
+/// First-run steps and whether setup is complete (no session needed: the
+/// wizard runs before anyone can log in)
+#[tauri::command]
+pub async fn setup_status(app: AppHandle) -> Result<SetupStatus, String> {
+    let setup = first_run_setup(&app)?;
+    tauri::async_runtime::spawn_blocking(move || setup.status())
+        .await
+        .map_err(|e| format!("setup join error: {e}"))
+}
+
+/// Run one first-run step: anyone until setup is complete, admins after.
+/// A new virtualenv becomes `kernel.python`
+#[tauri::command]
+pub async fn setup_run_step(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: Option<String>,
+    window_nonce: String,
+    step: SetupStep,
+) -> Result<StepStatus, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let setup = first_run_setup(&app)?;
+    let probe = setup.clone();
+    let complete = tauri::async_runtime::spawn_blocking(move || probe.status().complete)
+        .await
+        .map_err(|e| format!("setup join error: {e}"))?;
+    if complete {
+        let store = auth_state.read();
+        admin_session(&store, session_token.as_deref().unwrap_or_default())?;
+    }
+    let venv_python = setup.venv_python();
+    let status = tauri::async_runtime::spawn_blocking(move || setup.run(step))
+        .await
+        .map_err(|e| format!("setup join error: {e}"))?
+        .map_err(|e| e.to_string())?;
+    tracing::info!(?step, state = ?status.state, "setup step ran");
+    if step == SetupStep::Venv {
+        let config_state = app.state::<ConfigState>();
+        match config_state.set("kernel.python", json!(venv_python)) {
+            Ok(config) => {
+                apply_config(&config);
+                let _ = app.emit(SETTINGS_CHANGED_EVENT, current_settings(&app));
+            }
+            Err(e) => warn!(error = %e, "virtualenv not made kernel.python"),
+        }
+    }
+    Ok(status)
+}
+
+/// `--headless-check`: print the readiness report; the exit status
+pub fn headless_check(identifier: &str, cli: &CliArgs) -> i32 {
+    let Some((data_dir, config_dir)) = profile::headless_dirs(identifier) else {
+        eprintln!("no home directory");
+        return 1;
+    };
+    let report = HeadlessReport::check(cli, &data_dir, &config_dir);
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => println!("{json}"),
+        Err(e) => {
+            eprintln!("{e}");
+            return 1;
+        }
+    }
+    if report.ok {
+        0
+    } else {
+        1
+    }
+}
+
+/// Push `config.toml` settings into the subsystems that read them
+pub fn apply_config(config: &AppConfig) {
+    kernel::set_python(config.kernel.python.clone());
+    kernel::set_transport(config.kernel.transport);
+    kernel_backend::set_backend(config.kernel.backend);
+    auth::set_idle_timeout(Duration::from_secs(config.session.idle_timeout_secs));
+    if let Err(e) = logging::set_default_level(config.logging.level.as_deref()) {
+        tracing::warn!(error = %e, "config.toml log level not applied");
+    }
+}
+
+pub fn current_settings(app: &AppHandle) -> Settings {
+    let (identifier, name) = autostart_names(app);
+    let config_state = app.state::<ConfigState>();
+    Settings {
+        config: config_state.config(),
+        autostart: autostart::status(&identifier, &name).ok(),
+        overridden: config_state.overridden(),
+    }
+}
+
+/// Settings for the settings screen (not in guest sessions)
+#[tauri::command]
+pub fn settings_get(
+    app: AppHandle,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+) -> Result<Settings, String> {
+    {
+        let store = auth_state.read();
+        user_session(&store, &session_token)?;
+    }
+    Ok(current_settings(&app))
+}
+
+/// Change one setting (`config::SETTING_KEYS` or `autostart`), apply it
+/// and tell every window
+///
+/// `kernel.*` and `session.*` apply to every user (and `kernel.python` is
+/// spawned on the next start): those need an admin session and a recent
+/// password (see `auth_step_up`).
+#[tauri::command]
+pub fn settings_set(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    key: String,
+    value: Value,
+) -> Result<Settings, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let username = {
+        let store = auth_state.read();
+        if config::is_admin_setting(&key) {
+            let username = admin_session(&store, &session_token)?;
+            require_step_up(&store, &app.state::<StepUpState>(), &session_token)?;
+            username
+        } else {
+            user_session(&store, &session_token)?
+        }
+    };
+    if key == config::AUTOSTART_KEY {
+        let status: AutostartStatus =
+            serde_json::from_value(value).map_err(|e| format!("{}: {e}", config::AUTOSTART_KEY))?;
+        let (identifier, name) = autostart_names(&app);
+        let changed = if status.enabled {
+            autostart::enable(&identifier, &name, status.minimized)
+        } else {
+            autostart::disable(&identifier, &name)
+        };
+        changed.map_err(|e| e.to_string())?;
+    } else {
+        let config = app
+            .state::<ConfigState>()
+            .set(&key, value)
+            .map_err(|e| e.to_string())?;
+        apply_config(&config);
+    }
+    tracing::info!(%username, %key, "setting changed");
+    let settings = current_settings(&app);
+    if let Err(e) = app.emit(SETTINGS_CHANGED_EVENT, &settings) {
+        tracing::warn!(error = %e, "failed to emit settings change");
+    }
+    Ok(settings)
+}
+
+/// The system's light/dark scheme, accent color and text scale
+#[tauri::command]
+pub fn appearance_get(state: State<'_, AppearanceState>) -> Appearance {
+    state.current()
+}
+
+/// Last reported battery state (`None` before the first report, or where
+/// it isn't watched)
+#[tauri::command]
+pub fn power_status(state: State<'_, PowerState>) -> Option<Battery> {
+    *state.0.lock_or_recover()
+}
+
+/// Current log levels and log file (not in guest sessions)
+#[tauri::command]
+pub fn log_get_config(
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+) -> Result<LogConfig, String> {
+    user_session(&auth_state.read(), &session_token)?;
+    logging::config().map_err(|e| e.to_string())
+}
+
+/// Change the log level of `target` until the app exits (`level` `None`
+/// resets it); audited, since debug levels log more detail
+#[tauri::command]
+pub fn log_set_level(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    audit: State<'_, AuditState>,
+    session_token: String,
+    window_nonce: String,
+    target: String,
+    level: Option<String>,
+) -> Result<Levels, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let username = user_session(&auth_state.read(), &session_token)?;
+    let levels = logging::set_level(&target, level.as_deref()).map_err(|e| e.to_string())?;
+    audit.record(
+        AuditEntry::new(AuditEvent::LogLevelChanged)
+            .username(&username)
+            .window(window.label())
+            .detail(format!("{target}={}", level.as_deref().unwrap_or("reset"))),
+    );
+    Ok(levels)
+}
+
+/// Write a webview console entry or breadcrumb to the shell's log files
+/// (no session needed, so the login page can log too; rate limited per
+/// window, and entries over the limit are counted rather than logged)
+#[tauri::command]
+pub fn frontend_log(
+    window: Window,
+    limiter: State<'_, FrontendLogLimitState>,
+    level: String,
+    message: String,
+    context: Option<Value>,
+) -> Result<(), String> {
+    let Some(dropped) = limiter.0.lock_or_recover().check(window.label()) else {
+        return Ok(());
+    };
+    if dropped > 0 {
+        warn!(
+            target: logging::FRONTEND_TARGET,
+            window = window.label(),
+            dropped,
+            "frontend log entries dropped (rate limit)"
+        );
+    }
+    logging::frontend(window.label(), &level, &message, context).map_err(|e| e.to_string())
+}
+
+/// Request counts and latencies, kernel restarts, queue depth and auth
+/// failures since the app started (not in guest sessions)
+#[tauri::command]
+pub fn metrics_snapshot(
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+) -> Result<Snapshot, String> {
+    user_session(&auth_state.read(), &session_token)?;
+    Ok(metrics::snapshot())
+}
+
+/// Kernel, session, queue, disk and config status in one report (not in
+/// guest sessions)
+#[tauri::command]
+pub async fn health_check(
+    app: AppHandle,
+    auth_state: State<'_, AuthState>,
+    kernel_state: State<'_, KernelState>,
+    config_state: State<'_, ConfigState>,
+    session_token: String,
+) -> Result<HealthReport, String> {
+    let sessions = {
+        let store = auth_state.read();
+        user_session(&store, &session_token)?;
+        store.counts()
+    };
+    let kernel = KernelReport::check(&kernel_state.0, crash_report::kernel_version());
+    let app_version = app.package_info().version.to_string();
+    let data_dir = app.path().profile_data_dir().ok();
+    let mut problems: Vec<ConfigProblem> = config_state.error().iter().map(Into::into).collect();
+    problems.extend(
+        app.state::<MigrationState>()
+            .0
+            .failed
+            .iter()
+            .map(Into::into),
+    );
+    let overrides = config_state.overrides.clone();
+    // Disk and config checks touch the filesystem
+    tauri::async_runtime::spawn_blocking(move || {
+        HealthReport::new(
+            app_version,
+            kernel,
+            sessions,
+            metrics::queue_depth(),
+            data_dir.as_deref(),
+            problems,
+            overrides,
+        )
+    })
+    .await
+    .map_err(|e| format!("health check join error: {e}"))
+}
+
+/// Recent kernel requests after `after` (a `seq`), for the devtools panel:
+/// the caller's own, or every request for admins (not in guest sessions)
+#[tauri::command]
+pub fn debug_request_history(
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    after: Option<u64>,
+) -> Result<RequestHistory, String> {
+    let store = auth_state.read();
+    let username = user_session(&store, &session_token)?;
+    let admin = admin_session(&store, &session_token).is_ok();
+    let visible_to = (!admin).then_some(username.as_str());
+    Ok(request_history::snapshot(visible_to, after.unwrap_or(0)))
+}
+
+/// Saved crash reports, newest first (not in guest sessions)
+#[tauri::command]
+pub fn crash_report_list(
+    auth_state: State<'_, AuthState>,
+    reports: State<'_, CrashReportState>,
+    session_token: String,
+) -> Result<Vec<CrashSummary>, String> {
+    user_session(&auth_state.read(), &session_token)?;
+    Ok(reports.0.list())
+}
+
+/// One crash report in full, as it would be sent (not in guest sessions)
+#[tauri::command]
+pub fn crash_report_get(
+    auth_state: State<'_, AuthState>,
+    reports: State<'_, CrashReportState>,
+    session_token: String,
+    id: String,
+) -> Result<CrashReport, String> {
+    user_session(&auth_state.read(), &session_token)?;
+    reports.0.get(&id).map_err(|e| e.to_string())
+}
+
+/// Send a crash report to the configured endpoint once the user agrees in
+/// a native dialog; both outcomes are audited
+#[tauri::command]
+pub async fn crash_report_submit(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    audit: State<'_, AuditState>,
+    session_token: String,
+    window_nonce: String,
+    id: String,
+) -> Result<CrashSummary, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let username = user_session(&auth_state.read(), &session_token)?;
+    let app = window.app_handle().clone();
+    let (endpoint, report) = {
+        let reports = app.state::<CrashReportState>();
+        let endpoint = reports.0.config().endpoint().map_err(|e| e.to_string())?;
+        (endpoint, reports.0.get(&id).map_err(|e| e.to_string())?)
+    };
+    let entry = |event| {
+        AuditEntry::new(event)
+            .username(&username)
+            .window(window.label())
+            .detail(format!(
+                "{id} to {}",
+                endpoint.host_str().unwrap_or_default()
+            ))
+    };
+
+    let (dialog_app, prompt_report, prompt_endpoint) =
+        (app.clone(), report.clone(), endpoint.clone());
+    let approved = tauri::async_runtime::spawn_blocking(move || {
+        crash_report::ask(&dialog_app, &prompt_report, &prompt_endpoint)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    if !approved {
+        audit.record(entry(AuditEvent::CrashReportDeclined));
+        return Err(CrashReportError::Declined.to_string());
+    }
+    audit.record(entry(AuditEvent::CrashReportApproved));
+
+    tauri::async_runtime::spawn_blocking(move || crash_report::submit(&endpoint, &report))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    app.state::<CrashReportState>()
+        .0
+        .mark_submitted(&id)
+        .map_err(|e| e.to_string())
+}
+
+/// Drop transient state a panic may have left inconsistent (admin only)
+///
+/// Stops the kernel (restarted on the next request) and clears request
+/// rate-limit buckets. Sessions, enrollments and login lockouts are kept.
+#[tauri::command]
+pub fn state_reset(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let admin = {
+        let store = auth_state.read();
+        admin_session(&store, &session_token)?
+    };
+    let kernel = kernel::lock_shared(&app.state::<KernelState>().0).take();
+    drop(kernel);
+    app.state::<RequestRateLimitState>()
+        .0
+        .lock_or_recover()
+        .reset();
+    app.state::<AuditState>().record(
+        AuditEntry::new(AuditEvent::StateReset)
+            .session_id(&auth::session_id(&session_token))
+            .detail(format!("by {admin}")),
+    );
+    refresh_tray(&app);
+    Ok(())
+}
+
+/// Log panics (redacted) and audit them; with poison recovery in place a
+/// panicked command or background thread no longer takes state down with it
+pub fn install_panic_hook(app: AppHandle) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}", l.file(), l.line()))
+            .unwrap_or_default();
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        let thread = std::thread::current();
+        let detail = format!(
+            "thread '{}' panicked at {location}: {message}",
+            thread.name().unwrap_or("<unnamed>")
+        );
+        error!("{}", redact::text(&detail));
+        telemetry::record_crash();
+        if let Some(reports) = app.try_state::<CrashReportState>() {
+            let report = CrashReport::capture(
+                &app.package_info().version.to_string(),
+                thread.name().unwrap_or("<unnamed>"),
+                &location,
+                &message,
+            );
+            if let Err(e) = reports.0.save(&report) {
+                error!(error = %e, "failed to save crash report");
+            }
+        }
+        if let Some(audit) = app.try_state::<AuditState>() {
+            audit.try_record(AuditEntry::new(AuditEvent::Panic).detail(detail.clone()));
+        }
+        notifications::notify(
+            &app,
+            Notification::new(
+                Category::Crash,
+                "Talking Rock hit an internal error",
+                detail,
+            ),
+        );
+        if cfg!(debug_assertions) {
+            default_hook(info);
+        }
+    }));
+}
@@ -0,0 +1,1199 @@
+//! Authentication Commands
+//!
+//! Login (kernel, OS session, OIDC, guest), second factors, locking and
+//! unlocking, step-up and session lifecycle.
+
+use super::kernel::prune_kernel_output_subscribers;
+use crate::shell::kernel::{call_kernel, prepare_kernel, KernelState};
+use crate::shell::sessions::{
+    admin_session, client_info, pad_auth_failure, persist_sessions, quick_unlock_available,
+    register_session, require_step_up, revoke_sessions, user_session, verify_password,
+};
+use crate::shell::window::check_window_nonce;
+use reos_tauri_lib::audit::{AuditEntry, AuditEvent, AuditState};
+use reos_tauri_lib::auth::{AuthError, AuthResult, AuthState, SessionInfo};
+use reos_tauri_lib::auth_backend::{AuthBackendState, LoginRequest};
+use reos_tauri_lib::biometric::{BiometricError, BiometricState};
+use reos_tauri_lib::guest::GuestState;
+use reos_tauri_lib::kernel::rpc_result;
+use reos_tauri_lib::kernel_lifecycle::StartTrigger;
+use reos_tauri_lib::locks::Recover;
+use reos_tauri_lib::oidc::OidcState;
+use reos_tauri_lib::os_session::OsLoginState;
+use reos_tauri_lib::password_policy::PasswordPolicyState;
+use reos_tauri_lib::pin::{PinError, PinState};
+use reos_tauri_lib::rate_limit::LoginRateLimitState;
+use reos_tauri_lib::secret::SecretString;
+use reos_tauri_lib::session_events::SessionEvent;
+use reos_tauri_lib::step_up::StepUpState;
+use reos_tauri_lib::totp::{TotpEnrollment, TotpError, TotpState};
+use reos_tauri_lib::username::UsernameState;
+use reos_tauri_lib::{auth, biometric, guest, kernel, os_session, session_events};
+use serde_json::json;
+use std::time::Instant;
+use tauri::{AppHandle, Manager, State, Window};
+use tracing::warn;
+
+/// Authenticate a user via Python kernel (PAM happens there)
+///
+/// Flow:
+/// 1. Frontend calls this with username/password
+/// 2. We hand the credentials to the configured `AuthBackend`
+///    (default: the Python kernel's auth/login endpoint)
+/// 3. The backend authenticates (PAM, LDAP, password file) and the kernel
+///    derives the encryption key
+/// 4. The kernel returns success + session token
+/// 5. We store the session token for future request validation
+///    (or, if the user enrolled TOTP, park it until `auth_verify_totp`)
+///
+/// Attempts are rate limited per window and globally before anything
+/// reaches the kernel.
+#[tauri::command]
+pub async fn auth_login(
+    app: AppHandle,
+    window: Window,
+    state: State<'_, KernelState>,
+    backend: State<'_, AuthBackendState>,
+    username: String,
+    password: Option<SecretString>,
+) -> Result<AuthResult, String> {
+    if let Some(rejected) = precheck_login(&app, &window, &username)? {
+        return Ok(rejected);
+    }
+    prepare_kernel(&app, StartTrigger::Login).await?;
+
+    // The configured backend authenticates and opens the kernel session
+    // (the default PAM backend lets Polkit show its own system dialog)
+    let kernel = state.0.clone();
+    let backend = backend.0.clone();
+    let request = LoginRequest {
+        username: username.clone(),
+        password,
+    };
+    let result = tauri::async_runtime::spawn_blocking(move || backend.login(&kernel, &request))
+        .await
+        .map_err(|e| format!("login join error: {e}"))??;
+
+    finish_login(&app, &window, &username, result).await
+}
+
+/// Log in without a PAM prompt, vouched for by the desktop login session
+///
+/// Only when enabled in `os-login.json`. Rust confirms via logind that the
+/// app runs in `username`'s active, local, unlocked session; the kernel
+/// then only needs the passphrase to derive the encryption key. TOTP, if
+/// enrolled, is still required.
+#[tauri::command]
+pub async fn auth_login_os_session(
+    app: AppHandle,
+    window: Window,
+    os_login: State<'_, OsLoginState>,
+    username: String,
+    passphrase: SecretString,
+) -> Result<AuthResult, String> {
+    if !os_login.0.enabled {
+        return Ok(AuthResult::failure(
+            os_session::OsSessionError::Disabled.to_string(),
+        ));
+    }
+    if let Some(rejected) = precheck_login(&app, &window, &username)? {
+        return Ok(rejected);
+    }
+    prepare_kernel(&app, StartTrigger::Login).await?;
+
+    let check_user = username.clone();
+    let vouched = tauri::async_runtime::spawn_blocking(move || os_session::verify(&check_user))
+        .await
+        .map_err(|e| format!("session check join error: {e}"))?;
+    let os_session_id = match vouched {
+        Ok(id) => id,
+        Err(e) => {
+            app.state::<AuditState>().record(
+                AuditEntry::new(AuditEvent::LoginFailure)
+                    .username(&username)
+                    .window(window.label())
+                    .detail(format!("os_session: {e}")),
+            );
+            return Ok(AuthResult::failure(e.to_string()));
+        }
+    };
+
+    let result = call_kernel(
+        &app.state::<KernelState>(),
+        "auth/login_os_session",
+        json!({
+            "username": username,
+            "os_session_id": os_session_id,
+            "passphrase": passphrase.expose(),
+        }),
+    )
+    .await?;
+
+    finish_login(&app, &window, &username, rpc_result(result)?).await
+}
+
+/// Log in through the organization's identity provider (OIDC)
+///
+/// Only when configured in `oidc.json`. Opens the system browser, waits
+/// for the loopback redirect, and maps the verified identity to a local
+/// user; the kernel then opens that user's session with `passphrase`
+/// deriving the encryption key. TOTP, if enrolled, is still required.
+#[tauri::command]
+pub async fn auth_login_oidc(
+    app: AppHandle,
+    window: Window,
+    passphrase: SecretString,
+) -> Result<AuthResult, String> {
+    // Before the browser opens: a rate-limited window gets no new IdP flow
+    if let Some(rejected) = check_login_rate(&app, &window, None) {
+        return Ok(rejected);
+    }
+    let flow = app.clone();
+    let identity = tauri::async_runtime::spawn_blocking(move || {
+        flow.state::<OidcState>()
+            .login(|url| open::that_detached(url).map_err(|e| e.to_string()))
+    })
+    .await
+    .map_err(|e| format!("oidc login join error: {e}"))?;
+
+    let identity = match identity {
+        Ok(identity) => identity,
+        Err(e) => {
+            app.state::<AuditState>().record(
+                AuditEntry::new(AuditEvent::LoginFailure)
+                    .window(window.label())
+                    .detail(format!("oidc: {e}")),
+            );
+            return Ok(AuthResult::failure(e.to_string()));
+        }
+    };
+    let username = identity.username;
+    if let Some(rejected) = check_login_username(&app, &window, &username) {
+        return Ok(rejected);
+    }
+    prepare_kernel(&app, StartTrigger::Login).await?;
+
+    let result = call_kernel(
+        &app.state::<KernelState>(),
+        "auth/login_verified",
+        json!({
+            "username": username,
+            "password": passphrase.expose(),
+            "verified_by": "oidc",
+            "subject": identity.subject,
+            "__shell": {},
+        }),
+    )
+    .await?;
+
+    finish_login(&app, &window, &username, rpc_result(result)?).await
+}
+
+/// Rate limit and username checks shared by the login commands
+///
+/// Returns the failure to hand back to the frontend, if any.
+pub fn precheck_login(
+    app: &AppHandle,
+    window: &Window,
+    username: &str,
+) -> Result<Option<AuthResult>, String> {
+    // Rate limit before touching the kernel (protects PAM from a runaway frontend)
+    if let Some(rejected) = check_login_rate(app, window, Some(username)) {
+        return Ok(Some(rejected));
+    }
+    Ok(check_login_username(app, window, username))
+}
+
+/// Count a login attempt against the window and global limits, auditing
+/// a rejection (guest login uses this alone: its fixed username isn't
+/// subject to the username policy; OIDC login before the IdP names the user)
+fn check_login_rate(
+    app: &AppHandle,
+    window: &Window,
+    username: Option<&str>,
+) -> Option<AuthResult> {
+    let limiter = app.state::<LoginRateLimitState>();
+    let retry_after = limiter.0.lock_or_recover().check(window.label()).err()?;
+    let mut entry = AuditEntry::new(AuditEvent::Lockout)
+        .window(window.label())
+        .detail("login rate limit");
+    if let Some(username) = username {
+        entry = entry.username(username);
+    }
+    app.state::<AuditState>().record(entry);
+    Some(AuthResult::failure(format!(
+        "Too many login attempts, retry in {}s",
+        retry_after.as_secs().max(1)
+    )))
+}
+
+/// Validate username format (prevent injection) and allow/deny lists
+fn check_login_username(app: &AppHandle, window: &Window, username: &str) -> Option<AuthResult> {
+    let e = app.state::<UsernameState>().validate(username).err()?;
+    app.state::<AuditState>().record(
+        AuditEntry::new(AuditEvent::LoginFailure)
+            .window(window.label())
+            .detail(e.code()),
+    );
+    Some(AuthResult::failure_code(e.code(), e.to_string()))
+}
+
+/// Audit a kernel login result and, on success, store the session
+/// (or park it until `auth_verify_totp` if the user enrolled TOTP)
+pub async fn finish_login(
+    app: &AppHandle,
+    window: &Window,
+    username: &str,
+    auth_result: AuthResult,
+) -> Result<AuthResult, String> {
+    let audit = app.state::<AuditState>();
+
+    if !auth_result.success {
+        let mut entry = AuditEntry::new(AuditEvent::LoginFailure).username(username);
+        if let Some(error) = &auth_result.error {
+            entry = entry.detail(error.clone());
+        }
+        audit.record(entry);
+    }
+
+    // If successful, store the session in Rust
+    if auth_result.success {
+        if let (Some(token), Some(uname)) = (&auth_result.session_token, &auth_result.username) {
+            // Second factor: don't release the token until a TOTP code is verified
+            let challenge = {
+                let totp_state = app.state::<TotpState>();
+                let mut totp = totp_state.0.lock_or_recover();
+                totp.is_enrolled(uname)
+                    .then(|| totp.begin_challenge(SecretString::new(token.clone()), uname.clone()))
+            };
+            if let Some((challenge_id, expired)) = challenge {
+                logout_parked(app, expired);
+                audit.record(AuditEntry::new(AuditEvent::SecondFactorRequired).username(uname));
+                return Ok(AuthResult::second_factor_required(
+                    uname.clone(),
+                    challenge_id,
+                ));
+            }
+
+            register_session(app, token.clone(), uname.clone(), client_info(app, window)).await?;
+            audit.record(
+                AuditEntry::new(AuditEvent::LoginSuccess)
+                    .username(uname)
+                    .session_id(&auth::session_id(token)),
+            );
+        }
+    }
+
+    Ok(auth_result)
+}
+
+/// Start a guest session (no PAM login, no encryption key)
+///
+/// Only available when enabled in `guest.json`; the session may call only
+/// the policy's read-only kernel methods.
+#[tauri::command]
+pub fn auth_guest_login(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    guest_state: State<'_, GuestState>,
+    audit: State<'_, AuditState>,
+) -> Result<AuthResult, String> {
+    if !guest_state.0.enabled {
+        return Ok(AuthResult::failure("Guest sessions are disabled"));
+    }
+    if let Some(rejected) = check_login_rate(&app, &window, Some(guest::GUEST_USERNAME)) {
+        return Ok(rejected);
+    }
+
+    let token = auth::generate_session_token();
+    let session = auth::create_guest_session(
+        token.clone(),
+        guest::GUEST_USERNAME.to_string(),
+        client_info(&app, &window),
+    );
+    auth_state.store().insert(session);
+    session_events::emit(
+        &app,
+        SessionEvent::Created,
+        &auth::session_id(&token),
+        Some(guest::GUEST_USERNAME),
+        Some("guest"),
+    );
+
+    audit.record(
+        AuditEntry::new(AuditEvent::LoginSuccess)
+            .username(guest::GUEST_USERNAME)
+            .session_id(&auth::session_id(&token))
+            .window(window.label())
+            .detail("guest"),
+    );
+    Ok(AuthResult::authenticated(
+        token,
+        guest::GUEST_USERNAME.to_string(),
+    ))
+}
+
+/// Complete a login that is waiting on a TOTP code
+///
+/// After too many wrong codes the parked kernel session is logged out, so
+/// the user has to start over with a full PAM login.
+#[tauri::command]
+pub async fn auth_verify_totp(
+    app: AppHandle,
+    window: Window,
+    totp_state: State<'_, TotpState>,
+    audit: State<'_, AuditState>,
+    challenge_id: String,
+    code: String,
+) -> Result<AuthResult, String> {
+    let outcome = {
+        let mut totp = totp_state.0.lock_or_recover();
+        totp.complete_challenge(&challenge_id, &code)
+    };
+
+    match outcome {
+        Ok((token, uname)) => {
+            let token = token.expose().to_string();
+            register_session(
+                &app,
+                token.clone(),
+                uname.clone(),
+                client_info(&app, &window),
+            )
+            .await?;
+            audit.record(
+                AuditEntry::new(AuditEvent::LoginSuccess)
+                    .username(&uname)
+                    .session_id(&auth::session_id(&token))
+                    .detail("totp"),
+            );
+            Ok(AuthResult::authenticated(token, uname))
+        }
+        Err(TotpError::TooManyAttempts { session_token }) => {
+            audit.record(
+                AuditEntry::new(AuditEvent::Lockout)
+                    .session_id(&auth::session_id(session_token.expose()))
+                    .detail("too many TOTP codes"),
+            );
+            logout_parked(&app, vec![session_token]);
+            Ok(AuthResult::failure(
+                "Too many invalid codes, please log in again",
+            ))
+        }
+        Err(e) => {
+            audit.record(AuditEntry::new(AuditEvent::SecondFactorFailure).detail(e.to_string()));
+            Ok(AuthResult::failure(e.to_string()))
+        }
+    }
+}
+
+/// Best effort: drop parked kernel sessions (and their derived keys) that
+/// won't complete their second factor
+fn logout_parked(app: &AppHandle, tokens: Vec<SecretString>) {
+    if tokens.is_empty() {
+        return;
+    }
+    let kernel = app.state::<KernelState>().0.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Some(proc) = kernel::lock_shared(&kernel).as_mut() {
+            for token in tokens {
+                let _ = proc.request("auth/logout", json!({ "session_token": token.expose() }));
+            }
+        }
+    });
+}
+
+/// Start TOTP enrollment for the session's user
+///
+/// Returns the secret and an `otpauth://` URI for the authenticator app.
+/// Nothing changes at login until `auth_totp_confirm` succeeds.
+///
+/// Re-enrolling replaces the user's current secret, so an already enrolled
+/// user needs either a recent password (as for `auth_totp_disable`) or
+/// `current_code` from the authenticator being replaced.
+#[tauri::command]
+pub fn auth_totp_enroll(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    totp_state: State<'_, TotpState>,
+    step_up: State<'_, StepUpState>,
+    session_token: String,
+    window_nonce: String,
+    current_code: Option<String>,
+) -> Result<TotpEnrollment, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let (username, stepped_up) = {
+        let store = auth_state.read();
+        let username = user_session(&store, &session_token)?;
+        (
+            username,
+            require_step_up(&store, &step_up, &session_token).is_ok(),
+        )
+    };
+    let mut totp = totp_state.0.lock_or_recover();
+    if totp.is_enrolled(&username) && !stepped_up {
+        let code = current_code.ok_or_else(|| AuthError::ReauthRequired.to_string())?;
+        totp.verify_current(&username, &code)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(totp.begin_enrollment(&username))
+}
+
+/// Confirm TOTP enrollment with a code from the authenticator app
+#[tauri::command]
+pub fn auth_totp_confirm(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    totp_state: State<'_, TotpState>,
+    session_token: String,
+    window_nonce: String,
+    code: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let username = {
+        let store = auth_state.read();
+        user_session(&store, &session_token)?
+    };
+    let mut totp = totp_state.0.lock_or_recover();
+    totp.confirm_enrollment(&username, &code)
+        .map_err(|e| e.to_string())
+}
+
+/// Turn off TOTP for the session's user; whether it was enrolled
+///
+/// Needs a recent password like the `step-up.json` methods (see
+/// `auth_step_up`), whatever that file lists.
+#[tauri::command]
+pub fn auth_totp_disable(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    totp_state: State<'_, TotpState>,
+    step_up: State<'_, StepUpState>,
+    audit: State<'_, AuditState>,
+    session_token: String,
+    window_nonce: String,
+) -> Result<bool, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let username = {
+        let store = auth_state.read();
+        let username = user_session(&store, &session_token)?;
+        require_step_up(&store, &step_up, &session_token)?;
+        username
+    };
+
+    let disabled = totp_state
+        .0
+        .lock_or_recover()
+        .disable(&username)
+        .map_err(|e| e.to_string())?;
+    if disabled {
+        audit.record(
+            AuditEntry::new(AuditEvent::SecondFactorDisabled)
+                .username(&username)
+                .session_id(&auth::session_id(&session_token)),
+        );
+    }
+    Ok(disabled)
+}
+
+/// Change the user's password via the kernel's PAM chpass endpoint
+///
+/// The new password must satisfy `password-policy.json` first; violations
+/// come back in `AuthResult::violations` without contacting the kernel.
+/// The kernel re-derives the encryption key from the new password and
+/// issues a fresh session token. On success every existing session for the
+/// user is dropped and only the new one is kept, so other windows/devices
+/// must log in again.
+#[tauri::command]
+pub async fn auth_change_password(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    current_password: SecretString,
+    new_password: SecretString,
+) -> Result<AuthResult, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let audit = app.state::<AuditState>();
+    let username = {
+        let store = auth_state.read();
+        user_session(&store, &session_token)?
+    };
+
+    if new_password.expose() == current_password.expose() {
+        return Ok(AuthResult::failure(
+            "New password must differ from the current password",
+        ));
+    }
+    let violations = app
+        .state::<PasswordPolicyState>()
+        .check(&username, new_password.expose());
+    if !violations.is_empty() {
+        return Ok(AuthResult::policy_violation(violations));
+    }
+
+    let response = call_kernel(
+        &app.state::<KernelState>(),
+        "auth/change_password",
+        json!({
+            "username": username,
+            "session_token": session_token,
+            "current_password": current_password.expose(),
+            "new_password": new_password.expose(),
+        }),
+    )
+    .await?;
+    let auth_result: AuthResult = rpc_result(response)?;
+
+    if auth_result.success {
+        // Rotate: all of this user's sessions die with the old key
+        let revoked = auth_state.store().remove_user(&username);
+        revoke_sessions(&app, revoked, "password_changed");
+        let mut entry = AuditEntry::new(AuditEvent::PasswordChanged).username(&username);
+        if let Some(token) = &auth_result.session_token {
+            entry = entry.session_id(&auth::session_id(token));
+            register_session(&app, token.clone(), username, client_info(&app, &window)).await?;
+        }
+        audit.record(entry);
+    }
+
+    Ok(auth_result)
+}
+
+/// Set or replace the quick-unlock PIN for the session's user
+#[tauri::command]
+pub async fn auth_pin_set(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    pin: SecretString,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let username = {
+        let store = auth_state.read();
+        user_session(&store, &session_token)?
+    };
+
+    let pin_app = app.clone();
+    let pin_user = username.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        pin_app
+            .state::<PinState>()
+            .0
+            .lock_or_recover()
+            .set_pin(&pin_user, pin.expose())
+    })
+    .await
+    .map_err(|e| format!("PIN join error: {e}"))?
+    .map_err(|e| e.to_string())?;
+
+    auth_state.store().set_quick_unlock(&username, true);
+    Ok(())
+}
+
+/// Remove the quick-unlock PIN for the session's user
+#[tauri::command]
+pub fn auth_pin_clear(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    pin_state: State<'_, PinState>,
+    session_token: String,
+    window_nonce: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let mut store = auth_state.store();
+    let username = user_session(&store, &session_token)?;
+
+    pin_state.0.lock_or_recover().clear_pin(&username);
+    store.set_quick_unlock(&username, false);
+    Ok(())
+}
+
+/// Resume a locked session with the user's PIN
+///
+/// Too many wrong PINs destroy the session; the user must log in again.
+#[tauri::command]
+pub async fn auth_pin_unlock(
+    app: AppHandle,
+    window: Window,
+    session_token: String,
+    window_nonce: String,
+    pin: SecretString,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    unlock_with_pin(&app, &session_token, pin).await
+}
+
+/// Check `pin` for a locked session and unlock (or, after too many
+/// failures, drop) it
+///
+/// The Argon2 check runs off the async runtime without the session store
+/// locked; the store is only taken to look the session up and to commit.
+pub async fn unlock_with_pin(
+    app: &AppHandle,
+    session_token: &str,
+    pin: SecretString,
+) -> Result<(), String> {
+    let auth_state = app.state::<AuthState>();
+    let audit = app.state::<AuditState>();
+    let username = auth_state
+        .store()
+        .get_locked_mut(session_token)
+        .ok_or_else(|| "Session not locked or expired".to_string())?
+        .username
+        .clone();
+    let session_id = auth::session_id(session_token);
+
+    let pin_app = app.clone();
+    let pin_user = username.clone();
+    let verified = tauri::async_runtime::spawn_blocking(move || {
+        pin_app
+            .state::<PinState>()
+            .0
+            .lock_or_recover()
+            .verify(&pin_user, pin.expose())
+    })
+    .await
+    .map_err(|e| format!("PIN join error: {e}"))?;
+
+    match verified {
+        Ok(()) => {
+            auth_state
+                .store()
+                .get_locked_mut(session_token)
+                .ok_or_else(|| "Session not locked or expired".to_string())?
+                .unlock();
+            audit.record(
+                AuditEntry::new(AuditEvent::Unlock)
+                    .username(&username)
+                    .session_id(&session_id)
+                    .detail("pin"),
+            );
+            session_events::emit(
+                app,
+                SessionEvent::Unlocked,
+                &session_id,
+                Some(&username),
+                Some("pin"),
+            );
+            Ok(())
+        }
+        Err(e @ (PinError::TooManyAttempts | PinError::NotSet)) => {
+            auth_state.store().remove(session_token);
+            audit.record(
+                AuditEntry::new(AuditEvent::Lockout)
+                    .username(&username)
+                    .session_id(&session_id)
+                    .detail(e.to_string()),
+            );
+            session_events::emit(
+                app,
+                SessionEvent::Revoked,
+                &session_id,
+                Some(&username),
+                Some("lockout"),
+            );
+            Err(e.to_string())
+        }
+        Err(e) => {
+            audit.record(
+                AuditEntry::new(AuditEvent::UnlockFailure)
+                    .username(&username)
+                    .session_id(&session_id)
+                    .detail(e.to_string()),
+            );
+            Err(e.to_string())
+        }
+    }
+}
+
+/// Lock a session (lock screen): kernel requests are refused with `Locked`
+/// but the kernel keeps its key material, so `auth_unlock` can resume it
+#[tauri::command]
+pub fn auth_lock(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    audit: State<'_, AuditState>,
+    session_token: String,
+    window_nonce: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let mut store = auth_state.store();
+    let session = store
+        .get_mut(&session_token)
+        .ok_or_else(|| "Invalid or expired session".to_string())?;
+    session.lock();
+    let session_id = auth::session_id(&session_token);
+    audit.record(
+        AuditEntry::new(AuditEvent::Lock)
+            .username(&session.username)
+            .session_id(&session_id)
+            .detail("manual"),
+    );
+    session_events::emit(
+        &app,
+        SessionEvent::Locked,
+        &session_id,
+        Some(&session.username),
+        Some("manual"),
+    );
+    Ok(())
+}
+
+/// Resume a locked session with the user's PIN or password
+///
+/// The password is checked by the kernel (PAM) against the session's user;
+/// the kernel session and its key are left as they were.
+#[tauri::command]
+pub async fn auth_unlock(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    pin: Option<SecretString>,
+    password: Option<SecretString>,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    if let Some(pin) = pin {
+        return unlock_with_pin(&app, &session_token, pin).await;
+    }
+    let password = password.ok_or_else(|| "PIN or password required".to_string())?;
+
+    let username = {
+        let mut store = auth_state.store();
+        store
+            .get_locked_mut(&session_token)
+            .ok_or_else(|| "Session not locked or expired".to_string())?
+            .username
+            .clone()
+    };
+
+    let verified = verify_password(&app, &username, &session_token, &password, "unlock").await?;
+
+    let audit = app.state::<AuditState>();
+    let entry = |event| {
+        AuditEntry::new(event)
+            .username(&username)
+            .session_id(&auth::session_id(&session_token))
+    };
+    if !verified.success {
+        audit.record(entry(AuditEvent::UnlockFailure).detail("password"));
+        return Err(verified
+            .error
+            .unwrap_or_else(|| "Incorrect password".to_string()));
+    }
+
+    let mut store = auth_state.store();
+    let session = store
+        .get_locked_mut(&session_token)
+        .ok_or_else(|| "Session not locked or expired".to_string())?;
+    session.unlock();
+    session.authenticated_at = std::time::Instant::now();
+    audit.record(entry(AuditEvent::Unlock).detail("password"));
+    session_events::emit(
+        &app,
+        SessionEvent::Unlocked,
+        &auth::session_id(&session_token),
+        Some(&username),
+        Some("password"),
+    );
+    Ok(())
+}
+
+/// Re-enter the password to satisfy step-up for sensitive kernel methods
+///
+/// On success the session counts as freshly authenticated for
+/// `step-up.json`'s `max_age_secs`.
+#[tauri::command]
+pub async fn auth_step_up(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    audit: State<'_, AuditState>,
+    session_token: String,
+    window_nonce: String,
+    password: SecretString,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let username = {
+        let store = auth_state.read();
+        user_session(&store, &session_token)?
+    };
+
+    let verified = verify_password(&app, &username, &session_token, &password, "step_up").await?;
+    let entry = |event| {
+        AuditEntry::new(event)
+            .username(&username)
+            .session_id(&auth::session_id(&session_token))
+    };
+    if !verified.success {
+        audit.record(entry(AuditEvent::StepUpFailure));
+        return Err(verified
+            .error
+            .unwrap_or_else(|| "Incorrect password".to_string()));
+    }
+
+    let mut store = auth_state.store();
+    let session = store
+        .get_mut(&session_token)
+        .ok_or_else(|| AuthError::InvalidSession.to_string())?;
+    session.authenticated_at = std::time::Instant::now();
+    session.refresh();
+    audit.record(entry(AuditEvent::StepUp));
+    session_events::emit(
+        &app,
+        SessionEvent::Refreshed,
+        &auth::session_id(&session_token),
+        Some(&username),
+        Some("step_up"),
+    );
+    Ok(())
+}
+
+/// Biometric unlock availability for the session's user
+#[derive(serde::Serialize)]
+pub struct BiometricStatus {
+    /// Allowed by deployment policy
+    policy_enabled: bool,
+    /// A biometric is enrolled with the platform (fprintd, ...)
+    enrolled: bool,
+    /// User opted in to biometric unlock
+    active: bool,
+}
+
+#[tauri::command]
+pub async fn auth_biometric_status(
+    auth_state: State<'_, AuthState>,
+    biometric_state: State<'_, BiometricState>,
+    session_token: String,
+) -> Result<BiometricStatus, String> {
+    let username = {
+        let store = auth_state.read();
+        user_session(&store, &session_token)?
+    };
+    let (policy_enabled, active) = {
+        let biometric = biometric_state.0.lock_or_recover();
+        (biometric.policy().enabled, biometric.is_enabled(&username))
+    };
+    let enrolled = tauri::async_runtime::spawn_blocking(move || biometric::is_enrolled(&username))
+        .await
+        .map_err(|e| format!("biometric join error: {e}"))?
+        .unwrap_or(false);
+
+    Ok(BiometricStatus {
+        policy_enabled,
+        enrolled,
+        active,
+    })
+}
+
+/// Opt in to biometric unlock (requires an active, password-authenticated session)
+#[tauri::command]
+pub fn auth_biometric_enable(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    biometric_state: State<'_, BiometricState>,
+    session_token: String,
+    window_nonce: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let mut store = auth_state.store();
+    let username = user_session(&store, &session_token)?;
+
+    let mut biometric = biometric_state.0.lock_or_recover();
+    biometric.enable(&username).map_err(|e| e.to_string())?;
+    store.set_quick_unlock(&username, true);
+    Ok(())
+}
+
+/// Opt out of biometric unlock
+#[tauri::command]
+pub fn auth_biometric_disable(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    biometric_state: State<'_, BiometricState>,
+    session_token: String,
+    window_nonce: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let username = {
+        let store = auth_state.read();
+        user_session(&store, &session_token)?
+    };
+
+    biometric_state
+        .0
+        .lock_or_recover()
+        .disable(&username)
+        .map_err(|e| e.to_string())?;
+
+    // A PIN may still keep quick unlock available
+    let quick_unlock = quick_unlock_available(&app, &username)?;
+    let mut store = auth_state.store();
+    store.set_quick_unlock(&username, quick_unlock);
+    Ok(())
+}
+
+/// Resume a locked session with a biometric
+///
+/// Only allowed while the original PAM login is within the policy window.
+#[tauri::command]
+pub async fn auth_biometric_unlock(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    biometric_state: State<'_, BiometricState>,
+    audit: State<'_, AuditState>,
+    session_token: String,
+    window_nonce: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let username = {
+        let mut store = auth_state.store();
+        let session = store
+            .get_locked_mut(&session_token)
+            .ok_or_else(|| "Session not locked or expired".to_string())?;
+        biometric_state
+            .0
+            .lock_or_recover()
+            .check_policy(&session.username, session.authenticated_at.elapsed())
+            .map_err(|e| e.to_string())?;
+        session.username.clone()
+    };
+
+    let verify_user = username.clone();
+    let verified = tauri::async_runtime::spawn_blocking(move || biometric::verify(&verify_user))
+        .await
+        .map_err(|e| format!("biometric join error: {e}"))?;
+
+    let entry = |event| {
+        AuditEntry::new(event)
+            .username(&username)
+            .session_id(&auth::session_id(&session_token))
+    };
+    let mut store = auth_state.store();
+    match verified {
+        Ok(()) => {
+            // The session may have passed its grace period while we waited
+            let session = store
+                .get_locked_mut(&session_token)
+                .ok_or_else(|| "Session not locked or expired".to_string())?;
+            session.unlock();
+            audit.record(entry(AuditEvent::Unlock).detail("biometric"));
+            session_events::emit(
+                &app,
+                SessionEvent::Unlocked,
+                &auth::session_id(&session_token),
+                Some(&username),
+                Some("biometric"),
+            );
+            Ok(())
+        }
+        Err(e @ BiometricError::ReauthRequired) => {
+            store.remove(&session_token);
+            audit.record(entry(AuditEvent::Lockout).detail(e.to_string()));
+            session_events::emit(
+                &app,
+                SessionEvent::Revoked,
+                &auth::session_id(&session_token),
+                Some(&username),
+                Some("reauth_required"),
+            );
+            Err(e.to_string())
+        }
+        Err(e) => {
+            audit.record(entry(AuditEvent::UnlockFailure).detail(e.to_string()));
+            Err(e.to_string())
+        }
+    }
+}
+
+/// Log out and destroy a session (zeroizes key material)
+#[tauri::command]
+pub fn auth_logout(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    audit: State<'_, AuditState>,
+    session_token: String,
+    window_nonce: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let mut store = auth_state.store();
+    let username = match store.get(&session_token) {
+        Some(session) => Some(session.username.clone()),
+        None => store
+            .get_locked_mut(&session_token)
+            .map(|session| session.username.clone()),
+    };
+    if store.remove(&session_token) {
+        let session_id = auth::session_id(&session_token);
+        let mut entry = AuditEntry::new(AuditEvent::Logout).session_id(&session_id);
+        if let Some(username) = &username {
+            entry = entry.username(username);
+        }
+        audit.record(entry);
+        drop(store);
+        session_events::emit(
+            &app,
+            SessionEvent::Revoked,
+            &session_id,
+            username.as_deref(),
+            Some("logout"),
+        );
+        persist_sessions(&app);
+        prune_kernel_output_subscribers(&app);
+        Ok(())
+    } else {
+        Err("Session not found".to_string())
+    }
+}
+
+/// Outcome of `auth_revoke_user`
+#[derive(serde::Serialize)]
+pub struct RevokeOutcome {
+    /// Shell sessions revoked
+    revoked: usize,
+    /// Kernel sessions dropped (with their key material), if the kernel answered
+    kernel_revoked: Option<usize>,
+    /// Why the kernel couldn't drop them; its sessions may still be live
+    kernel_error: Option<String>,
+}
+
+/// Revoke every session of `username` and drop its kernel key material
+///
+/// Admin only; for operators who suspect a compromised account on a shared
+/// machine. The shell's sessions are revoked even if the kernel fails,
+/// which the outcome reports rather than hiding it behind an error.
+#[tauri::command]
+pub async fn auth_revoke_user(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    audit: State<'_, AuditState>,
+    session_token: String,
+    window_nonce: String,
+    username: String,
+) -> Result<RevokeOutcome, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let (admin, revoked) = {
+        let mut store = auth_state.store();
+        let admin = admin_session(&store, &session_token)?;
+        (admin, store.remove_user(&username))
+    };
+    let count = revoked.len();
+    revoke_sessions(&app, revoked, "admin_revoked");
+    persist_sessions(&app);
+
+    #[derive(serde::Deserialize)]
+    struct Revoked {
+        revoked: usize,
+    }
+    let kernel = call_kernel(
+        &app.state::<KernelState>(),
+        "auth/revoke_user",
+        json!({ "username": username, "__shell": {} }),
+    )
+    .await
+    .and_then(rpc_result::<Revoked>);
+    audit.record(
+        AuditEntry::new(AuditEvent::SessionsRevoked)
+            .username(&username)
+            .session_id(&auth::session_id(&session_token))
+            .detail(match &kernel {
+                Ok(k) => format!("by {admin}: {count} sessions, kernel: {}", k.revoked),
+                Err(e) => format!("by {admin}: {count} sessions, kernel failed: {e}"),
+            }),
+    );
+    let (kernel_revoked, kernel_error) = match kernel {
+        Ok(k) => (Some(k.revoked), None),
+        Err(e) => {
+            warn!(username = %username, error = %e, "kernel failed to revoke sessions");
+            (None, Some(e))
+        }
+    };
+    Ok(RevokeOutcome {
+        revoked: count,
+        kernel_revoked,
+        kernel_error,
+    })
+}
+
+/// Validate a session token
+#[tauri::command]
+pub async fn auth_validate(
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+) -> Result<bool, String> {
+    let started = Instant::now();
+    let valid = {
+        let store = auth_state.read();
+        store.get(&session_token).is_some()
+    };
+    if !valid {
+        pad_auth_failure(started).await;
+    }
+    Ok(valid)
+}
+
+/// Refresh session activity timestamp (coalesced: a refresh within
+/// `auth::REFRESH_COALESCE` of the last one is a no-op)
+#[tauri::command]
+pub fn auth_refresh(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let store = auth_state.read();
+    match store.get(&session_token) {
+        Some(session) => {
+            if !session.refresh_coalesced() {
+                return Ok(());
+            }
+            session_events::emit(
+                &app,
+                SessionEvent::Refreshed,
+                &auth::session_id(&session_token),
+                Some(&session.username),
+                None,
+            );
+            Ok(())
+        }
+        None => Err("Session not found or expired".to_string()),
+    }
+}
+
+/// Get the current system username
+#[tauri::command]
+pub fn get_system_username() -> Result<String, String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .map_err(|_| "Could not determine username".to_string())
+}
+
+/// Get current session info (for UI display)
+#[tauri::command]
+pub async fn auth_get_session(
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+) -> Result<SessionInfo, String> {
+    let started = Instant::now();
+    let checked = {
+        let store = auth_state.read();
+        store.check(&session_token)
+    };
+    if checked.is_err() {
+        pad_auth_failure(started).await;
+    }
+    checked.map_err(|e| e.to_string())
+}
@@ -0,0 +1,70 @@
+//! Autostart Commands
+//!
+//! Starting the shell at login.
+
+use super::kernel::lifecycle_session;
+use crate::shell::sessions::user_session;
+use crate::shell::window::check_window_nonce;
+use reos_tauri_lib::auth::AuthState;
+use reos_tauri_lib::autostart;
+use reos_tauri_lib::autostart::AutostartStatus;
+use tauri::{AppHandle, State, Window};
+
+/// Identifier and display name for the login entry
+pub fn autostart_names(app: &AppHandle) -> (String, String) {
+    (
+        app.config().identifier.clone(),
+        app.package_info().name.clone(),
+    )
+}
+
+/// Launch ReOS when the user logs in; `minimized` starts it in the tray
+/// with the kernel running and sessions locked
+#[tauri::command]
+pub fn autostart_enable(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    minimized: bool,
+) -> Result<AutostartStatus, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    {
+        let store = auth_state.read();
+        user_session(&store, &session_token)?;
+    }
+    let (identifier, name) = autostart_names(&app);
+    autostart::enable(&identifier, &name, minimized).map_err(|e| e.to_string())?;
+    autostart::status(&identifier, &name).map_err(|e| e.to_string())
+}
+
+/// Stop launching ReOS at login
+#[tauri::command]
+pub fn autostart_disable(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    {
+        let store = auth_state.read();
+        user_session(&store, &session_token)?;
+    }
+    let (identifier, name) = autostart_names(&app);
+    autostart::disable(&identifier, &name).map_err(|e| e.to_string())
+}
+
+/// Whether ReOS launches at login, and whether minimized
+#[tauri::command]
+pub fn autostart_status(
+    app: AppHandle,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+) -> Result<AutostartStatus, String> {
+    lifecycle_session(&auth_state, &session_token)?;
+    let (identifier, name) = autostart_names(&app);
+    autostart::status(&identifier, &name).map_err(|e| e.to_string())
+}
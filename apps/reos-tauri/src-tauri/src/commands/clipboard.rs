@@ -0,0 +1,112 @@
+//! Clipboard Commands
+//!
+//! Clipboard access, including secrets copied with a clear timer.
+
+use super::kernel::lifecycle_session;
+use super::vault::{shell_call, vault_session};
+use crate::shell::window::check_window_nonce;
+use reos_tauri_lib::auth::AuthState;
+use reos_tauri_lib::clipboard::{ClipboardError, ClipboardState};
+use reos_tauri_lib::secret::SecretString;
+use reos_tauri_lib::vault;
+use serde_json::json;
+use tauri::{AppHandle, Manager, State, Window};
+
+/// Run a blocking clipboard operation off the async runtime
+pub async fn on_clipboard<T: Send + 'static>(
+    app: &AppHandle,
+    f: impl FnOnce(&AppHandle) -> Result<T, ClipboardError> + Send + 'static,
+) -> Result<T, String> {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || f(&app))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Copy sanitized plain text to the system clipboard
+#[tauri::command]
+pub async fn clipboard_write(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    text: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    lifecycle_session(&auth_state, &session_token)?;
+    let app = window.app_handle().clone();
+    let text = app
+        .state::<ClipboardState>()
+        .config
+        .prepare(&text)
+        .map_err(|e| e.to_string())?;
+    on_clipboard(&app, move |app| app.state::<ClipboardState>().write(&text)).await
+}
+
+/// Sanitized clipboard text (`None` if the clipboard holds no text)
+#[tauri::command]
+pub async fn clipboard_read(
+    app: AppHandle,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+) -> Result<Option<String>, String> {
+    lifecycle_session(&auth_state, &session_token)?;
+    let text = on_clipboard(&app, |app| app.state::<ClipboardState>().read()).await?;
+    let config = &app.state::<ClipboardState>().config;
+    text.map(|text| config.prepare(&text).map_err(|e| e.to_string()))
+        .transpose()
+}
+
+/// Copy a vault secret to the clipboard without passing it through the
+/// webview; it's cleared after `clear_secrets_after_secs` if still there
+#[tauri::command]
+pub async fn clipboard_copy_secret(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    name: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    vault_session(&auth_state, &session_token)?;
+    vault::validate_name(&name).map_err(|e| e.to_string())?;
+
+    #[derive(serde::Deserialize)]
+    struct Entry {
+        value: Option<SecretString>,
+    }
+    let entry: Entry = shell_call(
+        &app,
+        "vault/get",
+        json!({ "session_token": session_token, "name": name }),
+    )
+    .await?;
+    let value = entry.value.ok_or("No such secret")?;
+    let state = app.state::<ClipboardState>();
+    if value.expose().len() > state.config.max_bytes {
+        return Err(ClipboardError::TooLarge(state.config.max_bytes).to_string());
+    }
+    // Secrets are copied verbatim; sanitizing could change them
+    let ticket = state.copied_secret(value.expose());
+    let text = zeroize::Zeroizing::new(value.expose().to_string());
+    on_clipboard(&app, move |app| app.state::<ClipboardState>().write(&text)).await?;
+
+    if let Some(delay) = state.config.clear_secrets_after() {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let _ = on_clipboard(&app, move |app| {
+                let state = app.state::<ClipboardState>();
+                let current = state.read()?;
+                if state.should_clear(ticket, current.as_deref()) {
+                    state.clear()?;
+                }
+                Ok(())
+            })
+            .await;
+        });
+    }
+    Ok(())
+}
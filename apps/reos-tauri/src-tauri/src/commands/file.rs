@@ -0,0 +1,326 @@
+//! File Commands
+//!
+//! Dropped files and their ingest, file exports and the diagnostics
+//! bundle.
+
+use super::kernel::{authorize_kernel_request, Authorized};
+use super::print::stream_export;
+use super::vault::{response_error, shell_call};
+use crate::shell::kernel::{call_kernel, KernelState};
+use crate::shell::sessions::user_session;
+use crate::shell::window::{check_window_nonce, finish_task_progress, report_task_progress};
+use data_encoding::BASE64;
+use reos_tauri_lib::audit::{AuditEntry, AuditEvent, AuditState};
+use reos_tauri_lib::auth::AuthState;
+use reos_tauri_lib::diagnostics::{Contents, Versions};
+use reos_tauri_lib::export::{ExportWriter, ExportedFile};
+use reos_tauri_lib::file_drop::{
+    DropError, DropNotice, DropRejected, DroppedFile, FileDropState, IngestProgress, IngestedFile,
+};
+use reos_tauri_lib::guest::GuestState;
+use reos_tauri_lib::kernel::rpc_result;
+use reos_tauri_lib::locks::Recover;
+use reos_tauri_lib::profile::ProfilePaths;
+use reos_tauri_lib::request_audit::{RequestAuditState, RequestRecord};
+use reos_tauri_lib::step_up::StepUpState;
+use reos_tauri_lib::{
+    auth, crash_report, diagnostics, file_drop, kernel_output, metrics, redact, request_audit,
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+use tauri_plugin_dialog::DialogExt;
+use tracing::warn;
+
+/// Validate files dropped on `window` and announce the accepted ones to it
+pub fn handle_file_drop(window: &Window, paths: &[PathBuf]) {
+    let state = window.state::<FileDropState>();
+    for path in paths {
+        let sent = match state.policy.check(path) {
+            Ok(file) => {
+                let (name, size) = (file.name.clone(), file.size);
+                let id = state.pending.lock_or_recover().add(window.label(), file);
+                window.emit_to(
+                    window.label(),
+                    file_drop::FILE_DROPPED_EVENT,
+                    DropNotice { id, name, size },
+                )
+            }
+            Err(e) => window.emit_to(
+                window.label(),
+                file_drop::FILE_REJECTED_EVENT,
+                DropRejected {
+                    name: path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    reason: e.to_string(),
+                },
+            ),
+        };
+        if let Err(e) = sent {
+            warn!(error = %e, "failed to emit file drop event");
+        }
+    }
+}
+
+/// Stream a file dropped on this window to the kernel (not for guests)
+///
+/// Returns the kernel-side handle; progress arrives as
+/// `file-ingest-progress` events.
+#[tauri::command]
+pub async fn file_ingest(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    drop_id: String,
+) -> Result<IngestedFile, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    {
+        let store = auth_state.read();
+        user_session(&store, &session_token)?;
+    }
+    let file = app
+        .state::<FileDropState>()
+        .pending
+        .lock_or_recover()
+        .take(window.label(), &drop_id)
+        .map_err(|e| e.to_string())?;
+
+    #[derive(serde::Deserialize)]
+    struct Begun {
+        upload_id: String,
+    }
+    let begun: Begun = shell_call(
+        &app,
+        "ingest/begin",
+        json!({ "session_token": session_token, "name": file.name, "size": file.size }),
+    )
+    .await?;
+    let result = stream_dropped_file(
+        &app,
+        &window,
+        &session_token,
+        &drop_id,
+        &begun.upload_id,
+        &file,
+    )
+    .await;
+    finish_task_progress(&app, &format!("ingest:{drop_id}"));
+    if result.is_err() {
+        let _ = shell_call::<Value>(
+            &app,
+            "ingest/abort",
+            json!({ "session_token": session_token, "upload_id": begun.upload_id }),
+        )
+        .await;
+    }
+    result
+}
+
+/// Send `file` as `ingest/chunk` calls, then `ingest/finish` with its hash
+pub async fn stream_dropped_file(
+    app: &AppHandle,
+    window: &Window,
+    session_token: &str,
+    drop_id: &str,
+    upload_id: &str,
+    file: &DroppedFile,
+) -> Result<IngestedFile, String> {
+    let io_error = |e: std::io::Error| DropError::Io(e.to_string()).to_string();
+    let mut reader = File::open(&file.path).map_err(io_error)?;
+    let mut chunk = vec![0u8; file_drop::CHUNK_SIZE];
+    let mut hasher = Sha256::new();
+    let mut sent = 0u64;
+    loop {
+        let n = reader.read(&mut chunk).map_err(io_error)?;
+        if n == 0 {
+            break;
+        }
+        sent += n as u64;
+        if sent > file.size {
+            return Err(DropError::Changed.to_string());
+        }
+        hasher.update(&chunk[..n]);
+        shell_call::<Value>(
+            app,
+            "ingest/chunk",
+            json!({
+                "session_token": session_token,
+                "upload_id": upload_id,
+                "data": BASE64.encode(&chunk[..n]),
+            }),
+        )
+        .await?;
+        let progress = IngestProgress {
+            id: drop_id,
+            sent,
+            total: file.size,
+        };
+        if let Err(e) = window.emit_to(window.label(), file_drop::INGEST_PROGRESS_EVENT, progress) {
+            warn!(error = %e, "failed to emit {}", file_drop::INGEST_PROGRESS_EVENT);
+        }
+        report_task_progress(app, &format!("ingest:{drop_id}"), sent, file.size);
+    }
+    if sent != file.size {
+        return Err(DropError::Changed.to_string());
+    }
+    shell_call(
+        app,
+        "ingest/finish",
+        json!({
+            "session_token": session_token,
+            "upload_id": upload_id,
+            "sha256": hex::encode(hasher.finalize()),
+        }),
+    )
+    .await
+}
+
+/// Run a kernel method and stream its result to a file picked in the
+/// native save dialog (`None` if the dialog was cancelled)
+///
+/// Same checks as `kernel_request`; progress arrives as
+/// `file-export-progress` events.
+#[tauri::command]
+pub async fn export_file(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    method: String,
+    params: Value,
+    file_name: Option<String>,
+) -> Result<Option<ExportedFile>, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let app = window.app_handle().clone();
+    let started = Instant::now();
+    let session_id = auth::session_id(&session_token);
+    let params_sha256 = request_audit::params_hash(&params);
+    let authorized = authorize_kernel_request(
+        &app,
+        &auth_state,
+        &app.state::<GuestState>(),
+        &app.state::<StepUpState>(),
+        &session_token,
+        &method,
+        params,
+    )
+    .await?;
+    let mut params = match authorized {
+        Authorized::Forward(params) => params,
+        Authorized::Respond(response) => {
+            return Err(response_error(&response).unwrap_or_default());
+        }
+    };
+
+    // Only a file name is suggested; the user picks the folder
+    let suggested = file_name
+        .as_deref()
+        .and_then(|name| Path::new(name).file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "export".to_string());
+    let dialog = app.dialog().file().set_file_name(suggested);
+    let dest = tauri::async_runtime::spawn_blocking(move || dialog.blocking_save_file())
+        .await
+        .map_err(|e| format!("save dialog join error: {e}"))?;
+    let Some(dest) = dest else {
+        return Ok(None);
+    };
+    let dest = dest.into_path().map_err(|e| e.to_string())?;
+
+    // The session claims sign for `export/begin`, which runs `method`
+    let claims = params
+        .as_object_mut()
+        .and_then(|map| map.remove("__session"));
+    let response = call_kernel(
+        &app.state::<KernelState>(),
+        "export/begin",
+        json!({ "method": method, "params": params, "__session": claims }),
+    )
+    .await;
+    app.state::<RequestAuditState>().record(RequestRecord::new(
+        &session_id,
+        &method,
+        params_sha256,
+        &response,
+        started.elapsed(),
+    ));
+    let response = response?;
+    if let Some(message) = response_error(&response) {
+        return Err(redact::text(&message));
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Begun {
+        export_id: String,
+        size: u64,
+    }
+    let begun: Begun = rpc_result(response)?;
+    let result = stream_export(&app, &window, &begun.export_id, begun.size, &dest).await;
+    finish_task_progress(&app, &format!("export:{}", begun.export_id));
+    let _ = shell_call::<Value>(&app, "export/end", json!({ "export_id": begun.export_id })).await;
+    result.map(Some)
+}
+
+/// Zip logs, redacted config, kernel stderr, versions and metrics into a
+/// file picked with the native save dialog (`None` if cancelled; not in
+/// guest sessions); audited
+#[tauri::command]
+pub async fn diagnostics_export(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    audit: State<'_, AuditState>,
+    session_token: String,
+    window_nonce: String,
+) -> Result<Option<ExportedFile>, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let username = user_session(&auth_state.read(), &session_token)?;
+    let app = window.app_handle().clone();
+    let versions = Versions::new(
+        app.package_info().version.to_string(),
+        crash_report::kernel_version(),
+    );
+    let dialog = app
+        .dialog()
+        .file()
+        .set_file_name(diagnostics::file_name(&versions))
+        .add_filter("Zip archive", &["zip"]);
+    let dest = tauri::async_runtime::spawn_blocking(move || dialog.blocking_save_file())
+        .await
+        .map_err(|e| format!("save dialog join error: {e}"))?;
+    let Some(dest) = dest else {
+        return Ok(None);
+    };
+    let dest = dest.into_path().map_err(|e| e.to_string())?;
+
+    let metrics = serde_json::to_value(metrics::snapshot()).unwrap_or(Value::Null);
+    let data_dir = app.path().profile_data_dir().ok();
+    let exported = tauri::async_runtime::spawn_blocking(move || {
+        let zip = diagnostics::bundle(&Contents {
+            versions,
+            metrics,
+            kernel_stderr: kernel_output::stderr_tail(),
+            data_dir: data_dir.as_deref(),
+        })?;
+        let mut writer = ExportWriter::create(&dest)?;
+        writer.write(&zip)?;
+        writer.commit()
+    })
+    .await
+    .map_err(|e| format!("diagnostics export join error: {e}"))?
+    .map_err(|e| e.to_string())?;
+    audit.record(
+        AuditEntry::new(AuditEvent::DiagnosticsExported)
+            .username(&username)
+            .window(window.label())
+            .detail(exported.path.display().to_string()),
+    );
+    Ok(Some(exported))
+}
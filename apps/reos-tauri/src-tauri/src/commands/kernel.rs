@@ -0,0 +1,703 @@
+//! Kernel Commands
+//!
+//! Starting and swapping the kernel, its logs, status, method manifest,
+//! blobs and streams, and `kernel_request` with the policy checks in front
+//! of it.
+
+use super::vault::response_error;
+use crate::shell::kernel::{call_kernel_scheduled, KernelState};
+use crate::shell::sessions::{admin_session, pad_auth_failure, user_session};
+use crate::shell::tray::refresh_tray;
+use crate::shell::window::check_window_nonce;
+use reos_tauri_lib::audit::{AuditEntry, AuditEvent, AuditState};
+use reos_tauri_lib::auth::{AuthError, AuthState, SessionInfo};
+use reos_tauri_lib::consent::ConsentState;
+use reos_tauri_lib::guest::GuestState;
+use reos_tauri_lib::health::KernelReport;
+use reos_tauri_lib::hot_swap::{HotSwapError, Swapped};
+use reos_tauri_lib::kernel::KernelError;
+use reos_tauri_lib::kernel_integrity::{IntegrityError, INTEGRITY_FAILED_EVENT};
+use reos_tauri_lib::kernel_output::{KernelOutputState, OutputLine, KERNEL_OUTPUT_EVENT};
+use reos_tauri_lib::locks::Recover;
+use reos_tauri_lib::method_allowlist::MethodAllowlistState;
+use reos_tauri_lib::method_manifest::{KernelMethod, MethodManifestState, KERNEL_METHODS_EVENT};
+use reos_tauri_lib::method_policy::{MethodDenied, MethodPolicyState};
+use reos_tauri_lib::profile::ProfilePaths;
+use reos_tauri_lib::rate_limit::RequestRateLimitState;
+use reos_tauri_lib::remote_kernel::Pairing;
+use reos_tauri_lib::request_audit::{ChainReport, RequestAuditState, RequestRecord};
+use reos_tauri_lib::roles::Role;
+use reos_tauri_lib::scheduler::Priority;
+use reos_tauri_lib::session_events::SessionEvent;
+use reos_tauri_lib::splash::{StartupProgress, KERNEL_PROGRESS_EVENT};
+use reos_tauri_lib::step_up::StepUpState;
+use reos_tauri_lib::warmup::{WarmupRequest, WarmupState};
+use reos_tauri_lib::{
+    auth, blobs, consent, crash_report, hot_swap, json_stream, kernel, kernel_backend,
+    kernel_integrity, kernel_output, params, redact, remote_kernel, request_audit, session_events,
+};
+use serde_json::{json, Value};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+use tracing::{debug, error, warn};
+
+/// Start the kernel ahead of the first request, whatever the start policy
+/// (authenticated sessions only)
+///
+/// The shell starts it itself where the policy asks (see
+/// `kernel_lifecycle.rs`), so an unauthenticated frontend has no reason to
+/// spawn it.
+#[tauri::command]
+pub fn kernel_start(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    state: State<'_, KernelState>,
+    session_token: String,
+    window_nonce: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    lifecycle_session(&auth_state, &session_token)?;
+    ensure_kernel(&app, &state)
+}
+
+/// Swap in a freshly started kernel, keeping every session (admin only)
+///
+/// Picks up kernel code updated on disk without logging anyone out (see
+/// `hot_swap.rs`); on failure the running kernel keeps serving.
+#[tauri::command]
+pub async fn kernel_hot_swap(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+) -> Result<Swapped, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let admin = {
+        let store = auth_state.read();
+        admin_session(&store, &session_token)?
+    };
+    let kernel = app.state::<KernelState>().0.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || hot_swap::swap(&kernel))
+        .await
+        .map_err(|e| format!("kernel hot swap join error: {e}"))?;
+    if let Err(HotSwapError::Start(KernelError::Integrity(integrity))) = &result {
+        emit_integrity_failure(&app, integrity);
+    }
+    app.state::<AuditState>().record(
+        AuditEntry::new(AuditEvent::KernelRestarted)
+            .session_id(&auth::session_id(&session_token))
+            .detail(match &result {
+                Ok(swapped) => format!("by {admin}, hot swap: {} sessions kept", swapped.sessions),
+                Err(e) => format!("by {admin}, hot swap failed: {e}"),
+            }),
+    );
+    refresh_tray(&app);
+    result.map_err(|e| e.to_string())
+}
+
+/// This shell's client certificate for pairing with a remote kernel,
+/// created on first use
+///
+/// Needs no session: with a remote kernel, logging in goes through the very
+/// kernel being paired with. Only the (public) certificate leaves; its key
+/// stays in the app data dir.
+#[tauri::command]
+pub fn remote_kernel_pair(
+    app: AppHandle,
+    window: Window,
+    window_nonce: String,
+) -> Result<Pairing, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let dir = app.path().profile_data_dir().map_err(|e| e.to_string())?;
+    remote_kernel::pairing(&dir).map_err(|e| e.to_string())
+}
+
+/// Spawn the kernel unless it's already running
+pub fn ensure_kernel(app: &AppHandle, state: &KernelState) -> Result<(), String> {
+    let mut guard = kernel::lock_shared(&state.0);
+    if guard.is_some() {
+        return Ok(());
+    }
+    let proc = kernel_backend::spawn(|stage| {
+        if let Err(e) = app.emit(KERNEL_PROGRESS_EVENT, StartupProgress::from(stage)) {
+            warn!(error = %e, "failed to emit {KERNEL_PROGRESS_EVENT}");
+        }
+    })
+    .map_err(|e| {
+        if let KernelError::Integrity(integrity) = &e {
+            emit_integrity_failure(app, integrity);
+        }
+        e.to_string()
+    })?;
+    *guard = Some(proc);
+    drop(guard);
+    refresh_tray(app);
+    Ok(())
+}
+
+/// Session check shared by kernel lifecycle commands
+pub fn lifecycle_session(auth_state: &AuthState, session_token: &str) -> Result<(), String> {
+    let store = auth_state.read();
+    store
+        .check(session_token)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Follow kernel output from the calling window: returns the stored lines
+/// after `after` (all of them for `None`) and sends each new one as a
+/// `kernel-output` event (not in guest sessions)
+#[tauri::command]
+pub fn kernel_logs_subscribe(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    output: State<'_, KernelOutputState>,
+    session_token: String,
+    window_nonce: String,
+    after: Option<u64>,
+) -> Result<Vec<OutputLine>, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    user_session(&auth_state.read(), &session_token)?;
+    output
+        .0
+        .lock_or_recover()
+        .insert(window.label().to_string(), auth::hash_token(&session_token));
+    Ok(kernel_output::since(after.unwrap_or(0)))
+}
+
+/// Stop sending kernel output to the calling window
+#[tauri::command]
+pub fn kernel_logs_unsubscribe(
+    window: Window,
+    output: State<'_, KernelOutputState>,
+    window_nonce: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    output.0.lock_or_recover().remove(window.label());
+    Ok(())
+}
+
+/// Drop kernel output subscribers whose session ended or locked
+pub fn prune_kernel_output_subscribers(app: &AppHandle) {
+    let output = app.state::<KernelOutputState>();
+    let mut subscribers = output.0.lock_or_recover();
+    if subscribers.is_empty() {
+        return;
+    }
+    let auth_state = app.state::<AuthState>();
+    let store = auth_state.read();
+    subscribers.retain(|_, hash| store.is_active_hash(hash));
+}
+
+/// Send a kernel output line to the windows following it
+pub fn send_kernel_output(app: &AppHandle, line: &OutputLine) {
+    let labels: Vec<String> = app
+        .state::<KernelOutputState>()
+        .0
+        .lock_or_recover()
+        .keys()
+        .cloned()
+        .collect();
+    for label in labels {
+        if let Err(e) = app.emit_to(label.as_str(), KERNEL_OUTPUT_EVENT, line) {
+            warn!(error = %e, "failed to emit {KERNEL_OUTPUT_EVENT}");
+        }
+    }
+}
+
+/// Kernel process state, version, heartbeat and latest resource sample
+#[tauri::command]
+pub fn kernel_status(
+    auth_state: State<'_, AuthState>,
+    kernel_state: State<'_, KernelState>,
+    session_token: String,
+) -> Result<KernelReport, String> {
+    lifecycle_session(&auth_state, &session_token)?;
+    Ok(KernelReport::check(
+        &kernel_state.0,
+        crash_report::kernel_version(),
+    ))
+}
+
+/// Methods the running kernel answers (`None` before its handshake, or if
+/// it sent no manifest), each with whether this session may send it
+#[tauri::command]
+pub fn kernel_list_methods(
+    app: AppHandle,
+    auth_state: State<'_, AuthState>,
+    manifest_state: State<'_, MethodManifestState>,
+    session_token: String,
+) -> Result<Option<Vec<KernelMethod>>, String> {
+    let role = {
+        let store = auth_state.read();
+        store.check(&session_token).map_err(|e| e.to_string())?.role
+    };
+    let Some(methods) = manifest_state.methods() else {
+        return Ok(None);
+    };
+    let allowlist = app.state::<MethodAllowlistState>();
+    let policy = app.state::<MethodPolicyState>();
+    let guest = app.state::<GuestState>();
+    let methods = methods
+        .into_iter()
+        .map(|name| {
+            let allowed = allowlist.0.allows(&name)
+                && policy.0.check(role, &name).is_ok()
+                && (role != Role::Guest || guest.0.allows(&name));
+            KernelMethod { name, allowed }
+        })
+        .collect();
+    Ok(Some(methods))
+}
+
+pub fn emit_kernel_methods_changed(app: &AppHandle) {
+    if let Err(e) = app.emit(KERNEL_METHODS_EVENT, ()) {
+        warn!(error = %e, "failed to emit {KERNEL_METHODS_EVENT}");
+    }
+}
+
+/// Segment `index` of a large result moved out of band (see `blobs.rs`),
+/// decrypted, as raw bytes
+#[tauri::command]
+pub fn kernel_blob_read(
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    handle: String,
+    index: u64,
+) -> Result<tauri::ipc::Response, String> {
+    let session_id = {
+        let store = auth_state.read();
+        store
+            .check(&session_token)
+            .map_err(|e| e.to_string())?
+            .session_id
+    };
+    let segment = blobs::read(&handle, &session_id, index).map_err(|e| e.to_string())?;
+    Ok(tauri::ipc::Response::new(segment))
+}
+
+/// Free a large result; whether the handle was still open
+#[tauri::command]
+pub fn kernel_blob_release(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    handle: String,
+) -> Result<bool, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let session_id = {
+        let store = auth_state.read();
+        store
+            .check(&session_token)
+            .map_err(|e| e.to_string())?
+            .session_id
+    };
+    Ok(blobs::release(&handle, &session_id))
+}
+
+/// Chunk `index` of a result array streamed in pieces (see `json_stream.rs`),
+/// as the raw bytes of a JSON array
+#[tauri::command]
+pub fn kernel_stream_read(
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    handle: String,
+    index: usize,
+) -> Result<tauri::ipc::Response, String> {
+    let session_id = {
+        let store = auth_state.read();
+        store
+            .check(&session_token)
+            .map_err(|e| e.to_string())?
+            .session_id
+    };
+    let chunk = json_stream::read(&handle, &session_id, index).map_err(|e| e.to_string())?;
+    Ok(tauri::ipc::Response::new(chunk))
+}
+
+/// Free a streamed result array; whether the handle was still open
+#[tauri::command]
+pub fn kernel_stream_release(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    handle: String,
+) -> Result<bool, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let session_id = {
+        let store = auth_state.read();
+        store
+            .check(&session_token)
+            .map_err(|e| e.to_string())?
+            .session_id
+    };
+    Ok(json_stream::release(&handle, &session_id))
+}
+
+/// Re-run the kernel integrity check (`None` when it passes or is disabled)
+#[tauri::command]
+pub fn kernel_integrity_status() -> Option<IntegrityError> {
+    kernel_integrity::check().err()
+}
+
+pub fn emit_integrity_failure(app: &AppHandle, error: &IntegrityError) {
+    error!(error = %error, "refusing to start kernel");
+    if let Err(e) = app.emit(INTEGRITY_FAILED_EVENT, error) {
+        warn!(error = %e, "failed to emit {INTEGRITY_FAILED_EVENT}");
+    }
+}
+
+/// Send a request to the Python kernel
+///
+/// # Security
+/// - Requires valid session token and the invoking window's nonce
+/// - Rate limited per session and method class (`rate-limits.json`)
+/// - Only allowlisted methods are forwarded (`kernel-methods.json`)
+/// - The session's role must be permitted (`method-policy.json`); denials
+///   come back as a JSON-RPC error with `METHOD_DENIED_CODE`
+/// - Params are depth/size limited and may not use reserved `__` keys
+/// - Dangerous methods prompt for consent once per session (`consent.json`)
+/// - Session info is injected into params for audit logging
+/// - Every call is recorded in the hash-chained `kernel-requests.jsonl`
+/// - Credentials never reach the kernel
+#[tauri::command]
+pub async fn kernel_request(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    method: String,
+    params: Value,
+) -> Result<Value, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let started = Instant::now();
+    let session_id = auth::session_id(&session_token);
+    let params_sha256 = request_audit::params_hash(&params);
+    let result = forward_kernel_request(
+        &app,
+        &auth_state,
+        &app.state::<GuestState>(),
+        &app.state::<StepUpState>(),
+        &session_token,
+        &method,
+        params,
+    )
+    .await;
+    app.state::<RequestAuditState>().record(RequestRecord::new(
+        &session_id,
+        &method,
+        params_sha256,
+        &result,
+        started.elapsed(),
+    ));
+    result.map_err(|e| redact::text(&e))
+}
+
+/// Forward a kernel-declared method (tray or app menu item, no params) with
+/// the same checks and request audit as `kernel_request`
+pub async fn forward_declared_method(
+    app: &AppHandle,
+    auth_state: &AuthState,
+    session_token: &str,
+    method: &str,
+) -> Result<Value, String> {
+    let started = Instant::now();
+    let session_id = auth::session_id(session_token);
+    let params = json!({});
+    let params_sha256 = request_audit::params_hash(&params);
+    let result = forward_kernel_request(
+        app,
+        auth_state,
+        &app.state::<GuestState>(),
+        &app.state::<StepUpState>(),
+        session_token,
+        method,
+        params,
+    )
+    .await;
+    app.state::<RequestAuditState>().record(RequestRecord::new(
+        &session_id,
+        method,
+        params_sha256,
+        &result,
+        started.elapsed(),
+    ));
+    result
+}
+
+/// Walk the hash chain of `kernel-requests.jsonl` (admin only)
+#[tauri::command]
+pub fn audit_verify_requests(
+    auth_state: State<'_, AuthState>,
+    request_audit: State<'_, RequestAuditState>,
+    session_token: String,
+) -> Result<ChainReport, String> {
+    {
+        let store = auth_state.read();
+        admin_session(&store, &session_token)?;
+    }
+    let log = request_audit.0.lock_or_recover();
+    Ok(log.verify())
+}
+
+/// Outcome of `authorize_kernel_request`
+pub enum Authorized {
+    /// Send these params (with signed-for `__session` info) to the kernel
+    Forward(Value),
+    /// Answer with this JSON-RPC response instead (rate limit, denial, ...)
+    Respond(Value),
+}
+
+/// Policy checks and forwarding behind `kernel_request`
+pub async fn forward_kernel_request(
+    app: &AppHandle,
+    auth_state: &AuthState,
+    guest_state: &GuestState,
+    step_up: &StepUpState,
+    session_token: &str,
+    method: &str,
+    params: Value,
+) -> Result<Value, String> {
+    match authorize_kernel_request(
+        app,
+        auth_state,
+        guest_state,
+        step_up,
+        session_token,
+        method,
+        params,
+    )
+    .await?
+    {
+        // Forward to kernel on background thread, in the session's turn
+        Authorized::Forward(params) => {
+            let session_id = auth::session_id(session_token);
+            call_kernel_scheduled(app, session_id, method, params, Priority::Normal).await
+        }
+        Authorized::Respond(response) => Ok(response),
+    }
+}
+
+/// Session, rate-limit, params, allowlist, role and consent checks for a
+/// frontend kernel request; refreshes the session when it passes
+pub async fn authorize_kernel_request(
+    app: &AppHandle,
+    auth_state: &AuthState,
+    guest_state: &GuestState,
+    step_up: &StepUpState,
+    session_token: &str,
+    method: &str,
+    params: Value,
+) -> Result<Authorized, String> {
+    // Validate session first (zero trust); locked sessions keep their
+    // kernel-side key but can't make requests until unlocked. The same
+    // lock records the activity (at most every few seconds)
+    let started = Instant::now();
+    let touched = auth_state.read().touch(session_token);
+    let touched = match touched {
+        Ok(touched) => touched,
+        Err(e) => {
+            pad_auth_failure(started).await;
+            return Err(e.to_string());
+        }
+    };
+    let auth_stale = step_up.0.is_stale(touched.authenticated_for);
+    let session_info = touched.info;
+    if touched.refreshed {
+        session_events::emit(
+            app,
+            SessionEvent::Refreshed,
+            &session_info.session_id,
+            Some(&session_info.username),
+            None,
+        );
+    }
+
+    // Per-session budget so a runaway loop can't saturate the kernel
+    let limited = app
+        .state::<RequestRateLimitState>()
+        .0
+        .lock_or_recover()
+        .check(&session_info.session_id, method);
+    if let Err(limited) = limited {
+        return Ok(Authorized::Respond(limited.to_response()));
+    }
+
+    // Bounded, normalized params without reserved `__` keys
+    let mut params = params;
+    if let Err(e) = params::sanitize(&mut params) {
+        return Ok(Authorized::Respond(e.to_response()));
+    }
+
+    // Only allowlisted methods reach the kernel (no internal/debug endpoints)
+    if !app.state::<MethodAllowlistState>().0.allows(method) {
+        app.state::<AuditState>().record(
+            AuditEntry::new(AuditEvent::MethodDenied)
+                .username(&session_info.username)
+                .session_id(&session_info.session_id)
+                .detail(format!("not allowlisted: {method}")),
+        );
+        return Err(format!("Kernel method not allowed: {method}"));
+    }
+
+    // Sensitive methods need a recent password (see `auth_step_up`)
+    if auth_stale && step_up.0.requires(method) {
+        return Err(AuthError::ReauthRequired.to_string());
+    }
+
+    // Role policy, then guests (no key material) only get read-only methods
+    let denied = app
+        .state::<MethodPolicyState>()
+        .0
+        .check(session_info.role, method)
+        .err()
+        .or_else(|| {
+            (session_info.role == Role::Guest && !guest_state.0.allows(method)).then(|| {
+                MethodDenied {
+                    method: method.to_string(),
+                    role: Role::Guest,
+                }
+            })
+        });
+    if let Some(denied) = denied {
+        app.state::<AuditState>().record(
+            AuditEntry::new(AuditEvent::MethodDenied)
+                .username(&session_info.username)
+                .session_id(&session_info.session_id)
+                .detail(denied.to_string()),
+        );
+        return Ok(Authorized::Respond(denied.to_response()));
+    }
+
+    // Dangerous methods need the user's approval once per session
+    if let Some(rule) = app.state::<ConsentState>().0.rule_for(method).cloned() {
+        let granted = {
+            let store = auth_state.read();
+            store
+                .get(session_token)
+                .is_some_and(|s| s.consents.contains(&rule.name))
+        };
+        if !granted {
+            let dialog_app = app.clone();
+            let (prompt_rule, username, prompt_method) = (
+                rule.clone(),
+                session_info.username.clone(),
+                method.to_string(),
+            );
+            let approved = tauri::async_runtime::spawn_blocking(move || {
+                consent::ask(&dialog_app, &prompt_rule, &username, &prompt_method)
+            })
+            .await
+            .map_err(|e| format!("consent prompt join error: {e}"))?;
+
+            let event = if approved {
+                AuditEvent::ConsentGranted
+            } else {
+                AuditEvent::ConsentDenied
+            };
+            app.state::<AuditState>().record(
+                AuditEntry::new(event)
+                    .username(&session_info.username)
+                    .session_id(&session_info.session_id)
+                    .detail(format!("{}: {method}", rule.name)),
+            );
+            if !approved {
+                return Ok(Authorized::Respond(consent::denied_response(&rule, method)));
+            }
+            let mut store = auth_state.store();
+            if let Some(session) = store.get_mut(session_token) {
+                session.consents.insert(rule.name.clone());
+            }
+        }
+    }
+
+    Ok(Authorized::Forward(auth::with_session_claims(
+        params,
+        &session_info,
+    )))
+}
+
+/// Params for one warmup request, or why it is skipped: `warmup/hint` gets
+/// the kernel session token, anything else must be callable by the
+/// frontend for this session without a prompt (see `warmup.rs`)
+pub fn warmup_params(
+    app: &AppHandle,
+    token: &str,
+    session_info: &SessionInfo,
+    request: &WarmupRequest,
+) -> Result<Value, &'static str> {
+    let mut params = match &request.params {
+        Value::Null => json!({}),
+        params => params.clone(),
+    };
+    if request.is_hint() {
+        let Value::Object(map) = &mut params else {
+            return Err("params must be an object");
+        };
+        map.insert("session_token".to_string(), json!(token));
+        return Ok(params);
+    }
+    let method = request.method.as_str();
+    if !app.state::<MethodAllowlistState>().0.allows(method) {
+        return Err("not allowlisted");
+    }
+    if app
+        .state::<MethodPolicyState>()
+        .0
+        .check(session_info.role, method)
+        .is_err()
+    {
+        return Err("not permitted for the role");
+    }
+    if app.state::<ConsentState>().0.rule_for(method).is_some()
+        || app.state::<StepUpState>().0.requires(method)
+    {
+        return Err("needs the user's approval");
+    }
+    params::sanitize(&mut params).map_err(|_| "invalid params")?;
+    Ok(auth::with_session_claims(params, session_info))
+}
+
+/// Send the configured warmup requests for a new session in the
+/// background, one at a time, while it stays active (see `warmup.rs`)
+pub fn start_warmup(app: &AppHandle, token: String) {
+    let config = app.state::<WarmupState>().0.clone();
+    if !config.active() {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(config.delay()).await;
+        for request in &config.requests {
+            // Checked without recording activity: warmup keeps no session alive
+            let Ok(session_info) = app.state::<AuthState>().read().check(&token) else {
+                return;
+            };
+            let method = request.method.as_str();
+            let params = match warmup_params(&app, &token, &session_info, request) {
+                Ok(params) => params,
+                Err(reason) => {
+                    warn!(method, reason, "skipping warmup request");
+                    continue;
+                }
+            };
+            let started = Instant::now();
+            let session_id = session_info.session_id.clone();
+            let result =
+                call_kernel_scheduled(&app, session_id, method, params, Priority::Background).await;
+            let elapsed_ms = started.elapsed().as_millis() as u64;
+            match result.as_ref().map(response_error) {
+                Ok(None) => debug!(method, elapsed_ms, "warmup request done"),
+                Ok(Some(error)) => {
+                    warn!(method, error = %redact::text(&error), "warmup request failed")
+                }
+                Err(error) => warn!(method, error = %error, "warmup request failed"),
+            }
+        }
+    });
+}
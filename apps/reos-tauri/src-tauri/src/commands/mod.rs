@@ -0,0 +1,16 @@
+//! Tauri Commands
+//!
+//! The commands the frontend invokes, by group; registered in `main.rs`.
+//! The state and helpers they share live in `shell`.
+
+pub mod app;
+pub mod auth;
+pub mod autostart;
+pub mod clipboard;
+pub mod file;
+pub mod kernel;
+pub mod open_external;
+pub mod print;
+pub mod update;
+pub mod vault;
+pub mod window;
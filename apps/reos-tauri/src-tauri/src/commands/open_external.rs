@@ -0,0 +1,60 @@
+//! External Open Commands
+//!
+//! URLs and files handed to the OS's default handler.
+
+use crate::shell::window::check_window_nonce;
+use reos_tauri_lib::audit::{AuditEntry, AuditEvent, AuditState};
+use reos_tauri_lib::auth::AuthState;
+use reos_tauri_lib::open_external;
+use reos_tauri_lib::open_external::{OpenError, OpenExternalState, Target};
+use reos_tauri_lib::roles::Role;
+use tauri::{Manager, State, Window};
+
+/// Open a URL or file with the OS's default handler, once it passes the
+/// open policy; every attempt is audited
+#[tauri::command]
+pub async fn open_external(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    target: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let info = auth_state
+        .read()
+        .check(&session_token)
+        .map_err(|e| e.to_string())?;
+    let app = window.app_handle().clone();
+    let checked =
+        app.state::<OpenExternalState>()
+            .0
+            .check(&target)
+            .and_then(|checked| match checked {
+                Target::Path(_) if info.role < Role::User => Err(OpenError::Guest),
+                checked => Ok(checked),
+            });
+    let entry = |event| {
+        AuditEntry::new(event)
+            .username(&info.username)
+            .session_id(&info.session_id)
+            .window(window.label())
+    };
+    let audit = app.state::<AuditState>();
+    let checked = match checked {
+        Ok(checked) => checked,
+        Err(e) => {
+            audit.record(
+                entry(AuditEvent::ExternalOpenDenied)
+                    .detail(format!("{e}: {}", open_external::describe_raw(&target))),
+            );
+            return Err(e.to_string());
+        }
+    };
+    // Recorded before the OS sees it
+    audit.record(entry(AuditEvent::ExternalOpened).detail(checked.describe()));
+    tauri::async_runtime::spawn_blocking(move || open_external::open(&checked))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,137 @@
+//! Printing Commands
+//!
+//! Printing kernel documents.
+
+use super::kernel::{authorize_kernel_request, Authorized};
+use super::vault::{response_error, shell_call};
+use crate::shell::kernel::{call_kernel, KernelState};
+use crate::shell::window::{check_window_nonce, report_task_progress};
+use data_encoding::BASE64;
+use reos_tauri_lib::auth::AuthState;
+use reos_tauri_lib::export::{ExportProgress, ExportWriter, ExportedFile};
+use reos_tauri_lib::guest::GuestState;
+use reos_tauri_lib::kernel::rpc_result;
+use reos_tauri_lib::print::{Document, PrintCapabilities, PrintOptions};
+use reos_tauri_lib::request_audit::{RequestAuditState, RequestRecord};
+use reos_tauri_lib::step_up::StepUpState;
+use reos_tauri_lib::{auth, export, print, redact, request_audit};
+use serde_json::json;
+use std::path::Path;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+use tracing::warn;
+
+/// Print a kernel document (`conversation:<id>`, `archive:<id>` or `me`)
+/// through the platform print dialog; false if the dialog was cancelled
+///
+/// The method that reads the document gets the same checks and request
+/// audit as `kernel_request`.
+#[tauri::command]
+pub async fn print_document(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    handle: String,
+    options: Option<PrintOptions>,
+) -> Result<bool, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let options = options.unwrap_or_default();
+    options.validate().map_err(|e| e.to_string())?;
+    let (method, params) = print::resolve(&handle).map_err(|e| e.to_string())?;
+    let app = window.app_handle().clone();
+    let started = Instant::now();
+    let session_id = auth::session_id(&session_token);
+    let params_sha256 = request_audit::params_hash(&params);
+    let authorized = authorize_kernel_request(
+        &app,
+        &auth_state,
+        &app.state::<GuestState>(),
+        &app.state::<StepUpState>(),
+        &session_token,
+        method,
+        params,
+    )
+    .await?;
+    let mut params = match authorized {
+        Authorized::Forward(params) => params,
+        Authorized::Respond(response) => {
+            return Err(response_error(&response).unwrap_or_default());
+        }
+    };
+
+    // The session claims sign for `print/render`, which runs `method`
+    let claims = params
+        .as_object_mut()
+        .and_then(|map| map.remove("__session"));
+    let response = call_kernel(
+        &app.state::<KernelState>(),
+        "print/render",
+        json!({ "method": method, "params": params, "__session": claims }),
+    )
+    .await;
+    app.state::<RequestAuditState>().record(RequestRecord::new(
+        &session_id,
+        method,
+        params_sha256,
+        &response,
+        started.elapsed(),
+    ));
+    let response = response?;
+    if let Some(message) = response_error(&response) {
+        return Err(redact::text(&message));
+    }
+    let document: Document = rpc_result(response)?;
+    tauri::async_runtime::spawn_blocking(move || print::print(&window, document, options))
+        .await
+        .map_err(|e| format!("print join error: {e}"))?
+        .map_err(|e| e.to_string())
+}
+
+/// What printing supports here (whether `dialog: true` shows a dialog)
+#[tauri::command]
+pub fn print_capabilities() -> PrintCapabilities {
+    print::CAPABILITIES
+}
+
+/// Fetch an export's data with `export/chunk` calls and write it to `dest`
+pub async fn stream_export(
+    app: &AppHandle,
+    window: &Window,
+    export_id: &str,
+    size: u64,
+    dest: &Path,
+) -> Result<ExportedFile, String> {
+    let io_error = |e: std::io::Error| format!("Cannot write {}: {e}", dest.display());
+    let mut writer = ExportWriter::create(dest).map_err(io_error)?;
+    let mut written = 0;
+    while written < size {
+        #[derive(serde::Deserialize)]
+        struct Chunk {
+            data: String,
+        }
+        let chunk: Chunk = shell_call(
+            app,
+            "export/chunk",
+            json!({ "export_id": export_id, "offset": written, "length": export::CHUNK_SIZE }),
+        )
+        .await?;
+        let data = BASE64
+            .decode(chunk.data.as_bytes())
+            .map_err(|e| format!("Invalid export data: {e}"))?;
+        if data.is_empty() {
+            return Err("Export ended early".to_string());
+        }
+        written = writer.write(&data).map_err(io_error)?;
+        let progress = ExportProgress {
+            id: export_id,
+            written,
+            total: size,
+        };
+        if let Err(e) = window.emit_to(window.label(), export::EXPORT_PROGRESS_EVENT, progress) {
+            warn!(error = %e, "failed to emit {}", export::EXPORT_PROGRESS_EVENT);
+        }
+        report_task_progress(app, &format!("export:{export_id}"), written, size);
+    }
+    writer.commit().map_err(io_error)
+}
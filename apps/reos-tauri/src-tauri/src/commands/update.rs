@@ -0,0 +1,162 @@
+//! Update Commands
+//!
+//! Checking for, downloading and installing signed releases.
+
+use super::kernel::lifecycle_session;
+use crate::shell::kernel::KernelState;
+use crate::shell::sessions::{admin_session, persist_sessions};
+use crate::shell::window::check_window_nonce;
+use reos_tauri_lib::audit::{AuditEntry, AuditEvent, AuditState};
+use reos_tauri_lib::auth::AuthState;
+use reos_tauri_lib::kernel::{rpc_result, SharedKernel};
+use reos_tauri_lib::locks::Recover;
+use reos_tauri_lib::profile::ProfilePaths;
+use reos_tauri_lib::updater::{UpdateError, UpdateInfo, UpdaterState};
+use reos_tauri_lib::{auth, kernel, updater};
+use serde_json::json;
+use tauri::{AppHandle, Manager, State, Window};
+
+/// Check the update endpoint for a newer signed release (any session)
+#[tauri::command]
+pub async fn update_check(
+    app: AppHandle,
+    auth_state: State<'_, AuthState>,
+    updater_state: State<'_, UpdaterState>,
+    session_token: String,
+) -> Result<Option<UpdateInfo>, String> {
+    lifecycle_session(&auth_state, &session_token)?;
+    let (endpoint, key, install_id) = {
+        let updater = updater_state.0.lock_or_recover();
+        let (endpoint, key) = updater.source().map_err(|e| e.to_string())?;
+        (endpoint, key, updater.install_id().to_string())
+    };
+    let manifest = tauri::async_runtime::spawn_blocking(move || updater::fetch_manifest(&endpoint))
+        .await
+        .map_err(|e| format!("update check join error: {e}"))?
+        .map_err(|e| e.to_string())?;
+    let current = app.package_info().version.clone();
+    let offered =
+        updater::evaluate(&manifest, &key, &current, &install_id).map_err(|e| e.to_string())?;
+
+    let mut updater = updater_state.0.lock_or_recover();
+    updater.set_available(offered.then_some(manifest));
+    Ok(updater.info(&current))
+}
+
+/// Download and verify the release found by `update_check` (admin only)
+#[tauri::command]
+pub async fn update_download(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    updater_state: State<'_, UpdaterState>,
+    session_token: String,
+    window_nonce: String,
+) -> Result<UpdateInfo, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    {
+        let store = auth_state.read();
+        admin_session(&store, &session_token)?;
+    }
+    let manifest = updater_state
+        .0
+        .lock_or_recover()
+        .available()
+        .cloned()
+        .ok_or_else(|| UpdateError::NoUpdate.to_string())?;
+    let dir = app
+        .path()
+        .profile_cache_dir()
+        .map_err(|e| e.to_string())?
+        .join("updates");
+    let staged = tauri::async_runtime::spawn_blocking(move || updater::download(&manifest, &dir))
+        .await
+        .map_err(|e| format!("update download join error: {e}"))?
+        .map_err(|e| e.to_string())?;
+
+    let mut updater = updater_state.0.lock_or_recover();
+    updater.set_staged(staged);
+    updater
+        .info(&app.package_info().version)
+        .ok_or_else(|| UpdateError::NoUpdate.to_string())
+}
+
+/// Install the downloaded release and restart (admin only)
+///
+/// Refused while the kernel has a migration or encrypted write in flight.
+#[tauri::command]
+pub async fn update_install(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    updater_state: State<'_, UpdaterState>,
+    session_token: String,
+    window_nonce: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let admin = {
+        let store = auth_state.read();
+        admin_session(&store, &session_token)?
+    };
+    let (staged, version, sha256) = {
+        let updater = updater_state.0.lock_or_recover();
+        match (updater.staged(), updater.available()) {
+            (Some(staged), Some(manifest)) => (
+                staged.to_path_buf(),
+                manifest.version.clone(),
+                manifest.sha256.clone(),
+            ),
+            _ => return Err("No update has been downloaded".to_string()),
+        }
+    };
+    let kernel = app.state::<KernelState>().0.clone();
+    let result =
+        tauri::async_runtime::spawn_blocking(move || install_update(&kernel, &staged, &sha256))
+            .await
+            .map_err(|e| format!("update install join error: {e}"))?;
+    app.state::<AuditState>().record(
+        AuditEntry::new(AuditEvent::UpdateInstalled)
+            .session_id(&auth::session_id(&session_token))
+            .detail(match &result {
+                Ok(()) => format!("by {admin}: {version}"),
+                Err(e) => format!("by {admin}: {version} failed: {e}"),
+            }),
+    );
+    result?;
+    persist_sessions(&app);
+    app.request_restart();
+    Ok(())
+}
+
+/// Swap in a staged update with kernel writes frozen (blocking); `sha256`
+/// is the signed manifest's, checked again before anything is replaced
+pub fn install_update(
+    kernel: &SharedKernel,
+    staged: &std::path::Path,
+    sha256: &str,
+) -> Result<(), String> {
+    #[derive(serde::Deserialize)]
+    struct Prepared {
+        ready: bool,
+        busy: Vec<String>,
+    }
+    // No request starts while the build is swapped; afterwards the frozen
+    // kernel refuses writes until the restart
+    let mut guard = kernel::lock_shared(kernel);
+    if let Some(proc) = guard.as_mut() {
+        let prepared: Prepared = proc
+            .request("update/prepare", json!({}))
+            .map_err(|e| e.to_string())
+            .and_then(rpc_result)?;
+        if !prepared.ready {
+            return Err(UpdateError::KernelBusy(prepared.busy).to_string());
+        }
+    }
+    if let Err(e) = updater::apply(staged, sha256) {
+        if let Some(proc) = guard.as_mut() {
+            let _ = proc.request("update/resume", json!({}));
+        }
+        return Err(e.to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,147 @@
+//! Secrets Vault Commands
+//!
+//! The user's secrets vault, kept by the kernel under the session's key.
+
+use crate::shell::kernel::{call_kernel, KernelState};
+use crate::shell::window::check_window_nonce;
+use reos_tauri_lib::auth::AuthState;
+use reos_tauri_lib::kernel::rpc_result;
+use reos_tauri_lib::roles::Role;
+use reos_tauri_lib::secret::SecretString;
+use reos_tauri_lib::vault;
+use reos_tauri_lib::vault::VaultError;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State, Window};
+
+/// Check a vault caller's session; guests have no kernel key material
+pub fn vault_session(auth_state: &AuthState, session_token: &str) -> Result<(), String> {
+    let store = auth_state.read();
+    let info = store.check(session_token).map_err(|e| e.to_string())?;
+    if info.role == Role::Guest {
+        return Err(VaultError::GuestSession.to_string());
+    }
+    Ok(())
+}
+
+/// Call a Rust-owned kernel method (`vault/*`, `ingest/*`), surfacing
+/// kernel errors as messages
+pub async fn shell_call<T: serde::de::DeserializeOwned>(
+    app: &AppHandle,
+    method: &str,
+    params: Value,
+) -> Result<T, String> {
+    let response = call_kernel(&app.state::<KernelState>(), method, params).await?;
+    if let Some(message) = response_error(&response) {
+        return Err(message);
+    }
+    rpc_result(response)
+}
+
+/// Message of a JSON-RPC error response (`None` for results)
+pub fn response_error(response: &Value) -> Option<String> {
+    let error = response.get("error")?;
+    let message = error.get("message").and_then(Value::as_str);
+    Some(message.unwrap_or("Kernel request failed").to_string())
+}
+
+/// Store a secret in the current user's vault (overwrites)
+#[tauri::command]
+pub async fn vault_set(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    name: String,
+    value: SecretString,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    vault_session(&auth_state, &session_token)?;
+    vault::validate_name(&name).map_err(|e| e.to_string())?;
+    vault::validate_value(value.expose()).map_err(|e| e.to_string())?;
+    shell_call::<Value>(
+        &app,
+        "vault/set",
+        json!({
+            "session_token": session_token,
+            "name": name,
+            "value": value.expose(),
+            "max_entries": vault::MAX_ENTRIES,
+        }),
+    )
+    .await
+    .map(|_| ())
+}
+
+/// Read a secret from the current user's vault (`None` if absent)
+#[tauri::command]
+pub async fn vault_get(
+    app: AppHandle,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    name: String,
+) -> Result<Option<SecretString>, String> {
+    vault_session(&auth_state, &session_token)?;
+    vault::validate_name(&name).map_err(|e| e.to_string())?;
+
+    #[derive(serde::Deserialize)]
+    struct Entry {
+        value: Option<SecretString>,
+    }
+    let entry: Entry = shell_call(
+        &app,
+        "vault/get",
+        json!({ "session_token": session_token, "name": name }),
+    )
+    .await?;
+    Ok(entry.value)
+}
+
+/// Delete a secret; returns whether it existed
+#[tauri::command]
+pub async fn vault_delete(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    name: String,
+) -> Result<bool, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    vault_session(&auth_state, &session_token)?;
+    vault::validate_name(&name).map_err(|e| e.to_string())?;
+
+    #[derive(serde::Deserialize)]
+    struct Deleted {
+        deleted: bool,
+    }
+    let result: Deleted = shell_call(
+        &app,
+        "vault/delete",
+        json!({ "session_token": session_token, "name": name }),
+    )
+    .await?;
+    Ok(result.deleted)
+}
+
+/// Names of the current user's secrets (never the values)
+#[tauri::command]
+pub async fn vault_list(
+    app: AppHandle,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+) -> Result<Vec<String>, String> {
+    vault_session(&auth_state, &session_token)?;
+
+    #[derive(serde::Deserialize)]
+    struct Names {
+        names: Vec<String>,
+    }
+    let result: Names = shell_call(
+        &app,
+        "vault/list",
+        json!({ "session_token": session_token }),
+    )
+    .await?;
+    Ok(result.names)
+}
@@ -0,0 +1,193 @@
+//! Window Commands
+//!
+//! Window nonces, secondary windows and handoff, deep links, and the app
+//! menu and tray actions.
+
+use super::kernel::{forward_declared_method, lifecycle_session};
+use super::vault::response_error;
+use crate::shell::window::{check_window_nonce, restore_window_state};
+use reos_tauri_lib::app_menu::AppMenuState;
+use reos_tauri_lib::auth::AuthState;
+use reos_tauri_lib::deep_link::{DeepLink, DeepLinkState};
+use reos_tauri_lib::kernel::rpc_result;
+use reos_tauri_lib::locks::Recover;
+use reos_tauri_lib::notifications::{Category, Notification};
+use reos_tauri_lib::quick_actions::QuickActionsState;
+use reos_tauri_lib::secret::SecretString;
+use reos_tauri_lib::window_nonce::WindowNonceState;
+use reos_tauri_lib::windows::{SessionHandoff, WindowError, WindowsState};
+use reos_tauri_lib::{notifications, quick_actions, redact, windows};
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State, WebviewUrl, WebviewWindowBuilder, Window};
+
+/// Nonce for the calling window, required by state-changing commands
+#[tauri::command]
+pub fn window_nonce(window: Window, nonces: State<'_, WindowNonceState>) -> Result<String, String> {
+    let mut nonces = nonces.0.lock_or_recover();
+    Ok(nonces.current(window.label()))
+}
+
+/// Run a kernel-declared app menu item (by id) with the calling window's
+/// session; returns the method's result
+///
+/// The focused window is asked to run it (`app-menu-action`); the method
+/// comes from the kernel's manifest.
+#[tauri::command]
+pub async fn app_menu_run(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    action_id: String,
+) -> Result<Value, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let entry = app
+        .state::<AppMenuState>()
+        .find(&action_id)
+        .ok_or("Unknown menu item")?;
+    let response = forward_declared_method(&app, &auth_state, &session_token, &entry.method)
+        .await
+        .map_err(|e| redact::text(&e))?;
+    if let Some(message) = response_error(&response) {
+        return Err(redact::text(&message));
+    }
+    rpc_result(response)
+}
+
+/// Run a tray quick action (by id) with the calling window's session and
+/// show the outcome as a notification
+///
+/// Only the main window is asked to run actions (`tray-quick-action`); the
+/// method comes from the kernel's manifest and gets the same checks and
+/// request audit as `kernel_request`.
+#[tauri::command]
+pub async fn tray_action_run(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    action_id: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let action = app
+        .state::<QuickActionsState>()
+        .0
+        .lock_or_recover()
+        .iter()
+        .find(|a| a.id == action_id)
+        .cloned()
+        .ok_or("Unknown quick action")?;
+    let result = forward_declared_method(&app, &auth_state, &session_token, &action.method).await;
+    let body = match &result {
+        Ok(response) => match response_error(response) {
+            Some(message) => format!("Failed: {}", redact::text(&message)),
+            None => quick_actions::summarize(response.get("result").unwrap_or(&Value::Null)),
+        },
+        Err(e) => format!("Failed: {}", redact::text(e)),
+    };
+    notifications::notify(
+        &app,
+        Notification::new(Category::QuickAction, action.title, body),
+    );
+    result.map(|_| ()).map_err(|e| redact::text(&e))
+}
+
+/// Open a secondary window (`doc-*`) on `route`, sharing the caller's session
+#[tauri::command]
+pub async fn window_open(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    label: String,
+    route: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    lifecycle_session(&auth_state, &session_token)?;
+    windows::check_label(&label).map_err(|e| e.to_string())?;
+    windows::check_route(&route).map_err(|e| e.to_string())?;
+    let open = app.webview_windows();
+    if open.contains_key(&label) {
+        return Err(WindowError::AlreadyOpen.to_string());
+    }
+    if open.keys().filter(|l| windows::is_secondary(l)).count() >= windows::MAX_SECONDARY_WINDOWS {
+        return Err(WindowError::TooMany.to_string());
+    }
+
+    let registry = &app.state::<WindowsState>().0;
+    registry
+        .lock_or_recover()
+        .offer(&label, SecretString::new(session_token));
+    // Hidden until its saved size and position are applied
+    let opened = WebviewWindowBuilder::new(&app, &label, WebviewUrl::App(route.into()))
+        .title("Talking Rock")
+        .inner_size(1000.0, 700.0)
+        .visible(false)
+        .build()
+        .map_err(|e| {
+            registry.lock_or_recover().remove(&label);
+            format!("Failed to open window: {e}")
+        })?;
+    restore_window_state(&opened);
+    opened.show().map_err(|e| e.to_string())
+}
+
+/// Close a secondary window
+#[tauri::command]
+pub fn window_close(
+    app: AppHandle,
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    window_nonce: String,
+    label: String,
+) -> Result<(), String> {
+    check_window_nonce(&window, &window_nonce)?;
+    lifecycle_session(&auth_state, &session_token)?;
+    windows::check_label(&label).map_err(|e| e.to_string())?;
+    match app.get_webview_window(&label) {
+        Some(target) => target.close().map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+/// Hand the opener's session to a window opened by `window_open` (once)
+#[tauri::command]
+pub fn window_handoff(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    windows_state: State<'_, WindowsState>,
+    window_nonce: String,
+) -> Result<SessionHandoff, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    let session_token = windows_state
+        .0
+        .lock_or_recover()
+        .claim(window.label())
+        .map_err(|e| e.to_string())?;
+    let info = auth_state
+        .read()
+        .check(session_token.expose())
+        .map_err(|e| e.to_string())?;
+    Ok(SessionHandoff {
+        session_token: session_token.expose().to_string(),
+        username: info.username,
+    })
+}
+
+/// Take the link held for login, for a window that loads after it was sent
+#[tauri::command]
+pub fn deep_link_take(
+    window: Window,
+    auth_state: State<'_, AuthState>,
+    deep_link_state: State<'_, DeepLinkState>,
+    session_token: String,
+    window_nonce: String,
+) -> Result<Option<DeepLink>, String> {
+    check_window_nonce(&window, &window_nonce)?;
+    lifecycle_session(&auth_state, &session_token)?;
+    Ok(deep_link_state.pending.lock_or_recover().take())
+}
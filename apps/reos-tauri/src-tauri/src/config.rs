@@ -0,0 +1,826 @@
+//! User Configuration File
+//!
+//! General settings live in `config.toml` in the platform config dir
+//! (`$XDG_CONFIG_HOME/<identifier>` on Linux, `~/Library/Application
+//! Support/<identifier>` on macOS, `%APPDATA%\<identifier>` on Windows);
+//! `config_path` tells users where that is. Every key is optional:
+//!
+//! ```toml
+//! [kernel]
+//! python = "/opt/reos/venv/bin/python"  # else a repo .venv or PATH
+//! start = "eager"                       # eager, on_login or on_first_request
+//! transport = "stdio"                   # stdio, unix, pipe or remote (kernel_transport.rs)
+//! backend = "process"                   # process, mock or embedded (kernel_backend.rs)
+//!
+//! [session]
+//! idle_timeout_secs = 900
+//!
+//! [logging]
+//! level = "info"                        # targets not listed; RUST_LOG wins
+//!
+//! [ui]
+//! theme = "system"                      # system, light or dark
+//!
+//! [features]                            # see features.rs
+//! streaming = true
+//! ```
+//!
+//! A missing file means defaults. A file that doesn't parse or validate is
+//! ignored as a whole (defaults again) and the error, naming the offending
+//! key, is logged and reported by `config_path` and `health_check`.
+//! Deployment policy (auth, redaction, limits) stays in the JSON files in
+//! the app data dir.
+//!
+//! The settings screen reads and changes these keys one at a time through
+//! `settings_get` / `settings_set` (see `SETTING_KEYS`). A change is
+//! validated, written back atomically (comments in a hand-edited file are
+//! not kept) and announced to every window with `settings-changed`. While
+//! the file on disk has errors it is never overwritten.
+//!
+//! Deployments can set keys without touching the file through `REOS_*`
+//! environment variables (`ENV_OVERRIDES`) and command-line flags (see
+//! `cli.rs`), read once at startup; flags win over variables, both over
+//! the file. Overridden keys can't be changed from the settings screen and
+//! are listed by `health_check`; an override that doesn't validate is
+//! ignored and reported.
+//!
+//! Edits made while the app runs are picked up within `WATCH_INTERVAL`:
+//! the file is re-validated, log level, idle timeout and theme apply at
+//! once, and `config://changed` lists the keys that changed and those that
+//! need a restart (`kernel.python`, `kernel.transport`, `kernel.backend`). `rate-limits.json`
+//! is watched too.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+
+use crate::autostart::AutostartStatus;
+use crate::features::{self, FeatureSetting};
+use crate::kernel_backend::Backend;
+use crate::kernel_lifecycle::StartPolicy;
+use crate::kernel_transport::Transport;
+use crate::locks::Recover;
+use crate::logging;
+use crate::storage;
+
+/// File name in the config dir
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Event emitted to all windows with the new `Settings` after a change
+pub const SETTINGS_CHANGED_EVENT: &str = "settings-changed";
+
+/// Launch-at-login key of `settings_set` (OS state, not in `config.toml`)
+pub const AUTOSTART_KEY: &str = "autostart";
+
+/// Keys `settings_set` changes in `config.toml`
+pub const SETTING_KEYS: &[&str] = &[
+    "kernel.python",
+    "kernel.start",
+    "kernel.transport",
+    "kernel.backend",
+    "session.idle_timeout_secs",
+    "logging.level",
+    "ui.theme",
+];
+
+/// Key prefixes of settings that apply to every user (the kernel that gets
+/// spawned, session policy): admins only, with a recent password
+pub const ADMIN_SETTING_PREFIXES: &[&str] = &["kernel.", "session."];
+
+/// Environment variables layered over `config.toml`, and their keys
+/// (later entries win)
+pub const ENV_OVERRIDES: &[(&str, &str)] = &[
+    ("REOS_PYTHON", "kernel.python"),
+    ("REOS_KERNEL_PATH", "kernel.python"),
+    ("REOS_KERNEL_START", "kernel.start"),
+    ("REOS_KERNEL_TRANSPORT", "kernel.transport"),
+    ("REOS_KERNEL_BACKEND", "kernel.backend"),
+    ("REOS_SESSION_TIMEOUT", "session.idle_timeout_secs"),
+    ("REOS_LOG_LEVEL", "logging.level"),
+    ("REOS_THEME", "ui.theme"),
+];
+
+/// Keys that only take effect after a restart (the interpreter, the
+/// transport and the backend are picked when the kernel starts)
+const RESTART_KEYS: &[&str] = &["kernel.python", "kernel.transport", "kernel.backend"];
+
+/// Event emitted to all windows after `config.toml` or `rate-limits.json`
+/// was edited outside the app, with a `ConfigChange`
+pub const CONFIG_CHANGED_EVENT: &str = "config://changed";
+
+/// `ConfigChange` key for an edited `rate-limits.json`
+pub const RATE_LIMITS_KEY: &str = "rate_limits";
+
+/// `ConfigChange` key for changed `[features]` entries
+pub const FEATURES_KEY: &str = "features";
+
+/// How often the watched config files are checked for edits
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Bounds of `session.idle_timeout_secs`
+const MIN_IDLE_TIMEOUT_SECS: u64 = 60;
+const MAX_IDLE_TIMEOUT_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("cannot read {path}: {message}")]
+    Read { path: String, message: String },
+    #[error("invalid TOML: {0}")]
+    Syntax(String),
+    #[error("{key}: {message}")]
+    Invalid { key: String, message: String },
+    #[error("Unknown setting {0}")]
+    UnknownKey(String),
+    #[error("{CONFIG_FILE} has errors; fix it before changing settings")]
+    FileInvalid,
+    #[error("No config directory on this platform")]
+    NoConfigDir,
+    #[error("cannot write {CONFIG_FILE}: {0}")]
+    Write(String),
+    #[error("{key} is set by {by} and can't be changed here")]
+    Overridden { key: String, by: &'static str },
+}
+
+impl ConfigError {
+    fn invalid(key: &str, message: impl Into<String>) -> Self {
+        Self::Invalid {
+            key: key.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct KernelSettings {
+    /// Python interpreter for the kernel
+    pub python: Option<PathBuf>,
+    /// When the kernel is started (see `kernel_lifecycle.rs`)
+    pub start: StartPolicy,
+    /// How the shell talks to it (see `kernel_transport.rs`)
+    pub transport: Transport,
+    /// What answers its requests (see `kernel_backend.rs`)
+    pub backend: Backend,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionSettings {
+    /// Inactivity before a session expires (or locks, with quick unlock)
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        Self {
+            idle_timeout_secs: 15 * 60,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingSettings {
+    /// Level for targets not listed (`None` = built-in default)
+    pub level: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiSettings {
+    pub theme: Theme,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    pub kernel: KernelSettings,
+    pub session: SessionSettings,
+    pub logging: LoggingSettings,
+    pub ui: UiSettings,
+    /// Feature flag overrides by flag name
+    pub features: BTreeMap<String, FeatureSetting>,
+}
+
+impl AppConfig {
+    /// Parse and validate `config.toml` contents
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let config: Self = serde_path_to_error::deserialize(toml::Deserializer::new(text))
+            .map_err(|e| {
+                let key = e.path().to_string();
+                let message = e.inner().message().to_string();
+                // Syntax errors happen before any key is reached
+                if key == "." {
+                    ConfigError::Syntax(message)
+                } else {
+                    ConfigError::invalid(&key, message)
+                }
+            })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Range and format checks serde can't express
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(python) = &self.kernel.python {
+            check_python(python).map_err(|e| ConfigError::invalid("kernel.python", e))?;
+        }
+        let timeout = self.session.idle_timeout_secs;
+        if !(MIN_IDLE_TIMEOUT_SECS..=MAX_IDLE_TIMEOUT_SECS).contains(&timeout) {
+            return Err(ConfigError::invalid(
+                "session.idle_timeout_secs",
+                format!("must be between {MIN_IDLE_TIMEOUT_SECS} and {MAX_IDLE_TIMEOUT_SECS}"),
+            ));
+        }
+        if let Some(level) = &self.logging.level {
+            if !logging::is_level(level) {
+                return Err(ConfigError::invalid(
+                    "logging.level",
+                    format!("unknown level {level:?}"),
+                ));
+            }
+        }
+        for (name, setting) in &self.features {
+            let key = format!("{FEATURES_KEY}.{name}");
+            if !features::is_valid_name(name) {
+                return Err(ConfigError::invalid(
+                    &key,
+                    "flag names use lowercase letters, digits and _",
+                ));
+            }
+            if let FeatureSetting::Users(users) = setting {
+                if users.iter().any(|user| user.trim().is_empty()) {
+                    return Err(ConfigError::invalid(&key, "usernames must not be empty"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Value of one of `SETTING_KEYS` as JSON (`Null` if unset or unknown)
+    pub fn get(&self, key: &str) -> Value {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|config| {
+                config
+                    .pointer(&format!("/{}", key.replace('.', "/")))
+                    .cloned()
+            })
+            .unwrap_or(Value::Null)
+    }
+
+    /// Set one of `SETTING_KEYS` from its JSON value (`null` clears
+    /// optional keys), then validate the whole config
+    pub fn set(&mut self, key: &str, value: Value) -> Result<(), ConfigError> {
+        match key {
+            "kernel.python" => self.kernel.python = typed(key, value)?,
+            "kernel.start" => self.kernel.start = typed(key, value)?,
+            "kernel.transport" => self.kernel.transport = typed(key, value)?,
+            "kernel.backend" => self.kernel.backend = typed(key, value)?,
+            "session.idle_timeout_secs" => self.session.idle_timeout_secs = typed(key, value)?,
+            "logging.level" => self.logging.level = typed(key, value)?,
+            "ui.theme" => self.ui.theme = typed(key, value)?,
+            _ => return Err(ConfigError::UnknownKey(key.to_string())),
+        }
+        self.validate()
+    }
+}
+
+/// Whether `settings_set` needs an admin session with step-up for `key`
+pub fn is_admin_setting(key: &str) -> bool {
+    ADMIN_SETTING_PREFIXES.iter().any(|p| key.starts_with(p))
+}
+
+/// `kernel.python` must name an executable file by absolute path (it is
+/// spawned on the next kernel start)
+fn check_python(path: &Path) -> Result<(), &'static str> {
+    if path.as_os_str().is_empty() {
+        return Err("must not be empty");
+    }
+    if !path.is_absolute() {
+        return Err("must be an absolute path");
+    }
+    let metadata = std::fs::metadata(path).map_err(|_| "must be an existing file")?;
+    if !metadata.is_file() {
+        return Err("must be an existing file");
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Err("must be executable");
+        }
+    }
+    Ok(())
+}
+
+/// A `REOS_*` variable or command-line flag that sets a key
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Override {
+    /// Variable or flag name
+    pub source: &'static str,
+    pub key: &'static str,
+    pub value: String,
+    /// Why it was ignored, if it was
+    pub error: Option<String>,
+}
+
+impl Override {
+    pub fn new(source: &'static str, key: &'static str, value: &str) -> Self {
+        Self {
+            source,
+            key,
+            value: value.trim().to_string(),
+            error: None,
+        }
+    }
+
+    /// `ENV_OVERRIDES` that `var` has a non-empty value for
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Vec<Self> {
+        ENV_OVERRIDES
+            .iter()
+            .filter_map(|&(name, key)| {
+                let value = var(name).filter(|v| !v.trim().is_empty())?;
+                Some(Self::new(name, key, &value))
+            })
+            .collect()
+    }
+
+    /// The override's text as the JSON value `AppConfig::set` expects
+    fn json(&self) -> Value {
+        match self.value.parse::<u64>() {
+            Ok(n) if self.key == "session.idle_timeout_secs" => Value::from(n),
+            _ => Value::String(self.value.clone()),
+        }
+    }
+}
+
+fn typed<T: DeserializeOwned>(key: &str, value: Value) -> Result<T, ConfigError> {
+    serde_json::from_value(value).map_err(|e| ConfigError::invalid(key, e.to_string()))
+}
+
+/// The loaded configuration and where it came from
+pub struct ConfigState {
+    /// `config.toml` (`None` if the platform has no config dir)
+    pub path: Option<PathBuf>,
+    /// As in the file (what `set` saves)
+    file: Mutex<AppConfig>,
+    /// Why the file was ignored, if it was
+    error: Mutex<Option<ConfigError>>,
+    /// `REOS_*` variables and flags found at startup, lowest priority first
+    pub overrides: Vec<Override>,
+}
+
+impl ConfigState {
+    /// Load `config.toml` from `dir`; missing file means defaults, an
+    /// invalid one defaults plus the error. `flags` go on top of the
+    /// environment's overrides
+    pub fn load(dir: Option<&Path>, flags: Vec<Override>) -> Self {
+        Self::load_with(dir, |var| std::env::var(var).ok(), flags)
+    }
+
+    /// `load` with the environment read through `var`
+    fn load_with(
+        dir: Option<&Path>,
+        var: impl Fn(&str) -> Option<String>,
+        flags: Vec<Override>,
+    ) -> Self {
+        let path = dir.map(|dir| dir.join(CONFIG_FILE));
+        let (config, error) = match read_file(path.as_deref()) {
+            Ok(config) => (config, None),
+            Err(e) => {
+                tracing::error!(error = %e, "ignoring {CONFIG_FILE}");
+                (AppConfig::default(), Some(e))
+            }
+        };
+        // Each override is checked on top of the file and those before it
+        let mut overrides = Override::from_env(var);
+        overrides.extend(flags);
+        let mut effective = config.clone();
+        for over in &mut overrides {
+            let mut updated = effective.clone();
+            match updated.set(over.key, over.json()) {
+                Ok(()) => effective = updated,
+                Err(e) => {
+                    tracing::error!(source = over.source, error = %e, "ignoring override");
+                    over.error = Some(e.to_string());
+                }
+            }
+        }
+        Self {
+            path,
+            file: Mutex::new(config),
+            error: Mutex::new(error),
+            overrides,
+        }
+    }
+
+    /// Current settings: the file with the valid overrides on top
+    pub fn config(&self) -> AppConfig {
+        self.with_overrides(self.file.lock_or_recover().clone())
+    }
+
+    fn with_overrides(&self, mut config: AppConfig) -> AppConfig {
+        for over in self.overrides.iter().filter(|over| over.error.is_none()) {
+            // Passed validation at load, and keys are checked independently
+            let _ = config.set(over.key, over.json());
+        }
+        config
+    }
+
+    /// Why the file on disk is being ignored, if it is
+    pub fn error(&self) -> Option<ConfigError> {
+        self.error.lock_or_recover().clone()
+    }
+
+    /// Re-read the file after an outside edit. A file that no longer loads
+    /// keeps the current settings (not the defaults, unlike at startup)
+    pub fn reload(&self) -> ConfigChange {
+        let before = self.config();
+        match read_file(self.path.as_deref()) {
+            Ok(config) => {
+                *self.file.lock_or_recover() = config;
+                *self.error.lock_or_recover() = None;
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "keeping previous settings, {CONFIG_FILE} is invalid");
+                let changed = self.error.lock_or_recover().replace(e.clone()) != Some(e.clone());
+                return ConfigChange {
+                    error: changed.then(|| e.to_string()),
+                    ..ConfigChange::default()
+                };
+            }
+        }
+        ConfigChange::between(&before, &self.config())
+    }
+
+    /// Keys an environment variable decides
+    pub fn overridden(&self) -> Vec<&'static str> {
+        self.overrides
+            .iter()
+            .filter(|over| over.error.is_none())
+            .map(|over| over.key)
+            .collect()
+    }
+
+    /// Change `key`, save the file and return the new settings; nothing
+    /// changes if validation or the write fails
+    pub fn set(&self, key: &str, value: Value) -> Result<AppConfig, ConfigError> {
+        if self.error.lock_or_recover().is_some() {
+            return Err(ConfigError::FileInvalid);
+        }
+        if let Some(over) = self
+            .overrides
+            .iter()
+            .rev()
+            .find(|over| over.key == key && over.error.is_none())
+        {
+            return Err(ConfigError::Overridden {
+                key: key.to_string(),
+                by: over.source,
+            });
+        }
+        let path = self.path.as_deref().ok_or(ConfigError::NoConfigDir)?;
+        let mut config = self.file.lock_or_recover();
+        let mut updated = config.clone();
+        updated.set(key, value)?;
+        let text =
+            toml::to_string_pretty(&updated).map_err(|e| ConfigError::Write(e.to_string()))?;
+        storage::write_private_file(path, text.as_bytes())
+            .map_err(|e| ConfigError::Write(e.to_string()))?;
+        *config = updated.clone();
+        Ok(self.with_overrides(updated))
+    }
+}
+
+/// `config.toml` contents; a missing file means defaults
+fn read_file(path: Option<&Path>) -> Result<AppConfig, ConfigError> {
+    match path.map(std::fs::read_to_string) {
+        None => Ok(AppConfig::default()),
+        Some(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => Ok(AppConfig::default()),
+        Some(Err(e)) => Err(ConfigError::Read {
+            path: CONFIG_FILE.to_string(),
+            message: e.to_string(),
+        }),
+        Some(Ok(text)) => AppConfig::parse(&text),
+    }
+}
+
+/// What a reload changed (`config://changed` payload)
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct ConfigChange {
+    /// Keys with a new value, applied now
+    pub changed: Vec<&'static str>,
+    /// Changed keys that only take effect after a restart
+    pub restart_required: Vec<&'static str>,
+    /// Why the edited file was rejected (first time it is seen)
+    pub error: Option<String>,
+}
+
+impl ConfigChange {
+    fn between(before: &AppConfig, after: &AppConfig) -> Self {
+        let mut changed: Vec<&'static str> = SETTING_KEYS
+            .iter()
+            .copied()
+            .filter(|key| before.get(key) != after.get(key))
+            .collect();
+        if before.features != after.features {
+            changed.push(FEATURES_KEY);
+        }
+        let restart_required = changed
+            .iter()
+            .copied()
+            .filter(|key| RESTART_KEYS.contains(key))
+            .collect();
+        Self {
+            changed,
+            restart_required,
+            error: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.error.is_none()
+    }
+}
+
+/// `settings_get` / `settings_set` result and `settings-changed` payload
+#[derive(Serialize, Clone)]
+pub struct Settings {
+    #[serde(flatten)]
+    pub config: AppConfig,
+    /// Launch at login (`None` where it can't be read)
+    pub autostart: Option<AutostartStatus>,
+    /// Keys set by `REOS_*` variables (read-only here)
+    pub overridden: Vec<&'static str>,
+}
+
+/// Modification time and size of a watched file (`None` while missing)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileStamp(Option<(SystemTime, u64)>);
+
+impl FileStamp {
+    pub fn of(path: &Path) -> Self {
+        Self(
+            std::fs::metadata(path)
+                .ok()
+                .and_then(|meta| Some((meta.modified().ok()?, meta.len()))),
+        )
+    }
+}
+
+/// `config_path` result
+#[derive(Serialize)]
+pub struct ConfigLocation {
+    pub path: Option<PathBuf>,
+    pub exists: bool,
+    /// Why the file is being ignored, if it is
+    pub error: Option<String>,
+}
+
+impl ConfigLocation {
+    pub fn of(state: &ConfigState) -> Self {
+        Self {
+            path: state.path.clone(),
+            exists: state.path.as_deref().is_some_and(Path::is_file),
+            error: state.error().map(|e| e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_defaults_and_partial_file() {
+        assert_eq!(AppConfig::parse("").unwrap(), AppConfig::default());
+        let config =
+            AppConfig::parse("[session]\nidle_timeout_secs = 300\n\n[ui]\ntheme = \"dark\"\n")
+                .unwrap();
+        assert_eq!(config.session.idle_timeout_secs, 300);
+        assert_eq!(config.ui.theme, Theme::Dark);
+        assert_eq!(config.kernel, KernelSettings::default());
+        let config =
+            AppConfig::parse("[features]\nstreaming = true\nremote_kernel = [\"alice\"]\n")
+                .unwrap();
+        assert_eq!(
+            config.features["remote_kernel"],
+            FeatureSetting::Users(vec!["alice".into()])
+        );
+    }
+
+    #[test]
+    fn test_errors_name_the_key() {
+        let key = |text: &str| match AppConfig::parse(text) {
+            Err(ConfigError::Invalid { key, .. }) => key,
+            other => panic!("unexpected {other:?}"),
+        };
+        assert_eq!(
+            key("[session]\nidle_timeout_secs = \"soon\"\n"),
+            "session.idle_timeout_secs"
+        );
+        assert_eq!(
+            key("[session]\nidle_timeout_secs = 5\n"),
+            "session.idle_timeout_secs"
+        );
+        assert_eq!(key("[ui]\ntheme = \"blue\"\n"), "ui.theme");
+        assert_eq!(key("[kernel]\nstart = \"later\"\n"), "kernel.start");
+        assert_eq!(key("[logging]\nlevel = \"loud\"\n"), "logging.level");
+        assert!(key("[ui]\ncolour = \"red\"\n").starts_with("ui"));
+        assert_eq!(
+            key("[features]\nstreaming = \"yes\"\n"),
+            "features.streaming"
+        );
+        assert_eq!(key("[features]\nBad = true\n"), "features.Bad");
+        assert!(matches!(
+            AppConfig::parse("[session"),
+            Err(ConfigError::Syntax(_))
+        ));
+    }
+
+    #[test]
+    fn test_set_validates_each_key() {
+        let mut config = AppConfig::default();
+        config.set("ui.theme", json!("light")).unwrap();
+        // Any executable will do: the test binary itself
+        let python = std::env::current_exe().unwrap();
+        config.set("kernel.python", json!(python)).unwrap();
+        for bad in [
+            json!("python3"),
+            json!(std::env::temp_dir()),
+            json!("/nonexistent/bin/python3"),
+        ] {
+            assert!(AppConfig::default().set("kernel.python", bad).is_err());
+        }
+        assert_eq!(config.ui.theme, Theme::Light);
+        config.set("kernel.start", json!("on_login")).unwrap();
+        assert_eq!(config.kernel.start, StartPolicy::OnLogin);
+        config.set("kernel.transport", json!("unix")).unwrap();
+        assert_eq!(config.kernel.transport, Transport::Unix);
+        config.set("kernel.transport", json!("pipe")).unwrap();
+        assert_eq!(config.kernel.transport, Transport::Pipe);
+        config.set("kernel.transport", json!("remote")).unwrap();
+        assert_eq!(config.kernel.transport, Transport::Remote);
+        assert!(config.set("kernel.transport", json!("tcp")).is_err());
+        config.set("kernel.backend", json!("mock")).unwrap();
+        assert_eq!(config.kernel.backend, Backend::Mock);
+        config.set("kernel.backend", json!("embedded")).unwrap();
+        assert_eq!(config.kernel.backend, Backend::Embedded);
+        assert!(config.set("kernel.backend", json!("wasm")).is_err());
+        config.set("kernel.python", Value::Null).unwrap();
+        assert_eq!(config.kernel.python, None);
+        assert!(matches!(
+            config.set("session.idle_timeout_secs", json!("long")),
+            Err(ConfigError::Invalid { key, .. }) if key == "session.idle_timeout_secs"
+        ));
+        assert!(config.set("session.idle_timeout_secs", json!(1)).is_err());
+        assert_eq!(
+            config.set("ui.font", json!("serif")),
+            Err(ConfigError::UnknownKey("ui.font".into()))
+        );
+    }
+
+    #[test]
+    fn test_set_persists_and_round_trips() {
+        let dir = std::env::temp_dir().join(format!("reos-settings-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let state = ConfigState::load_with(Some(&dir), |_| None, Vec::new());
+        let config = state.set("session.idle_timeout_secs", json!(600)).unwrap();
+        assert_eq!(config.session.idle_timeout_secs, 600);
+        assert!(state.set("logging.level", json!("loud")).is_err());
+        assert_eq!(state.config(), config);
+        assert_eq!(
+            ConfigState::load_with(Some(&dir), |_| None, Vec::new()).config(),
+            config
+        );
+
+        std::fs::write(dir.join(CONFIG_FILE), "[ui\n").unwrap();
+        let invalid = ConfigState::load_with(Some(&dir), |_| None, Vec::new());
+        assert_eq!(
+            invalid.set("ui.theme", json!("dark")),
+            Err(ConfigError::FileInvalid)
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_overrides_win_over_file() {
+        let dir = std::env::temp_dir().join(format!("reos-config-env-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(CONFIG_FILE),
+            "[session]\nidle_timeout_secs = 600\n[ui]\ntheme = \"dark\"\n",
+        )
+        .unwrap();
+        let flags = vec![Override::new("--log-level", "logging.level", "debug")];
+        let state = ConfigState::load_with(
+            Some(&dir),
+            |var| match var {
+                "REOS_SESSION_TIMEOUT" => Some("120".into()),
+                "REOS_LOG_LEVEL" => Some("chatty".into()),
+                "REOS_THEME" => Some("  ".into()),
+                _ => None,
+            },
+            flags,
+        );
+        let config = state.config();
+        assert_eq!(config.session.idle_timeout_secs, 120);
+        assert_eq!(config.ui.theme, Theme::Dark);
+        // The invalid variable is skipped, the flag applies
+        assert_eq!(config.logging.level.as_deref(), Some("debug"));
+        assert_eq!(
+            state.overridden(),
+            vec!["session.idle_timeout_secs", "logging.level"]
+        );
+        let bad = state
+            .overrides
+            .iter()
+            .find(|o| o.source == "REOS_LOG_LEVEL");
+        assert!(bad
+            .unwrap()
+            .error
+            .as_deref()
+            .unwrap()
+            .starts_with("logging.level"));
+
+        assert!(matches!(
+            state.set("session.idle_timeout_secs", json!(300)),
+            Err(ConfigError::Overridden {
+                by: "REOS_SESSION_TIMEOUT",
+                ..
+            })
+        ));
+        let saved = state.set("ui.theme", json!("light")).unwrap();
+        assert_eq!(saved.session.idle_timeout_secs, 120);
+        assert_eq!(
+            ConfigState::load_with(Some(&dir), |_| None, Vec::new())
+                .config()
+                .session
+                .idle_timeout_secs,
+            600
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reload_reports_changed_keys() {
+        let dir = std::env::temp_dir().join(format!("reos-config-reload-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let state = ConfigState::load_with(Some(&dir), |_| None, Vec::new());
+        assert!(state.reload().is_empty());
+
+        let python = std::env::current_exe().unwrap();
+        std::fs::write(
+            dir.join(CONFIG_FILE),
+            format!("[kernel]\npython = {python:?}\n[ui]\ntheme = \"dark\"\n"),
+        )
+        .unwrap();
+        let change = state.reload();
+        assert_eq!(change.changed, vec!["kernel.python", "ui.theme"]);
+        assert_eq!(change.restart_required, vec!["kernel.python"]);
+
+        std::fs::write(dir.join(CONFIG_FILE), "[ui]\ntheme = 1\n").unwrap();
+        let change = state.reload();
+        assert!(change.changed.is_empty());
+        assert!(change.error.unwrap().starts_with("ui.theme"));
+        assert_eq!(state.config().ui.theme, Theme::Dark);
+        assert!(state.reload().is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_load_from_dir() {
+        let dir = std::env::temp_dir().join(format!("reos-config-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let missing = ConfigState::load_with(Some(&dir), |_| None, Vec::new());
+        assert!(missing.error().is_none());
+        assert!(!ConfigLocation::of(&missing).exists);
+
+        std::fs::write(dir.join(CONFIG_FILE), "[kernel]\npython = 3\n").unwrap();
+        let invalid = ConfigState::load_with(Some(&dir), |_| None, Vec::new());
+        assert_eq!(invalid.config(), AppConfig::default());
+        let location = ConfigLocation::of(&invalid);
+        assert!(location.exists);
+        assert!(location.error.unwrap().starts_with("kernel.python"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,155 @@
+//! User Consent for Dangerous Kernel Methods
+//!
+//! Methods that run commands, delete files or reach the network need the
+//! user's explicit approval the first time a session uses them. The prompt
+//! is a native dialog owned by Rust, so a compromised webview can neither
+//! skip nor answer it. Grants are cached per session and category, and die
+//! with the session.
+//!
+//! Configurable via `consent.json` in the app data dir:
+//! ```json
+//! { "rules": [ { "name": "shell", "methods": ["tools/call"],
+//!                "description": "run commands on this computer" } ] }
+//! ```
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::methods::method_matches;
+
+/// JSON-RPC error code when the user declines a consent prompt
+pub const CONSENT_DENIED_CODE: i64 = -32406;
+
+/// A category of dangerous methods approved together
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ConsentRule {
+    /// Grant key, e.g. `shell`
+    pub name: String,
+    /// Method names or `prefix*` patterns
+    pub methods: Vec<String>,
+    /// Completes "ReOS wants to ..." in the prompt
+    pub description: String,
+}
+
+impl ConsentRule {
+    fn new(name: &str, methods: &[&str], description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            methods: methods.iter().map(|m| m.to_string()).collect(),
+            description: description.to_string(),
+        }
+    }
+}
+
+/// Deployment policy for consent prompts
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct ConsentPolicy {
+    pub rules: Vec<ConsentRule>,
+}
+
+impl Default for ConsentPolicy {
+    fn default() -> Self {
+        Self {
+            rules: vec![
+                ConsentRule::new(
+                    "shell",
+                    &[
+                        "tools/call",
+                        "code/exec/start",
+                        "execution/start",
+                        "system/open-terminal",
+                    ],
+                    "run commands on this computer",
+                ),
+                ConsentRule::new(
+                    "delete",
+                    &["archive/delete", "play/attachments/remove"],
+                    "delete files",
+                ),
+                ConsentRule::new(
+                    "network",
+                    &["ollama/pull_*", "thunderbird/configure"],
+                    "download data and connect to network services",
+                ),
+            ],
+        }
+    }
+}
+
+impl ConsentPolicy {
+    /// Load the policy from `path` (missing or invalid file = defaults)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// The rule requiring consent for `method`, if any
+    pub fn rule_for(&self, method: &str) -> Option<&ConsentRule> {
+        self.rules
+            .iter()
+            .find(|rule| rule.methods.iter().any(|p| method_matches(p, method)))
+    }
+}
+
+/// Consent policy state (read-only after startup)
+pub struct ConsentState(pub ConsentPolicy);
+
+/// Ask the user with a native dialog (blocking; call off the async runtime)
+pub fn ask(app: &AppHandle, rule: &ConsentRule, username: &str, method: &str) -> bool {
+    app.dialog()
+        .message(format!(
+            "ReOS wants to {} ({method}) for {username}.\n\n\
+             Allow this for the rest of the session?",
+            rule.description
+        ))
+        .title("Allow this action?")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Allow".to_string(),
+            "Deny".to_string(),
+        ))
+        .blocking_show()
+}
+
+/// JSON-RPC error response for a declined prompt
+pub fn denied_response(rule: &ConsentRule, method: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": null,
+        "error": {
+            "code": CONSENT_DENIED_CODE,
+            "message": format!("You declined to allow ReOS to {}", rule.description),
+            "data": { "reason": "consent_denied", "method": method, "category": rule.name },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_for() {
+        let policy = ConsentPolicy::default();
+        assert_eq!(policy.rule_for("tools/call").unwrap().name, "shell");
+        assert_eq!(
+            policy.rule_for("ollama/pull_start").unwrap().name,
+            "network"
+        );
+        assert!(policy.rule_for("chat/respond").is_none());
+    }
+
+    #[test]
+    fn test_denied_response() {
+        let policy = ConsentPolicy::default();
+        let response =
+            denied_response(policy.rule_for("archive/delete").unwrap(), "archive/delete");
+        assert_eq!(response["error"]["code"], CONSENT_DENIED_CODE);
+        assert_eq!(response["error"]["data"]["category"], "delete");
+    }
+}
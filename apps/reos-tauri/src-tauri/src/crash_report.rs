@@ -0,0 +1,407 @@
+//! Opt-in Crash Reports for ReOS
+//!
+//! The panic hook saves a report to `crash-reports/<id>.json` in the app
+//! data dir: the panic message and location, a backtrace, the app and
+//! kernel versions and the last `REPORT_LOG_LINES` log lines. The message
+//! and backtrace are redacted like the log lines already are.
+//!
+//! Reports never leave the machine on their own: `crash_report_submit`
+//! shows a native dialog (owned by Rust, so the webview can neither skip
+//! nor answer it) and posts the report to the HTTPS endpoint only when
+//! the user agrees. Without an endpoint reports are only kept locally.
+//!
+//! Configurable via `crash-reports.json` in the app data dir:
+//! `{ "endpoint": "https://crash.example.com/reports", "keep": 20 }`
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::backtrace::Backtrace;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError, TryLockError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use thiserror::Error;
+use url::Url;
+
+use crate::logging;
+use crate::redact;
+
+/// Log lines included in a report
+pub const REPORT_LOG_LINES: usize = 100;
+
+/// Reports kept on disk by default (oldest are removed first)
+const DEFAULT_KEEP: usize = 20;
+
+/// Longest message shown in listings and the consent dialog
+const SUMMARY_LEN: usize = 200;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+static KERNEL_VERSION: Mutex<Option<String>> = Mutex::new(None);
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CrashReportError {
+    #[error("Crash reporting is not configured")]
+    Disabled,
+    #[error("Crash report endpoint must use https")]
+    InsecureUrl,
+    #[error("No crash report {0}")]
+    NotFound(String),
+    #[error("Invalid crash report: {0}")]
+    Malformed(String),
+    #[error("You declined to send the crash report")]
+    Declined,
+    #[error("Failed to send crash report: {0}")]
+    Submit(String),
+    #[error("Failed to save crash report: {0}")]
+    Save(String),
+}
+
+/// Where reports may be sent and how many are kept
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct CrashReportConfig {
+    pub endpoint: Option<String>,
+    pub keep: usize,
+}
+
+impl Default for CrashReportConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            keep: DEFAULT_KEEP,
+        }
+    }
+}
+
+impl CrashReportConfig {
+    /// Load the config from `path` (missing or invalid file = local only)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// The submission endpoint, if one is configured and uses https
+    pub fn endpoint(&self) -> Result<Url, CrashReportError> {
+        let endpoint = self.endpoint.as_deref().ok_or(CrashReportError::Disabled)?;
+        let url = Url::parse(endpoint).map_err(|_| CrashReportError::InsecureUrl)?;
+        if url.scheme() != "https" || url.host_str().is_none() {
+            return Err(CrashReportError::InsecureUrl);
+        }
+        Ok(url)
+    }
+}
+
+/// Record the kernel version from its `initialize` result
+pub fn set_kernel_version(ready: &Value) {
+    let version = ready
+        .pointer("/serverInfo/version")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    *KERNEL_VERSION
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = version;
+}
+
+/// Last reported kernel version (`None` if the lock is held, as it may
+/// be by the panicking thread)
+pub fn kernel_version() -> Option<String> {
+    match KERNEL_VERSION.try_lock() {
+        Ok(version) => version.clone(),
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner().clone(),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Report ids are `<unix seconds>-<hex>`; anything else never touches disk
+fn is_valid_id(id: &str) -> bool {
+    match id.split_once('-') {
+        Some((secs, rand)) => {
+            !secs.is_empty()
+                && secs.len() <= 20
+                && secs.bytes().all(|b| b.is_ascii_digit())
+                && !rand.is_empty()
+                && rand.len() <= 32
+                && rand.bytes().all(|b| b.is_ascii_hexdigit())
+        }
+        None => false,
+    }
+}
+
+/// One saved panic
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CrashReport {
+    pub id: String,
+    pub created_at: u64,
+    pub app_version: String,
+    pub kernel_version: Option<String>,
+    pub os: String,
+    pub thread: String,
+    pub location: String,
+    pub message: String,
+    pub backtrace: String,
+    pub log_lines: Vec<String>,
+    /// When the user sent it (unix seconds)
+    #[serde(default)]
+    pub submitted_at: Option<u64>,
+}
+
+impl CrashReport {
+    /// Capture a report for a panic on the current thread
+    pub fn capture(app_version: &str, thread: &str, location: &str, message: &str) -> Self {
+        let created_at = now_secs();
+        let mut suffix = [0u8; 4];
+        rand::thread_rng().fill_bytes(&mut suffix);
+        Self {
+            id: format!("{created_at}-{}", hex::encode(suffix)),
+            created_at,
+            app_version: app_version.to_string(),
+            kernel_version: kernel_version(),
+            os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            thread: thread.to_string(),
+            location: location.to_string(),
+            message: redact::text(message),
+            backtrace: redact::text(&Backtrace::force_capture().to_string()),
+            log_lines: logging::recent_lines(REPORT_LOG_LINES),
+            submitted_at: None,
+        }
+    }
+
+    pub fn summary(&self) -> CrashSummary {
+        let first_line = self.message.lines().next().unwrap_or_default();
+        CrashSummary {
+            id: self.id.clone(),
+            created_at: self.created_at,
+            app_version: self.app_version.clone(),
+            message: first_line.chars().take(SUMMARY_LEN).collect(),
+            submitted_at: self.submitted_at,
+        }
+    }
+}
+
+/// A report as listed for the user
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct CrashSummary {
+    pub id: String,
+    pub created_at: u64,
+    pub app_version: String,
+    pub message: String,
+    pub submitted_at: Option<u64>,
+}
+
+/// Reports saved on disk
+pub struct CrashReports {
+    dir: Option<PathBuf>,
+    config: CrashReportConfig,
+}
+
+impl CrashReports {
+    pub fn new(dir: Option<PathBuf>, config: CrashReportConfig) -> Self {
+        Self { dir, config }
+    }
+
+    pub fn config(&self) -> &CrashReportConfig {
+        &self.config
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf, CrashReportError> {
+        match &self.dir {
+            Some(dir) if is_valid_id(id) => Ok(dir.join(format!("{id}.json"))),
+            _ => Err(CrashReportError::NotFound(id.to_string())),
+        }
+    }
+
+    /// Write `report` and drop the oldest beyond `keep`
+    pub fn save(&self, report: &CrashReport) -> io::Result<PathBuf> {
+        let path = self
+            .path(&report.id)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_vec_pretty(report).map_err(io::Error::from)?;
+        fs::write(&path, json)?;
+        self.prune();
+        Ok(path)
+    }
+
+    fn prune(&self) {
+        let reports = self.load_all();
+        for report in reports.iter().skip(self.config.keep.max(1)) {
+            if let Ok(path) = self.path(&report.id) {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+
+    /// Every readable report, newest first
+    fn load_all(&self) -> Vec<CrashReport> {
+        let Some(entries) = self.dir.as_ref().and_then(|dir| fs::read_dir(dir).ok()) else {
+            return Vec::new();
+        };
+        let mut reports: Vec<CrashReport> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                let id = name.to_str()?.strip_suffix(".json")?;
+                self.get(id).ok()
+            })
+            .collect();
+        reports.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+        reports
+    }
+
+    /// Summaries of saved reports, newest first
+    pub fn list(&self) -> Vec<CrashSummary> {
+        self.load_all().iter().map(CrashReport::summary).collect()
+    }
+
+    pub fn get(&self, id: &str) -> Result<CrashReport, CrashReportError> {
+        let path = self.path(id)?;
+        let text =
+            fs::read_to_string(path).map_err(|_| CrashReportError::NotFound(id.to_string()))?;
+        let report: CrashReport =
+            serde_json::from_str(&text).map_err(|e| CrashReportError::Malformed(e.to_string()))?;
+        if report.id != id {
+            return Err(CrashReportError::Malformed("id mismatch".into()));
+        }
+        Ok(report)
+    }
+
+    /// Remember that `id` was sent (now)
+    pub fn mark_submitted(&self, id: &str) -> Result<CrashSummary, CrashReportError> {
+        let mut report = self.get(id)?;
+        report.submitted_at = Some(now_secs());
+        self.save(&report)
+            .map_err(|e| CrashReportError::Save(e.to_string()))?;
+        Ok(report.summary())
+    }
+}
+
+/// Crash report storage (read-only after startup)
+pub struct CrashReportState(pub CrashReports);
+
+/// Ask the user whether to send `report` (blocking; call off the async runtime)
+pub fn ask(app: &AppHandle, report: &CrashReport, endpoint: &Url) -> bool {
+    app.dialog()
+        .message(format!(
+            "Send this crash report to {}?\n\n{}\n\n\
+             It includes a backtrace, the app and kernel versions and the \
+             last {} log lines, with secrets redacted.",
+            endpoint.host_str().unwrap_or_default(),
+            report.summary().message,
+            report.log_lines.len(),
+        ))
+        .title("Send crash report?")
+        .kind(MessageDialogKind::Info)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Send".to_string(),
+            "Don't Send".to_string(),
+        ))
+        .blocking_show()
+}
+
+/// Post a report to the endpoint (blocking)
+pub fn submit(endpoint: &Url, report: &CrashReport) -> Result<(), CrashReportError> {
+    ureq::AgentBuilder::new()
+        .timeout(HTTP_TIMEOUT)
+        .build()
+        .post(endpoint.as_str())
+        .send_json(report)
+        .map(|_| ())
+        .map_err(|e| CrashReportError::Submit(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("reos-crash-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn report(created_at: u64, message: &str) -> CrashReport {
+        CrashReport {
+            id: format!("{created_at}-ab12"),
+            created_at,
+            ..CrashReport::capture("1.0.0", "main", "src/main.rs:1", message)
+        }
+    }
+
+    #[test]
+    fn test_ids() {
+        assert!(is_valid_id("1700000000-0a1b2c3d"));
+        assert!(!is_valid_id("../../etc/passwd"));
+        assert!(!is_valid_id("1700000000-"));
+        assert!(!is_valid_id("1700000000-0a/.."));
+        assert!(!is_valid_id("abc-0a1b"));
+    }
+
+    #[test]
+    fn test_endpoint_requires_https() {
+        let mut config = CrashReportConfig::default();
+        assert_eq!(config.endpoint(), Err(CrashReportError::Disabled));
+        config.endpoint = Some("http://crash.example.com/".into());
+        assert_eq!(config.endpoint(), Err(CrashReportError::InsecureUrl));
+        config.endpoint = Some("https://crash.example.com/reports".into());
+        assert!(config.endpoint().is_ok());
+    }
+
+    #[test]
+    fn test_save_list_and_prune() {
+        let dir = temp_dir("store");
+        let reports = CrashReports::new(
+            Some(dir.clone()),
+            CrashReportConfig {
+                endpoint: None,
+                keep: 2,
+            },
+        );
+        for (at, message) in [(1, "first"), (3, "third"), (2, "second\nmore")] {
+            reports.save(&report(at, message)).unwrap();
+        }
+        let listed = reports.list();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].message, "third");
+        assert_eq!(listed[1].message, "second");
+        assert_eq!(
+            reports.get("1-ab12"),
+            Err(CrashReportError::NotFound("1-ab12".into()))
+        );
+
+        assert!(reports
+            .mark_submitted("3-ab12")
+            .unwrap()
+            .submitted_at
+            .is_some());
+        assert!(reports.get("3-ab12").unwrap().submitted_at.is_some());
+        assert!(matches!(
+            reports.get("../3-ab12"),
+            Err(CrashReportError::NotFound(_))
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_capture_redacts_and_reads_kernel_version() {
+        set_kernel_version(&serde_json::json!({ "serverInfo": { "version": "0.1.0" } }));
+        let report = CrashReport::capture("1.0.0", "main", "src/x.rs:1", "password=hunter2");
+        assert_eq!(report.kernel_version.as_deref(), Some("0.1.0"));
+        assert!(!report.message.contains("hunter2"));
+        assert!(is_valid_id(&report.id));
+    }
+}
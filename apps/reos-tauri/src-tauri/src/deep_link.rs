@@ -0,0 +1,250 @@
+//! `reos://` Deep Links for ReOS
+//!
+//! A link names a route, e.g. `reos://open/conversation/42`. Routes map to
+//! a kernel method and the parameter their `{id}` segment fills in; links
+//! that don't match a route exactly, or carry anything else (credentials,
+//! ports, queries), are rejected.
+//!
+//! Valid links go to the main window as `deep-link` events and the frontend
+//! sends them through `kernel_request`, so session, allowlist, role and
+//! consent checks all apply. With no active session the link is held,
+//! `deep-link-login-required` is emitted, and the link follows once a
+//! session is created or unlocked.
+//!
+//! Links arrive as a command-line argument (the desktop entry registers
+//! `x-scheme-handler/reos`). A second launch forwards its arguments, link
+//! included, to the running instance (see `single_instance`) and exits.
+//!
+//! Routes are configurable via `deep-links.json` in the app data dir:
+//! `{ "routes": [{ "pattern": "open/note/{id}", "method": "notes/get", "param": "note_id" }] }`
+//!
+//! Anything that can open a URL can fire a link, and the frontend dispatches
+//! it without asking, so the default routes only read. Mapping a route to a
+//! method that changes state is the config author's call.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::Mutex;
+use thiserror::Error;
+use url::Url;
+
+pub const SCHEME: &str = "reos";
+
+/// Event carrying a validated link to the main window
+pub const DEEP_LINK_EVENT: &str = "deep-link";
+
+/// Event asking the main window to log in before a held link is dispatched
+pub const LOGIN_REQUIRED_EVENT: &str = "deep-link-login-required";
+
+/// Placeholder for the link's argument in route patterns
+const ID_PLACEHOLDER: &str = "{id}";
+
+const MAX_URL_LEN: usize = 2048;
+const MAX_ID_LEN: usize = 128;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DeepLinkError {
+    #[error("Malformed deep link")]
+    Malformed,
+    #[error("Unknown deep link route")]
+    UnknownRoute,
+    #[error("Invalid deep link argument")]
+    InvalidArgument,
+}
+
+/// A route: `pattern` segments, with `{id}` passed to `method` as `param`
+#[derive(Deserialize, Clone)]
+pub struct DeepLinkRoute {
+    pub pattern: String,
+    pub method: String,
+    pub param: String,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct DeepLinkConfig {
+    pub routes: Vec<DeepLinkRoute>,
+}
+
+impl Default for DeepLinkConfig {
+    fn default() -> Self {
+        let route = |pattern: &str, method: &str, param: &str| DeepLinkRoute {
+            pattern: pattern.to_string(),
+            method: method.to_string(),
+            param: param.to_string(),
+        };
+        Self {
+            routes: vec![
+                route(
+                    "open/conversation/{id}",
+                    "conversation/get_messages",
+                    "conversation_id",
+                ),
+                route("open/persona/{id}", "personas/get", "persona_id"),
+                route("open/act/{id}", "play/scenes/list", "act_id"),
+            ],
+        }
+    }
+}
+
+impl DeepLinkConfig {
+    /// Load the config from `path` (missing or invalid file = defaults)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// Validate `url` and resolve it to a kernel call
+    pub fn resolve(&self, url: &str) -> Result<DeepLink, DeepLinkError> {
+        if url.len() > MAX_URL_LEN {
+            return Err(DeepLinkError::Malformed);
+        }
+        let parsed = Url::parse(url).map_err(|_| DeepLinkError::Malformed)?;
+        if parsed.scheme() != SCHEME
+            || !parsed.username().is_empty()
+            || parsed.password().is_some()
+            || parsed.port().is_some()
+            || parsed.query().is_some()
+        {
+            return Err(DeepLinkError::Malformed);
+        }
+        let host = parsed.host_str().ok_or(DeepLinkError::Malformed)?;
+        let segments: Vec<&str> = std::iter::once(host)
+            .chain(parsed.path_segments().into_iter().flatten())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        for route in &self.routes {
+            let pattern: Vec<&str> = route.pattern.split('/').collect();
+            if pattern.len() != segments.len() {
+                continue;
+            }
+            let mut id = None;
+            let matched = pattern.iter().zip(&segments).all(|(p, s)| {
+                if *p == ID_PLACEHOLDER {
+                    id = Some(*s);
+                    true
+                } else {
+                    p == s
+                }
+            });
+            if !matched {
+                continue;
+            }
+            let id = id.ok_or(DeepLinkError::UnknownRoute)?;
+            if !valid_id(id) {
+                return Err(DeepLinkError::InvalidArgument);
+            }
+            return Ok(DeepLink {
+                url: url.to_string(),
+                method: route.method.clone(),
+                params: json!({ route.param.as_str(): id }),
+            });
+        }
+        Err(DeepLinkError::UnknownRoute)
+    }
+}
+
+/// Ids are plain tokens: no separators, escapes or dots
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_ID_LEN
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// A validated link, ready for `kernel_request`
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct DeepLink {
+    pub url: String,
+    pub method: String,
+    pub params: Value,
+}
+
+/// Route table plus a link waiting for login
+pub struct DeepLinkState {
+    pub config: DeepLinkConfig,
+    pub pending: Mutex<Option<DeepLink>>,
+}
+
+impl DeepLinkState {
+    pub fn new(config: DeepLinkConfig) -> Self {
+        Self {
+            config,
+            pending: Mutex::new(None),
+        }
+    }
+}
+
+/// The `reos://` argument the app was launched with, if any
+pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<String> {
+    let prefix = format!("{SCHEME}://");
+    args.into_iter().skip(1).find(|a| a.starts_with(&prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_routes_only_read() {
+        for route in DeepLinkConfig::default().routes {
+            let verb = route.method.rsplit(['/', '_']).next().unwrap_or_default();
+            assert!(
+                matches!(verb, "get" | "list" | "messages"),
+                "{} dispatches {}",
+                route.pattern,
+                route.method
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolves_default_route() {
+        let config = DeepLinkConfig::default();
+        assert_eq!(
+            config.resolve("reos://open/conversation/abc-123"),
+            Ok(DeepLink {
+                url: "reos://open/conversation/abc-123".to_string(),
+                method: "conversation/get_messages".to_string(),
+                params: json!({ "conversation_id": "abc-123" }),
+            })
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed_and_unknown_links() {
+        let config = DeepLinkConfig::default();
+        for url in [
+            "https://open/conversation/1",
+            "reos://user:pw@open/conversation/1",
+            "reos://open:8080/conversation/1",
+            "reos://open/conversation/1?method=auth/login",
+            "not a url",
+        ] {
+            assert_eq!(config.resolve(url), Err(DeepLinkError::Malformed), "{url}");
+        }
+        assert_eq!(
+            config.resolve("reos://open/conversation"),
+            Err(DeepLinkError::UnknownRoute)
+        );
+        assert_eq!(
+            config.resolve("reos://open/vault/1"),
+            Err(DeepLinkError::UnknownRoute)
+        );
+        assert_eq!(
+            config.resolve("reos://open/conversation/..%2Fx"),
+            Err(DeepLinkError::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_from_args() {
+        let args = ["reos", "--flag", "reos://open/act/7"].map(String::from);
+        assert_eq!(from_args(args), Some("reos://open/act/7".to_string()));
+        assert_eq!(from_args(["reos".to_string()]), None);
+    }
+}
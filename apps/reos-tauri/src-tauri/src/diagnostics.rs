@@ -0,0 +1,250 @@
+//! Diagnostics Bundles for Bug Reports
+//!
+//! `diagnostics_export` zips what a maintainer needs to make sense of "it
+//! doesn't work" into one file chosen with the native save dialog:
+//!
+//! - `versions.json`: app, kernel and Tauri versions, OS and architecture
+//! - `metrics.json`: the current `metrics::snapshot()`
+//! - `kernel-stderr.log`: the kernel stderr lines still in `kernel_output`
+//! - `logs/`: the newest log files, up to `MAX_LOG_BYTES` in total
+//! - `config/`: the deployment config files in `CONFIG_FILES`
+//!
+//! Log and stderr lines are redacted as they are written. Config files are
+//! parsed and passed through `redact::value`, so sensitive keys lose their
+//! values; files holding secrets themselves (TOTP seeds, the session
+//! vault, the install id) are never included.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::logging::LOG_FILE;
+use crate::redact;
+
+/// Log bytes included, newest first (older content is cut)
+pub const MAX_LOG_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Deployment config files (in the app data dir): bundled after redaction,
+/// and checked by `health_check`
+pub const CONFIG_FILES: &[&str] = &[
+    "auth-backend.json",
+    "blobs.json",
+    "clipboard.json",
+    "consent.json",
+    "crash-reports.json",
+    "deep-links.json",
+    "file-drop.json",
+    "guest.json",
+    "kernel-methods.json",
+    "kernel-resources.json",
+    "logging.json",
+    "method-policy.json",
+    "metrics.json",
+    "notifications.json",
+    "oidc.json",
+    "open-external.json",
+    "os-login.json",
+    "password-policy.json",
+    "rate-limits.json",
+    "redaction.json",
+    "request-history.json",
+    "roles.json",
+    "scheduler.json",
+    "session-persistence.json",
+    "shortcuts.json",
+    "slow-requests.json",
+    "step-up.json",
+    "telemetry.json",
+    "updater.json",
+    "username-policy.json",
+    "warmup.json",
+    "window-policy.json",
+];
+
+/// Versions recorded in `versions.json`
+#[derive(Serialize)]
+pub struct Versions {
+    pub app: String,
+    pub kernel: Option<String>,
+    pub tauri: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    /// Unix seconds
+    pub created_at: u64,
+}
+
+impl Versions {
+    pub fn new(app: String, kernel: Option<String>) -> Self {
+        Self {
+            app,
+            kernel,
+            tauri: tauri::VERSION,
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Everything that goes into a bundle
+pub struct Contents<'a> {
+    pub versions: Versions,
+    pub metrics: Value,
+    pub kernel_stderr: Vec<String>,
+    /// App data dir (logs under `logs/`, config files at the top)
+    pub data_dir: Option<&'a Path>,
+}
+
+/// Zip file name suggested in the save dialog
+pub fn file_name(versions: &Versions) -> String {
+    format!("reos-diagnostics-{}.zip", versions.created_at)
+}
+
+/// A config file as it appears in the bundle (`None` if missing or not JSON)
+fn redacted_config(path: &Path) -> Option<Vec<u8>> {
+    let mut value: Value = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    redact::value(&mut value);
+    serde_json::to_vec_pretty(&value).ok()
+}
+
+/// The last `max` bytes of `path`, starting at a line boundary when cut
+fn tail(path: &Path, max: u64) -> io::Result<Vec<u8>> {
+    let mut file = fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    let cut = len > max;
+    file.seek(SeekFrom::Start(len.saturating_sub(max)))?;
+    let mut data = Vec::new();
+    file.take(max).read_to_end(&mut data)?;
+    if cut {
+        let start = data
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(data.len(), |i| i + 1);
+        data.drain(..start);
+    }
+    Ok(data)
+}
+
+/// `reos.log`, `reos.log.1`, ... as far as they exist
+fn log_files(dir: &Path) -> Vec<(String, PathBuf)> {
+    (0..)
+        .map(|n| match n {
+            0 => LOG_FILE.to_string(),
+            n => format!("{LOG_FILE}.{n}"),
+        })
+        .map(|name| (name.clone(), dir.join(name)))
+        .take_while(|(_, path)| path.is_file())
+        .collect()
+}
+
+/// Build the zip in memory
+pub fn bundle(contents: &Contents) -> io::Result<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut add = |name: &str, data: &[u8]| -> io::Result<()> {
+        zip.start_file(name, options).map_err(io::Error::other)?;
+        zip.write_all(data)
+    };
+
+    add(
+        "versions.json",
+        &serde_json::to_vec_pretty(&contents.versions)?,
+    )?;
+    add(
+        "metrics.json",
+        &serde_json::to_vec_pretty(&contents.metrics)?,
+    )?;
+    let mut stderr = contents.kernel_stderr.join("\n");
+    stderr.push('\n');
+    add("kernel-stderr.log", stderr.as_bytes())?;
+
+    if let Some(data_dir) = contents.data_dir {
+        let mut budget = MAX_LOG_BYTES;
+        for (name, path) in log_files(&data_dir.join("logs")) {
+            if budget == 0 {
+                break;
+            }
+            let data = tail(&path, budget)?;
+            budget = budget.saturating_sub(data.len() as u64);
+            add(&format!("logs/{name}"), &data)?;
+        }
+        for name in CONFIG_FILES {
+            if let Some(data) = redacted_config(&data_dir.join(name)) {
+                add(&format!("config/{name}"), &data)?;
+            }
+        }
+    }
+
+    let cursor = zip.finish().map_err(io::Error::other)?;
+    Ok(cursor.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zip::ZipArchive;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("reos-diag-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("logs")).unwrap();
+        dir
+    }
+
+    fn read(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> String {
+        let mut text = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    #[test]
+    fn test_bundle_contents() {
+        let dir = temp_dir("bundle");
+        fs::write(dir.join("logs/reos.log"), "new\n").unwrap();
+        fs::write(dir.join("logs/reos.log.1"), "old\n").unwrap();
+        fs::write(
+            dir.join("oidc.json"),
+            r#"{ "issuer": "https://id.example.com", "client_secret": "s3cr3t" }"#,
+        )
+        .unwrap();
+        fs::write(dir.join("totp.json"), r#"{ "alice": "JBSWY3DPEHPK3PXP" }"#).unwrap();
+
+        let contents = Contents {
+            versions: Versions::new("1.2.3".into(), Some("0.1.0".into())),
+            metrics: serde_json::json!({ "kernel_starts": 1 }),
+            kernel_stderr: vec!["Traceback".into()],
+            data_dir: Some(dir.as_path()),
+        };
+        let mut archive = ZipArchive::new(Cursor::new(bundle(&contents).unwrap())).unwrap();
+        assert!(read(&mut archive, "versions.json").contains("1.2.3"));
+        assert_eq!(read(&mut archive, "kernel-stderr.log"), "Traceback\n");
+        assert_eq!(read(&mut archive, "logs/reos.log.1"), "old\n");
+        let oidc = read(&mut archive, "config/oidc.json");
+        assert!(oidc.contains("id.example.com"));
+        assert!(!oidc.contains("s3cr3t"));
+        assert!(archive.by_name("config/totp.json").is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_tail_starts_at_a_line() {
+        let dir = temp_dir("tail");
+        let path = dir.join("logs/reos.log");
+        fs::write(&path, "first line\nsecond\nthird\n").unwrap();
+        assert_eq!(tail(&path, 10).unwrap(), b"third\n");
+        assert_eq!(tail(&path, 100).unwrap(), b"first line\nsecond\nthird\n");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
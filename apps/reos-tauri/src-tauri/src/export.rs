@@ -0,0 +1,123 @@
+//! Streaming File Export for ReOS
+//!
+//! `export_file` runs a kernel method after the same checks as
+//! `kernel_request`, asks for a destination with the native save dialog
+//! and writes the result there from Rust. The kernel keeps the serialized
+//! result (`export/begin`) and hands it out in `CHUNK_SIZE` pieces
+//! (`export/chunk`) until `export/end`, so large results never travel
+//! through the webview or sit in one IPC message.
+//!
+//! Data goes to `<name>.part` next to the destination and is renamed into
+//! place only when complete; a failed export leaves no partial file.
+
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Event reporting bytes written during an export
+pub const EXPORT_PROGRESS_EVENT: &str = "file-export-progress";
+
+/// Bytes requested per `export/chunk` call
+pub const CHUNK_SIZE: u64 = 256 * 1024;
+
+/// `file-export-progress` payload
+#[derive(Serialize, Clone)]
+pub struct ExportProgress<'a> {
+    pub id: &'a str,
+    pub written: u64,
+    pub total: u64,
+}
+
+/// Result of `export_file`
+#[derive(Serialize, Debug)]
+pub struct ExportedFile {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Temporary file that becomes the destination on `commit`
+pub struct ExportWriter {
+    dest: PathBuf,
+    part: PathBuf,
+    file: Option<File>,
+    written: u64,
+}
+
+impl ExportWriter {
+    pub fn create(dest: &Path) -> std::io::Result<Self> {
+        let mut part = dest.as_os_str().to_owned();
+        part.push(".part");
+        let part = PathBuf::from(part);
+        let file = File::create(&part)?;
+        Ok(Self {
+            dest: dest.to_path_buf(),
+            part,
+            file: Some(file),
+            written: 0,
+        })
+    }
+
+    /// Append `data`; returns the bytes written so far
+    pub fn write(&mut self, data: &[u8]) -> std::io::Result<u64> {
+        if let Some(file) = self.file.as_mut() {
+            file.write_all(data)?;
+        }
+        self.written += data.len() as u64;
+        Ok(self.written)
+    }
+
+    /// Flush to disk and move the file into place
+    pub fn commit(mut self) -> std::io::Result<ExportedFile> {
+        if let Some(file) = self.file.take() {
+            file.sync_all()?;
+        }
+        std::fs::rename(&self.part, &self.dest)?;
+        Ok(ExportedFile {
+            path: self.dest.clone(),
+            size: self.written,
+        })
+    }
+}
+
+impl Drop for ExportWriter {
+    /// Uncommitted exports leave nothing behind
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            let _ = std::fs::remove_file(&self.part);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dest(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("reos-export-{name}-{}.md", std::process::id()))
+    }
+
+    #[test]
+    fn test_commit_moves_part_into_place() {
+        let dest = temp_dest("commit");
+        let mut writer = ExportWriter::create(&dest).unwrap();
+        assert_eq!(writer.write(b"# Notes\n").unwrap(), 8);
+        assert_eq!(writer.write(b"body").unwrap(), 12);
+        let exported = writer.commit().unwrap();
+        assert_eq!(exported.size, 12);
+        assert_eq!(std::fs::read(&dest).unwrap(), b"# Notes\nbody");
+        std::fs::remove_file(dest).unwrap();
+    }
+
+    #[test]
+    fn test_dropped_writer_removes_part() {
+        let dest = temp_dest("drop");
+        let mut writer = ExportWriter::create(&dest).unwrap();
+        writer.write(b"partial").unwrap();
+        let part = writer.part.clone();
+        assert!(part.exists());
+        drop(writer);
+        assert!(!part.exists());
+        assert!(!dest.exists());
+    }
+}
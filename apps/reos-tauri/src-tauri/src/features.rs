@@ -0,0 +1,219 @@
+//! Feature Flags
+//!
+//! Experimental subsystems (streaming, remote kernels, ...) ship dark
+//! behind a flag and get turned on per deployment or per user. A flag's
+//! value comes from, lowest priority first:
+//!
+//! - the compiled default (`FEATURES`)
+//! - the kernel's `initialize` result, `"features": {"<name>": bool}`; a
+//!   kernel may also declare flags of its own
+//! - `[features]` in `config.toml`, on or off for everyone, or on for the
+//!   listed users only:
+//!
+//! ```toml
+//! [features]
+//! streaming = true
+//! remote_kernel = ["alice", "bob"]
+//! ```
+//!
+//! `features_get` resolves every known flag for the caller's session.
+//! Config entries for flags neither compiled in nor declared by the kernel
+//! are ignored. `features://changed` tells windows to ask again after a
+//! kernel restart or a `config.toml` edit changed them.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Event emitted to all windows when flag values may have changed
+pub const FEATURES_CHANGED_EVENT: &str = "features://changed";
+
+const MAX_NAME_LEN: usize = 64;
+
+/// Flags the kernel may declare beyond the compiled ones
+const MAX_KERNEL_FLAGS: usize = 64;
+
+/// A compiled-in flag
+pub struct Feature {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub default: bool,
+}
+
+pub const FEATURES: &[Feature] = &[
+    Feature {
+        name: "streaming",
+        description: "Show kernel responses as they are produced",
+        default: false,
+    },
+    Feature {
+        name: "remote_kernel",
+        description: "Connect to a kernel on another machine",
+        default: false,
+    },
+];
+
+/// A `[features]` entry: on/off for everyone, or on for these users
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum FeatureSetting {
+    All(bool),
+    Users(Vec<String>),
+}
+
+impl FeatureSetting {
+    fn enabled_for(&self, username: &str) -> bool {
+        match self {
+            Self::All(enabled) => *enabled,
+            Self::Users(users) => users.iter().any(|user| user == username),
+        }
+    }
+}
+
+/// Where a flag's value came from
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagSource {
+    Default,
+    Kernel,
+    Config,
+}
+
+/// `features_get` entry
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct FeatureStatus {
+    pub name: String,
+    pub enabled: bool,
+    pub source: FlagSource,
+    /// `None` for kernel-declared flags
+    pub description: Option<&'static str>,
+}
+
+/// Flag names: lowercase letters, digits and `_`
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_')
+}
+
+/// Kernel flags from an `initialize` result (entries with an invalid name
+/// or a non-boolean value are ignored)
+pub fn from_manifest(result: &Value) -> BTreeMap<String, bool> {
+    let Some(entries) = result.get("features").and_then(Value::as_object) else {
+        return BTreeMap::new();
+    };
+    let compiled = |name: &str| FEATURES.iter().any(|f| f.name == name);
+    let mut flags = BTreeMap::new();
+    let mut declared = 0;
+    for (name, value) in entries {
+        let Some(enabled) = value.as_bool().filter(|_| is_valid_name(name)) else {
+            continue;
+        };
+        if !compiled(name) {
+            if declared == MAX_KERNEL_FLAGS {
+                continue;
+            }
+            declared += 1;
+        }
+        flags.insert(name.clone(), enabled);
+    }
+    flags
+}
+
+/// Every known flag as it applies to `username`
+pub fn resolve(
+    kernel: &BTreeMap<String, bool>,
+    config: &BTreeMap<String, FeatureSetting>,
+    username: &str,
+) -> Vec<FeatureStatus> {
+    let compiled = FEATURES
+        .iter()
+        .map(|f| (f.name.to_string(), f.default, Some(f.description)));
+    let declared = kernel
+        .keys()
+        .filter(|name| !FEATURES.iter().any(|f| f.name == name.as_str()))
+        .map(|name| (name.clone(), false, None));
+    compiled
+        .chain(declared)
+        .map(|(name, default, description)| {
+            let (enabled, source) = match (config.get(&name), kernel.get(&name)) {
+                (Some(setting), _) => (setting.enabled_for(username), FlagSource::Config),
+                (None, Some(&enabled)) => (enabled, FlagSource::Kernel),
+                (None, None) => (default, FlagSource::Default),
+            };
+            FeatureStatus {
+                name,
+                enabled,
+                source,
+                description,
+            }
+        })
+        .collect()
+}
+
+/// Flags declared by the running kernel
+#[derive(Default)]
+pub struct FeaturesState(pub Mutex<BTreeMap<String, bool>>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn status<'a>(flags: &'a [FeatureStatus], name: &str) -> &'a FeatureStatus {
+        flags.iter().find(|f| f.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_config_wins_over_kernel_over_default() {
+        let kernel = from_manifest(&json!({ "features": {
+            "streaming": true,
+            "remote_kernel": true,
+            "kernel_only": true,
+            "Bad Name": true,
+            "not_bool": "yes",
+        } }));
+        assert_eq!(kernel.len(), 3);
+        let config = BTreeMap::from([
+            ("remote_kernel".to_string(), FeatureSetting::All(false)),
+            ("unknown".to_string(), FeatureSetting::All(true)),
+        ]);
+        let flags = resolve(&kernel, &config, "alice");
+        assert_eq!(flags.len(), 3);
+        let streaming = status(&flags, "streaming");
+        assert!(streaming.enabled && streaming.source == FlagSource::Kernel);
+        let remote = status(&flags, "remote_kernel");
+        assert!(!remote.enabled && remote.source == FlagSource::Config);
+        assert_eq!(status(&flags, "kernel_only").description, None);
+
+        let defaults = resolve(&BTreeMap::new(), &BTreeMap::new(), "alice");
+        assert_eq!(defaults.len(), FEATURES.len());
+        assert!(defaults.iter().all(|f| f.source == FlagSource::Default));
+    }
+
+    #[test]
+    fn test_per_user_setting() {
+        let config = BTreeMap::from([(
+            "streaming".to_string(),
+            FeatureSetting::Users(vec!["alice".into()]),
+        )]);
+        let kernel = BTreeMap::new();
+        assert!(status(&resolve(&kernel, &config, "alice"), "streaming").enabled);
+        assert!(!status(&resolve(&kernel, &config, "bob"), "streaming").enabled);
+    }
+
+    #[test]
+    fn test_kernel_declared_flags_capped() {
+        let entries: serde_json::Map<String, Value> = (0..100)
+            .map(|i| (format!("flag_{i}"), json!(true)))
+            .chain([("streaming".to_string(), json!(true))])
+            .collect();
+        let flags = from_manifest(&json!({ "features": entries }));
+        assert_eq!(flags.len(), MAX_KERNEL_FLAGS + 1);
+        assert_eq!(flags.get("streaming"), Some(&true));
+        assert!(from_manifest(&json!({ "protocolVersion": "jsonrpc-2.0" })).is_empty());
+    }
+}
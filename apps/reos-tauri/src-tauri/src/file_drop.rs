@@ -0,0 +1,300 @@
+//! Drag-and-Drop File Ingestion for ReOS
+//!
+//! Files dropped on a window are checked here, not in the webview: the
+//! path is canonicalized (symlinks resolved) and must be a regular file
+//! under an allowed root, with no hidden components below it, an allowed
+//! extension and at most `max_bytes`. Accepted files are held under a
+//! random drop id for the window they were dropped on; the window only
+//! sees the id, name and size (`file-dropped`).
+//!
+//! `file_ingest` then streams the file to the kernel in `CHUNK_SIZE`
+//! pieces (`ingest/begin`, `ingest/chunk`, `ingest/finish`) with
+//! `file-ingest-progress` events, and returns the kernel-side handle, so
+//! file contents never pass through the webview as JSON.
+//!
+//! Configurable via `file-drop.json` in the app data dir:
+//! `{ "roots": ["/home/me/Documents"], "extensions": ["md", "pdf"], "max_bytes": 10485760 }`
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Event announcing an accepted drop to its window
+pub const FILE_DROPPED_EVENT: &str = "file-dropped";
+/// Event for a dropped file that failed validation
+pub const FILE_REJECTED_EVENT: &str = "file-drop-rejected";
+/// Event reporting bytes sent while a drop is ingested
+pub const INGEST_PROGRESS_EVENT: &str = "file-ingest-progress";
+
+/// Bytes per `ingest/chunk` call
+pub const CHUNK_SIZE: usize = 256 * 1024;
+
+/// How long an accepted drop waits for `file_ingest`
+const DROP_TTL: Duration = Duration::from_secs(5 * 60);
+
+const DEFAULT_EXTENSIONS: &[&str] = &["txt", "md", "pdf", "csv", "json", "png", "jpg", "jpeg"];
+const DEFAULT_MAX_BYTES: u64 = 100 * 1024 * 1024;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DropError {
+    #[error("File is outside the allowed folders")]
+    OutsideRoots,
+    #[error("Not a regular file")]
+    NotAFile,
+    #[error("File type not allowed")]
+    Extension,
+    #[error("File is larger than {0} bytes")]
+    TooLarge(u64),
+    #[error("File changed while it was being read")]
+    Changed,
+    #[error("Unknown or expired drop")]
+    UnknownDrop,
+    #[error("Cannot read file: {0}")]
+    Io(String),
+}
+
+/// Where dropped files may come from
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct DropPolicy {
+    /// Allowed folders (empty = the user's home directory)
+    pub roots: Vec<PathBuf>,
+    /// Allowed extensions, lowercase without the dot
+    pub extensions: Vec<String>,
+    pub max_bytes: u64,
+}
+
+impl Default for DropPolicy {
+    fn default() -> Self {
+        Self {
+            roots: Vec::new(),
+            extensions: DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+}
+
+impl DropPolicy {
+    /// Load the policy from `path` (missing or invalid file = defaults),
+    /// with `home` as the root when none are configured
+    pub fn load(path: Option<&Path>, home: Option<PathBuf>) -> Self {
+        let mut policy: Self = path
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        if policy.roots.is_empty() {
+            policy.roots.extend(home);
+        }
+        // Compare against resolved roots, like the resolved dropped paths
+        policy.roots = policy
+            .roots
+            .iter()
+            .filter_map(|root| root.canonicalize().ok())
+            .collect();
+        policy
+    }
+
+    /// Validate a dropped path
+    pub fn check(&self, path: &Path) -> Result<DroppedFile, DropError> {
+        let path = path
+            .canonicalize()
+            .map_err(|e| DropError::Io(e.to_string()))?;
+        let below_root = self
+            .roots
+            .iter()
+            .find_map(|root| path.strip_prefix(root).ok())
+            .ok_or(DropError::OutsideRoots)?;
+        let hidden = below_root.components().any(|c| match c {
+            Component::Normal(part) => part.to_string_lossy().starts_with('.'),
+            _ => true,
+        });
+        if hidden {
+            return Err(DropError::OutsideRoots);
+        }
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        if !self.extensions.contains(&extension) {
+            return Err(DropError::Extension);
+        }
+        let metadata = path.metadata().map_err(|e| DropError::Io(e.to_string()))?;
+        if !metadata.is_file() {
+            return Err(DropError::NotAFile);
+        }
+        if metadata.len() > self.max_bytes {
+            return Err(DropError::TooLarge(self.max_bytes));
+        }
+        Ok(DroppedFile {
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            size: metadata.len(),
+            path,
+        })
+    }
+}
+
+/// A dropped file that passed `DropPolicy::check`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DroppedFile {
+    pub path: PathBuf,
+    pub name: String,
+    pub size: u64,
+}
+
+struct PendingDrop {
+    window: String,
+    file: DroppedFile,
+    dropped_at: Instant,
+}
+
+/// Accepted drops waiting for `file_ingest`
+#[derive(Default)]
+pub struct PendingDrops {
+    drops: HashMap<String, PendingDrop>,
+}
+
+impl PendingDrops {
+    /// Hold `file` for `window`; returns its drop id
+    pub fn add(&mut self, window: &str, file: DroppedFile) -> String {
+        self.drops
+            .retain(|_, drop| drop.dropped_at.elapsed() < DROP_TTL);
+        let mut bytes = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let id = hex::encode(bytes);
+        self.drops.insert(
+            id.clone(),
+            PendingDrop {
+                window: window.to_string(),
+                file,
+                dropped_at: Instant::now(),
+            },
+        );
+        id
+    }
+
+    /// Take drop `id`, only for the window it was dropped on
+    pub fn take(&mut self, window: &str, id: &str) -> Result<DroppedFile, DropError> {
+        match self.drops.get(id) {
+            Some(drop) if drop.window == window && drop.dropped_at.elapsed() < DROP_TTL => {}
+            _ => return Err(DropError::UnknownDrop),
+        }
+        self.drops
+            .remove(id)
+            .map(|drop| drop.file)
+            .ok_or(DropError::UnknownDrop)
+    }
+}
+
+pub struct FileDropState {
+    pub policy: DropPolicy,
+    pub pending: Mutex<PendingDrops>,
+}
+
+impl FileDropState {
+    pub fn new(policy: DropPolicy) -> Self {
+        Self {
+            policy,
+            pending: Mutex::new(PendingDrops::default()),
+        }
+    }
+}
+
+/// `file-dropped` payload
+#[derive(Serialize, Clone)]
+pub struct DropNotice {
+    pub id: String,
+    pub name: String,
+    pub size: u64,
+}
+
+/// `file-drop-rejected` payload
+#[derive(Serialize, Clone)]
+pub struct DropRejected {
+    pub name: String,
+    pub reason: String,
+}
+
+/// `file-ingest-progress` payload
+#[derive(Serialize, Clone)]
+pub struct IngestProgress<'a> {
+    pub id: &'a str,
+    pub sent: u64,
+    pub total: u64,
+}
+
+/// Result of `file_ingest`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IngestedFile {
+    pub handle: String,
+    pub name: String,
+    pub size: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("reos-drop-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join(".hidden")).unwrap();
+        std::fs::write(dir.join("notes.MD"), b"# hi").unwrap();
+        std::fs::write(dir.join("run.sh"), b"echo").unwrap();
+        std::fs::write(dir.join(".hidden/key.json"), b"{}").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_check_applies_roots_extensions_and_size() {
+        let root = temp_root("check");
+        let mut policy = DropPolicy::load(None, Some(root.clone()));
+        let file = policy.check(&root.join("notes.MD")).unwrap();
+        assert_eq!((file.name.as_str(), file.size), ("notes.MD", 4));
+        // `..` is resolved before the root check
+        assert_eq!(
+            policy
+                .check(&root.join(".hidden/../notes.MD"))
+                .map(|f| f.size),
+            Ok(4)
+        );
+        assert_eq!(
+            policy.check(&root.join("run.sh")),
+            Err(DropError::Extension)
+        );
+        assert_eq!(
+            policy.check(&root.join(".hidden/key.json")),
+            Err(DropError::OutsideRoots)
+        );
+        assert_eq!(
+            policy.check(&std::env::temp_dir().join("..")),
+            Err(DropError::OutsideRoots)
+        );
+        policy.max_bytes = 3;
+        assert_eq!(
+            policy.check(&root.join("notes.MD")),
+            Err(DropError::TooLarge(3))
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_drops_are_taken_once_by_their_window() {
+        let mut pending = PendingDrops::default();
+        let file = DroppedFile {
+            path: PathBuf::from("/tmp/a.md"),
+            name: "a.md".into(),
+            size: 1,
+        };
+        let id = pending.add("main", file.clone());
+        assert_eq!(pending.take("doc-a", &id), Err(DropError::UnknownDrop));
+        assert_eq!(pending.take("main", &id), Ok(file));
+        assert_eq!(pending.take("main", &id), Err(DropError::UnknownDrop));
+    }
+}
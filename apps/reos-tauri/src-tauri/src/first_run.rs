@@ -0,0 +1,361 @@
+//! First-Run Setup
+//!
+//! Backend of the onboarding wizard. `setup_status` reports each step and
+//! `setup_run_step` runs one:
+//!
+//! - `directories` (required): create the profile's data and config dirs,
+//!   private to the user (0700 on Unix)
+//! - `kernel` (required): check that the kernel's interpreter runs and can
+//!   import `reos`; running it just checks again, e.g. after the user
+//!   installed Python
+//! - `venv` (optional): create a virtualenv in the data dir, install the
+//!   kernel project (the nearest `pyproject.toml` above the executable)
+//!   into it and make it `kernel.python`
+//!
+//! Setup is complete once the required steps are done. Until then the
+//! steps run without a session (the kernel is needed to log in); after
+//! that only an admin may re-run them. Installer output goes to
+//! `setup.log` in the data dir.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, TryLockError};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::kernel;
+use crate::kernel_integrity::{self, KernelSource};
+
+/// Virtualenv location in the data dir
+const VENV_DIR: &str = "kernel-venv";
+
+/// Installer output
+const LOG_FILE: &str = "setup.log";
+
+/// Prints the interpreter version if the kernel package imports
+const PROBE_SCRIPT: &str = "import reos, sys; print(sys.version.split()[0])";
+
+/// Longest a venv step may take (pip downloads dependencies)
+const INSTALL_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SetupStep {
+    Directories,
+    Kernel,
+    Venv,
+}
+
+impl SetupStep {
+    const ALL: [Self; 3] = [Self::Directories, Self::Kernel, Self::Venv];
+
+    pub fn required(self) -> bool {
+        self != Self::Venv
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SetupError {
+    #[error("cannot prepare {path}: {message}")]
+    Directory { path: String, message: String },
+    #[error("Kernel unavailable: {0}")]
+    Kernel(String),
+    #[error("No kernel project (pyproject.toml) found near the app")]
+    NoProject,
+    #[error("{program} failed: {message} (see {LOG_FILE})")]
+    Command { program: String, message: String },
+    #[error("Another setup step is running")]
+    Busy,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "state", content = "error", rename_all = "snake_case")]
+pub enum StepState {
+    Done,
+    Pending,
+    Failed(String),
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct StepStatus {
+    pub step: SetupStep,
+    pub required: bool,
+    #[serde(flatten)]
+    pub state: StepState,
+    /// What was found, e.g. the interpreter and its version
+    pub detail: Option<String>,
+}
+
+/// `setup_status` result
+#[derive(Serialize, Clone, Debug)]
+pub struct SetupStatus {
+    /// Every required step is done
+    pub complete: bool,
+    pub steps: Vec<StepStatus>,
+}
+
+/// Held while a step runs (one at a time)
+static RUNNING: Mutex<()> = Mutex::new(());
+
+/// Where setup puts things (the current profile's dirs)
+#[derive(Clone)]
+pub struct Setup {
+    pub data_dir: PathBuf,
+    pub config_dir: PathBuf,
+}
+
+impl Setup {
+    /// Interpreter of the venv step's virtualenv
+    pub fn venv_python(&self) -> PathBuf {
+        let venv = self.data_dir.join(VENV_DIR);
+        if cfg!(windows) {
+            venv.join("Scripts").join("python.exe")
+        } else {
+            venv.join("bin").join("python")
+        }
+    }
+
+    pub fn status(&self) -> SetupStatus {
+        let steps: Vec<StepStatus> = SetupStep::ALL
+            .into_iter()
+            .map(|step| self.step_status(step))
+            .collect();
+        SetupStatus {
+            complete: steps
+                .iter()
+                .all(|s| !s.required || s.state == StepState::Done),
+            steps,
+        }
+    }
+
+    fn step_status(&self, step: SetupStep) -> StepStatus {
+        let (state, detail) = match step {
+            SetupStep::Directories => {
+                let ready = [&self.data_dir, &self.config_dir]
+                    .into_iter()
+                    .all(|dir| private_dir(dir));
+                let state = if ready {
+                    StepState::Done
+                } else {
+                    StepState::Pending
+                };
+                (state, Some(self.data_dir.display().to_string()))
+            }
+            SetupStep::Kernel => {
+                let python = kernel::python_command();
+                match probe(&python) {
+                    Ok(version) => (
+                        StepState::Done,
+                        Some(format!("Python {version} ({python})")),
+                    ),
+                    Err(e) => (StepState::Failed(e.to_string()), Some(python)),
+                }
+            }
+            SetupStep::Venv => {
+                let python = self.venv_python();
+                let state = if python.is_file() {
+                    StepState::Done
+                } else {
+                    StepState::Pending
+                };
+                (state, Some(python.display().to_string()))
+            }
+        };
+        StepStatus {
+            step,
+            required: step.required(),
+            state,
+            detail,
+        }
+    }
+
+    /// Run `step`; its status afterwards
+    pub fn run(&self, step: SetupStep) -> Result<StepStatus, SetupError> {
+        let _running = match RUNNING.try_lock() {
+            Ok(guard) => guard,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return Err(SetupError::Busy),
+        };
+        match step {
+            SetupStep::Directories => {
+                for dir in [&self.data_dir, &self.config_dir] {
+                    create_private_dir(dir).map_err(|e| SetupError::Directory {
+                        path: dir.display().to_string(),
+                        message: e.to_string(),
+                    })?;
+                }
+            }
+            SetupStep::Kernel => {
+                probe(&kernel::python_command())?;
+            }
+            SetupStep::Venv => self.create_venv()?,
+        }
+        Ok(self.step_status(step))
+    }
+
+    fn create_venv(&self) -> Result<(), SetupError> {
+        let project = find_project().ok_or(SetupError::NoProject)?;
+        create_private_dir(&self.data_dir).map_err(|e| SetupError::Directory {
+            path: self.data_dir.display().to_string(),
+            message: e.to_string(),
+        })?;
+        let log = self.data_dir.join(LOG_FILE);
+        let base = kernel::python_command();
+        run_logged(
+            Command::new(&base)
+                .args(["-m", "venv"])
+                .arg(self.data_dir.join(VENV_DIR)),
+            &log,
+        )?;
+        let python = self.venv_python();
+        run_logged(
+            Command::new(&python)
+                .args(["-m", "pip", "install", "--disable-pip-version-check"])
+                .arg(&project),
+            &log,
+        )?;
+        probe(&python.to_string_lossy())?;
+        Ok(())
+    }
+}
+
+/// Interpreter version if `python` can import the kernel package
+fn probe(python: &str) -> Result<String, SetupError> {
+    let mut command = Command::new(python);
+    command.args(["-c", PROBE_SCRIPT]).stdin(Stdio::null());
+    if let Ok(KernelSource::Verified(root)) = kernel_integrity::check() {
+        command.env("PYTHONPATH", root);
+    }
+    let output = command
+        .output()
+        .map_err(|e| SetupError::Kernel(format!("cannot run {python}: {e}")))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let last = stderr.lines().last().unwrap_or("no output").trim();
+        return Err(SetupError::Kernel(last.to_string()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Run `command` with its output appended to `log`, within `INSTALL_TIMEOUT`
+fn run_logged(command: &mut Command, log: &Path) -> Result<(), SetupError> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let failed = |message: String| SetupError::Command {
+        program: program.clone(),
+        message,
+    };
+    let open_log = || {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log)
+            .map_err(|e| failed(e.to_string()))
+    };
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(open_log()?)
+        .stderr(open_log()?)
+        .spawn()
+        .map_err(|e| failed(e.to_string()))?;
+    let deadline = Instant::now() + INSTALL_TIMEOUT;
+    loop {
+        match child.try_wait().map_err(|e| failed(e.to_string()))? {
+            Some(status) if status.success() => return Ok(()),
+            Some(status) => return Err(failed(status.to_string())),
+            None if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(failed("timed out".to_string()));
+            }
+            None => std::thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+/// Nearest directory above the executable with a `pyproject.toml`
+fn find_project() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    exe.ancestors()
+        .skip(1)
+        .take(12)
+        .find(|dir| dir.join("pyproject.toml").is_file())
+        .map(Path::to_path_buf)
+}
+
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+/// Exists and (on Unix) isn't accessible to other users
+fn private_dir(dir: &Path) -> bool {
+    let Ok(meta) = fs::metadata(dir) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if meta.permissions().mode() & 0o077 != 0 {
+            return false;
+        }
+    }
+    meta.is_dir()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(name: &str) -> Setup {
+        let root = std::env::temp_dir().join(format!("reos-setup-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        Setup {
+            data_dir: root.join("data"),
+            config_dir: root.join("config"),
+        }
+    }
+
+    #[test]
+    fn test_directories_step_creates_private_dirs() {
+        let setup = setup("dirs");
+        let before = setup.step_status(SetupStep::Directories);
+        assert_eq!(before.state, StepState::Pending);
+        let after = setup.run(SetupStep::Directories).unwrap();
+        assert_eq!(after.state, StepState::Done);
+        assert!(setup.config_dir.is_dir());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&setup.data_dir, fs::Permissions::from_mode(0o755)).unwrap();
+            assert!(!private_dir(&setup.data_dir));
+        }
+        let _ = fs::remove_dir_all(setup.data_dir.parent().unwrap());
+    }
+
+    #[test]
+    fn test_missing_interpreter_fails_kernel_probe() {
+        assert!(matches!(
+            probe("/nonexistent/reos-python"),
+            Err(SetupError::Kernel(_))
+        ));
+    }
+
+    #[test]
+    fn test_optional_venv_does_not_block_completion() {
+        let setup = setup("venv");
+        assert_eq!(setup.step_status(SetupStep::Venv).state, StepState::Pending);
+        assert!(!SetupStep::Venv.required());
+        assert!(setup
+            .venv_python()
+            .starts_with(setup.data_dir.join(VENV_DIR)));
+    }
+}
@@ -0,0 +1,235 @@
+//! Global Lock Shortcut for ReOS
+//!
+//! A system-wide key combination (default Ctrl+Alt+L) soft-locks every
+//! session and raises the main window onto the lock screen. It is
+//! registered and handled in Rust (tauri-plugin-global-shortcut), so it
+//! still works when the webview is wedged.
+//!
+//! Configurable via `shortcuts.json` in the app data dir; `null` turns it off:
+//! `{ "lock": "Ctrl+Shift+L" }`
+//!
+//! Platforms: Linux (X11 key grab), macOS and Windows. Wayland compositors
+//! don't offer global grabs, so under Wayland the shortcut only fires while
+//! an XWayland window has focus.
+
+use serde::Deserialize;
+use std::path::Path;
+use std::str::FromStr;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, ShortcutState};
+use thiserror::Error;
+use tracing::warn;
+
+const DEFAULT_LOCK_SHORTCUT: &str = "Ctrl+Alt+L";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ShortcutError {
+    #[error("Invalid shortcut: {0}")]
+    Invalid(String),
+    #[error("Shortcut unavailable: {0}")]
+    Unavailable(String),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub super_key: bool,
+}
+
+/// Modifiers plus one key (`a`-`z`, `0`-`9` or `F1`-`F24`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shortcut {
+    pub modifiers: Modifiers,
+    /// Key name (`l`, `7`, `F5`)
+    pub key: String,
+}
+
+impl Shortcut {
+    /// The plugin's form of this shortcut
+    fn hotkey(&self) -> Result<tauri_plugin_global_shortcut::Shortcut, ShortcutError> {
+        use tauri_plugin_global_shortcut::Modifiers as Mods;
+
+        let code = match self.key.as_bytes() {
+            [c] if c.is_ascii_digit() => format!("Digit{}", *c as char),
+            [c] => format!("Key{}", c.to_ascii_uppercase() as char),
+            _ => self.key.clone(),
+        };
+        let code = Code::from_str(&code).map_err(|_| ShortcutError::Invalid(self.key.clone()))?;
+        let mut mods = Mods::empty();
+        for (on, bit) in [
+            (self.modifiers.ctrl, Mods::CONTROL),
+            (self.modifiers.alt, Mods::ALT),
+            (self.modifiers.shift, Mods::SHIFT),
+            (self.modifiers.super_key, Mods::SUPER),
+        ] {
+            if on {
+                mods |= bit;
+            }
+        }
+        Ok(tauri_plugin_global_shortcut::Shortcut::new(
+            Some(mods),
+            code,
+        ))
+    }
+}
+
+impl FromStr for Shortcut {
+    type Err = ShortcutError;
+
+    /// Parse `Ctrl+Alt+L` (case-insensitive); at least one of Ctrl, Alt or
+    /// Super is required so the grab can't swallow ordinary typing
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ShortcutError::Invalid(s.to_string());
+        let mut modifiers = Modifiers::default();
+        let mut key = None;
+        for part in s.split('+').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "alt" => modifiers.alt = true,
+                "shift" => modifiers.shift = true,
+                "super" | "meta" | "win" => modifiers.super_key = true,
+                name if key.is_none() => key = Some(key_name(name).ok_or_else(invalid)?),
+                _ => return Err(invalid()),
+            }
+        }
+        if !(modifiers.ctrl || modifiers.alt || modifiers.super_key) {
+            return Err(invalid());
+        }
+        Ok(Self {
+            modifiers,
+            key: key.ok_or_else(invalid)?,
+        })
+    }
+}
+
+/// Keysym name for a lowercased key
+fn key_name(name: &str) -> Option<String> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return c.is_ascii_alphanumeric().then(|| c.to_string());
+    }
+    let n: u8 = name.strip_prefix('f')?.parse().ok()?;
+    (1..=24).contains(&n).then(|| format!("F{n}"))
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct ShortcutConfig {
+    /// Lock shortcut (`None` = disabled)
+    pub lock: Option<String>,
+}
+
+impl Default for ShortcutConfig {
+    fn default() -> Self {
+        Self {
+            lock: Some(DEFAULT_LOCK_SHORTCUT.to_string()),
+        }
+    }
+}
+
+impl ShortcutConfig {
+    /// Load the config from `path` (missing or invalid file = defaults)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Call `on_press` whenever `shortcut` is pressed
+///
+/// Needs the global shortcut plugin installed. Best-effort like
+/// `system_lock::watch`: if the shortcut can't be registered (no X server,
+/// taken by another app) it's reported and nothing is watched.
+pub fn register<R: Runtime>(
+    app: &AppHandle<R>,
+    shortcut: Shortcut,
+    on_press: impl Fn() + Send + Sync + 'static,
+) {
+    let result = shortcut.hotkey().and_then(|hotkey| {
+        app.global_shortcut()
+            .on_shortcut(hotkey, move |_, _, event| {
+                if event.state == ShortcutState::Pressed {
+                    on_press();
+                }
+            })
+            .map_err(|e| ShortcutError::Unavailable(e.to_string()))
+    });
+    if let Err(e) = result {
+        warn!(error = %e, "global shortcut unavailable");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shortcut() {
+        let shortcut: Shortcut = "Ctrl+Alt+L".parse().unwrap();
+        assert_eq!(
+            shortcut,
+            Shortcut {
+                modifiers: Modifiers {
+                    ctrl: true,
+                    alt: true,
+                    ..Modifiers::default()
+                },
+                key: "l".to_string(),
+            }
+        );
+        let shortcut: Shortcut = "super + shift + f12".parse().unwrap();
+        assert!(shortcut.modifiers.super_key && shortcut.modifiers.shift);
+        assert_eq!(shortcut.key, "F12");
+    }
+
+    #[test]
+    fn test_shortcut_hotkey() {
+        use tauri_plugin_global_shortcut::Modifiers as Mods;
+
+        let hotkey = "Ctrl+Alt+L".parse::<Shortcut>().unwrap().hotkey().unwrap();
+        assert_eq!(
+            hotkey,
+            tauri_plugin_global_shortcut::Shortcut::new(
+                Some(Mods::CONTROL | Mods::ALT),
+                Code::KeyL
+            )
+        );
+        let hotkey = "Super+7".parse::<Shortcut>().unwrap().hotkey().unwrap();
+        assert_eq!(hotkey.key, Code::Digit7);
+        let hotkey = "Ctrl+Shift+F12"
+            .parse::<Shortcut>()
+            .unwrap()
+            .hotkey()
+            .unwrap();
+        assert_eq!(hotkey.key, Code::F12);
+        assert!(hotkey.mods.contains(Mods::SHIFT));
+    }
+
+    #[test]
+    fn test_rejects_invalid_shortcuts() {
+        for s in [
+            "L",
+            "Shift+L",
+            "Ctrl+Alt",
+            "Ctrl+L+K",
+            "Ctrl+F25",
+            "Ctrl+Enter",
+            "",
+        ] {
+            assert!(s.parse::<Shortcut>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_config_default_and_disable() {
+        assert_eq!(
+            ShortcutConfig::default().lock.as_deref(),
+            Some(DEFAULT_LOCK_SHORTCUT)
+        );
+        let config: ShortcutConfig = serde_json::from_str(r#"{ "lock": null }"#).unwrap();
+        assert!(config.lock.is_none());
+    }
+}
@@ -0,0 +1,83 @@
+//! Guest Sessions for ReOS
+//!
+//! A guest session is created without PAM credentials, so the kernel holds
+//! no encryption key for it. It may only call an allowlisted set of
+//! read-only kernel methods — useful for kiosk displays and demos.
+//!
+//! Guest mode is off unless enabled in `guest.json` in the app data dir:
+//! `{ "enabled": true, "allowed_methods": ["ping", "system/hardware"] }`
+
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Username recorded for guest sessions
+pub const GUEST_USERNAME: &str = "guest";
+
+/// Read-only methods guests may call when `guest.json` doesn't list any
+const DEFAULT_GUEST_METHODS: &[&str] = &[
+    "ping",
+    "system/hardware",
+    "system/live_state",
+    "ollama/status",
+    "providers/list",
+    "providers/status",
+    "tools/list",
+];
+
+/// Deployment policy for guest sessions
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct GuestPolicy {
+    pub enabled: bool,
+    pub allowed_methods: HashSet<String>,
+}
+
+impl Default for GuestPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allowed_methods: DEFAULT_GUEST_METHODS
+                .iter()
+                .map(|m| m.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl GuestPolicy {
+    /// Load the policy from `path` (missing or invalid file = guest mode disabled)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether a guest session may call `method`
+    pub fn allows(&self, method: &str) -> bool {
+        self.allowed_methods.contains(method)
+    }
+}
+
+/// Guest policy state (read-only after startup)
+pub struct GuestState(pub GuestPolicy);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_policy_is_disabled_and_read_only() {
+        let policy = GuestPolicy::default();
+        assert!(!policy.enabled);
+        assert!(policy.allows("ping"));
+        assert!(!policy.allows("play/me/write"));
+    }
+
+    #[test]
+    fn test_partial_file_keeps_default_methods() {
+        let policy: GuestPolicy = serde_json::from_str(r#"{ "enabled": true }"#).unwrap();
+        assert!(policy.enabled);
+        assert!(policy.allows("system/hardware"));
+    }
+}
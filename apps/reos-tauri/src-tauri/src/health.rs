@@ -0,0 +1,284 @@
+//! System Status Report
+//!
+//! `health_check` gathers what an "About / System status" screen shows in
+//! one call:
+//!
+//! - kernel: process state, version, IPC protocol, last heartbeat (the
+//!   last time the kernel sent anything: a response or a notification) and
+//!   resource usage (also returned alone by `kernel_status`)
+//! - sessions: active, locked and guest session counts
+//! - queue depth: requests waiting for the kernel right now
+//! - disk: free space on the volume holding the app data dir
+//! - config: deployment config files that exist but aren't valid JSON, an
+//!   invalid `config.toml`, failed startup migrations (`migrations.rs`),
+//!   and the `REOS_*` and flag overrides in effect
+//! - platform watchers: whether screen locks are seen (`system_lock.rs`)
+//!
+//! Nothing here blocks on the kernel: while a request holds the kernel
+//! lock its state reads as `busy`.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::TryLockError;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::auth::SessionCounts;
+use crate::config::{self, ConfigError, Override};
+use crate::diagnostics::CONFIG_FILES;
+use crate::ipc;
+use crate::kernel::{self, SharedKernel};
+use crate::kernel_resources::{self, ResourceUsage};
+use crate::migrations::MigrationFailure;
+use crate::portable;
+use crate::profile;
+use crate::system_lock;
+
+/// Free space below this is reported as low
+pub const LOW_DISK_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Unix milliseconds of the last kernel frame (0 = never)
+static LAST_HEARTBEAT_MS: AtomicU64 = AtomicU64::new(0);
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// The kernel just sent a frame
+pub fn heartbeat() {
+    LAST_HEARTBEAT_MS.store(now_ms(), Ordering::Relaxed);
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KernelStatus {
+    /// No process (never started, or dropped after exiting)
+    NotStarted,
+    Running,
+    /// A request holds the kernel right now
+    Busy,
+    /// The process died; it restarts on the next request
+    Exited,
+}
+
+#[derive(Serialize, Debug)]
+pub struct KernelReport {
+    pub status: KernelStatus,
+    /// Backend of the current kernel (`kernel_backend.rs`), unless none is
+    /// started or it is busy
+    pub backend: Option<&'static str>,
+    pub pid: Option<u32>,
+    /// `serverInfo.version` from the last handshake
+    pub version: Option<String>,
+    pub protocol: &'static str,
+    /// Unix milliseconds
+    pub last_heartbeat_ms: Option<u64>,
+    pub since_heartbeat_ms: Option<u64>,
+    /// Latest resource sample (`kernel_resources`)
+    pub resources: Option<ResourceUsage>,
+}
+
+impl KernelReport {
+    pub fn check(kernel: &SharedKernel, version: Option<String>) -> Self {
+        let (status, backend) = match kernel.try_lock() {
+            Ok(mut guard) => match guard.as_mut() {
+                Some(proc) => {
+                    let status = if proc.is_running() {
+                        KernelStatus::Running
+                    } else {
+                        KernelStatus::Exited
+                    };
+                    (status, Some(proc.name()))
+                }
+                None => (KernelStatus::NotStarted, None),
+            },
+            Err(TryLockError::WouldBlock) => (KernelStatus::Busy, None),
+            // Reset (and the kernel dropped) by the next request
+            Err(TryLockError::Poisoned(_)) => (KernelStatus::NotStarted, None),
+        };
+        let last = LAST_HEARTBEAT_MS.load(Ordering::Relaxed);
+        let last_heartbeat_ms = (last > 0).then_some(last);
+        Self {
+            status,
+            backend,
+            pid: kernel::current_pid(),
+            version,
+            protocol: ipc::PROTOCOL,
+            last_heartbeat_ms,
+            since_heartbeat_ms: last_heartbeat_ms.map(|last| now_ms().saturating_sub(last)),
+            resources: kernel_resources::latest(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct DiskSpace {
+    pub path: PathBuf,
+    pub available_bytes: u64,
+    pub total_bytes: u64,
+    /// Under `LOW_DISK_BYTES` free
+    pub low: bool,
+}
+
+impl DiskSpace {
+    /// Space on the volume holding `dir` (`None` if it can't be read)
+    pub fn check(dir: &Path) -> Option<Self> {
+        let available_bytes = fs2::available_space(dir).ok()?;
+        let total_bytes = fs2::total_space(dir).ok()?;
+        Some(Self {
+            path: dir.to_path_buf(),
+            available_bytes,
+            total_bytes,
+            low: available_bytes < LOW_DISK_BYTES,
+        })
+    }
+}
+
+/// A config file that won't load (and so is silently replaced by defaults)
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ConfigProblem {
+    pub file: &'static str,
+    pub error: String,
+}
+
+/// Config files in `dir` that exist but don't parse
+pub fn config_problems(dir: &Path) -> Vec<ConfigProblem> {
+    CONFIG_FILES
+        .iter()
+        .filter_map(|&file| {
+            let text = match std::fs::read_to_string(dir.join(file)) {
+                Ok(text) => text,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+                Err(e) => {
+                    return Some(ConfigProblem {
+                        file,
+                        error: e.to_string(),
+                    })
+                }
+            };
+            serde_json::from_str::<serde_json::Value>(&text)
+                .err()
+                .map(|e| ConfigProblem {
+                    file,
+                    error: e.to_string(),
+                })
+        })
+        .collect()
+}
+
+impl From<&ConfigError> for ConfigProblem {
+    fn from(error: &ConfigError) -> Self {
+        Self {
+            file: config::CONFIG_FILE,
+            error: error.to_string(),
+        }
+    }
+}
+
+impl From<&MigrationFailure> for ConfigProblem {
+    fn from(failure: &MigrationFailure) -> Self {
+        let error = match &failure.backup {
+            Some(backup) => format!("{} (backup: {})", failure.error, backup.display()),
+            None => failure.error.clone(),
+        };
+        Self {
+            file: failure.file,
+            error,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct HealthReport {
+    /// False if the kernel died, disk space is low or config is invalid
+    pub ok: bool,
+    pub app_version: String,
+    /// `--profile` name (`default` without one)
+    pub profile: &'static str,
+    /// Portable data dir, if running portable
+    pub portable: Option<&'static Path>,
+    pub kernel: KernelReport,
+    pub sessions: SessionCounts,
+    pub queue_depth: u64,
+    pub disk: Option<DiskSpace>,
+    pub config_problems: Vec<ConfigProblem>,
+    /// `REOS_*` variables and flags layered over `config.toml`
+    pub overrides: Vec<Override>,
+    /// Screen locks lock sessions (false: no platform watcher is running)
+    pub screen_lock_watch: bool,
+}
+
+impl HealthReport {
+    pub fn new(
+        app_version: String,
+        kernel: KernelReport,
+        sessions: SessionCounts,
+        queue_depth: u64,
+        data_dir: Option<&Path>,
+        other_problems: Vec<ConfigProblem>,
+        overrides: Vec<Override>,
+    ) -> Self {
+        let disk = data_dir.and_then(DiskSpace::check);
+        let mut config_problems = data_dir.map(config_problems).unwrap_or_default();
+        config_problems.extend(other_problems);
+        let ok = kernel.status != KernelStatus::Exited
+            && !disk.as_ref().is_some_and(|disk| disk.low)
+            && config_problems.is_empty()
+            && overrides.iter().all(|o| o.error.is_none());
+        Self {
+            ok,
+            app_version,
+            profile: profile::name(),
+            portable: portable::root(),
+            kernel,
+            sessions,
+            queue_depth,
+            disk,
+            config_problems,
+            overrides,
+            screen_lock_watch: system_lock::is_watching(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_config_problems_skip_missing_files() {
+        let dir = std::env::temp_dir().join(format!("reos-health-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("oidc.json"),
+            r#"{ "issuer": "https://id.example.com" }"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("roles.json"), "{ admin_groups: ").unwrap();
+        std::fs::write(dir.join("totp.json"), "not json").unwrap();
+
+        let problems = config_problems(&dir);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].file, "roles.json");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_kernel_status_without_process() {
+        let kernel: SharedKernel = Arc::new(Mutex::new(None));
+        assert_eq!(
+            KernelReport::check(&kernel, None).status,
+            KernelStatus::NotStarted
+        );
+        let _held = kernel.lock().unwrap();
+        assert_eq!(
+            KernelReport::check(&kernel, None).status,
+            KernelStatus::Busy
+        );
+    }
+}
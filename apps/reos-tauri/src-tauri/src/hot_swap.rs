@@ -0,0 +1,190 @@
+//! Kernel Hot Swap
+//!
+//! Replaces the running kernel (after its code was updated on disk, or
+//! from the tray) without logging anyone out, blue/green style:
+//!
+//! 1. The new kernel ("green") is started beside the old one ("blue"),
+//!    with its own channel handshake and `__session` key, while blue keeps
+//!    answering requests
+//! 2. The shared kernel lock is taken. This waits for the request in
+//!    flight on blue to finish (the drain), and nothing reaches blue after
+//! 3. Blue's kernel-side sessions, with their key material, are exported
+//!    (`session/export`) and replayed into green (`session/import`)
+//! 4. Green replaces blue under the same lock, so every request goes to
+//!    exactly one of them, and becomes current (`KernelEvent::Ready`)
+//! 5. Blue is stopped: it is asked to exit (its stdin is closed, or a
+//!    socket kernel gets `kernel/shutdown`), and it is killed if still
+//!    running after `STOP_GRACE`
+//!
+//! If green fails to start or to take the sessions, blue keeps serving and
+//! nothing changes. A blue that has died has nothing to hand over, so green
+//! simply takes its place. A blue that is running but can't export (a
+//! kernel from before `session/export`) fails the swap with
+//! `HotSwapError::Export`, and the caller decides whether a plain restart
+//! is acceptable. A remote kernel is swapped on its own machine, never
+//! from here (`HotSwapError::Remote`), and other backends than `process`
+//! have nothing to swap (`HotSwapError::NotProcess`).
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::kernel::{self, KernelError, KernelProcess, SharedKernel};
+use crate::kernel_backend::{self, Backend, KernelBackend};
+use crate::kernel_transport::Transport;
+use crate::secret::zeroize_value;
+
+/// How long the old kernel gets to exit after being asked to
+pub const STOP_GRACE: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Error)]
+pub enum HotSwapError {
+    #[error("a kernel swap is already in progress")]
+    InProgress,
+    #[error("new kernel failed to start: {0}")]
+    Start(#[from] KernelError),
+    #[error("running kernel can't hand over its sessions: {0}")]
+    Export(String),
+    #[error("new kernel refused the sessions: {0}")]
+    Import(String),
+    #[error("the kernel runs on another machine")]
+    Remote,
+    #[error("only a kernel process can be swapped")]
+    NotProcess,
+}
+
+/// Outcome of a swap
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Swapped {
+    /// Kernel-side sessions carried over
+    pub sessions: usize,
+    /// Whether a running kernel was replaced
+    pub replaced: bool,
+}
+
+/// Set while a swap runs; one at a time
+static SWAPPING: AtomicBool = AtomicBool::new(false);
+
+struct SwapGuard;
+
+impl SwapGuard {
+    fn enter() -> Option<Self> {
+        (!SWAPPING.swap(true, Ordering::SeqCst)).then_some(SwapGuard)
+    }
+}
+
+impl Drop for SwapGuard {
+    fn drop(&mut self) {
+        SWAPPING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// `result` of a response, or its error message
+fn result_of(mut response: Value) -> Result<Value, String> {
+    if let Some(error) = response.get("error") {
+        let message = error.get("message").and_then(Value::as_str);
+        return Err(message.unwrap_or("kernel error").to_string());
+    }
+    Ok(response
+        .get_mut("result")
+        .map(Value::take)
+        .unwrap_or_default())
+}
+
+/// Sessions of a running kernel (a zeroizable list), `None` if it exited
+fn export(blue: &mut dyn KernelBackend) -> Result<Option<Value>, HotSwapError> {
+    if !blue.is_running() {
+        return Ok(None);
+    }
+    let response = match blue.request("session/export", json!({})) {
+        Ok(response) => response,
+        Err(KernelError::Exited) => return Ok(None),
+        Err(e) => return Err(HotSwapError::Export(e.to_string())),
+    };
+    let mut result = result_of(response).map_err(HotSwapError::Export)?;
+    let sessions = result.get_mut("sessions").map(Value::take);
+    zeroize_value(&mut result);
+    match sessions {
+        Some(sessions @ Value::Array(_)) => Ok(Some(sessions)),
+        Some(mut other) => {
+            zeroize_value(&mut other);
+            Err(HotSwapError::Export("malformed session list".to_string()))
+        }
+        None => Err(HotSwapError::Export("no session list".to_string())),
+    }
+}
+
+/// Replace the shared kernel with a freshly started one, carrying the
+/// kernel-side sessions over (blocking; see the module docs)
+pub fn swap(kernel: &SharedKernel) -> Result<Swapped, HotSwapError> {
+    let _swapping = SwapGuard::enter().ok_or(HotSwapError::InProgress)?;
+    if kernel_backend::backend() != Backend::Process {
+        return Err(HotSwapError::NotProcess);
+    }
+    if kernel::transport() == Transport::Remote {
+        return Err(HotSwapError::Remote);
+    }
+    let mut green = KernelProcess::start_standby()?;
+
+    let mut guard = kernel::lock_shared(kernel);
+    let sessions = match guard.as_mut() {
+        Some(blue) => export(blue.as_mut())?,
+        None => None,
+    };
+    let count = sessions
+        .as_ref()
+        .and_then(Value::as_array)
+        .map_or(0, Vec::len);
+    if count > 0 {
+        // The request (and with it the key material) is wiped once sealed
+        let response = green
+            .process()
+            .request("session/import", json!({ "sessions": sessions }));
+        let imported = response
+            .map_err(|e| e.to_string())
+            .and_then(result_of)
+            .map_err(HotSwapError::Import)?;
+        let taken = imported.get("imported").and_then(Value::as_u64);
+        if taken != Some(count as u64) {
+            return Err(HotSwapError::Import(format!(
+                "took {} of {count} sessions",
+                taken.unwrap_or(0)
+            )));
+        }
+    }
+    let blue = guard.replace(Box::new(green.activate()));
+    drop(guard);
+
+    let replaced = blue.is_some();
+    if let Some(blue) = blue {
+        blue.shutdown(STOP_GRACE);
+    }
+    Ok(Swapped {
+        sessions: count,
+        replaced,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_swap_at_a_time() {
+        let first = SwapGuard::enter().unwrap();
+        assert!(SwapGuard::enter().is_none());
+        drop(first);
+        assert!(SwapGuard::enter().is_some());
+    }
+
+    #[test]
+    fn test_result_of_error_and_result() {
+        let error =
+            json!({ "id": 1, "error": { "code": -32602, "message": "sessions already exist" } });
+        assert_eq!(result_of(error).unwrap_err(), "sessions already exist");
+        let ok = json!({ "id": 1, "result": { "imported": 2 } });
+        assert_eq!(result_of(ok).unwrap()["imported"], 2);
+    }
+}
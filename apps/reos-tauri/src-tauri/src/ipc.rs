@@ -0,0 +1,308 @@
+//! Encrypted Kernel IPC Channel
+//!
+//! Right after spawning the kernel, Rust sends an ephemeral X25519 public
+//! key as a plain JSON line (`{"channel":"reos-ipc-v1","e":"<hex>"}`) and
+//! the kernel answers in kind. Both derive per-direction ChaCha20-Poly1305
+//! keys with HKDF-SHA256 over the shared secret, salted with the transcript
+//! hash; every later line is a base64 frame whose nonce is an implicit
+//! per-direction counter, so frames can't be read, altered, dropped,
+//! replayed or reordered without detection.
+//!
+//! This protects the credentials and data in the pipe from same-user
+//! processes that read or inject into it after startup. Like Noise NN it is
+//! unauthenticated: a process already interposed at spawn time is out of
+//! scope (kernel integrity checks address that separately).
+//!
+//! Mirrors `reos.ipc_channel` on the Python side.
+
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use data_encoding::BASE64;
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::io::{self, Write};
+use thiserror::Error;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+use zeroize::Zeroizing;
+
+/// Protocol name; also the HKDF domain separator
+pub const PROTOCOL: &str = "reos-ipc-v1";
+
+/// Poly1305 tag appended to every frame
+const TAG_LEN: usize = 16;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ChannelError {
+    #[error("invalid channel handshake reply")]
+    Handshake,
+    #[error("invalid or tampered IPC frame")]
+    Frame,
+}
+
+/// Rust side of a handshake in progress
+pub struct Handshake {
+    secret: EphemeralSecret,
+    public: PublicKey,
+}
+
+impl Handshake {
+    pub fn new() -> Self {
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&secret);
+        Self { secret, public }
+    }
+
+    /// First line written to the kernel
+    pub fn hello(&self) -> String {
+        json!({ "channel": PROTOCOL, "e": hex::encode(self.public.as_bytes()) }).to_string()
+    }
+
+    /// Complete the handshake with the kernel's reply line
+    pub fn finish(self, reply: &str) -> Result<SecureChannel, ChannelError> {
+        let reply: Value = serde_json::from_str(reply).map_err(|_| ChannelError::Handshake)?;
+        if reply.get("channel").and_then(Value::as_str) != Some(PROTOCOL) {
+            return Err(ChannelError::Handshake);
+        }
+        let kernel_public: [u8; 32] = reply
+            .get("e")
+            .and_then(Value::as_str)
+            .and_then(|e| hex::decode(e).ok())
+            .and_then(|e| e.try_into().ok())
+            .ok_or(ChannelError::Handshake)?;
+
+        let rust_public = *self.public.as_bytes();
+        let shared = self.secret.diffie_hellman(&PublicKey::from(kernel_public));
+        if !shared.was_contributory() {
+            return Err(ChannelError::Handshake);
+        }
+        let (to_kernel, to_rust) = derive_keys(shared.as_bytes(), &rust_public, &kernel_public);
+        Ok(SecureChannel::new(&to_kernel, &to_rust))
+    }
+}
+
+impl Default for Handshake {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// (rust -> kernel, kernel -> rust) keys for a completed exchange
+fn derive_keys(
+    shared: &[u8],
+    rust_public: &[u8; 32],
+    kernel_public: &[u8; 32],
+) -> (Zeroizing<[u8; 32]>, Zeroizing<[u8; 32]>) {
+    let transcript = Sha256::new()
+        .chain_update(PROTOCOL.as_bytes())
+        .chain_update(rust_public)
+        .chain_update(kernel_public)
+        .finalize();
+    let mut okm = Zeroizing::new([0u8; 64]);
+    Hkdf::<Sha256>::new(Some(&transcript), shared)
+        .expand(format!("{PROTOCOL} keys").as_bytes(), okm.as_mut())
+        .expect("64 bytes is a valid HKDF-SHA256 output length");
+
+    let mut to_kernel = Zeroizing::new([0u8; 32]);
+    let mut to_rust = Zeroizing::new([0u8; 32]);
+    to_kernel.copy_from_slice(&okm[..32]);
+    to_rust.copy_from_slice(&okm[32..]);
+    (to_kernel, to_rust)
+}
+
+fn nonce(counter: u64) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_le_bytes());
+    nonce.into()
+}
+
+/// Per-direction AEAD state after a completed handshake
+pub struct SecureChannel {
+    send: ChaCha20Poly1305,
+    recv: ChaCha20Poly1305,
+    send_counter: u64,
+    recv_counter: u64,
+}
+
+impl SecureChannel {
+    fn new(send_key: &[u8; 32], recv_key: &[u8; 32]) -> Self {
+        Self {
+            send: ChaCha20Poly1305::new(Key::from_slice(send_key)),
+            recv: ChaCha20Poly1305::new(Key::from_slice(recv_key)),
+            send_counter: 0,
+            recv_counter: 0,
+        }
+    }
+
+    /// Both ends of one channel under fixed keys (`benches/rpc.rs`)
+    #[cfg(feature = "bench")]
+    #[allow(dead_code)]
+    pub fn pair() -> (Self, Self) {
+        let (to_kernel, to_rust) = derive_keys(&[1u8; 32], &[2u8; 32], &[3u8; 32]);
+        (
+            Self::new(&to_kernel, &to_rust),
+            Self::new(&to_rust, &to_kernel),
+        )
+    }
+
+    /// Encrypt one outgoing line (without the newline)
+    #[cfg(any(test, feature = "bench"))]
+    #[allow(dead_code)]
+    pub fn seal(&mut self, plaintext: &[u8]) -> String {
+        let mut buf = Zeroizing::new(Vec::with_capacity(plaintext.len() + TAG_LEN));
+        buf.extend_from_slice(plaintext);
+        self.seal_in_place(buf)
+    }
+
+    /// Serialize and encrypt a JSON message, leaving no plaintext copy behind
+    ///
+    /// The message is written once into a buffer sized up front, so no
+    /// reallocation strands partial copies on the heap, and is encrypted in
+    /// place; only the (non-sensitive) ciphertext is returned.
+    pub fn seal_json(&mut self, message: &Value) -> String {
+        let mut buf = Zeroizing::new(Vec::with_capacity(json_len(message) + TAG_LEN));
+        serde_json::to_writer(&mut *buf, message).expect("a JSON Value always serializes");
+        self.seal_in_place(buf)
+    }
+
+    fn seal_in_place(&mut self, mut buf: Zeroizing<Vec<u8>>) -> String {
+        self.send
+            .encrypt_in_place(&nonce(self.send_counter), b"", &mut *buf)
+            .expect("ChaCha20-Poly1305 encryption is infallible for in-memory buffers");
+        self.send_counter += 1;
+        BASE64.encode(&buf)
+    }
+
+    /// Decrypt one incoming line, in the buffer it is decoded into
+    pub fn open(&mut self, line: &[u8]) -> Result<Zeroizing<Vec<u8>>, ChannelError> {
+        let mut frame = Zeroizing::new(
+            BASE64
+                .decode(line.trim_ascii())
+                .map_err(|_| ChannelError::Frame)?,
+        );
+        self.recv
+            .decrypt_in_place(&nonce(self.recv_counter), b"", &mut *frame)
+            .map_err(|_| ChannelError::Frame)?;
+        self.recv_counter += 1;
+        Ok(frame)
+    }
+}
+
+/// Serialized length of `message`, without materializing it
+fn json_len(message: &Value) -> usize {
+    struct Counter(usize);
+    impl Write for Counter {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0 += bytes.len();
+            Ok(bytes.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    let mut counter = Counter(0);
+    serde_json::to_writer(&mut counter, message).expect("a JSON Value always serializes");
+    counter.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stand-in for the kernel side of the handshake
+    fn kernel_accept(hello: &str) -> (SecureChannel, String) {
+        let hello: Value = serde_json::from_str(hello).unwrap();
+        let rust_public: [u8; 32] = hex::decode(hello["e"].as_str().unwrap())
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let kernel_public = *PublicKey::from(&secret).as_bytes();
+        let shared = secret.diffie_hellman(&PublicKey::from(rust_public));
+        let (to_kernel, to_rust) = derive_keys(shared.as_bytes(), &rust_public, &kernel_public);
+        let reply = json!({ "channel": PROTOCOL, "e": hex::encode(kernel_public) }).to_string();
+        (SecureChannel::new(&to_rust, &to_kernel), reply)
+    }
+
+    #[test]
+    fn test_shared_vector() {
+        let (to_kernel, to_rust) = derive_keys(&[1u8; 32], &[2u8; 32], &[3u8; 32]);
+        // Shared test vector with tests/test_ipc_channel.py
+        assert_eq!(
+            hex::encode(to_kernel.as_ref()),
+            "d28833923e1ced9d90511bf3e5f4725dcff9b57aa97a9ca4da9498750f3b9257"
+        );
+        assert_eq!(
+            hex::encode(to_rust.as_ref()),
+            "2ee84077c81cab7e0ea8037948f0570664ec15c874db2e95668ba7741f666a79"
+        );
+        let mut rust = SecureChannel::new(&to_kernel, &to_rust);
+        assert_eq!(rust.seal(b"ping"), "J4GQ+TYk3b9HCIFDDWVY3FcwteM=");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let handshake = Handshake::new();
+        let (mut kernel, reply) = kernel_accept(&handshake.hello());
+        let mut rust = handshake.finish(&reply).unwrap();
+
+        let frame = rust.seal(br#"{"method":"ping"}"#);
+        assert_eq!(
+            &*kernel.open(frame.as_bytes()).unwrap(),
+            br#"{"method":"ping"}"#
+        );
+        let frame = kernel.seal(br#"{"result":true}"#);
+        assert_eq!(
+            &*rust.open(frame.as_bytes()).unwrap(),
+            br#"{"result":true}"#
+        );
+    }
+
+    #[test]
+    fn test_seal_json_matches_serialized_bytes() {
+        let (to_kernel, to_rust) = derive_keys(&[1u8; 32], &[2u8; 32], &[3u8; 32]);
+        let message = json!({ "method": "auth/login_ldap", "params": { "password": "hünter\"2" } });
+        let bytes = serde_json::to_vec(&message).unwrap();
+        assert_eq!(json_len(&message), bytes.len());
+        assert_eq!(
+            SecureChannel::new(&to_kernel, &to_rust).seal_json(&message),
+            SecureChannel::new(&to_kernel, &to_rust).seal(&bytes)
+        );
+    }
+
+    #[test]
+    fn test_tampered_and_replayed_frames_fail() {
+        let handshake = Handshake::new();
+        let (mut kernel, reply) = kernel_accept(&handshake.hello());
+        let mut rust = handshake.finish(&reply).unwrap();
+
+        let frame = rust.seal(b"first");
+        let mut tampered = BASE64.decode(frame.as_bytes()).unwrap();
+        tampered[0] ^= 1;
+        assert_eq!(
+            kernel
+                .open(BASE64.encode(&tampered).as_bytes())
+                .unwrap_err(),
+            ChannelError::Frame
+        );
+
+        assert!(kernel.open(frame.as_bytes()).is_ok());
+        // Same frame again: the counter moved on
+        assert_eq!(
+            kernel.open(frame.as_bytes()).unwrap_err(),
+            ChannelError::Frame
+        );
+    }
+
+    #[test]
+    fn test_bad_handshake_reply() {
+        assert!(Handshake::new().finish("{}").is_err());
+        assert!(Handshake::new()
+            .finish(r#"{"channel":"reos-ipc-v1","e":"00"}"#)
+            .is_err());
+        // All-zero public key: no contributory shared secret
+        let zero = json!({ "channel": PROTOCOL, "e": hex::encode([0u8; 32]) }).to_string();
+        assert!(Handshake::new().finish(&zero).is_err());
+    }
+}
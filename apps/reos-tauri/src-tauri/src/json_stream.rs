@@ -0,0 +1,292 @@
+//! Streaming of Large Result Arrays
+//!
+//! Parsing a big kernel response into a `serde_json::Value` costs several
+//! times its size in small allocations, and the frontend then receives it
+//! as one more serialized copy. Instead, the envelope of each response is
+//! read first with the result left as raw text; when the result belongs to
+//! the pending request and is larger than `STREAM_THRESHOLD`, a top-level
+//! array (or an array field of a top-level object) is cut at item
+//! boundaries into `CHUNK_BYTES` chunks of JSON text and kept here, and the
+//! response carries a reference in its place:
+//!
+//! `{"__stream": {"handle", "items", "chunks"}}`
+//!
+//! The frontend pulls the chunks with `kernel_stream_read` (each one a JSON
+//! array, as raw bytes) and frees them with `kernel_stream_release`; only
+//! the session whose request produced the stream can read it. The shell's
+//! own calls get plain values back (`kernel::rpc_result` materializes
+//! references). Streams not released expire after `STREAM_TTL`, and when
+//! `MAX_OPEN_STREAMS` are open a new result is parsed as usual instead.
+//!
+//! Results big enough to go out of band (`blobs`) never reach this point.
+
+use serde::{Deserialize, Deserializer};
+use serde_json::value::RawValue;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::locks::Recover;
+
+/// Raw results larger than this are considered for streaming
+pub const STREAM_THRESHOLD: usize = 1024 * 1024;
+
+/// Target size of one chunk (a single larger item makes its own chunk)
+pub const CHUNK_BYTES: usize = 256 * 1024;
+
+/// Streams held at once
+const MAX_OPEN_STREAMS: usize = 16;
+
+/// Streams never released are dropped after this long
+pub const STREAM_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum StreamError {
+    #[error("unknown or expired stream handle")]
+    UnknownHandle,
+    #[error("stream chunk {0} out of range")]
+    OutOfRange(usize),
+}
+
+struct Stream {
+    owner: Option<String>,
+    items: usize,
+    /// Each one a JSON array
+    chunks: Vec<Box<[u8]>>,
+    opened: Instant,
+}
+
+/// Open streams by handle
+static STREAMS: Mutex<BTreeMap<String, Arc<Stream>>> = Mutex::new(BTreeMap::new());
+
+/// Response envelope with the result left unparsed
+#[derive(Deserialize)]
+struct Head<'a> {
+    #[serde(default)]
+    id: Option<Value>,
+    #[serde(borrow, default, deserialize_with = "present")]
+    result: Option<&'a RawValue>,
+}
+
+/// `Some` for any result, `null` included
+fn present<'de, D: Deserializer<'de>>(d: D) -> Result<Option<&'de RawValue>, D::Error> {
+    <&RawValue>::deserialize(d).map(Some)
+}
+
+/// Parse one decrypted kernel frame; a large array in the result of
+/// request `id` is kept here and replaced by a stream reference
+pub fn parse(frame: &[u8], id: u64, owner: Option<&str>) -> serde_json::Result<Value> {
+    let head: Head = serde_json::from_slice(frame)?;
+    if head.id == Some(Value::from(id)) {
+        if let Some(raw) = head.result.filter(|r| r.get().len() > STREAM_THRESHOLD) {
+            if let Some(result) = stream_result(raw, owner)? {
+                return Ok(json!({ "jsonrpc": "2.0", "id": id, "result": result }));
+            }
+        }
+    }
+    serde_json::from_slice(frame)
+}
+
+/// The result with its large arrays streamed, `None` if nothing was
+fn stream_result(raw: &RawValue, owner: Option<&str>) -> serde_json::Result<Option<Value>> {
+    match raw.get().as_bytes().first() {
+        Some(b'[') => {
+            let items: Vec<&RawValue> = serde_json::from_str(raw.get())?;
+            Ok(insert(&items, owner))
+        }
+        Some(b'{') => {
+            let fields: BTreeMap<String, &RawValue> = serde_json::from_str(raw.get())?;
+            let mut result = Map::new();
+            let mut streamed = false;
+            for (key, value) in fields {
+                let text = value.get();
+                let reference = if text.starts_with('[') && text.len() > STREAM_THRESHOLD {
+                    let items: Vec<&RawValue> = serde_json::from_str(text)?;
+                    insert(&items, owner)
+                } else {
+                    None
+                };
+                streamed |= reference.is_some();
+                let value = match reference {
+                    Some(reference) => reference,
+                    None => serde_json::from_str(text)?,
+                };
+                result.insert(key, value);
+            }
+            Ok(streamed.then_some(Value::Object(result)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Cut `items` into chunks of JSON array text
+fn chunk(items: &[&RawValue]) -> Vec<Box<[u8]>> {
+    let mut chunks = Vec::new();
+    let mut current = Vec::new();
+    for item in items {
+        let text = item.get().as_bytes();
+        if current.len() > 1 && current.len() + text.len() + 1 > CHUNK_BYTES {
+            current.push(b']');
+            chunks.push(std::mem::take(&mut current).into_boxed_slice());
+        }
+        current.push(if current.is_empty() { b'[' } else { b',' });
+        current.extend_from_slice(text);
+    }
+    if !current.is_empty() {
+        current.push(b']');
+        chunks.push(current.into_boxed_slice());
+    }
+    chunks
+}
+
+fn insert(items: &[&RawValue], owner: Option<&str>) -> Option<Value> {
+    let mut streams = STREAMS.lock_or_recover();
+    streams.retain(|_, stream| stream.opened.elapsed() < STREAM_TTL);
+    if streams.len() >= MAX_OPEN_STREAMS {
+        return None;
+    }
+    let stream = Stream {
+        owner: owner.map(str::to_string),
+        items: items.len(),
+        chunks: chunk(items),
+        opened: Instant::now(),
+    };
+    let mut bytes = [0u8; 16];
+    rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut bytes);
+    let handle = hex::encode(bytes);
+    let reference = json!({ "__stream": {
+        "handle": handle,
+        "items": stream.items,
+        "chunks": stream.chunks.len(),
+    }});
+    streams.insert(handle, Arc::new(stream));
+    Some(reference)
+}
+
+/// Handle of a streamed array
+pub fn handle_of(value: &Value) -> Option<&str> {
+    value.get("__stream")?.get("handle")?.as_str()
+}
+
+/// Chunk `index` of a stream produced for session `owner`
+pub fn read(handle: &str, owner: &str, index: usize) -> Result<Vec<u8>, StreamError> {
+    let stream = {
+        let mut streams = STREAMS.lock_or_recover();
+        streams.retain(|_, stream| stream.opened.elapsed() < STREAM_TTL);
+        streams
+            .get(handle)
+            .filter(|stream| stream.owner.as_deref() == Some(owner))
+            .cloned()
+            .ok_or(StreamError::UnknownHandle)?
+    };
+    let chunk = stream
+        .chunks
+        .get(index)
+        .ok_or(StreamError::OutOfRange(index))?;
+    Ok(chunk.to_vec())
+}
+
+/// Free a stream produced for session `owner`; whether it existed
+pub fn release(handle: &str, owner: &str) -> bool {
+    let mut streams = STREAMS.lock_or_recover();
+    let owned = streams
+        .get(handle)
+        .is_some_and(|stream| stream.owner.as_deref() == Some(owner));
+    owned && streams.remove(handle).is_some()
+}
+
+fn take(handle: &str) -> Result<Value, StreamError> {
+    let stream = STREAMS
+        .lock_or_recover()
+        .remove(handle)
+        .ok_or(StreamError::UnknownHandle)?;
+    let mut items = Vec::with_capacity(stream.items);
+    for chunk in stream.chunks.iter() {
+        let chunk: Vec<Value> =
+            serde_json::from_slice(chunk).expect("chunks are cut from parsed JSON");
+        items.extend(chunk);
+    }
+    Ok(Value::Array(items))
+}
+
+/// Replace the stream references in a result (top level or fields) with
+/// the arrays they stand for, freeing them; for the shell's own calls
+pub fn materialize(result: &mut Value) -> Result<(), StreamError> {
+    if let Some(handle) = handle_of(result) {
+        *result = take(handle)?;
+    } else if let Some(fields) = result.as_object_mut() {
+        for value in fields.values_mut() {
+            if let Some(handle) = handle_of(value) {
+                *value = take(handle)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big_rows(n: usize) -> Value {
+        (0..n)
+            .map(|i| json!({ "id": i, "text": "x".repeat(100) }))
+            .collect()
+    }
+
+    #[test]
+    fn test_small_and_unrelated_frames_parse_as_usual() {
+        let frame = br#"{"jsonrpc":"2.0","id":3,"result":null}"#;
+        assert_eq!(parse(frame, 3, Some("s1")).unwrap()["result"], Value::Null);
+        let rows = big_rows(20_000);
+        let frame = json!({ "jsonrpc": "2.0", "id": 4, "result": rows }).to_string();
+        // Not the pending request: left alone
+        assert_eq!(
+            parse(frame.as_bytes(), 5, Some("s1")).unwrap()["result"],
+            rows
+        );
+        let frame = json!({ "jsonrpc": "2.0", "method": "event", "params": {} }).to_string();
+        assert_eq!(parse(frame.as_bytes(), 5, None).unwrap()["method"], "event");
+    }
+
+    #[test]
+    fn test_large_array_streamed_in_chunks() {
+        let rows = big_rows(20_000);
+        let frame = json!({ "jsonrpc": "2.0", "id": 7, "result": rows }).to_string();
+        let parsed = parse(frame.as_bytes(), 7, Some("s1")).unwrap();
+        let reference = &parsed["result"]["__stream"];
+        assert_eq!(reference["items"], 20_000);
+        let handle = handle_of(&parsed["result"]).unwrap().to_string();
+        let chunks = reference["chunks"].as_u64().unwrap() as usize;
+        assert!(chunks > 1);
+
+        assert_eq!(read(&handle, "s2", 0), Err(StreamError::UnknownHandle));
+        let mut items = Vec::new();
+        for index in 0..chunks {
+            let chunk = read(&handle, "s1", index).unwrap();
+            assert!(chunk.len() <= CHUNK_BYTES);
+            items.extend(serde_json::from_slice::<Vec<Value>>(&chunk).unwrap());
+        }
+        assert_eq!(Value::Array(items), rows);
+        assert_eq!(
+            read(&handle, "s1", chunks),
+            Err(StreamError::OutOfRange(chunks))
+        );
+        assert!(!release(&handle, "s2"));
+        assert!(release(&handle, "s1"));
+    }
+
+    #[test]
+    fn test_array_fields_materialize() {
+        let rows = big_rows(20_000);
+        let result = json!({ "rows": rows, "total": 20_000 });
+        let frame = json!({ "jsonrpc": "2.0", "id": 9, "result": result }).to_string();
+        let mut parsed = parse(frame.as_bytes(), 9, None).unwrap();
+        assert!(handle_of(&parsed["result"]["rows"]).is_some());
+        assert_eq!(parsed["result"]["total"], 20_000);
+        materialize(&mut parsed["result"]).unwrap();
+        assert_eq!(parsed["result"], result);
+    }
+}
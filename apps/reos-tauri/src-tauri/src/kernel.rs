@@ -1,9 +1,49 @@
-use std::io::{BufRead, BufReader, Write};
+use std::io::{self, BufRead, BufReader, Read, Write};
 use std::path::{Path, PathBuf};
-use std::process::{Child, ChildStdin, Command, Stdio};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
 
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::Serialize;
 use serde_json::{json, Value};
+use sha2::Sha256;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
+use zeroize::Zeroizing;
+
+use crate::blobs;
+use crate::health;
+use crate::ipc::{ChannelError, Handshake, SecureChannel};
+use crate::json_stream;
+use crate::kernel_backend::{self, KernelBackend};
+use crate::kernel_integrity::{self, IntegrityError, KernelSource};
+use crate::kernel_output::{self, OutputLine, Stream};
+use crate::kernel_resources::{self, ResourceWarning};
+use crate::kernel_transport::{KernelRecord, Link, RuntimeDir, Transport, LINGER, LISTEN_TIMEOUT};
+use crate::locks::Recover;
+use crate::metrics;
+use crate::redact;
+use crate::remote_kernel::{self, RemoteKernelError};
+use crate::request_history::{self, PendingRequest};
+use crate::secret::zeroize_value;
+use crate::slow_requests::{self, SlowRequest};
+
+/// Longest frame line read from the kernel; results beyond it go out of
+/// band (`blobs`), and a longer line means the channel can't be trusted
+pub const MAX_FRAME_LEN: usize = 256 * 1024 * 1024;
+
+/// Read buffers grown past this are freed after use rather than kept
+const KEEP_BUFFER_LEN: usize = 1024 * 1024;
+
+/// Set for a listening kernel we spawn: its session key arrives on stdin
+const KEY_STDIN_ENV: &str = "REOS_KERNEL_KEY_STDIN";
+
+/// Domain separator (and version) of signed `__session` claims
+const SESSION_SIGNATURE_VERSION: &str = "reos-session-v1";
+/// Domain separator (and version) of signed `__shell` proofs
+const SHELL_SIGNATURE_VERSION: &str = "reos-shell-v1";
 
 #[derive(Debug, Error)]
 pub enum KernelError {
@@ -19,13 +59,242 @@ pub enum KernelError {
     InvalidJson(String),
     #[error("kernel process exited")]
     Exited,
+    #[error("kernel sent a frame over the {0} byte limit")]
+    FrameTooLarge(usize),
+    #[error("kernel IPC channel: {0}")]
+    Channel(#[from] ChannelError),
+    #[error("kernel integrity check failed: {0}")]
+    Integrity(#[from] IntegrityError),
+    #[error("kernel refused the connection: {0}")]
+    Refused(String),
+    #[error(transparent)]
+    Remote(#[from] RemoteKernelError),
+    #[error("embedded kernel: {0}")]
+    Embedded(String),
+}
+
+/// Steps of `KernelProcess::start_with_progress`, in order
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartStage {
+    /// Checking the kernel sources against the signed manifest
+    Verifying,
+    /// Launching the Python process (or connecting to a remote kernel)
+    Spawning,
+    /// Encrypted channel key agreement
+    Handshake,
+    /// `initialize` (session key, redaction config)
+    Initializing,
+    Ready,
+}
+
+impl StartStage {
+    pub const COUNT: u32 = 5;
+
+    /// 1-based position of this stage
+    pub fn step(self) -> u32 {
+        self as u32 + 1
+    }
+}
+
+/// Something the kernel did outside a request/response pair
+pub enum KernelEvent {
+    /// A new process finished its handshake; carries the `initialize`
+    /// result (server info, quick actions)
+    Ready(Value),
+    /// JSON-RPC notification (no `id`) pushed by the kernel
+    Push(Value),
+    /// The process died; `status` describes how
+    Exited { status: String },
+    /// A line of output, as stored by `kernel_output`
+    Output(OutputLine),
+    /// A request passed its slow threshold (already logged)
+    SlowRequest(SlowRequest),
+    /// The process went over a resource threshold (already logged)
+    ResourceWarning(ResourceWarning),
+}
+
+static EVENT_HANDLER: OnceLock<Box<dyn Fn(KernelEvent) + Send + Sync>> = OnceLock::new();
+
+/// Install the handler for kernel events (once, at startup)
+///
+/// Called on the thread reading from the kernel, with the kernel lock held:
+/// it must not make kernel requests itself.
+pub fn on_event(handler: impl Fn(KernelEvent) + Send + Sync + 'static) {
+    let _ = EVENT_HANDLER.set(Box::new(handler));
+}
+
+/// Hand `event` to the installed handler (from any backend)
+pub fn report(event: KernelEvent) {
+    if let Some(handler) = EVENT_HANDLER.get() {
+        handler(event);
+    }
+}
+
+/// Echo the kernel's stderr (or, for a listening kernel, stdout too)
+/// redacted to our stderr and keep its last lines
+fn forward_output(output: impl Read + Send + 'static, stream: Stream) {
+    let name = match stream {
+        Stream::Stdout => "kernel-stdout",
+        Stream::Stderr => "kernel-stderr",
+    };
+    let spawned = std::thread::Builder::new()
+        .name(name.into())
+        .spawn(move || {
+            for line in BufReader::new(output).lines() {
+                let Ok(line) = line else { break };
+                let line = kernel_output::record(stream, &line);
+                let _ = writeln!(io::stderr(), "{}", line.line);
+                report(KernelEvent::Output(line));
+            }
+        });
+    if let Err(e) = spawned {
+        tracing::warn!(error = %e, "failed to forward kernel {name}");
+    }
+}
+
+/// Report requests that become slow while still running (once, at startup)
+pub fn spawn_slow_request_watchdog() {
+    let spawned = std::thread::Builder::new()
+        .name("slow-requests".into())
+        .spawn(|| loop {
+            std::thread::sleep(slow_requests::WATCHDOG_INTERVAL);
+            for slow in slow_requests::check_in_flight() {
+                report(KernelEvent::SlowRequest(slow));
+            }
+        });
+    if let Err(e) = spawned {
+        tracing::warn!(error = %e, "failed to start slow request watchdog");
+    }
+}
+
+/// Report resource usage of the running kernel periodically (once, at startup)
+pub fn spawn_resource_sampler() {
+    let spawned = std::thread::Builder::new()
+        .name("kernel-resources".into())
+        .spawn(|| loop {
+            std::thread::sleep(kernel_resources::interval());
+            for warning in kernel_resources::sample(current_pid()) {
+                report(KernelEvent::ResourceWarning(warning));
+            }
+        });
+    if let Err(e) = spawned {
+        tracing::warn!(error = %e, "failed to start kernel resource sampler");
+    }
+}
+
+/// Pid of the kernel process (0 = none), readable without the kernel lock
+static KERNEL_PID: AtomicU32 = AtomicU32::new(0);
+
+/// Pid of the current kernel process, if one is running
+pub fn current_pid() -> Option<u32> {
+    Some(KERNEL_PID.load(Ordering::Relaxed)).filter(|&pid| pid != 0)
+}
+
+/// Kernel shared between commands (started on first use, with the
+/// configured backend; see `kernel_backend.rs`)
+pub type SharedKernel = Arc<Mutex<Option<Box<dyn KernelBackend>>>>;
+
+/// Lock the shared kernel; a poisoned handle is dropped (killing the
+/// process) so the next request starts a fresh kernel
+pub fn lock_shared(kernel: &SharedKernel) -> MutexGuard<'_, Option<Box<dyn KernelBackend>>> {
+    let _queued = metrics::QueueGuard::enter();
+    kernel.lock_or_reset(|proc| *proc = None)
+}
+
+/// Send a request through the shared kernel, starting it if needed (blocking)
+pub fn request_shared(kernel: &SharedKernel, method: &str, params: Value) -> Result<Value, String> {
+    let mut params = params;
+    let mut guard = lock_shared(kernel);
+    if guard.is_none() {
+        match kernel_backend::spawn(|_| {}) {
+            Ok(proc) => *guard = Some(proc),
+            Err(e) => {
+                // Never sent: wipe any credentials in it here instead
+                zeroize_value(&mut params);
+                return Err(e.to_string());
+            }
+        }
+    }
+
+    let proc = guard
+        .as_mut()
+        .ok_or_else(|| KernelError::NotStarted.to_string())?;
+    let result = proc.request(method, params);
+    if let Err(KernelError::Exited | KernelError::FrameTooLarge(_)) = result {
+        // Drop the dead (or desynchronized) process so the next request
+        // starts a fresh one
+        *guard = None;
+    }
+    result.map_err(|e| e.to_string())
+}
+
+/// Extract and parse the `result` field from a JSON-RPC response envelope
+pub fn rpc_result<T: serde::de::DeserializeOwned>(mut response: Value) -> Result<T, String> {
+    let mut inner = response
+        .get_mut("result")
+        .map(Value::take)
+        .ok_or_else(|| "No result field in JSON-RPC response".to_string())?;
+    if let Some(handle) = blobs::handle_of(&inner) {
+        inner = blobs::take_json(handle).map_err(|e| e.to_string())?;
+    }
+    json_stream::materialize(&mut inner).map_err(|e| e.to_string())?;
+    serde_json::from_value(inner).map_err(|e| format!("Failed to parse kernel response: {e}"))
 }
 
 pub struct KernelProcess {
-    child: Child,
-    stdin: ChildStdin,
-    stdout: BufReader<std::process::ChildStdout>,
+    /// `None` for a listening kernel started by an earlier run of the shell
+    child: Option<Child>,
+    pid: u32,
+    transport: Transport,
+    /// Requests go out here; `None` once closed by `stop`
+    writer: Option<Box<dyn Write + Send>>,
+    reader: BufReader<Box<dyn Read + Send>>,
+    /// Listening kernel to record as current on `Standby::activate`
+    record: Option<(RuntimeDir, PathBuf)>,
     next_id: u64,
+    /// Per-boot HMAC key for `__session` claims, handed over in `initialize`
+    session_key: Zeroizing<[u8; 32]>,
+    /// Encrypts every line after the channel handshake
+    channel: SecureChannel,
+    /// `KernelEvent::Exited` already sent for this process
+    exit_reported: bool,
+    /// The kernel closed the connection (how a kernel that isn't our child
+    /// is known to be gone)
+    disconnected: bool,
+    /// Left running on drop (`detach`)
+    detached: bool,
+}
+
+/// A started and initialized kernel that isn't current yet: its pid isn't
+/// published and its `initialize` result isn't reported until `activate`
+pub struct Standby {
+    proc: KernelProcess,
+    ready: Value,
+}
+
+impl Standby {
+    pub fn process(&mut self) -> &mut KernelProcess {
+        &mut self.proc
+    }
+
+    /// Make this the current kernel (`current_pid`, `KernelEvent::Ready`;
+    /// the one a later shell reconnects to, for a listening kernel)
+    pub fn activate(self) -> KernelProcess {
+        if let Some((dir, endpoint)) = &self.proc.record {
+            let record = KernelRecord {
+                endpoint: endpoint.clone(),
+                key: hex::encode(self.proc.session_key.as_ref()),
+            };
+            if let Err(e) = dir.set_record(&record) {
+                tracing::warn!(error = %e, "kernel endpoint not recorded for reconnecting");
+            }
+        }
+        KERNEL_PID.store(self.proc.pid, Ordering::Relaxed);
+        metrics::record_kernel_start();
+        report(KernelEvent::Ready(self.ready));
+        self.proc
+    }
 }
 
 fn find_repo_venv_python() -> Option<PathBuf> {
@@ -47,8 +316,57 @@ fn find_repo_venv_python() -> Option<PathBuf> {
     None
 }
 
-fn python_command() -> String {
-    // Highest priority: explicit override.
+/// Effective `kernel.transport` (see `kernel_transport.rs`)
+static CONFIGURED_TRANSPORT: Mutex<Transport> = Mutex::new(Transport::Stdio);
+
+/// Install the configured transport (at startup and on config changes;
+/// used from the next kernel start)
+pub fn set_transport(transport: Transport) {
+    *CONFIGURED_TRANSPORT.lock_or_recover() = transport;
+}
+
+/// Transport the next kernel is started (or connected to) with
+pub fn transport() -> Transport {
+    *CONFIGURED_TRANSPORT.lock_or_recover()
+}
+
+/// Fresh per-boot `__session` key
+pub fn new_session_key() -> Zeroizing<[u8; 32]> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    rand::thread_rng().fill_bytes(key.as_mut());
+    key
+}
+
+/// Effective `kernel.python` (config file and overrides)
+static CONFIGURED_PYTHON: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Kernel data dir for a named profile (`REOS_DATA_DIR`)
+static KERNEL_DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Give the kernel its own data dir (named profiles, at startup)
+pub fn set_data_dir(dir: PathBuf) {
+    let _ = KERNEL_DATA_DIR.set(dir);
+}
+
+/// Kernel data dir, if the kernel gets its own
+pub fn data_dir() -> Option<&'static Path> {
+    KERNEL_DATA_DIR.get().map(PathBuf::as_path)
+}
+
+/// Install the configured interpreter (at startup)
+pub fn set_python(python: Option<PathBuf>) {
+    *CONFIGURED_PYTHON.lock_or_recover() = python;
+}
+
+/// Interpreter the kernel is started with
+pub fn python_command() -> String {
+    // Highest priority: `kernel.python` after flags, REOS_PYTHON /
+    // REOS_KERNEL_PATH and the config file (see `config.rs`).
+    if let Some(p) = CONFIGURED_PYTHON.lock_or_recover().as_ref() {
+        return p.to_string_lossy().to_string();
+    }
+
+    // Explicit override when no config was loaded.
     if let Ok(p) = std::env::var("REOS_PYTHON") {
         let p = p.trim();
         if !p.is_empty() {
@@ -66,17 +384,73 @@ fn python_command() -> String {
 }
 
 impl KernelProcess {
-    pub fn start() -> Result<Self, KernelError> {
-        // Dev-mode: prefer REOS_PYTHON or a repo `.venv/bin/python`.
+    /// Start a kernel and make it current, calling `progress` as each
+    /// stage begins
+    pub fn start_with_progress(progress: impl Fn(StartStage)) -> Result<Self, KernelError> {
+        let standby = Self::launch(&progress, true)?;
+        progress(StartStage::Ready);
+        Ok(standby.activate())
+    }
+
+    /// Start a kernel beside the running one, without making it current
+    /// (see `hot_swap.rs`)
+    pub fn start_standby() -> Result<Standby, KernelError> {
+        Self::launch(&|_| {}, false)
+    }
+
+    /// Spawn (or, over a socket or pipe and if `reconnect`, reconnect to),
+    /// then handshake and initialize a kernel
+    fn launch(progress: &impl Fn(StartStage), reconnect: bool) -> Result<Standby, KernelError> {
+        let transport = *CONFIGURED_TRANSPORT.lock_or_recover();
+        if transport == Transport::Remote {
+            return Self::launch_remote(progress);
+        }
+        if transport != Transport::Stdio {
+            match RuntimeDir::open(transport) {
+                Ok(Some(dir)) => return Self::launch_listening(dir, progress, reconnect),
+                Ok(None) => {
+                    tracing::warn!(?transport, "kernel transport unavailable here; using stdio")
+                }
+                Err(e) => tracing::warn!(error = %e, "kernel runtime dir unavailable; using stdio"),
+            }
+        }
+        Self::launch_stdio(progress)
+    }
+
+    /// The kernel command line (after checking its sources)
+    fn command(progress: &impl Fn(StartStage)) -> Result<Command, KernelError> {
+        // Dev-mode: prefer REOS_PYTHON, `kernel.python` or a repo `.venv/bin/python`.
         // Packaging: likely ship a Python runtime or use a platform sidecar.
         let python = python_command();
-        let mut child = Command::new(&python)
-            .args(["-m", "reos.ui_rpc_server"])
+        let mut command = Command::new(&python);
+        progress(StartStage::Verifying);
+        match kernel_integrity::check()? {
+            // Import only from the verified root: `-P` drops the working
+            // directory from sys.path, and no bytecode is written beside it
+            KernelSource::Verified(root) => command
+                .args(["-P", "-m", "reos.ui_rpc_server"])
+                .env("PYTHONPATH", root)
+                .env("PYTHONDONTWRITEBYTECODE", "1"),
+            KernelSource::Unverified => command.args(["-m", "reos.ui_rpc_server"]),
+        };
+        if let Some(dir) = data_dir() {
+            command.env("REOS_DATA_DIR", dir);
+        }
+        Ok(command)
+    }
+
+    fn launch_stdio(progress: &impl Fn(StartStage)) -> Result<Standby, KernelError> {
+        let mut command = Self::command(progress)?;
+        progress(StartStage::Spawning);
+        let mut child = command
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .stderr(Stdio::inherit())
+            .stderr(Stdio::piped())
             .spawn()
             .map_err(|e| KernelError::SpawnFailed(e.to_string()))?;
+        if let Some(stderr) = child.stderr.take() {
+            forward_output(stderr, Stream::Stderr);
+        }
 
         let stdin = child
             .stdin
@@ -86,57 +460,629 @@ impl KernelProcess {
             .stdout
             .take()
             .ok_or_else(|| KernelError::SpawnFailed("missing stdout".to_string()))?;
+        let link = Link::new(stdout, stdin);
+        Self::open(
+            Some(child),
+            Transport::Stdio,
+            link,
+            new_session_key(),
+            progress,
+        )
+    }
 
-        Ok(Self {
+    /// Reconnect to the recorded listening kernel if allowed and still
+    /// there, else spawn one listening on an endpoint of its own
+    fn launch_listening(
+        dir: RuntimeDir,
+        progress: &impl Fn(StartStage),
+        reconnect: bool,
+    ) -> Result<Standby, KernelError> {
+        if reconnect {
+            if let Some(record) = dir.record() {
+                match Self::reconnect(&record, dir.transport(), progress) {
+                    Ok(standby) => {
+                        tracing::info!(pid = standby.proc.pid, "reconnected to running kernel");
+                        return Ok(standby);
+                    }
+                    Err(e) => {
+                        tracing::info!(error = %e, "recorded kernel unavailable; starting a new one");
+                        dir.clear_record();
+                    }
+                }
+            }
+        }
+
+        let transport = dir.transport();
+        let endpoint = dir.new_endpoint();
+        let session_key = new_session_key();
+        let mut command = Self::command(progress)?;
+        command
+            .env(transport.endpoint_env(), &endpoint)
+            .env("REOS_KERNEL_LINGER_SECS", LINGER.as_secs().to_string())
+            .env(KEY_STDIN_ENV, "1");
+        progress(StartStage::Spawning);
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| KernelError::SpawnFailed(e.to_string()))?;
+        // The key goes down stdin before the kernel listens: whoever
+        // connects to the endpoint first can't install one of its own
+        if let Err(e) = hand_over_key(&mut child, &session_key) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e);
+        }
+        if let Some(stdout) = child.stdout.take() {
+            forward_output(stdout, Stream::Stdout);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_output(stderr, Stream::Stderr);
+        }
+
+        // Wait for it to listen
+        let deadline = Instant::now() + LISTEN_TIMEOUT;
+        let link = loop {
+            match Link::connect(transport, &endpoint) {
+                Ok(link) => break link,
+                Err(_) if Instant::now() < deadline && matches!(child.try_wait(), Ok(None)) => {
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+                Err(e) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(KernelError::SpawnFailed(format!("kernel endpoint: {e}")));
+                }
+            }
+        };
+        let mut standby = Self::open(Some(child), transport, link, session_key, progress)?;
+        standby.proc.record = Some((dir, endpoint));
+        Ok(standby)
+    }
+
+    /// Connect to a listening kernel an earlier run started
+    fn reconnect(
+        record: &KernelRecord,
+        transport: Transport,
+        progress: &impl Fn(StartStage),
+    ) -> Result<Standby, KernelError> {
+        let mut session_key = Zeroizing::new([0u8; 32]);
+        hex::decode_to_slice(&record.key, session_key.as_mut())
+            .map_err(|e| KernelError::Refused(format!("recorded key: {e}")))?;
+        let link = Link::connect(transport, &record.endpoint)
+            .map_err(|e| KernelError::SpawnFailed(format!("kernel endpoint: {e}")))?;
+        Self::open(None, transport, link, session_key, progress)
+    }
+
+    /// Connect to the kernel in `remote-kernel.json`, with the key it was
+    /// initialized with if this shell (or an earlier run) did
+    fn launch_remote(progress: &impl Fn(StartStage)) -> Result<Standby, KernelError> {
+        let url = remote_kernel::url().ok_or(RemoteKernelError::NotConfigured)?;
+        let dir = RuntimeDir::open(Transport::Remote).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "remote kernel key won't be kept");
+            None
+        });
+        let mut session_key = new_session_key();
+        let record = dir.as_ref().and_then(RuntimeDir::record);
+        if let Some(record) = record.filter(|record| record.endpoint == Path::new(url)) {
+            if hex::decode_to_slice(&record.key, session_key.as_mut()).is_err() {
+                session_key = new_session_key();
+            }
+        }
+        progress(StartStage::Spawning);
+        let link = remote_kernel::connect()?;
+        let mut standby = Self::open(None, Transport::Remote, link, session_key, progress)?;
+        standby.proc.record = dir.map(|dir| (dir, PathBuf::from(url)));
+        Ok(standby)
+    }
+
+    /// Open the encrypted channel over `link` and hand the kernel
+    /// `session_key` in `initialize`
+    fn open(
+        mut child: Option<Child>,
+        transport: Transport,
+        mut link: Link,
+        session_key: Zeroizing<[u8; 32]>,
+        progress: &impl Fn(StartStage),
+    ) -> Result<Standby, KernelError> {
+        progress(StartStage::Handshake);
+        // A pipe name anyone could have created first: it must be served
+        // by the kernel we spawned
+        let served_by = link.peer_pid;
+        if let (Some(child), Some(peer)) = (child.as_mut(), served_by) {
+            if peer != child.id() {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(KernelError::Refused(format!(
+                    "kernel endpoint served by another process ({peer})"
+                )));
+            }
+        }
+        let channel = match open_channel(&mut link) {
+            Ok(channel) => channel,
+            Err(e) => {
+                // Never fall back to plaintext: a kernel that can't (or
+                // won't) encrypt doesn't get any requests
+                if let Some(child) = child.as_mut() {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+                return Err(e);
+            }
+        };
+
+        let mut proc = Self {
+            pid: child.as_ref().map_or(0, Child::id),
             child,
-            stdin,
-            stdout: BufReader::new(stdout),
+            transport,
+            writer: Some(link.writer),
+            reader: link.reader,
+            record: None,
             next_id: 1,
-        })
+            session_key,
+            channel,
+            exit_reported: false,
+            disconnected: false,
+            detached: false,
+        };
+
+        // Handshake: the kernel accepts the first session key it is given
+        // (a listening kernel already has it from `hand_over_key`), so
+        // anything writing to it later can't swap in its own (a kernel
+        // reconnected to must be given the same one)
+        progress(StartStage::Initializing);
+        let key = Zeroizing::new(hex::encode(proc.session_key.as_ref()));
+        let response = proc.request(
+            "initialize",
+            json!({
+                "session_key": key.as_str(),
+                "redaction": redact::config(),
+                // Files and memfds don't reach another machine
+                "blobs": if transport == Transport::Remote {
+                    Value::Null
+                } else {
+                    blobs::offer()
+                },
+            }),
+        )?;
+        if let Some(error) = response.get("error") {
+            let message = error.get("message").and_then(Value::as_str);
+            // Not ours to stop: it keeps serving whoever holds its key
+            proc.detached = proc.child.is_none();
+            return Err(KernelError::Refused(
+                message.unwrap_or("initialize failed").to_string(),
+            ));
+        }
+        let ready = response.get("result").cloned().unwrap_or(Value::Null);
+        // A remote kernel's pid means nothing here
+        if proc.child.is_none() && transport != Transport::Remote {
+            // What the OS says serves the endpoint over what the kernel says
+            proc.pid = served_by
+                .or_else(|| {
+                    ready
+                        .get("pid")
+                        .and_then(Value::as_u64)
+                        .and_then(|pid| u32::try_from(pid).ok())
+                })
+                .unwrap_or(0);
+        }
+        Ok(Standby { ready, proc })
+    }
+
+    /// Whether the kernel process is still alive
+    pub fn is_running(&mut self) -> bool {
+        let Some(child) = self.child.as_mut() else {
+            return !self.disconnected;
+        };
+        match child.try_wait() {
+            Ok(None) => true,
+            Ok(Some(status)) => {
+                if !self.exit_reported {
+                    self.exit_reported = true;
+                    metrics::record_kernel_exit();
+                    report(KernelEvent::Exited {
+                        status: status.to_string(),
+                    });
+                }
+                false
+            }
+            Err(_) => false,
+        }
     }
 
+    #[tracing::instrument(
+        name = "kernel_request",
+        skip(self, params),
+        fields(id, correlation_id),
+        err(Display)
+    )]
     pub fn request(&mut self, method: &str, params: Value) -> Result<Value, KernelError> {
-        if let Some(status) = self.child.try_wait().map_err(|e| KernelError::Exited)? {
-            let _ = status;
+        track_request(method, params, |params, correlation_id| {
+            self.exchange(method, params, correlation_id)
+        })
+    }
+
+    fn exchange(
+        &mut self,
+        method: &str,
+        params: Value,
+        correlation_id: &str,
+    ) -> Result<Value, KernelError> {
+        let mut params = params;
+        if !self.is_running() {
+            zeroize_value(&mut params);
             return Err(KernelError::Exited);
         }
 
         let id = self.next_id;
         self.next_id += 1;
+        tracing::Span::current().record("id", id);
+        // A large result is only readable by the session that asked for it
+        let owner = params
+            .pointer("/__session/session_id")
+            .and_then(Value::as_str)
+            .map(str::to_string);
 
-        let req = json!({
+        sign_session(&self.session_key, method, &mut params);
+        sign_shell(&self.session_key, method, &mut params);
+        let mut req = json!({
             "jsonrpc": "2.0",
             "id": id,
             "method": method,
-            "params": params
+            "params": params,
+            "correlation_id": correlation_id
         });
 
-        let line = serde_json::to_string(&req).unwrap_or_else(|_| "{}".to_string());
-        self.stdin
-            .write_all(line.as_bytes())
-            .and_then(|_| self.stdin.write_all(b"\n"))
-            .and_then(|_| self.stdin.flush())
-            .map_err(|e| KernelError::StdinWriteFailed(e.to_string()))?;
+        // Params may carry credentials: the plaintext frame only ever exists
+        // in a zeroized buffer, and the request is wiped before any write
+        // error is reported. stdin is unbuffered, so nothing lingers after
+        // the flush either.
+        let frame = self.channel.seal_json(&req);
+        zeroize_value(&mut req);
+        let writer = self.writer.as_mut().ok_or(KernelError::Exited)?;
+        let written = writer
+            .write_all(frame.as_bytes())
+            .and_then(|_| writer.write_all(b"\n"))
+            .and_then(|_| writer.flush());
+        written.map_err(|e| KernelError::StdinWriteFailed(e.to_string()))?;
 
         // Read responses until we see the matching id.
-        let mut buf = String::new();
+        let mut buf = Vec::new();
         loop {
-            buf.clear();
-            let n = self
-                .stdout
-                .read_line(&mut buf)
-                .map_err(|e| KernelError::StdoutReadFailed(e.to_string()))?;
-            if n == 0 {
+            if !read_frame(&mut self.reader, &mut buf, MAX_FRAME_LEN)? {
+                self.disconnected = true;
                 return Err(KernelError::Exited);
             }
 
-            let parsed: Value = serde_json::from_str(buf.trim())
+            let plaintext = self.channel.open(&buf)?;
+            if buf.capacity() > KEEP_BUFFER_LEN {
+                buf = Vec::new();
+            }
+            health::heartbeat();
+            let mut parsed = json_stream::parse(&plaintext, id, owner.as_deref())
                 .map_err(|e| KernelError::InvalidJson(e.to_string()))?;
+            drop(plaintext);
 
             let resp_id = parsed.get("id");
+            if resp_id.is_none() && parsed.get("method").is_some() {
+                report_push(parsed);
+                continue;
+            }
             if resp_id == Some(&Value::Number(id.into())) {
+                // Kernel errors often echo params or exception text
+                if let Some(error) = parsed.get_mut("error") {
+                    redact::value(error);
+                }
+                if let Some(spec) = parsed.pointer_mut("/result/__blob").map(Value::take) {
+                    parsed = match blobs::receive(spec, self.pid, owner) {
+                        Ok(reference) => json!({ "jsonrpc": "2.0", "id": id, "result": reference }),
+                        Err(e) => {
+                            tracing::warn!(error = %e, method, "large result rejected");
+                            json!({
+                                "jsonrpc": "2.0",
+                                "id": id,
+                                "error": { "code": -32603, "message": e.to_string() },
+                            })
+                        }
+                    };
+                }
+                let line = kernel_output::describe_response(id, method, &parsed);
+                report(KernelEvent::Output(kernel_output::record(
+                    Stream::Stdout,
+                    &line,
+                )));
                 return Ok(parsed);
             }
         }
     }
 }
+
+/// Add `iat` and an HMAC (with `session_key`) over the claims the kernel
+/// relies on, bound to the method they were injected for
+pub fn sign_session(session_key: &[u8; 32], method: &str, params: &mut Value) {
+    let Some(Value::Object(claims)) = params.get_mut("__session") else {
+        return;
+    };
+    let iat = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    claims.insert("iat".to_string(), json!(iat));
+
+    let field = |name: &str| claims.get(name).and_then(Value::as_str).unwrap_or_default();
+    let message = session_claims_message(
+        field("username"),
+        field("session_id"),
+        field("role"),
+        method,
+        iat,
+    );
+    claims.insert(
+        "sig".to_string(),
+        json!(claims_signature(session_key, &message)),
+    );
+}
+
+/// Sign a `__shell` marker (`{}`) the same way: the kernel's proof that the
+/// shell itself made the call, for methods that trust its authentication
+/// (`auth/login_verified`, `auth/revoke_user`)
+pub fn sign_shell(session_key: &[u8; 32], method: &str, params: &mut Value) {
+    let Some(Value::Object(claims)) = params.get_mut("__shell") else {
+        return;
+    };
+    let iat = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    claims.insert("iat".to_string(), json!(iat));
+    let message = shell_claims_message(method, iat);
+    claims.insert(
+        "sig".to_string(),
+        json!(claims_signature(session_key, &message)),
+    );
+}
+
+fn claims_signature(session_key: &[u8; 32], message: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(session_key).expect("HMAC accepts any key length");
+    mac.update(message.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Run `exchange` (handed the params and a correlation id) as one request:
+/// metrics, slow request tracking and request history, for any backend
+pub fn track_request(
+    method: &str,
+    params: Value,
+    exchange: impl FnOnce(Value, &str) -> Result<Value, KernelError>,
+) -> Result<Value, KernelError> {
+    let correlation_id = slow_requests::correlation_id();
+    tracing::Span::current().record("correlation_id", correlation_id.as_str());
+    let history = request_history::enabled().then(|| PendingRequest::new(&params));
+    let started = Instant::now();
+    slow_requests::start(&correlation_id, method);
+    let result = exchange(params, &correlation_id);
+    let elapsed = started.elapsed();
+    let ok = matches!(&result, Ok(response) if response.get("error").is_none());
+    metrics::record_request(method, elapsed, ok);
+    if let Some(slow) = slow_requests::finish(&correlation_id, method, elapsed) {
+        report(KernelEvent::SlowRequest(slow));
+    }
+    if let Some(pending) = history {
+        let response = result.as_ref().map_err(|_| ());
+        request_history::record(pending, &correlation_id, method, elapsed, response);
+    }
+    result
+}
+
+/// Report a notification the kernel pushed (redacted, and in its output)
+pub fn report_push(mut push: Value) {
+    redact::value(&mut push);
+    let line = kernel_output::record(Stream::Stdout, &format!("push {push}"));
+    report(KernelEvent::Output(line));
+    report(KernelEvent::Push(push));
+}
+
+impl KernelProcess {
+    /// Ask a listening kernel to exit rather than wait for a shell to
+    /// reconnect (a stdio kernel exits at the end of its input, and a remote
+    /// one is left to serve the next connection)
+    fn request_exit(&mut self) {
+        if matches!(self.transport, Transport::Stdio | Transport::Remote) {
+            return;
+        }
+        let notice = json!({ "jsonrpc": "2.0", "method": "kernel/shutdown" });
+        let frame = self.channel.seal_json(&notice);
+        if let Some(writer) = self.writer.as_mut() {
+            let _ = writer
+                .write_all(frame.as_bytes())
+                .and_then(|_| writer.write_all(b"\n"))
+                .and_then(|_| writer.flush());
+        }
+    }
+
+    /// Stop the kernel gracefully: close its input, or ask a listening kernel
+    /// to exit, so it finishes what it is doing; kill it only if it is still
+    /// running after `grace`
+    pub fn stop(mut self, grace: Duration) {
+        self.request_exit();
+        drop(self.writer.take());
+        let pid = self.pid;
+        // A kernel reconnected to was asked to exit; it isn't ours to kill
+        let Some(child) = self.child.as_mut() else {
+            return;
+        };
+        let deadline = Instant::now() + grace;
+        while Instant::now() < deadline {
+            // An exit asked for isn't reported as `KernelEvent::Exited`
+            if !matches!(child.try_wait(), Ok(None)) {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        tracing::warn!(pid, "kernel did not exit in time; killing it");
+    }
+
+    /// Leave the kernel running once this handle is gone: a listening kernel
+    /// waits for the next shell to reconnect (app exit)
+    pub fn detach(mut self) {
+        self.detached = true;
+    }
+}
+
+impl Drop for KernelProcess {
+    /// Don't leave a kernel running once its handle is dropped (state reset
+    /// or poison recovery) unless detached; a fresh one is started on the
+    /// next request
+    fn drop(&mut self) {
+        let _ = KERNEL_PID.compare_exchange(self.pid, 0, Ordering::Relaxed, Ordering::Relaxed);
+        if self.detached {
+            return;
+        }
+        self.request_exit();
+        if let Some(child) = self.child.as_mut() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Read one line (without the newline) into `buf`, giving up once it
+/// grows past `max` instead of buffering whatever the kernel sends; `false`
+/// at end of stream
+fn read_frame(
+    reader: &mut impl BufRead,
+    buf: &mut Vec<u8>,
+    max: usize,
+) -> Result<bool, KernelError> {
+    buf.clear();
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(KernelError::StdoutReadFailed(e.to_string())),
+        };
+        if available.is_empty() {
+            // A partial last line is a kernel that died mid-write
+            return Ok(false);
+        }
+        let (line, done) = match available.iter().position(|&b| b == b'\n') {
+            Some(end) => (&available[..end], true),
+            None => (available, false),
+        };
+        if buf.len() + line.len() > max {
+            return Err(KernelError::FrameTooLarge(max));
+        }
+        buf.extend_from_slice(line);
+        let used = line.len() + usize::from(done);
+        reader.consume(used);
+        if done {
+            return Ok(true);
+        }
+    }
+}
+
+/// Write `session_key` (hex, one line) to a listening kernel's stdin and
+/// close it; the kernel reads it at startup when `KEY_STDIN_ENV` is set
+fn hand_over_key(child: &mut Child, session_key: &[u8; 32]) -> Result<(), KernelError> {
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| KernelError::SpawnFailed("missing stdin".to_string()))?;
+    let key = Zeroizing::new(hex::encode(session_key));
+    stdin
+        .write_all(key.as_bytes())
+        .and_then(|_| stdin.write_all(b"\n"))
+        .and_then(|_| stdin.flush())
+        .map_err(|e| KernelError::StdinWriteFailed(e.to_string()))
+}
+
+/// Exchange handshake lines with a kernel just connected to
+fn open_channel(link: &mut Link) -> Result<SecureChannel, KernelError> {
+    let handshake = Handshake::new();
+    let writer = &mut link.writer;
+    writer
+        .write_all(handshake.hello().as_bytes())
+        .and_then(|_| writer.write_all(b"\n"))
+        .and_then(|_| writer.flush())
+        .map_err(|e| KernelError::StdinWriteFailed(e.to_string()))?;
+
+    let mut reply = String::new();
+    let n = link
+        .reader
+        .read_line(&mut reply)
+        .map_err(|e| KernelError::StdoutReadFailed(e.to_string()))?;
+    if n == 0 {
+        return Err(KernelError::Exited);
+    }
+    Ok(handshake.finish(reply.trim())?)
+}
+
+/// Canonical byte string signed for `__session` (mirrored by `reos.session`)
+fn session_claims_message(
+    username: &str,
+    session_id: &str,
+    role: &str,
+    method: &str,
+    iat: u64,
+) -> String {
+    format!("{SESSION_SIGNATURE_VERSION}\n{username}\n{session_id}\n{role}\n{method}\n{iat}")
+}
+
+/// Canonical byte string signed for `__shell` (mirrored by `reos.session`)
+fn shell_claims_message(method: &str, iat: u64) -> String {
+    format!("{SHELL_SIGNATURE_VERSION}\n{method}\n{iat}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_claims_message() {
+        // Must stay in sync with reos.session.session_claims_message
+        assert_eq!(
+            session_claims_message("alice", "0123abcd", "user", "chat/respond", 1700000000),
+            "reos-session-v1\nalice\n0123abcd\nuser\nchat/respond\n1700000000"
+        );
+    }
+
+    #[test]
+    fn test_session_signature_vector() {
+        let message =
+            session_claims_message("alice", "0123abcd", "user", "chat/respond", 1700000000);
+        let mut mac = Hmac::<Sha256>::new_from_slice(&[7u8; 32]).unwrap();
+        mac.update(message.as_bytes());
+        // Shared test vector with tests/test_session_signing.py
+        assert_eq!(
+            hex::encode(mac.finalize().into_bytes()),
+            "29aa3bf2878080adec4c653eac86184e9721cecd362275b7336ac433ef9bfe16"
+        );
+    }
+
+    #[test]
+    fn test_shell_signature_vector() {
+        // Must stay in sync with reos.session.shell_claims_message
+        let message = shell_claims_message("auth/revoke_user", 1700000000);
+        assert_eq!(message, "reos-shell-v1\nauth/revoke_user\n1700000000");
+        // Shared test vector with tests/test_session_signing.py
+        assert_eq!(
+            claims_signature(&[7u8; 32], &message),
+            "a80d81e1fb59acee74b6fea3cc2514201df93365b3d77c939a72aa539534b9d2"
+        );
+    }
+
+    #[test]
+    fn test_read_frame_limit() {
+        let mut reader = io::Cursor::new(b"abc\ndefgh\nij".to_vec());
+        let mut buf = Vec::new();
+        assert!(read_frame(&mut reader, &mut buf, 4).unwrap());
+        assert_eq!(buf, b"abc");
+        assert!(matches!(
+            read_frame(&mut reader, &mut buf, 4),
+            Err(KernelError::FrameTooLarge(4))
+        ));
+        let mut reader = io::Cursor::new(b"ij".to_vec());
+        assert!(!read_frame(&mut reader, &mut buf, 4).unwrap());
+    }
+}
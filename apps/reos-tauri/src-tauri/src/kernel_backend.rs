@@ -0,0 +1,238 @@
+//! Kernel Backends
+//!
+//! What answers the shell's kernel requests, set by `kernel.backend` in
+//! `config.toml` (or `REOS_KERNEL_BACKEND`) and read each time a kernel
+//! starts:
+//!
+//! - `process` (default): the Python kernel, spawned here or reached over
+//!   `kernel.transport` (a remote kernel is this backend too; see
+//!   `kernel.rs`, `kernel_transport.rs`)
+//! - `mock`: canned results from `kernel-mock.json` in the app data dir,
+//!   for working on the UI (or testing) without Python:
+//!   ```json
+//!   { "initialize": { "serverInfo": { "name": "mock" } },
+//!     "results": { "chat/respond": { "answer": "Hello" } } }
+//!   ```
+//!   Methods without a result get a JSON-RPC "method not found" error
+//! - `embedded`: the Python kernel loaded into this process through PyO3,
+//!   in builds with the `embedded-kernel` feature (see `kernel_embedded.rs`)
+//!
+//! Commands only ever see `SharedKernel`, a lock around whichever backend
+//! `spawn` started, so a backend is swapped without touching them. What a
+//! backend does outside a request (ready, pushes, output, exits) goes out
+//! as `KernelEvent`s, so `kernel::on_event` subscribes to all of them.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::kernel::{self, KernelError, KernelEvent, KernelProcess, StartStage};
+use crate::kernel_embedded;
+use crate::locks::Recover;
+use crate::secret::zeroize_value;
+
+/// JSON-RPC error code for a method the kernel doesn't have
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// A running kernel, as the shell talks to it
+///
+/// Calls block and are made with the `SharedKernel` lock held.
+pub trait KernelBackend: Send {
+    /// Short name for logs and health reports
+    fn name(&self) -> &'static str;
+
+    /// Send a JSON-RPC request; the whole response envelope (`result` or
+    /// `error`). `params` may carry credentials and is wiped once used
+    fn request(&mut self, method: &str, params: Value) -> Result<Value, KernelError>;
+
+    /// Whether it can still answer requests
+    fn is_running(&mut self) -> bool;
+
+    /// Stop gracefully, giving in-flight work up to `grace`
+    fn shutdown(self: Box<Self>, grace: Duration);
+
+    /// Let go without stopping it (app exit), where the kernel can outlive
+    /// the shell; otherwise the same as dropping it
+    fn detach(self: Box<Self>) {}
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    #[default]
+    Process,
+    Mock,
+    Embedded,
+}
+
+/// Effective `kernel.backend`
+static CONFIGURED_BACKEND: Mutex<Backend> = Mutex::new(Backend::Process);
+
+/// Install the configured backend (at startup and on config changes; used
+/// from the next kernel start)
+pub fn set_backend(backend: Backend) {
+    *CONFIGURED_BACKEND.lock_or_recover() = backend;
+}
+
+/// Backend the next kernel is started with
+pub fn backend() -> Backend {
+    *CONFIGURED_BACKEND.lock_or_recover()
+}
+
+/// Start a kernel with the configured backend, calling `progress` as each
+/// stage begins
+pub fn spawn(progress: impl Fn(StartStage)) -> Result<Box<dyn KernelBackend>, KernelError> {
+    match backend() {
+        Backend::Process => Ok(Box::new(KernelProcess::start_with_progress(progress)?)),
+        Backend::Mock => {
+            let config = MOCK_CONFIG.get().cloned().unwrap_or_default();
+            let mock = MockKernel::new(config);
+            progress(StartStage::Ready);
+            kernel::report(KernelEvent::Ready(mock.config.initialize.clone()));
+            Ok(Box::new(mock))
+        }
+        Backend::Embedded => kernel_embedded::start(&progress),
+    }
+}
+
+impl KernelBackend for KernelProcess {
+    fn name(&self) -> &'static str {
+        "process"
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Result<Value, KernelError> {
+        KernelProcess::request(self, method, params)
+    }
+
+    fn is_running(&mut self) -> bool {
+        KernelProcess::is_running(self)
+    }
+
+    fn shutdown(self: Box<Self>, grace: Duration) {
+        KernelProcess::stop(*self, grace);
+    }
+
+    fn detach(self: Box<Self>) {
+        KernelProcess::detach(*self);
+    }
+}
+
+/// `kernel-mock.json`
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct MockKernelConfig {
+    /// `initialize` result reported on start (`KernelEvent::Ready`)
+    pub initialize: Value,
+    /// `result` per method
+    pub results: Map<String, Value>,
+}
+
+impl MockKernelConfig {
+    /// Load the configuration from `path` (missing or invalid file = none)
+    pub fn load(path: Option<&Path>) -> Option<Self> {
+        let text = std::fs::read_to_string(path?).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+}
+
+static MOCK_CONFIG: OnceLock<MockKernelConfig> = OnceLock::new();
+
+/// Install `kernel-mock.json` (at startup; first call wins)
+pub fn init_mock(config: Option<MockKernelConfig>) {
+    if let Some(config) = config {
+        let _ = MOCK_CONFIG.set(config);
+    }
+}
+
+/// Answers from `MockKernelConfig`, without any process
+pub struct MockKernel {
+    config: MockKernelConfig,
+    next_id: u64,
+}
+
+impl MockKernel {
+    pub fn new(config: MockKernelConfig) -> Self {
+        Self { config, next_id: 1 }
+    }
+}
+
+impl KernelBackend for MockKernel {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn request(&mut self, method: &str, mut params: Value) -> Result<Value, KernelError> {
+        zeroize_value(&mut params);
+        let id = self.next_id;
+        self.next_id += 1;
+        Ok(match self.config.results.get(method) {
+            Some(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            None => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {
+                    "code": METHOD_NOT_FOUND,
+                    "message": format!("Method not found: {method}"),
+                },
+            }),
+        })
+    }
+
+    fn is_running(&mut self) -> bool {
+        true
+    }
+
+    fn shutdown(self: Box<Self>, _grace: Duration) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::{request_shared, rpc_result, SharedKernel};
+    use std::sync::Arc;
+
+    fn mock() -> MockKernel {
+        let config: MockKernelConfig = serde_json::from_value(json!({
+            "results": { "system/ping": { "pong": true } },
+        }))
+        .unwrap();
+        MockKernel::new(config)
+    }
+
+    #[test]
+    fn test_mock_answers_from_config() {
+        let mut kernel = mock();
+        let response = kernel.request("system/ping", json!({})).unwrap();
+        assert_eq!(response["result"], json!({ "pong": true }));
+        assert_eq!(response["id"], json!(1));
+
+        let response = kernel.request("chat/respond", json!({})).unwrap();
+        assert_eq!(response["error"]["code"], json!(METHOD_NOT_FOUND));
+        assert_eq!(response["id"], json!(2));
+    }
+
+    #[test]
+    fn test_shared_kernel_holds_any_backend() {
+        let kernel: SharedKernel = Arc::new(Mutex::new(Some(Box::new(mock()))));
+        let response = request_shared(&kernel, "system/ping", json!({})).unwrap();
+        let result: Value = rpc_result(response).unwrap();
+        assert_eq!(result, json!({ "pong": true }));
+        assert!(kernel::lock_shared(&kernel).as_mut().unwrap().is_running());
+    }
+
+    #[test]
+    fn test_load_mock_config() {
+        let dir = std::env::temp_dir().join(format!("reos-mock-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("kernel-mock.json");
+        assert!(MockKernelConfig::load(Some(&path)).is_none());
+        std::fs::write(&path, r#"{ "results": { "a/b": 1 } }"#).unwrap();
+        let config = MockKernelConfig::load(Some(&path)).unwrap();
+        assert_eq!(config.results["a/b"], json!(1));
+        assert_eq!(config.initialize, Value::Null);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,217 @@
+//! Embedded Kernel (optional)
+//!
+//! Builds with the `embedded-kernel` feature can run the Python kernel in
+//! the shell's own process through PyO3 (`kernel.backend = "embedded"`),
+//! for small deployments and single-binary packages: there is no process
+//! to spawn, no pipe and no channel to encrypt, and a request is one call
+//! of `reos.ui_rpc_server.embedded_handle` with its JSON text. Requests
+//! still carry signed `__session` claims, go through the same `initialize`
+//! (minus `blobs`: results come back whole) and are redacted the same way.
+//!
+//! The interpreter is the one the shell was built against (`PYO3_PYTHON`),
+//! with the kernel sources checked as for a process (`kernel_integrity.rs`)
+//! and put first on `sys.path`. It lives as long as the shell: stopping the
+//! kernel only stops handing it requests, and starting one again gives the
+//! same interpreter a new session key. Its logs go to the shell's stderr,
+//! and it has no pid of its own to sample (`kernel_resources.rs`) or to
+//! hot-swap (`hot_swap.rs`).
+//!
+//! Without the feature, starting it fails with a clear error.
+
+use crate::kernel::{KernelError, StartStage};
+use crate::kernel_backend::KernelBackend;
+
+/// Load and initialize the kernel in this process, calling `progress` as
+/// each stage begins
+#[cfg(not(feature = "embedded-kernel"))]
+pub fn start(_progress: &impl Fn(StartStage)) -> Result<Box<dyn KernelBackend>, KernelError> {
+    Err(KernelError::Embedded(
+        "not in this build (feature `embedded-kernel`)".to_string(),
+    ))
+}
+
+#[cfg(feature = "embedded-kernel")]
+pub use embedded::start;
+
+#[cfg(feature = "embedded-kernel")]
+mod embedded {
+    use pyo3::prelude::*;
+    use pyo3::types::{PyCFunction, PyDict, PyTuple};
+    use serde_json::{json, Value};
+    use std::time::Duration;
+    use zeroize::Zeroizing;
+
+    use super::{KernelBackend, KernelError, StartStage};
+    use crate::health;
+    use crate::kernel::{self, KernelEvent};
+    use crate::kernel_integrity::{self, KernelSource};
+    use crate::kernel_output::{self, Stream};
+    use crate::metrics;
+    use crate::redact;
+    use crate::secret::zeroize_value;
+
+    const SERVER_MODULE: &str = "reos.ui_rpc_server";
+
+    /// The kernel module loaded in this process's interpreter
+    pub struct EmbeddedKernel {
+        server: Py<PyModule>,
+        next_id: u64,
+        /// Per-start HMAC key for `__session` claims, handed over in `initialize`
+        session_key: Zeroizing<[u8; 32]>,
+    }
+
+    pub fn start(progress: &impl Fn(StartStage)) -> Result<Box<dyn KernelBackend>, KernelError> {
+        progress(StartStage::Verifying);
+        let source = kernel_integrity::check()?;
+        progress(StartStage::Spawning);
+        let server = Python::with_gil(|py| load(py, source)).map_err(python_error)?;
+        let mut embedded = EmbeddedKernel {
+            server,
+            next_id: 1,
+            session_key: kernel::new_session_key(),
+        };
+
+        progress(StartStage::Initializing);
+        let key = Zeroizing::new(hex::encode(embedded.session_key.as_ref()));
+        let response = embedded.request(
+            "initialize",
+            json!({
+                "session_key": key.as_str(),
+                "redaction": redact::config(),
+                // Nothing to hand over out of band within one process
+                "blobs": Value::Null,
+            }),
+        )?;
+        if let Some(error) = response.get("error") {
+            let message = error.get("message").and_then(Value::as_str);
+            return Err(KernelError::Refused(
+                message.unwrap_or("initialize failed").to_string(),
+            ));
+        }
+        progress(StartStage::Ready);
+        metrics::record_kernel_start();
+        let ready = response.get("result").cloned().unwrap_or(Value::Null);
+        kernel::report(KernelEvent::Ready(ready));
+        Ok(Box::new(embedded))
+    }
+
+    /// Import the kernel module from the checked sources and start serving
+    /// in-process, pushes going to `report_push`
+    fn load(py: Python<'_>, source: KernelSource) -> PyResult<Py<PyModule>> {
+        let sys = py.import_bound("sys")?;
+        if let KernelSource::Verified(root) = source {
+            // As `-P` and PYTHONDONTWRITEBYTECODE do for a process (once:
+            // a restart loads into the same interpreter)
+            let path = sys.getattr("path")?;
+            if !path.contains(&root)? {
+                path.call_method1("insert", (0, root))?;
+            }
+            sys.setattr("dont_write_bytecode", true)?;
+        }
+        // Read once, when the kernel's settings are first imported
+        if let Some(dir) = kernel::data_dir() {
+            py.import_bound("os")?
+                .getattr("environ")?
+                .set_item("REOS_DATA_DIR", dir)?;
+        }
+        let server = py.import_bound(SERVER_MODULE)?;
+        let push = PyCFunction::new_closure_bound(
+            py,
+            None,
+            None,
+            |args: &Bound<'_, PyTuple>, _kwargs: Option<&Bound<'_, PyDict>>| -> PyResult<()> {
+                let line: String = args.get_item(0)?.extract()?;
+                match serde_json::from_str(&line) {
+                    Ok(push) => kernel::report_push(push),
+                    Err(e) => tracing::warn!(error = %e, "ignoring malformed kernel push"),
+                }
+                Ok(())
+            },
+        )?;
+        server.call_method1("embedded_start", (push,))?;
+        Ok(server.unbind())
+    }
+
+    /// A Python exception as a kernel error (its text redacted: it may
+    /// echo params)
+    fn python_error(e: PyErr) -> KernelError {
+        KernelError::Embedded(redact::text(&e.to_string()))
+    }
+
+    impl EmbeddedKernel {
+        fn exchange(
+            &mut self,
+            method: &str,
+            params: Value,
+            correlation_id: &str,
+        ) -> Result<Value, KernelError> {
+            let mut params = params;
+            let id = self.next_id;
+            self.next_id += 1;
+            tracing::Span::current().record("id", id);
+
+            kernel::sign_session(&self.session_key, method, &mut params);
+            kernel::sign_shell(&self.session_key, method, &mut params);
+            let mut req = json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": method,
+                "params": params,
+                "correlation_id": correlation_id
+            });
+            // Params may carry credentials: only the zeroized text is handed
+            // to Python (whose copy is its own to collect)
+            let line = Zeroizing::new(req.to_string());
+            zeroize_value(&mut req);
+            let response = Python::with_gil(|py| -> PyResult<Option<String>> {
+                self.server
+                    .bind(py)
+                    .call_method1("embedded_handle", (line.as_str(),))?
+                    .extract()
+            })
+            .map_err(python_error)?;
+            health::heartbeat();
+
+            let response =
+                response.ok_or_else(|| KernelError::InvalidJson("no response".to_string()))?;
+            let mut parsed: Value = serde_json::from_str(&response)
+                .map_err(|e| KernelError::InvalidJson(e.to_string()))?;
+            // Kernel errors often echo params or exception text
+            if let Some(error) = parsed.get_mut("error") {
+                redact::value(error);
+            }
+            let line = kernel_output::describe_response(id, method, &parsed);
+            kernel::report(KernelEvent::Output(kernel_output::record(
+                Stream::Stdout,
+                &line,
+            )));
+            Ok(parsed)
+        }
+    }
+
+    impl KernelBackend for EmbeddedKernel {
+        fn name(&self) -> &'static str {
+            "embedded"
+        }
+
+        #[tracing::instrument(
+            name = "kernel_request",
+            skip(self, params),
+            fields(id, correlation_id),
+            err(Display)
+        )]
+        fn request(&mut self, method: &str, params: Value) -> Result<Value, KernelError> {
+            kernel::track_request(method, params, |params, correlation_id| {
+                self.exchange(method, params, correlation_id)
+            })
+        }
+
+        fn is_running(&mut self) -> bool {
+            true
+        }
+
+        /// The interpreter stays loaded; requests in flight have returned
+        /// by now (they hold the kernel lock)
+        fn shutdown(self: Box<Self>, _grace: Duration) {}
+    }
+}
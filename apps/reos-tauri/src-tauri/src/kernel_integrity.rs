@@ -0,0 +1,305 @@
+//! Kernel Integrity Verification
+//!
+//! Release builds embed an Ed25519 public key (`REOS_KERNEL_SIGNING_KEY`,
+//! hex, read at compile time). Before every kernel spawn the shell checks
+//! `kernel-manifest.json` against its detached signature
+//! (`kernel-manifest.json.sig`, hex), then every listed file against its
+//! SHA-256, and refuses to start if anything differs. Unlisted loadable
+//! files (`.py`, `.pyc`, `.pth`, native modules, symlinks) are rejected
+//! too, so nothing can be dropped next to the package to shadow a module.
+//! The verified root is the only extra import path the kernel gets (see
+//! `KernelProcess::command`).
+//!
+//! Debug builds without a key (development) skip verification; a release
+//! build without one refuses to start any kernel. `REOS_KERNEL_ROOT` only
+//! points debug builds at another checkout. The interpreter and its
+//! site-packages are out of scope.
+//!
+//! Manifests are produced by `scripts/sign_kernel_manifest.py`.
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+pub const MANIFEST_FILE: &str = "kernel-manifest.json";
+pub const SIGNATURE_FILE: &str = "kernel-manifest.json.sig";
+
+/// Event emitted when verification fails
+pub const INTEGRITY_FAILED_EVENT: &str = "kernel-integrity-failed";
+
+/// Public key baked in by release builds
+const SIGNING_KEY: Option<&str> = option_env!("REOS_KERNEL_SIGNING_KEY");
+
+/// File types Python can load code from
+const LOADABLE_EXTENSIONS: &[&str] = &["py", "pyc", "pyo", "pth", "so", "pyd"];
+
+#[derive(Debug, Error, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", content = "path", rename_all = "snake_case")]
+pub enum IntegrityError {
+    #[error("kernel manifest not found")]
+    ManifestMissing,
+    #[error("kernel manifest signature is invalid")]
+    BadSignature,
+    #[error("kernel manifest is malformed")]
+    Malformed,
+    #[error("kernel file is missing: {0}")]
+    FileMissing(String),
+    #[error("kernel file was modified: {0}")]
+    FileModified(String),
+    #[error("unexpected file in kernel package: {0}")]
+    Unlisted(String),
+    #[error("this release build has no kernel signing key")]
+    Unsigned,
+}
+
+/// What `check` established about the kernel to launch
+#[derive(Debug, PartialEq, Eq)]
+pub enum KernelSource {
+    /// No signing key in this (debug) build: launch from the environment as before
+    Unverified,
+    /// Launch from this verified root only
+    Verified(PathBuf),
+}
+
+/// Signed list of kernel files, relative to the kernel root
+#[derive(Deserialize)]
+struct Manifest {
+    version: u32,
+    files: BTreeMap<String, String>,
+}
+
+/// Verify the kernel this build should launch
+pub fn check() -> Result<KernelSource, IntegrityError> {
+    let Some(key) = SIGNING_KEY else {
+        return if cfg!(debug_assertions) {
+            Ok(KernelSource::Unverified)
+        } else {
+            Err(IntegrityError::Unsigned)
+        };
+    };
+    let key = parse_key(key).ok_or(IntegrityError::BadSignature)?;
+    let root = find_root().ok_or(IntegrityError::ManifestMissing)?;
+    verify_root(&root, &key)?;
+    Ok(KernelSource::Verified(root))
+}
+
+pub fn parse_key(hex_key: &str) -> Option<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(hex_key.trim()).ok()?.try_into().ok()?;
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+/// `REOS_KERNEL_ROOT` (debug builds), else the nearest `kernel/` (packaged)
+/// or `src/` (checkout) holding a manifest above the executable
+fn find_root() -> Option<PathBuf> {
+    if let (true, Ok(root)) = (cfg!(debug_assertions), std::env::var("REOS_KERNEL_ROOT")) {
+        if !root.trim().is_empty() {
+            return Some(PathBuf::from(root.trim()));
+        }
+    }
+
+    let exe = std::env::current_exe().ok()?;
+    let mut dir = exe.parent()?.to_path_buf();
+    for _ in 0..12 {
+        for sub in ["kernel", "src"] {
+            let candidate = dir.join(sub);
+            if candidate.join(MANIFEST_FILE).is_file() {
+                return Some(candidate);
+            }
+        }
+        if !dir.pop() {
+            break;
+        }
+    }
+    None
+}
+
+/// Check the manifest signature, every listed file, and for unlisted code
+pub fn verify_root(root: &Path, key: &VerifyingKey) -> Result<(), IntegrityError> {
+    let manifest_bytes =
+        fs::read(root.join(MANIFEST_FILE)).map_err(|_| IntegrityError::ManifestMissing)?;
+    let signature = fs::read_to_string(root.join(SIGNATURE_FILE))
+        .ok()
+        .and_then(|sig| hex::decode(sig.trim()).ok())
+        .and_then(|sig| Signature::from_slice(&sig).ok())
+        .ok_or(IntegrityError::BadSignature)?;
+    key.verify_strict(&manifest_bytes, &signature)
+        .map_err(|_| IntegrityError::BadSignature)?;
+
+    let manifest: Manifest =
+        serde_json::from_slice(&manifest_bytes).map_err(|_| IntegrityError::Malformed)?;
+    if manifest.version != 1 {
+        return Err(IntegrityError::Malformed);
+    }
+
+    for (relative, expected) in &manifest.files {
+        if !is_plain_relative(relative) {
+            return Err(IntegrityError::Malformed);
+        }
+        let contents = fs::read(root.join(relative))
+            .map_err(|_| IntegrityError::FileMissing(relative.clone()))?;
+        if hex::encode(Sha256::digest(&contents)) != expected.to_ascii_lowercase() {
+            return Err(IntegrityError::FileModified(relative.clone()));
+        }
+    }
+
+    find_unlisted(root, root, &manifest.files)
+}
+
+/// Only `a/b/c.py`-style paths; no `..`, absolute or empty components
+fn is_plain_relative(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+}
+
+fn find_unlisted(
+    root: &Path,
+    dir: &Path,
+    listed: &BTreeMap<String, String>,
+) -> Result<(), IntegrityError> {
+    let entries = fs::read_dir(dir).map_err(|_| IntegrityError::ManifestMissing)?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let relative = path
+            .strip_prefix(root)
+            .map(|p| {
+                p.components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .unwrap_or_default();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        if file_type.is_dir() {
+            find_unlisted(root, &path, listed)?;
+            continue;
+        }
+        let loadable = file_type.is_symlink()
+            || path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| LOADABLE_EXTENSIONS.contains(&e));
+        if loadable && !listed.contains_key(&relative) {
+            return Err(IntegrityError::Unlisted(relative));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("reos-integrity-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("reos")).unwrap();
+        dir
+    }
+
+    /// Write a kernel package and a manifest signed with a fixed key
+    fn signed_root(name: &str) -> (PathBuf, VerifyingKey) {
+        let root = temp_dir(name);
+        let files = [
+            ("reos/__init__.py", "".to_string()),
+            ("reos/ui_rpc_server.py", "print('kernel')\n".to_string()),
+        ];
+        let mut listed = BTreeMap::new();
+        for (relative, contents) in &files {
+            fs::write(root.join(relative), contents).unwrap();
+            listed.insert(
+                relative.to_string(),
+                hex::encode(Sha256::digest(contents.as_bytes())),
+            );
+        }
+        let manifest = serde_json::json!({ "version": 1, "files": listed }).to_string();
+        fs::write(root.join(MANIFEST_FILE), &manifest).unwrap();
+
+        let key = SigningKey::from_bytes(&[9u8; 32]);
+        let signature = key.sign(manifest.as_bytes());
+        fs::write(root.join(SIGNATURE_FILE), hex::encode(signature.to_bytes())).unwrap();
+        (root, key.verifying_key())
+    }
+
+    #[test]
+    fn test_intact_kernel_verifies() {
+        let (root, key) = signed_root("intact");
+        // Non-code files may sit alongside
+        fs::write(root.join("README.txt"), "notes").unwrap();
+        assert_eq!(verify_root(&root, &key), Ok(()));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_swapped_file_is_rejected() {
+        let (root, key) = signed_root("swapped");
+        fs::write(root.join("reos/ui_rpc_server.py"), "import evil\n").unwrap();
+        assert_eq!(
+            verify_root(&root, &key),
+            Err(IntegrityError::FileModified(
+                "reos/ui_rpc_server.py".to_string()
+            ))
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_unlisted_module_and_bytecode_are_rejected() {
+        let (root, key) = signed_root("unlisted");
+        fs::write(root.join("json.py"), "").unwrap();
+        assert_eq!(
+            verify_root(&root, &key),
+            Err(IntegrityError::Unlisted("json.py".to_string()))
+        );
+        fs::remove_file(root.join("json.py")).unwrap();
+
+        fs::create_dir_all(root.join("reos/__pycache__")).unwrap();
+        fs::write(
+            root.join("reos/__pycache__/ui_rpc_server.cpython-311.pyc"),
+            "",
+        )
+        .unwrap();
+        assert!(matches!(
+            verify_root(&root, &key),
+            Err(IntegrityError::Unlisted(_))
+        ));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_tampered_manifest_or_wrong_key_is_rejected() {
+        let (root, key) = signed_root("manifest");
+        let other = SigningKey::from_bytes(&[1u8; 32]).verifying_key();
+        assert_eq!(
+            verify_root(&root, &other),
+            Err(IntegrityError::BadSignature)
+        );
+
+        let manifest = fs::read_to_string(root.join(MANIFEST_FILE)).unwrap();
+        fs::write(
+            root.join(MANIFEST_FILE),
+            manifest.replace("\"version\"", " \"version\""),
+        )
+        .unwrap();
+        assert_eq!(verify_root(&root, &key), Err(IntegrityError::BadSignature));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_plain_relative_paths() {
+        assert!(is_plain_relative("reos/agent.py"));
+        assert!(!is_plain_relative("../etc/passwd"));
+        assert!(!is_plain_relative("/etc/passwd"));
+        assert!(!is_plain_relative(""));
+    }
+}
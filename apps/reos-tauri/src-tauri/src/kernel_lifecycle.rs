@@ -0,0 +1,86 @@
+//! Kernel Start Policy
+//!
+//! When the shell starts the kernel process ahead of its first request,
+//! set by `kernel.start` in `config.toml` (or `REOS_KERNEL_START`):
+//!
+//! - `eager` (default): at launch, behind the splash (in the tray for a
+//!   minimized autostart), so the first window is already warm
+//! - `on_login`: when someone starts logging in or persisted sessions are
+//!   restored; launch is quicker and a kernel nobody logs into never runs
+//! - `on_first_request`: only when a request needs it, with no start
+//!   progress shown
+//!
+//! There is one way a kernel gets started ahead of time: `prepare_kernel`
+//! in `main.rs` asks `StartPolicy::starts_at` whether the moment
+//! (`StartTrigger`) calls for it, then starts it with
+//! `kernel-startup-progress` events; `kernel_start` starts it whatever the
+//! policy. Independently of the policy, a request that finds no kernel (not
+//! started yet, or dropped after it died) starts one itself
+//! (`kernel::request_shared`), so the policy only decides how early the
+//! cost is paid, never whether a request can succeed.
+//!
+//! The policy is read when needed: `on_login` and `on_first_request` apply
+//! at the next login, `eager` at the next launch.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartPolicy {
+    #[default]
+    Eager,
+    OnLogin,
+    OnFirstRequest,
+}
+
+/// Moments the shell may start the kernel ahead of a request
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StartTrigger {
+    /// App launch (splash or minimized autostart)
+    Launch,
+    /// A login begins, or persisted sessions are being restored
+    Login,
+}
+
+impl StartTrigger {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Launch => "launch",
+            Self::Login => "login",
+        }
+    }
+}
+
+impl StartPolicy {
+    /// Whether the kernel should be started (if it isn't running) at
+    /// `trigger`
+    pub fn starts_at(self, trigger: StartTrigger) -> bool {
+        match self {
+            // A kernel that died since launch comes back before the login
+            // that needs it rather than inside it
+            Self::Eager => true,
+            Self::OnLogin => trigger == StartTrigger::Login,
+            Self::OnFirstRequest => false,
+        }
+    }
+
+    /// Whether launch waits for the kernel behind the splash window
+    pub fn shows_splash(self) -> bool {
+        self.starts_at(StartTrigger::Launch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_triggers() {
+        use StartTrigger::{Launch, Login};
+        assert!(StartPolicy::Eager.starts_at(Launch) && StartPolicy::Eager.starts_at(Login));
+        assert!(!StartPolicy::OnLogin.starts_at(Launch) && StartPolicy::OnLogin.starts_at(Login));
+        assert!(!StartPolicy::OnFirstRequest.starts_at(Launch));
+        assert!(!StartPolicy::OnFirstRequest.starts_at(Login));
+        assert!(StartPolicy::Eager.shows_splash() && !StartPolicy::OnLogin.shows_splash());
+    }
+}
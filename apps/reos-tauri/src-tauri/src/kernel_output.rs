@@ -0,0 +1,161 @@
+//! Kernel Output for the Debug Console
+//!
+//! The last `MAX_LINES` lines of kernel output are kept in memory, across
+//! kernel restarts, each with a sequence number:
+//!
+//! - `stderr`: what the kernel prints (tracebacks, Python logging)
+//! - `stdout`: stdout is the encrypted JSON-RPC channel, so it shows up as
+//!   one line per response (`#id method ok|error`) and per pushed
+//!   notification
+//!
+//! Lines are redacted before they are stored. `kernel_logs_subscribe`
+//! returns the lines after a given sequence number and adds the window to
+//! the subscribers, which get every new line as a `kernel-output` event
+//! until `kernel_logs_unsubscribe`, the window closes or the subscribing
+//! session ends.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use crate::auth::TokenHash;
+use crate::locks::Recover;
+use crate::redact;
+
+/// Event carrying one new `OutputLine` to subscribed windows
+pub const KERNEL_OUTPUT_EVENT: &str = "kernel-output";
+
+/// Lines kept in memory
+pub const MAX_LINES: usize = 1000;
+
+/// Longest line kept (longer ones are cut)
+const MAX_LINE_LEN: usize = 4096;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// One line of kernel output
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct OutputLine {
+    /// Increases by one per line, starting at 1
+    pub seq: u64,
+    pub stream: Stream,
+    pub line: String,
+}
+
+struct OutputBuffer {
+    next_seq: u64,
+    lines: VecDeque<OutputLine>,
+}
+
+static OUTPUT: Mutex<OutputBuffer> = Mutex::new(OutputBuffer {
+    next_seq: 1,
+    lines: VecDeque::new(),
+});
+
+/// Redact and store `line`; returns it as stored
+pub fn record(stream: Stream, line: &str) -> OutputLine {
+    let mut line = redact::text(line.trim_end());
+    if let Some((end, _)) = line.char_indices().nth(MAX_LINE_LEN) {
+        line.truncate(end);
+    }
+    let mut output = OUTPUT.lock_or_recover();
+    let entry = OutputLine {
+        seq: output.next_seq,
+        stream,
+        line,
+    };
+    output.next_seq += 1;
+    if output.lines.len() == MAX_LINES {
+        output.lines.pop_front();
+    }
+    output.lines.push_back(entry.clone());
+    entry
+}
+
+/// Summary of a kernel response (`#id method ok|error: message`)
+pub fn describe_response(id: u64, method: &str, response: &Value) -> String {
+    match response.pointer("/error/message").and_then(Value::as_str) {
+        Some(message) => format!("#{id} {method} error: {message}"),
+        None => format!("#{id} {method} ok"),
+    }
+}
+
+/// Stored lines with `seq` greater than `after`, oldest first
+pub fn since(after: u64) -> Vec<OutputLine> {
+    OUTPUT
+        .lock_or_recover()
+        .lines
+        .iter()
+        .filter(|line| line.seq > after)
+        .cloned()
+        .collect()
+}
+
+/// Stored stderr lines, oldest first
+pub fn stderr_tail() -> Vec<String> {
+    OUTPUT
+        .lock_or_recover()
+        .lines
+        .iter()
+        .filter(|line| line.stream == Stream::Stderr)
+        .map(|line| line.line.clone())
+        .collect()
+}
+
+/// Windows following kernel output, with the hash of the session that
+/// subscribed (dropped once that session ends or locks)
+pub struct KernelOutputState(pub Mutex<HashMap<String, TokenHash>>);
+
+impl KernelOutputState {
+    pub fn new() -> Self {
+        Self(Mutex::new(HashMap::new()))
+    }
+}
+
+impl Default for KernelOutputState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_record_redacts_and_numbers_lines() {
+        let first = record(Stream::Stderr, "password=hunter2\n");
+        assert_eq!(first.line, "password=[REDACTED]");
+        let second = record(Stream::Stdout, "#1 ping ok");
+        assert!(second.seq > first.seq);
+        let after = since(first.seq);
+        assert!(after.iter().any(|line| line == &second));
+        assert!(after.iter().all(|line| line.seq > first.seq));
+        assert!(stderr_tail().contains(&first.line));
+        assert!(
+            record(Stream::Stderr, &"x".repeat(MAX_LINE_LEN * 2))
+                .line
+                .len()
+                <= MAX_LINE_LEN
+        );
+    }
+
+    #[test]
+    fn test_describe_response() {
+        assert_eq!(
+            describe_response(3, "chat/respond", &json!({ "result": {} })),
+            "#3 chat/respond ok"
+        );
+        assert_eq!(
+            describe_response(4, "x", &json!({ "error": { "message": "nope" } })),
+            "#4 x error: nope"
+        );
+    }
+}
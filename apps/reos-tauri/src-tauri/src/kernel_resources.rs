@@ -0,0 +1,457 @@
+//! Kernel Process Resource Usage
+//!
+//! The running kernel is sampled every `interval_secs`: resident memory,
+//! CPU (percent of one core since the previous sample), open file
+//! descriptors (handles on Windows) and direct child processes. Linux
+//! reads procfs, Windows the process APIs; other platforms have no samples.
+//!
+//! The latest sample is part of `kernel_status`, `health_check` and the
+//! metrics snapshot / Prometheus gauges. A resource going over its
+//! threshold is logged and sent to every window as a
+//! `kernel-resource-warning` event, once until it drops back under.
+//!
+//! Configurable via `kernel-resources.json` in the app data dir (`null`
+//! turns a warning off):
+//! `{ "interval_secs": 5, "rss_warn_bytes": 2147483648,
+//!    "cpu_warn_percent": 90.0, "open_fds_warn": 1000 }`
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::locks::Recover;
+
+/// Event carrying a `ResourceWarning` to every window
+pub const RESOURCE_WARNING_EVENT: &str = "kernel-resource-warning";
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ResourceConfig {
+    pub interval_secs: u64,
+    pub rss_warn_bytes: Option<u64>,
+    pub cpu_warn_percent: Option<f64>,
+    pub open_fds_warn: Option<u64>,
+}
+
+impl Default for ResourceConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 5,
+            rss_warn_bytes: Some(2 * 1024 * 1024 * 1024),
+            cpu_warn_percent: Some(90.0),
+            open_fds_warn: Some(1000),
+        }
+    }
+}
+
+impl ResourceConfig {
+    /// Load from `path`; missing or invalid file means defaults
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.max(1))
+    }
+}
+
+/// What the platform reports for a process
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RawSample {
+    pub rss_bytes: u64,
+    /// User plus system time since the process started
+    pub cpu_time: Duration,
+    pub open_fds: Option<u64>,
+    pub children: Option<u64>,
+}
+
+/// One sample of the kernel process
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ResourceUsage {
+    pub pid: u32,
+    pub rss_bytes: u64,
+    /// Percent of one core since the previous sample (`None` on the first)
+    pub cpu_percent: Option<f64>,
+    pub open_fds: Option<u64>,
+    pub children: Option<u64>,
+    /// Unix milliseconds
+    pub sampled_at: u64,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Resource {
+    Rss,
+    Cpu,
+    OpenFds,
+}
+
+/// `kernel-resource-warning` payload
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ResourceWarning {
+    pub resource: Resource,
+    pub value: f64,
+    pub threshold: f64,
+    pub usage: ResourceUsage,
+}
+
+struct Sampler {
+    config: ResourceConfig,
+    /// pid, CPU time and when, of the previous sample
+    previous: Option<(u32, Duration, Instant)>,
+    latest: Option<ResourceUsage>,
+    /// Resources currently over their threshold (already warned about)
+    over: Vec<Resource>,
+}
+
+impl Sampler {
+    /// Record `raw` for `pid`, returning newly crossed thresholds
+    fn add(&mut self, pid: u32, raw: RawSample, now: Instant) -> Vec<ResourceWarning> {
+        let cpu_percent = match self.previous {
+            Some((prev_pid, prev_cpu, prev_at)) if prev_pid == pid => {
+                let wall = now.saturating_duration_since(prev_at).as_secs_f64();
+                let cpu = raw.cpu_time.saturating_sub(prev_cpu).as_secs_f64();
+                (wall > 0.0).then(|| cpu / wall * 100.0)
+            }
+            _ => None,
+        };
+        self.previous = Some((pid, raw.cpu_time, now));
+        let usage = ResourceUsage {
+            pid,
+            rss_bytes: raw.rss_bytes,
+            cpu_percent,
+            open_fds: raw.open_fds,
+            children: raw.children,
+            sampled_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+        };
+
+        let checks = [
+            (
+                Resource::Rss,
+                Some(usage.rss_bytes as f64),
+                self.config.rss_warn_bytes.map(|t| t as f64),
+            ),
+            (
+                Resource::Cpu,
+                usage.cpu_percent,
+                self.config.cpu_warn_percent,
+            ),
+            (
+                Resource::OpenFds,
+                usage.open_fds.map(|n| n as f64),
+                self.config.open_fds_warn.map(|t| t as f64),
+            ),
+        ];
+        let mut warnings = Vec::new();
+        for (resource, value, threshold) in checks {
+            let (Some(value), Some(threshold)) = (value, threshold) else {
+                continue;
+            };
+            let was_over = self.over.contains(&resource);
+            if value > threshold && !was_over {
+                self.over.push(resource);
+                warnings.push(ResourceWarning {
+                    resource,
+                    value,
+                    threshold,
+                    usage: usage.clone(),
+                });
+            } else if value <= threshold && was_over {
+                self.over.retain(|r| *r != resource);
+            }
+        }
+        self.latest = Some(usage);
+        warnings
+    }
+
+    /// The kernel is gone: forget its samples
+    fn clear(&mut self) {
+        self.previous = None;
+        self.latest = None;
+        self.over.clear();
+    }
+}
+
+static SAMPLER: Mutex<Option<Sampler>> = Mutex::new(None);
+
+/// Install the deployment thresholds (at startup)
+pub fn configure(config: ResourceConfig) {
+    *SAMPLER.lock_or_recover() = Some(Sampler {
+        config,
+        previous: None,
+        latest: None,
+        over: Vec::new(),
+    });
+}
+
+/// Sampling interval
+pub fn interval() -> Duration {
+    SAMPLER.lock_or_recover().as_ref().map_or_else(
+        || ResourceConfig::default().interval(),
+        |s| s.config.interval(),
+    )
+}
+
+/// Sample `pid` (`None` = no kernel running); returns new warnings
+/// (already logged)
+pub fn sample(pid: Option<u32>) -> Vec<ResourceWarning> {
+    let raw = pid.and_then(|pid| platform::sample(pid).map(|raw| (pid, raw)));
+    let mut sampler = SAMPLER.lock_or_recover();
+    let Some(sampler) = sampler.as_mut() else {
+        return Vec::new();
+    };
+    let Some((pid, raw)) = raw else {
+        sampler.clear();
+        return Vec::new();
+    };
+    let warnings = sampler.add(pid, raw, Instant::now());
+    for warning in &warnings {
+        tracing::warn!(
+            resource = ?warning.resource,
+            value = warning.value,
+            threshold = warning.threshold,
+            pid,
+            "kernel resource usage over threshold"
+        );
+    }
+    warnings
+}
+
+/// The latest sample, if the kernel is running and the platform supported
+pub fn latest() -> Option<ResourceUsage> {
+    SAMPLER
+        .lock_or_recover()
+        .as_ref()
+        .and_then(|s| s.latest.clone())
+}
+
+#[cfg(any(target_os = "linux", test))]
+mod procfs {
+    use std::time::Duration;
+
+    /// Clock ticks per second in `/proc/<pid>/stat` (fixed by the kernel ABI)
+    const USER_HZ: u64 = 100;
+
+    /// Fields after the `(comm)` of a `/proc/<pid>/stat` line (state first)
+    fn stat_fields(stat: &str) -> Option<Vec<&str>> {
+        let (_, rest) = stat.rsplit_once(')')?;
+        Some(rest.split_whitespace().collect())
+    }
+
+    /// utime + stime
+    pub fn cpu_time(stat: &str) -> Option<Duration> {
+        let fields = stat_fields(stat)?;
+        let utime: u64 = fields.get(11)?.parse().ok()?;
+        let stime: u64 = fields.get(12)?.parse().ok()?;
+        Some(Duration::from_millis((utime + stime) * 1000 / USER_HZ))
+    }
+
+    pub fn parent_pid(stat: &str) -> Option<u32> {
+        stat_fields(stat)?.get(1)?.parse().ok()
+    }
+
+    /// `VmRSS` of a `/proc/<pid>/status` file, in bytes
+    pub fn rss_bytes(status: &str) -> Option<u64> {
+        let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+        let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+        Some(kb * 1024)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{procfs, RawSample};
+    use std::fs;
+
+    fn children(pid: u32) -> Option<u64> {
+        let count = fs::read_dir("/proc")
+            .ok()?
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().parse::<u32>().is_ok())
+            .filter_map(|entry| fs::read_to_string(entry.path().join("stat")).ok())
+            .filter(|stat| procfs::parent_pid(stat) == Some(pid))
+            .count();
+        Some(count as u64)
+    }
+
+    pub fn sample(pid: u32) -> Option<RawSample> {
+        let dir = format!("/proc/{pid}");
+        let stat = fs::read_to_string(format!("{dir}/stat")).ok()?;
+        let status = fs::read_to_string(format!("{dir}/status")).ok()?;
+        Some(RawSample {
+            rss_bytes: procfs::rss_bytes(&status)?,
+            cpu_time: procfs::cpu_time(&stat)?,
+            open_fds: fs::read_dir(format!("{dir}/fd"))
+                .ok()
+                .map(|fds| fds.count() as u64),
+            children: children(pid),
+        })
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::RawSample;
+    use std::time::Duration;
+    use windows_sys::Win32::Foundation::{CloseHandle, FILETIME, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
+        TH32CS_SNAPPROCESS,
+    };
+    use windows_sys::Win32::System::ProcessStatus::{
+        GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+    };
+    use windows_sys::Win32::System::Threading::{
+        GetProcessHandleCount, GetProcessTimes, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    fn filetime(ft: &FILETIME) -> Duration {
+        let ticks = (u64::from(ft.dwHighDateTime) << 32) | u64::from(ft.dwLowDateTime);
+        // 100 ns units
+        Duration::from_nanos(ticks.saturating_mul(100))
+    }
+
+    fn children(pid: u32) -> Option<u64> {
+        // SAFETY: the snapshot handle is checked and closed; the entry is
+        // sized as the API requires
+        unsafe {
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+            if snapshot == INVALID_HANDLE_VALUE {
+                return None;
+            }
+            let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+            entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+            let mut count = 0;
+            let mut more = Process32FirstW(snapshot, &mut entry) != 0;
+            while more {
+                if entry.th32ParentProcessID == pid {
+                    count += 1;
+                }
+                more = Process32NextW(snapshot, &mut entry) != 0;
+            }
+            CloseHandle(snapshot);
+            Some(count)
+        }
+    }
+
+    pub fn sample(pid: u32) -> Option<RawSample> {
+        // SAFETY: the process handle is checked and closed; out-parameters
+        // are zeroed structs of the sizes the APIs expect
+        let (rss_bytes, cpu_time, handles) = unsafe {
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if process.is_null() {
+                return None;
+            }
+            let mut memory: PROCESS_MEMORY_COUNTERS = std::mem::zeroed();
+            let memory_ok = GetProcessMemoryInfo(
+                process,
+                &mut memory,
+                std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32,
+            ) != 0;
+            let mut created: FILETIME = std::mem::zeroed();
+            let mut exited: FILETIME = std::mem::zeroed();
+            let mut kernel: FILETIME = std::mem::zeroed();
+            let mut user: FILETIME = std::mem::zeroed();
+            let times_ok =
+                GetProcessTimes(process, &mut created, &mut exited, &mut kernel, &mut user) != 0;
+            let mut handles = 0u32;
+            let handles_ok = GetProcessHandleCount(process, &mut handles) != 0;
+            CloseHandle(process);
+            if !memory_ok || !times_ok {
+                return None;
+            }
+            (
+                memory.WorkingSetSize as u64,
+                filetime(&kernel) + filetime(&user),
+                handles_ok.then_some(u64::from(handles)),
+            )
+        };
+        Some(RawSample {
+            rss_bytes,
+            cpu_time,
+            open_fds: handles,
+            children: children(pid),
+        })
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    use super::RawSample;
+
+    pub fn sample(_pid: u32) -> Option<RawSample> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sampler() -> Sampler {
+        Sampler {
+            config: ResourceConfig {
+                interval_secs: 5,
+                rss_warn_bytes: Some(1000),
+                cpu_warn_percent: Some(50.0),
+                open_fds_warn: None,
+            },
+            previous: None,
+            latest: None,
+            over: Vec::new(),
+        }
+    }
+
+    fn raw(rss_bytes: u64, cpu_ms: u64) -> RawSample {
+        RawSample {
+            rss_bytes,
+            cpu_time: Duration::from_millis(cpu_ms),
+            open_fds: Some(5000),
+            children: Some(0),
+        }
+    }
+
+    #[test]
+    fn test_cpu_percent_and_warnings_once_per_crossing() {
+        let mut sampler = sampler();
+        let start = Instant::now();
+        assert!(sampler.add(7, raw(500, 0), start).is_empty());
+        assert_eq!(sampler.latest.as_ref().unwrap().cpu_percent, None);
+
+        // 4.5 s of CPU in 5 s
+        let warnings = sampler.add(7, raw(2000, 4500), start + Duration::from_secs(5));
+        let resources: Vec<_> = warnings.iter().map(|w| w.resource).collect();
+        assert_eq!(resources, vec![Resource::Rss, Resource::Cpu]);
+        let cpu = sampler.latest.as_ref().unwrap().cpu_percent.unwrap();
+        assert!((cpu - 90.0).abs() < 0.01);
+
+        assert!(sampler
+            .add(7, raw(2000, 9000), start + Duration::from_secs(10))
+            .is_empty());
+        assert!(sampler
+            .add(7, raw(500, 9000), start + Duration::from_secs(15))
+            .is_empty());
+        let again = sampler.add(7, raw(2000, 9000), start + Duration::from_secs(20));
+        assert_eq!(again.len(), 1);
+        assert_eq!(again[0].resource, Resource::Rss);
+
+        // A new process starts without a CPU baseline
+        sampler.add(8, raw(100, 50_000), start + Duration::from_secs(25));
+        assert_eq!(sampler.latest.as_ref().unwrap().cpu_percent, None);
+    }
+
+    #[test]
+    fn test_procfs_parsing() {
+        let stat = "1234 (python (reos)) S 1200 1234 1200 0 -1 4194304 100 0 0 0 250 50 0 0 20 0";
+        assert_eq!(procfs::parent_pid(stat), Some(1200));
+        assert_eq!(procfs::cpu_time(stat), Some(Duration::from_secs(3)));
+        let status = "Name:\tpython\nVmPeak:\t  900 kB\nVmRSS:\t  512 kB\n";
+        assert_eq!(procfs::rss_bytes(status), Some(512 * 1024));
+    }
+}
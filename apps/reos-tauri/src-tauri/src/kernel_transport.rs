@@ -0,0 +1,325 @@
+//! Kernel Transports
+//!
+//! How frames travel between the shell and the kernel, set by
+//! `kernel.transport` in `config.toml` (or `REOS_KERNEL_TRANSPORT`) and
+//! read each time a kernel starts:
+//!
+//! - `stdio` (default): over the kernel's stdin and stdout. The kernel
+//!   lives exactly as long as its pipes
+//! - `unix`: over a Unix domain socket the kernel listens on, in a
+//!   directory of its own under `$XDG_RUNTIME_DIR` created with 0700
+//!   permissions (`reos-kernel/`, `reos-kernel@<profile>/` for named
+//!   profiles)
+//! - `pipe` (Windows): over a named pipe (`\\.\pipe\reos-kernel-<random>`)
+//!   the kernel creates as the first and only instance of its name, with a
+//!   protected DACL granting only the current user and remote clients
+//!   rejected (`kernel_pipe.py`). The shell connects at identification
+//!   level (the kernel can't impersonate it) and checks the pipe is served
+//!   by the kernel it expects. `kernel.json` is kept under the local app
+//!   data dir, private to the user
+//! - `remote`: over a WebSocket (or plain HTTPS where those are blocked) to
+//!   a kernel on another machine (`remote_kernel.rs`). Nothing is started
+//!   here; `kernel.json` under the local app data dir keeps the key so a
+//!   restarted shell gets its sessions back
+//!
+//! With `unix` and `pipe` the kernel's stdout and stderr only carry output,
+//! so a stray `print` can't corrupt a frame. When the shell goes away the
+//! kernel keeps running for `LINGER` and a shell started meanwhile
+//! reconnects to it instead of starting another, sessions included.
+//!
+//! Each kernel gets its own endpoint (`kernel-<random>.sock`, or a pipe
+//! named as above, so one being hot swapped in doesn't collide with the one
+//! serving), and `kernel.json` (0600) names the current one with its
+//! `__session` key. A reconnecting shell proves it is the one the kernel
+//! was started for with that key: `initialize` with any other is refused
+//! and the connection dropped. Each connection gets a fresh encrypted
+//! channel. Only the shell's own children have their output forwarded
+//! (`kernel_output`).
+//!
+//! Where a local transport isn't available (`unix` without
+//! `$XDG_RUNTIME_DIR` or off Unix, `pipe` off Windows) it falls back to
+//! `stdio` with a warning.
+
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::profile;
+
+/// Directory stem for `kernel.json` and sockets (scoped to the profile)
+const RUNTIME_DIR: &str = "reos-kernel";
+
+/// The current listening kernel, in the runtime dir
+const RECORD_FILE: &str = "kernel.json";
+
+/// How long a listening kernel waits for a shell to (re)connect before
+/// exiting
+pub const LINGER: Duration = Duration::from_secs(5 * 60);
+
+/// How long a freshly spawned kernel gets to start listening
+pub const LISTEN_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Transport {
+    #[default]
+    Stdio,
+    Unix,
+    Pipe,
+    Remote,
+}
+
+impl Transport {
+    /// Environment variable handing a spawned kernel its endpoint
+    pub fn endpoint_env(self) -> &'static str {
+        match self {
+            Transport::Pipe => "REOS_KERNEL_PIPE",
+            _ => "REOS_KERNEL_SOCKET",
+        }
+    }
+}
+
+/// The two directions of a connection to the kernel
+pub struct Link {
+    pub reader: BufReader<Box<dyn Read + Send>>,
+    pub writer: Box<dyn Write + Send>,
+    /// Process serving the endpoint, where the OS tells (named pipes)
+    pub peer_pid: Option<u32>,
+}
+
+impl Link {
+    pub fn new(reader: impl Read + Send + 'static, writer: impl Write + Send + 'static) -> Self {
+        Self {
+            reader: BufReader::new(Box::new(reader)),
+            writer: Box::new(writer),
+            peer_pid: None,
+        }
+    }
+
+    /// Connect to a kernel listening on `endpoint`
+    pub fn connect(transport: Transport, endpoint: &Path) -> io::Result<Self> {
+        match transport {
+            #[cfg(unix)]
+            Transport::Unix => {
+                let stream = std::os::unix::net::UnixStream::connect(endpoint)?;
+                let reader = stream.try_clone()?;
+                Ok(Self::new(reader, stream))
+            }
+            #[cfg(windows)]
+            Transport::Pipe => pipe::connect(endpoint),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("can't connect over {transport:?} here"),
+            )),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod pipe {
+    use std::io;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use std::path::Path;
+    use windows_sys::Win32::Storage::FileSystem::SECURITY_IDENTIFICATION;
+    use windows_sys::Win32::System::Pipes::GetNamedPipeServerProcessId;
+
+    use super::Link;
+
+    pub fn connect(name: &Path) -> io::Result<Link> {
+        // Identification level: the kernel may learn who we are, but can't
+        // act as us
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .security_qos_flags(SECURITY_IDENTIFICATION)
+            .open(name)?;
+        let mut pid = 0u32;
+        // SAFETY: the handle is a connected pipe owned by `file`, and `pid`
+        // outlives the call
+        if unsafe { GetNamedPipeServerProcessId(file.as_raw_handle() as _, &mut pid) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let reader = file.try_clone()?;
+        let mut link = Link::new(reader, file);
+        link.peer_pid = Some(pid);
+        Ok(link)
+    }
+}
+
+/// `kernel.json`: the listening kernel a new shell reconnects to
+#[derive(Serialize, Deserialize)]
+pub struct KernelRecord {
+    /// Socket path, pipe name or remote URL
+    pub endpoint: PathBuf,
+    /// `__session` key it was initialized with (hex)
+    pub key: String,
+}
+
+impl Drop for KernelRecord {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.key);
+    }
+}
+
+/// Where listening kernels of one transport are found: their endpoints
+/// and `kernel.json`
+#[derive(Clone, Debug)]
+pub struct RuntimeDir {
+    dir: PathBuf,
+    transport: Transport,
+}
+
+impl RuntimeDir {
+    /// The profile's runtime dir for `transport`, created (or tightened to
+    /// 0700 on Unix); `None` where the transport isn't available
+    pub fn open(transport: Transport) -> io::Result<Option<Self>> {
+        let base = match transport {
+            Transport::Unix if cfg!(unix) => std::env::var_os("XDG_RUNTIME_DIR")
+                .map(PathBuf::from)
+                .filter(|p| p.is_absolute()),
+            Transport::Pipe if cfg!(windows) => dirs::data_local_dir(),
+            Transport::Remote => dirs::data_local_dir(),
+            _ => None,
+        };
+        let Some(base) = base else {
+            return Ok(None);
+        };
+        let dir = Self::create(base.join(profile::scope_id(RUNTIME_DIR)))?;
+        Ok(Some(Self { dir, transport }))
+    }
+
+    fn create(dir: PathBuf) -> io::Result<PathBuf> {
+        let mut builder = std::fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        match builder.create(&dir) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e),
+        }
+        // Never follow a link planted in place of the directory
+        let meta = std::fs::symlink_metadata(&dir)?;
+        if !meta.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a directory", dir.display()),
+            ));
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if meta.permissions().mode() & 0o077 != 0 {
+                std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))?;
+            }
+        }
+        Ok(dir)
+    }
+
+    pub fn transport(&self) -> Transport {
+        self.transport
+    }
+
+    /// Endpoint for a new kernel: a socket in the dir, or a pipe name
+    pub fn new_endpoint(&self) -> PathBuf {
+        let mut bytes = [0u8; 8];
+        rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut bytes);
+        let id = hex::encode(bytes);
+        match self.transport {
+            Transport::Pipe => PathBuf::from(format!(r"\\.\pipe\{RUNTIME_DIR}-{id}")),
+            _ => self.dir.join(format!("kernel-{id}.sock")),
+        }
+    }
+
+    /// The kernel to reconnect to, if one was recorded
+    pub fn record(&self) -> Option<KernelRecord> {
+        let text =
+            zeroize::Zeroizing::new(std::fs::read_to_string(self.dir.join(RECORD_FILE)).ok()?);
+        serde_json::from_str(&text).ok()
+    }
+
+    /// Make `record` the kernel to reconnect to (written 0600, then
+    /// renamed into place)
+    pub fn set_record(&self, record: &KernelRecord) -> io::Result<()> {
+        let text = zeroize::Zeroizing::new(serde_json::to_string(record)?);
+        let temp = self.dir.join(format!("{RECORD_FILE}.tmp"));
+        let _ = std::fs::remove_file(&temp);
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&temp)?;
+        file.write_all(text.as_bytes())?;
+        std::fs::rename(&temp, self.dir.join(RECORD_FILE))
+    }
+
+    /// Forget the recorded kernel (gone, or not ours)
+    pub fn clear_record(&self) {
+        let _ = std::fs::remove_file(self.dir.join(RECORD_FILE));
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_socket_dir_private() {
+        let base = std::env::temp_dir().join(format!("reos-transport-{}", std::process::id()));
+        std::fs::create_dir_all(&base).unwrap();
+        let dir = base.join("kernel");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let socket_dir = RuntimeDir {
+            dir: RuntimeDir::create(dir.clone()).unwrap(),
+            transport: Transport::Unix,
+        };
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        assert!(socket_dir.record().is_none());
+
+        let socket = socket_dir.new_endpoint();
+        assert!(socket.starts_with(&dir));
+        assert_ne!(socket, socket_dir.new_endpoint());
+        for key in ["00ff", "abcd"] {
+            let record = KernelRecord {
+                endpoint: socket.clone(),
+                key: key.to_string(),
+            };
+            socket_dir.set_record(&record).unwrap();
+        }
+        let record = socket_dir.record().unwrap();
+        assert_eq!(
+            (record.endpoint.as_path(), record.key.as_str()),
+            (socket.as_path(), "abcd")
+        );
+        let mode = std::fs::metadata(dir.join(RECORD_FILE))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+        socket_dir.clear_record();
+        assert!(socket_dir.record().is_none());
+
+        let file = base.join("file");
+        std::fs::write(&file, "").unwrap();
+        assert!(RuntimeDir::create(file).is_err());
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_pipe_endpoint_name() {
+        let pipes = RuntimeDir {
+            dir: std::env::temp_dir(),
+            transport: Transport::Pipe,
+        };
+        let name = pipes.new_endpoint();
+        let name = name.to_str().unwrap();
+        assert!(name.starts_with(r"\\.\pipe\reos-kernel-"));
+        assert_eq!(name.len(), r"\\.\pipe\reos-kernel-".len() + 16);
+        assert_eq!(Transport::Pipe.endpoint_env(), "REOS_KERNEL_PIPE");
+    }
+}
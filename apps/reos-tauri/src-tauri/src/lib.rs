@@ -0,0 +1,91 @@
+//! ReOS Shell Library
+//!
+//! Everything but the Tauri app itself (`main.rs`: its state wiring and
+//! event loop, with the commands under `commands/`), shared with the
+//! `reos-cli` binary and the benchmarks.
+
+pub mod app_menu;
+pub mod appearance;
+pub mod audit;
+pub mod auth;
+pub mod auth_backend;
+pub mod autostart;
+pub mod biometric;
+pub mod blobs;
+pub mod cli;
+pub mod clipboard;
+pub mod config;
+pub mod consent;
+pub mod crash_report;
+pub mod deep_link;
+pub mod diagnostics;
+pub mod export;
+pub mod features;
+pub mod file_drop;
+pub mod first_run;
+pub mod global_shortcut;
+pub mod guest;
+pub mod health;
+pub mod hot_swap;
+pub mod ipc;
+pub mod json_stream;
+pub mod kernel;
+pub mod kernel_backend;
+pub mod kernel_embedded;
+pub mod kernel_integrity;
+pub mod kernel_lifecycle;
+pub mod kernel_output;
+pub mod kernel_resources;
+pub mod kernel_transport;
+pub mod locks;
+pub mod logging;
+#[cfg(target_os = "windows")]
+pub mod message_window;
+pub mod method_allowlist;
+pub mod method_manifest;
+pub mod method_policy;
+pub mod methods;
+pub mod metrics;
+pub mod migrations;
+pub mod notifications;
+pub mod oidc;
+pub mod open_external;
+pub mod os_session;
+pub mod params;
+pub mod password_policy;
+pub mod pin;
+pub mod portable;
+pub mod power;
+pub mod print;
+pub mod profile;
+pub mod quick_actions;
+pub mod rate_limit;
+pub mod redact;
+pub mod remote_grpc;
+pub mod remote_http;
+pub mod remote_kernel;
+pub mod request_audit;
+pub mod request_history;
+pub mod roles;
+pub mod scheduler;
+pub mod secret;
+pub mod session_events;
+pub mod session_vault;
+pub mod single_instance;
+pub mod slow_requests;
+pub mod splash;
+pub mod step_up;
+pub mod storage;
+pub mod system_lock;
+pub mod taskbar_progress;
+pub mod telemetry;
+pub mod totp;
+pub mod tray;
+pub mod updater;
+pub mod username;
+pub mod vault;
+pub mod warmup;
+pub mod window_nonce;
+pub mod window_policy;
+pub mod window_state;
+pub mod windows;
@@ -0,0 +1,134 @@
+//! Poison-Tolerant Locking
+//!
+//! A panic while a state mutex is held poisons it; mapping that to a
+//! fatal "lock poisoned" error left auth and kernel access broken until
+//! restart. Commands lock through `Recover` instead:
+//!
+//! - `lock_or_recover` keeps the data (audit logs, enrollment managers,
+//!   limiters), since their operations don't leave partial state behind
+//! - `lock_or_reset` runs a reset first, for state that must fail closed
+//!   (the session store is cleared so everyone logs in again; the kernel
+//!   handle is dropped and respawned on the next request)
+//!
+//! Either way the poison flag is cleared and the event is logged.
+//! `RwLock`s (the session store) get the same treatment through
+//! `RecoverRw`.
+
+use std::sync::{Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tracing::warn;
+
+pub trait Recover<T> {
+    /// Lock, taking over the data of a poisoned mutex as-is
+    fn lock_or_recover(&self) -> MutexGuard<'_, T>;
+
+    /// Lock, applying `reset` to the data of a poisoned mutex first
+    fn lock_or_reset(&self, reset: impl FnOnce(&mut T)) -> MutexGuard<'_, T>;
+}
+
+impl<T> Recover<T> for Mutex<T> {
+    fn lock_or_recover(&self) -> MutexGuard<'_, T> {
+        self.lock_or_reset(|_| {})
+    }
+
+    fn lock_or_reset(&self, reset: impl FnOnce(&mut T)) -> MutexGuard<'_, T> {
+        match self.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                warn!(
+                    lock = std::any::type_name::<T>(),
+                    "recovering poisoned lock"
+                );
+                let mut guard = poisoned.into_inner();
+                reset(&mut guard);
+                self.clear_poison();
+                guard
+            }
+        }
+    }
+}
+
+pub trait RecoverRw<T> {
+    /// Read-lock, resetting the data of a poisoned lock first
+    fn read_or_reset(&self, reset: impl FnOnce(&mut T)) -> RwLockReadGuard<'_, T>;
+
+    /// Write-lock, applying `reset` to the data of a poisoned lock first
+    fn write_or_reset(&self, reset: impl FnOnce(&mut T)) -> RwLockWriteGuard<'_, T>;
+}
+
+impl<T> RecoverRw<T> for RwLock<T> {
+    fn read_or_reset(&self, reset: impl FnOnce(&mut T)) -> RwLockReadGuard<'_, T> {
+        if let Ok(guard) = self.read() {
+            return guard;
+        }
+        // Only a writer's panic poisons: reset under the write lock
+        drop(self.write_or_reset(reset));
+        self.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write_or_reset(&self, reset: impl FnOnce(&mut T)) -> RwLockWriteGuard<'_, T> {
+        match self.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                warn!(
+                    lock = std::any::type_name::<T>(),
+                    "recovering poisoned lock"
+                );
+                let mut guard = poisoned.into_inner();
+                reset(&mut guard);
+                self.clear_poison();
+                guard
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn poison(mutex: &Arc<Mutex<Vec<u32>>>) {
+        let mutex = Arc::clone(mutex);
+        let _ = std::thread::spawn(move || {
+            let mut guard = mutex.lock().unwrap();
+            guard.push(2);
+            panic!("boom");
+        })
+        .join();
+    }
+
+    #[test]
+    fn test_recover_keeps_data_and_clears_poison() {
+        let mutex = Arc::new(Mutex::new(vec![1]));
+        poison(&mutex);
+        assert!(mutex.is_poisoned());
+        assert_eq!(*mutex.lock_or_recover(), vec![1, 2]);
+        assert!(!mutex.is_poisoned());
+    }
+
+    #[test]
+    fn test_reset_only_runs_when_poisoned() {
+        let mutex = Arc::new(Mutex::new(vec![1]));
+        assert_eq!(*mutex.lock_or_reset(|v| v.clear()), vec![1]);
+        poison(&mutex);
+        assert!(mutex.lock_or_reset(|v| v.clear()).is_empty());
+        assert!(mutex.lock().is_ok());
+    }
+
+    #[test]
+    fn test_rwlock_reset_on_read() {
+        let lock = Arc::new(RwLock::new(vec![1]));
+        {
+            let lock = Arc::clone(&lock);
+            let _ = std::thread::spawn(move || {
+                let _guard = lock.write().unwrap();
+                panic!("boom");
+            })
+            .join();
+        }
+        assert!(lock.is_poisoned());
+        assert!(lock.read_or_reset(|v| v.clear()).is_empty());
+        assert!(!lock.is_poisoned());
+        assert_eq!(*lock.write_or_reset(|v| v.push(9)), Vec::<u32>::new());
+    }
+}
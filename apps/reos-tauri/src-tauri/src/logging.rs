@@ -0,0 +1,642 @@
+//! Structured Logging for ReOS
+//!
+//! Everything logs through `tracing`. Commands run in a `command` span
+//! (name, window) and kernel requests in a `kernel_request` span (method,
+//! id), so an event carries the call it happened in.
+//!
+//! Events are written as JSON lines to `logs/reos.log` under the app data
+//! dir, rotated when the file would pass `max_bytes` or on a new (UTC) day;
+//! `keep` older files are kept as `reos.log.1` (newest) to `reos.log.<keep>`.
+//! Debug builds also print events to the console. Every line passes through
+//! `redact` on its way out. The level comes from `RUST_LOG`
+//! (default `warn,reos_tauri=debug`).
+//!
+//! The last `RECENT_LINES` file lines are also kept in memory for crash
+//! reports.
+//!
+//! The subscriber starts with the process; events before the file is
+//! opened (during setup) only reach the console.
+//! Builds with the `otel` feature also have an (initially empty) slot for
+//! the span exporter that `telemetry` fills when it is configured.
+//!
+//! Levels can be changed at runtime (`log_set_level`) until the app exits,
+//! so support can ask for `kernel=debug` without a restart. Shell modules
+//! may be named without the crate prefix (`kernel` for `reos_tauri::kernel`);
+//! `*` is the level for everything not listed.
+//!
+//! Configurable via `logging.json` in the app data dir:
+//! `{ "max_bytes": 10485760, "keep": 5 }`
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, PoisonError, TryLockError};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tracing::Level;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::Layered;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+use crate::redact;
+
+/// Active log file; rotated ones are `reos.log.<n>`
+pub const LOG_FILE: &str = "reos.log";
+/// Debug for the shell keeps Tauri's per-command spans, which are
+/// expanded into this crate at debug level
+const DEFAULT_FILTER: &str = "warn,reos_tauri=debug";
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+/// Target of the default level
+const ANY_TARGET: &str = "*";
+const CRATE_TARGET: &str = "reos_tauri";
+const LEVELS: &[&str] = &["off", "error", "warn", "info", "debug", "trace"];
+const MAX_TARGET_LEN: usize = 128;
+/// Redacted lines kept in memory for crash reports
+pub const RECENT_LINES: usize = 200;
+/// Target of webview entries from `frontend_log`
+pub const FRONTEND_TARGET: &str = "reos_tauri::frontend";
+const MAX_FRONTEND_MESSAGE: usize = 4096;
+const MAX_FRONTEND_CONTEXT: usize = 8192;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum LogError {
+    #[error("Unknown log level {0}")]
+    Level(String),
+    #[error("Invalid log target {0:?}")]
+    Target(String),
+    #[error("Logging is not initialized")]
+    Uninitialized,
+    #[error("Cannot apply log filter: {0}")]
+    Reload(String),
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Size a log file may reach before it's rotated
+    pub max_bytes: u64,
+    /// Rotated files kept besides the current one
+    pub keep: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: 10 * 1024 * 1024,
+            keep: 5,
+        }
+    }
+}
+
+impl LoggingConfig {
+    /// Load from `path`; missing or invalid file means defaults
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Days since the Unix epoch (UTC)
+fn day(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / SECS_PER_DAY)
+        .unwrap_or(0)
+}
+
+/// Log file rotated by size and day
+pub struct RotatingFile {
+    dir: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: File,
+    size: u64,
+    day: u64,
+}
+
+impl RotatingFile {
+    pub fn open(dir: &Path, config: &LoggingConfig) -> io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(LOG_FILE);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            max_bytes: config.max_bytes,
+            keep: config.keep,
+            file,
+            size: metadata.len(),
+            day: day(metadata.modified().unwrap_or_else(|_| SystemTime::now())),
+        })
+    }
+
+    /// `reos.log` for 0, `reos.log.<n>` for older files
+    fn path(&self, n: usize) -> PathBuf {
+        match n {
+            0 => self.dir.join(LOG_FILE),
+            n => self.dir.join(format!("{LOG_FILE}.{n}")),
+        }
+    }
+
+    fn rotate(&mut self, now: SystemTime) -> io::Result<()> {
+        let _ = std::fs::remove_file(self.path(self.keep));
+        for n in (0..self.keep).rev() {
+            match std::fs::rename(self.path(n), self.path(n + 1)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(self.path(0))?;
+        self.size = 0;
+        self.day = day(now);
+        Ok(())
+    }
+
+    /// Append `line`, rotating first if it would overflow the file or a
+    /// new day started
+    pub fn write_line(&mut self, line: &[u8], now: SystemTime) -> io::Result<()> {
+        let overflow = self.size + line.len() as u64 > self.max_bytes;
+        if self.size > 0 && (overflow || day(now) != self.day) {
+            self.rotate(now)?;
+        }
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+static LOG_FILE_SINK: OnceLock<Mutex<RotatingFile>> = OnceLock::new();
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+fn remember(line: &[u8]) {
+    let mut recent = RECENT.lock().unwrap_or_else(PoisonError::into_inner);
+    if recent.len() == RECENT_LINES {
+        recent.pop_front();
+    }
+    recent.push_back(String::from_utf8_lossy(line).trim_end().to_string());
+}
+
+/// Up to the last `n` (redacted) log lines, oldest first; empty if another
+/// thread is logging right now (safe to call from a panic hook)
+pub fn recent_lines(n: usize) -> Vec<String> {
+    let recent = match RECENT.try_lock() {
+        Ok(recent) => recent,
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(TryLockError::WouldBlock) => return Vec::new(),
+    };
+    recent
+        .iter()
+        .skip(recent.len().saturating_sub(n))
+        .cloned()
+        .collect()
+}
+
+/// Levels by target, as given to `EnvFilter`
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Levels {
+    /// Level for targets not listed
+    pub default: String,
+    pub targets: BTreeMap<String, String>,
+}
+
+impl Levels {
+    /// Parse `warn,reos_tauri=debug` style directives; ones with span or
+    /// field filters are dropped
+    fn parse(spec: &str) -> Self {
+        let mut levels = Self {
+            default: "error".to_string(),
+            targets: BTreeMap::new(),
+        };
+        for directive in spec.split(',').map(str::trim) {
+            match directive.split_once('=') {
+                _ if directive.contains('[') => {}
+                Some((target, level)) => {
+                    if let (true, Ok(level)) = (valid_target(target), check_level(level)) {
+                        levels.targets.insert(target.to_string(), level);
+                    }
+                }
+                None => match check_level(directive) {
+                    Ok(level) => levels.default = level,
+                    // A bare target enables everything from it
+                    Err(_) if valid_target(directive) => {
+                        levels
+                            .targets
+                            .insert(directive.to_string(), "trace".to_string());
+                    }
+                    Err(_) => {}
+                },
+            }
+        }
+        levels
+    }
+
+    /// Set (or with `None`, clear) the level of `target`
+    pub fn set(&mut self, target: &str, level: Option<&str>) -> Result<(), LogError> {
+        let level = level.map(check_level).transpose()?;
+        if target == ANY_TARGET {
+            self.default = level.unwrap_or_else(|| Levels::parse(DEFAULT_FILTER).default);
+            return Ok(());
+        }
+        let target = check_target(target)?;
+        match level {
+            Some(level) => self.targets.insert(target, level),
+            None => self.targets.remove(&target),
+        };
+        Ok(())
+    }
+
+    fn directives(&self) -> String {
+        std::iter::once(self.default.clone())
+            .chain(self.targets.iter().map(|(t, l)| format!("{t}={l}")))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+fn check_level(level: &str) -> Result<String, LogError> {
+    let lower = level.trim().to_ascii_lowercase();
+    if LEVELS.contains(&lower.as_str()) {
+        Ok(lower)
+    } else {
+        Err(LogError::Level(level.to_string()))
+    }
+}
+
+/// Whether `level` is one of `LEVELS` (any case)
+pub fn is_level(level: &str) -> bool {
+    check_level(level).is_ok()
+}
+
+fn valid_target(target: &str) -> bool {
+    !target.is_empty()
+        && target.len() <= MAX_TARGET_LEN
+        && target.split("::").all(|part| {
+            !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+/// A module path; bare names are modules of this crate
+fn check_target(target: &str) -> Result<String, LogError> {
+    let target = target.trim();
+    if !valid_target(target) {
+        return Err(LogError::Target(target.to_string()));
+    }
+    if target.contains("::") || target == CRATE_TARGET {
+        Ok(target.to_string())
+    } else {
+        Ok(format!("{CRATE_TARGET}::{target}"))
+    }
+}
+
+/// The live filter and the levels it was built from
+struct FilterControl {
+    handle: reload::Handle<EnvFilter, Registry>,
+    levels: Mutex<Levels>,
+}
+
+static FILTER: OnceLock<FilterControl> = OnceLock::new();
+
+/// The subscriber below the span exporter slot (registry and filter)
+pub type LayerBase = Layered<reload::Layer<EnvFilter, Registry>, Registry>;
+
+#[cfg(feature = "otel")]
+static SPAN_LAYER: OnceLock<reload::Handle<crate::telemetry::SpanLayer, LayerBase>> =
+    OnceLock::new();
+
+/// Fill (or with `None`, empty) the span exporter slot
+#[cfg(feature = "otel")]
+pub fn set_span_layer(layer: crate::telemetry::SpanLayer) -> Result<(), LogError> {
+    SPAN_LAYER
+        .get()
+        .ok_or(LogError::Uninitialized)?
+        .reload(layer)
+        .map_err(|e| LogError::Reload(e.to_string()))
+}
+
+/// Current logging setup, for support
+#[derive(Serialize)]
+pub struct LogConfig {
+    pub levels: Levels,
+    /// Active log file (`None` if file logging is unavailable)
+    pub file: Option<PathBuf>,
+    pub max_bytes: Option<u64>,
+    pub keep: Option<usize>,
+}
+
+pub fn config() -> Result<LogConfig, LogError> {
+    let control = FILTER.get().ok_or(LogError::Uninitialized)?;
+    let levels = control
+        .levels
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+    let file = LOG_FILE_SINK
+        .get()
+        .map(|file| file.lock().unwrap_or_else(PoisonError::into_inner));
+    Ok(LogConfig {
+        levels,
+        file: file.as_ref().map(|f| f.path(0)),
+        max_bytes: file.as_ref().map(|f| f.max_bytes),
+        keep: file.as_ref().map(|f| f.keep),
+    })
+}
+
+/// Change the level of `target` (`*` for the default; `None` resets) until
+/// the app exits
+pub fn set_level(target: &str, level: Option<&str>) -> Result<Levels, LogError> {
+    let control = FILTER.get().ok_or(LogError::Uninitialized)?;
+    let mut levels = control
+        .levels
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let mut updated = levels.clone();
+    updated.set(target, level)?;
+    let filter =
+        EnvFilter::try_new(updated.directives()).map_err(|e| LogError::Reload(e.to_string()))?;
+    control
+        .handle
+        .reload(filter)
+        .map_err(|e| LogError::Reload(e.to_string()))?;
+    *levels = updated.clone();
+    Ok(updated)
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Log an entry from the webview under `FRONTEND_TARGET`, so it lands in
+/// the same files (and redaction) as the shell's own events; `log` is
+/// taken as `info`, like `console.log`
+pub fn frontend(
+    window: &str,
+    level: &str,
+    message: &str,
+    context: Option<Value>,
+) -> Result<(), LogError> {
+    let message = truncate(message, MAX_FRONTEND_MESSAGE);
+    let context = context.map(|mut context| {
+        redact::value(&mut context);
+        truncate(&context.to_string(), MAX_FRONTEND_CONTEXT)
+    });
+    let context = context.as_deref();
+    macro_rules! emit {
+        ($level:expr) => {
+            tracing::event!(target: FRONTEND_TARGET, $level, window, context, "{message}")
+        };
+    }
+    match level.to_ascii_lowercase().as_str() {
+        "error" => emit!(Level::ERROR),
+        "warn" => emit!(Level::WARN),
+        "info" | "log" => emit!(Level::INFO),
+        "debug" => emit!(Level::DEBUG),
+        "trace" => emit!(Level::TRACE),
+        _ => return Err(LogError::Level(level.to_string())),
+    }
+    Ok(())
+}
+
+/// A formatted event with its secrets redacted: JSON lines value by value
+/// (so the line stays valid JSON), anything else as text
+fn redact_line(line: &[u8]) -> Vec<u8> {
+    match serde_json::from_slice::<Value>(line) {
+        Ok(mut value) => {
+            redact::value(&mut value);
+            let mut line = serde_json::to_vec(&value).unwrap_or_default();
+            line.push(b'\n');
+            line
+        }
+        Err(_) => redact::text(&String::from_utf8_lossy(line)).into_bytes(),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Sink {
+    File,
+    Console,
+}
+
+impl<'a> MakeWriter<'a> for Sink {
+    type Writer = EventWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        EventWriter {
+            sink: *self,
+            buf: Vec::new(),
+        }
+    }
+}
+
+/// Collects one formatted event and writes it, redacted, when dropped
+struct EventWriter {
+    sink: Sink,
+    buf: Vec<u8>,
+}
+
+impl Write for EventWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for EventWriter {
+    fn drop(&mut self) {
+        if self.buf.is_empty() {
+            return;
+        }
+        let line = redact_line(&self.buf);
+        match self.sink {
+            Sink::File => {
+                remember(&line);
+                // Not `lock_or_recover`: recovering logs, which would land here
+                if let Some(file) = LOG_FILE_SINK.get() {
+                    let mut file = file.lock().unwrap_or_else(PoisonError::into_inner);
+                    let _ = file.write_line(&line, SystemTime::now());
+                }
+            }
+            Sink::Console => {
+                let _ = io::stderr().write_all(&line);
+            }
+        }
+    }
+}
+
+/// Default level from `config.toml` (`None` = built-in); ignored if
+/// `RUST_LOG` chose the levels
+pub fn set_default_level(level: Option<&str>) -> Result<(), LogError> {
+    if std::env::var_os(EnvFilter::DEFAULT_ENV).is_none() {
+        set_level(ANY_TARGET, level)?;
+    }
+    Ok(())
+}
+
+/// Install the global subscriber (once, at process start)
+pub fn init() {
+    let spec = std::env::var(EnvFilter::DEFAULT_ENV).unwrap_or_else(|_| DEFAULT_FILTER.into());
+    let levels = Levels::parse(&spec);
+    let (filter, handle) = reload::Layer::new(EnvFilter::new(levels.directives()));
+    let file = fmt::layer()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_writer(Sink::File);
+    let console = cfg!(debug_assertions).then(|| fmt::layer().with_writer(Sink::Console));
+    let subscriber: LayerBase = tracing_subscriber::registry().with(filter);
+    // Empty until `telemetry::init` finds an OTLP endpoint
+    #[cfg(feature = "otel")]
+    let subscriber = {
+        let (spans, handle) = reload::Layer::new(None);
+        let _ = SPAN_LAYER.set(handle);
+        subscriber.with(spans)
+    };
+    let _ = subscriber.with(file).with(console).try_init();
+    let _ = FILTER.set(FilterControl {
+        handle,
+        levels: Mutex::new(levels),
+    });
+}
+
+/// Start writing to `dir` (first call wins)
+pub fn open_file(dir: &Path, config: &LoggingConfig) {
+    match RotatingFile::open(dir, config) {
+        Ok(file) => {
+            let _ = LOG_FILE_SINK.set(Mutex::new(file));
+        }
+        Err(e) => tracing::warn!(error = %e, "file logging unavailable"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("reos-logs-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_rotates_by_size_and_keeps_limit() {
+        let dir = temp_dir("size");
+        let config = LoggingConfig {
+            max_bytes: 10,
+            keep: 2,
+        };
+        let mut file = RotatingFile::open(&dir, &config).unwrap();
+        let now = SystemTime::now();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_line(line.as_bytes(), now).unwrap();
+        }
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("reos.log"), "fourth\n");
+        assert_eq!(read("reos.log.1"), "third\n");
+        assert_eq!(read("reos.log.2"), "second\n");
+        assert!(!dir.join("reos.log.3").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rotates_on_a_new_day() {
+        let dir = temp_dir("day");
+        let mut file = RotatingFile::open(&dir, &LoggingConfig::default()).unwrap();
+        let now = SystemTime::now();
+        file.write_line(b"today\n", now).unwrap();
+        file.write_line(b"still today\n", now).unwrap();
+        file.write_line(b"tomorrow\n", now + Duration::from_secs(SECS_PER_DAY))
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("reos.log.1")).unwrap(),
+            "today\nstill today\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("reos.log")).unwrap(),
+            "tomorrow\n"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_levels() {
+        let mut levels = Levels::parse(DEFAULT_FILTER);
+        assert_eq!(levels.default, "warn");
+        assert_eq!(levels.targets["reos_tauri"], "debug");
+
+        levels.set("kernel", Some("TRACE")).unwrap();
+        levels.set("tauri::ipc", Some("info")).unwrap();
+        levels.set("*", Some("error")).unwrap();
+        assert_eq!(
+            levels.directives(),
+            "error,reos_tauri=debug,reos_tauri::kernel=trace,tauri::ipc=info"
+        );
+        levels.set("reos_tauri::kernel", None).unwrap();
+        levels.set("*", None).unwrap();
+        assert_eq!(levels.directives(), "warn,reos_tauri=debug,tauri::ipc=info");
+
+        assert_eq!(
+            levels.set("kernel", Some("loud")),
+            Err(LogError::Level("loud".into()))
+        );
+        assert!(levels.set("a=b,c", Some("debug")).is_err());
+        assert!(levels.set("tauri::", Some("debug")).is_err());
+        let parsed = Levels::parse("info,[span]=trace,zbus");
+        assert_eq!(parsed.default, "info");
+        assert_eq!(parsed.targets["zbus"], "trace");
+        assert_eq!(parsed.targets.len(), 1);
+    }
+
+    #[test]
+    fn test_recent_lines_keep_the_tail() {
+        for i in 0..RECENT_LINES + 5 {
+            remember(format!("line {i}\n").as_bytes());
+        }
+        let last = RECENT_LINES + 4;
+        assert_eq!(
+            recent_lines(2),
+            vec![format!("line {}", last - 1), format!("line {last}")]
+        );
+        assert_eq!(recent_lines(usize::MAX).len(), RECENT_LINES);
+    }
+
+    #[test]
+    fn test_frontend_levels_and_truncation() {
+        assert!(frontend("main", "log", "clicked save", None).is_ok());
+        assert!(frontend("main", "ERROR", "boom", Some(serde_json::json!({ "a": 1 }))).is_ok());
+        assert_eq!(
+            frontend("main", "fatal", "boom", None),
+            Err(LogError::Level("fatal".into()))
+        );
+        assert_eq!(truncate("héllo", 2), "hé...");
+        assert_eq!(truncate("hi", 2), "hi");
+    }
+
+    #[test]
+    fn test_redact_line_keeps_json_valid() {
+        let line = br#"{"fields":{"message":"login failed","password":"hunter2","detail":"Authorization: Bearer abcdefghijkl\""}}"#;
+        let redacted: Value = serde_json::from_slice(&redact_line(line)).unwrap();
+        assert_eq!(redacted["fields"]["password"], "[REDACTED]");
+        assert_eq!(redacted["fields"]["message"], "login failed");
+        assert!(!redacted.to_string().contains("abcdefghijkl"));
+        assert_eq!(
+            redact_line(b"token=abc123\n"),
+            b"token=[REDACTED]\n".to_vec()
+        );
+    }
+}
@@ -1,239 +1,1027 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod auth;
-mod kernel;
+mod commands;
+mod shell;
 
-use auth::{AuthResult, AuthState, SessionInfo};
-use kernel::{KernelError, KernelProcess};
-use serde_json::{json, Value};
+use commands::app::{apply_config, current_settings, headless_check, install_panic_hook};
+use commands::file::handle_file_drop;
+use commands::kernel::{emit_integrity_failure, emit_kernel_methods_changed, send_kernel_output};
+use reos_tauri_lib::app_menu::AppMenuState;
+use reos_tauri_lib::appearance::{AppearanceState, ColorScheme, APPEARANCE_EVENT};
+use reos_tauri_lib::audit::{AuditEntry, AuditEvent, AuditState};
+use reos_tauri_lib::auth::AuthState;
+use reos_tauri_lib::auth_backend::{AuthBackendState, BackendConfig};
+use reos_tauri_lib::autostart::AutostartLaunch;
+use reos_tauri_lib::biometric::BiometricState;
+use reos_tauri_lib::blobs::BlobConfig;
+use reos_tauri_lib::cli::CliArgs;
+use reos_tauri_lib::clipboard::{ClipboardConfig, ClipboardState};
+use reos_tauri_lib::config::{
+    ConfigChange, ConfigState, FileStamp, CONFIG_CHANGED_EVENT, SETTINGS_CHANGED_EVENT,
+};
+use reos_tauri_lib::consent::{ConsentPolicy, ConsentState};
+use reos_tauri_lib::crash_report::{CrashReportConfig, CrashReportState, CrashReports};
+use reos_tauri_lib::deep_link::{DeepLinkConfig, DeepLinkState};
+use reos_tauri_lib::features::{FeaturesState, FEATURES_CHANGED_EVENT};
+use reos_tauri_lib::file_drop::{DropPolicy, FileDropState};
+use reos_tauri_lib::global_shortcut::{Shortcut, ShortcutConfig};
+use reos_tauri_lib::guest::{GuestPolicy, GuestState};
+use reos_tauri_lib::hot_swap::HotSwapError;
+use reos_tauri_lib::kernel::{KernelError, KernelEvent, SharedKernel};
+use reos_tauri_lib::kernel_backend::MockKernelConfig;
+use reos_tauri_lib::kernel_integrity::KernelSource;
+use reos_tauri_lib::kernel_lifecycle::StartTrigger;
+use reos_tauri_lib::kernel_output::KernelOutputState;
+use reos_tauri_lib::kernel_resources::{ResourceConfig, RESOURCE_WARNING_EVENT};
+use reos_tauri_lib::locks::Recover;
+use reos_tauri_lib::logging::LoggingConfig;
+use reos_tauri_lib::method_allowlist::{
+    MethodAllowlist, MethodAllowlistConfig, MethodAllowlistState,
+};
+use reos_tauri_lib::method_manifest::MethodManifestState;
+use reos_tauri_lib::method_policy::{MethodPolicy, MethodPolicyState};
+use reos_tauri_lib::metrics::MetricsConfig;
+use reos_tauri_lib::migrations::{MigrationReport, MigrationState};
+use reos_tauri_lib::notifications::{
+    Category, Notification, NotificationConfig, NotificationState,
+};
+use reos_tauri_lib::oidc::{OidcConfig, OidcState};
+use reos_tauri_lib::open_external::{OpenExternalState, OpenPolicy};
+use reos_tauri_lib::os_session::{OsLoginPolicy, OsLoginState};
+use reos_tauri_lib::password_policy::{PasswordPolicy, PasswordPolicyState};
+use reos_tauri_lib::pin::PinState;
+use reos_tauri_lib::power::{PowerEvent, PowerState, POWER_EVENT};
+use reos_tauri_lib::profile::ProfilePaths;
+use reos_tauri_lib::quick_actions::QuickActionsState;
+use reos_tauri_lib::rate_limit::{
+    FrontendLogLimitState, LoginRateLimitState, RequestRateLimitState, RequestRateLimiter,
+    RequestRatePolicy,
+};
+use reos_tauri_lib::redact::RedactionConfig;
+use reos_tauri_lib::remote_kernel::RemoteKernelConfig;
+use reos_tauri_lib::request_audit::RequestAuditState;
+use reos_tauri_lib::request_history::RequestHistoryConfig;
+use reos_tauri_lib::roles::{RoleMapping, RoleState};
+use reos_tauri_lib::scheduler::{Scheduler, SchedulerConfig, SchedulerState};
+use reos_tauri_lib::session_events::SessionEvent;
+use reos_tauri_lib::session_vault::{PersistencePolicy, SessionVaultState};
+use reos_tauri_lib::single_instance::{Instance, Launch};
+use reos_tauri_lib::slow_requests::{SlowRequestConfig, SLOW_REQUEST_EVENT};
+use reos_tauri_lib::step_up::{StepUpPolicy, StepUpState};
+use reos_tauri_lib::system_lock::LockReason;
+use reos_tauri_lib::taskbar_progress::{KernelProgress, TaskbarProgressState};
+use reos_tauri_lib::telemetry::TelemetryConfig;
+use reos_tauri_lib::totp::TotpState;
+use reos_tauri_lib::tray::TrayState;
+use reos_tauri_lib::updater::{Updater, UpdaterConfig, UpdaterState};
+use reos_tauri_lib::username::{UsernamePolicy, UsernameState};
+use reos_tauri_lib::warmup::{WarmupConfig, WarmupState};
+use reos_tauri_lib::window_nonce::WindowNonceState;
+use reos_tauri_lib::window_policy::{WindowPolicy, WindowPolicyState};
+use reos_tauri_lib::window_state::{WindowStateState, WindowStateStore};
+use reos_tauri_lib::windows::{WindowRegistry, WindowsState};
+use reos_tauri_lib::{
+    app_menu, appearance, blobs, config, crash_report, deep_link, features, global_shortcut,
+    hot_swap, kernel, kernel_backend, kernel_integrity, kernel_resources, logging, method_manifest,
+    metrics, migrations, notifications, portable, power, profile, quick_actions, redact,
+    remote_kernel, request_history, single_instance, slow_requests, splash, system_lock, telemetry,
+    tray, windows,
+};
+use serde_json::json;
+use shell::kernel::{notify_kernel, prepare_kernel, KernelState};
+use shell::sessions::{
+    lock_all_sessions, persist_sessions, restore_sessions, spawn_session_sweeper,
+};
+use shell::tray::refresh_tray;
+use shell::window::{
+    finish_task_progress, persist_window_state, record_window_state, report_task_progress,
+    restore_window_state, show_main_window, show_taskbar_progress,
+};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use tauri::ipc::Invoke;
+use tauri::webview::PageLoadEvent;
+use tauri::{
+    AppHandle, DragDropEvent, Emitter, Listener, Manager, RunEvent, Runtime, Theme, WindowEvent,
+    Wry,
+};
+use tracing::{info_span, warn};
 
-use tauri::State;
-
-struct KernelState(Arc<Mutex<Option<KernelProcess>>>);
-
-// =============================================================================
-// Authentication Commands
-// =============================================================================
-
-/// Authenticate a user via Python kernel (PAM happens there)
-///
-/// Flow:
-/// 1. Frontend calls this with username/password
-/// 2. We forward to Python kernel's auth/login endpoint
-/// 3. Python validates via PAM and derives encryption key
-/// 4. Python returns success + session token
-/// 5. We store the session token for future request validation
-#[tauri::command]
-async fn auth_login(
-    state: State<'_, KernelState>,
-    auth_state: State<'_, AuthState>,
-    username: String,
-) -> Result<AuthResult, String> {
-    // Validate username format (prevent injection)
-    if username.is_empty() || username.len() > 32 {
-        return Ok(AuthResult {
-            success: false,
-            session_token: None,
-            username: None,
-            error: Some("Invalid username".to_string()),
+/// Poll `config.toml` and `rate-limits.json` for outside edits and apply
+/// them live (see `config.rs`)
+fn spawn_config_watcher(app: AppHandle) {
+    let config_path = app.state::<ConfigState>().path.clone();
+    let rate_limits_path = app
+        .path()
+        .profile_data_dir()
+        .ok()
+        .map(|d| d.join("rate-limits.json"));
+    let stamp = |path: &Option<PathBuf>| path.as_deref().map(FileStamp::of);
+    let mut seen = (stamp(&config_path), stamp(&rate_limits_path));
+    let spawned = std::thread::Builder::new()
+        .name("config-watch".into())
+        .spawn(move || loop {
+            std::thread::sleep(config::WATCH_INTERVAL);
+            let current = (stamp(&config_path), stamp(&rate_limits_path));
+            if current == seen {
+                continue;
+            }
+            let mut change = ConfigChange::default();
+            if current.0 != seen.0 {
+                let state = app.state::<ConfigState>();
+                change = state.reload();
+                apply_config(&state.config());
+            }
+            if current.1 != seen.1 {
+                let policy = RequestRatePolicy::load(rate_limits_path.as_deref());
+                app.state::<RequestRateLimitState>()
+                    .0
+                    .lock_or_recover()
+                    .set_policy(policy);
+                change.changed.push(config::RATE_LIMITS_KEY);
+            }
+            seen = current;
+            if change.is_empty() {
+                continue;
+            }
+            tracing::info!(changed = ?change.changed, "configuration reloaded");
+            if let Err(e) = app.emit(CONFIG_CHANGED_EVENT, &change) {
+                warn!(error = %e, "failed to emit {CONFIG_CHANGED_EVENT}");
+            }
+            if let Err(e) = app.emit(SETTINGS_CHANGED_EVENT, current_settings(&app)) {
+                warn!(error = %e, "failed to emit {SETTINGS_CHANGED_EVENT}");
+            }
+            if change.changed.contains(&config::FEATURES_KEY) {
+                if let Err(e) = app.emit(FEATURES_CHANGED_EVENT, ()) {
+                    warn!(error = %e, "failed to emit {FEATURES_CHANGED_EVENT}");
+                }
+            }
         });
+    if let Err(e) = spawned {
+        warn!(error = %e, "failed to start config watcher");
     }
+}
 
-    // Forward to Python kernel for Polkit authentication
-    let state_clone = state.0.clone();
-    let result = tauri::async_runtime::spawn_blocking(move || {
-        let mut guard = state_clone.lock().map_err(|_| "lock poisoned".to_string())?;
-        if guard.is_none() {
-            let proc = KernelProcess::start().map_err(|e| e.to_string())?;
-            *guard = Some(proc);
-        }
-
-        let proc = guard
-            .as_mut()
-            .ok_or_else(|| KernelError::NotStarted.to_string())?;
+/// Global shortcut: lock every session and raise the lock screen
+fn lock_from_shortcut(app: &AppHandle) {
+    lock_all_sessions(app, LockReason::Shortcut);
+    show_main_window(app);
+}
 
-        // Call Python's auth/login endpoint (Polkit handles auth via system dialog)
-        proc.request(
-            "auth/login",
-            json!({
-                "username": username,
-            }),
-        )
-        .map_err(|e| e.to_string())
-    })
-    .await
-    .map_err(|e| format!("auth_login join error: {e}"))??;
-
-    // Parse response from Python - extract the 'result' field from JSON-RPC envelope
-    let result_inner = result
-        .get("result")
-        .ok_or_else(|| "No result field in JSON-RPC response".to_string())?;
-    let auth_result: AuthResult = serde_json::from_value(result_inner.clone())
-        .map_err(|e| format!("Failed to parse auth response: {e}"))?;
-
-    // If successful, store the session in Rust
-    if auth_result.success {
-        if let (Some(token), Some(uname)) = (&auth_result.session_token, &auth_result.username) {
-            let session = auth::create_session(token.clone(), uname.clone());
-            let mut store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-            store.insert(session);
-        }
+/// Normal launch: the splash stays up until the kernel is ready, then the
+/// main window replaces it (restored sessions locked with `--start-locked`)
+async fn startup(app: AppHandle, start_locked: bool) {
+    // Failures surface on the first request
+    let _ = prepare_kernel(&app, StartTrigger::Launch).await;
+    splash::finish(&app);
+    restore_sessions(app.clone()).await;
+    if start_locked {
+        lock_all_sessions(&app, LockReason::StartLocked);
     }
+}
 
-    Ok(auth_result)
+/// Minimized autostart: stay in the tray with the kernel running and
+/// restored sessions locked until the user opens the window
+async fn autostart_minimized(app: AppHandle) {
+    let _ = prepare_kernel(&app, StartTrigger::Launch).await;
+    restore_sessions(app.clone()).await;
+    lock_all_sessions(&app, LockReason::Autostart);
 }
 
-/// Log out and destroy a session (zeroizes key material)
-#[tauri::command]
-fn auth_logout(auth_state: State<'_, AuthState>, session_token: String) -> Result<(), String> {
-    let mut store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-    if store.remove(&session_token) {
-        Ok(())
+/// A later launch's arguments: raise the app, route its link and let the
+/// main window know
+fn handle_second_instance(app: &AppHandle, launch: Launch) {
+    // Still starting up: the splash hands over to the main window itself
+    if let Some(splash) = app.get_webview_window(splash::SPLASH_LABEL) {
+        let _ = splash.set_focus();
     } else {
-        Err("Session not found".to_string())
+        show_main_window(app);
+    }
+    if let Some(url) = deep_link::from_args(launch.argv()) {
+        handle_deep_link(app, &url);
     }
+    let _ = app.emit_to("main", single_instance::SECOND_INSTANCE_EVENT, &launch.args);
 }
 
-/// Validate a session token
-#[tauri::command]
-fn auth_validate(auth_state: State<'_, AuthState>, session_token: String) -> Result<bool, String> {
-    let store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-    Ok(store.get(&session_token).is_some())
+/// Route an incoming `reos://` link: dispatched if a session is active,
+/// otherwise held until login
+fn handle_deep_link(app: &AppHandle, url: &str) {
+    let state = app.state::<DeepLinkState>();
+    match state.config.resolve(url) {
+        Ok(link) => *state.pending.lock_or_recover() = Some(link),
+        Err(e) => {
+            app.state::<AuditState>().record(
+                AuditEntry::new(AuditEvent::DeepLinkRejected)
+                    .detail(format!("{e}: {}", redact::text(url))),
+            );
+            return;
+        }
+    }
+    show_main_window(app);
+    dispatch_deep_link(app);
 }
 
-/// Refresh session activity timestamp
-#[tauri::command]
-fn auth_refresh(auth_state: State<'_, AuthState>, session_token: String) -> Result<(), String> {
-    let mut store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-    match store.get_mut(&session_token) {
-        Some(session) => {
-            session.refresh();
-            Ok(())
+/// Send the held link to the main window, or ask it to log in first
+///
+/// Runs on a blocking thread: session listeners fire with the store locked.
+fn dispatch_deep_link(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let active = {
+            let auth_state = app.state::<AuthState>();
+            let store = auth_state.read();
+            let active = store.live_sessions().any(|session| !session.is_locked());
+            active
+        };
+        let state = app.state::<DeepLinkState>();
+        let mut pending = state.pending.lock_or_recover();
+        let result = match pending.as_ref() {
+            None => return,
+            Some(link) if !active => {
+                app.emit_to("main", deep_link::LOGIN_REQUIRED_EVENT, &link.url)
+            }
+            Some(_) => {
+                let link = pending.take();
+                app.emit_to("main", deep_link::DEEP_LINK_EVENT, link)
+            }
+        };
+        if let Err(e) = result {
+            warn!(error = %e, "failed to emit deep link");
+        }
+    });
+}
+
+/// Tray menu actions
+fn on_tray_action(app: &AppHandle, id: &str) {
+    match id {
+        tray::MENU_SHOW => show_main_window(app),
+        tray::MENU_LOCK => lock_all_sessions(app, LockReason::Tray),
+        tray::MENU_RESTART_KERNEL => restart_kernel(app),
+        tray::MENU_QUIT => app.exit(0),
+        _ => {
+            if let Some(action_id) = id.strip_prefix(quick_actions::MENU_PREFIX) {
+                request_quick_action(app, action_id);
+            }
         }
-        None => Err("Session not found or expired".to_string()),
     }
 }
 
-/// Get the current system username
-#[tauri::command]
-fn get_system_username() -> Result<String, String> {
-    std::env::var("USER")
-        .or_else(|_| std::env::var("USERNAME"))
-        .map_err(|_| "Could not determine username".to_string())
+/// List the kernel's quick actions in the tray
+///
+/// Runs on a blocking thread like `refresh_tray`.
+fn show_quick_actions(app: &AppHandle) {
+    if app.try_state::<TrayState<Wry>>().is_none() {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let items: Vec<(String, String)> = app
+            .state::<QuickActionsState>()
+            .0
+            .lock_or_recover()
+            .iter()
+            .map(|action| (action.menu_id(), action.title.clone()))
+            .collect();
+        if let Some(tray) = app.try_state::<TrayState<Wry>>() {
+            tray.set_actions(&app, &items);
+        }
+    });
 }
 
-/// Get current session info (for UI display)
-#[tauri::command]
-fn auth_get_session(
-    auth_state: State<'_, AuthState>,
-    session_token: String,
-) -> Result<SessionInfo, String> {
-    let store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-    auth::validate_session(&store, &session_token).ok_or_else(|| "Session not found".to_string())
+/// Re-read the system appearance and, if it changed, tell every window
+/// and the kernel (blocks on platform calls: keep off the main thread)
+fn refresh_appearance(app: &AppHandle) {
+    let fallback = match app.get_webview_window("main").and_then(|w| w.theme().ok()) {
+        Some(Theme::Dark) => ColorScheme::Dark,
+        _ => ColorScheme::Light,
+    };
+    let appearance = appearance::detect(fallback);
+    if !app.state::<AppearanceState>().update(appearance.clone()) {
+        return;
+    }
+    if let Err(e) = app.emit(APPEARANCE_EVENT, &appearance) {
+        warn!(error = %e, "failed to emit {APPEARANCE_EVENT}");
+    }
+    send_appearance(app);
 }
 
-// =============================================================================
-// Kernel Commands (now session-aware)
-// =============================================================================
+/// Pass the current appearance to a running kernel (a starting one gets it
+/// when it reports ready)
+fn send_appearance(app: &AppHandle) {
+    let appearance = app.state::<AppearanceState>().current();
+    notify_kernel(app, "appearance/set", json!(appearance));
+}
 
-#[tauri::command]
-fn kernel_start(state: State<'_, KernelState>) -> Result<(), String> {
-    let mut guard = state.0.lock().map_err(|_| "lock poisoned".to_string())?;
-    if guard.is_some() {
-        return Ok(());
+/// Suspend, resume or battery change: lock every session on suspend, then
+/// tell the windows and the kernel
+fn handle_power_event(app: &AppHandle, event: PowerEvent) {
+    match event {
+        PowerEvent::Suspend => lock_all_sessions(app, LockReason::Suspend),
+        PowerEvent::Resume => {}
+        PowerEvent::Battery(battery) => {
+            *app.state::<PowerState>().0.lock_or_recover() = Some(battery);
+        }
+    }
+    if let Err(e) = app.emit(POWER_EVENT, event) {
+        warn!(error = %e, "failed to emit {POWER_EVENT}");
     }
-    let proc = KernelProcess::start().map_err(|e| e.to_string())?;
-    *guard = Some(proc);
-    Ok(())
+    notify_kernel(app, "power/event", json!(event));
 }
 
-/// Send a request to the Python kernel
-///
-/// # Security
-/// - Requires valid session token
-/// - Session info is injected into params for audit logging
-/// - Credentials never reach the kernel
-#[tauri::command]
-async fn kernel_request(
-    state: State<'_, KernelState>,
-    auth_state: State<'_, AuthState>,
-    session_token: String,
-    method: String,
-    params: Value,
-) -> Result<Value, String> {
-    // Validate session first (zero trust)
-    let session_info = {
-        let store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-        auth::validate_session(&store, &session_token)
-            .ok_or_else(|| "Invalid or expired session".to_string())?
-    };
-
-    // Refresh session activity
-    {
-        let mut store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-        if let Some(session) = store.get_mut(&session_token) {
-            session.refresh();
+/// Put the kernel's menu sections into the app menu
+fn show_app_menu(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let sections = app.state::<AppMenuState>().0.lock_or_recover().clone();
+        // Elsewhere windows have no menu bar until the kernel declares one
+        if sections.is_empty() && !cfg!(target_os = "macos") {
+            let _ = app.remove_menu();
+            return;
+        }
+        let result = app_menu::build(&app, &sections).and_then(|menu| app.set_menu(menu));
+        if let Err(e) = result {
+            warn!(error = %e, "failed to update app menu");
         }
+    });
+}
+
+/// App menu item chosen: the focused window (or main) runs it with its
+/// session
+fn request_app_menu_action(app: &AppHandle, action_id: &str) {
+    let target = app
+        .webview_windows()
+        .into_values()
+        .filter(|w| w.label() != splash::SPLASH_LABEL)
+        .find(|w| w.is_focused().unwrap_or(false))
+        .map(|w| w.label().to_string())
+        .unwrap_or_else(|| "main".to_string());
+    if let Err(e) = app.emit_to(target.as_str(), app_menu::APP_MENU_EVENT, action_id) {
+        warn!(error = %e, "failed to emit {}", app_menu::APP_MENU_EVENT);
     }
+}
 
-    // Inject session info into params for kernel-side audit logging
-    let mut enriched_params = match params {
-        Value::Object(map) => Value::Object(map),
-        Value::Null => json!({}),
-        other => json!({ "value": other }),
+/// Tray quick action clicked: the main window runs it with its session,
+/// or the user is asked to unlock first
+fn request_quick_action(app: &AppHandle, action_id: &str) {
+    let Some(action) = app
+        .state::<QuickActionsState>()
+        .0
+        .lock_or_recover()
+        .iter()
+        .find(|a| a.id == action_id)
+        .cloned()
+    else {
+        return;
     };
-
-    if let Value::Object(ref mut map) = enriched_params {
-        map.insert(
-            "__session".to_string(),
-            json!({
-                "username": session_info.username,
-                "session_id": session_info.session_id,
-            }),
+    let active = app.state::<AuthState>().read().any_active();
+    let sent = active
+        && app
+            .emit_to("main", quick_actions::TRAY_ACTION_EVENT, &action.id)
+            .is_ok();
+    if !sent {
+        show_main_window(app);
+        notifications::notify(
+            app,
+            Notification::new(
+                Category::QuickAction,
+                action.title,
+                "Log in or unlock ReOS to run this action",
+            ),
         );
     }
+}
 
-    // Forward to kernel on background thread
-    let state = state.0.clone();
+/// Replace the kernel process (tray "Restart kernel"): hot swapped so
+/// sessions survive, or restarted outright if the running kernel can't
+/// hand them over
+fn restart_kernel(app: &AppHandle) {
+    let Some(tray) = app.try_state::<TrayState<Wry>>() else {
+        return;
+    };
+    if !tray.begin_restart() {
+        return;
+    }
+    refresh_tray(app);
+    let app = app.clone();
     tauri::async_runtime::spawn_blocking(move || {
-        let mut guard = state.lock().map_err(|_| "lock poisoned".to_string())?;
-        if guard.is_none() {
-            let proc = KernelProcess::start().map_err(|e| e.to_string())?;
+        let kernel = app.state::<KernelState>().0.clone();
+        let detail = match hot_swap::swap(&kernel) {
+            Ok(swapped) => format!("from tray, hot swap: {} sessions kept", swapped.sessions),
+            Err(HotSwapError::Export(e)) => {
+                warn!(error = %e, "kernel can't hand over its sessions; restarting it");
+                restart_outright(&app, &kernel)
+            }
+            // Reconnect: the remote kernel keeps its sessions for our key
+            Err(HotSwapError::Remote) => restart_outright(&app, &kernel),
+            // Nothing to carry over from a mock; an embedded kernel keeps its
+            // interpreter (and sessions) across a restart
+            Err(HotSwapError::NotProcess) => restart_outright(&app, &kernel),
+            Err(e) => {
+                if let HotSwapError::Start(KernelError::Integrity(integrity)) = &e {
+                    emit_integrity_failure(&app, integrity);
+                }
+                format!("from tray, hot swap failed: {e}")
+            }
+        };
+        app.state::<AuditState>()
+            .record(AuditEntry::new(AuditEvent::KernelRestarted).detail(detail));
+        app.state::<TrayState<Wry>>().end_restart();
+        refresh_tray(&app);
+    });
+}
+
+/// Drop the kernel (and its kernel-side sessions) and start a fresh one;
+/// the audit detail (blocking)
+fn restart_outright(app: &AppHandle, kernel: &SharedKernel) -> String {
+    let mut guard = kernel::lock_shared(kernel);
+    *guard = None;
+    if app.state::<MethodManifestState>().clear() {
+        emit_kernel_methods_changed(app);
+    }
+    match kernel_backend::spawn(|_| {}) {
+        Ok(proc) => {
             *guard = Some(proc);
+            "from tray".to_string()
         }
-
-        let proc = guard
-            .as_mut()
-            .ok_or_else(|| KernelError::NotStarted.to_string())?;
-        proc.request(&method, enriched_params)
-            .map_err(|e| e.to_string())
-    })
-    .await
-    .map_err(|e| format!("kernel_request join error: {e}"))?
+        Err(e) => {
+            if let KernelError::Integrity(integrity) = &e {
+                emit_integrity_failure(app, integrity);
+            }
+            format!("from tray, start failed: {e}")
+        }
+    }
 }
 
 // =============================================================================
 // Application Entry Point
 // =============================================================================
 
+/// Run the window label/origin policy before every command handler
+fn with_window_policy<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let webview = invoke.message.webview();
+        let _span = info_span!(
+            "command",
+            name = invoke.message.command(),
+            window = webview.label()
+        )
+        .entered();
+        let denied = webview.state::<WindowPolicyState>().0.check(
+            invoke.message.command(),
+            webview.label(),
+            webview.url().ok().as_ref(),
+        );
+        if let Err(denied) = denied {
+            webview.state::<AuditState>().record(
+                AuditEntry::new(AuditEvent::CommandDenied)
+                    .window(webview.label())
+                    .detail(denied.to_string()),
+            );
+            invoke.resolver.reject(denied.to_string());
+            return true;
+        }
+        handler(invoke)
+    }
+}
+
 fn main() {
+    logging::init();
+    let cli = match CliArgs::parse(std::env::args()) {
+        Ok(cli) => cli,
+        Err(e) => {
+            tracing::error!(error = %e, "not starting");
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+    profile::init(cli.profile.clone());
+    if let Err(e) = portable::init(cli.portable) {
+        tracing::error!(error = %e, "not starting");
+        eprintln!("{e}");
+        std::process::exit(2);
+    }
+    let context = tauri::generate_context!();
+    if cli.headless_check {
+        std::process::exit(headless_check(&context.config().identifier, &cli));
+    }
+    // A second launch hands its arguments to the running instance instead
+    // of starting another kernel on the same store
+    let instance = match single_instance::acquire(&Launch::from_args(std::env::args())) {
+        Instance::Primary(instance) => instance,
+        Instance::Secondary => return,
+    };
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(tauri_plugin_notification::init())
         .manage(KernelState(Arc::new(Mutex::new(None))))
         .manage(AuthState::new())
-        .invoke_handler(tauri::generate_handler![
-            // Auth commands
-            auth_login,
-            auth_logout,
-            auth_validate,
-            auth_refresh,
-            auth_get_session,
-            get_system_username,
-            // Kernel commands
-            kernel_start,
-            kernel_request,
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .manage(LoginRateLimitState::new())
+        .manage(FrontendLogLimitState::new())
+        .manage(KernelOutputState::new())
+        .manage(PinState::new())
+        .manage(TaskbarProgressState::default())
+        .manage(AppMenuState::default())
+        .manage(AppearanceState::default())
+        .manage(PowerState::default())
+        .manage(WindowNonceState::new())
+        .manage(WindowsState(Mutex::new(WindowRegistry::default())))
+        .manage(QuickActionsState::default())
+        .manage(FeaturesState::default())
+        .manage(MethodManifestState::default())
+        .on_page_load(|webview, payload| {
+            // A (re)loaded page starts over: earlier nonces stop working
+            if payload.event() == PageLoadEvent::Started {
+                webview
+                    .state::<WindowNonceState>()
+                    .0
+                    .lock_or_recover()
+                    .issue(webview.label());
+            }
+        })
+        .on_menu_event(|app, event| {
+            if let Some(id) = event.id().as_ref().strip_prefix(app_menu::MENU_PREFIX) {
+                request_app_menu_action(app, id);
+            }
+        })
+        .on_window_event(|window, event| match event {
+            WindowEvent::Focused(true) => {
+                let changed = window
+                    .state::<WindowsState>()
+                    .0
+                    .lock_or_recover()
+                    .focus(window.label());
+                if changed {
+                    if let Err(e) = window.emit(windows::WINDOW_FOCUS_EVENT, window.label()) {
+                        warn!(error = %e, "failed to emit {}", windows::WINDOW_FOCUS_EVENT);
+                    }
+                }
+            }
+            WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) => {
+                handle_file_drop(window, paths);
+            }
+            WindowEvent::Moved(_) | WindowEvent::Resized(_) => record_window_state(window),
+            WindowEvent::ThemeChanged(_) => {
+                let app = window.app_handle().clone();
+                tauri::async_runtime::spawn_blocking(move || refresh_appearance(&app));
+            }
+            WindowEvent::Destroyed => {
+                persist_window_state(window.app_handle());
+                window
+                    .state::<KernelOutputState>()
+                    .0
+                    .lock_or_recover()
+                    .remove(window.label());
+                window
+                    .state::<WindowNonceState>()
+                    .0
+                    .lock_or_recover()
+                    .remove(window.label());
+                window
+                    .state::<WindowsState>()
+                    .0
+                    .lock_or_recover()
+                    .remove(window.label());
+            }
+            _ => {}
+        })
+        .setup(move |app| {
+            let data_dir = app.path().profile_data_dir().ok();
+            let redaction_path = data_dir.as_deref().map(|d| d.join("redaction.json"));
+            redact::init(RedactionConfig::load(redaction_path.as_deref()));
+            let remote_kernel_path = data_dir.as_deref().map(|d| d.join("remote-kernel.json"));
+            remote_kernel::init(RemoteKernelConfig::load(remote_kernel_path.as_deref()));
+            let mock_kernel_path = data_dir.as_deref().map(|d| d.join("kernel-mock.json"));
+            kernel_backend::init_mock(MockKernelConfig::load(mock_kernel_path.as_deref()));
+            if let Some(data_dir) = &data_dir {
+                let config = LoggingConfig::load(Some(&data_dir.join("logging.json")));
+                logging::open_file(&data_dir.join("logs"), &config);
+                if profile::current().is_some() || portable::root().is_some() {
+                    kernel::set_data_dir(data_dir.join("kernel"));
+                }
+                if let Some(root) = portable::root() {
+                    tracing::info!(dir = %root.display(), "running portable");
+                }
+                if let Some(name) = profile::current() {
+                    tracing::info!(profile = name, "using profile");
+                    // Tell side-by-side profiles apart
+                    if let Some(main) = app.get_webview_window("main") {
+                        let title = main.title().unwrap_or_default();
+                        let _ = main.set_title(&format!("{title} ({name})"));
+                    }
+                }
+            }
+            // Before anything reads the files it migrates
+            let dirs = (data_dir.as_deref(), app.path().profile_config_dir());
+            let migrations = match dirs {
+                (Some(data_dir), Ok(config_dir)) => migrations::run(data_dir, &config_dir),
+                _ => MigrationReport::default(),
+            };
+            app.manage(MigrationState(migrations));
+            let config_state = ConfigState::load(
+                app.path().profile_config_dir().ok().as_deref(),
+                cli.overrides(),
+            );
+            apply_config(&config_state.config());
+            // The flag also wins over RUST_LOG
+            if let Some(level) = &cli.log_level {
+                if let Err(e) = logging::set_level("*", Some(level)) {
+                    warn!(error = %e, "--log-level not applied");
+                }
+            }
+            app.manage(config_state);
+            let totp_path = data_dir.as_deref().map(|d| d.join("totp.json"));
+            let totp_state = TotpState::load(totp_path);
+            if let Some(e) = totp_state.0.lock_or_recover().load_error() {
+                tracing::error!(error = %e, "totp.json unreadable, refusing second-factor logins");
+            }
+            app.manage(totp_state);
+            let biometric_path = data_dir.as_deref().map(|d| d.join("biometric.json"));
+            app.manage(BiometricState::load(biometric_path));
+            let audit_path = data_dir.as_deref().map(|d| d.join("auth-audit.jsonl"));
+            app.manage(AuditState::open(audit_path));
+            let request_audit_path = data_dir.as_deref().map(|d| d.join("kernel-requests.jsonl"));
+            app.manage(RequestAuditState::open(request_audit_path));
+            match kernel_integrity::check() {
+                Ok(KernelSource::Unverified) => {
+                    warn!("kernel integrity verification disabled (no signing key in this build)")
+                }
+                Ok(KernelSource::Verified(_)) => {}
+                Err(e) => emit_integrity_failure(app.handle(), &e),
+            }
+            let guest_path = data_dir.as_deref().map(|d| d.join("guest.json"));
+            app.manage(GuestState(GuestPolicy::load(guest_path.as_deref())));
+            let roles_path = data_dir.as_deref().map(|d| d.join("roles.json"));
+            app.manage(RoleState(RoleMapping::load(roles_path.as_deref())));
+            let step_up_path = data_dir.as_deref().map(|d| d.join("step-up.json"));
+            app.manage(StepUpState(StepUpPolicy::load(step_up_path.as_deref())));
+            let os_login_path = data_dir.as_deref().map(|d| d.join("os-login.json"));
+            app.manage(OsLoginState(OsLoginPolicy::load(os_login_path.as_deref())));
+            let backend_path = data_dir.as_deref().map(|d| d.join("auth-backend.json"));
+            let methods_path = data_dir.as_deref().map(|d| d.join("kernel-methods.json"));
+            let window_policy_path = data_dir.as_deref().map(|d| d.join("window-policy.json"));
+            app.manage(WindowPolicyState(WindowPolicy::load(
+                window_policy_path.as_deref(),
+            )));
+            app.manage(MethodAllowlistState(MethodAllowlist::new(
+                MethodAllowlistConfig::load(methods_path.as_deref()),
+            )));
+            let rate_limits_path = data_dir.as_deref().map(|d| d.join("rate-limits.json"));
+            app.manage(RequestRateLimitState(Mutex::new(RequestRateLimiter::new(
+                RequestRatePolicy::load(rate_limits_path.as_deref()),
+            ))));
+            let scheduler_path = data_dir.as_deref().map(|d| d.join("scheduler.json"));
+            app.manage(SchedulerState(Arc::new(Scheduler::new(
+                SchedulerConfig::load(scheduler_path.as_deref()),
+            ))));
+            let warmup_path = data_dir.as_deref().map(|d| d.join("warmup.json"));
+            app.manage(WarmupState(WarmupConfig::load(warmup_path.as_deref())));
+            let blobs_path = data_dir.as_deref().map(|d| d.join("blobs.json"));
+            if let Ok(cache_dir) = app.path().profile_cache_dir() {
+                let config = BlobConfig::load(blobs_path.as_deref());
+                if let Err(e) = blobs::init(cache_dir.join("kernel-blobs"), config) {
+                    warn!(error = %e, "large results stay in the kernel pipe");
+                }
+            }
+            let consent_path = data_dir.as_deref().map(|d| d.join("consent.json"));
+            app.manage(ConsentState(ConsentPolicy::load(consent_path.as_deref())));
+            let method_policy_path = data_dir.as_deref().map(|d| d.join("method-policy.json"));
+            app.manage(MethodPolicyState(MethodPolicy::load(
+                method_policy_path.as_deref(),
+            )));
+            let username_policy_path = data_dir.as_deref().map(|d| d.join("username-policy.json"));
+            app.manage(UsernameState::new(UsernamePolicy::load(
+                username_policy_path.as_deref(),
+            )));
+            let password_policy_path = data_dir.as_deref().map(|d| d.join("password-policy.json"));
+            app.manage(PasswordPolicyState::new(PasswordPolicy::load(
+                password_policy_path.as_deref(),
+            )));
+            let oidc_path = data_dir.as_deref().map(|d| d.join("oidc.json"));
+            app.manage(OidcState::new(OidcConfig::load(oidc_path.as_deref())));
+            app.manage(AuthBackendState(
+                BackendConfig::load(backend_path.as_deref()).build(),
+            ));
+            let persistence_path = data_dir.as_deref().map(|d| d.join("session-persistence.json"));
+            let vault_path = data_dir.as_deref().map(|d| d.join("sessions.vault"));
+            app.manage(SessionVaultState::load(
+                &PersistencePolicy::load(persistence_path.as_deref()),
+                vault_path,
+            ));
+            spawn_session_sweeper(app.handle().clone());
+            spawn_config_watcher(app.handle().clone());
+            let crash_reports_path = data_dir.as_deref().map(|d| d.join("crash-reports.json"));
+            let crash_reports_dir = data_dir.as_deref().map(|d| d.join("crash-reports"));
+            app.manage(CrashReportState(CrashReports::new(
+                crash_reports_dir,
+                CrashReportConfig::load(crash_reports_path.as_deref()),
+            )));
+            install_panic_hook(app.handle().clone());
+            let updater_path = data_dir.as_deref().map(|d| d.join("updater.json"));
+            let install_id_path = data_dir.as_deref().map(|d| d.join("install-id"));
+            app.manage(UpdaterState(Mutex::new(Updater::new(
+                UpdaterConfig::load(updater_path.as_deref()),
+                install_id_path,
+            ))));
+            let notifications_path = data_dir.as_deref().map(|d| d.join("notifications.json"));
+            app.manage(NotificationState::new(NotificationConfig::load(
+                notifications_path.as_deref(),
+            )));
+            let handle = app.handle().clone();
+            kernel::on_event(move |event| match event {
+                KernelEvent::Ready(result) => {
+                    crash_report::set_kernel_version(&result);
+                    *handle.state::<QuickActionsState>().0.lock_or_recover() =
+                        quick_actions::from_manifest(&result);
+                    show_quick_actions(&handle);
+                    *handle.state::<AppMenuState>().0.lock_or_recover() =
+                        app_menu::from_manifest(&result);
+                    show_app_menu(&handle);
+                    let flags = features::from_manifest(&result);
+                    let previous = std::mem::replace(
+                        &mut *handle.state::<FeaturesState>().0.lock_or_recover(),
+                        flags.clone(),
+                    );
+                    if previous != flags {
+                        if let Err(e) = handle.emit(FEATURES_CHANGED_EVENT, ()) {
+                            warn!(error = %e, "failed to emit {FEATURES_CHANGED_EVENT}");
+                        }
+                    }
+                    handle.state::<MethodManifestState>().replace(
+                        kernel::current_pid(),
+                        method_manifest::from_manifest(&result),
+                    );
+                    emit_kernel_methods_changed(&handle);
+                    send_appearance(&handle);
+                    let battery = *handle.state::<PowerState>().0.lock_or_recover();
+                    if let Some(battery) = battery {
+                        notify_kernel(&handle, "power/event", json!(PowerEvent::Battery(battery)));
+                    }
+                }
+                KernelEvent::Push(event) => {
+                    if let Some(notification) = Notification::from_kernel_event(&event) {
+                        notifications::notify(&handle, notification);
+                    }
+                    if let Some(sections) = app_menu::from_capabilities_event(&event) {
+                        *handle.state::<AppMenuState>().0.lock_or_recover() = sections;
+                        show_app_menu(&handle);
+                    }
+                    if let Some(progress) = KernelProgress::from_kernel_event(&event) {
+                        if progress.failed {
+                            finish_task_progress(&handle, &progress.job);
+                        } else {
+                            report_task_progress(
+                                &handle,
+                                &progress.job,
+                                progress.done,
+                                progress.total,
+                            );
+                        }
+                    }
+                }
+                KernelEvent::Output(line) => send_kernel_output(&handle, &line),
+                KernelEvent::SlowRequest(slow) => {
+                    if let Err(e) = handle.emit(SLOW_REQUEST_EVENT, &slow) {
+                        warn!(error = %e, "failed to emit {SLOW_REQUEST_EVENT}");
+                    }
+                }
+                KernelEvent::ResourceWarning(warning) => {
+                    if let Err(e) = handle.emit(RESOURCE_WARNING_EVENT, &warning) {
+                        warn!(error = %e, "failed to emit {RESOURCE_WARNING_EVENT}");
+                    }
+                }
+                KernelEvent::Exited { status } => {
+                    notifications::notify(
+                        &handle,
+                        Notification::new(
+                            Category::Crash,
+                            "Kernel stopped",
+                            format!("The kernel exited unexpectedly ({status}); it restarts on the next request"),
+                        ),
+                    );
+                    let bar = handle
+                        .state::<TaskbarProgressState>()
+                        .0
+                        .lock_or_recover()
+                        .finish_kernel_jobs();
+                    show_taskbar_progress(&handle, bar);
+                    refresh_tray(&handle);
+                    if handle.state::<MethodManifestState>().clear() {
+                        emit_kernel_methods_changed(&handle);
+                    }
+                }
+            });
+            let slow_requests_path = data_dir.as_deref().map(|d| d.join("slow-requests.json"));
+            slow_requests::configure(SlowRequestConfig::load(slow_requests_path.as_deref()));
+            kernel::spawn_slow_request_watchdog();
+            let kernel_resources_path =
+                data_dir.as_deref().map(|d| d.join("kernel-resources.json"));
+            kernel_resources::configure(ResourceConfig::load(kernel_resources_path.as_deref()));
+            kernel::spawn_resource_sampler();
+            let request_history_path = data_dir.as_deref().map(|d| d.join("request-history.json"));
+            request_history::configure(RequestHistoryConfig::load(
+                request_history_path.as_deref(),
+            ));
+            match tray::install(app.handle(), on_tray_action) {
+                Ok(tray) => {
+                    app.manage(tray);
+                    for event in SessionEvent::ALL {
+                        let handle = app.handle().clone();
+                        app.listen_any(event.name(), move |_| refresh_tray(&handle));
+                    }
+                    refresh_tray(app.handle());
+                    show_quick_actions(app.handle());
+                }
+                Err(e) => warn!(error = %e, "system tray unavailable"),
+            }
+            let window_state_path = app
+                .path()
+                .profile_config_dir()
+                .ok()
+                .map(|d| d.join("window-state.json"));
+            app.manage(WindowStateState(Mutex::new(WindowStateStore::load(
+                window_state_path,
+            ))));
+            if let Some(main) = app.get_webview_window("main") {
+                restore_window_state(&main);
+            }
+            // The main window starts hidden; staying hidden needs the tray
+            // to bring it back
+            let minimized = AutostartLaunch::from_args(std::env::args())
+                .is_some_and(|launch| launch.minimized)
+                && app.try_state::<TrayState<Wry>>().is_some();
+            if minimized {
+                tauri::async_runtime::spawn(autostart_minimized(app.handle().clone()));
+            } else {
+                let policy = app.state::<ConfigState>().config().kernel.start;
+                if policy.shows_splash() {
+                    if let Err(e) = splash::open(app.handle()) {
+                        warn!(error = %e, "splash window unavailable");
+                        splash::finish(app.handle());
+                    }
+                }
+                tauri::async_runtime::spawn(startup(app.handle().clone(), cli.start_locked));
+            }
+            let deep_links_path = data_dir.as_deref().map(|d| d.join("deep-links.json"));
+            app.manage(DeepLinkState::new(DeepLinkConfig::load(
+                deep_links_path.as_deref(),
+            )));
+            for event in [SessionEvent::Created, SessionEvent::Unlocked] {
+                let handle = app.handle().clone();
+                app.listen_any(event.name(), move |_| dispatch_deep_link(&handle));
+            }
+            let handle = app.handle().clone();
+            instance.listen(move |launch| handle_second_instance(&handle, launch));
+            if let Some(url) = deep_link::from_args(std::env::args()) {
+                handle_deep_link(app.handle(), &url);
+            }
+            let file_drop_path = data_dir.as_deref().map(|d| d.join("file-drop.json"));
+            app.manage(FileDropState::new(DropPolicy::load(
+                file_drop_path.as_deref(),
+                app.path().home_dir().ok(),
+            )));
+            let open_external_path = data_dir.as_deref().map(|d| d.join("open-external.json"));
+            app.manage(OpenExternalState(OpenPolicy::load(
+                open_external_path.as_deref(),
+                app.path().home_dir().ok(),
+            )));
+            let metrics_path = data_dir.as_deref().map(|d| d.join("metrics.json"));
+            metrics::serve_prometheus(&MetricsConfig::load(metrics_path.as_deref()));
+            let telemetry_path = data_dir.as_deref().map(|d| d.join("telemetry.json"));
+            telemetry::init(
+                &TelemetryConfig::load(telemetry_path.as_deref()),
+                &app.package_info().version.to_string(),
+            );
+            let clipboard_path = data_dir.as_deref().map(|d| d.join("clipboard.json"));
+            app.manage(ClipboardState::new(ClipboardConfig::load(
+                clipboard_path.as_deref(),
+            )));
+            let shortcuts_path = data_dir.as_deref().map(|d| d.join("shortcuts.json"));
+            if let Some(lock) = ShortcutConfig::load(shortcuts_path.as_deref()).lock {
+                match lock.parse::<Shortcut>() {
+                    Ok(shortcut) => {
+                        let handle = app.handle().clone();
+                        global_shortcut::register(app.handle(), shortcut, move || {
+                            lock_from_shortcut(&handle)
+                        });
+                    }
+                    Err(e) => warn!(error = %e, "lock shortcut disabled"),
+                }
+            }
+            let handle = app.handle().clone();
+            system_lock::watch(move |reason| lock_all_sessions(&handle, reason));
+            let handle = app.handle().clone();
+            power::watch(move |event| handle_power_event(&handle, event));
+            let handle = app.handle().clone();
+            appearance::watch(move || refresh_appearance(&handle));
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn_blocking(move || refresh_appearance(&handle));
+            Ok(())
+        })
+        .invoke_handler(with_window_policy(tauri::generate_handler![
+            commands::auth::auth_login,
+            commands::auth::auth_login_os_session,
+            commands::auth::auth_login_oidc,
+            commands::auth::auth_guest_login,
+            commands::auth::auth_verify_totp,
+            commands::auth::auth_totp_enroll,
+            commands::auth::auth_totp_confirm,
+            commands::auth::auth_totp_disable,
+            commands::auth::auth_change_password,
+            commands::auth::auth_pin_set,
+            commands::auth::auth_pin_clear,
+            commands::auth::auth_pin_unlock,
+            commands::auth::auth_lock,
+            commands::auth::auth_unlock,
+            commands::auth::auth_step_up,
+            commands::auth::auth_biometric_status,
+            commands::auth::auth_biometric_enable,
+            commands::auth::auth_biometric_disable,
+            commands::auth::auth_biometric_unlock,
+            commands::auth::auth_logout,
+            commands::auth::auth_revoke_user,
+            commands::auth::auth_validate,
+            commands::auth::auth_refresh,
+            commands::auth::auth_get_session,
+            commands::auth::get_system_username,
+            commands::kernel::kernel_start,
+            commands::kernel::kernel_hot_swap,
+            commands::kernel::remote_kernel_pair,
+            commands::kernel::kernel_request,
+            commands::kernel::kernel_integrity_status,
+            commands::kernel::kernel_status,
+            commands::kernel::kernel_list_methods,
+            commands::kernel::kernel_blob_read,
+            commands::kernel::kernel_blob_release,
+            commands::kernel::kernel_stream_read,
+            commands::kernel::kernel_stream_release,
+            commands::kernel::kernel_logs_subscribe,
+            commands::kernel::kernel_logs_unsubscribe,
+            commands::kernel::audit_verify_requests,
+            commands::app::state_reset,
+            commands::app::appearance_get,
+            commands::app::config_path,
+            commands::app::settings_get,
+            commands::app::settings_set,
+            commands::app::setup_status,
+            commands::app::setup_run_step,
+            commands::app::features_get,
+            commands::app::power_status,
+            commands::app::log_get_config,
+            commands::app::log_set_level,
+            commands::app::frontend_log,
+            commands::app::metrics_snapshot,
+            commands::app::health_check,
+            commands::app::debug_request_history,
+            commands::app::crash_report_list,
+            commands::app::crash_report_get,
+            commands::app::crash_report_submit,
+            commands::window::window_nonce,
+            commands::window::deep_link_take,
+            commands::window::window_open,
+            commands::window::window_close,
+            commands::window::window_handoff,
+            commands::window::tray_action_run,
+            commands::window::app_menu_run,
+            commands::update::update_check,
+            commands::update::update_download,
+            commands::update::update_install,
+            commands::vault::vault_set,
+            commands::vault::vault_get,
+            commands::vault::vault_delete,
+            commands::vault::vault_list,
+            commands::open_external::open_external,
+            commands::clipboard::clipboard_write,
+            commands::clipboard::clipboard_read,
+            commands::clipboard::clipboard_copy_secret,
+            commands::autostart::autostart_enable,
+            commands::autostart::autostart_disable,
+            commands::autostart::autostart_status,
+            commands::file::file_ingest,
+            commands::file::export_file,
+            commands::file::diagnostics_export,
+            commands::print::print_document,
+            commands::print::print_capabilities,
+        ]))
+        .build(context)
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                persist_sessions(app);
+                persist_window_state(app);
+                // X11 hands the last copied text to the clipboard manager
+                if let Some(clipboard) = app.try_state::<ClipboardState>() {
+                    clipboard.close();
+                }
+                // A listening kernel waits for the next launch to reconnect
+                if let Ok(mut kernel) = app.state::<KernelState>().0.try_lock() {
+                    if let Some(proc) = kernel.take() {
+                        proc.detach();
+                    }
+                }
+                telemetry::shutdown();
+            }
+        });
 }
@@ -0,0 +1,95 @@
+//! Hidden Window for Windows System Notifications
+//!
+//! Session changes (WTS) and power broadcasts are only delivered to
+//! windows. Each watcher that needs them gets a hidden top-level window on
+//! a thread of its own, and sees every message the window receives.
+
+use std::cell::RefCell;
+use std::io;
+use std::sync::mpsc;
+
+use windows_sys::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW, MSG, WNDCLASSW,
+};
+
+type Handler = Box<dyn Fn(u32, WPARAM, LPARAM)>;
+
+thread_local! {
+    /// The handler of the window owned by this thread
+    static HANDLER: RefCell<Option<Handler>> = const { RefCell::new(None) };
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    HANDLER.with(|handler| {
+        if let Some(handler) = handler.borrow().as_ref() {
+            handler(msg, wparam, lparam);
+        }
+    });
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+/// Start a hidden window of class `class` and pass it every message
+///
+/// `setup` runs on the window's thread before any message is handled (to
+/// register for notifications); its error, or a failure to create the
+/// window, is returned here.
+pub fn spawn(
+    class: &'static str,
+    setup: impl FnOnce(HWND) -> io::Result<()> + Send + 'static,
+    on_message: impl Fn(u32, WPARAM, LPARAM) + Send + 'static,
+) -> io::Result<()> {
+    let (ready_tx, ready_rx) = mpsc::channel();
+    std::thread::Builder::new()
+        .name(class.to_string())
+        .spawn(move || {
+            HANDLER.with(|handler| *handler.borrow_mut() = Some(Box::new(on_message)));
+            let name: Vec<u16> = class.encode_utf16().chain([0]).collect();
+            let hwnd = unsafe {
+                let instance = GetModuleHandleW(std::ptr::null());
+                let mut window_class: WNDCLASSW = std::mem::zeroed();
+                window_class.lpfnWndProc = Some(window_proc);
+                window_class.hInstance = instance;
+                window_class.lpszClassName = name.as_ptr();
+                RegisterClassW(&window_class);
+                CreateWindowExW(
+                    0,
+                    name.as_ptr(),
+                    name.as_ptr(),
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    instance,
+                    std::ptr::null(),
+                )
+            };
+            if hwnd.is_null() {
+                let _ = ready_tx.send(Err(io::Error::last_os_error()));
+                return;
+            }
+            let ready = setup(hwnd);
+            let failed = ready.is_err();
+            let _ = ready_tx.send(ready);
+            if failed {
+                return;
+            }
+
+            let mut msg: MSG = unsafe { std::mem::zeroed() };
+            while unsafe { GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) } > 0 {
+                unsafe { DispatchMessageW(&msg) };
+            }
+        })?;
+    ready_rx
+        .recv()
+        .unwrap_or_else(|_| Err(io::Error::other("message window thread exited")))
+}
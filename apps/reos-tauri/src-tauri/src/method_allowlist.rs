@@ -0,0 +1,158 @@
+//! Kernel Method Allowlist for ReOS
+//!
+//! `kernel_request` only forwards methods on this list, so a compromised
+//! webview can't reach internal or debug kernel endpoints (`auth/*`,
+//! `vault/*`, `update/*`, `ingest/*`, `export/*`, `print/*`,
+//! `appearance/*`, `power/*`, `warmup/*`, `session/*` and `kernel/*` are
+//! owned by the Rust shell, `initialize`, raw session dumps, ...).
+//!
+//! The shipped default covers the app's features; deployments can add
+//! patterns via `kernel-methods.json` in the app data dir:
+//! `{ "allow": ["service/action", "container/*"] }`. The shell's own
+//! methods stay refused whatever the file allows (`"*"` included).
+
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::methods::method_matches;
+
+/// Methods the frontend uses (exact or `prefix*`)
+const DEFAULT_ALLOWED_METHODS: &[&str] = &[
+    "ping",
+    "anthropic/*",
+    "approval/*",
+    "archive/*",
+    "cairn/*",
+    "chat/*",
+    "code-exec/cancel",
+    "code/diff/*",
+    "code/exec/*",
+    "code/map/*",
+    "code/plan/*",
+    "code/sessions/get",
+    "code/sessions/list",
+    "compact/*",
+    "context/*",
+    "conversation/*",
+    "execution/*",
+    "handoff/*",
+    "intent/detect",
+    "learned/*",
+    "ollama/*",
+    "personas/*",
+    "plan/*",
+    "play/*",
+    "providers/*",
+    "safety/*",
+    "system/hardware",
+    "system/live_state",
+    "system/open-terminal",
+    "thunderbird/*",
+    "tools/call",
+    "tools/list",
+];
+
+/// Methods the Rust shell owns, checked before any allow pattern
+const SHELL_OWNED_METHODS: &[&str] = &[
+    "initialize",
+    "appearance/*",
+    "auth/*",
+    "export/*",
+    "ingest/*",
+    "kernel/*",
+    "power/*",
+    "print/*",
+    "session/*",
+    "update/*",
+    "vault/*",
+    "warmup/*",
+];
+
+/// Deployment additions to the shipped allowlist
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct MethodAllowlistConfig {
+    /// Extra method names or `prefix*` patterns
+    pub allow: Vec<String>,
+}
+
+impl MethodAllowlistConfig {
+    /// Load the additions from `path` (missing or invalid file = none)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Shipped default plus configured additions
+pub struct MethodAllowlist {
+    patterns: Vec<String>,
+}
+
+impl MethodAllowlist {
+    pub fn new(config: MethodAllowlistConfig) -> Self {
+        let mut patterns: Vec<String> = DEFAULT_ALLOWED_METHODS
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        patterns.extend(config.allow);
+        Self { patterns }
+    }
+
+    /// Whether `method` may be forwarded to the kernel
+    pub fn allows(&self, method: &str) -> bool {
+        !SHELL_OWNED_METHODS
+            .iter()
+            .any(|p| method_matches(p, method))
+            && self.patterns.iter().any(|p| method_matches(p, method))
+    }
+}
+
+/// Kernel method allowlist state (read-only after startup)
+pub struct MethodAllowlistState(pub MethodAllowlist);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_blocks_internal_methods() {
+        let allowlist = MethodAllowlist::new(MethodAllowlistConfig::default());
+        assert!(allowlist.allows("chat/respond"));
+        assert!(allowlist.allows("play/kb/read"));
+        assert!(!allowlist.allows("auth/login"));
+        assert!(!allowlist.allows("initialize"));
+        assert!(!allowlist.allows("code/sessions/raw"));
+        assert!(!allowlist.allows("state/set"));
+    }
+
+    #[test]
+    fn test_config_additions() {
+        let config: MethodAllowlistConfig =
+            serde_json::from_str(r#"{ "allow": ["service/action", "container/*"] }"#).unwrap();
+        let allowlist = MethodAllowlist::new(config);
+        assert!(allowlist.allows("service/action"));
+        assert!(allowlist.allows("container/action"));
+        assert!(!allowlist.allows("auth/logout"));
+        assert!(!allowlist.allows("vault/get"));
+        assert!(!allowlist.allows("update/prepare"));
+        assert!(!allowlist.allows("ingest/chunk"));
+        assert!(!allowlist.allows("export/begin"));
+        assert!(!allowlist.allows("print/render"));
+        assert!(!allowlist.allows("appearance/set"));
+        assert!(!allowlist.allows("power/event"));
+    }
+
+    #[test]
+    fn test_config_cannot_allow_shell_methods() {
+        let config: MethodAllowlistConfig = serde_json::from_str(r#"{ "allow": ["*"] }"#).unwrap();
+        let allowlist = MethodAllowlist::new(config);
+        assert!(allowlist.allows("service/action"));
+        assert!(!allowlist.allows("auth/login_verified"));
+        assert!(!allowlist.allows("auth/revoke_user"));
+        assert!(!allowlist.allows("initialize"));
+        assert!(!allowlist.allows("session/export"));
+        assert!(!allowlist.allows("vault/get"));
+    }
+}
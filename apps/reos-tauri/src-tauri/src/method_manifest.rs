@@ -0,0 +1,130 @@
+//! Kernel Method Manifest
+//!
+//! The kernel lists the methods it answers in its `initialize` result
+//! (`methods`). The shell keeps that list for the process that sent it, so
+//! the frontend can check a method exists, or hide UI for one that doesn't,
+//! without asking the kernel each time (`kernel_list_methods`).
+//!
+//! The cache belongs to one kernel process: it is replaced at each
+//! handshake, cleared when the process exits, and ignored once the running
+//! kernel's pid no longer matches (a restart whose handshake hasn't
+//! finished, or failed). `kernel://methods-changed` fires whenever it
+//! changes.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Mutex;
+
+use crate::kernel;
+use crate::locks::Recover;
+
+/// Emitted (no payload) when the manifest is replaced or cleared
+pub const KERNEL_METHODS_EVENT: &str = "kernel://methods-changed";
+
+/// Most methods taken from one manifest
+const MAX_METHODS: usize = 1024;
+
+/// Longest method name taken
+const MAX_NAME_LEN: usize = 128;
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'/' | b'_' | b'-' | b'.'))
+}
+
+/// Method names from an `initialize` result, sorted and deduplicated
+/// (invalid names are ignored; `None` if the kernel sent no manifest)
+pub fn from_manifest(result: &Value) -> Option<Vec<String>> {
+    let entries = result.get("methods")?.as_array()?;
+    let mut methods: Vec<String> = entries
+        .iter()
+        .filter_map(Value::as_str)
+        .filter(|name| is_valid_name(name))
+        .take(MAX_METHODS)
+        .map(str::to_string)
+        .collect();
+    methods.sort();
+    methods.dedup();
+    Some(methods)
+}
+
+struct Cached {
+    pid: u32,
+    methods: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct MethodManifestState(Mutex<Option<Cached>>);
+
+impl MethodManifestState {
+    /// Keep `methods` for the kernel process `pid`
+    pub fn replace(&self, pid: Option<u32>, methods: Option<Vec<String>>) {
+        *self.0.lock_or_recover() = pid
+            .zip(methods)
+            .map(|(pid, methods)| Cached { pid, methods });
+    }
+
+    /// Forget the manifest; whether there was one
+    pub fn clear(&self) -> bool {
+        self.0.lock_or_recover().take().is_some()
+    }
+
+    /// Methods of the running kernel, if it sent a manifest
+    pub fn methods(&self) -> Option<Vec<String>> {
+        self.methods_for(kernel::current_pid())
+    }
+
+    fn methods_for(&self, pid: Option<u32>) -> Option<Vec<String>> {
+        let cached = self.0.lock_or_recover();
+        cached
+            .as_ref()
+            .filter(|cached| Some(cached.pid) == pid)
+            .map(|cached| cached.methods.clone())
+    }
+}
+
+/// A kernel method as `kernel_list_methods` reports it
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct KernelMethod {
+    pub name: String,
+    /// Whether the caller's session may send it (allowlist, role and guest
+    /// policy)
+    pub allowed: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_manifest_sorted_and_filtered() {
+        let result = json!({
+            "methods": ["vault/list", "chat/respond", "vault/list", "bad name", "", 7]
+        });
+        assert_eq!(
+            from_manifest(&result),
+            Some(vec!["chat/respond".to_string(), "vault/list".to_string()])
+        );
+        assert_eq!(from_manifest(&json!({ "serverInfo": {} })), None);
+        assert_eq!(from_manifest(&json!({ "methods": "ping" })), None);
+    }
+
+    #[test]
+    fn test_cache_tied_to_process() {
+        let state = MethodManifestState::default();
+        state.replace(Some(41), Some(vec!["ping".to_string()]));
+        assert_eq!(state.methods_for(Some(41)), Some(vec!["ping".to_string()]));
+        // Restarted kernel that hasn't sent its own yet
+        assert_eq!(state.methods_for(Some(42)), None);
+        assert_eq!(state.methods_for(None), None);
+        assert!(state.clear());
+        assert!(!state.clear());
+        // A kernel without a manifest leaves nothing cached
+        state.replace(Some(42), None);
+        assert_eq!(state.methods_for(Some(42)), None);
+    }
+}
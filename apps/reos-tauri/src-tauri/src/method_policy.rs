@@ -0,0 +1,171 @@
+//! Per-role Kernel Method Policy for ReOS
+//!
+//! Decides which session roles (see `roles.rs`) may call which kernel
+//! methods. Rules are checked in order and the first rule whose patterns
+//! match the method decides; a method no rule matches is denied.
+//!
+//! Configurable via `method-policy.json` in the app data dir:
+//! ```json
+//! { "rules": [
+//!     { "methods": ["admin/*"], "roles": ["admin"] },
+//!     { "methods": ["files/read*"], "roles": ["guest", "user", "admin"] },
+//!     { "methods": ["*"], "roles": ["user", "admin"] } ] }
+//! ```
+//!
+//! Denials reach the frontend as a JSON-RPC error (`METHOD_DENIED_CODE`)
+//! so they flow through the same path as kernel errors.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::path::Path;
+
+use crate::methods::method_matches;
+use crate::roles::Role;
+
+/// JSON-RPC error code for denied methods
+pub const METHOD_DENIED_CODE: i64 = -32403;
+
+/// Roles allowed to call the methods matching `methods`
+#[derive(Deserialize, Clone)]
+pub struct MethodRule {
+    /// Method names or `prefix*` patterns
+    pub methods: Vec<String>,
+    pub roles: Vec<Role>,
+}
+
+/// Ordered method rules (first match wins)
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct MethodPolicy {
+    pub rules: Vec<MethodRule>,
+}
+
+impl Default for MethodPolicy {
+    fn default() -> Self {
+        Self {
+            rules: vec![
+                MethodRule {
+                    methods: vec!["admin/*".into(), "service/*".into(), "container/*".into()],
+                    roles: vec![Role::Admin],
+                },
+                // Guests are narrowed further by `guest.json`
+                MethodRule {
+                    methods: vec!["*".into()],
+                    roles: vec![Role::Guest, Role::User, Role::Admin],
+                },
+            ],
+        }
+    }
+}
+
+impl MethodPolicy {
+    /// Load the policy from `path` (missing or invalid file = defaults)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether `role` may call `method`
+    pub fn check(&self, role: Role, method: &str) -> Result<(), MethodDenied> {
+        let allowed = self
+            .rules
+            .iter()
+            .find(|rule| rule.methods.iter().any(|p| method_matches(p, method)))
+            .is_some_and(|rule| rule.roles.contains(&role));
+        if allowed {
+            Ok(())
+        } else {
+            Err(MethodDenied {
+                method: method.to_string(),
+                role,
+            })
+        }
+    }
+}
+
+/// A kernel method the session's role may not call
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct MethodDenied {
+    pub method: String,
+    pub role: Role,
+}
+
+impl MethodDenied {
+    /// JSON-RPC error response handed back in place of the kernel's
+    pub fn to_response(&self) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": null,
+            "error": {
+                "code": METHOD_DENIED_CODE,
+                "message": self.to_string(),
+                "data": { "reason": "method_denied", "method": self.method, "role": self.role },
+            },
+        })
+    }
+}
+
+impl fmt::Display for MethodDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let role = serde_json::to_value(self.role).unwrap_or_default();
+        write!(
+            f,
+            "Method {} is not permitted for role {}",
+            self.method,
+            role.as_str().unwrap_or("unknown")
+        )
+    }
+}
+
+/// Method policy state (read-only after startup)
+pub struct MethodPolicyState(pub MethodPolicy);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_reserves_admin_methods() {
+        let policy = MethodPolicy::default();
+        assert!(policy.check(Role::User, "chat/respond").is_ok());
+        assert!(policy.check(Role::Admin, "admin/users").is_ok());
+        assert_eq!(
+            policy.check(Role::User, "admin/users"),
+            Err(MethodDenied {
+                method: "admin/users".into(),
+                role: Role::User,
+            })
+        );
+    }
+
+    #[test]
+    fn test_first_match_wins_and_unmatched_is_denied() {
+        let policy: MethodPolicy = serde_json::from_str(
+            r#"{ "rules": [
+                { "methods": ["files/read*"], "roles": ["guest", "user"] },
+                { "methods": ["files/*"], "roles": ["user"] } ] }"#,
+        )
+        .unwrap();
+        assert!(policy.check(Role::Guest, "files/read_text").is_ok());
+        assert!(policy.check(Role::Guest, "files/delete").is_err());
+        assert!(policy.check(Role::User, "files/delete").is_ok());
+        assert!(policy.check(Role::Admin, "chat/respond").is_err());
+    }
+
+    #[test]
+    fn test_denial_response() {
+        let denied = MethodDenied {
+            method: "admin/users".into(),
+            role: Role::Guest,
+        };
+        let response = denied.to_response();
+        assert_eq!(response["error"]["code"], METHOD_DENIED_CODE);
+        assert_eq!(response["error"]["data"]["role"], "guest");
+        assert_eq!(
+            response["error"]["message"],
+            "Method admin/users is not permitted for role guest"
+        );
+    }
+}
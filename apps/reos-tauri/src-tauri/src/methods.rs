@@ -0,0 +1,26 @@
+//! Kernel Method Patterns
+//!
+//! Policies (step-up, ...) name kernel methods either exactly
+//! (`anthropic/set_key`) or by prefix with a trailing `*` (`safety/set_*`).
+
+/// Whether `method` matches `pattern` (exact, or prefix when it ends in `*`)
+pub fn method_matches(pattern: &str, method: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => method.starts_with(prefix),
+        None => pattern == method,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_and_prefix_patterns() {
+        assert!(method_matches("ping", "ping"));
+        assert!(!method_matches("ping", "ping/extra"));
+        assert!(method_matches("safety/set_*", "safety/set_rate_limit"));
+        assert!(!method_matches("safety/set_*", "safety/settings"));
+        assert!(method_matches("*", "anything"));
+    }
+}
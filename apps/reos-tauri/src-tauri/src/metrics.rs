@@ -0,0 +1,430 @@
+//! Shell Metrics for ReOS
+//!
+//! In-memory counters and histograms, reset when the app exits:
+//! - kernel requests per method (count, errors, latency histogram)
+//! - kernel starts and unexpected exits
+//! - requests waiting for the kernel (queue depth, current and peak)
+//! - authentication failures per audit event
+//! - the kernel process's latest resource sample (`kernel_resources`)
+//!
+//! `metrics_snapshot` returns them with latency percentiles (estimated from
+//! the histogram buckets). Power users can also scrape them in Prometheus
+//! text format from `http://127.0.0.1:<port>/metrics`; the listener only
+//! binds to localhost, is off by default and is enabled via `metrics.json`
+//! in the app data dir: `{ "prometheus_port": 9464 }`
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::kernel_resources::{self, ResourceUsage};
+use crate::locks::Recover;
+use crate::telemetry;
+
+/// Upper bounds of the latency buckets, in milliseconds
+const BUCKETS_MS: &[u64] = &[
+    1, 2, 5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000,
+];
+
+/// Methods tracked separately; later ones are counted as `other`
+const MAX_METHODS: usize = 256;
+const OTHER_METHOD: &str = "other";
+
+/// How long a scraper gets to send its request line
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct MetricsConfig {
+    /// Serve Prometheus text on this localhost port (`None` = off)
+    pub prometheus_port: Option<u16>,
+}
+
+impl MetricsConfig {
+    /// Load from `path`; missing or invalid file means defaults
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Latency histogram over `BUCKETS_MS` (plus an overflow bucket)
+#[derive(Clone, Debug, Default)]
+struct Histogram {
+    counts: Vec<u64>,
+    sum_ms: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, ms: f64) {
+        if self.counts.is_empty() {
+            self.counts = vec![0; BUCKETS_MS.len() + 1];
+        }
+        let bucket = BUCKETS_MS
+            .iter()
+            .position(|&bound| ms <= bound as f64)
+            .unwrap_or(BUCKETS_MS.len());
+        self.counts[bucket] += 1;
+        self.sum_ms += ms;
+    }
+
+    fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Upper bound of the bucket holding the `q` quantile (the largest
+    /// bound for the overflow bucket)
+    fn quantile(&self, q: f64) -> Option<u64> {
+        let total = self.count();
+        if total == 0 {
+            return None;
+        }
+        let rank = ((q * total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(
+                    BUCKETS_MS
+                        .get(i)
+                        .or(BUCKETS_MS.last())
+                        .copied()
+                        .unwrap_or(0),
+                );
+            }
+        }
+        None
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct MethodStats {
+    errors: u64,
+    latency: Histogram,
+}
+
+struct Registry {
+    methods: BTreeMap<String, MethodStats>,
+    auth_failures: BTreeMap<String, u64>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    methods: BTreeMap::new(),
+    auth_failures: BTreeMap::new(),
+});
+static KERNEL_STARTS: AtomicU64 = AtomicU64::new(0);
+static KERNEL_EXITS: AtomicU64 = AtomicU64::new(0);
+static QUEUE_DEPTH: AtomicU64 = AtomicU64::new(0);
+static QUEUE_PEAK: AtomicU64 = AtomicU64::new(0);
+
+fn registry() -> MutexGuard<'static, Registry> {
+    REGISTRY.lock_or_recover()
+}
+
+/// A finished kernel request (`ok` = a response without `error`)
+pub fn record_request(method: &str, elapsed: Duration, ok: bool) {
+    let mut registry = registry();
+    let tracked = registry.methods.contains_key(method) || registry.methods.len() < MAX_METHODS;
+    let key = if tracked { method } else { OTHER_METHOD };
+    let stats = registry.methods.entry(key.to_string()).or_default();
+    stats.latency.observe(elapsed.as_secs_f64() * 1000.0);
+    if !ok {
+        stats.errors += 1;
+    }
+    telemetry::record_request(key, elapsed, ok);
+}
+
+/// A kernel finished its handshake
+pub fn record_kernel_start() {
+    KERNEL_STARTS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A kernel process died
+pub fn record_kernel_exit() {
+    KERNEL_EXITS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A failed authentication attempt, by audit event name
+pub fn record_auth_failure(event: &str) {
+    *registry()
+        .auth_failures
+        .entry(event.to_string())
+        .or_default() += 1;
+}
+
+/// Counts its holder as queued for the kernel until dropped
+pub struct QueueGuard(());
+
+impl QueueGuard {
+    pub fn enter() -> Self {
+        let depth = QUEUE_DEPTH.fetch_add(1, Ordering::Relaxed) + 1;
+        QUEUE_PEAK.fetch_max(depth, Ordering::Relaxed);
+        QueueGuard(())
+    }
+}
+
+impl Drop for QueueGuard {
+    fn drop(&mut self) {
+        QUEUE_DEPTH.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Requests waiting for the kernel right now
+pub fn queue_depth() -> u64 {
+    QUEUE_DEPTH.load(Ordering::Relaxed)
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct MethodSnapshot {
+    pub count: u64,
+    pub errors: u64,
+    pub p50_ms: Option<u64>,
+    pub p90_ms: Option<u64>,
+    pub p99_ms: Option<u64>,
+    pub mean_ms: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Snapshot {
+    pub requests: BTreeMap<String, MethodSnapshot>,
+    pub kernel_starts: u64,
+    /// Starts after the first (each one follows an exit or a reset)
+    pub kernel_restarts: u64,
+    pub kernel_exits: u64,
+    pub queue_depth: u64,
+    pub queue_peak: u64,
+    pub auth_failures: BTreeMap<String, u64>,
+    pub kernel_resources: Option<ResourceUsage>,
+}
+
+pub fn snapshot() -> Snapshot {
+    let registry = registry();
+    let requests = registry
+        .methods
+        .iter()
+        .map(|(method, stats)| {
+            let count = stats.latency.count();
+            let snapshot = MethodSnapshot {
+                count,
+                errors: stats.errors,
+                p50_ms: stats.latency.quantile(0.5),
+                p90_ms: stats.latency.quantile(0.9),
+                p99_ms: stats.latency.quantile(0.99),
+                mean_ms: (count > 0).then(|| stats.latency.sum_ms / count as f64),
+            };
+            (method.clone(), snapshot)
+        })
+        .collect();
+    let kernel_starts = KERNEL_STARTS.load(Ordering::Relaxed);
+    Snapshot {
+        requests,
+        kernel_starts,
+        kernel_restarts: kernel_starts.saturating_sub(1),
+        kernel_exits: KERNEL_EXITS.load(Ordering::Relaxed),
+        queue_depth: QUEUE_DEPTH.load(Ordering::Relaxed),
+        queue_peak: QUEUE_PEAK.load(Ordering::Relaxed),
+        auth_failures: registry.auth_failures.clone(),
+        kernel_resources: kernel_resources::latest(),
+    }
+}
+
+fn label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// All metrics in Prometheus text exposition format
+pub fn prometheus() -> String {
+    let registry = registry();
+    let mut out = String::new();
+    let _ = writeln!(out, "# TYPE reos_kernel_requests_total counter");
+    for (method, stats) in &registry.methods {
+        let method = label(method);
+        let _ = writeln!(
+            out,
+            "reos_kernel_requests_total{{method=\"{method}\"}} {}",
+            stats.latency.count()
+        );
+    }
+    let _ = writeln!(out, "# TYPE reos_kernel_request_errors_total counter");
+    for (method, stats) in &registry.methods {
+        let method = label(method);
+        let _ = writeln!(
+            out,
+            "reos_kernel_request_errors_total{{method=\"{method}\"}} {}",
+            stats.errors
+        );
+    }
+    let _ = writeln!(out, "# TYPE reos_kernel_request_duration_seconds histogram");
+    for (method, stats) in &registry.methods {
+        let method = label(method);
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS_MS.iter().zip(&stats.latency.counts) {
+            cumulative += count;
+            let le = *bound as f64 / 1000.0;
+            let _ = writeln!(
+                out,
+                "reos_kernel_request_duration_seconds_bucket{{method=\"{method}\",le=\"{le}\"}} {cumulative}"
+            );
+        }
+        let count = stats.latency.count();
+        let _ = writeln!(
+            out,
+            "reos_kernel_request_duration_seconds_bucket{{method=\"{method}\",le=\"+Inf\"}} {count}"
+        );
+        let _ = writeln!(
+            out,
+            "reos_kernel_request_duration_seconds_sum{{method=\"{method}\"}} {}",
+            stats.latency.sum_ms / 1000.0
+        );
+        let _ = writeln!(
+            out,
+            "reos_kernel_request_duration_seconds_count{{method=\"{method}\"}} {count}"
+        );
+    }
+    let _ = writeln!(out, "# TYPE reos_auth_failures_total counter");
+    for (event, count) in &registry.auth_failures {
+        let _ = writeln!(out, "reos_auth_failures_total{{event=\"{event}\"}} {count}");
+    }
+    for (name, kind, value) in [
+        ("reos_kernel_starts_total", "counter", &KERNEL_STARTS),
+        ("reos_kernel_exits_total", "counter", &KERNEL_EXITS),
+        ("reos_kernel_queue_depth", "gauge", &QUEUE_DEPTH),
+        ("reos_kernel_queue_peak", "gauge", &QUEUE_PEAK),
+    ] {
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+        let _ = writeln!(out, "{name} {}", value.load(Ordering::Relaxed));
+    }
+    if let Some(usage) = kernel_resources::latest() {
+        for (name, value) in [
+            (
+                "reos_kernel_resident_memory_bytes",
+                Some(usage.rss_bytes as f64),
+            ),
+            ("reos_kernel_cpu_percent", usage.cpu_percent),
+            ("reos_kernel_open_fds", usage.open_fds.map(|n| n as f64)),
+            ("reos_kernel_children", usage.children.map(|n| n as f64)),
+        ] {
+            if let Some(value) = value {
+                let _ = writeln!(out, "# TYPE {name} gauge");
+                let _ = writeln!(out, "{name} {value}");
+            }
+        }
+    }
+    out
+}
+
+fn serve(mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(SCRAPE_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(stream.try_clone()?)
+        .take(1024)
+        .read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", prometheus()),
+        _ => ("404 Not Found", String::new()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// Serve `/metrics` on `127.0.0.1:<prometheus_port>` from a background
+/// thread, if configured
+pub fn serve_prometheus(config: &MetricsConfig) {
+    let Some(port) = config.prometheus_port else {
+        return;
+    };
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::warn!(error = %e, port, "metrics endpoint unavailable");
+            return;
+        }
+    };
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = serve(stream);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_quantiles() {
+        let mut histogram = Histogram::default();
+        assert_eq!(histogram.quantile(0.5), None);
+        for ms in [
+            0.4, 3.0, 4.0, 20.0, 20.0, 20.0, 90.0, 90.0, 400.0, 100_000.0,
+        ] {
+            histogram.observe(ms);
+        }
+        assert_eq!(histogram.count(), 10);
+        assert_eq!(histogram.quantile(0.5), Some(25));
+        assert_eq!(histogram.quantile(0.9), Some(500));
+        assert_eq!(histogram.quantile(0.99), Some(60_000));
+        assert_eq!(histogram.quantile(0.0), Some(1));
+    }
+
+    #[test]
+    fn test_snapshot_and_prometheus() {
+        record_request("metrics/test", Duration::from_millis(3), true);
+        record_request("metrics/test", Duration::from_millis(40), false);
+        record_auth_failure("login_failure");
+        {
+            let _waiting = QueueGuard::enter();
+            assert!(snapshot().queue_peak >= 1);
+        }
+
+        let snapshot = snapshot();
+        let stats = &snapshot.requests["metrics/test"];
+        assert_eq!((stats.count, stats.errors), (2, 1));
+        assert_eq!(stats.p50_ms, Some(5));
+        assert_eq!(stats.p99_ms, Some(50));
+        assert!(snapshot.auth_failures["login_failure"] >= 1);
+
+        let text = prometheus();
+        assert!(text.contains("reos_kernel_requests_total{method=\"metrics/test\"} 2"));
+        assert!(text.contains(
+            "reos_kernel_request_duration_seconds_bucket{method=\"metrics/test\",le=\"0.005\"} 1"
+        ));
+        assert!(text.contains(
+            "reos_kernel_request_duration_seconds_bucket{method=\"metrics/test\",le=\"+Inf\"} 2"
+        ));
+        assert_eq!(label("a\"b\\c"), "a\\\"b\\\\c");
+    }
+
+    #[test]
+    fn test_prometheus_endpoint() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let scrape = |path: &str| {
+            let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+            write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+            let (server, _) = listener.accept().unwrap();
+            serve(server).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let response = scrape("/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("reos_kernel_starts_total"));
+        assert!(scrape("/").starts_with("HTTP/1.1 404"));
+    }
+}
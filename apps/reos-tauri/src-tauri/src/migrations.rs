@@ -0,0 +1,368 @@
+//! Versioned Migrations of Local Files
+//!
+//! Files the shell persists (`config.toml`, window state, the session
+//! vault) change shape between releases. `schema-versions.json` in the
+//! data dir records the version each file was last brought to, and at
+//! startup, before anything loads them, `run` applies the newer
+//! migrations of each file (`STORES`) in order:
+//!
+//! - a copy of the file goes to `backups/<file>.v<version>-<unix time>`
+//!   in the data dir first
+//! - migrations must be idempotent: if the app dies before the new version
+//!   is recorded they run again
+//! - if one fails the file is restored from the backup, keeps its old
+//!   version and the failure (with the backup's path) is logged and listed
+//!   by `health_check`; other files still migrate
+//! - a file recorded at a version newer than this build knows (after a
+//!   downgrade) is backed up and reported, never rewritten here
+//!
+//! Files from before versions were recorded count as version 1; a file
+//! that doesn't exist yet is recorded at the current version. A missing or
+//! unreadable `schema-versions.json` means every file starts from 1 again,
+//! which idempotent migrations make safe.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+use crate::config;
+use crate::storage::write_private_file;
+
+/// Version record in the data dir
+pub const VERSIONS_FILE: &str = "schema-versions.json";
+
+/// Pre-migration copies, in the data dir
+const BACKUP_DIR: &str = "backups";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MigrationError {
+    #[error("written by a newer version (v{found}, this build knows up to v{supported})")]
+    Newer { found: u32, supported: u32 },
+    #[error("cannot back up: {0}")]
+    Backup(String),
+    #[error("migration to v{to} ({description}) failed: {message}")]
+    Failed {
+        to: u32,
+        description: &'static str,
+        message: String,
+    },
+}
+
+/// Which of the profile's dirs a file lives in
+#[derive(Clone, Copy, Debug)]
+pub enum Location {
+    Data,
+    Config,
+}
+
+/// One step of a file's format
+pub struct Migration {
+    /// Version the file has afterwards
+    pub to: u32,
+    pub description: &'static str,
+    /// Rewrite the file at the given path in place
+    pub apply: fn(&Path) -> Result<(), String>,
+}
+
+/// A persisted file and its migrations, oldest first
+pub struct Store {
+    pub file: &'static str,
+    pub location: Location,
+    pub migrations: &'static [Migration],
+}
+
+impl Store {
+    /// Version this build writes
+    pub fn version(&self) -> u32 {
+        self.migrations.last().map_or(1, |m| m.to)
+    }
+}
+
+/// Files under migration; append a `Migration` when a format changes
+pub const STORES: &[Store] = &[
+    Store {
+        file: config::CONFIG_FILE,
+        location: Location::Config,
+        migrations: &[],
+    },
+    Store {
+        file: "window-state.json",
+        location: Location::Config,
+        migrations: &[],
+    },
+    Store {
+        file: "sessions.vault",
+        location: Location::Data,
+        migrations: &[],
+    },
+];
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Migrated {
+    pub file: &'static str,
+    pub from: u32,
+    pub to: u32,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct MigrationFailure {
+    pub file: &'static str,
+    pub error: String,
+    /// Copy of the file as it was before
+    pub backup: Option<PathBuf>,
+}
+
+/// What a startup run did
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct MigrationReport {
+    pub migrated: Vec<Migrated>,
+    pub failed: Vec<MigrationFailure>,
+}
+
+/// Result of the startup run
+pub struct MigrationState(pub MigrationReport);
+
+/// Bring the profile's files up to date (see the module docs)
+pub fn run(data_dir: &Path, config_dir: &Path) -> MigrationReport {
+    run_stores(STORES, data_dir, config_dir)
+}
+
+fn run_stores(stores: &[Store], data_dir: &Path, config_dir: &Path) -> MigrationReport {
+    let versions_path = data_dir.join(VERSIONS_FILE);
+    let mut versions = read_versions(&versions_path);
+    let before = versions.clone();
+    let mut report = MigrationReport::default();
+    for store in stores {
+        let dir = match store.location {
+            Location::Data => data_dir,
+            Location::Config => config_dir,
+        };
+        let path = dir.join(store.file);
+        let target = store.version();
+        if !path.exists() {
+            versions.insert(store.file.to_string(), target);
+            continue;
+        }
+        let from = versions.get(store.file).copied().unwrap_or(1);
+        if from == target {
+            versions.insert(store.file.to_string(), target);
+            continue;
+        }
+        let backup = match backup(&path, data_dir, store.file, from) {
+            Ok(backup) => backup,
+            Err(e) => {
+                report.fail(store.file, e, None);
+                continue;
+            }
+        };
+        if from > target {
+            let error = MigrationError::Newer {
+                found: from,
+                supported: target,
+            };
+            report.fail(store.file, error, Some(backup));
+            continue;
+        }
+        match migrate(store, from, &path) {
+            Ok(()) => {
+                tracing::info!(file = store.file, from, to = target, "migrated");
+                versions.insert(store.file.to_string(), target);
+                report.migrated.push(Migrated {
+                    file: store.file,
+                    from,
+                    to: target,
+                });
+            }
+            Err(e) => {
+                if let Err(restore) = fs::copy(&backup, &path) {
+                    tracing::error!(file = store.file, error = %restore, "cannot restore backup");
+                }
+                report.fail(store.file, e, Some(backup));
+            }
+        }
+    }
+    if versions != before {
+        let saved = serde_json::to_vec_pretty(&versions)
+            .map_err(|e| e.to_string())
+            .and_then(|data| write_private_file(&versions_path, &data).map_err(|e| e.to_string()));
+        if let Err(e) = saved {
+            tracing::error!(error = %e, "cannot record {VERSIONS_FILE}");
+        }
+    }
+    report
+}
+
+impl MigrationReport {
+    fn fail(&mut self, file: &'static str, error: MigrationError, backup: Option<PathBuf>) {
+        tracing::error!(file, error = %error, backup = ?backup, "migration failed");
+        self.failed.push(MigrationFailure {
+            file,
+            error: error.to_string(),
+            backup,
+        });
+    }
+}
+
+/// Apply `store`'s migrations past `from`, in order
+fn migrate(store: &Store, from: u32, path: &Path) -> Result<(), MigrationError> {
+    for migration in store.migrations.iter().filter(|m| m.to > from) {
+        (migration.apply)(path).map_err(|message| MigrationError::Failed {
+            to: migration.to,
+            description: migration.description,
+            message,
+        })?;
+    }
+    Ok(())
+}
+
+/// Recorded versions (empty if the file is missing or unreadable)
+fn read_versions(path: &Path) -> BTreeMap<String, u32> {
+    match fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "ignoring invalid {VERSIONS_FILE}");
+            BTreeMap::new()
+        }),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+/// Copy `path` into the backup dir, private to the user
+fn backup(
+    path: &Path,
+    data_dir: &Path,
+    file: &str,
+    version: u32,
+) -> Result<PathBuf, MigrationError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let target = data_dir
+        .join(BACKUP_DIR)
+        .join(format!("{file}.v{version}-{now}"));
+    let data = fs::read(path).map_err(|e| MigrationError::Backup(e.to_string()))?;
+    write_private_file(&target, &data).map_err(|e| MigrationError::Backup(e.to_string()))?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dirs(name: &str) -> (PathBuf, PathBuf) {
+        let root = std::env::temp_dir().join(format!("reos-migrate-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let (data, config) = (root.join("data"), root.join("config"));
+        fs::create_dir_all(&data).unwrap();
+        fs::create_dir_all(&config).unwrap();
+        (data, config)
+    }
+
+    fn append_v2(path: &Path) -> Result<(), String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        if !text.contains("v2") {
+            fs::write(path, format!("{text}v2\n")).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn fail(_: &Path) -> Result<(), String> {
+        Err("unexpected layout".into())
+    }
+
+    const STORES_V2: &[Store] = &[Store {
+        file: "state.txt",
+        location: Location::Data,
+        migrations: &[Migration {
+            to: 2,
+            description: "append v2",
+            apply: append_v2,
+        }],
+    }];
+
+    #[test]
+    fn test_migrates_once_with_backup() {
+        let (data, config) = dirs("once");
+        fs::write(data.join("state.txt"), "v1\n").unwrap();
+        let report = run_stores(STORES_V2, &data, &config);
+        assert_eq!(
+            report.migrated,
+            vec![Migrated {
+                file: "state.txt",
+                from: 1,
+                to: 2
+            }]
+        );
+        assert_eq!(
+            fs::read_to_string(data.join("state.txt")).unwrap(),
+            "v1\nv2\n"
+        );
+        let backups: Vec<_> = fs::read_dir(data.join(BACKUP_DIR)).unwrap().collect();
+        assert_eq!(backups.len(), 1);
+        // Recorded: nothing runs again
+        let report = run_stores(STORES_V2, &data, &config);
+        assert!(report.migrated.is_empty() && report.failed.is_empty());
+        // Lost record: re-running is harmless
+        fs::remove_file(data.join(VERSIONS_FILE)).unwrap();
+        run_stores(STORES_V2, &data, &config);
+        assert_eq!(
+            fs::read_to_string(data.join("state.txt")).unwrap(),
+            "v1\nv2\n"
+        );
+        let _ = fs::remove_dir_all(data.parent().unwrap());
+    }
+
+    #[test]
+    fn test_failure_restores_backup_and_keeps_version() {
+        const FAILING: &[Store] = &[Store {
+            file: "state.txt",
+            location: Location::Data,
+            migrations: &[
+                Migration {
+                    to: 2,
+                    description: "append v2",
+                    apply: append_v2,
+                },
+                Migration {
+                    to: 3,
+                    description: "reshape",
+                    apply: fail,
+                },
+            ],
+        }];
+        let (data, config) = dirs("fail");
+        fs::write(data.join("state.txt"), "v1\n").unwrap();
+        let report = run_stores(FAILING, &data, &config);
+        assert_eq!(report.failed.len(), 1);
+        assert!(report.failed[0].error.contains("v3 (reshape)"));
+        assert!(report.failed[0].backup.as_ref().unwrap().is_file());
+        assert_eq!(fs::read_to_string(data.join("state.txt")).unwrap(), "v1\n");
+        assert_eq!(
+            read_versions(&data.join(VERSIONS_FILE)).get("state.txt"),
+            None
+        );
+        let _ = fs::remove_dir_all(data.parent().unwrap());
+    }
+
+    #[test]
+    fn test_new_and_newer_files() {
+        let (data, config) = dirs("newer");
+        // Not there yet: recorded at the current version, nothing to do
+        let report = run_stores(STORES_V2, &data, &config);
+        assert!(report.migrated.is_empty());
+        assert_eq!(read_versions(&data.join(VERSIONS_FILE))["state.txt"], 2);
+
+        fs::write(data.join(VERSIONS_FILE), r#"{"state.txt": 7}"#).unwrap();
+        fs::write(data.join("state.txt"), "future\n").unwrap();
+        let report = run_stores(STORES_V2, &data, &config);
+        assert!(report.failed[0].error.contains("newer version (v7"));
+        assert_eq!(
+            fs::read_to_string(data.join("state.txt")).unwrap(),
+            "future\n"
+        );
+        let _ = fs::remove_dir_all(data.parent().unwrap());
+    }
+}
@@ -0,0 +1,217 @@
+//! Native Desktop Notifications for ReOS
+//!
+//! Routes four sources to the desktop's notification service:
+//! - kernel push events tagged `notify`
+//!   (`{"method":"event","params":{"tag":"notify","title":..,"body":..}}`)
+//! - session-expiry warnings from the sweeper, once per idle stretch
+//! - crash reports (panics, kernel exits)
+//! - results of tray quick actions
+//!
+//! Categories can be muted in `notifications.json` in the app data dir:
+//! `{ "muted": ["session_expiry"] }`
+//!
+//! Shown through tauri-plugin-notification (the desktop notification
+//! service on Linux, Notification Center on macOS, toasts on Windows). If
+//! the plugin can't prepare one, a `notification` event goes to the focused
+//! window (every window if none has focus) to display instead. Delivery
+//! itself happens in the background, so a missing notification service
+//! only shows in the logs.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+use tracing::warn;
+
+use crate::locks::Recover;
+use crate::windows::WindowsState;
+
+/// Frontend fallback event
+pub const NOTIFICATION_EVENT: &str = "notification";
+
+/// Kernel push event tag that asks for a notification
+const NOTIFY_TAG: &str = "notify";
+
+/// Longest title/body shown (kernel-supplied text is truncated)
+const MAX_TEXT_CHARS: usize = 256;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Kernel,
+    SessionExpiry,
+    Crash,
+    QuickAction,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Notification {
+    pub category: Category,
+    pub title: String,
+    pub body: String,
+}
+
+impl Notification {
+    pub fn new(category: Category, title: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            category,
+            title: title.into(),
+            body: body.into(),
+        }
+    }
+
+    /// Parse a kernel push event (`None` unless tagged `notify`)
+    pub fn from_kernel_event(event: &Value) -> Option<Self> {
+        let params = event.get("params")?;
+        if params.get("tag").and_then(Value::as_str) != Some(NOTIFY_TAG) {
+            return None;
+        }
+        let text = |key: &str| {
+            params
+                .get(key)
+                .and_then(Value::as_str)
+                .map(|s| s.chars().take(MAX_TEXT_CHARS).collect::<String>())
+        };
+        Some(Self::new(
+            Category::Kernel,
+            text("title")?,
+            text("body").unwrap_or_default(),
+        ))
+    }
+}
+
+/// Muted categories
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub muted: HashSet<Category>,
+}
+
+impl NotificationConfig {
+    /// Load the config from `path` (missing or invalid file = nothing muted)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Notification config plus the expiry warnings already shown
+pub struct NotificationState {
+    config: NotificationConfig,
+    /// Session id -> last activity at the time of its warning
+    warned: Mutex<HashMap<String, Instant>>,
+}
+
+impl NotificationState {
+    pub fn new(config: NotificationConfig) -> Self {
+        Self {
+            config,
+            warned: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether to warn about `session_id` expiring: once until it sees
+    /// activity again
+    pub fn should_warn_expiry(&self, session_id: &str, last_activity: Instant) -> bool {
+        let mut warned = self.warned.lock_or_recover();
+        if warned.get(session_id) == Some(&last_activity) {
+            return false;
+        }
+        warned.insert(session_id.to_string(), last_activity);
+        true
+    }
+
+    /// Forget warnings for sessions that no longer exist
+    pub fn retain_sessions(&self, live: &HashSet<String>) {
+        self.warned
+            .lock_or_recover()
+            .retain(|session_id, _| live.contains(session_id));
+    }
+}
+
+/// Show `notification` unless its category is muted (doesn't block)
+pub fn notify(app: &AppHandle, notification: Notification) {
+    let Some(state) = app.try_state::<NotificationState>() else {
+        return;
+    };
+    if state.config.muted.contains(&notification.category) {
+        return;
+    }
+    let notification = Notification {
+        title: crate::redact::text(&notification.title),
+        body: crate::redact::text(&notification.body),
+        ..notification
+    };
+    let shown = app
+        .notification()
+        .builder()
+        .title(&notification.title)
+        .body(&notification.body)
+        .show();
+    if let Err(e) = shown {
+        warn!(error = %e, "native notification unavailable, using frontend event");
+        let focused = app
+            .state::<WindowsState>()
+            .0
+            .lock_or_recover()
+            .focused()
+            .map(str::to_string);
+        let sent = match focused {
+            Some(label) => app.emit_to(label.as_str(), NOTIFICATION_EVENT, &notification),
+            None => app.emit(NOTIFICATION_EVENT, &notification),
+        };
+        if let Err(e) = sent {
+            warn!(error = %e, "failed to emit {NOTIFICATION_EVENT}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+
+    #[test]
+    fn test_kernel_event_needs_notify_tag_and_title() {
+        let event = json!({ "method": "event", "params": {
+            "tag": "notify", "title": "Import finished", "body": "42 notes" } });
+        assert_eq!(
+            Notification::from_kernel_event(&event),
+            Some(Notification::new(
+                Category::Kernel,
+                "Import finished",
+                "42 notes"
+            ))
+        );
+        let untagged = json!({ "method": "event", "params": { "title": "x" } });
+        assert_eq!(Notification::from_kernel_event(&untagged), None);
+        let untitled = json!({ "method": "event", "params": { "tag": "notify" } });
+        assert_eq!(Notification::from_kernel_event(&untitled), None);
+    }
+
+    #[test]
+    fn test_config_mutes_categories() {
+        let config: NotificationConfig =
+            serde_json::from_str(r#"{ "muted": ["session_expiry"] }"#).unwrap();
+        assert!(config.muted.contains(&Category::SessionExpiry));
+        assert!(!config.muted.contains(&Category::Crash));
+    }
+
+    #[test]
+    fn test_expiry_warning_once_per_idle_stretch() {
+        let state = NotificationState::new(NotificationConfig::default());
+        let idle_since = Instant::now();
+        assert!(state.should_warn_expiry("abc", idle_since));
+        assert!(!state.should_warn_expiry("abc", idle_since));
+        assert!(state.should_warn_expiry("abc", idle_since + Duration::from_secs(1)));
+
+        state.retain_sessions(&HashSet::new());
+        assert!(state.should_warn_expiry("abc", idle_since));
+    }
+}
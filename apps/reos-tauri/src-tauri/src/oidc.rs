@@ -0,0 +1,505 @@
+//! OIDC Login via the System Browser
+//!
+//! For organizations whose users no longer have local PAM passwords. The
+//! authorization code flow (with PKCE) runs in the user's default browser;
+//! Rust listens on a loopback port for the redirect, exchanges the code at
+//! the token endpoint, and maps the ID token's identity to a local user.
+//! The kernel then opens a session for that user (`auth/login_verified`),
+//! deriving the encryption key from the passphrase as usual.
+//!
+//! The ID token comes straight from the token endpoint over TLS, so per
+//! OIDC Core 3.1.3.7 its signature isn't checked; issuer, audience,
+//! expiry and nonce are.
+//!
+//! Configured via `oidc.json` in the app data dir (absent = disabled):
+//! ```json
+//! { "issuer": "https://id.example.com", "client_id": "reos",
+//!   "username_claim": "email", "users": { "alice@example.com": "alice" } }
+//! ```
+
+use data_encoding::BASE64URL_NOPAD;
+use rand::RngCore;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+use crate::secret::constant_time_eq;
+
+/// How long to wait for the browser to come back to the loopback listener
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(180);
+/// Poll interval while waiting for the redirect
+const CALLBACK_POLL: Duration = Duration::from_millis(100);
+/// Timeout for requests to the identity provider
+const HTTP_TIMEOUT: Duration = Duration::from_secs(15);
+/// Tolerated clock skew when checking `exp`
+const CLOCK_SKEW_SECS: u64 = 60;
+
+/// Page shown in the browser once the redirect has been received
+const CALLBACK_PAGE: &str = "<!doctype html><title>ReOS</title>\
+<p>Sign-in received. You can close this tab and return to ReOS.</p>";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum OidcError {
+    #[error("OIDC login is not configured")]
+    NotConfigured,
+    #[error("an OIDC login is already in progress")]
+    Busy,
+    #[error("identity provider discovery failed: {0}")]
+    Discovery(String),
+    #[error("could not open the browser: {0}")]
+    Browser(String),
+    #[error("timed out waiting for the identity provider")]
+    Timeout,
+    #[error("identity provider denied the login: {0}")]
+    Denied(String),
+    #[error("login response did not match this request")]
+    StateMismatch,
+    #[error("token exchange failed: {0}")]
+    TokenExchange(String),
+    #[error("invalid ID token: {0}")]
+    InvalidToken(String),
+    #[error("no local user for identity {0}")]
+    UnmappedIdentity(String),
+    #[error("loopback listener error: {0}")]
+    Listener(String),
+}
+
+/// Identity provider settings from `oidc.json`
+#[derive(Deserialize, Clone)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    /// Only for providers that insist on confidential clients
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<String>,
+    /// ID token claim identifying the user
+    #[serde(default = "default_username_claim")]
+    pub username_claim: String,
+    /// Claim value -> local username
+    #[serde(default)]
+    pub users: HashMap<String, String>,
+    /// If set, `user@<domain>` maps to local `user` when not in `users`
+    #[serde(default)]
+    pub domain: Option<String>,
+}
+
+fn default_scopes() -> Vec<String> {
+    vec!["openid".into(), "email".into(), "profile".into()]
+}
+
+fn default_username_claim() -> String {
+    "email".to_string()
+}
+
+impl OidcConfig {
+    /// Load the configuration from `path` (missing or invalid file = disabled)
+    pub fn load(path: Option<&Path>) -> Option<Self> {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+    }
+
+    /// Map an ID token's claims to a local username
+    fn map_user(&self, claims: &Value) -> Result<String, OidcError> {
+        let identity = claims
+            .get(&self.username_claim)
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                OidcError::InvalidToken(format!("missing claim {}", self.username_claim))
+            })?;
+        if self.username_claim == "email"
+            && claims.get("email_verified") == Some(&Value::Bool(false))
+        {
+            return Err(OidcError::UnmappedIdentity(format!(
+                "{identity} (email not verified)"
+            )));
+        }
+
+        if let Some(user) = self.users.get(identity) {
+            return Ok(user.clone());
+        }
+        self.domain
+            .as_deref()
+            .and_then(|domain| identity.strip_suffix(domain)?.strip_suffix('@'))
+            .filter(|user| !user.is_empty())
+            .map(str::to_string)
+            .ok_or_else(|| OidcError::UnmappedIdentity(identity.to_string()))
+    }
+}
+
+/// The provider endpoints we use from the discovery document
+#[derive(Deserialize)]
+struct ProviderMetadata {
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+/// A verified login
+#[derive(Debug)]
+pub struct OidcIdentity {
+    /// Local username the identity maps to
+    pub username: String,
+    /// Provider's stable subject identifier (`sub`)
+    pub subject: String,
+}
+
+/// OIDC configuration plus a guard against concurrent browser flows
+pub struct OidcState {
+    pub config: Option<OidcConfig>,
+    in_flight: AtomicBool,
+}
+
+impl OidcState {
+    pub fn new(config: Option<OidcConfig>) -> Self {
+        Self {
+            config,
+            in_flight: AtomicBool::new(false),
+        }
+    }
+
+    /// Run the browser flow, one at a time (blocking)
+    pub fn login(
+        &self,
+        open_browser: impl FnOnce(&str) -> Result<(), String>,
+    ) -> Result<OidcIdentity, OidcError> {
+        let config = self.config.as_ref().ok_or(OidcError::NotConfigured)?;
+        if self.in_flight.swap(true, Ordering::SeqCst) {
+            return Err(OidcError::Busy);
+        }
+        let result = authorize(config, open_browser);
+        self.in_flight.store(false, Ordering::SeqCst);
+        result
+    }
+}
+
+/// Authorization code flow with PKCE through a loopback redirect
+fn authorize(
+    config: &OidcConfig,
+    open_browser: impl FnOnce(&str) -> Result<(), String>,
+) -> Result<OidcIdentity, OidcError> {
+    let agent = ureq::AgentBuilder::new().timeout(HTTP_TIMEOUT).build();
+    let issuer = config.issuer.trim_end_matches('/');
+    let metadata: ProviderMetadata = agent
+        .get(&format!("{issuer}/.well-known/openid-configuration"))
+        .call()
+        .map_err(|e| OidcError::Discovery(e.to_string()))?
+        .into_json()
+        .map_err(|e| OidcError::Discovery(e.to_string()))?;
+
+    let listener =
+        TcpListener::bind(("127.0.0.1", 0)).map_err(|e| OidcError::Listener(e.to_string()))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| OidcError::Listener(e.to_string()))?
+        .port();
+    let redirect_uri = format!("http://127.0.0.1:{port}/callback");
+
+    let state = random_token();
+    let nonce = random_token();
+    let verifier = random_token();
+    let auth_url = url::Url::parse_with_params(
+        &metadata.authorization_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", config.client_id.as_str()),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("scope", config.scopes.join(" ").as_str()),
+            ("state", state.as_str()),
+            ("nonce", nonce.as_str()),
+            ("code_challenge", pkce_challenge(&verifier).as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .map_err(|e| OidcError::Discovery(format!("bad authorization endpoint: {e}")))?;
+
+    open_browser(auth_url.as_str()).map_err(OidcError::Browser)?;
+    let code = wait_for_code(&listener, &state)?;
+
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+        ("redirect_uri", redirect_uri.as_str()),
+        ("client_id", config.client_id.as_str()),
+        ("code_verifier", verifier.as_str()),
+    ];
+    if let Some(secret) = &config.client_secret {
+        form.push(("client_secret", secret.as_str()));
+    }
+    let tokens: TokenResponse = agent
+        .post(&metadata.token_endpoint)
+        .send_form(&form)
+        .map_err(|e| OidcError::TokenExchange(e.to_string()))?
+        .into_json()
+        .map_err(|e| OidcError::TokenExchange(e.to_string()))?;
+
+    let claims = decode_claims(&tokens.id_token)?;
+    validate_claims(&claims, config, &nonce, unix_now())?;
+    let subject = claims
+        .get("sub")
+        .and_then(Value::as_str)
+        .ok_or_else(|| OidcError::InvalidToken("missing sub".into()))?
+        .to_string();
+    Ok(OidcIdentity {
+        username: config.map_user(&claims)?,
+        subject,
+    })
+}
+
+/// Accept connections until the provider redirects back with a code
+fn wait_for_code(listener: &TcpListener, state: &str) -> Result<String, OidcError> {
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| OidcError::Listener(e.to_string()))?;
+    let deadline = Instant::now() + CALLBACK_TIMEOUT;
+
+    while Instant::now() < deadline {
+        let mut stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                std::thread::sleep(CALLBACK_POLL);
+                continue;
+            }
+            Err(e) => return Err(OidcError::Listener(e.to_string())),
+        };
+
+        let Some(target) = read_request_target(&stream) else {
+            continue;
+        };
+        // Browsers also ask for /favicon.ico and the like
+        let Some(params) = parse_callback(&target) else {
+            let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+            continue;
+        };
+        respond(&mut stream, CALLBACK_PAGE);
+        return params.into_code(state);
+    }
+    Err(OidcError::Timeout)
+}
+
+/// Request target (path and query) of an HTTP request line
+fn read_request_target(stream: &TcpStream) -> Option<String> {
+    stream.set_nonblocking(false).ok()?;
+    stream.set_read_timeout(Some(Duration::from_secs(5))).ok()?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    (parts.next()? == "GET").then_some(())?;
+    parts.next().map(str::to_string)
+}
+
+fn respond(stream: &mut TcpStream, body: &str) {
+    let _ = write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+}
+
+/// Query parameters of the redirect
+#[derive(Default)]
+struct CallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+impl CallbackParams {
+    fn into_code(self, expected_state: &str) -> Result<String, OidcError> {
+        if let Some(error) = self.error {
+            return Err(OidcError::Denied(error));
+        }
+        let state = self.state.unwrap_or_default();
+        if !constant_time_eq(state.as_bytes(), expected_state.as_bytes()) {
+            return Err(OidcError::StateMismatch);
+        }
+        self.code
+            .ok_or_else(|| OidcError::Denied("no authorization code".into()))
+    }
+}
+
+/// Parse `/callback?...` (anything else is not ours)
+fn parse_callback(target: &str) -> Option<CallbackParams> {
+    let query = target.strip_prefix("/callback")?;
+    let query = query.strip_prefix('?').unwrap_or(query);
+    let mut params = CallbackParams::default();
+    for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+        match key.as_ref() {
+            "code" => params.code = Some(value.into_owned()),
+            "state" => params.state = Some(value.into_owned()),
+            "error" => params.error = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+    Some(params)
+}
+
+/// Decode the (unverified) payload of a compact JWT
+fn decode_claims(id_token: &str) -> Result<Value, OidcError> {
+    let payload = id_token
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| OidcError::InvalidToken("malformed".into()))?;
+    let bytes = BASE64URL_NOPAD
+        .decode(payload.trim_end_matches('=').as_bytes())
+        .map_err(|e| OidcError::InvalidToken(e.to_string()))?;
+    serde_json::from_slice(&bytes).map_err(|e| OidcError::InvalidToken(e.to_string()))
+}
+
+/// Check issuer, audience, expiry and nonce
+fn validate_claims(
+    claims: &Value,
+    config: &OidcConfig,
+    nonce: &str,
+    now: u64,
+) -> Result<(), OidcError> {
+    let issuer = claims
+        .get("iss")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if issuer.trim_end_matches('/') != config.issuer.trim_end_matches('/') {
+        return Err(OidcError::InvalidToken("issuer mismatch".into()));
+    }
+
+    let audience_ok = match claims.get("aud") {
+        Some(Value::String(aud)) => *aud == config.client_id,
+        Some(Value::Array(auds)) => auds.iter().any(|a| a.as_str() == Some(&config.client_id)),
+        _ => false,
+    };
+    if !audience_ok {
+        return Err(OidcError::InvalidToken("audience mismatch".into()));
+    }
+
+    let exp = claims.get("exp").and_then(Value::as_u64).unwrap_or(0);
+    if exp + CLOCK_SKEW_SECS < now {
+        return Err(OidcError::InvalidToken("expired".into()));
+    }
+
+    let token_nonce = claims
+        .get("nonce")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if !constant_time_eq(token_nonce.as_bytes(), nonce.as_bytes()) {
+        return Err(OidcError::InvalidToken("nonce mismatch".into()));
+    }
+    Ok(())
+}
+
+/// PKCE S256 challenge for `verifier` (RFC 7636)
+fn pkce_challenge(verifier: &str) -> String {
+    BASE64URL_NOPAD.encode(&Sha256::digest(verifier.as_bytes()))
+}
+
+/// 256-bit random value, base64url encoded (state, nonce, PKCE verifier)
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    BASE64URL_NOPAD.encode(&bytes)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config() -> OidcConfig {
+        serde_json::from_value(json!({
+            "issuer": "https://id.example.com/",
+            "client_id": "reos",
+            "users": { "boss@partner.org": "alice" },
+            "domain": "example.com",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_pkce_challenge_rfc7636() {
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn test_parse_callback() {
+        let params = parse_callback("/callback?code=abc%2F1&state=xyz").unwrap();
+        assert_eq!(params.into_code("xyz").unwrap(), "abc/1");
+
+        let params = parse_callback("/callback?code=abc&state=other").unwrap();
+        assert_eq!(params.into_code("xyz"), Err(OidcError::StateMismatch));
+
+        let params = parse_callback("/callback?error=access_denied").unwrap();
+        assert!(matches!(params.into_code("xyz"), Err(OidcError::Denied(_))));
+
+        assert!(parse_callback("/favicon.ico").is_none());
+    }
+
+    #[test]
+    fn test_validate_claims() {
+        let config = config();
+        let claims = json!({
+            "iss": "https://id.example.com",
+            "aud": ["other", "reos"],
+            "exp": 1_000,
+            "nonce": "n1",
+            "sub": "123",
+        });
+        assert!(validate_claims(&claims, &config, "n1", 900).is_ok());
+        assert!(validate_claims(&claims, &config, "n2", 900).is_err());
+        assert!(validate_claims(&claims, &config, "n1", 2_000).is_err());
+
+        let mut wrong_aud = claims.clone();
+        wrong_aud["aud"] = json!("someone-else");
+        assert!(validate_claims(&wrong_aud, &config, "n1", 900).is_err());
+    }
+
+    #[test]
+    fn test_decode_claims() {
+        let payload = BASE64URL_NOPAD.encode(br#"{"sub":"123"}"#);
+        let claims = decode_claims(&format!("e30.{payload}.sig")).unwrap();
+        assert_eq!(claims["sub"], "123");
+        assert!(decode_claims("garbage").is_err());
+    }
+
+    #[test]
+    fn test_map_user() {
+        let config = config();
+        assert_eq!(
+            config
+                .map_user(&json!({ "email": "boss@partner.org" }))
+                .unwrap(),
+            "alice"
+        );
+        assert_eq!(
+            config
+                .map_user(&json!({ "email": "bob@example.com" }))
+                .unwrap(),
+            "bob"
+        );
+        assert!(config
+            .map_user(&json!({ "email": "bob@evil-example.com" }))
+            .is_err());
+        assert!(config
+            .map_user(&json!({ "email": "bob@example.com", "email_verified": false }))
+            .is_err());
+    }
+}
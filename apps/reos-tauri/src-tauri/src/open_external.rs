@@ -0,0 +1,313 @@
+//! Audited Opening of Links and Files for ReOS
+//!
+//! `open_external` is the webview's only way to hand something to the OS
+//! (there is no shell plugin): the target is checked here, and every
+//! attempt, allowed or not, goes to the audit log with the session id.
+//!
+//! - URLs need a scheme on the allowlist, no embedded credentials and, if
+//!   hosts are configured, one of those hosts or a subdomain of one
+//! - Files (absolute paths or `file://` URLs) are canonicalized (symlinks
+//!   resolved) and must be under an allowed root with no hidden components
+//!   below it; launchers, scripts and installers are refused, as is (on
+//!   Unix) any file with an executable bit
+//!
+//! Guest sessions may open URLs but not files.
+//!
+//! Opened with the platform's default handler (`xdg-open` and friends on
+//! Linux, `open` on macOS, `ShellExecute` on Windows).
+//!
+//! Configurable via `open-external.json` in the app data dir:
+//! `{ "schemes": ["https", "mailto"], "hosts": ["example.com"], "roots": ["/home/me/Documents"] }`
+
+use serde::Deserialize;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+use url::Url;
+
+const MAX_TARGET_LEN: usize = 2048;
+
+const DEFAULT_SCHEMES: &[&str] = &["https", "http", "mailto"];
+
+/// Extensions the OS would run rather than show (lowercase)
+const EXECUTABLE_EXTENSIONS: &[&str] = &[
+    "app", "appimage", "apk", "bat", "bin", "cmd", "com", "command", "cpl", "deb", "desktop",
+    "dmg", "exe", "hta", "jar", "js", "lnk", "msc", "msi", "pif", "pkg", "ps1", "reg", "rpm",
+    "run", "scr", "sh", "url", "vbs", "wsf",
+];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum OpenError {
+    #[error("Target is longer than {MAX_TARGET_LEN} characters")]
+    TooLong,
+    #[error("Not a URL or absolute path")]
+    Malformed,
+    #[error("Links with scheme {0} are not allowed")]
+    Scheme(String),
+    #[error("Links with credentials are not allowed")]
+    Credentials,
+    #[error("Links to this host are not allowed")]
+    Host,
+    #[error("Path is outside the allowed folders")]
+    OutsideRoots,
+    #[error("Programs and scripts cannot be opened")]
+    Executable,
+    #[error("Files cannot be opened from guest sessions")]
+    Guest,
+    #[error("Cannot open: {0}")]
+    Io(String),
+}
+
+impl From<std::io::Error> for OpenError {
+    fn from(e: std::io::Error) -> Self {
+        OpenError::Io(e.to_string())
+    }
+}
+
+/// A checked target
+#[derive(Debug, PartialEq, Eq)]
+pub enum Target {
+    Url(Url),
+    Path(PathBuf),
+}
+
+impl Target {
+    /// What the audit log records: URLs without query or fragment
+    pub fn describe(&self) -> String {
+        match self {
+            Target::Url(url) => strip_url(url),
+            Target::Path(path) => path.display().to_string(),
+        }
+    }
+}
+
+fn strip_url(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_query(None);
+    url.set_fragment(None);
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    url.to_string()
+}
+
+/// What the audit log records for a refused target
+pub fn describe_raw(target: &str) -> String {
+    match Url::parse(target) {
+        Ok(url) => strip_url(&url),
+        Err(_) => target.chars().take(200).collect(),
+    }
+}
+
+/// What may be opened
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct OpenPolicy {
+    /// Allowed URL schemes, lowercase (`file` is governed by `roots`)
+    pub schemes: Vec<String>,
+    /// Allowed hosts and their subdomains (empty = any)
+    pub hosts: Vec<String>,
+    /// Folders files may be opened from (empty = the user's home directory)
+    pub roots: Vec<PathBuf>,
+}
+
+impl Default for OpenPolicy {
+    fn default() -> Self {
+        Self {
+            schemes: DEFAULT_SCHEMES.iter().map(|s| s.to_string()).collect(),
+            hosts: Vec::new(),
+            roots: Vec::new(),
+        }
+    }
+}
+
+impl OpenPolicy {
+    /// Load the policy from `path` (missing or invalid file = defaults),
+    /// with `home` as the root when none are configured
+    pub fn load(path: Option<&Path>, home: Option<PathBuf>) -> Self {
+        let mut policy: Self = path
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        if policy.roots.is_empty() {
+            policy.roots.extend(home);
+        }
+        // Compare against resolved roots, like the resolved targets
+        policy.roots = policy
+            .roots
+            .iter()
+            .filter_map(|root| root.canonicalize().ok())
+            .collect();
+        for host in &mut policy.hosts {
+            *host = host.to_ascii_lowercase();
+        }
+        policy
+    }
+
+    /// Validate a URL or absolute path
+    pub fn check(&self, target: &str) -> Result<Target, OpenError> {
+        if target.len() > MAX_TARGET_LEN {
+            return Err(OpenError::TooLong);
+        }
+        // Before URL parsing: `C:\...` would parse with scheme `c`
+        if Path::new(target).is_absolute() {
+            return self.check_path(Path::new(target)).map(Target::Path);
+        }
+        let url = Url::parse(target).map_err(|_| OpenError::Malformed)?;
+        if url.scheme() == "file" {
+            let path = url.to_file_path().map_err(|_| OpenError::Malformed)?;
+            return self.check_path(&path).map(Target::Path);
+        }
+        if !self.schemes.iter().any(|s| s == url.scheme()) {
+            return Err(OpenError::Scheme(url.scheme().to_string()));
+        }
+        if !url.username().is_empty() || url.password().is_some() {
+            return Err(OpenError::Credentials);
+        }
+        if let (Some(host), false) = (url.host_str(), self.hosts.is_empty()) {
+            let host = host.to_ascii_lowercase();
+            let allowed = self
+                .hosts
+                .iter()
+                .any(|h| host == *h || host.ends_with(&format!(".{h}")));
+            if !allowed {
+                return Err(OpenError::Host);
+            }
+        }
+        Ok(Target::Url(url))
+    }
+
+    fn check_path(&self, path: &Path) -> Result<PathBuf, OpenError> {
+        let path = path.canonicalize()?;
+        let below_root = self
+            .roots
+            .iter()
+            .find_map(|root| path.strip_prefix(root).ok())
+            .ok_or(OpenError::OutsideRoots)?;
+        let hidden = below_root.components().any(|c| match c {
+            Component::Normal(part) => part.to_string_lossy().starts_with('.'),
+            _ => true,
+        });
+        if hidden {
+            return Err(OpenError::OutsideRoots);
+        }
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        if EXECUTABLE_EXTENSIONS.contains(&extension.as_str()) {
+            return Err(OpenError::Executable);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = path.metadata()?;
+            if metadata.is_file() && metadata.permissions().mode() & 0o111 != 0 {
+                return Err(OpenError::Executable);
+            }
+        }
+        Ok(path)
+    }
+}
+
+/// Open policy for `open_external`
+pub struct OpenExternalState(pub OpenPolicy);
+
+/// Hand a checked target to the OS's default handler
+pub fn open(target: &Target) -> Result<(), OpenError> {
+    match target {
+        Target::Url(url) => open::that_detached(url.as_str())?,
+        Target::Path(path) => open::that_detached(path)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("reos-open-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join(".hidden")).unwrap();
+        std::fs::write(dir.join("report.pdf"), b"%PDF").unwrap();
+        std::fs::write(dir.join("install.desktop"), b"[Desktop Entry]").unwrap();
+        std::fs::write(dir.join(".hidden/key.txt"), b"secret").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_check_urls() {
+        let mut policy = OpenPolicy::load(None, None);
+        assert!(matches!(
+            policy.check("https://example.com/a?b=c"),
+            Ok(Target::Url(_))
+        ));
+        assert!(policy.check("mailto:someone@example.com").is_ok());
+        assert_eq!(
+            policy.check("javascript:alert(1)"),
+            Err(OpenError::Scheme("javascript".into()))
+        );
+        assert_eq!(
+            policy.check("https://user:pw@example.com/"),
+            Err(OpenError::Credentials)
+        );
+        assert_eq!(policy.check("notes/todo.md"), Err(OpenError::Malformed));
+        let long = format!("https://example.com/{}", "a".repeat(MAX_TARGET_LEN));
+        assert_eq!(policy.check(&long), Err(OpenError::TooLong));
+
+        policy.hosts = vec!["example.com".into()];
+        assert!(policy.check("https://docs.example.com/").is_ok());
+        assert_eq!(
+            policy.check("https://example.com.evil.test/"),
+            Err(OpenError::Host)
+        );
+        assert!(policy.check("mailto:someone@example.org").is_ok());
+    }
+
+    #[test]
+    fn test_check_paths() {
+        let root = temp_root("check");
+        let policy = OpenPolicy::load(None, Some(root.clone()));
+        let report = root.join("report.pdf");
+        assert_eq!(
+            policy.check(report.to_str().unwrap()),
+            Ok(Target::Path(report.canonicalize().unwrap()))
+        );
+        let url = Url::from_file_path(&report).unwrap();
+        assert!(matches!(policy.check(url.as_str()), Ok(Target::Path(_))));
+        assert!(policy.check(root.to_str().unwrap()).is_ok());
+        assert_eq!(
+            policy.check(root.join("install.desktop").to_str().unwrap()),
+            Err(OpenError::Executable)
+        );
+        assert_eq!(
+            policy.check(root.join(".hidden/key.txt").to_str().unwrap()),
+            Err(OpenError::OutsideRoots)
+        );
+        assert_eq!(
+            policy.check(root.join(".hidden/../../").to_str().unwrap()),
+            Err(OpenError::OutsideRoots)
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let script = root.join("tool");
+            std::fs::write(&script, b"#!/bin/sh").unwrap();
+            std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+            assert_eq!(
+                policy.check(script.to_str().unwrap()),
+                Err(OpenError::Executable)
+            );
+        }
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_audit_descriptions_drop_query_and_credentials() {
+        let target = Target::Url(Url::parse("https://example.com/p?token=abc#x").unwrap());
+        assert_eq!(target.describe(), "https://example.com/p");
+        assert_eq!(
+            describe_raw("https://u:pw@example.com/?q=1"),
+            "https://example.com/"
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Passwordless Login via the Desktop Session
+//!
+//! Opt-in mode for single-user desktops: if the app runs inside an active,
+//! local, unlocked login session that belongs to the requested user, the
+//! PAM prompt is skipped. The kernel (running under the same uid) is told
+//! which logind session vouched for the user and only asks for the
+//! encryption passphrase.
+//!
+//! Enabled via `os-login.json` in the app data dir: `{ "enabled": true }`
+//!
+//! Platforms:
+//! - Linux: logind (`GetSessionByPID` for this process)
+//! - macOS and Windows: not wired yet, report `Unsupported`
+
+use serde::Deserialize;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum OsSessionError {
+    #[error("desktop session login is disabled")]
+    Disabled,
+    #[error("desktop session login is not supported on this platform")]
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    Unsupported,
+    #[error("not running inside a login session")]
+    NoSession,
+    #[error("login session is not active")]
+    Inactive,
+    #[error("remote sessions must log in with a password")]
+    Remote,
+    #[error("login session is locked")]
+    Locked,
+    #[error("login session belongs to another user")]
+    WrongUser,
+    #[error("session service error: {0}")]
+    Service(String),
+}
+
+/// Deployment policy for desktop session login
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct OsLoginPolicy {
+    pub enabled: bool,
+}
+
+impl OsLoginPolicy {
+    /// Load the policy from `path` (missing or invalid file = disabled)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Desktop session login policy state (read-only after startup)
+pub struct OsLoginState(pub OsLoginPolicy);
+
+/// What the platform reports about the login session this process runs in
+#[derive(Debug)]
+pub struct SessionFacts {
+    /// Platform session id (logind `Id`)
+    pub id: String,
+    pub username: String,
+    pub uid: u32,
+    pub active: bool,
+    pub remote: bool,
+    pub locked: bool,
+}
+
+impl SessionFacts {
+    /// Whether this session vouches for `username` running as `process_uid`
+    fn vouches_for(&self, username: &str, process_uid: u32) -> Result<(), OsSessionError> {
+        if self.username != username || self.uid != process_uid {
+            return Err(OsSessionError::WrongUser);
+        }
+        if self.remote {
+            return Err(OsSessionError::Remote);
+        }
+        if !self.active {
+            return Err(OsSessionError::Inactive);
+        }
+        if self.locked {
+            return Err(OsSessionError::Locked);
+        }
+        Ok(())
+    }
+}
+
+/// Check that this process runs in `username`'s active local session,
+/// returning the platform session id (blocking)
+pub fn verify(username: &str) -> Result<String, OsSessionError> {
+    let (facts, process_uid) = current_session()?;
+    facts.vouches_for(username, process_uid)?;
+    Ok(facts.id)
+}
+
+#[cfg(target_os = "linux")]
+fn current_session() -> Result<(SessionFacts, u32), OsSessionError> {
+    use std::os::unix::fs::MetadataExt;
+    use zbus::blocking::{Connection, Proxy};
+    use zbus::zvariant::OwnedObjectPath;
+
+    const LOGIND: &str = "org.freedesktop.login1";
+    let service = |e: zbus::Error| OsSessionError::Service(e.to_string());
+
+    let conn = Connection::system().map_err(service)?;
+    let manager = Proxy::new(
+        &conn,
+        LOGIND,
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )
+    .map_err(service)?;
+    let path: OwnedObjectPath = manager
+        .call("GetSessionByPID", &std::process::id())
+        .map_err(|_| OsSessionError::NoSession)?;
+    let session =
+        Proxy::new(&conn, LOGIND, path, "org.freedesktop.login1.Session").map_err(service)?;
+
+    let (uid, _user_path): (u32, OwnedObjectPath) =
+        session.get_property("User").map_err(service)?;
+    let facts = SessionFacts {
+        id: session.get_property("Id").map_err(service)?,
+        username: session.get_property("Name").map_err(service)?,
+        uid,
+        active: session.get_property("Active").map_err(service)?,
+        remote: session.get_property("Remote").map_err(service)?,
+        locked: session.get_property("LockedHint").map_err(service)?,
+    };
+    let process_uid = std::fs::metadata("/proc/self")
+        .map_err(|e| OsSessionError::Service(e.to_string()))?
+        .uid();
+    Ok((facts, process_uid))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn current_session() -> Result<(SessionFacts, u32), OsSessionError> {
+    Err(OsSessionError::Unsupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn facts() -> SessionFacts {
+        SessionFacts {
+            id: "2".to_string(),
+            username: "alice".to_string(),
+            uid: 1000,
+            active: true,
+            remote: false,
+            locked: false,
+        }
+    }
+
+    #[test]
+    fn test_active_local_session_vouches() {
+        assert_eq!(facts().vouches_for("alice", 1000), Ok(()));
+        assert_eq!(
+            facts().vouches_for("bob", 1000),
+            Err(OsSessionError::WrongUser)
+        );
+        assert_eq!(
+            facts().vouches_for("alice", 1001),
+            Err(OsSessionError::WrongUser)
+        );
+    }
+
+    #[test]
+    fn test_remote_inactive_or_locked_rejected() {
+        let remote = SessionFacts {
+            remote: true,
+            ..facts()
+        };
+        assert_eq!(
+            remote.vouches_for("alice", 1000),
+            Err(OsSessionError::Remote)
+        );
+        let inactive = SessionFacts {
+            active: false,
+            ..facts()
+        };
+        assert_eq!(
+            inactive.vouches_for("alice", 1000),
+            Err(OsSessionError::Inactive)
+        );
+        let locked = SessionFacts {
+            locked: true,
+            ..facts()
+        };
+        assert_eq!(
+            locked.vouches_for("alice", 1000),
+            Err(OsSessionError::Locked)
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! Kernel Request Parameter Sanitization
+//!
+//! `params` from the webview are checked before anything is written to the
+//! kernel: bounded nesting depth and key count, no `__`-prefixed keys (those
+//! are reserved for values Rust injects, like `__session`), and numbers
+//! normalized so handlers never see values JavaScript couldn't have meant.
+
+use serde_json::{json, Number, Value};
+use thiserror::Error;
+
+/// Deepest allowed nesting of objects/arrays
+const MAX_DEPTH: usize = 32;
+/// Most object keys allowed across the whole payload
+const MAX_KEYS: usize = 10_000;
+/// Largest integer JavaScript represents exactly (`Number.MAX_SAFE_INTEGER`)
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+/// JSON-RPC "invalid params" error code
+const INVALID_PARAMS_CODE: i64 = -32602;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ParamError {
+    #[error("params nested deeper than {MAX_DEPTH} levels")]
+    TooDeep,
+    #[error("params have more than {MAX_KEYS} keys")]
+    TooManyKeys,
+    #[error("params key {0:?} is reserved")]
+    ReservedKey(String),
+}
+
+impl ParamError {
+    /// JSON-RPC error response handed back in place of the kernel's
+    pub fn to_response(&self) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": null,
+            "error": {
+                "code": INVALID_PARAMS_CODE,
+                "message": self.to_string(),
+                "data": { "reason": "invalid_params" },
+            },
+        })
+    }
+}
+
+/// Validate `params` in place, normalizing numbers
+pub fn sanitize(params: &mut Value) -> Result<(), ParamError> {
+    let mut keys = 0;
+    walk(params, 0, &mut keys)
+}
+
+fn walk(value: &mut Value, depth: usize, keys: &mut usize) -> Result<(), ParamError> {
+    match value {
+        Value::Object(map) => {
+            if depth >= MAX_DEPTH {
+                return Err(ParamError::TooDeep);
+            }
+            *keys += map.len();
+            if *keys > MAX_KEYS {
+                return Err(ParamError::TooManyKeys);
+            }
+            if let Some(key) = map.keys().find(|k| k.starts_with("__")) {
+                return Err(ParamError::ReservedKey(key.clone()));
+            }
+            map.values_mut().try_for_each(|v| walk(v, depth + 1, keys))
+        }
+        Value::Array(items) => {
+            if depth >= MAX_DEPTH {
+                return Err(ParamError::TooDeep);
+            }
+            items.iter_mut().try_for_each(|v| walk(v, depth + 1, keys))
+        }
+        Value::Number(n) => {
+            *value = normalize_number(n);
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Non-finite floats become `null`; integers beyond JavaScript's safe range
+/// (which the frontend can't have sent exactly) become floats, so handlers
+/// don't treat them as exact counts or sizes
+fn normalize_number(n: &Number) -> Value {
+    let unsafe_int = n.as_u64().is_some_and(|u| u > MAX_SAFE_INTEGER)
+        || n.as_i64()
+            .is_some_and(|i| i.unsigned_abs() > MAX_SAFE_INTEGER);
+    match n.as_f64() {
+        Some(f) if !f.is_finite() => Value::Null,
+        Some(f) if unsafe_int => Number::from_f64(f).map_or(Value::Null, Value::Number),
+        _ => Value::Number(n.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_params_pass_unchanged() {
+        let mut params = json!({ "name": "x", "n": 3, "f": 1.5, "list": [1, { "a": null }] });
+        let expected = params.clone();
+        assert!(sanitize(&mut params).is_ok());
+        assert_eq!(params, expected);
+    }
+
+    #[test]
+    fn test_reserved_keys_are_rejected() {
+        let mut params = json!({ "nested": { "__session": { "username": "root" } } });
+        assert_eq!(
+            sanitize(&mut params),
+            Err(ParamError::ReservedKey("__session".into()))
+        );
+    }
+
+    #[test]
+    fn test_depth_and_key_limits() {
+        let mut deep = json!(1);
+        for _ in 0..=MAX_DEPTH {
+            deep = json!([deep]);
+        }
+        assert_eq!(sanitize(&mut deep), Err(ParamError::TooDeep));
+
+        let wide: serde_json::Map<String, Value> = (0..=MAX_KEYS)
+            .map(|i| (format!("k{i}"), json!(i)))
+            .collect();
+        assert_eq!(
+            sanitize(&mut Value::Object(wide)),
+            Err(ParamError::TooManyKeys)
+        );
+    }
+
+    #[test]
+    fn test_unsafe_integers_become_floats() {
+        let mut params = json!({ "count": u64::MAX, "neg": i64::MIN, "ok": MAX_SAFE_INTEGER });
+        sanitize(&mut params).unwrap();
+        assert!(params["count"].is_f64());
+        assert!(params["neg"].is_f64());
+        assert_eq!(params["ok"], json!(MAX_SAFE_INTEGER));
+    }
+}
@@ -0,0 +1,255 @@
+//! Password Policy for ReOS
+//!
+//! New passwords are checked in Rust before they reach the kernel, so the
+//! UI gets every problem at once as structured violations instead of a
+//! bare PAM "authentication token manipulation error".
+//!
+//! Configurable via `password-policy.json` in the app data dir:
+//! `{ "min_length": 12, "require_digit": true, "breach_filter": "/path" }`
+//!
+//! Breached passwords are looked up (lowercased) in a bloom filter. The
+//! bundled one covers common passwords; deployments can point
+//! `breach_filter` at a larger one built by `scripts/build_breach_bloom.py`.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Filter built from common breached passwords, shipped with the app
+const BUNDLED_FILTER: &[u8] = include_bytes!("../assets/breached-passwords.bloom");
+/// Magic bytes of the filter file format
+const FILTER_MAGIC: &[u8; 4] = b"RBF1";
+/// Magic + k (u8) + m (u32)
+const FILTER_HEADER_LEN: usize = 9;
+
+/// Why a password was rejected (serialized for the UI as `{ "code": ... }`)
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum PolicyViolation {
+    TooShort { min: usize },
+    TooLong { max: usize },
+    MissingLowercase,
+    MissingUppercase,
+    MissingDigit,
+    MissingSymbol,
+    ContainsUsername,
+    Breached,
+}
+
+impl PolicyViolation {
+    /// Short user-facing description
+    pub fn message(&self) -> String {
+        match self {
+            Self::TooShort { min } => format!("must be at least {min} characters"),
+            Self::TooLong { max } => format!("must be at most {max} characters"),
+            Self::MissingLowercase => "must contain a lowercase letter".into(),
+            Self::MissingUppercase => "must contain an uppercase letter".into(),
+            Self::MissingDigit => "must contain a digit".into(),
+            Self::MissingSymbol => "must contain a symbol".into(),
+            Self::ContainsUsername => "must not contain the username".into(),
+            Self::Breached => "appears in a list of breached passwords".into(),
+        }
+    }
+}
+
+/// Deployment policy for new passwords
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct PasswordPolicy {
+    /// Minimum length in characters
+    pub min_length: usize,
+    /// Maximum length in characters (bounds PAM/KDF work)
+    pub max_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    pub forbid_username: bool,
+    pub check_breached: bool,
+    /// Custom bloom filter replacing the bundled one
+    pub breach_filter: Option<PathBuf>,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 12,
+            max_length: 256,
+            require_lowercase: false,
+            require_uppercase: false,
+            require_digit: false,
+            require_symbol: false,
+            forbid_username: true,
+            check_breached: true,
+            breach_filter: None,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Load the policy from `path` (missing or invalid file = defaults)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Password policy plus the breached-password filter it checks against
+pub struct PasswordPolicyState {
+    policy: PasswordPolicy,
+    breached: Option<BloomFilter>,
+}
+
+impl PasswordPolicyState {
+    pub fn new(policy: PasswordPolicy) -> Self {
+        let breached = if !policy.check_breached {
+            None
+        } else if let Some(path) = &policy.breach_filter {
+            // A configured but unreadable filter falls back to the bundled one
+            std::fs::read(path)
+                .ok()
+                .and_then(BloomFilter::parse)
+                .or_else(|| {
+                    warn!(path = %path.display(), "breach filter unreadable, using bundled");
+                    BloomFilter::parse(BUNDLED_FILTER.to_vec())
+                })
+        } else {
+            BloomFilter::parse(BUNDLED_FILTER.to_vec())
+        };
+        Self { policy, breached }
+    }
+
+    /// Every rule `password` breaks (empty = acceptable)
+    pub fn check(&self, username: &str, password: &str) -> Vec<PolicyViolation> {
+        let policy = &self.policy;
+        let mut violations = Vec::new();
+
+        let length = password.chars().count();
+        if length < policy.min_length {
+            violations.push(PolicyViolation::TooShort {
+                min: policy.min_length,
+            });
+        }
+        if length > policy.max_length {
+            violations.push(PolicyViolation::TooLong {
+                max: policy.max_length,
+            });
+        }
+
+        let has = |pred: fn(&char) -> bool| password.chars().any(|c| pred(&c));
+        if policy.require_lowercase && !has(char::is_ascii_lowercase) {
+            violations.push(PolicyViolation::MissingLowercase);
+        }
+        if policy.require_uppercase && !has(char::is_ascii_uppercase) {
+            violations.push(PolicyViolation::MissingUppercase);
+        }
+        if policy.require_digit && !has(char::is_ascii_digit) {
+            violations.push(PolicyViolation::MissingDigit);
+        }
+        if policy.require_symbol && !has(|c| !c.is_alphanumeric()) {
+            violations.push(PolicyViolation::MissingSymbol);
+        }
+
+        if policy.forbid_username
+            && !username.is_empty()
+            && password.to_lowercase().contains(&username.to_lowercase())
+        {
+            violations.push(PolicyViolation::ContainsUsername);
+        }
+        if self
+            .breached
+            .as_ref()
+            .is_some_and(|filter| filter.contains(&password.to_lowercase()))
+        {
+            violations.push(PolicyViolation::Breached);
+        }
+        violations
+    }
+}
+
+/// Read-only bloom filter in the `RBF1` format (see the build script)
+struct BloomFilter {
+    k: u8,
+    m: u64,
+    bits: Vec<u8>,
+}
+
+impl BloomFilter {
+    fn parse(bytes: Vec<u8>) -> Option<Self> {
+        if bytes.len() < FILTER_HEADER_LEN || &bytes[..4] != FILTER_MAGIC {
+            return None;
+        }
+        let k = bytes[4];
+        let m = u32::from_le_bytes(bytes[5..9].try_into().ok()?) as u64;
+        let bits = bytes[FILTER_HEADER_LEN..].to_vec();
+        if k == 0 || m == 0 || (bits.len() as u64) * 8 < m {
+            return None;
+        }
+        Some(Self { k, m, bits })
+    }
+
+    fn contains(&self, item: &str) -> bool {
+        let digest = Sha256::digest(item.as_bytes());
+        let h1 = u64::from_le_bytes(digest[..8].try_into().expect("8 bytes"));
+        let h2 = u64::from_le_bytes(digest[8..16].try_into().expect("8 bytes")) | 1;
+        (0..self.k as u64).all(|j| {
+            let i = h1.wrapping_add(j.wrapping_mul(h2)) % self.m;
+            self.bits[(i / 8) as usize] & (1 << (i % 8)) != 0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_length_and_classes() {
+        let state = PasswordPolicyState::new(PasswordPolicy {
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: true,
+            check_breached: false,
+            ..PasswordPolicy::default()
+        });
+        assert_eq!(
+            state.check("alice", "short"),
+            vec![
+                PolicyViolation::TooShort { min: 12 },
+                PolicyViolation::MissingUppercase,
+                PolicyViolation::MissingDigit,
+                PolicyViolation::MissingSymbol,
+            ]
+        );
+        assert!(state.check("alice", "Tr0ub4dor&3-horse").is_empty());
+    }
+
+    #[test]
+    fn test_username_is_forbidden() {
+        let state = PasswordPolicyState::new(PasswordPolicy::default());
+        assert!(state
+            .check("alice", "my-ALICE-password-x9")
+            .contains(&PolicyViolation::ContainsUsername));
+    }
+
+    #[test]
+    fn test_bundled_breach_filter() {
+        let state = PasswordPolicyState::new(PasswordPolicy {
+            min_length: 1,
+            ..PasswordPolicy::default()
+        });
+        assert_eq!(
+            state.check("bob", "Password123"),
+            vec![PolicyViolation::Breached]
+        );
+        assert!(state.check("bob", "vivid-orchid-tractor-41").is_empty());
+    }
+
+    #[test]
+    fn test_malformed_filter_is_rejected() {
+        assert!(BloomFilter::parse(b"nope".to_vec()).is_none());
+        assert!(BloomFilter::parse(b"RBF1\x03\x00\x01\x00\x00".to_vec()).is_none());
+    }
+}
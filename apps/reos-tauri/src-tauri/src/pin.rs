@@ -0,0 +1,207 @@
+//! PIN Quick Unlock for ReOS
+//!
+//! After a full PAM login the user may set a short numeric PIN. When their
+//! session idles out it is kept in a locked state (see `auth::QUICK_UNLOCK_GRACE`)
+//! and the PIN can resume it without another round trip to the kernel.
+//!
+//! Security:
+//! - Only an Argon2id verifier (PHC string) is kept, never the PIN itself
+//! - Verifiers live in the OS keyring, cached in memory for this process
+//! - Too many wrong PINs discard the verifier, forcing a full PAM login
+//!
+//! Hashing, verification and keyring access all block; commands run them
+//! off the async runtime, holding only `PinState` (never the session store).
+
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, SaltString};
+use argon2::{Argon2, PasswordHasher, PasswordVerifier};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use thiserror::Error;
+
+/// Keyring service name for PIN verifiers
+const KEYRING_SERVICE: &str = "dev.reos.app.pin";
+/// Minimum PIN length (digits)
+const PIN_MIN_LEN: usize = 4;
+/// Maximum PIN length (digits)
+const PIN_MAX_LEN: usize = 12;
+/// Wrong PINs allowed before the verifier is discarded
+const PIN_MAX_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Error)]
+pub enum PinError {
+    #[error("PIN must be {PIN_MIN_LEN}-{PIN_MAX_LEN} digits")]
+    InvalidFormat,
+    #[error("no PIN set")]
+    NotSet,
+    #[error("incorrect PIN")]
+    Incorrect,
+    #[error("too many incorrect PINs, please log in again")]
+    TooManyAttempts,
+    #[error("failed to hash PIN: {0}")]
+    Hash(String),
+    #[error("failed to save PIN in the keyring: {0}")]
+    Keyring(String),
+}
+
+/// PIN verifiers and failed-attempt counters
+pub struct PinManager {
+    verifiers: HashMap<String, String>,
+    attempts: HashMap<String, u32>,
+    use_keyring: bool,
+}
+
+impl PinManager {
+    pub fn new() -> Self {
+        Self {
+            verifiers: HashMap::new(),
+            attempts: HashMap::new(),
+            use_keyring: true,
+        }
+    }
+
+    /// Whether `username` has a PIN configured
+    pub fn has_pin(&mut self, username: &str) -> bool {
+        self.verifier(username).is_some()
+    }
+
+    /// Set (or replace) the PIN for `username`
+    pub fn set_pin(&mut self, username: &str, pin: &str) -> Result<(), PinError> {
+        if pin.len() < PIN_MIN_LEN
+            || pin.len() > PIN_MAX_LEN
+            || !pin.bytes().all(|b| b.is_ascii_digit())
+        {
+            return Err(PinError::InvalidFormat);
+        }
+
+        let salt = SaltString::generate(&mut OsRng);
+        let verifier = Argon2::default()
+            .hash_password(pin.as_bytes(), &salt)
+            .map_err(|e| PinError::Hash(e.to_string()))?
+            .to_string();
+
+        if self.use_keyring {
+            keyring::Entry::new(KEYRING_SERVICE, username)
+                .and_then(|entry| entry.set_password(&verifier))
+                .map_err(|e| PinError::Keyring(e.to_string()))?;
+        }
+        self.verifiers.insert(username.to_string(), verifier);
+        self.attempts.remove(username);
+        Ok(())
+    }
+
+    /// Remove the PIN for `username`
+    pub fn clear_pin(&mut self, username: &str) {
+        self.verifiers.remove(username);
+        self.attempts.remove(username);
+        if self.use_keyring {
+            if let Ok(entry) = keyring::Entry::new(KEYRING_SERVICE, username) {
+                let _ = entry.delete_credential();
+            }
+        }
+    }
+
+    /// Check `pin` for `username`, discarding the PIN after too many failures
+    pub fn verify(&mut self, username: &str, pin: &str) -> Result<(), PinError> {
+        let verifier = self.verifier(username).ok_or(PinError::NotSet)?;
+        let parsed = PasswordHash::new(&verifier).map_err(|e| PinError::Hash(e.to_string()))?;
+
+        if Argon2::default()
+            .verify_password(pin.as_bytes(), &parsed)
+            .is_ok()
+        {
+            self.attempts.remove(username);
+            return Ok(());
+        }
+
+        let attempts = self.attempts.entry(username.to_string()).or_insert(0);
+        *attempts += 1;
+        if *attempts >= PIN_MAX_ATTEMPTS {
+            self.clear_pin(username);
+            return Err(PinError::TooManyAttempts);
+        }
+        Err(PinError::Incorrect)
+    }
+
+    fn verifier(&mut self, username: &str) -> Option<String> {
+        if let Some(v) = self.verifiers.get(username) {
+            return Some(v.clone());
+        }
+        if !self.use_keyring {
+            return None;
+        }
+        let stored = keyring::Entry::new(KEYRING_SERVICE, username)
+            .and_then(|entry| entry.get_password())
+            .ok()?;
+        self.verifiers.insert(username.to_string(), stored.clone());
+        Some(stored)
+    }
+}
+
+impl Default for PinManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Thread-safe PIN state
+pub struct PinState(pub Mutex<PinManager>);
+
+impl PinState {
+    pub fn new() -> Self {
+        Self(Mutex::new(PinManager::new()))
+    }
+}
+
+impl Default for PinState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager() -> PinManager {
+        PinManager {
+            use_keyring: false,
+            ..PinManager::new()
+        }
+    }
+
+    #[test]
+    fn test_pin_format() {
+        let mut pins = manager();
+        assert!(matches!(
+            pins.set_pin("alice", "12"),
+            Err(PinError::InvalidFormat)
+        ));
+        assert!(matches!(
+            pins.set_pin("alice", "12ab"),
+            Err(PinError::InvalidFormat)
+        ));
+        assert!(!pins.has_pin("alice"));
+    }
+
+    #[test]
+    fn test_pin_verify_and_lockout() {
+        let mut pins = manager();
+        pins.set_pin("alice", "4821").unwrap();
+        assert!(pins.verify("alice", "4821").is_ok());
+
+        for _ in 0..PIN_MAX_ATTEMPTS - 1 {
+            assert!(matches!(
+                pins.verify("alice", "0000"),
+                Err(PinError::Incorrect)
+            ));
+        }
+        assert!(matches!(
+            pins.verify("alice", "0000"),
+            Err(PinError::TooManyAttempts)
+        ));
+        assert!(matches!(
+            pins.verify("alice", "4821"),
+            Err(PinError::NotSet)
+        ));
+    }
+}
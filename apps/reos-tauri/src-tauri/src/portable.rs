@@ -0,0 +1,132 @@
+//! Portable Mode
+//!
+//! For running from a USB stick or a network share without touching the
+//! home directory. With `--portable`, or a `reos.portable` file next to the
+//! executable, everything lives in `reos-data/` beside the binary:
+//!
+//! - `config/`: `config.toml`, window state
+//! - `data/`: deployment config, logs, persisted sessions, kernel data and
+//!   the first-run virtualenv
+//! - `cache/`: downloaded updates
+//! - `webview/`: the webview's storage and cache (Linux and Windows; macOS
+//!   keeps its own)
+//!
+//! Profiles nest inside these dirs as usual. A portable copy is its own
+//! instance (lock file and keyring entry keyed by its location), so it runs
+//! next to an installed one; the keyring itself stays on the machine, so
+//! persisted sessions don't follow the stick to another computer.
+
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use thiserror::Error;
+
+/// Marker file next to the executable that turns portable mode on
+pub const MARKER_FILE: &str = "reos.portable";
+
+/// Directory next to the executable holding everything
+pub const PORTABLE_DIR: &str = "reos-data";
+
+/// Subdirectories of `PORTABLE_DIR`
+pub const CONFIG: &str = "config";
+pub const DATA: &str = "data";
+pub const CACHE: &str = "cache";
+const WEBVIEW: &str = "webview";
+
+#[derive(Debug, Error)]
+pub enum PortableError {
+    #[error("cannot locate the executable: {0}")]
+    Executable(std::io::Error),
+    #[error("portable data dir {path} is not writable: {source}")]
+    NotWritable {
+        path: String,
+        source: std::io::Error,
+    },
+}
+
+/// `PORTABLE_DIR` when running portable, set once at startup
+static ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Portable dir for an executable at `exe`, if the flag or marker asks
+/// for one
+pub fn detect(exe: &Path, flag: bool) -> Option<PathBuf> {
+    let dir = exe.parent()?;
+    (flag || dir.join(MARKER_FILE).is_file()).then(|| dir.join(PORTABLE_DIR))
+}
+
+/// Turn portable mode on if `--portable` was given or the marker exists;
+/// call early in `main`, before any thread starts (it sets the webview's
+/// storage variables)
+pub fn init(flag: bool) -> Result<(), PortableError> {
+    let exe = std::env::current_exe().map_err(PortableError::Executable)?;
+    let root = detect(&exe, flag);
+    if let Some(root) = &root {
+        for sub in [CONFIG, DATA, CACHE, WEBVIEW] {
+            let dir = root.join(sub);
+            std::fs::create_dir_all(&dir).map_err(|source| PortableError::NotWritable {
+                path: dir.display().to_string(),
+                source,
+            })?;
+        }
+        set_webview_dirs(&root.join(WEBVIEW));
+    }
+    let _ = ROOT.set(root);
+    Ok(())
+}
+
+fn set_webview_dirs(dir: &Path) {
+    if cfg!(target_os = "linux") {
+        // WebKitGTK keeps its storage under the XDG base dirs
+        std::env::set_var("XDG_DATA_HOME", dir.join("data"));
+        std::env::set_var("XDG_CACHE_HOME", dir.join("cache"));
+    } else if cfg!(windows) {
+        std::env::set_var("WEBVIEW2_USER_DATA_FOLDER", dir);
+    }
+}
+
+/// `PORTABLE_DIR` in portable mode
+pub fn root() -> Option<&'static Path> {
+    ROOT.get().and_then(Option::as_deref)
+}
+
+/// A subdirectory (`CONFIG`, `DATA`, `CACHE`) in portable mode
+pub fn dir(sub: &str) -> Option<PathBuf> {
+    root().map(|root| root.join(sub))
+}
+
+/// Suffix telling this portable copy's instance ids apart from other
+/// copies and the installed app (empty when not portable)
+pub fn id_suffix() -> String {
+    root().map(location_id).unwrap_or_default()
+}
+
+fn location_id(root: &Path) -> String {
+    let digest = Sha256::digest(root.to_string_lossy().as_bytes());
+    format!("~{}", hex::encode(&digest[..6]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flag_or_marker_enables_portable() {
+        let dir = std::env::temp_dir().join(format!("reos-portable-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let exe = dir.join("reos");
+        assert_eq!(detect(&exe, false), None);
+        assert_eq!(detect(&exe, true), Some(dir.join(PORTABLE_DIR)));
+        std::fs::write(dir.join(MARKER_FILE), "").unwrap();
+        assert_eq!(detect(&exe, false), Some(dir.join(PORTABLE_DIR)));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_location_id_differs_per_copy() {
+        let a = location_id(Path::new("/media/usb/reos-data"));
+        assert_eq!(a, location_id(Path::new("/media/usb/reos-data")));
+        assert_ne!(a, location_id(Path::new("/mnt/share/reos-data")));
+        assert_eq!(a.len(), 13);
+    }
+}
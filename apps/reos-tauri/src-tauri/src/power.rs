@@ -0,0 +1,345 @@
+//! Power Events for ReOS
+//!
+//! Suspend, resume and battery changes go to every window
+//! (`power-changed`) and to the kernel (`power/event`), which pauses
+//! background jobs while the machine sleeps and flushes its caches when the
+//! battery runs low. Suspending also locks every session.
+//!
+//! Platforms:
+//! - Linux: logind's `PrepareForSleep` over the system bus, and UPower's
+//!   `OnBattery` plus the display device's `Percentage` and `WarningLevel`
+//! - macOS: NSWorkspace's will-sleep and did-wake notifications (battery
+//!   changes aren't reported)
+//! - Windows: `WM_POWERBROADCAST` to a hidden window, with
+//!   `GetSystemPowerStatus` for the battery
+
+use serde::Serialize;
+use std::sync::Mutex;
+
+/// Event carrying a `PowerEvent`
+pub const POWER_EVENT: &str = "power-changed";
+
+/// Battery charge treated as low when UPower reports no warning level
+/// (missing or `Unknown`)
+const LOW_PERCENT: u8 = 10;
+
+/// UPower `WarningLevel`s from `Low` (3) up to `Action` (5)
+const LOW_WARNING_LEVELS: std::ops::RangeInclusive<u32> = 3..=5;
+
+/// `SYSTEM_POWER_STATUS` battery flags
+const FLAG_CRITICAL: u8 = 4;
+const FLAG_NO_BATTERY: u8 = 128;
+const FLAG_UNKNOWN: u8 = 255;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Battery {
+    pub on_battery: bool,
+    /// Charge of the system battery (`None` without one)
+    pub percent: Option<u8>,
+    pub low: bool,
+}
+
+impl Battery {
+    /// Battery state from UPower's properties
+    pub fn from_upower(
+        on_battery: bool,
+        percentage: Option<f64>,
+        warning_level: Option<u32>,
+    ) -> Self {
+        let percent = percentage
+            .filter(|p| (0.0..=100.0).contains(p))
+            .map(|p| p.round() as u8);
+        let low = match warning_level {
+            Some(level) if level > 0 => LOW_WARNING_LEVELS.contains(&level),
+            _ => on_battery && percent.is_some_and(|p| p <= LOW_PERCENT),
+        };
+        Self {
+            on_battery,
+            percent,
+            low,
+        }
+    }
+
+    /// Battery state from Windows' `SYSTEM_POWER_STATUS`
+    ///
+    /// Windows' own "low" flag starts at 33%, so only its critical flag or
+    /// `LOW_PERCENT` count as low.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub fn from_power_status(ac_line_status: u8, battery_flag: u8, life_percent: u8) -> Self {
+        let present = battery_flag != FLAG_UNKNOWN && battery_flag & FLAG_NO_BATTERY == 0;
+        let percent = Some(life_percent).filter(|p| present && *p <= 100);
+        let on_battery = ac_line_status == 0;
+        let low = on_battery
+            && (present && battery_flag & FLAG_CRITICAL != 0
+                || percent.is_some_and(|p| p <= LOW_PERCENT));
+        Self {
+            on_battery,
+            percent,
+            low,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PowerEvent {
+    Suspend,
+    Resume,
+    Battery(Battery),
+}
+
+/// Call `on_event` on suspend, resume and battery changes (the current
+/// battery state is reported right away)
+///
+/// Best-effort: if the platform service is unavailable nothing is watched.
+/// On macOS this must be called on the main thread, which also runs
+/// `on_event`; elsewhere it runs on background threads.
+#[cfg(target_os = "linux")]
+pub fn watch(on_event: impl Fn(PowerEvent) + Send + Sync + 'static) {
+    let on_event = std::sync::Arc::new(on_event);
+    if let Err(e) = linux::watch_sleep(on_event.clone()) {
+        tracing::warn!(error = %e, "suspend watcher unavailable");
+    }
+    if let Err(e) = linux::watch_battery(on_event) {
+        tracing::warn!(error = %e, "battery watcher unavailable");
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn watch(on_event: impl Fn(PowerEvent) + Send + Sync + 'static) {
+    macos::watch(std::sync::Arc::new(on_event));
+}
+
+#[cfg(target_os = "windows")]
+pub fn watch(on_event: impl Fn(PowerEvent) + Send + Sync + 'static) {
+    if let Err(e) = windows::watch(on_event) {
+        tracing::warn!(error = %e, "power watcher unavailable");
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn watch(_on_event: impl Fn(PowerEvent) + Send + Sync + 'static) {}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::{Battery, PowerEvent};
+    use std::sync::{Arc, Mutex};
+    use zbus::blocking::{Connection, Proxy};
+
+    use crate::locks::Recover;
+
+    type Callback = Arc<dyn Fn(PowerEvent) + Send + Sync>;
+
+    const UPOWER: &str = "org.freedesktop.UPower";
+
+    pub fn watch_sleep(on_event: Callback) -> zbus::Result<()> {
+        let conn = Connection::system()?;
+        let manager = Proxy::new(
+            &conn,
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1",
+            "org.freedesktop.login1.Manager",
+        )?;
+        // PrepareForSleep(true) fires just before suspend/hibernate,
+        // PrepareForSleep(false) after waking up
+        let sleep = manager.receive_signal("PrepareForSleep")?;
+        std::thread::spawn(move || {
+            for msg in sleep {
+                match msg.body().deserialize::<bool>() {
+                    Ok(true) => on_event(PowerEvent::Suspend),
+                    Ok(false) => on_event(PowerEvent::Resume),
+                    Err(_) => {}
+                }
+            }
+        });
+        Ok(())
+    }
+
+    pub fn watch_battery(on_event: Callback) -> zbus::Result<()> {
+        let conn = Connection::system()?;
+        let upower = Proxy::new(&conn, UPOWER, "/org/freedesktop/UPower", UPOWER)?;
+        let device = Proxy::new(
+            &conn,
+            UPOWER,
+            "/org/freedesktop/UPower/devices/DisplayDevice",
+            "org.freedesktop.UPower.Device",
+        )?;
+        let upower = Arc::new(upower);
+        let device = Arc::new(device);
+
+        // Reported only when it changes (percent in whole steps)
+        let last = Arc::new(Mutex::new(None));
+        let report = {
+            let (upower, device) = (upower.clone(), device.clone());
+            Arc::new(move || {
+                // Desktops have a display device too, just not a present one
+                let present = device.get_property("IsPresent").unwrap_or(false);
+                let battery = Battery::from_upower(
+                    upower.get_property("OnBattery").unwrap_or(false),
+                    device.get_property("Percentage").ok().filter(|_| present),
+                    device.get_property("WarningLevel").ok(),
+                );
+                let mut last = last.lock_or_recover();
+                if *last != Some(battery) {
+                    *last = Some(battery);
+                    on_event(PowerEvent::Battery(battery));
+                }
+            })
+        };
+        report();
+
+        let changes = upower.receive_property_changed::<bool>("OnBattery");
+        let callback = report.clone();
+        std::thread::spawn(move || changes.for_each(|_| callback()));
+        let changes = device.receive_property_changed::<f64>("Percentage");
+        let callback = report.clone();
+        std::thread::spawn(move || changes.for_each(|_| callback()));
+        let changes = device.receive_property_changed::<u32>("WarningLevel");
+        std::thread::spawn(move || changes.for_each(|_| report()));
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::PowerEvent;
+    use block2::RcBlock;
+    use objc2_app_kit::{
+        NSWorkspace, NSWorkspaceDidWakeNotification, NSWorkspaceWillSleepNotification,
+    };
+    use objc2_foundation::{NSNotification, NSNotificationName};
+    use std::ptr::NonNull;
+    use std::sync::Arc;
+
+    type Callback = Arc<dyn Fn(PowerEvent) + Send + Sync>;
+
+    pub fn watch(on_event: Callback) {
+        let center = NSWorkspace::sharedWorkspace().notificationCenter();
+        let observe = |name: &NSNotificationName, event: PowerEvent| {
+            let on_event = on_event.clone();
+            let block = RcBlock::new(move |_: NonNull<NSNotification>| on_event(event));
+            // No queue: the block runs on the posting (main) thread
+            let observer = unsafe {
+                center.addObserverForName_object_queue_usingBlock(Some(name), None, None, &block)
+            };
+            // Observed for the life of the app
+            std::mem::forget(observer);
+        };
+        observe(
+            unsafe { NSWorkspaceWillSleepNotification },
+            PowerEvent::Suspend,
+        );
+        observe(
+            unsafe { NSWorkspaceDidWakeNotification },
+            PowerEvent::Resume,
+        );
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod windows {
+    use super::{Battery, PowerEvent};
+    use std::io;
+    use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        PBT_APMPOWERSTATUSCHANGE, PBT_APMRESUMEAUTOMATIC, PBT_APMSUSPEND, WM_POWERBROADCAST,
+    };
+
+    use crate::message_window;
+
+    fn battery() -> Option<Battery> {
+        let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+        if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+            return None;
+        }
+        Some(Battery::from_power_status(
+            status.ACLineStatus,
+            status.BatteryFlag,
+            status.BatteryLifePercent,
+        ))
+    }
+
+    pub fn watch(on_event: impl Fn(PowerEvent) + Send + Sync + 'static) -> io::Result<()> {
+        // Reported only when it changes (Windows repeats status broadcasts)
+        let last = battery();
+        if let Some(battery) = last {
+            on_event(PowerEvent::Battery(battery));
+        }
+        let last = std::cell::Cell::new(last);
+        message_window::spawn(
+            "ReOSPowerWatcher",
+            |_| Ok(()),
+            move |msg, wparam, _| {
+                if msg != WM_POWERBROADCAST {
+                    return;
+                }
+                match wparam as u32 {
+                    PBT_APMSUSPEND => on_event(PowerEvent::Suspend),
+                    // Sent on every resume; PBT_APMRESUMESUSPEND follows
+                    // only when a user woke the machine
+                    PBT_APMRESUMEAUTOMATIC => on_event(PowerEvent::Resume),
+                    PBT_APMPOWERSTATUSCHANGE => {
+                        if let Some(battery) = battery().filter(|b| last.get() != Some(*b)) {
+                            last.set(Some(battery));
+                            on_event(PowerEvent::Battery(battery));
+                        }
+                    }
+                    _ => {}
+                }
+            },
+        )
+    }
+}
+
+/// Last battery state reported
+#[derive(Default)]
+pub struct PowerState(pub Mutex<Option<Battery>>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_battery_from_upower() {
+        let battery = Battery::from_upower(true, Some(54.6), Some(1));
+        assert_eq!(battery.percent, Some(55));
+        assert!(!battery.low);
+        // UPower's warning level wins over the percentage...
+        assert!(Battery::from_upower(true, Some(20.0), Some(3)).low);
+        assert!(!Battery::from_upower(true, Some(5.0), Some(1)).low);
+        // ...and the percentage decides without one
+        assert!(Battery::from_upower(true, Some(8.0), None).low);
+        assert!(!Battery::from_upower(false, Some(8.0), None).low);
+        assert_eq!(Battery::from_upower(false, Some(-1.0), None).percent, None);
+    }
+
+    #[test]
+    fn test_battery_from_power_status() {
+        let battery = Battery::from_power_status(0, 0, 54);
+        assert_eq!(battery.percent, Some(54));
+        assert!(battery.on_battery && !battery.low);
+        // Windows' "low" flag (2) alone isn't low, critical (4) is
+        assert!(!Battery::from_power_status(0, 2, 30).low);
+        assert!(Battery::from_power_status(0, 4, 4).low);
+        assert!(Battery::from_power_status(0, 0, 8).low);
+        assert!(!Battery::from_power_status(1, 4, 4).low);
+        // Desktops: no system battery, percent unknown
+        let desktop = Battery::from_power_status(1, 128, 255);
+        assert_eq!(desktop.percent, None);
+        assert!(!desktop.on_battery && !desktop.low);
+    }
+
+    #[test]
+    fn test_power_event_payload() {
+        assert_eq!(
+            serde_json::to_value(PowerEvent::Suspend).unwrap(),
+            json!({ "kind": "suspend" })
+        );
+        let battery = Battery::from_upower(true, Some(42.0), Some(1));
+        assert_eq!(
+            serde_json::to_value(PowerEvent::Battery(battery)).unwrap(),
+            json!({ "kind": "battery", "on_battery": true, "percent": 42, "low": false })
+        );
+    }
+}
@@ -0,0 +1,887 @@
+//! Native Printing for ReOS
+//!
+//! `print_document` takes a document handle (`conversation:<id>`,
+//! `archive:<id>` or `me`), maps it to the kernel method that reads it, and
+//! runs that method after the same checks as `kernel_request`. The kernel
+//! turns the result into a title and blocks (`print/render`); Rust lays
+//! them out into pages with a header (title, page n of m) and drives the
+//! platform printing, so nothing is printed from the webview and documents
+//! the webview never loaded print the same way.
+//!
+//! Platforms (`CAPABILITIES` tells the UI which apply):
+//! - Linux: the GTK print dialog (or straight to the default printer with
+//!   `dialog: false`), drawn with cairo on the main thread
+//! - macOS: sent to the default printer with `lp` (no dialog)
+//! - Windows: the common print dialog (or the default printer), drawn with
+//!   GDI on the main thread
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+/// Most pages a single document may print
+const MAX_PAGES: usize = 1000;
+const MAX_COPIES: u32 = 99;
+const MAX_ID_LEN: usize = 128;
+/// Spaces per tab stop
+const TAB_WIDTH: usize = 4;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PrintError {
+    #[error("Unknown document handle")]
+    UnknownHandle,
+    #[error("Copies must be between 1 and {MAX_COPIES}")]
+    InvalidCopies,
+    #[error("Document is longer than {MAX_PAGES} pages")]
+    TooLong,
+    #[error("Printing failed: {0}")]
+    Failed(String),
+    #[error("Printing is not supported on this platform yet")]
+    #[cfg_attr(
+        any(target_os = "linux", target_os = "macos", target_os = "windows"),
+        allow(dead_code)
+    )]
+    Unsupported,
+}
+
+/// What `print` can do on this platform
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrintCapabilities {
+    /// Printing works at all
+    pub supported: bool,
+    /// `dialog: true` shows a print dialog; without one documents go
+    /// straight to the default printer
+    pub dialog: bool,
+}
+
+pub const CAPABILITIES: PrintCapabilities = PrintCapabilities {
+    supported: cfg!(any(
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "windows"
+    )),
+    dialog: cfg!(any(target_os = "linux", target_os = "windows")),
+};
+
+/// Document kinds: handle prefix, read method and the parameter its id fills
+const SOURCES: &[(&str, &str, Option<&str>)] = &[
+    (
+        "conversation",
+        "conversation/get_messages",
+        Some("conversation_id"),
+    ),
+    ("archive", "archive/get", Some("archive_id")),
+    ("me", "play/me/read", None),
+];
+
+/// The kernel method and params that read the document behind `handle`
+pub fn resolve(handle: &str) -> Result<(&'static str, Value), PrintError> {
+    let (kind, id) = match handle.split_once(':') {
+        Some((kind, id)) => (kind, Some(id)),
+        None => (handle, None),
+    };
+    let &(_, method, param) = SOURCES
+        .iter()
+        .find(|(prefix, _, _)| *prefix == kind)
+        .ok_or(PrintError::UnknownHandle)?;
+    match (param, id) {
+        (None, None) => Ok((method, json!({}))),
+        (Some(param), Some(id))
+            if !id.is_empty()
+                && id.len() <= MAX_ID_LEN
+                && id
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_') =>
+        {
+            Ok((method, json!({ param: id })))
+        }
+        _ => Err(PrintError::UnknownHandle),
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Paper {
+    #[default]
+    A4,
+    Letter,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct PrintOptions {
+    pub paper: Paper,
+    pub landscape: bool,
+    pub copies: u32,
+    /// Title and page numbers at the top of each page
+    pub header: bool,
+    /// Show the print dialog (otherwise print to the default printer)
+    pub dialog: bool,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            paper: Paper::A4,
+            landscape: false,
+            copies: 1,
+            header: true,
+            dialog: true,
+        }
+    }
+}
+
+impl PrintOptions {
+    pub fn validate(&self) -> Result<(), PrintError> {
+        if (1..=MAX_COPIES).contains(&self.copies) {
+            Ok(())
+        } else {
+            Err(PrintError::InvalidCopies)
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockKind {
+    Heading,
+    Text,
+    /// Printed as-is, without rewrapping at spaces
+    Code,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub kind: BlockKind,
+    pub text: String,
+}
+
+/// `print/render` result
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Document {
+    pub title: String,
+    pub blocks: Vec<Block>,
+}
+
+/// One printed line
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    pub text: String,
+    pub heading: bool,
+}
+
+/// Break `line` into pieces of at most `columns` characters, at spaces
+/// unless `hard`
+fn wrap(line: &str, columns: usize, hard: bool, out: &mut Vec<String>) {
+    let line = line.replace('\t', &" ".repeat(TAB_WIDTH));
+    let chars: Vec<char> = line.trim_end().chars().collect();
+    if chars.is_empty() {
+        out.push(String::new());
+        return;
+    }
+    let mut start = 0;
+    while start < chars.len() {
+        let mut end = (start + columns).min(chars.len());
+        if end < chars.len() && !hard {
+            if let Some(space) = chars[start..end].iter().rposition(|c| *c == ' ') {
+                if space > 0 {
+                    end = start + space;
+                }
+            }
+        }
+        out.push(chars[start..end].iter().collect());
+        start = end;
+        if !hard {
+            while chars.get(start) == Some(&' ') {
+                start += 1;
+            }
+        }
+    }
+}
+
+/// Lay `document` out into pages of `rows` lines of `columns` characters
+pub fn paginate(
+    document: &Document,
+    columns: usize,
+    rows: usize,
+) -> Result<Vec<Vec<Line>>, PrintError> {
+    let (columns, rows) = (columns.max(1), rows.max(2));
+    let mut lines = Vec::new();
+    for (i, block) in document.blocks.iter().enumerate() {
+        if i > 0 {
+            lines.push(Line {
+                text: String::new(),
+                heading: false,
+            });
+        }
+        let mut wrapped = Vec::new();
+        for line in block.text.lines() {
+            wrap(line, columns, block.kind == BlockKind::Code, &mut wrapped);
+        }
+        let heading = block.kind == BlockKind::Heading;
+        lines.extend(wrapped.into_iter().map(|text| Line { text, heading }));
+    }
+
+    let mut pages: Vec<Vec<Line>> = vec![Vec::new()];
+    let mut lines = lines.into_iter().peekable();
+    while let Some(line) = lines.next() {
+        let used = pages.last().map_or(0, Vec::len);
+        // A heading on the last line would be cut off from what it heads
+        let orphaned =
+            line.heading && used + 1 == rows && lines.peek().is_some_and(|next| !next.heading);
+        if used == rows || orphaned {
+            if pages.len() == MAX_PAGES {
+                return Err(PrintError::TooLong);
+            }
+            pages.push(Vec::new());
+        }
+        let page = pages.last_mut().expect("at least one page");
+        // Blank lines don't start a page
+        if page.is_empty() && line.text.is_empty() {
+            continue;
+        }
+        page.push(line);
+    }
+    Ok(pages)
+}
+
+/// Page header text: the title, and the page number at the right edge
+fn header(title: &str, page: usize, total: usize, columns: usize) -> (String, String) {
+    let number = format!("{page} / {total}");
+    let room = columns.saturating_sub(number.chars().count() + 2);
+    let title: String = title
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(room)
+        .collect();
+    (title, number)
+}
+
+/// Pages as plain text with form feeds, for text spoolers
+#[cfg(any(target_os = "macos", test))]
+fn as_text(title: &str, pages: &[Vec<Line>], columns: usize, with_header: bool) -> String {
+    let mut text = String::new();
+    for (i, page) in pages.iter().enumerate() {
+        if i > 0 {
+            text.push('\u{c}');
+        }
+        if with_header {
+            let (title, number) = header(title, i + 1, pages.len(), columns);
+            let gap = columns.saturating_sub(title.chars().count() + number.chars().count());
+            text.push_str(&format!("{title}{}{number}\n\n", " ".repeat(gap)));
+        }
+        for line in page {
+            text.push_str(&line.text);
+            text.push('\n');
+        }
+    }
+    text
+}
+
+#[cfg(target_os = "linux")]
+mod backend {
+    use super::{header, paginate, Document, Line, Paper, PrintError, PrintOptions};
+    use gtk::cairo::{Context, FontSlant, FontWeight};
+    use gtk::prelude::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::mpsc;
+    use tauri::{Manager, Runtime, Window};
+
+    const FONT: &str = "Monospace";
+    const FONT_SIZE: f64 = 10.0;
+    const LINE_SPACING: f64 = 1.3;
+    /// Lines taken by the header (title plus a gap)
+    const HEADER_ROWS: usize = 2;
+
+    /// Pages as laid out for the chosen paper
+    #[derive(Default)]
+    struct Layout {
+        pages: Vec<Vec<Line>>,
+        columns: usize,
+        line_height: f64,
+    }
+
+    fn set_font(cr: &Context, bold: bool) {
+        let weight = if bold {
+            FontWeight::Bold
+        } else {
+            FontWeight::Normal
+        };
+        cr.select_font_face(FONT, FontSlant::Normal, weight);
+        cr.set_font_size(FONT_SIZE);
+    }
+
+    fn show(cr: &Context, x: f64, y: f64, text: &str, bold: bool) {
+        set_font(cr, bold);
+        cr.move_to(x, y);
+        let _ = cr.show_text(text);
+    }
+
+    fn run(
+        window: &gtk::ApplicationWindow,
+        document: Document,
+        options: &PrintOptions,
+    ) -> Result<bool, PrintError> {
+        let operation = gtk::PrintOperation::new();
+        operation.set_job_name(&document.title);
+        operation.set_unit(gtk::Unit::Points);
+        let setup = gtk::PageSetup::new();
+        let paper = match options.paper {
+            Paper::A4 => gtk::PAPER_NAME_A4,
+            Paper::Letter => gtk::PAPER_NAME_LETTER,
+        };
+        setup.set_paper_size_and_default_margins(&gtk::PaperSize::new(Some(paper)));
+        if options.landscape {
+            setup.set_orientation(gtk::PageOrientation::Landscape);
+        }
+        operation.set_default_page_setup(Some(&setup));
+        let settings = gtk::PrintSettings::new();
+        settings.set_n_copies(options.copies as i32);
+        operation.set_print_settings(Some(&settings));
+
+        // Laid out once the paper size (possibly changed in the dialog) is known
+        let layout: Rc<RefCell<Layout>> = Rc::default();
+        let error: Rc<RefCell<Option<PrintError>>> = Rc::default();
+        let with_header = options.header;
+        {
+            let (layout, error, document) = (layout.clone(), error.clone(), document.clone());
+            operation.connect_begin_print(move |operation, context| {
+                let Some(cr) = context.cairo_context() else {
+                    return;
+                };
+                set_font(&cr, true);
+                let advance = cr.text_extents("M").map(|e| e.x_advance()).unwrap_or(6.0);
+                let line_height = FONT_SIZE * LINE_SPACING;
+                let columns = (context.width() / advance).floor() as usize;
+                let mut rows = (context.height() / line_height).floor() as usize;
+                if with_header {
+                    rows = rows.saturating_sub(HEADER_ROWS);
+                }
+                match paginate(&document, columns, rows) {
+                    Ok(pages) => {
+                        operation.set_n_pages(pages.len() as i32);
+                        *layout.borrow_mut() = Layout {
+                            pages,
+                            columns,
+                            line_height,
+                        };
+                    }
+                    Err(e) => {
+                        *error.borrow_mut() = Some(e);
+                        operation.cancel();
+                    }
+                }
+            });
+        }
+        {
+            let layout = layout.clone();
+            let title = document.title.clone();
+            operation.connect_draw_page(move |_, context, page_nr| {
+                let Some(cr) = context.cairo_context() else {
+                    return;
+                };
+                let layout = layout.borrow();
+                let Some(page) = layout.pages.get(page_nr as usize) else {
+                    return;
+                };
+                cr.set_source_rgb(0.0, 0.0, 0.0);
+                let mut y = FONT_SIZE;
+                if with_header {
+                    let (title, number) = header(
+                        &title,
+                        page_nr as usize + 1,
+                        layout.pages.len(),
+                        layout.columns,
+                    );
+                    show(&cr, 0.0, y, &title, true);
+                    set_font(&cr, false);
+                    let width = cr
+                        .text_extents(&number)
+                        .map(|e| e.x_advance())
+                        .unwrap_or(0.0);
+                    show(&cr, context.width() - width, y, &number, false);
+                    y += layout.line_height * HEADER_ROWS as f64;
+                }
+                for line in page {
+                    show(&cr, 0.0, y, &line.text, line.heading);
+                    y += layout.line_height;
+                }
+            });
+        }
+
+        let action = if options.dialog {
+            gtk::PrintOperationAction::PrintDialog
+        } else {
+            gtk::PrintOperationAction::Print
+        };
+        let result = operation
+            .run(action, Some(window))
+            .map_err(|e| PrintError::Failed(e.to_string()))?;
+        if let Some(e) = error.borrow_mut().take() {
+            return Err(e);
+        }
+        match result {
+            gtk::PrintOperationResult::Error => {
+                Err(PrintError::Failed("the print job failed".to_string()))
+            }
+            gtk::PrintOperationResult::Cancel => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    /// Print `document` from `window` (false if the dialog was cancelled);
+    /// blocks until the dialog closes, so call it off the main thread
+    pub fn print<R: Runtime>(
+        window: &Window<R>,
+        document: Document,
+        options: PrintOptions,
+    ) -> Result<bool, PrintError> {
+        let (tx, rx) = mpsc::channel();
+        let window = window.clone();
+        let app = window.app_handle().clone();
+        app.run_on_main_thread(move || {
+            let result = window
+                .gtk_window()
+                .map_err(|e| PrintError::Failed(e.to_string()))
+                .and_then(|parent| run(&parent, document, &options));
+            let _ = tx.send(result);
+        })
+        .map_err(|e| PrintError::Failed(e.to_string()))?;
+        rx.recv().map_err(|e| PrintError::Failed(e.to_string()))?
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod backend {
+    use super::{as_text, paginate, Document, Paper, PrintError, PrintOptions};
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+    use tauri::{Runtime, Window};
+
+    /// Characters and lines of CUPS' default text layout (12 pt Courier)
+    fn page_size(options: &PrintOptions) -> (usize, usize) {
+        let (columns, rows) = match options.paper {
+            Paper::A4 => (80, 66),
+            Paper::Letter => (82, 62),
+        };
+        if options.landscape {
+            (rows * 3 / 2, columns * 2 / 3)
+        } else {
+            (columns, rows)
+        }
+    }
+
+    /// Send `document` to the default printer with `lp`
+    pub fn print<R: Runtime>(
+        _window: &Window<R>,
+        document: Document,
+        options: PrintOptions,
+    ) -> Result<bool, PrintError> {
+        let (columns, rows) = page_size(&options);
+        let header_rows = if options.header { 2 } else { 0 };
+        let pages = paginate(&document, columns, rows - header_rows)?;
+        let text = as_text(&document.title, &pages, columns, options.header);
+        let media = match options.paper {
+            Paper::A4 => "media=A4",
+            Paper::Letter => "media=Letter",
+        };
+        let mut command = Command::new("/usr/bin/lp");
+        command
+            .args(["-t", &document.title, "-n", &options.copies.to_string()])
+            .args(["-o", media]);
+        if options.landscape {
+            command.args(["-o", "landscape"]);
+        }
+        let failed = |e: std::io::Error| PrintError::Failed(e.to_string());
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(failed)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes()).map_err(failed)?;
+        }
+        let output = child.wait_with_output().map_err(failed)?;
+        if !output.status.success() {
+            let message = String::from_utf8_lossy(&output.stderr);
+            return Err(PrintError::Failed(message.trim().to_string()));
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod backend {
+    use super::{header, paginate, Document, Paper, PrintError, PrintOptions};
+    use std::sync::mpsc;
+    use tauri::{Manager, Runtime, Window};
+    use windows_sys::Win32::Foundation::{GlobalFree, HGLOBAL, HWND, SIZE};
+    use windows_sys::Win32::Graphics::Gdi::{
+        CreateDCW, CreateFontW, DeleteDC, DeleteObject, GetDeviceCaps, GetTextExtentPoint32W,
+        GetTextMetricsW, SelectObject, TextOutW, CLIP_DEFAULT_PRECIS, DEFAULT_CHARSET,
+        DEFAULT_QUALITY, DEVMODEW, DMORIENT_LANDSCAPE, DMORIENT_PORTRAIT, DMPAPER_A4,
+        DMPAPER_LETTER, DM_COPIES, DM_ORIENTATION, DM_PAPERSIZE, FF_MODERN, FIXED_PITCH, FW_BOLD,
+        FW_NORMAL, HDC, HFONT, HORZRES, LOGPIXELSX, LOGPIXELSY, OUT_DEFAULT_PRECIS, TEXTMETRICW,
+        VERTRES,
+    };
+    use windows_sys::Win32::Storage::Xps::{
+        AbortDoc, EndDoc, EndPage, StartDocW, StartPage, DOCINFOW,
+    };
+    use windows_sys::Win32::System::Memory::{GlobalLock, GlobalUnlock};
+    use windows_sys::Win32::UI::Controls::Dialogs::{
+        CommDlgExtendedError, PrintDlgW, DEVNAMES, PD_NOPAGENUMS, PD_NOSELECTION, PD_RETURNDC,
+        PD_RETURNDEFAULT, PD_USEDEVMODECOPIESANDCOLLATE, PRINTDLGW,
+    };
+
+    const FONT: &str = "Courier New";
+    /// Points
+    const FONT_SIZE: i32 = 10;
+    const LINE_SPACING: f64 = 1.3;
+    /// Lines taken by the header (title plus a gap)
+    const HEADER_ROWS: usize = 2;
+
+    fn wide(text: &str) -> Vec<u16> {
+        text.encode_utf16().chain([0]).collect()
+    }
+
+    fn failed(what: &str) -> PrintError {
+        PrintError::Failed(format!("{what} failed"))
+    }
+
+    /// Preset the default printer's settings with `options`
+    unsafe fn apply_options(devmode: HGLOBAL, options: &PrintOptions) {
+        let mode = GlobalLock(devmode) as *mut DEVMODEW;
+        if mode.is_null() {
+            return;
+        }
+        let fields = &mut (*mode).Anonymous1.Anonymous1;
+        let paper = match options.paper {
+            Paper::A4 => DMPAPER_A4,
+            Paper::Letter => DMPAPER_LETTER,
+        };
+        let orientation = if options.landscape {
+            DMORIENT_LANDSCAPE
+        } else {
+            DMORIENT_PORTRAIT
+        };
+        fields.dmPaperSize = paper as i16;
+        fields.dmOrientation = orientation as i16;
+        fields.dmCopies = options.copies as i16;
+        (*mode).dmFields |= DM_PAPERSIZE | DM_ORIENTATION | DM_COPIES;
+        GlobalUnlock(devmode);
+    }
+
+    /// Device context for the printer and settings in `names` / `devmode`
+    unsafe fn create_dc(names: HGLOBAL, devmode: HGLOBAL) -> HDC {
+        let base = GlobalLock(names) as *const u16;
+        if base.is_null() {
+            return std::ptr::null_mut();
+        }
+        let offsets = &*(base as *const DEVNAMES);
+        let dc = CreateDCW(
+            base.add(offsets.wDriverOffset as usize),
+            base.add(offsets.wDeviceOffset as usize),
+            std::ptr::null(),
+            GlobalLock(devmode) as *const DEVMODEW,
+        );
+        GlobalUnlock(devmode);
+        GlobalUnlock(names);
+        dc
+    }
+
+    /// Printer picked in the dialog, or the default one (`None` if the
+    /// dialog was cancelled)
+    unsafe fn printer_dc(owner: HWND, options: &PrintOptions) -> Result<Option<HDC>, PrintError> {
+        let mut dialog: PRINTDLGW = std::mem::zeroed();
+        dialog.lStructSize = std::mem::size_of::<PRINTDLGW>() as u32;
+        dialog.hwndOwner = owner;
+        dialog.Flags = PD_RETURNDEFAULT;
+        if PrintDlgW(&mut dialog) == 0 {
+            return Err(failed("finding the default printer"));
+        }
+        apply_options(dialog.hDevMode, options);
+
+        let dc = if options.dialog {
+            dialog.Flags =
+                PD_RETURNDC | PD_USEDEVMODECOPIESANDCOLLATE | PD_NOPAGENUMS | PD_NOSELECTION;
+            if PrintDlgW(&mut dialog) == 0 {
+                // No extended error: the dialog was cancelled
+                let cancelled = CommDlgExtendedError() == 0;
+                GlobalFree(dialog.hDevMode);
+                GlobalFree(dialog.hDevNames);
+                return if cancelled {
+                    Ok(None)
+                } else {
+                    Err(failed("the print dialog"))
+                };
+            }
+            dialog.hDC
+        } else {
+            create_dc(dialog.hDevNames, dialog.hDevMode)
+        };
+        GlobalFree(dialog.hDevMode);
+        GlobalFree(dialog.hDevNames);
+        if dc.is_null() {
+            return Err(failed("opening the printer"));
+        }
+        Ok(Some(dc))
+    }
+
+    unsafe fn font(dc: HDC, bold: bool) -> HFONT {
+        let height = -(FONT_SIZE * GetDeviceCaps(dc, LOGPIXELSY as i32) / 72);
+        let weight = if bold { FW_BOLD } else { FW_NORMAL };
+        let face = wide(FONT);
+        CreateFontW(
+            height,
+            0,
+            0,
+            0,
+            weight as i32,
+            0,
+            0,
+            0,
+            DEFAULT_CHARSET as u32,
+            OUT_DEFAULT_PRECIS as u32,
+            CLIP_DEFAULT_PRECIS as u32,
+            DEFAULT_QUALITY as u32,
+            (FIXED_PITCH | FF_MODERN) as u32,
+            face.as_ptr(),
+        )
+    }
+
+    unsafe fn show(dc: HDC, x: i32, y: i32, text: &str) {
+        let text: Vec<u16> = text.encode_utf16().collect();
+        TextOutW(dc, x, y, text.as_ptr(), text.len() as i32);
+    }
+
+    /// Lay `document` out for the printer behind `dc` and print it
+    unsafe fn print_pages(
+        dc: HDC,
+        document: &Document,
+        with_header: bool,
+    ) -> Result<(), PrintError> {
+        let (regular, bold) = (font(dc, false), font(dc, true));
+        let previous = SelectObject(dc, bold);
+        let mut metrics: TEXTMETRICW = std::mem::zeroed();
+        GetTextMetricsW(dc, &mut metrics);
+        // A quarter inch inside the printable area
+        let (margin_x, margin_y) = (
+            GetDeviceCaps(dc, LOGPIXELSX as i32) / 4,
+            GetDeviceCaps(dc, LOGPIXELSY as i32) / 4,
+        );
+        let width = GetDeviceCaps(dc, HORZRES as i32) - 2 * margin_x;
+        let height = GetDeviceCaps(dc, VERTRES as i32) - 2 * margin_y;
+        let line_height = ((metrics.tmHeight as f64 * LINE_SPACING).round() as i32).max(1);
+        let columns = (width / metrics.tmAveCharWidth.max(1)).max(1) as usize;
+        let mut rows = (height / line_height).max(1) as usize;
+        if with_header {
+            rows = rows.saturating_sub(HEADER_ROWS);
+        }
+
+        let result = paginate(document, columns, rows).and_then(|pages| {
+            let name = wide(&document.title);
+            let mut info: DOCINFOW = std::mem::zeroed();
+            info.cbSize = std::mem::size_of::<DOCINFOW>() as i32;
+            info.lpszDocName = name.as_ptr();
+            if StartDocW(dc, &info) <= 0 {
+                return Err(failed("starting the print job"));
+            }
+            for (i, page) in pages.iter().enumerate() {
+                if StartPage(dc) <= 0 {
+                    AbortDoc(dc);
+                    return Err(failed("starting a page"));
+                }
+                let mut y = margin_y;
+                if with_header {
+                    let (title, number) = header(&document.title, i + 1, pages.len(), columns);
+                    SelectObject(dc, bold);
+                    show(dc, margin_x, y, &title);
+                    SelectObject(dc, regular);
+                    let number_wide: Vec<u16> = number.encode_utf16().collect();
+                    let mut size = SIZE { cx: 0, cy: 0 };
+                    GetTextExtentPoint32W(
+                        dc,
+                        number_wide.as_ptr(),
+                        number_wide.len() as i32,
+                        &mut size,
+                    );
+                    show(dc, margin_x + width - size.cx, y, &number);
+                    y += line_height * HEADER_ROWS as i32;
+                }
+                for line in page {
+                    SelectObject(dc, if line.heading { bold } else { regular });
+                    show(dc, margin_x, y, &line.text);
+                    y += line_height;
+                }
+                if EndPage(dc) <= 0 {
+                    AbortDoc(dc);
+                    return Err(failed("finishing a page"));
+                }
+            }
+            if EndDoc(dc) <= 0 {
+                return Err(failed("finishing the print job"));
+            }
+            Ok(())
+        });
+        SelectObject(dc, previous);
+        DeleteObject(regular);
+        DeleteObject(bold);
+        result
+    }
+
+    /// Print `document` from `window` (false if the dialog was cancelled);
+    /// blocks until the job is spooled, so call it off the main thread
+    pub fn print<R: Runtime>(
+        window: &Window<R>,
+        document: Document,
+        options: PrintOptions,
+    ) -> Result<bool, PrintError> {
+        // Raw handles aren't Send; the dialog is modal to the window, so it
+        // runs on the thread that owns it
+        let owner = window
+            .hwnd()
+            .map_err(|e| PrintError::Failed(e.to_string()))?
+            .0 as isize;
+        let (tx, rx) = mpsc::channel();
+        let app = window.app_handle().clone();
+        app.run_on_main_thread(move || {
+            // SAFETY: `owner` is the live window; the printer DC is created,
+            // used and deleted on this thread only.
+            let result = unsafe {
+                printer_dc(owner as HWND, &options).and_then(|dc| {
+                    let Some(dc) = dc else {
+                        return Ok(false);
+                    };
+                    let printed = print_pages(dc, &document, options.header);
+                    DeleteDC(dc);
+                    printed.map(|()| true)
+                })
+            };
+            let _ = tx.send(result);
+        })
+        .map_err(|e| PrintError::Failed(e.to_string()))?;
+        rx.recv().map_err(|e| PrintError::Failed(e.to_string()))?
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+mod backend {
+    use super::{Document, PrintError, PrintOptions};
+    use tauri::{Runtime, Window};
+
+    pub fn print<R: Runtime>(
+        _window: &Window<R>,
+        _document: Document,
+        _options: PrintOptions,
+    ) -> Result<bool, PrintError> {
+        Err(PrintError::Unsupported)
+    }
+}
+
+pub use backend::print;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(kind: BlockKind, text: &str) -> Block {
+        Block {
+            kind,
+            text: text.to_string(),
+        }
+    }
+
+    fn texts(page: &[Line]) -> Vec<&str> {
+        page.iter().map(|l| l.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_resolve_handles() {
+        assert_eq!(
+            resolve("conversation:abc-1"),
+            Ok((
+                "conversation/get_messages",
+                json!({ "conversation_id": "abc-1" })
+            ))
+        );
+        assert_eq!(resolve("me"), Ok(("play/me/read", json!({}))));
+        assert_eq!(resolve("me:1"), Err(PrintError::UnknownHandle));
+        assert_eq!(resolve("conversation"), Err(PrintError::UnknownHandle));
+        assert_eq!(resolve("conversation:../x"), Err(PrintError::UnknownHandle));
+        assert_eq!(resolve("vault:x"), Err(PrintError::UnknownHandle));
+    }
+
+    #[test]
+    fn test_options_defaults_and_copies() {
+        let options: PrintOptions = serde_json::from_value(json!({ "paper": "letter" })).unwrap();
+        assert_eq!(options.paper, Paper::Letter);
+        assert!(options.header && options.dialog);
+        assert_eq!(options.validate(), Ok(()));
+        let none = PrintOptions {
+            copies: 0,
+            ..PrintOptions::default()
+        };
+        assert_eq!(none.validate(), Err(PrintError::InvalidCopies));
+    }
+
+    #[test]
+    fn test_wraps_text_at_spaces_and_code_hard() {
+        let document = Document {
+            title: "T".to_string(),
+            blocks: vec![
+                block(BlockKind::Text, "the quick brown fox"),
+                block(BlockKind::Code, "abcdefghij"),
+            ],
+        };
+        let pages = paginate(&document, 8, 20).unwrap();
+        assert_eq!(
+            texts(&pages[0]),
+            ["the", "quick", "brown", "fox", "", "abcdefgh", "ij"]
+        );
+    }
+
+    #[test]
+    fn test_headings_move_to_the_next_page() {
+        let document = Document {
+            title: "T".to_string(),
+            blocks: vec![
+                block(BlockKind::Text, "one\ntwo"),
+                block(BlockKind::Heading, "Next"),
+                block(BlockKind::Text, "three"),
+            ],
+        };
+        // "one", "two", "" fill the page; the heading would be its last line
+        let pages = paginate(&document, 20, 4).unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(texts(&pages[0]), ["one", "two", ""]);
+        assert_eq!(texts(&pages[1]), ["Next", "", "three"]);
+        assert!(pages[1][0].heading);
+    }
+
+    #[test]
+    fn test_page_cap() {
+        let document = Document {
+            title: "T".to_string(),
+            blocks: vec![block(BlockKind::Code, &"x\n".repeat(MAX_PAGES * 2 + 1))],
+        };
+        assert_eq!(paginate(&document, 10, 2), Err(PrintError::TooLong));
+    }
+
+    #[test]
+    fn test_text_pages_have_headers_and_form_feeds() {
+        let pages = vec![
+            vec![Line {
+                text: "a".to_string(),
+                heading: false,
+            }],
+            vec![Line {
+                text: "b".to_string(),
+                heading: false,
+            }],
+        ];
+        let text = as_text("Notes", &pages, 12, true);
+        assert_eq!(text, "Notes  1 / 2\n\na\n\u{c}Notes  2 / 2\n\nb\n");
+    }
+}
@@ -0,0 +1,153 @@
+//! Named Profiles
+//!
+//! `--profile <name>` (see `cli.rs`) runs an isolated copy of the app next
+//! to the usual one, e.g. a "work" and a "personal" profile, or a throwaway
+//! one for testing. A profile gets its own:
+//!
+//! - data dir (`<app data dir>/profiles/<name>`): deployment config, logs,
+//!   audit trails, persisted sessions, crash reports
+//! - config dir (`<app config dir>/profiles/<name>`): `config.toml`,
+//!   window state
+//! - instance lock, so each profile runs once and its own kernel
+//! - kernel data (`REOS_DATA_DIR` = `<profile data dir>/kernel`)
+//! - session vault key in the keyring
+//!
+//! Without the flag the default profile uses the plain dirs, as before
+//! profiles existed. Names are 1-32 ASCII letters, digits, `-` or `_`.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tauri::path::PathResolver;
+use tauri::Runtime;
+use thiserror::Error;
+
+use crate::portable;
+
+/// Name reported for the default profile
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Subdirectory holding the named profiles
+const PROFILES_DIR: &str = "profiles";
+
+const MAX_NAME_LEN: usize = 32;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ProfileError {
+    #[error("Invalid profile name {0:?} (use letters, digits, - or _)")]
+    InvalidName(String),
+}
+
+/// Named profile of this process (`None` = default), set once at startup
+static PROFILE: OnceLock<Option<String>> = OnceLock::new();
+
+/// Check a `--profile` value (`None` for the default profile)
+pub fn parse_name(name: String) -> Result<Option<String>, ProfileError> {
+    if !valid_name(&name) {
+        return Err(ProfileError::InvalidName(name));
+    }
+    // The default profile by name is the default profile
+    Ok((name != DEFAULT_PROFILE).then_some(name))
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Select the profile (first call wins)
+pub fn init(name: Option<String>) {
+    let _ = PROFILE.set(name);
+}
+
+/// The named profile, `None` for the default one
+pub fn current() -> Option<&'static str> {
+    PROFILE.get().and_then(Option::as_deref)
+}
+
+/// Profile name for display and reports
+pub fn name() -> &'static str {
+    current().unwrap_or(DEFAULT_PROFILE)
+}
+
+/// `base` for the default profile, `base/profiles/<name>` otherwise
+pub fn scope_dir(base: PathBuf) -> PathBuf {
+    match current() {
+        Some(name) => base.join(PROFILES_DIR).join(name),
+        None => base,
+    }
+}
+
+/// `id` for the default profile, `id@<name>` otherwise (lock files,
+/// keyring accounts); portable copies add their location
+pub fn scope_id(id: &str) -> String {
+    let id = format!("{id}{}", portable::id_suffix());
+    match current() {
+        Some(name) => format!("{id}@{name}"),
+        None => id,
+    }
+}
+
+/// The app's data, config and cache dirs for the current profile (beside
+/// the executable in portable mode)
+pub trait ProfilePaths {
+    fn profile_data_dir(&self) -> tauri::Result<PathBuf>;
+    fn profile_config_dir(&self) -> tauri::Result<PathBuf>;
+    fn profile_cache_dir(&self) -> tauri::Result<PathBuf>;
+}
+
+impl<R: Runtime> ProfilePaths for PathResolver<R> {
+    fn profile_data_dir(&self) -> tauri::Result<PathBuf> {
+        portable::dir(portable::DATA)
+            .map_or_else(|| self.app_data_dir(), Ok)
+            .map(scope_dir)
+    }
+
+    fn profile_config_dir(&self) -> tauri::Result<PathBuf> {
+        portable::dir(portable::CONFIG)
+            .map_or_else(|| self.app_config_dir(), Ok)
+            .map(scope_dir)
+    }
+
+    fn profile_cache_dir(&self) -> tauri::Result<PathBuf> {
+        portable::dir(portable::CACHE)
+            .map_or_else(|| self.app_cache_dir(), Ok)
+            .map(scope_dir)
+    }
+}
+
+/// The data and config dirs `ProfilePaths` gives the app `identifier`,
+/// without a Tauri app (`--headless-check`, `reos-cli`); `None` without a
+/// home directory
+pub fn headless_dirs(identifier: &str) -> Option<(PathBuf, PathBuf)> {
+    let app_dir = |portable_dir: &str, platform_dir: Option<PathBuf>| {
+        portable::dir(portable_dir)
+            .or_else(|| platform_dir.map(|dir| dir.join(identifier)))
+            .map(scope_dir)
+    };
+    Some((
+        app_dir(portable::DATA, dirs::data_dir())?,
+        app_dir(portable::CONFIG, dirs::config_dir())?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_names_cannot_escape_dir() {
+        assert_eq!(parse_name("work".into()), Ok(Some("work".into())));
+        assert_eq!(parse_name("test_1".into()), Ok(Some("test_1".into())));
+        assert_eq!(parse_name(DEFAULT_PROFILE.into()), Ok(None));
+        let long = "n".repeat(MAX_NAME_LEN + 1);
+        for name in ["../etc", "a/b", "", "x y", long.as_str()] {
+            assert_eq!(
+                parse_name(name.into()),
+                Err(ProfileError::InvalidName(name.into()))
+            );
+        }
+    }
+}
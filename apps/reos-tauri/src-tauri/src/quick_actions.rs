@@ -0,0 +1,141 @@
+//! Kernel-Declared Tray Quick Actions for ReOS
+//!
+//! The kernel lists a few quick actions (`id`, `title`, `method`) in its
+//! `initialize` result; they show up as tray menu items. Clicking one asks
+//! the main window to run it (`tray-quick-action`), since only windows hold
+//! session tokens: the window calls `tray_action_run` with its session and
+//! the action id, and the method (taken from the manifest here, never from
+//! the window) goes through the same checks as `kernel_request`. The
+//! outcome is shown as a notification.
+//!
+//! Manifest entries with an invalid id or title, or beyond
+//! `MAX_QUICK_ACTIONS`, are ignored.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Mutex;
+
+/// Event asking the main window to run a quick action (payload: its id)
+pub const TRAY_ACTION_EVENT: &str = "tray-quick-action";
+
+/// Tray menu id prefix of quick action items
+pub const MENU_PREFIX: &str = "tray-action:";
+
+pub const MAX_QUICK_ACTIONS: usize = 8;
+const MAX_ID_LEN: usize = 32;
+const MAX_TITLE_CHARS: usize = 48;
+const MAX_SUMMARY_CHARS: usize = 200;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct QuickAction {
+    pub id: String,
+    pub title: String,
+    pub method: String,
+}
+
+/// Kernel-declared action ids: lowercase letters, digits and `-`
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_ID_LEN
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+/// Kernel-declared menu titles: short, non-blank, no control characters
+pub fn is_valid_title(title: &str) -> bool {
+    let title = title.trim();
+    !title.is_empty()
+        && title.chars().count() <= MAX_TITLE_CHARS
+        && !title.chars().any(char::is_control)
+}
+
+impl QuickAction {
+    fn is_valid(&self) -> bool {
+        is_valid_id(&self.id) && is_valid_title(&self.title) && !self.method.is_empty()
+    }
+
+    pub fn menu_id(&self) -> String {
+        format!("{MENU_PREFIX}{}", self.id)
+    }
+}
+
+/// Quick actions from an `initialize` result
+pub fn from_manifest(result: &Value) -> Vec<QuickAction> {
+    let Some(entries) = result.get("quick_actions").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    let mut actions: Vec<QuickAction> = Vec::new();
+    for entry in entries {
+        let Ok(action) = serde_json::from_value::<QuickAction>(entry.clone()) else {
+            continue;
+        };
+        if action.is_valid() && !actions.iter().any(|a| a.id == action.id) {
+            actions.push(action);
+        }
+        if actions.len() == MAX_QUICK_ACTIONS {
+            break;
+        }
+    }
+    actions
+}
+
+/// Notification text for a quick action's result
+pub fn summarize(result: &Value) -> String {
+    let text = |key: &str| result.get(key).and_then(Value::as_str);
+    let summary = if result.get("success").and_then(Value::as_bool) == Some(false) {
+        format!("Failed: {}", text("error").unwrap_or("unknown error"))
+    } else {
+        text("summary")
+            .or_else(|| text("message"))
+            .or_else(|| result.as_str())
+            .unwrap_or("Done")
+            .to_string()
+    };
+    summary.chars().take(MAX_SUMMARY_CHARS).collect()
+}
+
+/// Quick actions of the running kernel
+#[derive(Default)]
+pub struct QuickActionsState(pub Mutex<Vec<QuickAction>>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_manifest_filters_invalid_entries() {
+        let result = json!({ "quick_actions": [
+            { "id": "system-status", "title": "System status", "method": "system/live_state" },
+            { "id": "Bad Id", "title": "x", "method": "ping" },
+            { "id": "no-title", "title": "  ", "method": "ping" },
+            { "id": "system-status", "title": "Duplicate", "method": "ping" },
+            { "id": "missing-method", "title": "Missing" },
+        ] });
+        let actions = from_manifest(&result);
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].menu_id(), "tray-action:system-status");
+        assert!(from_manifest(&json!({ "protocolVersion": "jsonrpc-2.0" })).is_empty());
+    }
+
+    #[test]
+    fn test_from_manifest_caps_count() {
+        let entries: Vec<Value> = (0..20)
+            .map(|i| json!({ "id": format!("a{i}"), "title": "A", "method": "ping" }))
+            .collect();
+        let actions = from_manifest(&json!({ "quick_actions": entries }));
+        assert_eq!(actions.len(), MAX_QUICK_ACTIONS);
+    }
+
+    #[test]
+    fn test_summarize() {
+        assert_eq!(summarize(&json!({ "summary": "CPU 3%" })), "CPU 3%");
+        assert_eq!(
+            summarize(&json!({ "success": false, "error": "No terminal" })),
+            "Failed: No terminal"
+        );
+        assert_eq!(summarize(&json!({ "success": true })), "Done");
+        assert_eq!(summarize(&json!("x".repeat(500))).len(), MAX_SUMMARY_CHARS);
+    }
+}
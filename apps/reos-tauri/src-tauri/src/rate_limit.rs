@@ -0,0 +1,469 @@
+//! Rate limiting for Tauri commands
+//!
+//! Token buckets guard commands that are expensive or security-sensitive
+//! on the kernel side (e.g. `auth_login`, which triggers a PAM/Polkit
+//! round trip). This is independent of any per-account lockout the kernel
+//! applies: it protects against a compromised or buggy frontend hammering
+//! the command layer, regardless of which username is being tried.
+//!
+//! `kernel_request` is limited per session and method class; classes are
+//! configurable via `rate-limits.json` in the app data dir:
+//! `{ "classes": [{ "name": "llm", "methods": ["chat/*"], "burst": 5, "per_sec": 1.0 }] }`
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::methods::method_matches;
+
+/// Burst size for login attempts from a single window
+const LOGIN_WINDOW_BURST: u32 = 5;
+/// Sustained login attempts per second from a single window
+const LOGIN_WINDOW_REFILL_PER_SEC: f64 = 0.5;
+/// Burst size for login attempts across all windows
+const LOGIN_GLOBAL_BURST: u32 = 20;
+/// Sustained login attempts per second across all windows
+const LOGIN_GLOBAL_REFILL_PER_SEC: f64 = 2.0;
+/// Burst size for `frontend_log` entries from a single window
+const FRONTEND_LOG_BURST: u32 = 50;
+/// Sustained `frontend_log` entries per second from a single window
+const FRONTEND_LOG_REFILL_PER_SEC: f64 = 10.0;
+
+/// Classic token bucket: holds up to `capacity` tokens, refilled continuously
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: u32, refill_per_sec: f64) -> Self {
+        Self {
+            capacity: f64::from(capacity),
+            tokens: f64::from(capacity),
+            refill_per_sec,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Whether a token is available at `now` (without consuming it)
+    fn has_token(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= 1.0
+    }
+
+    /// Time until the next token becomes available
+    fn retry_after(&self) -> Duration {
+        if self.tokens >= 1.0 || self.refill_per_sec <= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64((1.0 - self.tokens) / self.refill_per_sec)
+    }
+
+    /// Consume one token (callers check `has_token` first)
+    fn take(&mut self) {
+        self.tokens -= 1.0;
+    }
+}
+
+/// Rate limiter for `auth_login`: one global bucket plus one per window
+pub struct LoginRateLimiter {
+    global: TokenBucket,
+    per_window: HashMap<String, TokenBucket>,
+}
+
+impl LoginRateLimiter {
+    pub fn new() -> Self {
+        Self {
+            global: TokenBucket::new(LOGIN_GLOBAL_BURST, LOGIN_GLOBAL_REFILL_PER_SEC),
+            per_window: HashMap::new(),
+        }
+    }
+
+    /// Record a login attempt from `window`.
+    ///
+    /// Returns `Err(retry_after)` if either the window's bucket or the global
+    /// bucket is empty. Tokens are only consumed when both allow the attempt.
+    pub fn check(&mut self, window: &str) -> Result<(), Duration> {
+        self.check_at(window, Instant::now())
+    }
+
+    fn check_at(&mut self, window: &str, now: Instant) -> Result<(), Duration> {
+        let window_bucket = self
+            .per_window
+            .entry(window.to_string())
+            .or_insert_with(|| TokenBucket::new(LOGIN_WINDOW_BURST, LOGIN_WINDOW_REFILL_PER_SEC));
+
+        let window_ok = window_bucket.has_token(now);
+        let global_ok = self.global.has_token(now);
+        if !window_ok || !global_ok {
+            return Err(window_bucket.retry_after().max(self.global.retry_after()));
+        }
+
+        window_bucket.take();
+        self.global.take();
+        Ok(())
+    }
+}
+
+impl Default for LoginRateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Rate limiter for `frontend_log`, one bucket per window, so a console
+/// error loop can't flood the log files
+pub struct FrontendLogLimiter {
+    /// Bucket and entries dropped since the last accepted one
+    per_window: HashMap<String, (TokenBucket, u64)>,
+}
+
+impl FrontendLogLimiter {
+    pub fn new() -> Self {
+        Self {
+            per_window: HashMap::new(),
+        }
+    }
+
+    /// `Some(dropped)` if an entry from `window` may be logged (with the
+    /// number dropped before it), `None` if it is dropped
+    pub fn check(&mut self, window: &str) -> Option<u64> {
+        self.check_at(window, Instant::now())
+    }
+
+    fn check_at(&mut self, window: &str, now: Instant) -> Option<u64> {
+        let (bucket, dropped) = self
+            .per_window
+            .entry(window.to_string())
+            .or_insert_with(|| {
+                (
+                    TokenBucket::new(FRONTEND_LOG_BURST, FRONTEND_LOG_REFILL_PER_SEC),
+                    0,
+                )
+            });
+        if !bucket.has_token(now) {
+            *dropped += 1;
+            return None;
+        }
+        bucket.take();
+        Some(std::mem::take(dropped))
+    }
+}
+
+impl Default for FrontendLogLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// JSON-RPC error code for rate-limited kernel requests (same as the kernel's)
+pub const RATE_LIMITED_CODE: i64 = -32429;
+
+/// Kernel methods sharing one per-session budget
+#[derive(Deserialize, Clone)]
+pub struct MethodClass {
+    pub name: String,
+    /// Method names or `prefix*` patterns (first matching class wins)
+    pub methods: Vec<String>,
+    pub burst: u32,
+    pub per_sec: f64,
+}
+
+impl MethodClass {
+    fn new(name: &str, methods: &[&str], burst: u32, per_sec: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            methods: methods.iter().map(|m| m.to_string()).collect(),
+            burst,
+            per_sec,
+        }
+    }
+}
+
+/// Per-session budgets for `kernel_request`
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct RequestRatePolicy {
+    pub classes: Vec<MethodClass>,
+}
+
+impl Default for RequestRatePolicy {
+    fn default() -> Self {
+        Self {
+            classes: vec![
+                // Each call can start an LLM or tool run
+                MethodClass::new(
+                    "llm",
+                    &[
+                        "chat/respond",
+                        "intent/detect",
+                        "code/plan/start",
+                        "code/exec/start",
+                        "execution/start",
+                        "compact/*",
+                    ],
+                    5,
+                    1.0,
+                ),
+                // UI polling loops
+                MethodClass::new(
+                    "polling",
+                    &[
+                        "ollama/status",
+                        "ollama/pull_status",
+                        "code/exec/state",
+                        "code/plan/state",
+                        "execution/status",
+                        "system/live_state",
+                        "context/stats",
+                        "approval/pending",
+                    ],
+                    30,
+                    10.0,
+                ),
+                MethodClass::new("default", &["*"], 60, 20.0),
+            ],
+        }
+    }
+}
+
+impl RequestRatePolicy {
+    /// Load the policy from `path` (missing or invalid file = defaults)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// A kernel request refused by the per-session limiter
+#[derive(Debug, PartialEq)]
+pub struct RateLimited {
+    pub class: String,
+    pub retry_after: Duration,
+}
+
+impl RateLimited {
+    /// JSON-RPC error response handed back in place of the kernel's
+    pub fn to_response(&self) -> Value {
+        let retry_after_ms = self.retry_after.as_millis().max(1) as u64;
+        json!({
+            "jsonrpc": "2.0",
+            "id": null,
+            "error": {
+                "code": RATE_LIMITED_CODE,
+                "message": format!("Too many requests, retry in {retry_after_ms}ms"),
+                "data": {
+                    "reason": "rate_limited",
+                    "class": self.class,
+                    "retry_after_ms": retry_after_ms,
+                },
+            },
+        })
+    }
+}
+
+/// Token buckets per (session, method class)
+pub struct RequestRateLimiter {
+    policy: RequestRatePolicy,
+    buckets: HashMap<(String, usize), TokenBucket>,
+}
+
+impl RequestRateLimiter {
+    pub fn new(policy: RequestRatePolicy) -> Self {
+        Self {
+            policy,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Record a `method` call from `session_id` (unclassified methods pass)
+    pub fn check(&mut self, session_id: &str, method: &str) -> Result<(), RateLimited> {
+        self.check_at(session_id, method, Instant::now())
+    }
+
+    fn check_at(
+        &mut self,
+        session_id: &str,
+        method: &str,
+        now: Instant,
+    ) -> Result<(), RateLimited> {
+        let Some((index, class)) = self
+            .policy
+            .classes
+            .iter()
+            .enumerate()
+            .find(|(_, class)| class.methods.iter().any(|p| method_matches(p, method)))
+        else {
+            return Ok(());
+        };
+        let bucket = self
+            .buckets
+            .entry((session_id.to_string(), index))
+            .or_insert_with(|| TokenBucket::new(class.burst, class.per_sec));
+        if !bucket.has_token(now) {
+            return Err(RateLimited {
+                class: class.name.clone(),
+                retry_after: bucket.retry_after(),
+            });
+        }
+        bucket.take();
+        Ok(())
+    }
+
+    /// Drop buckets that have refilled completely (same as a fresh bucket),
+    /// so ended sessions don't accumulate
+    pub fn prune(&mut self) {
+        let now = Instant::now();
+        self.buckets.retain(|_, bucket| {
+            bucket.refill(now);
+            bucket.tokens < bucket.capacity
+        });
+    }
+
+    /// Switch to an edited policy; buckets start full again since classes
+    /// may have changed
+    pub fn set_policy(&mut self, policy: RequestRatePolicy) {
+        self.policy = policy;
+        self.buckets.clear();
+    }
+
+    /// Forget all buckets (admin state reset)
+    pub fn reset(&mut self) {
+        self.buckets.clear();
+    }
+}
+
+/// Thread-safe kernel request rate limiter state
+pub struct RequestRateLimitState(pub Mutex<RequestRateLimiter>);
+
+/// Thread-safe login rate limiter state
+pub struct LoginRateLimitState(pub Mutex<LoginRateLimiter>);
+
+impl LoginRateLimitState {
+    pub fn new() -> Self {
+        Self(Mutex::new(LoginRateLimiter::new()))
+    }
+}
+
+impl Default for LoginRateLimitState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Thread-safe `frontend_log` rate limiter state
+pub struct FrontendLogLimitState(pub Mutex<FrontendLogLimiter>);
+
+impl FrontendLogLimitState {
+    pub fn new() -> Self {
+        Self(Mutex::new(FrontendLogLimiter::new()))
+    }
+}
+
+impl Default for FrontendLogLimitState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, 1.0);
+        for _ in 0..2 {
+            assert!(bucket.has_token(start));
+            bucket.take();
+        }
+        assert!(!bucket.has_token(start));
+        assert!(bucket.retry_after() > Duration::ZERO);
+        assert!(bucket.has_token(start + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_login_limiter_per_window() {
+        let now = Instant::now();
+        let mut limiter = LoginRateLimiter::new();
+        for _ in 0..LOGIN_WINDOW_BURST {
+            assert!(limiter.check_at("main", now).is_ok());
+        }
+        let retry = limiter.check_at("main", now).unwrap_err();
+        assert!(retry > Duration::ZERO);
+
+        // Another window has its own budget
+        assert!(limiter.check_at("dashboard", now).is_ok());
+    }
+
+    #[test]
+    fn test_frontend_log_limiter_counts_dropped() {
+        let now = Instant::now();
+        let mut limiter = FrontendLogLimiter::new();
+        for _ in 0..FRONTEND_LOG_BURST {
+            assert_eq!(limiter.check_at("main", now), Some(0));
+        }
+        assert_eq!(limiter.check_at("main", now), None);
+        assert_eq!(limiter.check_at("main", now), None);
+        assert_eq!(limiter.check_at("me", now), Some(0));
+        let later = now + Duration::from_secs(1);
+        assert_eq!(limiter.check_at("main", later), Some(2));
+        assert_eq!(limiter.check_at("main", later), Some(0));
+    }
+
+    #[test]
+    fn test_request_limiter_per_session_and_class() {
+        let now = Instant::now();
+        let mut limiter = RequestRateLimiter::new(RequestRatePolicy::default());
+        for _ in 0..5 {
+            assert!(limiter.check_at("s1", "chat/respond", now).is_ok());
+        }
+        let limited = limiter.check_at("s1", "chat/respond", now).unwrap_err();
+        assert_eq!(limited.class, "llm");
+        assert!(limited.retry_after > Duration::ZERO);
+
+        // Other classes and other sessions have their own budgets
+        assert!(limiter.check_at("s1", "play/acts/list", now).is_ok());
+        assert!(limiter.check_at("s2", "chat/respond", now).is_ok());
+    }
+
+    #[test]
+    fn test_request_limiter_prunes_full_buckets() {
+        let mut limiter = RequestRateLimiter::new(RequestRatePolicy {
+            classes: vec![MethodClass::new("slow", &["*"], 1, 0.0)],
+        });
+        assert!(limiter.check("s1", "ping").is_ok());
+        assert!(limiter.check("s1", "ping").is_err());
+        limiter.prune();
+        assert_eq!(limiter.buckets.len(), 1);
+        assert!(limiter.check("s1", "ping").is_err());
+    }
+
+    #[test]
+    fn test_login_limiter_global() {
+        let now = Instant::now();
+        let mut limiter = LoginRateLimiter::new();
+        let mut allowed = 0;
+        for i in 0..100 {
+            if limiter.check_at(&format!("w{i}"), now).is_ok() {
+                allowed += 1;
+            }
+        }
+        assert_eq!(allowed, LOGIN_GLOBAL_BURST);
+    }
+}
@@ -0,0 +1,324 @@
+//! Remote Kernel over gRPC (optional)
+//!
+//! Builds with the `grpc` feature can reach a kernel serving the protocol
+//! in `proto/kernel.proto` (`kernel_grpc.py`), for sites that standardize
+//! on gRPC: `remote-kernel.json` with a `grpcs://host:port` URL
+//! (`grpc://` only to this machine, for debugging). TLS is set up as for
+//! the other remote transports (`ca_cert`, pinning, client certificate),
+//! and the token goes in the `authorization` metadata.
+//!
+//! One `Connect` call is one shell connection, frames streaming both ways
+//! over HTTP/2: the channel hello as `Frame.hello`, then each encrypted
+//! frame as raw `Frame.sealed` bytes. The typed plain JSON-RPC bodies are
+//! for clients without the channel; the shell neither sends nor expects
+//! them.
+//!
+//! Without the feature, a gRPC URL fails to connect with a clear error.
+
+/// Connect to the kernel at `url` (`grpcs://` or `grpc://`)
+#[cfg(not(feature = "grpc"))]
+pub fn connect(
+    _config: &crate::remote_kernel::RemoteKernelConfig,
+    _url: url::Url,
+) -> Result<crate::kernel_transport::Link, crate::remote_kernel::RemoteKernelError> {
+    Err(crate::remote_kernel::RemoteKernelError::Unsupported)
+}
+
+#[cfg(feature = "grpc")]
+pub use client::connect;
+
+#[cfg(feature = "grpc")]
+mod client {
+    use data_encoding::BASE64;
+    use hyper_util::rt::TokioIo;
+    use rustls::pki_types::ServerName;
+    use std::io::{self, Read, Write};
+    use std::sync::{mpsc, Arc};
+    use std::time::Duration;
+    use tokio::net::TcpStream;
+    use tokio_rustls::TlsConnector;
+    use tokio_stream::wrappers::ReceiverStream;
+    use tonic::codegen::http::uri::PathAndQuery;
+    use tonic::codegen::http::Uri;
+    use tonic::transport::Endpoint;
+    use tower::service_fn;
+
+    use super::proto::{frame::Body, Frame};
+    use crate::kernel::MAX_FRAME_LEN;
+    use crate::kernel_transport::Link;
+    use crate::remote_kernel::{split_lines, Pending, RemoteKernelConfig, RemoteKernelError};
+
+    /// Timeout for the connection and the TLS handshake
+    const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+    /// Frames queued each way before the sender waits
+    const QUEUE_DEPTH: usize = 64;
+
+    const CONNECT_PATH: &str = "/reos.kernel.v1.Kernel/Connect";
+
+    /// Open a `Connect` call to the kernel at `url`
+    pub fn connect(config: &RemoteKernelConfig, url: url::Url) -> Result<Link, RemoteKernelError> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| RemoteKernelError::Connect(e.to_string()))?;
+        let (outbound, to_send) = tokio::sync::mpsc::channel(QUEUE_DEPTH);
+        let mut inbound = runtime.block_on(open(config, url, to_send))?;
+
+        // The call (and its HTTP/2 connection) is driven on its own thread
+        // for as long as the kernel sends
+        let (deliver, received) = mpsc::sync_channel(QUEUE_DEPTH);
+        std::thread::Builder::new()
+            .name("kernel-grpc".to_string())
+            .spawn(move || {
+                runtime.block_on(async move {
+                    loop {
+                        let next = match inbound.message().await {
+                            Ok(Some(frame)) => Ok(frame),
+                            Ok(None) => break,
+                            Err(status) => Err(io::Error::other(status.message().to_string())),
+                        };
+                        let failed = next.is_err();
+                        if deliver.send(next).is_err() || failed {
+                            break;
+                        }
+                    }
+                })
+            })
+            .map_err(|e| RemoteKernelError::Connect(e.to_string()))?;
+
+        Ok(Link::new(
+            FrameReader {
+                received,
+                pending: Pending::default(),
+            },
+            FrameWriter {
+                outbound,
+                line: Vec::new(),
+            },
+        ))
+    }
+
+    async fn open(
+        config: &RemoteKernelConfig,
+        url: url::Url,
+        to_send: tokio::sync::mpsc::Receiver<Frame>,
+    ) -> Result<tonic::Streaming<Frame>, RemoteKernelError> {
+        let host = url.host_str().unwrap_or_default().to_string();
+        let port = url.port().unwrap_or(443);
+        let endpoint = Endpoint::from_shared(format!("http://{host}:{port}"))
+            .map_err(|e| RemoteKernelError::InvalidUrl(e.to_string()))?
+            .connect_timeout(CONNECT_TIMEOUT);
+        let channel = if url.scheme() == "grpcs" {
+            // TLS as for the other transports (pinning, client certificate),
+            // rather than tonic's own setup
+            let mut tls = (*config.tls()?).clone();
+            tls.alpn_protocols = vec![b"h2".to_vec()];
+            let connector = TlsConnector::from(Arc::new(tls));
+            let server_name = ServerName::try_from(host.trim_matches(['[', ']']).to_string())
+                .map_err(|e| RemoteKernelError::InvalidUrl(e.to_string()))?;
+            endpoint
+                .connect_with_connector(service_fn(move |_: Uri| {
+                    let (connector, server_name, host) =
+                        (connector.clone(), server_name.clone(), host.clone());
+                    async move {
+                        let tcp = TcpStream::connect((host.trim_matches(['[', ']']), port)).await?;
+                        let _ = tcp.set_nodelay(true);
+                        let tls = connector.connect(server_name, tcp).await?;
+                        Ok::<_, io::Error>(TokioIo::new(tls))
+                    }
+                }))
+                .await
+        } else {
+            endpoint.connect().await
+        }
+        .map_err(|e| RemoteKernelError::Connect(e.to_string()))?;
+
+        let mut request = tonic::Request::new(ReceiverStream::new(to_send));
+        let mut authorization = config
+            .bearer()
+            .parse::<tonic::metadata::AsciiMetadataValue>()
+            .map_err(|e| RemoteKernelError::InvalidUrl(format!("token: {e}")))?;
+        authorization.set_sensitive(true);
+        request
+            .metadata_mut()
+            .insert("authorization", authorization);
+
+        // Frames up to the same limit as over stdio
+        let mut grpc = tonic::client::Grpc::new(channel)
+            .max_decoding_message_size(MAX_FRAME_LEN)
+            .max_encoding_message_size(MAX_FRAME_LEN);
+        grpc.ready()
+            .await
+            .map_err(|e| RemoteKernelError::Connect(e.to_string()))?;
+        let codec = tonic::codec::ProstCodec::<Frame, Frame>::default();
+        let response = grpc
+            .streaming(request, PathAndQuery::from_static(CONNECT_PATH), codec)
+            .await
+            .map_err(|status| match status.code() {
+                tonic::Code::Unauthenticated => {
+                    RemoteKernelError::Handshake("token rejected".to_string())
+                }
+                tonic::Code::Unavailable => {
+                    RemoteKernelError::Handshake(status.message().to_string())
+                }
+                _ => RemoteKernelError::Connect(status.message().to_string()),
+            })?;
+        Ok(response.into_inner())
+    }
+
+    /// The frame carrying a line the shell writes
+    pub(super) fn frame_for_line(line: String) -> io::Result<Frame> {
+        let body = if line.starts_with('{') {
+            Body::Hello(line)
+        } else {
+            let sealed = BASE64
+                .decode(line.as_bytes())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            Body::Sealed(sealed)
+        };
+        Ok(Frame { body: Some(body) })
+    }
+
+    /// The line (without newline) the shell reads for a kernel frame
+    pub(super) fn line_for_frame(frame: Frame) -> io::Result<Vec<u8>> {
+        match frame.body {
+            Some(Body::Hello(hello)) => Ok(hello.into_bytes()),
+            Some(Body::Sealed(sealed)) => Ok(BASE64.encode(&sealed).into_bytes()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "plain message on an encrypted connection",
+            )),
+        }
+    }
+
+    /// Received frames as lines; end of stream once the call ends
+    struct FrameReader {
+        received: mpsc::Receiver<io::Result<Frame>>,
+        pending: Pending,
+    }
+
+    impl Read for FrameReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            while self.pending.is_empty() {
+                match self.received.recv() {
+                    Ok(frame) => self.pending.set_line(line_for_frame(frame?)?),
+                    Err(_) => return Ok(0),
+                }
+            }
+            Ok(self.pending.read(buf))
+        }
+    }
+
+    /// Each written line goes out as one frame; dropping it ends the call
+    struct FrameWriter {
+        outbound: tokio::sync::mpsc::Sender<Frame>,
+        line: Vec<u8>,
+    }
+
+    impl Write for FrameWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let outbound = &self.outbound;
+            split_lines(&mut self.line, buf, |text| {
+                outbound
+                    .blocking_send(frame_for_line(text)?)
+                    .map_err(|_| io::ErrorKind::BrokenPipe.into())
+            })?;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
+
+/// Messages of `proto/kernel.proto`
+#[cfg(feature = "grpc")]
+pub mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Frame {
+        #[prost(oneof = "frame::Body", tags = "1, 2, 3, 4, 5")]
+        pub body: Option<frame::Body>,
+    }
+
+    pub mod frame {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Body {
+            #[prost(string, tag = "1")]
+            Hello(String),
+            #[prost(bytes = "vec", tag = "2")]
+            Sealed(Vec<u8>),
+            #[prost(message, tag = "3")]
+            Request(super::Request),
+            #[prost(message, tag = "4")]
+            Response(super::Response),
+            #[prost(message, tag = "5")]
+            Notification(super::Notification),
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Request {
+        #[prost(string, tag = "1")]
+        pub id: String,
+        #[prost(string, tag = "2")]
+        pub method: String,
+        #[prost(string, tag = "3")]
+        pub params: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Response {
+        #[prost(string, tag = "1")]
+        pub id: String,
+        #[prost(string, tag = "2")]
+        pub result: String,
+        #[prost(message, optional, tag = "3")]
+        pub error: Option<Error>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Error {
+        #[prost(int32, tag = "1")]
+        pub code: i32,
+        #[prost(string, tag = "2")]
+        pub message: String,
+        #[prost(string, tag = "3")]
+        pub data: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Notification {
+        #[prost(string, tag = "1")]
+        pub method: String,
+        #[prost(string, tag = "2")]
+        pub params: String,
+    }
+}
+
+#[cfg(all(test, feature = "grpc"))]
+mod tests {
+    use super::client::{frame_for_line, line_for_frame};
+    use super::proto::{frame::Body, Frame, Notification};
+    use prost::Message;
+
+    #[test]
+    fn test_lines_round_trip_as_frames() {
+        let hello = r#"{"channel":"reos-ipc-v1","e":"00"}"#.to_string();
+        let frame = frame_for_line(hello.clone()).unwrap();
+        assert_eq!(frame.body, Some(Body::Hello(hello.clone())));
+        assert_eq!(line_for_frame(frame).unwrap(), hello.into_bytes());
+
+        let frame = frame_for_line("AAEC/w==".to_string()).unwrap();
+        assert_eq!(frame.body, Some(Body::Sealed(vec![0, 1, 2, 255])));
+        let decoded = Frame::decode(frame.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(line_for_frame(decoded).unwrap(), b"AAEC/w==".to_vec());
+
+        assert!(frame_for_line("not base64!".to_string()).is_err());
+        let plain = Frame {
+            body: Some(Body::Notification(Notification::default())),
+        };
+        assert!(line_for_frame(plain).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+//! Remote Kernel over HTTP
+//!
+//! The fallback for networks where WebSockets don't get through (proxies
+//! that only pass plain requests), and a way to poke a kernel with curl:
+//! `remote-kernel.json` with an `https://` URL (or `http://` to this
+//! machine). Under that URL the kernel (`kernel_http.py`) serves:
+//!
+//! - `POST connect`: open the connection, answered with
+//!   `{"connection": "<id>"}` (503 while another shell is connected)
+//! - `POST frames?connection=<id>`: frame lines for the kernel
+//! - `GET events?connection=<id>`: everything the kernel sends, as
+//!   server-sent events (`data: <line>`) with keep-alive comments
+//! - `GET poll?connection=<id>`: the same by long polling, for proxies that
+//!   hold event streams back (`"notifications": "long_poll"`): the lines
+//!   sent within the kernel's poll wait, possibly none
+//! - `POST close?connection=<id>`
+//!
+//! Every request carries the token as `Authorization: Bearer`, and frames
+//! are the same encrypted lines as over any other transport. The kernel
+//! drops a connection that is neither streamed nor polled for a minute.
+
+use serde::Deserialize;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::Arc;
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+use crate::kernel::MAX_FRAME_LEN;
+use crate::kernel_transport::Link;
+use crate::remote_kernel::{split_lines, Pending, RemoteKernelConfig, RemoteKernelError};
+
+/// Timeout for opening, posting to and closing a connection
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a poll may take (the kernel answers within 25 seconds)
+const POLL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How the kernel's lines reach the shell
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Notifications {
+    /// One long-lived `text/event-stream` response
+    #[default]
+    Sse,
+    /// Repeated `poll` requests
+    LongPoll,
+}
+
+/// Answer to `POST connect`
+#[derive(Deserialize)]
+struct Opened {
+    connection: String,
+}
+
+/// One connection's endpoints and credentials
+struct Session {
+    agent: ureq::Agent,
+    base: url::Url,
+    connection: String,
+    bearer: Zeroizing<String>,
+}
+
+impl Session {
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let mut url = self.base.join(path).unwrap_or_else(|_| self.base.clone());
+        url.query_pairs_mut()
+            .append_pair("connection", &self.connection);
+        self.agent
+            .request_url(method, &url)
+            .set("Authorization", &self.bearer)
+    }
+}
+
+fn http_error(e: ureq::Error) -> RemoteKernelError {
+    match e {
+        ureq::Error::Status(401, _) => RemoteKernelError::Handshake("token rejected".to_string()),
+        ureq::Error::Status(503, _) => {
+            RemoteKernelError::Handshake("kernel busy with another shell".to_string())
+        }
+        ureq::Error::Status(code, _) => RemoteKernelError::Handshake(format!("HTTP {code}")),
+        ureq::Error::Transport(e) => RemoteKernelError::Connect(e.to_string()),
+    }
+}
+
+/// Open a connection to the kernel at `base`
+pub fn connect(
+    config: &RemoteKernelConfig,
+    mut base: url::Url,
+    tls: Arc<rustls::ClientConfig>,
+) -> Result<Link, RemoteKernelError> {
+    if !base.path().ends_with('/') {
+        let path = format!("{}/", base.path());
+        base.set_path(&path);
+    }
+    let agent = ureq::AgentBuilder::new()
+        .tls_config(tls)
+        .timeout_connect(REQUEST_TIMEOUT)
+        .build();
+    let bearer = config.bearer();
+    let connect = base
+        .join("connect")
+        .map_err(|e| RemoteKernelError::InvalidUrl(e.to_string()))?;
+    let opened: Opened = agent
+        .request_url("POST", &connect)
+        .set("Authorization", &bearer)
+        .timeout(REQUEST_TIMEOUT)
+        .call()
+        .map_err(http_error)?
+        .into_json()
+        .map_err(|e| RemoteKernelError::Handshake(e.to_string()))?;
+
+    let session = Arc::new(Session {
+        agent,
+        base,
+        connection: opened.connection,
+        bearer,
+    });
+    let reader: Box<dyn Read + Send> = match config.notifications {
+        Notifications::Sse => {
+            let events = session
+                .request("GET", "events")
+                .set("Accept", "text/event-stream")
+                .call()
+                .map_err(http_error)?;
+            Box::new(EventReader {
+                events: BufReader::new(events.into_reader()),
+                pending: Pending::default(),
+            })
+        }
+        Notifications::LongPoll => Box::new(PollReader {
+            session: session.clone(),
+            pending: Pending::default(),
+        }),
+    };
+    Ok(Link::new(
+        reader,
+        FrameWriter {
+            session,
+            line: Vec::new(),
+        },
+    ))
+}
+
+/// `data:` of server-sent events as lines; end of stream when the kernel
+/// ends it
+struct EventReader {
+    events: BufReader<Box<dyn Read + Send + Sync>>,
+    pending: Pending,
+}
+
+impl EventReader {
+    /// The next event's data, `None` at end of stream
+    fn next_event(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut data: Option<Vec<u8>> = None;
+        loop {
+            let mut line = Vec::new();
+            let limit = MAX_FRAME_LEN as u64 + 8;
+            let read = (&mut self.events)
+                .take(limit)
+                .read_until(b'\n', &mut line)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            if line.pop() != Some(b'\n') {
+                return Err(if read as u64 >= limit {
+                    io::Error::new(io::ErrorKind::InvalidData, "event over the frame limit")
+                } else {
+                    io::Error::new(io::ErrorKind::UnexpectedEof, "event stream cut short")
+                });
+            }
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            if line.is_empty() {
+                match data.take() {
+                    Some(data) => return Ok(Some(data)),
+                    None => continue,
+                }
+            }
+            // Comments (keep-alives) and other fields are skipped
+            if let Some(value) = line.strip_prefix(b"data:") {
+                let value = value.strip_prefix(b" ").unwrap_or(value);
+                match data.as_mut() {
+                    Some(data) => {
+                        data.push(b'\n');
+                        data.extend_from_slice(value);
+                    }
+                    None => data = Some(value.to_vec()),
+                }
+            }
+        }
+    }
+}
+
+impl Read for EventReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pending.is_empty() {
+            match self.next_event()? {
+                Some(data) => self.pending.set_line(data),
+                None => return Ok(0),
+            }
+        }
+        Ok(self.pending.read(buf))
+    }
+}
+
+/// Lines from repeated polls; end of stream once the connection is gone
+struct PollReader {
+    session: Arc<Session>,
+    pending: Pending,
+}
+
+impl Read for PollReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pending.is_empty() {
+            let response = match self
+                .session
+                .request("GET", "poll")
+                .timeout(POLL_TIMEOUT)
+                .call()
+            {
+                Ok(response) => response,
+                Err(ureq::Error::Status(410, _)) => return Ok(0),
+                Err(e) => return Err(io::Error::other(e.to_string())),
+            };
+            let mut body = Vec::new();
+            response
+                .into_reader()
+                .take(MAX_FRAME_LEN as u64 + 1)
+                .read_to_end(&mut body)?;
+            if !body.is_empty() && !body.ends_with(b"\n") {
+                body.push(b'\n');
+            }
+            self.pending.set(body);
+        }
+        Ok(self.pending.read(buf))
+    }
+}
+
+/// Each written line is posted as it completes
+struct FrameWriter {
+    session: Arc<Session>,
+    line: Vec<u8>,
+}
+
+impl Write for FrameWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let session = &self.session;
+        split_lines(&mut self.line, buf, |text| {
+            match session
+                .request("POST", "frames")
+                .timeout(REQUEST_TIMEOUT)
+                .send_string(&text)
+            {
+                Ok(_) => Ok(()),
+                Err(ureq::Error::Status(410, _)) => Err(io::ErrorKind::BrokenPipe.into()),
+                Err(e) => Err(io::Error::other(e.to_string())),
+            }
+        })?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for FrameWriter {
+    /// The kernel keeps running for the next connection
+    fn drop(&mut self) {
+        let _ = self
+            .session
+            .request("POST", "close")
+            .timeout(REQUEST_TIMEOUT)
+            .call();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(stream: &'static [u8]) -> EventReader {
+        let boxed: Box<dyn Read + Send + Sync> = Box::new(stream);
+        EventReader {
+            events: BufReader::new(boxed),
+            pending: Pending::default(),
+        }
+    }
+
+    #[test]
+    fn test_event_stream_as_lines() {
+        let mut reader = events(
+            b": keep-alive\n\ndata: {\"id\":1}\n\nevent: x\r\ndata: two\r\n\r\n: keep-alive\n\n",
+        );
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "{\"id\":1}\ntwo\n");
+    }
+
+    #[test]
+    fn test_event_stream_ends_with_the_response() {
+        let mut reader = events(b"data: partial");
+        let mut text = String::new();
+        let e = reader.read_to_string(&mut text).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+        let mut reader = events(b"");
+        assert_eq!(reader.read(&mut [0u8; 8]).unwrap(), 0);
+    }
+}
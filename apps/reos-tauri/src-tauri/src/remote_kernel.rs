@@ -0,0 +1,687 @@
+//! Remote Kernel
+//!
+//! With `kernel.transport = "remote"` no kernel is started here: the shell
+//! connects to one running on another machine (a home server doing the
+//! heavy lifting for a thin client), configured via `remote-kernel.json`
+//! in the app data dir:
+//! ```json
+//! { "url": "wss://home.example.net:8765/", "token": "<shared secret>",
+//!   "ca_cert": "/etc/reos/home-ca.pem" }
+//! ```
+//!
+//! - `wss://`, or `https://` where WebSockets are blocked (see
+//!   `remote_http.rs`; `http://` only to this machine, for debugging): TLS
+//!   is checked against the web PKI roots plus `ca_cert` if set (for a
+//!   self-signed home server). Builds with the `grpc` feature also take
+//!   `grpcs://` (see `remote_grpc.rs`)
+//! - `server_cert_sha256` pins the kernel's certificate instead: that one
+//!   is accepted and no other, so a CA can't vouch for an impostor
+//! - Mutual TLS: this shell presents `client_cert`/`client_key`, by default
+//!   the identity `remote_kernel_pair` creates next to this file. Pairing
+//!   is handing its certificate to the kernel's operator, who adds it with
+//!   `python -m reos.kernel_pairing add`; a kernel with paired clients
+//!   turns away any other TLS client before the token is even seen
+//! - `token` goes in the `Authorization: Bearer` header of the upgrade
+//!   request (every request, over HTTP); the kernel (`kernel_websocket.py`,
+//!   `kernel_http.py`) turns anyone else away before a frame is read
+//! - `notifications` (HTTP only): `"sse"` (default) streams the kernel's
+//!   lines, `"long_poll"` polls for them where proxies buffer streams
+//! - Each frame line is one text message (or event), so requests,
+//!   responses, pushes and chunked results keep their stdio semantics,
+//!   inside the same encrypted channel
+//!
+//! The kernel outlives the shell: it is never asked to shut down from
+//! here, and the `__session` key is kept in `kernel.json` (see
+//! `kernel_transport.rs`) so a restarted shell reconnects to the same
+//! kernel-side sessions. It has no local pid (no resource sampling, no
+//! out-of-band blobs) and can't be hot swapped from here.
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use thiserror::Error;
+use tungstenite::client::IntoClientRequest;
+use tungstenite::protocol::WebSocketConfig;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Connector, Message, WebSocket};
+
+use crate::kernel::MAX_FRAME_LEN;
+use crate::kernel_transport::Link;
+use crate::locks::Recover;
+use crate::remote_grpc;
+use crate::remote_http::{self, Notifications};
+use crate::storage;
+
+/// Timeout for the TCP connection and the TLS/WebSocket handshake
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Error)]
+pub enum RemoteKernelError {
+    #[error("remote kernel is not configured (remote-kernel.json)")]
+    NotConfigured,
+    #[error("invalid remote kernel URL: {0}")]
+    InvalidUrl(String),
+    #[error("remote kernel CA certificate: {0}")]
+    Certificate(String),
+    #[error("can't reach the remote kernel: {0}")]
+    Connect(String),
+    #[error("remote kernel refused the connection: {0}")]
+    Handshake(String),
+    #[error("this build has no gRPC support (feature `grpc`)")]
+    Unsupported,
+    #[error("remote kernel client certificate: {0}")]
+    Identity(String),
+}
+
+/// Settings from `remote-kernel.json`
+#[derive(Deserialize, Clone)]
+pub struct RemoteKernelConfig {
+    pub url: String,
+    pub token: String,
+    /// Extra trusted CA (PEM), for a server with a private certificate
+    #[serde(default)]
+    pub ca_cert: Option<PathBuf>,
+    /// How an `https://` kernel sends responses and pushes
+    #[serde(default)]
+    pub notifications: Notifications,
+    /// SHA-256 (hex) of the kernel's certificate: when set, that
+    /// certificate and no other is accepted, whoever signed it
+    #[serde(default)]
+    pub server_cert_sha256: Option<String>,
+    /// Client certificate and key (PEM) presented to the kernel; the
+    /// paired identity next to `remote-kernel.json` if left out
+    #[serde(default)]
+    pub client_cert: Option<PathBuf>,
+    #[serde(default)]
+    pub client_key: Option<PathBuf>,
+}
+
+impl Drop for RemoteKernelConfig {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.token);
+    }
+}
+
+impl RemoteKernelConfig {
+    /// Load the configuration from `path` (missing or invalid file = none)
+    pub fn load(path: Option<&Path>) -> Option<Self> {
+        let path = path?;
+        let text = zeroize::Zeroizing::new(std::fs::read_to_string(path).ok()?);
+        let mut config: Self = serde_json::from_str(&text).ok()?;
+        if config.client_cert.is_none() && config.client_key.is_none() {
+            let cert = path.with_file_name(CLIENT_CERT_FILE);
+            let key = path.with_file_name(CLIENT_KEY_FILE);
+            if cert.is_file() && key.is_file() {
+                config.client_cert = Some(cert);
+                config.client_key = Some(key);
+            }
+        }
+        Some(config)
+    }
+
+    /// The kernel's URL: `wss://`, `https://` or `grpcs://`, or `http://`
+    /// or `grpc://` to a loopback address (a kernel on this machine, for
+    /// debugging)
+    fn endpoint(&self) -> Result<url::Url, RemoteKernelError> {
+        let url =
+            url::Url::parse(&self.url).map_err(|e| RemoteKernelError::InvalidUrl(e.to_string()))?;
+        let loopback = match url.host() {
+            // Non-special schemes (grpc://) leave IP hosts as opaque names
+            Some(url::Host::Domain(domain)) => {
+                domain == "localhost"
+                    || domain
+                        .parse::<std::net::IpAddr>()
+                        .is_ok_and(|ip| ip.is_loopback())
+            }
+            Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+            Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+            None => return Err(RemoteKernelError::InvalidUrl("no host".to_string())),
+        };
+        match url.scheme() {
+            "wss" | "https" | "grpcs" => Ok(url),
+            "http" | "grpc" if loopback => Ok(url),
+            _ => Err(RemoteKernelError::InvalidUrl(
+                "only wss://, https://, grpcs:// or plain http:// or grpc:// to this machine \
+                 is allowed"
+                    .to_string(),
+            )),
+        }
+    }
+
+    /// `Authorization` header value
+    pub fn bearer(&self) -> zeroize::Zeroizing<String> {
+        zeroize::Zeroizing::new(format!("Bearer {}", self.token))
+    }
+
+    fn tls(&self) -> Result<Arc<rustls::ClientConfig>, RemoteKernelError> {
+        use rustls::pki_types::pem::PemObject;
+        use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        if let Some(path) = &self.ca_cert {
+            let certs = CertificateDer::pem_file_iter(path)
+                .map_err(|e| RemoteKernelError::Certificate(e.to_string()))?;
+            for cert in certs {
+                let cert = cert.map_err(|e| RemoteKernelError::Certificate(e.to_string()))?;
+                roots
+                    .add(cert)
+                    .map_err(|e| RemoteKernelError::Certificate(e.to_string()))?;
+            }
+        }
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| RemoteKernelError::Connect(e.to_string()))?;
+        let builder = match &self.server_cert_sha256 {
+            Some(pin) => builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(PinnedCert::new(pin, provider)?)),
+            None => builder.with_root_certificates(roots),
+        };
+        let config = match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => {
+                let certs = CertificateDer::pem_file_iter(cert)
+                    .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+                    .map_err(|e| RemoteKernelError::Identity(e.to_string()))?;
+                let key = PrivateKeyDer::from_pem_file(key)
+                    .map_err(|e| RemoteKernelError::Identity(e.to_string()))?;
+                builder
+                    .with_client_auth_cert(certs, key)
+                    .map_err(|e| RemoteKernelError::Identity(e.to_string()))?
+            }
+            (None, None) => builder.with_no_client_auth(),
+            _ => {
+                return Err(RemoteKernelError::Identity(
+                    "client_cert and client_key go together".to_string(),
+                ))
+            }
+        };
+        Ok(Arc::new(config))
+    }
+}
+
+/// Accepts exactly the pinned certificate (by SHA-256 of its DER), then
+/// checks handshake signatures as usual
+#[derive(Debug)]
+struct PinnedCert {
+    sha256: [u8; 32],
+    provider: Arc<rustls::crypto::CryptoProvider>,
+}
+
+impl PinnedCert {
+    fn new(
+        pin: &str,
+        provider: Arc<rustls::crypto::CryptoProvider>,
+    ) -> Result<Self, RemoteKernelError> {
+        let pin: String = pin.chars().filter(|c| *c != ':').collect();
+        let sha256 = hex::decode(pin)
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or_else(|| {
+                RemoteKernelError::Certificate("server_cert_sha256 is not a SHA-256".to_string())
+            })?;
+        Ok(Self { sha256, provider })
+    }
+}
+
+impl ServerCertVerifier for PinnedCert {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::pki_types::CertificateDer<'_>,
+        _intermediates: &[rustls::pki_types::CertificateDer<'_>],
+        _server_name: &rustls::pki_types::ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: rustls::pki_types::UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if Sha256::digest(end_entity.as_ref()).as_slice() == self.sha256 {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::InvalidCertificate(
+                rustls::CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+/// This shell's paired client certificate and key, next to
+/// `remote-kernel.json`
+pub const CLIENT_CERT_FILE: &str = "remote-kernel-client.pem";
+pub const CLIENT_KEY_FILE: &str = "remote-kernel-client.key";
+
+/// What the kernel's operator needs to pair this shell
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Pairing {
+    /// SHA-256 of the certificate (hex), to compare on both ends
+    pub fingerprint: String,
+    /// The certificate (PEM; public) for `python -m reos.kernel_pairing add`
+    pub certificate: String,
+}
+
+/// This shell's client certificate in `dir`, created (with its key, private
+/// to the user) on first use
+pub fn pairing(dir: &Path) -> Result<Pairing, RemoteKernelError> {
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::CertificateDer;
+
+    let cert_path = dir.join(CLIENT_CERT_FILE);
+    let key_path = dir.join(CLIENT_KEY_FILE);
+    if !cert_path.is_file() || !key_path.is_file() {
+        let host = gethostname::gethostname().to_string_lossy().into_owned();
+        let mut params = rcgen::CertificateParams::new(Vec::new())
+            .map_err(|e| RemoteKernelError::Identity(e.to_string()))?;
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, format!("ReOS shell on {host}"));
+        let key =
+            rcgen::KeyPair::generate().map_err(|e| RemoteKernelError::Identity(e.to_string()))?;
+        let cert = params
+            .self_signed(&key)
+            .map_err(|e| RemoteKernelError::Identity(e.to_string()))?;
+        let key_pem = zeroize::Zeroizing::new(key.serialize_pem());
+        storage::write_private_file(&key_path, key_pem.as_bytes())
+            .and_then(|_| storage::write_private_file(&cert_path, cert.pem().as_bytes()))
+            .map_err(|e| RemoteKernelError::Identity(e.to_string()))?;
+    }
+    let certificate = std::fs::read_to_string(&cert_path)
+        .map_err(|e| RemoteKernelError::Identity(e.to_string()))?;
+    let der = CertificateDer::from_pem_slice(certificate.as_bytes())
+        .map_err(|e| RemoteKernelError::Identity(e.to_string()))?;
+    Ok(Pairing {
+        fingerprint: hex::encode(Sha256::digest(der.as_ref())),
+        certificate,
+    })
+}
+
+static CONFIG: OnceLock<RemoteKernelConfig> = OnceLock::new();
+
+/// Install `remote-kernel.json` (at startup; first call wins)
+pub fn init(config: Option<RemoteKernelConfig>) {
+    if let Some(config) = config {
+        let _ = CONFIG.set(config);
+    }
+}
+
+/// The configured kernel's URL, if any
+pub fn url() -> Option<&'static str> {
+    CONFIG.get().map(|config| config.url.as_str())
+}
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+/// Connect to the configured remote kernel
+pub fn connect() -> Result<Link, RemoteKernelError> {
+    let config = CONFIG.get().ok_or(RemoteKernelError::NotConfigured)?;
+    let url = config.endpoint()?;
+    match url.scheme() {
+        "wss" => {}
+        "grpcs" | "grpc" => return remote_grpc::connect(config, url),
+        _ => return remote_http::connect(config, url, config.tls()?),
+    }
+    let tls = config.tls()?;
+    let host = url.host_str().unwrap_or_default().to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let address = (host.as_str(), port)
+        .to_socket_addrs()
+        .map_err(|e| RemoteKernelError::Connect(e.to_string()))?
+        .next()
+        .ok_or_else(|| RemoteKernelError::Connect(format!("{host} did not resolve")))?;
+    let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)
+        .map_err(|e| RemoteKernelError::Connect(e.to_string()))?;
+    let _ = stream.set_nodelay(true);
+    stream
+        .set_read_timeout(Some(CONNECT_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(CONNECT_TIMEOUT)))
+        .map_err(|e| RemoteKernelError::Connect(e.to_string()))?;
+
+    let mut request = config
+        .url
+        .as_str()
+        .into_client_request()
+        .map_err(|e| RemoteKernelError::InvalidUrl(e.to_string()))?;
+    let bearer = config.bearer();
+    let mut authorization = tungstenite::http::HeaderValue::from_str(&bearer)
+        .map_err(|e| RemoteKernelError::InvalidUrl(format!("token: {e}")))?;
+    authorization.set_sensitive(true);
+    request
+        .headers_mut()
+        .insert(tungstenite::http::header::AUTHORIZATION, authorization);
+
+    // Frames up to the same limit as over stdio; no compression (they are
+    // encrypted already)
+    let limits = WebSocketConfig {
+        max_message_size: Some(MAX_FRAME_LEN),
+        max_frame_size: Some(MAX_FRAME_LEN),
+        ..WebSocketConfig::default()
+    };
+    let (socket, _) = tungstenite::client_tls_with_config(
+        request,
+        stream,
+        Some(limits),
+        Some(Connector::Rustls(tls)),
+    )
+    .map_err(|e| RemoteKernelError::Handshake(e.to_string()))?;
+
+    // Requests may take as long as they take, as over stdio
+    let tcp = match socket.get_ref() {
+        MaybeTlsStream::Rustls(tls) => &tls.sock,
+        MaybeTlsStream::Plain(tcp) => tcp,
+        _ => return Err(RemoteKernelError::Connect("unexpected stream".to_string())),
+    };
+    tcp.set_read_timeout(None)
+        .and_then(|_| tcp.set_write_timeout(None))
+        .map_err(|e| RemoteKernelError::Connect(e.to_string()))?;
+
+    let socket = Arc::new(Mutex::new(socket));
+    Ok(Link::new(
+        MessageReader {
+            socket: socket.clone(),
+            pending: Pending::default(),
+        },
+        MessageWriter {
+            socket,
+            line: Vec::new(),
+        },
+    ))
+}
+
+/// Received lines not read out yet
+#[derive(Default)]
+pub struct Pending {
+    bytes: Vec<u8>,
+    offset: usize,
+}
+
+impl Pending {
+    pub fn is_empty(&self) -> bool {
+        self.offset == self.bytes.len()
+    }
+
+    /// Replace what is pending with `bytes` (whole lines)
+    pub fn set(&mut self, bytes: Vec<u8>) {
+        self.bytes = bytes;
+        self.offset = 0;
+    }
+
+    /// Replace what is pending with `line` and its newline
+    pub fn set_line(&mut self, mut line: Vec<u8>) {
+        line.push(b'\n');
+        self.set(line);
+    }
+
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let count = buf.len().min(self.bytes.len() - self.offset);
+        buf[..count].copy_from_slice(&self.bytes[self.offset..self.offset + count]);
+        self.offset += count;
+        count
+    }
+}
+
+/// Append `buf` to `line`, passing on each line it completes (without the
+/// newline)
+pub fn split_lines(
+    line: &mut Vec<u8>,
+    buf: &[u8],
+    mut send: impl FnMut(String) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut rest = buf;
+    while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+        line.extend_from_slice(&rest[..end]);
+        let text = String::from_utf8(std::mem::take(line))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        send(text)?;
+        rest = &rest[end + 1..];
+    }
+    line.extend_from_slice(rest);
+    Ok(())
+}
+
+fn io_error(e: tungstenite::Error) -> io::Error {
+    match e {
+        tungstenite::Error::Io(e) => e,
+        e => io::Error::other(e),
+    }
+}
+
+/// Text messages as newline-terminated lines; end of stream once closed
+struct MessageReader {
+    socket: Arc<Mutex<Socket>>,
+    pending: Pending,
+}
+
+impl Read for MessageReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pending.is_empty() {
+            // Pings are answered by tungstenite as messages are read
+            let message = match self.socket.lock_or_recover().read() {
+                Ok(message) => message,
+                Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                    return Ok(0)
+                }
+                Err(e) => return Err(io_error(e)),
+            };
+            match message {
+                Message::Text(text) => self.pending.set_line(text.into_bytes()),
+                Message::Close(_) => return Ok(0),
+                _ => {}
+            }
+        }
+        Ok(self.pending.read(buf))
+    }
+}
+
+/// Each written line goes out as one text message
+struct MessageWriter {
+    socket: Arc<Mutex<Socket>>,
+    line: Vec<u8>,
+}
+
+impl Write for MessageWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let socket = &self.socket;
+        split_lines(&mut self.line, buf, |text| {
+            socket
+                .lock_or_recover()
+                .send(Message::Text(text))
+                .map_err(io_error)
+        })?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.socket.lock_or_recover().flush().map_err(io_error)
+    }
+}
+
+impl Drop for MessageWriter {
+    /// The kernel keeps running for the next connection
+    fn drop(&mut self) {
+        let _ = self.socket.lock_or_recover().close(None);
+        let _ = self.socket.lock_or_recover().flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(url: &str) -> RemoteKernelConfig {
+        RemoteKernelConfig {
+            url: url.to_string(),
+            token: "secret".to_string(),
+            ca_cert: None,
+            notifications: Notifications::default(),
+            server_cert_sha256: None,
+            client_cert: None,
+            client_key: None,
+        }
+    }
+
+    #[test]
+    fn test_endpoint_requires_tls_off_this_machine() {
+        let url = config("wss://home.example.net:8765/").endpoint().unwrap();
+        assert_eq!(url.port_or_known_default(), Some(8765));
+        assert!(config("https://home.example.net/kernel/")
+            .endpoint()
+            .is_ok());
+        assert!(config("http://127.0.0.1:8766/").endpoint().is_ok());
+        assert!(config("http://localhost:8766/").endpoint().is_ok());
+        assert!(config("http://[::1]:8766/").endpoint().is_ok());
+        assert!(config("http://home.example.net/").endpoint().is_err());
+        assert!(config("grpcs://home.example.net:8767").endpoint().is_ok());
+        assert!(config("grpc://127.0.0.1:8767").endpoint().is_ok());
+        assert!(config("grpc://home.example.net:8767").endpoint().is_err());
+        assert!(config("ws://home.example.net:8765/").endpoint().is_err());
+        assert!(config("ws://127.0.0.1:8765/").endpoint().is_err());
+        assert!(config("not a url").endpoint().is_err());
+    }
+
+    #[test]
+    fn test_split_lines_and_pending() {
+        let mut line = Vec::new();
+        let mut sent = Vec::new();
+        split_lines(&mut line, b"one\ntw", |text| {
+            sent.push(text);
+            Ok(())
+        })
+        .unwrap();
+        split_lines(&mut line, b"o\n", |text| {
+            sent.push(text);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(sent, ["one", "two"]);
+        assert!(line.is_empty());
+
+        let mut pending = Pending::default();
+        assert!(pending.is_empty());
+        pending.set_line(b"abc".to_vec());
+        let mut buf = [0u8; 2];
+        assert_eq!(pending.read(&mut buf), 2);
+        assert_eq!(&buf, b"ab");
+        assert_eq!(pending.read(&mut buf), 2);
+        assert_eq!(&buf, b"c\n");
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_load_config() {
+        let dir = std::env::temp_dir().join(format!("reos-remote-kernel-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("remote-kernel.json");
+        assert!(RemoteKernelConfig::load(Some(&path)).is_none());
+        std::fs::write(&path, r#"{ "url": "wss://home.example.net/" }"#).unwrap();
+        assert!(RemoteKernelConfig::load(Some(&path)).is_none());
+        std::fs::write(
+            &path,
+            r#"{ "url": "wss://home.example.net/", "token": "t0k3n" }"#,
+        )
+        .unwrap();
+        let loaded = RemoteKernelConfig::load(Some(&path)).unwrap();
+        assert_eq!(
+            (loaded.url.as_str(), loaded.token.as_str()),
+            ("wss://home.example.net/", "t0k3n")
+        );
+        assert!(loaded.ca_cert.is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_missing_ca_cert_is_an_error() {
+        let mut config = config("wss://home.example.net/");
+        config.ca_cert = Some(PathBuf::from("/nonexistent/reos-ca.pem"));
+        assert!(matches!(
+            config.tls(),
+            Err(RemoteKernelError::Certificate(_))
+        ));
+    }
+
+    #[test]
+    fn test_pairing_identity_is_presented() {
+        let dir = std::env::temp_dir().join(format!("reos-remote-pairing-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let first = pairing(&dir).unwrap();
+        assert_eq!(first.fingerprint.len(), 64);
+        assert!(first.certificate.starts_with("-----BEGIN CERTIFICATE-----"));
+        assert_eq!(pairing(&dir).unwrap().fingerprint, first.fingerprint);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.join(CLIENT_KEY_FILE))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let path = dir.join("remote-kernel.json");
+        let pin = "ab".repeat(32);
+        std::fs::write(
+            &path,
+            format!(
+                r#"{{ "url": "wss://h.example/", "token": "t", "server_cert_sha256": "{pin}" }}"#
+            ),
+        )
+        .unwrap();
+        let loaded = RemoteKernelConfig::load(Some(&path)).unwrap();
+        assert_eq!(loaded.client_cert, Some(dir.join(CLIENT_CERT_FILE)));
+        assert_eq!(loaded.client_key, Some(dir.join(CLIENT_KEY_FILE)));
+        assert!(loaded.tls().unwrap().client_auth_cert_resolver.has_certs());
+
+        let mut mismatched = config("wss://h.example/");
+        mismatched.client_cert = loaded.client_cert.clone();
+        assert!(matches!(
+            mismatched.tls(),
+            Err(RemoteKernelError::Identity(_))
+        ));
+        let mut bad_pin = config("wss://h.example/");
+        bad_pin.server_cert_sha256 = Some("not hex".to_string());
+        assert!(matches!(
+            bad_pin.tls(),
+            Err(RemoteKernelError::Certificate(_))
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
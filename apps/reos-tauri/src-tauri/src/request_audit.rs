@@ -0,0 +1,317 @@
+//! Kernel Request Audit Log
+//!
+//! Rust-side record of every `kernel_request` (time, session, method, a
+//! hash of the params, outcome, latency), independent of the kernel's own
+//! logging so a compromised kernel can't hide what it was asked to do.
+//!
+//! Lines are hash-chained: each carries `prev` (the previous line's hash)
+//! and `hash` = SHA-256 of the line without `hash`. Editing, dropping or
+//! reordering lines breaks the chain, which `verify_files` reports. The
+//! chain continues across restarts and rotation (same scheme as
+//! `auth-audit.jsonl`: `kernel-requests.jsonl` -> `.1` -> ...). Anyone able
+//! to rewrite the whole log can rebuild the chain; it is tamper-evident,
+//! not tamper-proof.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::error;
+
+use crate::audit::{open_append, rotate, rotated_path, AUDIT_KEEP_FILES, AUDIT_MAX_BYTES};
+use crate::locks::Recover;
+
+/// `prev` of the very first line
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// How a request ended
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// JSON-RPC result
+    Ok,
+    /// JSON-RPC error response (from Rust policy checks or the kernel)
+    Error,
+    /// Command failed (invalid session, method not allowed, kernel down)
+    Failed,
+}
+
+/// One request as recorded (before chaining)
+#[derive(Serialize)]
+pub struct RequestRecord {
+    /// Unix time in milliseconds
+    pub ts: u128,
+    pub session_id: String,
+    pub method: String,
+    /// SHA-256 of the params as sent by the webview
+    pub params_sha256: String,
+    pub outcome: Outcome,
+    /// JSON-RPC error code for `Outcome::Error`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<i64>,
+    pub latency_ms: u64,
+}
+
+impl RequestRecord {
+    /// Classify the result `kernel_request` is about to return
+    pub fn new(
+        session_id: &str,
+        method: &str,
+        params_sha256: String,
+        result: &Result<Value, String>,
+        latency: Duration,
+    ) -> Self {
+        let (outcome, code) = match result {
+            Ok(response) => match response.get("error") {
+                Some(error) => (Outcome::Error, error.get("code").and_then(Value::as_i64)),
+                None => (Outcome::Ok, None),
+            },
+            Err(_) => (Outcome::Failed, None),
+        };
+        Self {
+            ts: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or(0),
+            session_id: session_id.to_string(),
+            method: method.to_string(),
+            params_sha256,
+            outcome,
+            code,
+            latency_ms: u64::try_from(latency.as_millis()).unwrap_or(u64::MAX),
+        }
+    }
+}
+
+/// Hex SHA-256 of `params` in their serialized form
+pub fn params_hash(params: &Value) -> String {
+    let serialized = serde_json::to_vec(params).unwrap_or_default();
+    hex::encode(Sha256::digest(&serialized))
+}
+
+fn line_hash(body: &str) -> String {
+    hex::encode(Sha256::digest(body.as_bytes()))
+}
+
+/// Hash-chained, size-rotated JSON-lines writer
+pub struct RequestAuditLog {
+    path: Option<PathBuf>,
+    file: Option<File>,
+    /// Hash of the last line written (chain head)
+    head: String,
+}
+
+impl RequestAuditLog {
+    /// Log to `path`, continuing an existing chain; `None` disables persistence
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let head = path
+            .as_deref()
+            .and_then(last_hash)
+            .unwrap_or_else(|| GENESIS.to_string());
+        Self {
+            path,
+            file: None,
+            head,
+        }
+    }
+
+    pub fn append(&mut self, record: &RequestRecord) -> std::io::Result<()> {
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+
+        let mut entry = serde_json::to_value(record)?;
+        entry["prev"] = json!(self.head);
+        let hash = line_hash(&entry.to_string());
+        entry["hash"] = json!(hash);
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 > AUDIT_MAX_BYTES {
+            self.file = None;
+            rotate(&path)?;
+        }
+
+        if self.file.is_none() {
+            self.file = Some(open_append(&path)?);
+        }
+        let file = self.file.as_mut().expect("request audit file opened above");
+        file.write_all(&line)?;
+        file.flush()?;
+        self.head = hash;
+        Ok(())
+    }
+
+    /// Check the chain across the retained files (oldest first)
+    pub fn verify(&self) -> ChainReport {
+        let Some(path) = self.path.as_deref() else {
+            return ChainReport::default();
+        };
+        let mut files: Vec<PathBuf> = (1..=AUDIT_KEEP_FILES)
+            .rev()
+            .map(|n| rotated_path(path, n))
+            .collect();
+        files.push(path.to_path_buf());
+        verify_files(&files)
+    }
+}
+
+/// Result of walking the chain
+#[derive(Serialize, Default, Debug, PartialEq, Eq)]
+pub struct ChainReport {
+    /// Lines whose hash and link checked out
+    pub verified: usize,
+    /// First broken line, as `file:line`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub broken_at: Option<String>,
+}
+
+/// Walk `files` in order; the oldest file's first `prev` is trusted as-is
+/// since older files have rotated away
+pub fn verify_files(files: &[PathBuf]) -> ChainReport {
+    let mut report = ChainReport::default();
+    let mut expected_prev: Option<String> = None;
+    for file in files {
+        let Ok(contents) = fs::read_to_string(file) else {
+            continue;
+        };
+        for (n, line) in contents.lines().enumerate() {
+            let Some(hash) = check_line(line, expected_prev.as_deref()) else {
+                report.broken_at = Some(format!("{}:{}", file.display(), n + 1));
+                return report;
+            };
+            expected_prev = Some(hash);
+            report.verified += 1;
+        }
+    }
+    report
+}
+
+/// Recompute one line's hash; `None` if it doesn't match or doesn't link
+fn check_line(line: &str, expected_prev: Option<&str>) -> Option<String> {
+    let mut entry: Value = serde_json::from_str(line).ok()?;
+    let hash = entry.as_object_mut()?.remove("hash")?;
+    let hash = hash.as_str()?;
+    let prev = entry.get("prev")?.as_str()?;
+    if expected_prev.is_some_and(|expected| expected != prev) {
+        return None;
+    }
+    (line_hash(&entry.to_string()) == hash).then(|| hash.to_string())
+}
+
+/// Chain head left by a previous run (active file, else the newest rotated one)
+fn last_hash(path: &Path) -> Option<String> {
+    [path.to_path_buf(), rotated_path(path, 1)]
+        .iter()
+        .find_map(|file| {
+            let contents = fs::read_to_string(file).ok()?;
+            let last = contents.lines().last()?;
+            let entry: Value = serde_json::from_str(last).ok()?;
+            entry.get("hash")?.as_str().map(str::to_string)
+        })
+}
+
+/// Thread-safe request audit log
+pub struct RequestAuditState(pub Mutex<RequestAuditLog>);
+
+impl RequestAuditState {
+    pub fn open(path: Option<PathBuf>) -> Self {
+        Self(Mutex::new(RequestAuditLog::open(path)))
+    }
+
+    /// Append a record (best effort: auditing never blocks requests)
+    pub fn record(&self, record: RequestRecord) {
+        if let Err(e) = self.0.lock_or_recover().append(&record) {
+            error!(error = %e, "kernel request audit log write failed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("reos-reqaudit-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn record(method: &str, result: Result<Value, String>) -> RequestRecord {
+        let params = json!({ "q": method });
+        RequestRecord::new(
+            "abcd",
+            method,
+            params_hash(&params),
+            &result,
+            Duration::from_millis(3),
+        )
+    }
+
+    #[test]
+    fn test_outcome_classification() {
+        let ok = record("chat/respond", Ok(json!({ "result": 1 })));
+        assert_eq!((ok.outcome, ok.code), (Outcome::Ok, None));
+        let error = record("chat/respond", Ok(json!({ "error": { "code": -32403 } })));
+        assert_eq!((error.outcome, error.code), (Outcome::Error, Some(-32403)));
+        let failed = record("chat/respond", Err("Invalid session".to_string()));
+        assert_eq!(failed.outcome, Outcome::Failed);
+    }
+
+    #[test]
+    fn test_chain_verifies_and_survives_reopen() {
+        let dir = temp_dir("chain");
+        let path = dir.join("kernel-requests.jsonl");
+        let mut log = RequestAuditLog::open(Some(path.clone()));
+        log.append(&record("a", Ok(json!({})))).unwrap();
+        log.append(&record("b", Ok(json!({})))).unwrap();
+
+        // A restart continues from the last line
+        let mut log = RequestAuditLog::open(Some(path.clone()));
+        log.append(&record("c", Ok(json!({})))).unwrap();
+
+        let lines: Vec<Value> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines[0]["prev"], GENESIS);
+        assert_eq!(lines[2]["prev"], lines[1]["hash"]);
+        assert_eq!(
+            log.verify(),
+            ChainReport {
+                verified: 3,
+                broken_at: None
+            }
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let dir = temp_dir("tamper");
+        let path = dir.join("kernel-requests.jsonl");
+        let mut log = RequestAuditLog::open(Some(path.clone()));
+        for method in ["a", "b", "c"] {
+            log.append(&record(method, Ok(json!({})))).unwrap();
+        }
+        let original = fs::read_to_string(&path).unwrap();
+
+        // Edited method
+        fs::write(&path, original.replacen("\"b\"", "\"x\"", 1)).unwrap();
+        let report = log.verify();
+        assert_eq!(report.verified, 1);
+        assert!(report.broken_at.unwrap().ends_with(":2"));
+
+        // Dropped line
+        let lines: Vec<&str> = original.lines().collect();
+        fs::write(&path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+        assert!(log.verify().broken_at.unwrap().ends_with(":2"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
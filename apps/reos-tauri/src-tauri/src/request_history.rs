@@ -0,0 +1,272 @@
+//! Kernel Request History for Devtools
+//!
+//! The last `max_entries` kernel requests are kept in memory (lost on exit)
+//! for a devtools panel: method, a redacted summary of the params,
+//! duration, outcome and correlation id. `debug_request_history` returns a
+//! user's own requests; admins also see other sessions' requests and the
+//! shell's own (`initialize`, notifications).
+//!
+//! On by default in debug builds only. Configurable via
+//! `request-history.json` in the app data dir:
+//! `{ "enabled": true, "max_entries": 200 }`
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::locks::Recover;
+use crate::redact;
+use crate::request_audit::Outcome;
+
+/// Longest params summary kept (longer ones are cut)
+const MAX_PARAMS_LEN: usize = 512;
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct RequestHistoryConfig {
+    pub enabled: bool,
+    pub max_entries: usize,
+}
+
+impl RequestHistoryConfig {
+    const DEFAULT: Self = Self {
+        enabled: cfg!(debug_assertions),
+        max_entries: 200,
+    };
+
+    /// Load from `path`; missing or invalid file means defaults
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+}
+
+impl Default for RequestHistoryConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// One finished request
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    /// Increases by one per request, starting at 1
+    pub seq: u64,
+    /// Unix time in milliseconds
+    pub ts: u64,
+    pub correlation_id: String,
+    pub method: String,
+    /// Session user (`None` for the shell's own requests)
+    pub username: Option<String>,
+    /// Redacted, truncated params without the injected `__` keys
+    pub params: String,
+    pub duration_ms: u64,
+    pub outcome: Outcome,
+    /// JSON-RPC error code for `Outcome::Error`
+    pub code: Option<i64>,
+}
+
+/// What is known about a request before it is sent
+pub struct PendingRequest {
+    username: Option<String>,
+    params: String,
+}
+
+impl PendingRequest {
+    pub fn new(params: &Value) -> Self {
+        Self {
+            username: params
+                .pointer("/__session/username")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            params: summarize_params(params),
+        }
+    }
+}
+
+/// Params as shown in the history: injected `__` keys dropped, secrets
+/// redacted, cut at `MAX_PARAMS_LEN`
+fn summarize_params(params: &Value) -> String {
+    let mut params = params.clone();
+    if let Value::Object(map) = &mut params {
+        map.retain(|key, _| !key.starts_with("__"));
+    }
+    redact::value(&mut params);
+    let mut summary = params.to_string();
+    if let Some((end, _)) = summary.char_indices().nth(MAX_PARAMS_LEN) {
+        summary.truncate(end);
+        summary.push_str("...");
+    }
+    summary
+}
+
+struct History {
+    config: RequestHistoryConfig,
+    next_seq: u64,
+    entries: VecDeque<HistoryEntry>,
+}
+
+impl History {
+    fn push(
+        &mut self,
+        pending: PendingRequest,
+        correlation_id: &str,
+        method: &str,
+        duration: Duration,
+        response: Result<&Value, ()>,
+    ) {
+        if !self.config.enabled || self.config.max_entries == 0 {
+            return;
+        }
+        let (outcome, code) = match response {
+            Ok(response) => match response.get("error") {
+                Some(error) => (Outcome::Error, error.get("code").and_then(Value::as_i64)),
+                None => (Outcome::Ok, None),
+            },
+            Err(()) => (Outcome::Failed, None),
+        };
+        let entry = HistoryEntry {
+            seq: self.next_seq,
+            ts: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            correlation_id: correlation_id.to_string(),
+            method: method.to_string(),
+            username: pending.username,
+            params: pending.params,
+            duration_ms: u64::try_from(duration.as_millis()).unwrap_or(u64::MAX),
+            outcome,
+            code,
+        };
+        self.next_seq += 1;
+        while self.entries.len() >= self.config.max_entries {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Entries after `after` visible to `username` (`None` = all), oldest first
+    fn visible(&self, username: Option<&str>, after: u64) -> Vec<HistoryEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.seq > after)
+            .filter(|entry| username.is_none() || entry.username.as_deref() == username)
+            .cloned()
+            .collect()
+    }
+}
+
+static HISTORY: Mutex<History> = Mutex::new(History {
+    config: RequestHistoryConfig::DEFAULT,
+    next_seq: 1,
+    entries: VecDeque::new(),
+});
+
+/// Install the deployment setting (at startup)
+pub fn configure(config: RequestHistoryConfig) {
+    let mut history = HISTORY.lock_or_recover();
+    if !config.enabled {
+        history.entries.clear();
+    }
+    history.config = config;
+}
+
+/// Whether requests are being recorded (params are only summarized if so)
+pub fn enabled() -> bool {
+    HISTORY.lock_or_recover().config.enabled
+}
+
+/// A request ended (`Err` if it failed without a response)
+pub fn record(
+    pending: PendingRequest,
+    correlation_id: &str,
+    method: &str,
+    duration: Duration,
+    response: Result<&Value, ()>,
+) {
+    HISTORY
+        .lock_or_recover()
+        .push(pending, correlation_id, method, duration, response);
+}
+
+/// `debug_request_history` result
+#[derive(Serialize)]
+pub struct RequestHistory {
+    pub enabled: bool,
+    pub entries: Vec<HistoryEntry>,
+}
+
+/// Entries with `seq` greater than `after` that `username` may see
+/// (`None` = every entry, for admins)
+pub fn snapshot(username: Option<&str>, after: u64) -> RequestHistory {
+    let history = HISTORY.lock_or_recover();
+    RequestHistory {
+        enabled: history.config.enabled,
+        entries: history.visible(username, after),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn history(max_entries: usize) -> History {
+        History {
+            config: RequestHistoryConfig {
+                enabled: true,
+                max_entries,
+            },
+            next_seq: 1,
+            entries: VecDeque::new(),
+        }
+    }
+
+    #[test]
+    fn test_params_summary_is_redacted() {
+        let pending = PendingRequest::new(&json!({
+            "password": "hunter2",
+            "text": "hello",
+            "__session": { "username": "alice", "sig": "abc" }
+        }));
+        assert_eq!(pending.username.as_deref(), Some("alice"));
+        assert!(!pending.params.contains("hunter2"));
+        assert!(!pending.params.contains("__session"));
+        assert!(pending.params.contains("hello"));
+        let long = PendingRequest::new(&json!({ "text": "x".repeat(2 * MAX_PARAMS_LEN) }));
+        assert!(long.params.ends_with("..."));
+    }
+
+    #[test]
+    fn test_bounded_and_filtered_by_user() {
+        let mut history = history(2);
+        let ok = json!({ "result": {} });
+        let denied = json!({ "error": { "code": -32001, "message": "denied" } });
+        for (user, response) in [("alice", Ok(&ok)), ("bob", Ok(&denied)), ("alice", Err(()))] {
+            let pending = PendingRequest::new(&json!({ "__session": { "username": user } }));
+            history.push(
+                pending,
+                "c0ffee",
+                "chat/respond",
+                Duration::from_millis(5),
+                response,
+            );
+        }
+        let all = history.visible(None, 0);
+        assert_eq!(all.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(
+            (all[0].outcome, all[0].code),
+            (Outcome::Error, Some(-32001))
+        );
+
+        let alice = history.visible(Some("alice"), 0);
+        assert_eq!(alice.len(), 1);
+        assert_eq!(alice[0].outcome, Outcome::Failed);
+        assert!(history.visible(None, 3).is_empty());
+    }
+}
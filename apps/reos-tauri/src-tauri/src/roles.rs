@@ -0,0 +1,93 @@
+//! Session Roles for ReOS
+//!
+//! After login the kernel reports the user's Unix groups (it already talks
+//! to PAM/NSS), and Rust maps them to a coarse role stored on the session.
+//! The mapping is configurable via `roles.json` in the app data dir:
+//! `{ "admin_groups": ["wheel"], "user_groups": [], "fallback": "guest" }`
+//!
+//! Mapping order: any admin group -> `admin`; otherwise any user group (or
+//! no user groups configured) -> `user`; otherwise `fallback`.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Coarse authorization level carried by every session
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Guest,
+    User,
+    Admin,
+}
+
+/// Configurable group -> role mapping
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct RoleMapping {
+    /// Members of any of these groups are admins
+    pub admin_groups: Vec<String>,
+    /// Members of any of these groups are users (empty = every authenticated user)
+    pub user_groups: Vec<String>,
+    /// Role for authenticated users matching neither list
+    pub fallback: Role,
+}
+
+impl Default for RoleMapping {
+    fn default() -> Self {
+        Self {
+            admin_groups: vec!["wheel".into(), "sudo".into(), "admin".into()],
+            user_groups: Vec::new(),
+            fallback: Role::Guest,
+        }
+    }
+}
+
+impl RoleMapping {
+    /// Load the mapping from `path` (missing or invalid file = defaults)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// Map a user's groups to a role
+    pub fn role_for(&self, groups: &[String]) -> Role {
+        let member_of = |wanted: &[String]| groups.iter().any(|g| wanted.contains(g));
+        if member_of(&self.admin_groups) {
+            Role::Admin
+        } else if self.user_groups.is_empty() || member_of(&self.user_groups) {
+            Role::User
+        } else {
+            self.fallback
+        }
+    }
+}
+
+/// Role mapping state (read-only after startup)
+pub struct RoleState(pub RoleMapping);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn groups(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_default_mapping() {
+        let mapping = RoleMapping::default();
+        assert_eq!(mapping.role_for(&groups(&["users", "wheel"])), Role::Admin);
+        assert_eq!(mapping.role_for(&groups(&["users"])), Role::User);
+        assert_eq!(mapping.role_for(&[]), Role::User);
+    }
+
+    #[test]
+    fn test_restricted_user_groups_fall_back() {
+        let mapping: RoleMapping =
+            serde_json::from_str(r#"{ "user_groups": ["reos"], "fallback": "guest" }"#).unwrap();
+        assert_eq!(mapping.role_for(&groups(&["reos"])), Role::User);
+        assert_eq!(mapping.role_for(&groups(&["users"])), Role::Guest);
+        assert_eq!(mapping.role_for(&groups(&["sudo"])), Role::Admin);
+    }
+}
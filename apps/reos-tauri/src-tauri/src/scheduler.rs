@@ -0,0 +1,391 @@
+//! Fair Scheduling of Kernel Requests
+//!
+//! Frontend requests wait here for a slot at the kernel instead of racing
+//! for its lock, so one session queueing heavy calls can't starve the rest:
+//!
+//! - sessions take turns: the next free slot goes to the session after the
+//!   one served last (round-robin), each getting its oldest request that
+//!   fits
+//! - method classes cap how many of their requests hold slots at once
+//!   (e.g. one `code/map/index`); a request over its class's limit waits
+//!   while the session's other requests may go ahead
+//!
+//! `scheduler.json` in the app data dir (missing or invalid file =
+//! defaults):
+//! `{ "slots": 1, "classes": [{ "name": "index", "methods": ["code/map/index"], "max_concurrent": 1 }] }`
+//!
+//! Background requests (the shell's post-login warmup, see `warmup.rs`)
+//! only get a slot when no normal request that fits is waiting, so they
+//! fill idle time instead of delaying the user.
+//!
+//! `slots` is how many requests may be at the kernel at once. The kernel
+//! answers one at a time, so the default of 1 keeps its queue in this
+//! order; class limits matter once there are more. The shell's own calls
+//! (login, role lookup, ...) don't wait here.
+
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::locks::Recover;
+use crate::methods::method_matches;
+
+/// Kernel methods sharing one concurrency limit
+#[derive(Deserialize, Clone, Debug)]
+pub struct ClassLimit {
+    pub name: String,
+    /// Method names or `prefix*` patterns (first matching class wins)
+    pub methods: Vec<String>,
+    pub max_concurrent: usize,
+}
+
+impl ClassLimit {
+    fn new(name: &str, methods: &[&str], max_concurrent: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            methods: methods.iter().map(|m| m.to_string()).collect(),
+            max_concurrent,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    pub slots: usize,
+    pub classes: Vec<ClassLimit>,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            slots: 1,
+            classes: vec![
+                // Long-running scans and rewrites
+                ClassLimit::new(
+                    "heavy",
+                    &[
+                        "code/map/index",
+                        "compact/apply",
+                        "archive/search",
+                        "ollama/pull_model",
+                    ],
+                    1,
+                ),
+                // Each call can start an LLM or tool run
+                ClassLimit::new(
+                    "llm",
+                    &[
+                        "chat/respond",
+                        "intent/detect",
+                        "code/plan/start",
+                        "code/exec/start",
+                        "execution/start",
+                    ],
+                    1,
+                ),
+            ],
+        }
+    }
+}
+
+impl SchedulerConfig {
+    /// Load from `path` (missing or invalid file = defaults; zero limits
+    /// are raised to 1)
+    pub fn load(path: Option<&Path>) -> Self {
+        let mut config: Self = path
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        config.slots = config.slots.max(1);
+        for class in &mut config.classes {
+            class.max_concurrent = class.max_concurrent.max(1);
+        }
+        config
+    }
+
+    /// Index of `method`'s class, if it has one
+    fn class_of(&self, method: &str) -> Option<usize> {
+        self.classes
+            .iter()
+            .position(|class| class.methods.iter().any(|p| method_matches(p, method)))
+    }
+}
+
+/// How urgently a request wants a slot
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Priority {
+    /// A frontend request someone is waiting on
+    Normal,
+    /// Work done ahead of need; yields to every waiting `Normal` request
+    Background,
+}
+
+/// Whether a request of `class` may take a slot now
+fn has_room(config: &SchedulerConfig, running_by_class: &[usize], class: Option<usize>) -> bool {
+    class.is_none_or(|c| running_by_class[c] < config.classes[c].max_concurrent)
+}
+
+struct Waiter {
+    ticket: u64,
+    class: Option<usize>,
+    priority: Priority,
+}
+
+/// Waiting requests and slot use (no locking or blocking: see `Scheduler`)
+struct Queue {
+    config: SchedulerConfig,
+    next_ticket: u64,
+    /// Sessions with waiting requests, next to be served first
+    rotation: VecDeque<String>,
+    waiting: HashMap<String, VecDeque<Waiter>>,
+    running: usize,
+    running_by_class: Vec<usize>,
+    /// Tickets given a slot whose waiter hasn't woken yet
+    granted: Vec<u64>,
+}
+
+impl Queue {
+    fn new(config: SchedulerConfig) -> Self {
+        let classes = config.classes.len();
+        Self {
+            config,
+            next_ticket: 0,
+            rotation: VecDeque::new(),
+            waiting: HashMap::new(),
+            running: 0,
+            running_by_class: vec![0; classes],
+            granted: Vec::new(),
+        }
+    }
+
+    /// Queue a request; its ticket
+    fn push(&mut self, session: &str, method: &str, priority: Priority) -> u64 {
+        let ticket = self.next_ticket;
+        self.next_ticket += 1;
+        let class = self.config.class_of(method);
+        let queue = self.waiting.entry(session.to_string()).or_default();
+        if queue.is_empty() {
+            self.rotation.push_back(session.to_string());
+        }
+        queue.push_back(Waiter {
+            ticket,
+            class,
+            priority,
+        });
+        ticket
+    }
+
+    /// Give free slots to waiting requests, taking sessions in turn
+    fn dispatch(&mut self) {
+        while self.running < self.config.slots {
+            let (config, running_by_class) = (&self.config, &self.running_by_class);
+            let has_slot = |waiter: &Waiter| has_room(config, running_by_class, waiter.class);
+            let normal_waiting = self
+                .waiting
+                .values()
+                .flatten()
+                .any(|waiter| waiter.priority == Priority::Normal && has_slot(waiter));
+            let fits = |waiter: &Waiter| {
+                has_slot(waiter) && !(normal_waiting && waiter.priority == Priority::Background)
+            };
+            let Some(turn) = self
+                .rotation
+                .iter()
+                .position(|session| self.waiting[session].iter().any(fits))
+            else {
+                break;
+            };
+            let session = self.rotation.remove(turn).expect("position is in range");
+            let queue = self
+                .waiting
+                .get_mut(&session)
+                .expect("rotation has a queue");
+            let index = queue
+                .iter()
+                .position(fits)
+                .expect("session has a fitting request");
+            let waiter = queue.remove(index).expect("index is in range");
+            if queue.is_empty() {
+                self.waiting.remove(&session);
+            } else {
+                self.rotation.push_back(session);
+            }
+            self.running += 1;
+            if let Some(class) = waiter.class {
+                self.running_by_class[class] += 1;
+            }
+            self.granted.push(waiter.ticket);
+        }
+    }
+
+    /// Whether `ticket` was given a slot (consuming the grant)
+    fn take_grant(&mut self, ticket: u64) -> bool {
+        match self.granted.iter().position(|&t| t == ticket) {
+            Some(index) => {
+                self.granted.swap_remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn release(&mut self, class: Option<usize>) {
+        self.running -= 1;
+        if let Some(class) = class {
+            self.running_by_class[class] -= 1;
+        }
+        self.dispatch();
+    }
+}
+
+pub struct Scheduler {
+    queue: Mutex<Queue>,
+    changed: Condvar,
+}
+
+impl Scheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        Self {
+            queue: Mutex::new(Queue::new(config)),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Wait (blocking) until `session` may send `method` to the kernel; the
+    /// slot is held until the permit is dropped
+    pub fn acquire(&self, session: &str, method: &str) -> Permit<'_> {
+        self.acquire_with(session, method, Priority::Normal)
+    }
+
+    /// `acquire` at `priority`
+    pub fn acquire_with(&self, session: &str, method: &str, priority: Priority) -> Permit<'_> {
+        let mut queue = self.queue.lock_or_recover();
+        let ticket = queue.push(session, method, priority);
+        let class = queue.config.class_of(method);
+        queue.dispatch();
+        if !queue.take_grant(ticket) {
+            let limit = class.map(|c| queue.config.classes[c].name.as_str());
+            tracing::debug!(session, method, class = limit, "waiting for a kernel slot");
+            while !queue.take_grant(ticket) {
+                queue = self
+                    .changed
+                    .wait(queue)
+                    .unwrap_or_else(std::sync::PoisonError::into_inner);
+            }
+        }
+        Permit {
+            scheduler: self,
+            class,
+        }
+    }
+}
+
+/// A kernel slot, given back on drop
+pub struct Permit<'a> {
+    scheduler: &'a Scheduler,
+    class: Option<usize>,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.scheduler.queue.lock_or_recover().release(self.class);
+        self.scheduler.changed.notify_all();
+    }
+}
+
+/// Shared with the blocking threads that wait for slots
+pub struct SchedulerState(pub Arc<Scheduler>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(slots: usize) -> SchedulerConfig {
+        SchedulerConfig {
+            slots,
+            classes: vec![ClassLimit::new("index", &["index/*"], 1)],
+        }
+    }
+
+    /// Tickets granted by one dispatch, in order
+    fn dispatched(queue: &mut Queue) -> Vec<u64> {
+        queue.dispatch();
+        std::mem::take(&mut queue.granted)
+    }
+
+    #[test]
+    fn test_sessions_take_turns() {
+        let mut queue = Queue::new(config(1));
+        let a1 = queue.push("a", "files/read", Priority::Normal);
+        let a2 = queue.push("a", "files/read", Priority::Normal);
+        let a3 = queue.push("a", "files/read", Priority::Normal);
+        let b1 = queue.push("b", "files/read", Priority::Normal);
+        let mut order = dispatched(&mut queue);
+        for _ in 0..3 {
+            queue.release(None);
+            order.append(&mut queue.granted);
+        }
+        assert_eq!(order, vec![a1, b1, a2, a3]);
+    }
+
+    #[test]
+    fn test_class_limit_lets_other_requests_past() {
+        let mut queue = Queue::new(config(4));
+        let first = queue.push("a", "index/rebuild", Priority::Normal);
+        let second = queue.push("b", "index/rebuild", Priority::Normal);
+        let read = queue.push("b", "files/read", Priority::Normal);
+        assert_eq!(dispatched(&mut queue), vec![first, read]);
+        queue.release(Some(0));
+        assert_eq!(std::mem::take(&mut queue.granted), vec![second]);
+    }
+
+    #[test]
+    fn test_background_yields_to_normal_requests() {
+        let mut queue = Queue::new(config(1));
+        let warm1 = queue.push("a", "files/read", Priority::Background);
+        let warm2 = queue.push("a", "files/read", Priority::Background);
+        assert_eq!(dispatched(&mut queue), vec![warm1]);
+        let user = queue.push("b", "files/read", Priority::Normal);
+        queue.release(None);
+        assert_eq!(std::mem::take(&mut queue.granted), vec![user]);
+        queue.release(None);
+        assert_eq!(std::mem::take(&mut queue.granted), vec![warm2]);
+    }
+
+    #[test]
+    fn test_permits_block_until_released() {
+        let scheduler = Arc::new(Scheduler::new(config(1)));
+        let held = scheduler.acquire("a", "index/rebuild");
+        let waiting = {
+            let scheduler = scheduler.clone();
+            std::thread::spawn(move || {
+                let _permit = scheduler.acquire("b", "files/read");
+            })
+        };
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!waiting.is_finished());
+        drop(held);
+        waiting.join().unwrap();
+        assert_eq!(scheduler.queue.lock_or_recover().running, 0);
+    }
+
+    #[test]
+    fn test_zero_limits_raised() {
+        let dir = std::env::temp_dir().join(format!("reos-scheduler-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("scheduler.json");
+        std::fs::write(
+            &path,
+            r#"{ "slots": 0, "classes": [{ "name": "x", "methods": ["x/*"], "max_concurrent": 0 }] }"#,
+        )
+        .unwrap();
+        let config = SchedulerConfig::load(Some(&path));
+        assert_eq!(config.slots, 1);
+        assert_eq!(config.classes[0].max_concurrent, 1);
+        assert_eq!(config.class_of("x/y"), Some(0));
+        assert_eq!(SchedulerConfig::load(None).slots, 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
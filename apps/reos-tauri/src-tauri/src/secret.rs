@@ -0,0 +1,94 @@
+//! Zeroizing Secret Wrappers
+//!
+//! Passwords, PINs, and session tokens are wrapped so their heap buffers
+//! are overwritten when dropped instead of lingering in freed memory.
+//! `Debug` never prints the contents.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+use zeroize::{Zeroize, Zeroizing};
+
+/// A string secret that is wiped from memory on drop
+#[derive(Clone)]
+pub struct SecretString(Zeroizing<String>);
+
+impl SecretString {
+    pub fn new(value: String) -> Self {
+        Self(Zeroizing::new(value))
+    }
+
+    /// Borrow the plaintext (keep the borrow short-lived)
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString([REDACTED])")
+    }
+}
+
+// Commands take secrets directly as arguments; the deserialized String is
+// moved (not copied) into the wrapper.
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+// Only serialized on purpose (e.g. into the encrypted session vault)
+impl Serialize for SecretString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.expose())
+    }
+}
+
+/// Compare two byte strings without an early exit on the first mismatch
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Overwrite every string (not object keys) inside a JSON value
+pub fn zeroize_value(value: &mut Value) {
+    match value {
+        Value::String(s) => s.zeroize(),
+        Value::Array(items) => items.iter_mut().for_each(zeroize_value),
+        Value::Object(map) => map.values_mut().for_each(zeroize_value),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_debug_is_redacted() {
+        let secret = SecretString::new("hunter2".to_string());
+        assert_eq!(format!("{secret:?}"), "SecretString([REDACTED])");
+        assert_eq!(secret.expose(), "hunter2");
+    }
+
+    #[test]
+    fn test_zeroize_value_clears_nested_strings() {
+        let mut value =
+            json!({ "password": "hunter2", "nested": ["a", { "pin": "1234" }], "n": 1 });
+        zeroize_value(&mut value);
+        assert_eq!(
+            value,
+            json!({ "password": "", "nested": ["", { "pin": "" }], "n": 1 })
+        );
+    }
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"abcd", b"abcd"));
+        assert!(!constant_time_eq(b"abcd", b"abce"));
+        assert!(!constant_time_eq(b"abcd", b"abc"));
+    }
+}
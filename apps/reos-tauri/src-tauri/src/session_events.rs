@@ -0,0 +1,71 @@
+//! Session Lifecycle Events
+//!
+//! Windows (and the tray) learn about auth state changes from Tauri events
+//! instead of polling `auth_validate`. Payloads identify the session by its
+//! truncated id only, never by token.
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+/// Session state changes broadcast to every window
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionEvent {
+    Created,
+    Refreshed,
+    Locked,
+    Unlocked,
+    Expired,
+    Revoked,
+}
+
+impl SessionEvent {
+    pub const ALL: [SessionEvent; 6] = [
+        SessionEvent::Created,
+        SessionEvent::Refreshed,
+        SessionEvent::Locked,
+        SessionEvent::Unlocked,
+        SessionEvent::Expired,
+        SessionEvent::Revoked,
+    ];
+
+    /// Tauri event name
+    pub fn name(self) -> &'static str {
+        match self {
+            SessionEvent::Created => "session-created",
+            SessionEvent::Refreshed => "session-refreshed",
+            SessionEvent::Locked => "session-locked",
+            SessionEvent::Unlocked => "session-unlocked",
+            SessionEvent::Expired => "session-expired",
+            SessionEvent::Revoked => "session-revoked",
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct SessionEventPayload<'a> {
+    session_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    username: Option<&'a str>,
+    /// What caused the change (`logout`, `suspend`, `pin`, ...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<&'a str>,
+}
+
+/// Broadcast `event` for a session to all windows (best effort)
+pub fn emit(
+    app: &AppHandle,
+    event: SessionEvent,
+    session_id: &str,
+    username: Option<&str>,
+    reason: Option<&str>,
+) {
+    let payload = SessionEventPayload {
+        session_id,
+        username,
+        reason,
+    };
+    if let Err(e) = app.emit(event.name(), payload) {
+        warn!(error = %e, "failed to emit {}", event.name());
+    }
+}
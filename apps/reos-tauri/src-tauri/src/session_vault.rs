@@ -0,0 +1,332 @@
+//! Encrypted Session Persistence for ReOS
+//!
+//! Sessions normally live only in memory, so restarting the app forces a
+//! fresh PAM login. When enabled, the session store is written to
+//! `sessions.vault` in the app data dir, encrypted with ChaCha20-Poly1305
+//! under a random key kept in the OS keyring. Like the in-memory store it
+//! holds token hashes, not tokens. On startup each restored session is
+//! re-validated with the kernel (by token hash) before it is usable.
+//!
+//! Opt-in via `session-persistence.json`: `{ "enabled": true }`
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+use crate::auth::{Activity, ClientInfo, Session};
+use crate::profile;
+use crate::roles::Role;
+use crate::storage::write_private_file;
+
+/// Keyring service holding the vault key
+const KEYRING_SERVICE: &str = "dev.reos.app.sessions";
+/// Keyring account for the vault key (scoped to the profile)
+const KEYRING_ACCOUNT: &str = "vault-key";
+/// ChaCha20-Poly1305 nonce length
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug, Error)]
+pub enum VaultError {
+    #[error("session vault key unavailable: {0}")]
+    Key(String),
+    #[error("session vault is corrupt or was encrypted with another key")]
+    Decrypt,
+    #[error("failed to access session vault: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to encode session vault: {0}")]
+    Encode(#[from] serde_json::Error),
+}
+
+/// Deployment policy for session persistence
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct PersistencePolicy {
+    pub enabled: bool,
+}
+
+impl PersistencePolicy {
+    /// Load the policy from `path` (missing or invalid file = disabled)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// On-disk form of a `Session` (instants stored as unix seconds)
+#[derive(Serialize, Deserialize)]
+pub struct PersistedSession {
+    /// Hex SHA-256 of the token (the token itself is never kept)
+    token_hash: String,
+    session_id: String,
+    username: String,
+    role: Role,
+    client: ClientInfo,
+    quick_unlock: bool,
+    locked: bool,
+    /// When a locked session was locked (absent in older vaults)
+    #[serde(default)]
+    locked_at: Option<u64>,
+    created_at: u64,
+    last_activity: u64,
+    authenticated_at: u64,
+}
+
+impl PersistedSession {
+    pub fn from_session(session: &Session) -> Self {
+        Self {
+            token_hash: hex::encode(session.token_hash),
+            session_id: session.session_id.clone(),
+            username: session.username.clone(),
+            role: session.role,
+            client: session.client.clone(),
+            quick_unlock: session.quick_unlock,
+            locked: session.locked_at.is_some(),
+            locked_at: session.locked_at.map(to_unix),
+            created_at: to_unix(session.created_at),
+            last_activity: to_unix(session.last_activity.get()),
+            authenticated_at: to_unix(session.authenticated_at),
+        }
+    }
+
+    /// Hex token hash, re-validated with the kernel before restoring
+    pub fn token_hash(&self) -> &str {
+        &self.token_hash
+    }
+
+    /// Rebuild the session (`None` if the stored hash is malformed)
+    ///
+    /// A locked session keeps its original lock time so the PIN grace
+    /// window doesn't restart; one whose lock time is unknown (older vault)
+    /// or predates this boot isn't restored, so the user logs in again.
+    pub fn into_session(self) -> Option<Session> {
+        let token_hash = hex::decode(&self.token_hash).ok()?.try_into().ok()?;
+        let locked_at = match (self.locked, self.locked_at) {
+            (false, _) => None,
+            (true, None) => return None,
+            (true, Some(secs)) => Some(checked_from_unix(secs)?),
+        };
+        Some(Session {
+            token_hash,
+            session_id: self.session_id,
+            username: self.username,
+            created_at: from_unix(self.created_at),
+            authenticated_at: from_unix(self.authenticated_at),
+            last_activity: Activity::new(from_unix(self.last_activity)),
+            quick_unlock: self.quick_unlock,
+            locked_at,
+            role: self.role,
+            client: self.client,
+            // Consent is re-asked after a restart
+            consents: Default::default(),
+        })
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn to_unix(instant: Instant) -> u64 {
+    unix_now().saturating_sub(instant.elapsed().as_secs())
+}
+
+fn from_unix(secs: u64) -> Instant {
+    checked_from_unix(secs).unwrap_or_else(Instant::now)
+}
+
+/// `secs` as an `Instant`, if the monotonic clock reaches back that far
+fn checked_from_unix(secs: u64) -> Option<Instant> {
+    let age = Duration::from_secs(unix_now().saturating_sub(secs));
+    Instant::now().checked_sub(age)
+}
+
+/// Encrypted file holding persisted sessions
+pub struct SessionVault {
+    path: PathBuf,
+    key: Option<Zeroizing<[u8; 32]>>,
+}
+
+impl SessionVault {
+    /// Vault at `path`, keyed from the OS keyring
+    pub fn open(path: PathBuf) -> Self {
+        Self { path, key: None }
+    }
+
+    fn key(&mut self) -> Result<&Key, VaultError> {
+        if self.key.is_none() {
+            self.key = Some(keyring_key()?);
+        }
+        let key = self.key.as_ref().expect("vault key loaded above");
+        Ok(Key::from_slice(key.as_slice()))
+    }
+
+    /// Encrypt and write `sessions`, replacing the previous contents
+    pub fn save(&mut self, sessions: &[PersistedSession]) -> Result<(), VaultError> {
+        let plaintext = Zeroizing::new(serde_json::to_vec(sessions)?);
+        let cipher = ChaCha20Poly1305::new(self.key()?);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| VaultError::Decrypt)?;
+
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&ciphertext);
+        write_private_file(&self.path, &data)?;
+        Ok(())
+    }
+
+    /// Read and decrypt persisted sessions (missing file = none)
+    pub fn load(&mut self) -> Result<Vec<PersistedSession>, VaultError> {
+        let data = match std::fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        if data.len() < NONCE_LEN {
+            return Err(VaultError::Decrypt);
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let cipher = ChaCha20Poly1305::new(self.key()?);
+        let plaintext = Zeroizing::new(
+            cipher
+                .decrypt(Nonce::from_slice(nonce), ciphertext)
+                .map_err(|_| VaultError::Decrypt)?,
+        );
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+}
+
+/// Thread-safe vault (`None` when persistence is disabled)
+pub struct SessionVaultState(pub Mutex<Option<SessionVault>>);
+
+impl SessionVaultState {
+    pub fn load(policy: &PersistencePolicy, path: Option<PathBuf>) -> Self {
+        let vault = path.filter(|_| policy.enabled).map(SessionVault::open);
+        Self(Mutex::new(vault))
+    }
+}
+
+/// Fetch the vault key from the keyring, creating it on first use
+fn keyring_key() -> Result<Zeroizing<[u8; 32]>, VaultError> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, &profile::scope_id(KEYRING_ACCOUNT))
+        .map_err(|e| VaultError::Key(e.to_string()))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+
+    match entry.get_password() {
+        Ok(stored) => {
+            let stored = Zeroizing::new(stored);
+            let bytes = Zeroizing::new(
+                hex::decode(stored.as_str()).map_err(|e| VaultError::Key(e.to_string()))?,
+            );
+            if bytes.len() != key.len() {
+                return Err(VaultError::Key("stored key has wrong length".to_string()));
+            }
+            key.copy_from_slice(&bytes);
+        }
+        Err(keyring::Error::NoEntry) => {
+            rand::rngs::OsRng.fill_bytes(key.as_mut_slice());
+            let encoded = Zeroizing::new(hex::encode(key.as_slice()));
+            entry
+                .set_password(&encoded)
+                .map_err(|e| VaultError::Key(e.to_string()))?;
+        }
+        Err(e) => return Err(VaultError::Key(e.to_string())),
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth;
+
+    fn vault(name: &str) -> SessionVault {
+        let path = std::env::temp_dir().join(format!("reos-vault-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        SessionVault {
+            path,
+            key: Some(Zeroizing::new([7u8; 32])),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut vault = vault("roundtrip");
+        let mut session = auth::create_session(
+            "tok".to_string(),
+            "alice".to_string(),
+            ClientInfo::default(),
+        );
+        session.lock();
+        vault
+            .save(&[PersistedSession::from_session(&session)])
+            .unwrap();
+
+        let raw = std::fs::read(&vault.path).unwrap();
+        assert!(!raw.windows(5).any(|w| w == b"alice"));
+
+        let restored: Vec<Session> = vault
+            .load()
+            .unwrap()
+            .into_iter()
+            .filter_map(PersistedSession::into_session)
+            .collect();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].token_hash, auth::hash_token("tok"));
+        assert!(restored[0].locked_at.is_some());
+        let _ = std::fs::remove_file(&vault.path);
+    }
+
+    #[test]
+    fn test_restored_lock_keeps_its_time() {
+        let mut session = auth::create_session(
+            "tok".to_string(),
+            "alice".to_string(),
+            ClientInfo::default(),
+        );
+        session.lock();
+        let mut persisted = PersistedSession::from_session(&session);
+        persisted.locked_at = Some(unix_now() - 30);
+        let restored = persisted.into_session().unwrap();
+        assert!(restored.locked_at.unwrap().elapsed() >= Duration::from_secs(30));
+
+        // Older vaults didn't record when: no grace window to resume
+        let mut persisted = PersistedSession::from_session(&session);
+        persisted.locked_at = None;
+        assert!(persisted.into_session().is_none());
+    }
+
+    #[test]
+    fn test_token_hash_matches_kernel() {
+        // `auth/validate` with `session_token_sha256` compares this against
+        // hashlib's hexdigest (pinned in tests/test_auth_kernel.py as well)
+        let session = auth::create_session(
+            "abc".to_string(),
+            "alice".to_string(),
+            ClientInfo::default(),
+        );
+        assert_eq!(
+            PersistedSession::from_session(&session).token_hash(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_wrong_key_rejected() {
+        let mut vault = vault("wrongkey");
+        vault.save(&[]).unwrap();
+        vault.key = Some(Zeroizing::new([8u8; 32]));
+        assert!(matches!(vault.load(), Err(VaultError::Decrypt)));
+        let _ = std::fs::remove_file(&vault.path);
+    }
+}
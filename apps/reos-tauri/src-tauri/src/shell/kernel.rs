@@ -0,0 +1,78 @@
+//! Kernel Calls
+//!
+//! The shared kernel handle and the requests the shell makes through it.
+
+use crate::commands::kernel::ensure_kernel;
+use reos_tauri_lib::config::ConfigState;
+use reos_tauri_lib::kernel::SharedKernel;
+use reos_tauri_lib::kernel_lifecycle::StartTrigger;
+use reos_tauri_lib::scheduler::{Priority, SchedulerState};
+use reos_tauri_lib::{kernel, redact};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+/// The kernel process (started on first use), as Tauri state
+pub struct KernelState(pub SharedKernel);
+
+/// Send a request to the kernel on a blocking thread, starting it if needed
+pub async fn call_kernel(
+    state: &KernelState,
+    method: &str,
+    params: Value,
+) -> Result<Value, String> {
+    let kernel = state.0.clone();
+    let method = method.to_string();
+    tauri::async_runtime::spawn_blocking(move || kernel::request_shared(&kernel, &method, params))
+        .await
+        .map_err(|e| format!("kernel request join error: {e}"))?
+        .map_err(|e| redact::text(&e))
+}
+
+/// `call_kernel` for frontend requests (and warmup): waits for the
+/// session's turn at the kernel (see `scheduler.rs`)
+pub async fn call_kernel_scheduled(
+    app: &AppHandle,
+    session_id: String,
+    method: &str,
+    params: Value,
+    priority: Priority,
+) -> Result<Value, String> {
+    let kernel = app.state::<KernelState>().0.clone();
+    let scheduler = app.state::<SchedulerState>().0.clone();
+    let method = method.to_string();
+    tauri::async_runtime::spawn_blocking(move || {
+        let _slot = scheduler.acquire_with(&session_id, &method, priority);
+        kernel::request_shared(&kernel, &method, params)
+    })
+    .await
+    .map_err(|e| format!("kernel request join error: {e}"))?
+    .map_err(|e| redact::text(&e))
+}
+
+/// Start the kernel ahead of its first request if the start policy asks
+/// for it at `trigger` (see `kernel_lifecycle.rs`)
+pub async fn prepare_kernel(app: &AppHandle, trigger: StartTrigger) -> Result<(), String> {
+    let policy = app.state::<ConfigState>().config().kernel.start;
+    if !policy.starts_at(trigger) {
+        return Ok(());
+    }
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        ensure_kernel(&handle, &handle.state::<KernelState>())
+    })
+    .await
+    .map_err(|e| format!("kernel start join error: {e}"))?
+    .inspect_err(|e| warn!(error = %e, trigger = trigger.as_str(), "kernel start failed"))
+}
+
+/// Send a Rust-owned `method` to the kernel if it is running (without
+/// starting one just for it)
+pub fn notify_kernel(app: &AppHandle, method: &'static str, params: Value) {
+    let kernel = app.state::<KernelState>().0.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Some(proc) = kernel::lock_shared(&kernel).as_mut() {
+            let _ = proc.request(method, params);
+        }
+    });
+}
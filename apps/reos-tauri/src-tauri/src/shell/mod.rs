@@ -0,0 +1,10 @@
+//! Shell State and Helpers
+//!
+//! What `main.rs` and the commands share: the kernel handle and the calls
+//! made through it, session bookkeeping, window helpers and the tray
+//! status.
+
+pub mod kernel;
+pub mod sessions;
+pub mod tray;
+pub mod window;
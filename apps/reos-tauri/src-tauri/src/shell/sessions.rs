@@ -0,0 +1,406 @@
+//! Session Bookkeeping
+//!
+//! Registering, sweeping, persisting, restoring and locking sessions, and
+//! the role checks commands run on a session token.
+
+use crate::commands::kernel::{prune_kernel_output_subscribers, start_warmup};
+use crate::shell::kernel::{call_kernel, prepare_kernel, KernelState};
+use crate::shell::tray::refresh_tray;
+use reos_tauri_lib::audit::{AuditEntry, AuditEvent, AuditState};
+use reos_tauri_lib::auth::{
+    AuthError, AuthResult, AuthState, ClientInfo, SessionInfo, SessionStore,
+};
+use reos_tauri_lib::biometric::BiometricState;
+use reos_tauri_lib::kernel::rpc_result;
+use reos_tauri_lib::kernel_lifecycle::StartTrigger;
+use reos_tauri_lib::locks::Recover;
+use reos_tauri_lib::notifications::{Category, Notification, NotificationState};
+use reos_tauri_lib::pin::PinState;
+use reos_tauri_lib::rate_limit::RequestRateLimitState;
+use reos_tauri_lib::roles::{Role, RoleState};
+use reos_tauri_lib::secret::SecretString;
+use reos_tauri_lib::session_events::SessionEvent;
+use reos_tauri_lib::session_vault::{PersistedSession, SessionVaultState};
+use reos_tauri_lib::step_up::StepUpState;
+use reos_tauri_lib::system_lock::LockReason;
+use reos_tauri_lib::window_nonce::{WindowNonceState, NONCE_ROTATED_EVENT};
+use reos_tauri_lib::{auth, notifications, session_events};
+use serde_json::json;
+use std::panic::AssertUnwindSafe;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, Window};
+use tracing::warn;
+
+/// How often idle sessions are swept (and their expiry audited)
+const SESSION_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Notify this long before an active session idles out
+const SESSION_EXPIRY_WARNING: Duration = Duration::from_secs(2 * 60);
+
+/// Minimum time a failed session check takes, so unknown, expired and
+/// locked tokens can't be told apart by response time
+const AUTH_FAILURE_FLOOR: Duration = Duration::from_millis(50);
+
+/// Pad a failed session check that began at `started` to `AUTH_FAILURE_FLOOR`
+pub async fn pad_auth_failure(started: Instant) {
+    if let Some(rest) = AUTH_FAILURE_FLOOR.checked_sub(started.elapsed()) {
+        tokio::time::sleep(rest).await;
+    }
+}
+
+/// Whether `username`'s idle sessions should lock (PIN or biometric resume)
+pub fn quick_unlock_available(app: &AppHandle, username: &str) -> Result<bool, String> {
+    let has_pin = app
+        .state::<PinState>()
+        .0
+        .lock_or_recover()
+        .has_pin(username);
+    let biometric = app
+        .state::<BiometricState>()
+        .0
+        .lock_or_recover()
+        .is_enabled(username);
+    Ok(has_pin || biometric)
+}
+
+/// Ask the kernel for `username`'s Unix groups and map them to a role
+///
+/// Falls back to `Role::User` (with a warning) if the kernel can't answer,
+/// so a failed lookup never grants admin.
+async fn resolve_role(app: &AppHandle, username: &str) -> Role {
+    let response = call_kernel(
+        &app.state::<KernelState>(),
+        "auth/groups",
+        json!({ "username": username }),
+    )
+    .await;
+
+    #[derive(serde::Deserialize)]
+    struct Groups {
+        groups: Vec<String>,
+    }
+    match response.and_then(rpc_result::<Groups>) {
+        Ok(Groups { groups }) => app.state::<RoleState>().0.role_for(&groups),
+        Err(e) => {
+            warn!(username, error = %e, "failed to resolve the role; using the user role");
+            Role::User
+        }
+    }
+}
+
+/// Client metadata for a login from `window`
+pub fn client_info(app: &AppHandle, window: &Window) -> ClientInfo {
+    ClientInfo::new(window.label(), app.package_info().version.to_string())
+}
+
+/// Store a freshly authenticated session, enabling quick unlock if the user set it up
+pub async fn register_session(
+    app: &AppHandle,
+    token: String,
+    username: String,
+    client: ClientInfo,
+) -> Result<(), String> {
+    let role = resolve_role(app, &username).await;
+    let quick_unlock = quick_unlock_available(app, &username)?;
+
+    let mut session = auth::create_session(token.clone(), username, client);
+    session.quick_unlock = quick_unlock;
+    session.role = role;
+
+    let info = session.info();
+    let auth_state = app.state::<AuthState>();
+    let expired = {
+        let mut store = auth_state.store();
+        let expired = store.cleanup_expired();
+        store.insert(session);
+        expired
+    };
+    audit_expired(app, expired);
+    session_events::emit(
+        app,
+        SessionEvent::Created,
+        &info.session_id,
+        Some(&info.username),
+        None,
+    );
+    start_warmup(app, token);
+    Ok(())
+}
+
+/// Record sessions removed by `cleanup_expired` in the audit log and tell windows
+fn audit_expired(app: &AppHandle, expired: Vec<SessionInfo>) {
+    let audit = app.state::<AuditState>();
+    for info in expired {
+        audit.record(
+            AuditEntry::new(AuditEvent::SessionExpired)
+                .username(&info.username)
+                .session_id(&info.session_id),
+        );
+        session_events::emit(
+            app,
+            SessionEvent::Expired,
+            &info.session_id,
+            Some(&info.username),
+            None,
+        );
+    }
+}
+
+/// Record and broadcast sessions removed for `reason` (password change, ...)
+pub fn revoke_sessions(app: &AppHandle, revoked: Vec<SessionInfo>, reason: &str) {
+    for info in revoked {
+        session_events::emit(
+            app,
+            SessionEvent::Revoked,
+            &info.session_id,
+            Some(&info.username),
+            Some(reason),
+        );
+    }
+}
+
+/// Periodically purge expired sessions so expiries are audited promptly
+///
+/// A panicking pass is logged by the panic hook and the loop carries on.
+pub fn spawn_session_sweeper(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(SESSION_SWEEP_INTERVAL);
+        let _ = std::panic::catch_unwind(AssertUnwindSafe(|| sweep(&app)));
+    });
+}
+
+/// One pass of the session sweeper
+fn sweep(app: &AppHandle) {
+    let expired = app.state::<AuthState>().store().cleanup_expired();
+    audit_expired(app, expired);
+    persist_sessions(app);
+    app.state::<RequestRateLimitState>()
+        .0
+        .lock_or_recover()
+        .prune();
+    let rotated = app
+        .state::<WindowNonceState>()
+        .0
+        .lock_or_recover()
+        .rotate_due();
+    for (label, nonce) in rotated {
+        if let Err(e) = app.emit_to(label.as_str(), NONCE_ROTATED_EVENT, nonce) {
+            warn!(error = %e, "failed to emit {NONCE_ROTATED_EVENT}");
+        }
+    }
+    warn_expiring_sessions(app);
+    prune_kernel_output_subscribers(app);
+    // Also picks up a kernel that exited on its own
+    refresh_tray(app);
+}
+
+/// Notify once per idle stretch before an active session idles out
+fn warn_expiring_sessions(app: &AppHandle) {
+    let Some(notifications) = app.try_state::<NotificationState>() else {
+        return;
+    };
+    let auth_state = app.state::<AuthState>();
+    let expiring: Vec<(String, bool)> = {
+        let store = auth_state.read();
+        notifications.retain_sessions(
+            &store
+                .live_sessions()
+                .map(|s| s.session_id.clone())
+                .collect(),
+        );
+        store
+            .live_sessions()
+            .filter(|s| {
+                s.idle_remaining()
+                    .is_some_and(|left| left <= SESSION_EXPIRY_WARNING)
+            })
+            .filter(|s| notifications.should_warn_expiry(&s.session_id, s.last_activity.get()))
+            .map(|s| (s.username.clone(), s.quick_unlock))
+            .collect()
+    };
+    for (username, quick_unlock) in expiring {
+        let outcome = if quick_unlock { "lock" } else { "end" };
+        notifications::notify(
+            app,
+            Notification::new(
+                Category::SessionExpiry,
+                "Session expiring",
+                format!(
+                    "{username}'s session will {outcome} in about {} minutes without activity",
+                    SESSION_EXPIRY_WARNING.as_secs() / 60
+                ),
+            ),
+        );
+    }
+}
+
+/// Write live sessions to the encrypted vault (no-op unless persistence is enabled)
+pub fn persist_sessions(app: &AppHandle) {
+    let vault_state = app.state::<SessionVaultState>();
+    let mut vault = vault_state.0.lock_or_recover();
+    let Some(vault) = vault.as_mut() else {
+        return;
+    };
+    let sessions: Vec<PersistedSession> = app
+        .state::<AuthState>()
+        .read()
+        .live_sessions()
+        .map(PersistedSession::from_session)
+        .collect();
+    if let Err(e) = vault.save(&sessions) {
+        warn!(error = %e, "failed to persist sessions");
+    }
+}
+
+/// Reload persisted sessions, keeping only those the kernel still accepts
+pub async fn restore_sessions(app: AppHandle) {
+    let persisted = {
+        let vault_state = app.state::<SessionVaultState>();
+        let mut vault = vault_state.0.lock_or_recover();
+        match vault.as_mut().map(|v| v.load()) {
+            Some(Ok(sessions)) => sessions,
+            Some(Err(e)) => {
+                warn!(error = %e, "failed to restore sessions");
+                return;
+            }
+            None => return,
+        }
+    };
+    if persisted.is_empty() {
+        return;
+    }
+    // Each validation would start the kernel anyway; this way it shows
+    // progress
+    let _ = prepare_kernel(&app, StartTrigger::Login).await;
+
+    #[derive(serde::Deserialize)]
+    struct Validity {
+        valid: bool,
+    }
+    for persisted in persisted {
+        let response = call_kernel(
+            &app.state::<KernelState>(),
+            "auth/validate",
+            json!({ "session_token_sha256": persisted.token_hash() }),
+        )
+        .await;
+        if !matches!(
+            response.and_then(rpc_result::<Validity>),
+            Ok(Validity { valid: true })
+        ) {
+            continue;
+        }
+
+        let Some(mut session) = persisted.into_session() else {
+            continue;
+        };
+        // Group membership may have changed while the app was closed
+        if session.role != Role::Guest {
+            session.role = resolve_role(&app, &session.username).await;
+        }
+        let info = session.info();
+        app.state::<AuthState>().store().insert(session);
+        app.state::<AuditState>().record(
+            AuditEntry::new(AuditEvent::SessionRestored)
+                .username(&info.username)
+                .session_id(&info.session_id),
+        );
+        session_events::emit(
+            &app,
+            SessionEvent::Created,
+            &info.session_id,
+            Some(&info.username),
+            Some("restored"),
+        );
+    }
+    // Drop anything the kernel rejected from disk as well
+    persist_sessions(&app);
+}
+
+/// Lock every session (system suspend / screen lock) and tell the frontend
+///
+/// Emits `session-locked` per session so windows switch to the lock screen;
+/// sessions resume through the usual unlock commands.
+pub fn lock_all_sessions(app: &AppHandle, reason: LockReason) {
+    let locked = app.state::<AuthState>().store().lock_all();
+    if locked.is_empty() {
+        return;
+    }
+
+    let audit = app.state::<AuditState>();
+    for info in &locked {
+        audit.record(
+            AuditEntry::new(AuditEvent::Lock)
+                .username(&info.username)
+                .session_id(&info.session_id)
+                .detail(reason.as_str()),
+        );
+        session_events::emit(
+            app,
+            SessionEvent::Locked,
+            &info.session_id,
+            Some(&info.username),
+            Some(reason.as_str()),
+        );
+    }
+}
+
+/// Username for a session with at least the `user` role
+pub fn user_session(store: &SessionStore, session_token: &str) -> Result<String, String> {
+    let info = store.check(session_token).map_err(|e| e.to_string())?;
+    if info.role < Role::User {
+        return Err("Not available in guest sessions".to_string());
+    }
+    Ok(info.username)
+}
+
+/// Username for a session with the `admin` role
+pub fn admin_session(store: &SessionStore, session_token: &str) -> Result<String, String> {
+    let info = store.check(session_token).map_err(|e| e.to_string())?;
+    if info.role < Role::Admin {
+        return Err("Administrator role required".to_string());
+    }
+    Ok(info.username)
+}
+
+/// Fail with `ReauthRequired` unless the session's password was entered
+/// within the `step-up.json` window (see `auth_step_up`)
+pub fn require_step_up(
+    store: &SessionStore,
+    step_up: &StepUpState,
+    session_token: &str,
+) -> Result<(), String> {
+    let session = store
+        .get(session_token)
+        .ok_or_else(|| AuthError::InvalidSession.to_string())?;
+    if step_up.0.is_stale(session.authenticated_at.elapsed()) {
+        return Err(AuthError::ReauthRequired.to_string());
+    }
+    Ok(())
+}
+
+/// Check `username`'s account password with the kernel (PAM), without
+/// changing its key material (the kernel session is refreshed on success)
+///
+/// `purpose` (`unlock` or `step_up`) goes into the kernel's audit of
+/// failed checks.
+pub async fn verify_password(
+    app: &AppHandle,
+    username: &str,
+    session_token: &str,
+    password: &SecretString,
+    purpose: &str,
+) -> Result<AuthResult, String> {
+    let response = call_kernel(
+        &app.state::<KernelState>(),
+        "auth/verify_password",
+        json!({
+            "username": username,
+            "session_token": session_token,
+            "password": password.expose(),
+            "purpose": purpose,
+        }),
+    )
+    .await?;
+    rpc_result(response)
+}
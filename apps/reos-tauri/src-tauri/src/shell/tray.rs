@@ -0,0 +1,55 @@
+//! Tray Status
+//!
+//! Kernel health and lock state shown in the system tray.
+
+use crate::shell::kernel::KernelState;
+use reos_tauri_lib::auth::AuthState;
+use reos_tauri_lib::tray::{KernelHealth, LockStatus, TrayState, TrayStatus};
+use std::sync::TryLockError;
+use tauri::{AppHandle, Manager, Wry};
+
+/// Current kernel health and lock state for the tray
+fn tray_status(app: &AppHandle) -> TrayStatus {
+    let restarting = app
+        .try_state::<TrayState<Wry>>()
+        .is_some_and(|tray| tray.is_restarting());
+    let kernel = if restarting {
+        KernelHealth::Restarting
+    } else {
+        match app.state::<KernelState>().0.try_lock() {
+            Ok(mut guard) => {
+                if guard.as_mut().is_some_and(|proc| proc.is_running()) {
+                    KernelHealth::Running
+                } else {
+                    KernelHealth::Down
+                }
+            }
+            // Held for the duration of a request
+            Err(TryLockError::WouldBlock) => KernelHealth::Running,
+            Err(TryLockError::Poisoned(_)) => KernelHealth::Down,
+        }
+    };
+    let lock = LockStatus::from_sessions(
+        app.state::<AuthState>()
+            .read()
+            .live_sessions()
+            .map(|session| session.is_locked()),
+    );
+    TrayStatus { kernel, lock }
+}
+
+/// Show the current status in the tray
+///
+/// Runs on a blocking thread: callers may hold the session store or kernel lock.
+pub fn refresh_tray(app: &AppHandle) {
+    if app.try_state::<TrayState<Wry>>().is_none() {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let status = tray_status(&app);
+        if let Some(tray) = app.try_state::<TrayState<Wry>>() {
+            tray.show(status);
+        }
+    });
+}
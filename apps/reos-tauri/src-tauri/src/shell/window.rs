@@ -0,0 +1,156 @@
+//! Window Helpers
+//!
+//! Window nonces, the main window, taskbar progress and saved window
+//! state.
+
+use reos_tauri_lib::locks::Recover;
+use reos_tauri_lib::taskbar_progress::{Bar, TaskbarProgressState};
+use reos_tauri_lib::window_nonce::WindowNonceState;
+use reos_tauri_lib::window_state::{Geometry, MonitorArea, WindowStateState};
+use reos_tauri_lib::{splash, window_state};
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, WebviewWindow, Window};
+use tracing::warn;
+
+/// Check the invoking window's nonce (state-changing commands)
+pub fn check_window_nonce(window: &Window, nonce: &str) -> Result<(), String> {
+    window
+        .state::<WindowNonceState>()
+        .0
+        .lock_or_recover()
+        .verify(window.label(), nonce)
+        .map_err(|e| e.to_string())
+}
+
+/// Bring the main window to the front (also when hidden in the tray)
+pub fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+/// Show `bar` (if it changed) on the main window's taskbar entry
+pub fn show_taskbar_progress(app: &AppHandle, bar: Option<Bar>) {
+    let Some(bar) = bar else {
+        return;
+    };
+    let Some(main) = app.get_webview_window("main") else {
+        return;
+    };
+    let state = match bar {
+        Bar::Hidden => ProgressBarState {
+            status: Some(ProgressBarStatus::None),
+            progress: None,
+        },
+        Bar::Indeterminate => ProgressBarState {
+            status: Some(ProgressBarStatus::Indeterminate),
+            progress: None,
+        },
+        Bar::Percent(percent) => ProgressBarState {
+            status: Some(ProgressBarStatus::Normal),
+            progress: Some(percent),
+        },
+    };
+    if let Err(e) = main.set_progress_bar(state) {
+        warn!(error = %e, "failed to set taskbar progress");
+    }
+}
+
+/// Record progress of a long-running task for the taskbar
+pub fn report_task_progress(app: &AppHandle, job: &str, done: u64, total: u64) {
+    let bar = app
+        .state::<TaskbarProgressState>()
+        .0
+        .lock_or_recover()
+        .update(job, done, total);
+    show_taskbar_progress(app, bar);
+}
+
+/// A long-running task ended (finished or failed)
+pub fn finish_task_progress(app: &AppHandle, job: &str) {
+    let bar = app
+        .state::<TaskbarProgressState>()
+        .0
+        .lock_or_recover()
+        .finish(job);
+    show_taskbar_progress(app, bar);
+}
+
+/// Remember `window`'s size, position, maximized state and monitor
+pub fn record_window_state(window: &Window) {
+    if window.label() == splash::SPLASH_LABEL || window.is_minimized().unwrap_or(true) {
+        return;
+    }
+    let (Ok(position), Ok(size)) = (window.outer_position(), window.inner_size()) else {
+        return;
+    };
+    let geometry = Geometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    };
+    let maximized = window.is_maximized().unwrap_or(false);
+    let monitor = window
+        .current_monitor()
+        .ok()
+        .flatten()
+        .and_then(|m| m.name().cloned());
+    // Config windows can move before `setup` has loaded the saved state
+    if let Some(state) = window.try_state::<WindowStateState>() {
+        state
+            .0
+            .lock_or_recover()
+            .update(window.label(), geometry, maximized, monitor);
+    }
+}
+
+/// Apply the saved state for `window`'s label, if any
+pub fn restore_window_state(window: &WebviewWindow) {
+    let Some(saved) = window
+        .state::<WindowStateState>()
+        .0
+        .lock_or_recover()
+        .get(window.label())
+        .cloned()
+    else {
+        return;
+    };
+    // Primary first: it's where a window with no usable monitor goes
+    let primary = window.primary_monitor().ok().flatten();
+    let monitors: Vec<MonitorArea> = primary
+        .into_iter()
+        .chain(window.available_monitors().unwrap_or_default())
+        .map(|m| {
+            let area = m.work_area();
+            MonitorArea {
+                name: m.name().cloned(),
+                x: area.position.x,
+                y: area.position.y,
+                width: area.size.width,
+                height: area.size.height,
+            }
+        })
+        .collect();
+    let Some(geometry) = window_state::place(&saved, &monitors) else {
+        return;
+    };
+    let _ = window.set_size(PhysicalSize::new(geometry.width, geometry.height));
+    let _ = window.set_position(PhysicalPosition::new(geometry.x, geometry.y));
+    if saved.maximized {
+        let _ = window.maximize();
+    }
+}
+
+/// Write saved window states to disk
+pub fn persist_window_state(app: &AppHandle) {
+    let Some(state) = app.try_state::<WindowStateState>() else {
+        return;
+    };
+    let saved = state.0.lock_or_recover().save();
+    if let Err(e) = saved {
+        warn!(error = %e, "failed to save window state");
+    }
+}
@@ -0,0 +1,409 @@
+//! Single-Instance Enforcement for ReOS
+//!
+//! Two instances would each start a kernel on the same encrypted store, so
+//! only one runs per user. The first launch holds an exclusive lock on
+//! `reos-instance.lock` for its lifetime and listens for later launches
+//! next to it. A later launch finds the lock taken, sends its command-line
+//! arguments (one JSON line) and exits; the running instance raises its
+//! main window, routes any `reos://` link among the arguments and tells the
+//! main window (`second-instance`). Each profile (`--profile`) has its own
+//! lock and endpoint, `reos-instance@<name>.*`.
+//!
+//! The lock lives in `$XDG_RUNTIME_DIR` (Unix), else in a `reos` dir under
+//! the per-user local data dir (created private to the user). Launches are
+//! forwarded over:
+//! - Unix: the socket `reos-instance.sock`
+//! - Windows: a named pipe with a random name, recorded in
+//!   `reos-instance.pipe` (only this user's launches can find or write it)
+
+use serde::{Deserialize, Serialize};
+use std::fs::{File, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::profile;
+
+/// Event telling the main window about a forwarded launch
+pub const SECOND_INSTANCE_EVENT: &str = "second-instance";
+
+/// Lock and endpoint file stem (scoped to the profile)
+const INSTANCE_FILE: &str = "reos-instance";
+
+/// Under the local data dir, without `$XDG_RUNTIME_DIR`
+const FALLBACK_DIR: &str = "reos";
+
+/// Largest forwarded message
+const MAX_MESSAGE_BYTES: usize = 64 * 1024;
+const MAX_ARGS: usize = 64;
+
+/// How long a second launch keeps trying while the first is starting up
+const FORWARD_ATTEMPTS: u32 = 20;
+const FORWARD_RETRY: Duration = Duration::from_millis(100);
+
+/// A later launch's arguments (without the program name)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Launch {
+    pub args: Vec<String>,
+}
+
+impl Launch {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            args: args.into_iter().skip(1).take(MAX_ARGS).collect(),
+        }
+    }
+
+    /// Parse a forwarded line (`None` if malformed or oversized)
+    pub fn decode(line: &str) -> Option<Self> {
+        if line.len() > MAX_MESSAGE_BYTES {
+            return None;
+        }
+        let launch: Self = serde_json::from_str(line).ok()?;
+        (launch.args.len() <= MAX_ARGS).then_some(launch)
+    }
+
+    /// With the program name put back, for the `from_args` parsers
+    pub fn argv(&self) -> impl Iterator<Item = String> + '_ {
+        std::iter::once(String::new()).chain(self.args.iter().cloned())
+    }
+}
+
+/// Result of `acquire`
+pub enum Instance {
+    /// This is the only instance; keep it alive and call `listen`
+    Primary(PrimaryInstance),
+    /// Another instance is running (and got the arguments if it could)
+    Secondary,
+}
+
+/// The lock held by the running instance
+pub struct PrimaryInstance {
+    // Held for the process lifetime; dropping it releases the lock
+    _lock: Option<File>,
+    /// Where the lock is, and the endpoint goes
+    dir: Option<PathBuf>,
+}
+
+/// `$XDG_RUNTIME_DIR`, else the fallback dir (created)
+fn runtime_dir() -> io::Result<PathBuf> {
+    let xdg = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|p| cfg!(unix) && p.is_absolute());
+    if let Some(dir) = xdg {
+        return Ok(dir);
+    }
+    let dir = dirs::data_local_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no local data dir"))?
+        .join(FALLBACK_DIR);
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(&dir)?;
+    Ok(dir)
+}
+
+fn instance_file(extension: &str) -> String {
+    format!("{}.{extension}", profile::scope_id(INSTANCE_FILE))
+}
+
+/// Take the instance lock, or hand `launch` to the instance holding it
+pub fn acquire(launch: &Launch) -> Instance {
+    let dir = match runtime_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            warn!(error = %e, "single-instance lock unavailable");
+            return Instance::Primary(PrimaryInstance::unlocked());
+        }
+    };
+    let lock = match File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(dir.join(instance_file("lock")))
+    {
+        Ok(lock) => lock,
+        Err(e) => {
+            warn!(error = %e, "single-instance lock unavailable");
+            return Instance::Primary(PrimaryInstance::unlocked());
+        }
+    };
+    match lock.try_lock() {
+        Ok(()) => Instance::Primary(PrimaryInstance {
+            _lock: Some(lock),
+            dir: Some(dir),
+        }),
+        Err(TryLockError::WouldBlock) => {
+            if !forward(&dir, launch) {
+                warn!("ReOS is already running but didn't answer");
+            }
+            Instance::Secondary
+        }
+        Err(TryLockError::Error(e)) => {
+            warn!(error = %e, "single-instance lock unavailable");
+            Instance::Primary(PrimaryInstance::unlocked())
+        }
+    }
+}
+
+/// Send `launch` to the running instance (true if it was delivered)
+fn forward(dir: &Path, launch: &Launch) -> bool {
+    use std::io::Write;
+
+    let Ok(mut line) = serde_json::to_string(launch) else {
+        return false;
+    };
+    line.push('\n');
+    // The endpoint appears shortly after the lock while the first instance
+    // starts up (and a pipe is briefly busy between launches)
+    for _ in 0..FORWARD_ATTEMPTS {
+        if let Ok(mut endpoint) = connect(dir) {
+            return endpoint.write_all(line.as_bytes()).is_ok();
+        }
+        std::thread::sleep(FORWARD_RETRY);
+    }
+    false
+}
+
+#[cfg(unix)]
+fn connect(dir: &Path) -> io::Result<std::os::unix::net::UnixStream> {
+    std::os::unix::net::UnixStream::connect(dir.join(instance_file("sock")))
+}
+
+#[cfg(windows)]
+fn connect(dir: &Path) -> io::Result<File> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use windows_sys::Win32::Storage::FileSystem::SECURITY_IDENTIFICATION;
+
+    let name = std::fs::read_to_string(dir.join(instance_file("pipe")))?;
+    // Identification level, as for kernel pipes
+    std::fs::OpenOptions::new()
+        .write(true)
+        .security_qos_flags(SECURITY_IDENTIFICATION)
+        .open(name.trim())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn connect(_dir: &Path) -> io::Result<File> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Read one forwarded launch from a connection
+fn receive(connection: impl io::Read) -> Option<Launch> {
+    use std::io::{BufRead, BufReader};
+
+    let mut reader = BufReader::new(connection.take(MAX_MESSAGE_BYTES as u64 + 1));
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    Launch::decode(line.trim_end())
+}
+
+#[cfg(windows)]
+mod pipe {
+    use std::fs::File;
+    use std::io;
+    use std::os::windows::io::{AsRawHandle, FromRawHandle};
+    use windows_sys::Win32::Foundation::{ERROR_PIPE_CONNECTED, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_INBOUND,
+    };
+    use windows_sys::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE,
+        PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    };
+
+    use super::MAX_MESSAGE_BYTES;
+
+    /// A new instance of pipe `name` (failing if `first` and the name is
+    /// taken)
+    pub fn create(name: &str, first: bool) -> io::Result<File> {
+        let wide: Vec<u16> = name.encode_utf16().chain([0]).collect();
+        let mut mode = PIPE_ACCESS_INBOUND;
+        if first {
+            mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+        }
+        // SAFETY: `wide` is NUL-terminated and outlives the call; the
+        // handle is checked and then owned by the returned `File`
+        unsafe {
+            let handle = CreateNamedPipeW(
+                wide.as_ptr(),
+                mode,
+                PIPE_TYPE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                0,
+                MAX_MESSAGE_BYTES as u32,
+                0,
+                std::ptr::null(),
+            );
+            if handle == INVALID_HANDLE_VALUE {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(File::from_raw_handle(handle as _))
+        }
+    }
+
+    /// Wait for a client on `pipe`
+    pub fn wait(pipe: &File) -> io::Result<()> {
+        // SAFETY: the handle is a pipe instance owned by `pipe`
+        if unsafe { ConnectNamedPipe(pipe.as_raw_handle() as _, std::ptr::null_mut()) } != 0 {
+            return Ok(());
+        }
+        let e = io::Error::last_os_error();
+        // Connected between create and wait
+        if e.raw_os_error() == Some(ERROR_PIPE_CONNECTED as i32) {
+            return Ok(());
+        }
+        Err(e)
+    }
+}
+
+impl PrimaryInstance {
+    fn unlocked() -> Self {
+        Self {
+            _lock: None,
+            dir: None,
+        }
+    }
+
+    /// Accept launches forwarded by later instances, calling `on_launch`
+    /// for each from a background thread; keeps the lock until exit
+    #[cfg(unix)]
+    pub fn listen(self, on_launch: impl Fn(Launch) + Send + 'static) {
+        use std::os::unix::net::UnixListener;
+
+        let Some(path) = self.dir.as_ref().map(|dir| dir.join(instance_file("sock"))) else {
+            return;
+        };
+        // Left behind by an instance that crashed; the lock says it's gone
+        let _ = std::fs::remove_file(&path);
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                warn!(error = %e, "single-instance socket unavailable");
+                return;
+            }
+        };
+        std::thread::spawn(move || {
+            let _instance = self;
+            for stream in listener.incoming().flatten() {
+                let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+                if let Some(launch) = receive(stream) {
+                    on_launch(launch);
+                }
+            }
+        });
+    }
+
+    #[cfg(windows)]
+    pub fn listen(self, on_launch: impl Fn(Launch) + Send + 'static) {
+        let Some(record) = self.dir.as_ref().map(|dir| dir.join(instance_file("pipe"))) else {
+            return;
+        };
+        let mut bytes = [0u8; 8];
+        rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut bytes);
+        let name = format!(r"\\.\pipe\{INSTANCE_FILE}-{}", hex::encode(bytes));
+        // The first instance is ours before the name is published
+        let first = pipe::create(&name, true).and_then(|pipe| {
+            std::fs::write(&record, &name)?;
+            Ok(pipe)
+        });
+        let mut pipe = match first {
+            Ok(pipe) => pipe,
+            Err(e) => {
+                warn!(error = %e, "single-instance pipe unavailable");
+                return;
+            }
+        };
+        std::thread::spawn(move || {
+            let _instance = self;
+            loop {
+                if let Err(e) = pipe::wait(&pipe) {
+                    warn!(error = %e, "single-instance pipe failed");
+                    return;
+                }
+                // Only this user can write to the pipe (the default DACL
+                // lets others read, and it's inbound only)
+                let next = pipe::create(&name, false);
+                if let Some(launch) = receive(&pipe) {
+                    on_launch(launch);
+                }
+                pipe = match next {
+                    Ok(next) => next,
+                    Err(e) => {
+                        warn!(error = %e, "single-instance pipe failed");
+                        return;
+                    }
+                };
+            }
+        });
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    pub fn listen(self, _on_launch: impl Fn(Launch) + Send + 'static) {
+        // Nothing to accept; just keep the lock
+        std::mem::forget(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launch_round_trip() {
+        let launch = Launch::from_args(["reos", "reos://open/act/7"].map(String::from));
+        assert_eq!(launch.args, vec!["reos://open/act/7"]);
+        let line = serde_json::to_string(&launch).unwrap();
+        assert_eq!(Launch::decode(&line), Some(launch.clone()));
+        assert_eq!(
+            launch.argv().collect::<Vec<_>>(),
+            vec!["".to_string(), "reos://open/act/7".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_decode_rejects_bad_messages() {
+        assert_eq!(Launch::decode("not json"), None);
+        let too_many = Launch {
+            args: vec!["x".to_string(); MAX_ARGS + 1],
+        };
+        assert_eq!(
+            Launch::decode(&serde_json::to_string(&too_many).unwrap()),
+            None
+        );
+        let huge = format!(r#"{{"args":["{}"]}}"#, "a".repeat(MAX_MESSAGE_BYTES));
+        assert_eq!(Launch::decode(&huge), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_second_acquire_forwards_to_first() {
+        let dir = std::env::temp_dir().join(format!("reos-instance-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lock = File::create(dir.join(instance_file("lock"))).unwrap();
+        lock.try_lock().unwrap();
+        let first = PrimaryInstance {
+            _lock: Some(lock),
+            dir: Some(dir.clone()),
+        };
+        let (tx, rx) = std::sync::mpsc::channel();
+        first.listen(move |launch| {
+            let _ = tx.send(launch);
+        });
+
+        let launch = Launch {
+            args: vec!["--flag".to_string()],
+        };
+        assert!(forward(&dir, &launch));
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), launch);
+        // A second handle on the same file can't take the lock
+        let again = File::options()
+            .write(true)
+            .open(dir.join(instance_file("lock")))
+            .unwrap();
+        assert!(matches!(again.try_lock(), Err(TryLockError::WouldBlock)));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
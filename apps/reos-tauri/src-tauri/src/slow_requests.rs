@@ -0,0 +1,310 @@
+//! Slow Kernel Request Warnings
+//!
+//! Every kernel request gets a correlation id, sent to the kernel with the
+//! request (its logs use the same id) and recorded on the `kernel_request`
+//! span. Each method keeps a latency baseline (moving average); a request
+//! is slow once it takes longer than `multiple` times its method's
+//! baseline (and at least `floor_ms`), or longer than `absolute_ms`
+//! whatever the baseline. Methods with fewer than `min_samples` requests
+//! only have the absolute threshold.
+//!
+//! A slow request is logged and sent to every window as a
+//! `kernel-slow-request` event: once while it is still running (so hangs
+//! show up before they end, if they ever do) and again when it finishes.
+//!
+//! Configurable via `slow-requests.json` in the app data dir:
+//! `{ "multiple": 5.0, "absolute_ms": 15000, "floor_ms": 500, "min_samples": 20 }`
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::locks::Recover;
+
+/// Event carrying a `SlowRequest` to every window
+pub const SLOW_REQUEST_EVENT: &str = "kernel-slow-request";
+
+/// How often running requests are checked
+pub const WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Weight of the newest sample in a baseline
+const BASELINE_WEIGHT: f64 = 0.1;
+
+/// Methods with their own baseline; later ones only get the absolute threshold
+const MAX_METHODS: usize = 256;
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct SlowRequestConfig {
+    /// Slow past this multiple of the method's baseline
+    pub multiple: f64,
+    /// Slow past this, whatever the baseline
+    pub absolute_ms: u64,
+    /// Never slow below this (a 2 ms method taking 10 ms is no hang)
+    pub floor_ms: u64,
+    /// Requests a method needs before its baseline counts
+    pub min_samples: u64,
+}
+
+impl SlowRequestConfig {
+    const DEFAULT: Self = Self {
+        multiple: 5.0,
+        absolute_ms: 15_000,
+        floor_ms: 500,
+        min_samples: 20,
+    };
+
+    /// Load from `path`; missing or invalid file means defaults
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+}
+
+impl Default for SlowRequestConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// `kernel-slow-request` payload
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct SlowRequest {
+    pub correlation_id: String,
+    pub method: String,
+    pub elapsed_ms: u64,
+    /// The method's baseline, if it has enough samples
+    pub baseline_ms: Option<u64>,
+    pub threshold_ms: u64,
+    /// `false` while the request is still running
+    pub finished: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Baseline {
+    mean_ms: f64,
+    samples: u64,
+}
+
+struct InFlight {
+    method: String,
+    started: Instant,
+    reported: bool,
+}
+
+/// Baselines and running requests
+struct Tracker {
+    config: SlowRequestConfig,
+    baselines: BTreeMap<String, Baseline>,
+    in_flight: BTreeMap<String, InFlight>,
+}
+
+impl Tracker {
+    const fn new(config: SlowRequestConfig) -> Self {
+        Self {
+            config,
+            baselines: BTreeMap::new(),
+            in_flight: BTreeMap::new(),
+        }
+    }
+
+    fn baseline_ms(&self, method: &str) -> Option<f64> {
+        self.baselines
+            .get(method)
+            .filter(|b| b.samples >= self.config.min_samples)
+            .map(|b| b.mean_ms)
+    }
+
+    fn threshold_ms(&self, method: &str) -> u64 {
+        match self.baseline_ms(method) {
+            Some(mean) => ((mean * self.config.multiple) as u64)
+                .max(self.config.floor_ms)
+                .min(self.config.absolute_ms),
+            None => self.config.absolute_ms,
+        }
+    }
+
+    fn slow(&self, correlation_id: &str, method: &str, elapsed: Duration) -> Option<SlowRequest> {
+        let elapsed_ms = elapsed.as_millis() as u64;
+        let threshold_ms = self.threshold_ms(method);
+        (elapsed_ms > threshold_ms).then(|| SlowRequest {
+            correlation_id: correlation_id.to_string(),
+            method: method.to_string(),
+            elapsed_ms,
+            baseline_ms: self.baseline_ms(method).map(|mean| mean.round() as u64),
+            threshold_ms,
+            finished: false,
+        })
+    }
+
+    fn start(&mut self, correlation_id: &str, method: &str, now: Instant) {
+        self.in_flight.insert(
+            correlation_id.to_string(),
+            InFlight {
+                method: method.to_string(),
+                started: now,
+                reported: false,
+            },
+        );
+    }
+
+    /// The request ended after `elapsed`: `Some` if it was slow (judged
+    /// against the baseline before this sample, which it then joins)
+    fn finish(
+        &mut self,
+        correlation_id: &str,
+        method: &str,
+        elapsed: Duration,
+    ) -> Option<SlowRequest> {
+        self.in_flight.remove(correlation_id);
+        let slow = self
+            .slow(correlation_id, method, elapsed)
+            .map(|slow| SlowRequest {
+                finished: true,
+                ..slow
+            });
+        let tracked = self.baselines.contains_key(method) || self.baselines.len() < MAX_METHODS;
+        if tracked {
+            let ms = elapsed.as_secs_f64() * 1000.0;
+            let baseline = self.baselines.entry(method.to_string()).or_default();
+            baseline.mean_ms = if baseline.samples == 0 {
+                ms
+            } else {
+                baseline.mean_ms + BASELINE_WEIGHT * (ms - baseline.mean_ms)
+            };
+            baseline.samples += 1;
+        }
+        slow
+    }
+
+    /// Running requests that just became slow (each reported once)
+    fn check_in_flight(&mut self, now: Instant) -> Vec<SlowRequest> {
+        let mut found = Vec::new();
+        for (correlation_id, request) in &self.in_flight {
+            if request.reported {
+                continue;
+            }
+            let elapsed = now.saturating_duration_since(request.started);
+            found.extend(self.slow(correlation_id, &request.method, elapsed));
+        }
+        for slow in &found {
+            if let Some(request) = self.in_flight.get_mut(&slow.correlation_id) {
+                request.reported = true;
+            }
+        }
+        found
+    }
+}
+
+static TRACKER: Mutex<Tracker> = Mutex::new(Tracker::new(SlowRequestConfig::DEFAULT));
+
+fn tracker() -> MutexGuard<'static, Tracker> {
+    TRACKER.lock_or_recover()
+}
+
+/// Install deployment thresholds (at startup)
+pub fn configure(config: SlowRequestConfig) {
+    tracker().config = config;
+}
+
+/// A fresh correlation id (12 hex characters, like the kernel's own)
+pub fn correlation_id() -> String {
+    let mut bytes = [0u8; 6];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+fn log(slow: &SlowRequest) {
+    warn!(
+        correlation_id = %slow.correlation_id,
+        method = %slow.method,
+        elapsed_ms = slow.elapsed_ms,
+        baseline_ms = slow.baseline_ms,
+        threshold_ms = slow.threshold_ms,
+        finished = slow.finished,
+        "slow kernel request"
+    );
+}
+
+/// A request was sent to the kernel
+pub fn start(correlation_id: &str, method: &str) {
+    tracker().start(correlation_id, method, Instant::now());
+}
+
+/// A request ended; `Some` (already logged) if it was slow
+pub fn finish(correlation_id: &str, method: &str, elapsed: Duration) -> Option<SlowRequest> {
+    let slow = tracker().finish(correlation_id, method, elapsed);
+    if let Some(slow) = &slow {
+        log(slow);
+    }
+    slow
+}
+
+/// Running requests that just became slow (already logged)
+pub fn check_in_flight() -> Vec<SlowRequest> {
+    let found = tracker().check_in_flight(Instant::now());
+    found.iter().for_each(log);
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SlowRequestConfig {
+        SlowRequestConfig {
+            multiple: 4.0,
+            absolute_ms: 10_000,
+            floor_ms: 100,
+            min_samples: 3,
+        }
+    }
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_absolute_threshold_until_baseline() {
+        let mut tracker = Tracker::new(config());
+        assert!(tracker.finish("a", "chat/respond", ms(9_000)).is_none());
+        let slow = tracker.finish("b", "chat/respond", ms(12_000)).unwrap();
+        assert_eq!(slow.threshold_ms, 10_000);
+        assert_eq!(slow.baseline_ms, None);
+        assert!(slow.finished);
+    }
+
+    #[test]
+    fn test_multiple_of_baseline_with_floor() {
+        let mut tracker = Tracker::new(config());
+        for id in ["a", "b", "c"] {
+            assert!(tracker.finish(id, "ping", ms(5)).is_none());
+            assert!(tracker.finish(id, "play/read", ms(200)).is_none());
+        }
+        // 4 x 5 ms is under the floor
+        assert!(tracker.finish("d", "ping", ms(90)).is_none());
+        let slow = tracker.finish("e", "play/read", ms(900)).unwrap();
+        assert_eq!(slow.baseline_ms, Some(200));
+        assert_eq!(slow.threshold_ms, 800);
+        assert_eq!(slow.correlation_id, "e");
+    }
+
+    #[test]
+    fn test_in_flight_reported_once() {
+        let mut tracker = Tracker::new(config());
+        let start = Instant::now();
+        tracker.start("a", "chat/respond", start);
+        assert!(tracker.check_in_flight(start + ms(5_000)).is_empty());
+        let found = tracker.check_in_flight(start + ms(11_000));
+        assert_eq!(found.len(), 1);
+        assert!(!found[0].finished);
+        assert!(tracker.check_in_flight(start + ms(12_000)).is_empty());
+        assert!(tracker.finish("a", "chat/respond", ms(13_000)).is_some());
+        assert!(tracker.in_flight.is_empty());
+    }
+}
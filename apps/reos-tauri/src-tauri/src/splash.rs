@@ -0,0 +1,81 @@
+//! Startup Splash Window for ReOS
+//!
+//! The main window starts hidden. At launch a small undecorated `splash`
+//! window (`splash.html`) is shown instead while the kernel is started
+//! (with the default `eager` start policy; otherwise the main window is
+//! shown at once, see `kernel_lifecycle.rs`), and
+//! follows `kernel-startup-progress` events through the start stages. Once
+//! the kernel reports ready (or fails to start; the main window then shows
+//! the error on its first request) the main window is shown and the splash
+//! closed, so the first call after the window appears is already warm.
+//!
+//! The splash window has its own capability with event access only; it
+//! can't invoke any ReOS command.
+
+use crate::kernel::StartStage;
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Runtime, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+
+/// Label of the splash window
+pub const SPLASH_LABEL: &str = "splash";
+
+/// Event reporting kernel start stages (to every window)
+pub const KERNEL_PROGRESS_EVENT: &str = "kernel-startup-progress";
+
+/// `kernel-startup-progress` payload
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct StartupProgress {
+    pub stage: StartStage,
+    pub step: u32,
+    pub total: u32,
+}
+
+impl From<StartStage> for StartupProgress {
+    fn from(stage: StartStage) -> Self {
+        Self {
+            stage,
+            step: stage.step(),
+            total: StartStage::COUNT,
+        }
+    }
+}
+
+/// Open the splash window
+pub fn open<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<WebviewWindow<R>> {
+    WebviewWindowBuilder::new(app, SPLASH_LABEL, WebviewUrl::App("splash.html".into()))
+        .title("Talking Rock")
+        .inner_size(360.0, 220.0)
+        .resizable(false)
+        .decorations(false)
+        .center()
+        .skip_taskbar(true)
+        .build()
+}
+
+/// Show the main window and close the splash
+pub fn finish<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(main) = app.get_webview_window("main") {
+        let _ = main.show();
+        let _ = main.set_focus();
+    }
+    if let Some(splash) = app.get_webview_window(SPLASH_LABEL) {
+        let _ = splash.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_steps() {
+        let first = StartupProgress::from(StartStage::Verifying);
+        assert_eq!((first.step, first.total), (1, StartStage::COUNT));
+        let last = StartupProgress::from(StartStage::Ready);
+        assert_eq!(last.step, StartStage::COUNT);
+        assert_eq!(
+            serde_json::to_value(&last).unwrap(),
+            serde_json::json!({ "stage": "ready", "step": 5, "total": 5 })
+        );
+    }
+}
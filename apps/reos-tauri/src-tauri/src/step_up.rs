@@ -0,0 +1,82 @@
+//! Step-up Re-authentication for ReOS
+//!
+//! Sensitive kernel methods (API key changes, safety limits, ...) need more
+//! than a live session: the user must have entered their password within
+//! the last few minutes. Otherwise `kernel_request` fails with
+//! `ReauthRequired` and the frontend calls `auth_step_up`.
+//!
+//! Configurable via `step-up.json` in the app data dir:
+//! `{ "methods": ["anthropic/set_key", "safety/set_*"], "max_age_secs": 300 }`
+
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::methods::method_matches;
+
+/// Methods that require a recent password when `step-up.json` doesn't list any
+const DEFAULT_STEP_UP_METHODS: &[&str] = &[
+    "anthropic/set_key",
+    "anthropic/delete_key",
+    "providers/set",
+    "safety/set_*",
+];
+
+/// Deployment policy for step-up re-authentication
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct StepUpPolicy {
+    /// Method names or `prefix*` patterns
+    pub methods: Vec<String>,
+    /// How recent the last password entry must be
+    pub max_age_secs: u64,
+}
+
+impl Default for StepUpPolicy {
+    fn default() -> Self {
+        Self {
+            methods: DEFAULT_STEP_UP_METHODS
+                .iter()
+                .map(|m| m.to_string())
+                .collect(),
+            max_age_secs: 5 * 60,
+        }
+    }
+}
+
+impl StepUpPolicy {
+    /// Load the policy from `path` (missing or invalid file = defaults)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether `method` needs a recent password
+    pub fn requires(&self, method: &str) -> bool {
+        self.methods.iter().any(|p| method_matches(p, method))
+    }
+
+    /// Whether a password entered `since_auth` ago is too old
+    pub fn is_stale(&self, since_auth: Duration) -> bool {
+        since_auth > Duration::from_secs(self.max_age_secs)
+    }
+}
+
+/// Step-up policy state (read-only after startup)
+pub struct StepUpState(pub StepUpPolicy);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_policy() {
+        let policy = StepUpPolicy::default();
+        assert!(policy.requires("anthropic/set_key"));
+        assert!(policy.requires("safety/set_sudo_limit"));
+        assert!(!policy.requires("safety/settings"));
+        assert!(!policy.is_stale(Duration::from_secs(60)));
+        assert!(policy.is_stale(Duration::from_secs(10 * 60)));
+    }
+}
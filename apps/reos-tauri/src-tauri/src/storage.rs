@@ -0,0 +1,27 @@
+//! Local file storage helpers
+//!
+//! Small utilities shared by the subsystems that persist Rust-side state
+//! (TOTP secrets, biometric enrollment, ...) under the app data directory.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Write `data` to `path` atomically with owner-only permissions
+pub fn write_private_file(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
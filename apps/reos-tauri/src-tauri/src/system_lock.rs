@@ -0,0 +1,190 @@
+//! Screen Lock Watcher for ReOS
+//!
+//! When the desktop locks its screen (or the machine is about to sleep,
+//! see `power`), all sessions are locked so the frontend swaps to the lock
+//! screen instead of leaving decrypted state on display.
+//!
+//! Platforms:
+//! - Linux: logind over the system bus (the current session's `Lock`
+//!   signal and `LockedHint` property)
+//! - macOS: the `com.apple.screenIsLocked` distributed notification
+//! - Windows: WTS session notifications (`WTS_SESSION_LOCK`)
+//!
+//! Whether a watcher is running shows in the health report
+//! (`screen_lock_watch`); without one sessions still lock on idle.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set once the platform watcher is registered
+static WATCHING: AtomicBool = AtomicBool::new(false);
+
+/// Whether screen locks are being watched
+pub fn is_watching() -> bool {
+    WATCHING.load(Ordering::Relaxed)
+}
+
+/// Why sessions were locked
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LockReason {
+    Suspend,
+    ScreenLock,
+    /// "Lock" from the tray menu
+    Tray,
+    /// The global lock shortcut
+    Shortcut,
+    /// Restored at a minimized login autostart
+    Autostart,
+    /// Restored at a launch with `--start-locked`
+    StartLocked,
+}
+
+impl LockReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LockReason::Suspend => "suspend",
+            LockReason::ScreenLock => "screen_lock",
+            LockReason::Tray => "tray",
+            LockReason::Shortcut => "shortcut",
+            LockReason::Autostart => "autostart",
+            LockReason::StartLocked => "start_locked",
+        }
+    }
+}
+
+/// Call `on_lock` whenever the screen locks
+///
+/// Best-effort: if the platform service is unavailable nothing is watched
+/// (and `is_watching` stays false). On macOS this must be called on the
+/// main thread, which also runs `on_lock`.
+#[cfg(target_os = "linux")]
+pub fn watch(on_lock: impl Fn(LockReason) + Send + Sync + 'static) {
+    match linux::watch(std::sync::Arc::new(on_lock)) {
+        Ok(true) => WATCHING.store(true, Ordering::Relaxed),
+        Ok(false) => tracing::warn!("not in a login session, screen locks not watched"),
+        Err(e) => tracing::warn!(error = %e, "system lock watcher unavailable"),
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn watch(on_lock: impl Fn(LockReason) + Send + Sync + 'static) {
+    macos::watch(on_lock);
+    WATCHING.store(true, Ordering::Relaxed);
+}
+
+#[cfg(target_os = "windows")]
+pub fn watch(on_lock: impl Fn(LockReason) + Send + Sync + 'static) {
+    match windows::watch(on_lock) {
+        Ok(()) => WATCHING.store(true, Ordering::Relaxed),
+        Err(e) => tracing::warn!(error = %e, "system lock watcher unavailable"),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn watch(_on_lock: impl Fn(LockReason) + Send + Sync + 'static) {
+    tracing::warn!("screen locks aren't watched on this platform");
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::LockReason;
+    use std::sync::Arc;
+    use zbus::blocking::{Connection, Proxy};
+    use zbus::zvariant::OwnedObjectPath;
+
+    const LOGIND: &str = "org.freedesktop.login1";
+
+    /// Whether a login session was found to watch
+    pub fn watch(on_lock: Arc<dyn Fn(LockReason) + Send + Sync>) -> zbus::Result<bool> {
+        let conn = Connection::system()?;
+        let manager = Proxy::new(
+            &conn,
+            LOGIND,
+            "/org/freedesktop/login1",
+            "org.freedesktop.login1.Manager",
+        )?;
+
+        // Screen lock is per login session; without one (e.g. launched over
+        // ssh) nothing is watched
+        let session_path: OwnedObjectPath =
+            match manager.call("GetSessionByPID", &std::process::id()) {
+                Ok(path) => path,
+                Err(_) => return Ok(false),
+            };
+        let session = Proxy::new(
+            &conn,
+            LOGIND,
+            session_path,
+            "org.freedesktop.login1.Session",
+        )?;
+
+        // `loginctl lock-session` and some screensavers send Lock...
+        let lock = session.receive_signal("Lock")?;
+        let callback = on_lock.clone();
+        std::thread::spawn(move || {
+            for _ in lock {
+                callback(LockReason::ScreenLock);
+            }
+        });
+
+        // ...while GNOME/KDE only flip LockedHint
+        std::thread::spawn(move || {
+            for change in session.receive_property_changed::<bool>("LockedHint") {
+                if let Ok(true) = change.get() {
+                    on_lock(LockReason::ScreenLock);
+                }
+            }
+        });
+        Ok(true)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::LockReason;
+    use block2::RcBlock;
+    use objc2_foundation::{NSDistributedNotificationCenter, NSNotification, NSString};
+    use std::ptr::NonNull;
+
+    pub fn watch(on_lock: impl Fn(LockReason) + Send + Sync + 'static) {
+        let name = NSString::from_str("com.apple.screenIsLocked");
+        let block = RcBlock::new(move |_: NonNull<NSNotification>| on_lock(LockReason::ScreenLock));
+        // No queue: the block runs on the posting (main) thread
+        let observer = unsafe {
+            NSDistributedNotificationCenter::defaultCenter()
+                .addObserverForName_object_queue_usingBlock(Some(&name), None, None, &block)
+        };
+        // Observed for the life of the app
+        std::mem::forget(observer);
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod windows {
+    use super::LockReason;
+    use std::io;
+    use windows_sys::Win32::System::RemoteDesktop::{
+        WTSRegisterSessionNotification, NOTIFY_FOR_THIS_SESSION,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{WM_WTSSESSION_CHANGE, WTS_SESSION_LOCK};
+
+    use crate::message_window;
+
+    pub fn watch(on_lock: impl Fn(LockReason) + Send + Sync + 'static) -> io::Result<()> {
+        message_window::spawn(
+            "ReOSScreenLockWatcher",
+            |hwnd| {
+                if unsafe { WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION) } == 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            },
+            move |msg, wparam, _| {
+                if msg == WM_WTSSESSION_CHANGE && wparam as u32 == WTS_SESSION_LOCK {
+                    on_lock(LockReason::ScreenLock);
+                }
+            },
+        )
+    }
+}
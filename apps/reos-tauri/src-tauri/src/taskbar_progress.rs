@@ -0,0 +1,201 @@
+//! Taskbar Progress for Long-Running Tasks in ReOS
+//!
+//! File ingests, exports and kernel jobs that report progress (e.g. model
+//! downloads, as `event` pushes tagged `progress` with `job`, `done` and
+//! `total`) are combined into one progress bar on the main window's
+//! taskbar entry, so they stay visible while the window is minimized or
+//! hidden in the tray.
+//!
+//! Platforms (via the window's progress bar):
+//! - Windows: taskbar button
+//! - Linux: launcher entry (libunity, matched by the `.desktop` file name)
+//! - macOS: dock icon
+//!
+//! A job ends when `done` reaches `total` (or it fails); the bar disappears
+//! with the last job. Jobs with an unknown total show as indeterminate.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const PROGRESS_TAG: &str = "progress";
+/// Job id prefix of kernel-reported jobs
+const KERNEL_JOB_PREFIX: &str = "kernel:";
+
+/// Most jobs tracked at once; later ones aren't shown
+const MAX_JOBS: usize = 64;
+const MAX_JOB_ID_LEN: usize = 128;
+
+/// What the taskbar should show
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bar {
+    Hidden,
+    /// Running, but no total is known
+    Indeterminate,
+    /// Percent done, 0-100
+    Percent(u64),
+}
+
+/// A progress update parsed from a kernel push event
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KernelProgress {
+    pub job: String,
+    pub done: u64,
+    pub total: u64,
+    pub failed: bool,
+}
+
+impl KernelProgress {
+    /// Parse a kernel push event (`None` unless tagged `progress`)
+    pub fn from_kernel_event(event: &Value) -> Option<Self> {
+        let params = event.get("params")?;
+        if params.get("tag").and_then(Value::as_str) != Some(PROGRESS_TAG) {
+            return None;
+        }
+        let job = params.get("job").and_then(Value::as_str)?;
+        if job.is_empty() || job.len() > MAX_JOB_ID_LEN {
+            return None;
+        }
+        let number = |key: &str| params.get(key).and_then(Value::as_u64).unwrap_or(0);
+        Some(Self {
+            job: format!("{KERNEL_JOB_PREFIX}{job}"),
+            done: number("done"),
+            total: number("total"),
+            failed: params.get("failed").and_then(Value::as_bool) == Some(true),
+        })
+    }
+}
+
+/// Running jobs (id -> done, total) and the bar last shown for them
+pub struct TaskbarProgress {
+    jobs: HashMap<String, (u64, u64)>,
+    shown: Bar,
+}
+
+impl Default for TaskbarProgress {
+    fn default() -> Self {
+        Self {
+            jobs: HashMap::new(),
+            shown: Bar::Hidden,
+        }
+    }
+}
+
+impl TaskbarProgress {
+    /// Record `job`'s progress (ending it once `done` reaches a known
+    /// `total`); returns the bar to show if it changed
+    pub fn update(&mut self, job: &str, done: u64, total: u64) -> Option<Bar> {
+        if total > 0 && done >= total {
+            return self.finish(job);
+        }
+        if self.jobs.len() >= MAX_JOBS && !self.jobs.contains_key(job) {
+            return None;
+        }
+        self.jobs.insert(job.to_string(), (done, total));
+        self.refresh()
+    }
+
+    /// Forget `job`; returns the bar to show if it changed
+    pub fn finish(&mut self, job: &str) -> Option<Bar> {
+        self.jobs.remove(job)?;
+        self.refresh()
+    }
+
+    /// Forget the kernel's jobs (it exited); returns the bar to show if it
+    /// changed
+    pub fn finish_kernel_jobs(&mut self) -> Option<Bar> {
+        self.jobs
+            .retain(|job, _| !job.starts_with(KERNEL_JOB_PREFIX));
+        self.refresh()
+    }
+
+    fn bar(&self) -> Bar {
+        if self.jobs.is_empty() {
+            return Bar::Hidden;
+        }
+        if self.jobs.values().any(|&(_, total)| total == 0) {
+            return Bar::Indeterminate;
+        }
+        let (done, total) = self
+            .jobs
+            .values()
+            .fold((0u128, 0u128), |(d, t), &(done, total)| {
+                (d + done.min(total) as u128, t + total as u128)
+            });
+        Bar::Percent((done * 100 / total) as u64)
+    }
+
+    fn refresh(&mut self) -> Option<Bar> {
+        let bar = self.bar();
+        (bar != self.shown).then(|| {
+            self.shown = bar;
+            bar
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct TaskbarProgressState(pub Mutex<TaskbarProgress>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_combines_jobs_and_reports_changes_only() {
+        let mut progress = TaskbarProgress::default();
+        assert_eq!(progress.update("ingest:a", 0, 100), Some(Bar::Percent(0)));
+        assert_eq!(progress.update("ingest:a", 0, 100), None);
+        assert_eq!(progress.update("export:b", 50, 100), Some(Bar::Percent(25)));
+        assert_eq!(
+            progress.update("ingest:a", 100, 100),
+            Some(Bar::Percent(50))
+        );
+        assert_eq!(progress.finish("export:b"), Some(Bar::Hidden));
+        assert_eq!(progress.finish("export:b"), None);
+    }
+
+    #[test]
+    fn test_unknown_total_is_indeterminate() {
+        let mut progress = TaskbarProgress::default();
+        progress.update("ingest:a", 10, 100);
+        assert_eq!(
+            progress.update("kernel:sync", 3, 0),
+            Some(Bar::Indeterminate)
+        );
+        assert_eq!(progress.finish("kernel:sync"), Some(Bar::Percent(10)));
+        progress.update("kernel:pull", 0, 0);
+        assert_eq!(progress.finish_kernel_jobs(), Some(Bar::Percent(10)));
+    }
+
+    #[test]
+    fn test_caps_jobs() {
+        let mut progress = TaskbarProgress::default();
+        for i in 0..MAX_JOBS {
+            progress.update(&format!("job-{i}"), 0, 10);
+        }
+        assert_eq!(progress.update("one-more", 0, 0), None);
+        assert!(!progress.jobs.contains_key("one-more"));
+    }
+
+    #[test]
+    fn test_parses_kernel_event() {
+        let event = json!({ "method": "event", "params": {
+            "tag": "progress", "job": "model-pull:ab12", "done": 5, "total": 10
+        } });
+        assert_eq!(
+            KernelProgress::from_kernel_event(&event),
+            Some(KernelProgress {
+                job: "kernel:model-pull:ab12".to_string(),
+                done: 5,
+                total: 10,
+                failed: false,
+            })
+        );
+        let notify = json!({ "params": { "tag": "notify", "title": "Hi" } });
+        assert_eq!(KernelProgress::from_kernel_event(&notify), None);
+        let no_job = json!({ "params": { "tag": "progress", "done": 1 } });
+        assert_eq!(KernelProgress::from_kernel_event(&no_job), None);
+    }
+}
@@ -0,0 +1,458 @@
+//! OpenTelemetry Export (optional)
+//!
+//! Builds with the `otel` feature can send spans and metrics over OTLP/HTTP
+//! to a collector, so a fleet of installs can be watched centrally. It is
+//! off unless `telemetry.json` in the app data dir names an endpoint:
+//! `{ "otlp_endpoint": "https://otel.example.com:4318",
+//!    "headers": { "authorization": "Bearer ..." },
+//!    "traces": true, "metrics": true, "metrics_interval_secs": 60 }`
+//!
+//! The endpoint must use https (plain http only to a collector on this
+//! machine); `/v1/traces` and `/v1/metrics` are appended to it.
+//!
+//! - Traces: the `command` and `kernel_request` spans with their fields and
+//!   timing. Events are not exported; log lines stay in the redacted files.
+//! - Metrics: `reos.kernel.request.duration` (histogram by method and
+//!   outcome), `reos.app.crashes`, and the `metrics` registry's counters
+//!   (`reos.kernel.requests`, `reos.kernel.request.errors`,
+//!   `reos.kernel.starts`, `reos.kernel.exits`, `reos.auth.failures`) and
+//!   gauges (`reos.kernel.queue_depth`, `reos.kernel.resident_memory`,
+//!   `reos.kernel.cpu`), read at each export.
+//!
+//! Without the feature, a configured endpoint only logs a warning.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+use url::Url;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TelemetryError {
+    #[error("telemetry is not configured")]
+    Disabled,
+    #[error("OTLP endpoint must be https (or http to localhost)")]
+    InsecureUrl,
+    #[error("this build has no OpenTelemetry support (feature `otel`)")]
+    Unsupported,
+    #[error("OTLP exporter: {0}")]
+    Exporter(String),
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// Collector base URL (`None` = off)
+    pub otlp_endpoint: Option<String>,
+    /// Sent with every export (collector API keys)
+    pub headers: BTreeMap<String, String>,
+    pub service_name: String,
+    pub traces: bool,
+    pub metrics: bool,
+    pub metrics_interval_secs: u64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            headers: BTreeMap::new(),
+            service_name: "reos".to_string(),
+            traces: true,
+            metrics: true,
+            metrics_interval_secs: 60,
+        }
+    }
+}
+
+impl TelemetryConfig {
+    /// Load from `path`; missing or invalid file means defaults
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    /// The collector URL, if one is configured and acceptable
+    pub fn endpoint(&self) -> Result<Url, TelemetryError> {
+        let endpoint = self
+            .otlp_endpoint
+            .as_deref()
+            .ok_or(TelemetryError::Disabled)?;
+        let url = Url::parse(endpoint).map_err(|_| TelemetryError::InsecureUrl)?;
+        let loopback = match url.host() {
+            Some(url::Host::Domain(domain)) => domain == "localhost",
+            Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+            Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+            None => return Err(TelemetryError::InsecureUrl),
+        };
+        match url.scheme() {
+            "https" => Ok(url),
+            "http" if loopback => Ok(url),
+            _ => Err(TelemetryError::InsecureUrl),
+        }
+    }
+
+    #[cfg_attr(not(feature = "otel"), allow(dead_code))]
+    fn metrics_interval(&self) -> Duration {
+        Duration::from_secs(self.metrics_interval_secs.max(1))
+    }
+}
+
+/// `base` with `path` appended (keeping any path prefix of the collector)
+#[cfg_attr(not(feature = "otel"), allow(dead_code))]
+fn signal_url(base: &Url, path: &str) -> String {
+    format!("{}/{path}", base.as_str().trim_end_matches('/'))
+}
+
+/// Start exporting as configured (at startup, once)
+pub fn init(config: &TelemetryConfig, app_version: &str) {
+    let endpoint = match config.endpoint() {
+        Ok(endpoint) => endpoint,
+        Err(TelemetryError::Disabled) => return,
+        Err(e) => {
+            tracing::warn!(error = %e, "telemetry disabled");
+            return;
+        }
+    };
+    match start(config, &endpoint, app_version) {
+        Ok(()) => tracing::info!(endpoint = %endpoint, "exporting telemetry"),
+        Err(e) => tracing::warn!(error = %e, "telemetry disabled"),
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+fn start(
+    _config: &TelemetryConfig,
+    _endpoint: &Url,
+    _app_version: &str,
+) -> Result<(), TelemetryError> {
+    Err(TelemetryError::Unsupported)
+}
+
+/// A finished kernel request (`method` as capped by `metrics`)
+#[cfg(not(feature = "otel"))]
+pub fn record_request(_method: &str, _elapsed: Duration, _ok: bool) {}
+
+/// The app panicked
+#[cfg(not(feature = "otel"))]
+pub fn record_crash() {}
+
+/// Flush and stop the exporters (at exit)
+#[cfg(not(feature = "otel"))]
+pub fn shutdown() {}
+
+#[cfg(feature = "otel")]
+use otlp::start;
+#[cfg(feature = "otel")]
+pub use otlp::{record_crash, record_request, shutdown, SpanLayer};
+
+#[cfg(feature = "otel")]
+mod otlp {
+    use opentelemetry::metrics::{Counter, Histogram, MeterProvider as _};
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig, WithHttpConfig};
+    use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+    use opentelemetry_sdk::trace::{Tracer, TracerProvider};
+    use opentelemetry_sdk::{runtime, Resource};
+    use std::collections::HashMap;
+    use std::sync::OnceLock;
+    use std::time::Duration;
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Dispatch, Subscriber};
+    use tracing_opentelemetry::OpenTelemetryLayer;
+    use tracing_subscriber::layer::{Context, Layer};
+    use url::Url;
+
+    use super::{signal_url, TelemetryConfig, TelemetryError};
+    use crate::kernel_resources;
+    use crate::logging::{self, LayerBase};
+    use crate::metrics;
+
+    /// Exports spans (names, fields, timing) but drops events, which may
+    /// hold unredacted text
+    pub struct SpansOnly<L>(L);
+
+    impl<S: Subscriber, L: Layer<S>> Layer<S> for SpansOnly<L> {
+        fn on_register_dispatch(&self, subscriber: &Dispatch) {
+            self.0.on_register_dispatch(subscriber);
+        }
+
+        fn on_layer(&mut self, subscriber: &mut S) {
+            self.0.on_layer(subscriber);
+        }
+
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            self.0.on_new_span(attrs, id, ctx);
+        }
+
+        fn on_record(&self, span: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+            self.0.on_record(span, values, ctx);
+        }
+
+        fn on_follows_from(&self, span: &Id, follows: &Id, ctx: Context<'_, S>) {
+            self.0.on_follows_from(span, follows, ctx);
+        }
+
+        fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+            self.0.on_enter(id, ctx);
+        }
+
+        fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+            self.0.on_exit(id, ctx);
+        }
+
+        fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+            self.0.on_close(id, ctx);
+        }
+
+        fn on_id_change(&self, old: &Id, new: &Id, ctx: Context<'_, S>) {
+            self.0.on_id_change(old, new, ctx);
+        }
+    }
+
+    /// Slot in the subscriber for the span exporter (empty until `init`)
+    pub type SpanLayer = Option<SpansOnly<OpenTelemetryLayer<LayerBase, Tracer>>>;
+
+    struct Telemetry {
+        tracer_provider: Option<TracerProvider>,
+        meter_provider: Option<SdkMeterProvider>,
+        request_duration: Option<Histogram<f64>>,
+        crashes: Option<Counter<u64>>,
+    }
+
+    static TELEMETRY: OnceLock<Telemetry> = OnceLock::new();
+
+    fn exporter_error(e: impl std::fmt::Display) -> TelemetryError {
+        TelemetryError::Exporter(e.to_string())
+    }
+
+    fn tracer_provider(
+        endpoint: &Url,
+        resource: Resource,
+        headers: HashMap<String, String>,
+    ) -> Result<TracerProvider, TelemetryError> {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(signal_url(endpoint, "v1/traces"))
+            .with_headers(headers)
+            .build()
+            .map_err(exporter_error)?;
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_resource(resource)
+            .build();
+        let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("reos-tauri"));
+        logging::set_span_layer(Some(SpansOnly(layer))).map_err(exporter_error)?;
+        Ok(provider)
+    }
+
+    fn meter_provider(
+        config: &TelemetryConfig,
+        endpoint: &Url,
+        resource: Resource,
+        headers: HashMap<String, String>,
+    ) -> Result<SdkMeterProvider, TelemetryError> {
+        let exporter = MetricExporter::builder()
+            .with_http()
+            .with_endpoint(signal_url(endpoint, "v1/metrics"))
+            .with_headers(headers)
+            .build()
+            .map_err(exporter_error)?;
+        let reader = PeriodicReader::builder(exporter, runtime::Tokio)
+            .with_interval(config.metrics_interval())
+            .build();
+        let provider = SdkMeterProvider::builder()
+            .with_reader(reader)
+            .with_resource(resource)
+            .build();
+
+        let meter = provider.meter("reos-tauri");
+        meter
+            .u64_observable_counter("reos.kernel.requests")
+            .with_callback(|observer| {
+                for (method, stats) in metrics::snapshot().requests {
+                    observer.observe(stats.count, &[KeyValue::new("method", method)]);
+                }
+            })
+            .build();
+        meter
+            .u64_observable_counter("reos.kernel.request.errors")
+            .with_callback(|observer| {
+                for (method, stats) in metrics::snapshot().requests {
+                    observer.observe(stats.errors, &[KeyValue::new("method", method)]);
+                }
+            })
+            .build();
+        meter
+            .u64_observable_counter("reos.kernel.starts")
+            .with_callback(|observer| observer.observe(metrics::snapshot().kernel_starts, &[]))
+            .build();
+        meter
+            .u64_observable_counter("reos.kernel.exits")
+            .with_callback(|observer| observer.observe(metrics::snapshot().kernel_exits, &[]))
+            .build();
+        meter
+            .u64_observable_gauge("reos.kernel.queue_depth")
+            .with_callback(|observer| observer.observe(metrics::queue_depth(), &[]))
+            .build();
+        meter
+            .u64_observable_gauge("reos.kernel.resident_memory")
+            .with_unit("By")
+            .with_callback(|observer| {
+                if let Some(usage) = kernel_resources::latest() {
+                    observer.observe(usage.rss_bytes, &[]);
+                }
+            })
+            .build();
+        meter
+            .f64_observable_gauge("reos.kernel.cpu")
+            .with_unit("%")
+            .with_callback(|observer| {
+                if let Some(cpu) = kernel_resources::latest().and_then(|u| u.cpu_percent) {
+                    observer.observe(cpu, &[]);
+                }
+            })
+            .build();
+        meter
+            .u64_observable_counter("reos.auth.failures")
+            .with_callback(|observer| {
+                for (event, count) in metrics::snapshot().auth_failures {
+                    observer.observe(count, &[KeyValue::new("event", event)]);
+                }
+            })
+            .build();
+        Ok(provider)
+    }
+
+    pub(super) fn start(
+        config: &TelemetryConfig,
+        endpoint: &Url,
+        app_version: &str,
+    ) -> Result<(), TelemetryError> {
+        // The batch exporters run on Tauri's Tokio runtime
+        let _runtime = tauri::async_runtime::handle().inner().enter();
+        let resource = Resource::new([
+            KeyValue::new("service.name", config.service_name.clone()),
+            KeyValue::new("service.version", app_version.to_string()),
+            KeyValue::new("os.type", std::env::consts::OS),
+        ]);
+        let headers: HashMap<String, String> = config
+            .headers
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+
+        let tracer_provider = config
+            .traces
+            .then(|| tracer_provider(endpoint, resource.clone(), headers.clone()))
+            .transpose()?;
+        let meter_provider = config
+            .metrics
+            .then(|| meter_provider(config, endpoint, resource, headers))
+            .transpose()?;
+        let meter = meter_provider
+            .as_ref()
+            .map(|provider| provider.meter("reos-tauri"));
+        let telemetry = Telemetry {
+            tracer_provider,
+            meter_provider,
+            request_duration: meter.as_ref().map(|meter| {
+                meter
+                    .f64_histogram("reos.kernel.request.duration")
+                    .with_unit("ms")
+                    .build()
+            }),
+            crashes: meter
+                .as_ref()
+                .map(|meter| meter.u64_counter("reos.app.crashes").build()),
+        };
+        let _ = TELEMETRY.set(telemetry);
+        Ok(())
+    }
+
+    /// A finished kernel request (`method` as capped by `metrics`)
+    pub fn record_request(method: &str, elapsed: Duration, ok: bool) {
+        if let Some(histogram) = TELEMETRY.get().and_then(|t| t.request_duration.as_ref()) {
+            histogram.record(
+                elapsed.as_secs_f64() * 1000.0,
+                &[
+                    KeyValue::new("method", method.to_string()),
+                    KeyValue::new("ok", ok),
+                ],
+            );
+        }
+    }
+
+    /// The app panicked (exported with the next interval if it survives)
+    pub fn record_crash() {
+        if let Some(crashes) = TELEMETRY.get().and_then(|t| t.crashes.as_ref()) {
+            crashes.add(1, &[]);
+        }
+    }
+
+    /// Flush and stop the exporters (at exit)
+    pub fn shutdown() {
+        let Some(telemetry) = TELEMETRY.get() else {
+            return;
+        };
+        if let Some(provider) = &telemetry.tracer_provider {
+            let _ = logging::set_span_layer(None);
+            if let Err(e) = provider.shutdown() {
+                tracing::warn!(error = %e, "failed to flush spans");
+            }
+        }
+        if let Some(provider) = &telemetry.meter_provider {
+            if let Err(e) = provider.shutdown() {
+                tracing::warn!(error = %e, "failed to flush metrics");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(endpoint: &str) -> TelemetryConfig {
+        TelemetryConfig {
+            otlp_endpoint: Some(endpoint.to_string()),
+            ..TelemetryConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_endpoint_requires_https_off_this_machine() {
+        assert!(config("https://otel.example.com:4318").endpoint().is_ok());
+        assert!(config("http://localhost:4318").endpoint().is_ok());
+        assert!(config("http://127.0.0.1:4318").endpoint().is_ok());
+        assert_eq!(
+            config("http://otel.example.com:4318").endpoint(),
+            Err(TelemetryError::InsecureUrl)
+        );
+        assert_eq!(
+            config("otel.example.com").endpoint(),
+            Err(TelemetryError::InsecureUrl)
+        );
+        assert_eq!(
+            TelemetryConfig::default().endpoint(),
+            Err(TelemetryError::Disabled)
+        );
+    }
+
+    #[test]
+    fn test_signal_url_keeps_prefix() {
+        let base = Url::parse("https://otel.example.com/collector/").unwrap();
+        assert_eq!(
+            signal_url(&base, "v1/traces"),
+            "https://otel.example.com/collector/v1/traces"
+        );
+        let base = Url::parse("https://otel.example.com:4318").unwrap();
+        assert_eq!(
+            signal_url(&base, "v1/metrics"),
+            "https://otel.example.com:4318/v1/metrics"
+        );
+    }
+}
@@ -0,0 +1,510 @@
+//! TOTP Second Factor for ReOS
+//!
+//! After the kernel accepts the user's PAM credentials, users with an
+//! enrolled authenticator must also present a time-based one-time code
+//! (RFC 6238, SHA-1, 6 digits, 30 second steps) before Rust hands the
+//! session token to the frontend.
+//!
+//! Flow:
+//! - `auth_login` succeeds in the kernel -> if enrolled, Rust parks the
+//!   session token under a short-lived challenge id instead of storing it
+//! - Frontend calls `auth_verify_totp` with the challenge id and code
+//! - On success the parked session is promoted into the `SessionStore`
+//!
+//! Enrolled secrets are kept in `totp.json` in the app data directory,
+//! readable only by the owning user (same model as pam_google_authenticator).
+//! If that file exists but can't be read or parsed, nobody can tell who is
+//! enrolled, so every login is refused at the second factor until it is
+//! repaired (or removed).
+
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+use crate::auth;
+use crate::secret::{constant_time_eq, SecretString};
+use crate::storage::write_private_file;
+
+/// Length of a TOTP time step
+const TOTP_STEP_SECS: u64 = 30;
+/// Number of digits in a code
+const TOTP_DIGITS: u32 = 6;
+/// Accept codes from one step before/after to tolerate clock drift
+const TOTP_SKEW_STEPS: i64 = 1;
+/// How long a pending second-factor challenge stays valid
+const CHALLENGE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// Wrong codes allowed per challenge before it is discarded
+const CHALLENGE_MAX_ATTEMPTS: u32 = 5;
+/// Issuer shown in authenticator apps
+const TOTP_ISSUER: &str = "Talking Rock";
+
+#[derive(Debug, Error)]
+pub enum TotpError {
+    #[error("unknown or expired challenge")]
+    UnknownChallenge,
+    #[error("invalid code")]
+    InvalidCode,
+    #[error("too many invalid codes")]
+    TooManyAttempts { session_token: SecretString },
+    #[error("no enrollment in progress")]
+    NoPendingEnrollment,
+    #[error("failed to save TOTP secrets: {0}")]
+    Persist(String),
+    #[error("TOTP secrets unreadable, second factor unavailable: {0}")]
+    Unreadable(String),
+}
+
+/// An enrolled TOTP secret
+#[derive(Serialize, Deserialize, Clone)]
+struct TotpRecord {
+    /// Base32-encoded shared secret
+    secret: String,
+    /// Last time step accepted (prevents code replay)
+    #[serde(default)]
+    last_step: u64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct TotpFile {
+    users: HashMap<String, TotpRecord>,
+}
+
+/// A PAM-authenticated login waiting for its second factor
+struct PendingLogin {
+    session_token: SecretString,
+    username: String,
+    created_at: Instant,
+    attempts: u32,
+}
+
+/// Secret handed to the frontend during enrollment (shown as QR / text)
+#[derive(Serialize, Clone)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// TOTP enrollment records plus in-flight challenges
+pub struct TotpManager {
+    path: Option<PathBuf>,
+    users: HashMap<String, TotpRecord>,
+    /// Why `totp.json` couldn't be loaded (fails every second factor closed)
+    unreadable: Option<String>,
+    pending_enrollments: HashMap<String, Vec<u8>>,
+    pending_logins: HashMap<String, PendingLogin>,
+}
+
+impl TotpManager {
+    /// Load enrolled secrets from `path` (missing file = no enrollments)
+    ///
+    /// Any other read or parse failure is kept and fails closed: every user
+    /// counts as enrolled and no code is accepted.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let loaded = match path.as_ref().map(std::fs::read_to_string) {
+            None => Ok(TotpFile::default()),
+            Some(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => Ok(TotpFile::default()),
+            Some(Err(e)) => Err(e.to_string()),
+            Some(Ok(s)) => serde_json::from_str::<TotpFile>(&s).map_err(|e| e.to_string()),
+        };
+        let (users, unreadable) = match loaded {
+            Ok(file) => (file.users, None),
+            Err(e) => (HashMap::new(), Some(e)),
+        };
+        Self {
+            path,
+            users,
+            unreadable,
+            pending_enrollments: HashMap::new(),
+            pending_logins: HashMap::new(),
+        }
+    }
+
+    /// Why the secrets couldn't be loaded, if they couldn't
+    pub fn load_error(&self) -> Option<&str> {
+        self.unreadable.as_deref()
+    }
+
+    fn save(&self) -> Result<(), TotpError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        // Don't clobber a file we couldn't read
+        if let Some(e) = &self.unreadable {
+            return Err(TotpError::Unreadable(e.clone()));
+        }
+        let file = TotpFile {
+            users: self.users.clone(),
+        };
+        let data =
+            serde_json::to_vec_pretty(&file).map_err(|e| TotpError::Persist(e.to_string()))?;
+        write_private_file(path, &data).map_err(|e| TotpError::Persist(e.to_string()))
+    }
+
+    /// Whether `username` must present a TOTP code at login
+    pub fn is_enrolled(&self, username: &str) -> bool {
+        self.unreadable.is_some() || self.users.contains_key(username)
+    }
+
+    /// Park a PAM-authenticated session until the second factor arrives
+    ///
+    /// Also hands back the session tokens of challenges that timed out, for
+    /// the caller to log out of the kernel.
+    pub fn begin_challenge(
+        &mut self,
+        session_token: SecretString,
+        username: String,
+    ) -> (String, Vec<SecretString>) {
+        let expired: Vec<String> = self
+            .pending_logins
+            .iter()
+            .filter(|(_, p)| p.created_at.elapsed() > CHALLENGE_TIMEOUT)
+            .map(|(id, _)| id.clone())
+            .collect();
+        let expired = expired
+            .iter()
+            .filter_map(|id| self.pending_logins.remove(id))
+            .map(|p| p.session_token)
+            .collect();
+        let challenge_id = auth::generate_session_token();
+        self.pending_logins.insert(
+            challenge_id.clone(),
+            PendingLogin {
+                session_token,
+                username,
+                created_at: Instant::now(),
+                attempts: 0,
+            },
+        );
+        (challenge_id, expired)
+    }
+
+    /// Check a code against a pending challenge.
+    ///
+    /// Returns the parked `(session_token, username)` on success.
+    pub fn complete_challenge(
+        &mut self,
+        challenge_id: &str,
+        code: &str,
+    ) -> Result<(SecretString, String), TotpError> {
+        if let Some(e) = &self.unreadable {
+            return Err(TotpError::Unreadable(e.clone()));
+        }
+        let pending = self
+            .pending_logins
+            .get_mut(challenge_id)
+            .filter(|p| p.created_at.elapsed() <= CHALLENGE_TIMEOUT)
+            .ok_or(TotpError::UnknownChallenge)?;
+
+        let record = self
+            .users
+            .get_mut(&pending.username)
+            .ok_or(TotpError::UnknownChallenge)?;
+
+        match verify_code(&record.secret, code, unix_now(), record.last_step) {
+            Some(step) => {
+                record.last_step = step;
+                let pending = self
+                    .pending_logins
+                    .remove(challenge_id)
+                    .ok_or(TotpError::UnknownChallenge)?;
+                // Replay protection is best-effort if the file can't be written
+                let _ = self.save();
+                Ok((pending.session_token, pending.username))
+            }
+            None => {
+                pending.attempts += 1;
+                if pending.attempts >= CHALLENGE_MAX_ATTEMPTS {
+                    let pending = self
+                        .pending_logins
+                        .remove(challenge_id)
+                        .ok_or(TotpError::UnknownChallenge)?;
+                    return Err(TotpError::TooManyAttempts {
+                        session_token: pending.session_token,
+                    });
+                }
+                Err(TotpError::InvalidCode)
+            }
+        }
+    }
+
+    /// Check a code from `username`'s current authenticator outside a login
+    /// (consumes the code like a login would)
+    pub fn verify_current(&mut self, username: &str, code: &str) -> Result<(), TotpError> {
+        if let Some(e) = &self.unreadable {
+            return Err(TotpError::Unreadable(e.clone()));
+        }
+        let record = self.users.get_mut(username).ok_or(TotpError::InvalidCode)?;
+        let step = verify_code(&record.secret, code, unix_now(), record.last_step)
+            .ok_or(TotpError::InvalidCode)?;
+        record.last_step = step;
+        self.save()
+    }
+
+    /// Generate a fresh secret for `username` (not active until confirmed)
+    ///
+    /// Re-enrolling replaces the current secret on confirmation, so callers
+    /// must have checked the user for that first (see `auth_totp_enroll`).
+    pub fn begin_enrollment(&mut self, username: &str) -> TotpEnrollment {
+        let mut secret = vec![0u8; 20];
+        rand::rngs::OsRng.fill_bytes(&mut secret);
+        let encoded = data_encoding::BASE32_NOPAD.encode(&secret);
+        self.pending_enrollments
+            .insert(username.to_string(), secret);
+
+        TotpEnrollment {
+            otpauth_uri: format!(
+                "otpauth://totp/{issuer}:{user}?secret={encoded}&issuer={issuer}&digits={TOTP_DIGITS}&period={TOTP_STEP_SECS}",
+                issuer = utf8_percent_encode(TOTP_ISSUER, NON_ALPHANUMERIC),
+                user = utf8_percent_encode(username, NON_ALPHANUMERIC),
+            ),
+            secret: encoded,
+        }
+    }
+
+    /// Activate a pending enrollment once the user proves their app produces valid codes
+    pub fn confirm_enrollment(&mut self, username: &str, code: &str) -> Result<(), TotpError> {
+        let secret = self
+            .pending_enrollments
+            .get(username)
+            .ok_or(TotpError::NoPendingEnrollment)?;
+        let encoded = data_encoding::BASE32_NOPAD.encode(secret);
+        let step = verify_code(&encoded, code, unix_now(), 0).ok_or(TotpError::InvalidCode)?;
+
+        self.pending_enrollments.remove(username);
+        self.users.insert(
+            username.to_string(),
+            TotpRecord {
+                secret: encoded,
+                last_step: step,
+            },
+        );
+        self.save()
+    }
+
+    /// Turn off `username`'s second factor (and drop an enrollment in
+    /// progress); whether one was enrolled
+    pub fn disable(&mut self, username: &str) -> Result<bool, TotpError> {
+        if let Some(e) = &self.unreadable {
+            return Err(TotpError::Unreadable(e.clone()));
+        }
+        self.pending_enrollments.remove(username);
+        if self.users.remove(username).is_none() {
+            return Ok(false);
+        }
+        self.save().map(|()| true)
+    }
+}
+
+/// Thread-safe TOTP state
+pub struct TotpState(pub Mutex<TotpManager>);
+
+impl TotpState {
+    pub fn load(path: Option<PathBuf>) -> Self {
+        Self(Mutex::new(TotpManager::load(path)))
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// HOTP (RFC 4226) truncated to `TOTP_DIGITS`
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = (u32::from(hash[offset]) & 0x7f) << 24
+        | u32::from(hash[offset + 1]) << 16
+        | u32::from(hash[offset + 2]) << 8
+        | u32::from(hash[offset + 3]);
+    binary % 10u32.pow(TOTP_DIGITS)
+}
+
+/// Verify `code` at `now`, returning the matched time step.
+///
+/// Steps at or before `last_step` are rejected so a code can't be replayed.
+fn verify_code(secret_b32: &str, code: &str, now: u64, last_step: u64) -> Option<u64> {
+    let secret = data_encoding::BASE32_NOPAD
+        .decode(secret_b32.as_bytes())
+        .ok()?;
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let current = (now / TOTP_STEP_SECS) as i64;
+    let mut matched = None;
+    // Check every candidate step so timing doesn't reveal which one matched
+    for delta in -TOTP_SKEW_STEPS..=TOTP_SKEW_STEPS {
+        let step = current + delta;
+        if step < 0 || step as u64 <= last_step {
+            continue;
+        }
+        let expected = format!(
+            "{:0width$}",
+            hotp(&secret, step as u64),
+            width = TOTP_DIGITS as usize
+        );
+        if constant_time_eq(expected.as_bytes(), code.as_bytes()) {
+            matched = Some(step as u64);
+        }
+    }
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238 Appendix B test secret ("12345678901234567890")
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        assert_eq!(verify_code(RFC_SECRET, "287082", 59, 0), Some(1));
+        assert_eq!(
+            verify_code(RFC_SECRET, "081804", 1111111109, 0),
+            Some(37037036)
+        );
+        assert_eq!(verify_code(RFC_SECRET, "000000", 59, 0), None);
+    }
+
+    #[test]
+    fn test_replayed_code_rejected() {
+        let step = verify_code(RFC_SECRET, "287082", 59, 0).unwrap();
+        assert_eq!(verify_code(RFC_SECRET, "287082", 59, step), None);
+    }
+
+    #[test]
+    fn test_challenge_attempt_limit() {
+        let mut manager = TotpManager::load(None);
+        manager.users.insert(
+            "alice".to_string(),
+            TotpRecord {
+                secret: RFC_SECRET.to_string(),
+                last_step: 0,
+            },
+        );
+        let (challenge, _) =
+            manager.begin_challenge(SecretString::new("tok".to_string()), "alice".to_string());
+        for _ in 0..CHALLENGE_MAX_ATTEMPTS - 1 {
+            assert!(matches!(
+                manager.complete_challenge(&challenge, "abc"),
+                Err(TotpError::InvalidCode)
+            ));
+        }
+        match manager.complete_challenge(&challenge, "abc") {
+            Err(TotpError::TooManyAttempts { session_token }) => {
+                assert_eq!(session_token.expose(), "tok")
+            }
+            _ => panic!("expected TooManyAttempts"),
+        }
+        assert!(matches!(
+            manager.complete_challenge(&challenge, "abc"),
+            Err(TotpError::UnknownChallenge)
+        ));
+    }
+
+    #[test]
+    fn test_expired_challenges_handed_back() {
+        let mut manager = TotpManager::load(None);
+        manager.pending_logins.insert(
+            "old".to_string(),
+            PendingLogin {
+                session_token: SecretString::new("stale".to_string()),
+                username: "alice".to_string(),
+                created_at: Instant::now() - CHALLENGE_TIMEOUT - Duration::from_secs(1),
+                attempts: 0,
+            },
+        );
+        let (_, expired) =
+            manager.begin_challenge(SecretString::new("tok".to_string()), "bob".to_string());
+        let expired: Vec<&str> = expired.iter().map(|t| t.expose()).collect();
+        assert_eq!(expired, ["stale"]);
+        assert!(!manager.pending_logins.contains_key("old"));
+        assert_eq!(manager.pending_logins.len(), 1);
+    }
+
+    #[test]
+    fn test_unreadable_file_fails_closed() {
+        let dir = std::env::temp_dir().join(format!("reos-totp-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("totp.json");
+        std::fs::write(&path, "{not json").unwrap();
+
+        let mut manager = TotpManager::load(Some(path.clone()));
+        assert!(manager.load_error().is_some());
+        assert!(manager.is_enrolled("anyone"));
+        let (challenge, _) =
+            manager.begin_challenge(SecretString::new("tok".to_string()), "anyone".to_string());
+        assert!(matches!(
+            manager.complete_challenge(&challenge, "287082"),
+            Err(TotpError::Unreadable(_))
+        ));
+        assert!(matches!(
+            manager.disable("anyone"),
+            Err(TotpError::Unreadable(_))
+        ));
+        manager.begin_enrollment("anyone");
+        assert!(manager.confirm_enrollment("anyone", "000000").is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{not json");
+
+        let missing = TotpManager::load(Some(dir.join("missing.json")));
+        assert!(missing.load_error().is_none());
+        assert!(!missing.is_enrolled("anyone"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_otpauth_uri_encodes_username() {
+        let mut manager = TotpManager::load(None);
+        let enrollment = manager.begin_enrollment("a b&issuer=evil");
+        assert!(enrollment
+            .otpauth_uri
+            .starts_with("otpauth://totp/Talking%20Rock:a%20b%26issuer%3Devil?secret="));
+    }
+
+    #[test]
+    fn test_verify_current() {
+        let mut manager = TotpManager::load(None);
+        assert!(manager.verify_current("alice", "287082").is_err());
+        manager.users.insert(
+            "alice".to_string(),
+            TotpRecord {
+                secret: RFC_SECRET.to_string(),
+                last_step: 0,
+            },
+        );
+        assert!(matches!(
+            manager.verify_current("alice", "000000"),
+            Err(TotpError::InvalidCode)
+        ));
+    }
+
+    #[test]
+    fn test_disable() {
+        let mut manager = TotpManager::load(None);
+        manager.users.insert(
+            "alice".to_string(),
+            TotpRecord {
+                secret: RFC_SECRET.to_string(),
+                last_step: 0,
+            },
+        );
+        assert!(manager.disable("alice").unwrap());
+        assert!(!manager.is_enrolled("alice"));
+        assert!(!manager.disable("alice").unwrap());
+    }
+}
@@ -0,0 +1,259 @@
+//! System Tray for ReOS
+//!
+//! A tray icon whose tooltip and menu show kernel health and session lock
+//! state, with Open, Lock, Restart kernel and Quit actions. `main.rs` computes
+//! the `TrayStatus` from `KernelState`/`AuthState` and pushes it here
+//! whenever either changes (session events, kernel start/reset, the sweeper).
+//! Quick actions declared by the kernel (see `quick_actions.rs`) are listed
+//! above Quit.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::menu::{Menu, MenuEvent, MenuItem, MenuItemKind, PredefinedMenuItem};
+use tauri::tray::{TrayIcon, TrayIconBuilder};
+use tauri::{AppHandle, Runtime};
+use tracing::warn;
+
+use crate::locks::Recover;
+
+/// Tray icon id
+const TRAY_ID: &str = "reos";
+
+/// Menu position of the first quick action (just before Quit)
+const ACTIONS_POSITION: usize = 7;
+
+/// Menu item ids
+pub const MENU_SHOW: &str = "tray-show";
+pub const MENU_LOCK: &str = "tray-lock";
+pub const MENU_RESTART_KERNEL: &str = "tray-restart-kernel";
+pub const MENU_QUIT: &str = "tray-quit";
+
+/// Kernel process health as shown in the tray
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelHealth {
+    Running,
+    Restarting,
+    Down,
+}
+
+impl KernelHealth {
+    fn label(self) -> &'static str {
+        match self {
+            KernelHealth::Running => "running",
+            KernelHealth::Restarting => "restarting",
+            KernelHealth::Down => "down",
+        }
+    }
+}
+
+/// Session state as shown in the tray
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockStatus {
+    SignedOut,
+    Unlocked,
+    Locked,
+}
+
+impl LockStatus {
+    /// Locked only when every live session is locked (`true` = locked)
+    pub fn from_sessions(locked: impl IntoIterator<Item = bool>) -> Self {
+        let mut status = LockStatus::SignedOut;
+        for locked in locked {
+            if !locked {
+                return LockStatus::Unlocked;
+            }
+            status = LockStatus::Locked;
+        }
+        status
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            LockStatus::SignedOut => "signed out",
+            LockStatus::Unlocked => "unlocked",
+            LockStatus::Locked => "locked",
+        }
+    }
+}
+
+/// Everything the tray displays
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrayStatus {
+    pub kernel: KernelHealth,
+    pub lock: LockStatus,
+}
+
+impl TrayStatus {
+    pub fn kernel_text(&self) -> String {
+        format!("Kernel: {}", self.kernel.label())
+    }
+
+    pub fn lock_text(&self) -> String {
+        format!("Session: {}", self.lock.label())
+    }
+
+    pub fn tooltip(&self) -> String {
+        format!(
+            "Talking Rock — {}, {}",
+            self.kernel_text(),
+            self.lock_text()
+        )
+    }
+}
+
+/// Tray handles, plus whether a tray-initiated kernel restart is running
+pub struct TrayState<R: Runtime> {
+    tray: TrayIcon<R>,
+    kernel_item: MenuItem<R>,
+    lock_item: MenuItem<R>,
+    lock_action: MenuItem<R>,
+    menu: Menu<R>,
+    /// Quick action items and their separator, currently in `menu`
+    action_items: Mutex<Vec<MenuItemKind<R>>>,
+    restarting: AtomicBool,
+}
+
+impl<R: Runtime> TrayState<R> {
+    /// Show `status` in the tooltip and menu
+    pub fn show(&self, status: TrayStatus) {
+        let result = self
+            .tray
+            .set_tooltip(Some(status.tooltip()))
+            .and_then(|_| self.kernel_item.set_text(status.kernel_text()))
+            .and_then(|_| self.lock_item.set_text(status.lock_text()))
+            .and_then(|_| {
+                self.lock_action
+                    .set_enabled(status.lock == LockStatus::Unlocked)
+            });
+        if let Err(e) = result {
+            warn!(error = %e, "failed to update tray");
+        }
+    }
+
+    /// Mark a restart as started; false if one is already in progress
+    pub fn begin_restart(&self) -> bool {
+        !self.restarting.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn end_restart(&self) {
+        self.restarting.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_restarting(&self) -> bool {
+        self.restarting.load(Ordering::SeqCst)
+    }
+
+    /// Replace the quick action items with `actions` (menu id, title)
+    pub fn set_actions(&self, app: &AppHandle<R>, actions: &[(String, String)]) {
+        let mut items = self.action_items.lock_or_recover();
+        let result = (|| -> tauri::Result<()> {
+            for item in items.drain(..) {
+                self.menu.remove(&item)?;
+            }
+            if actions.is_empty() {
+                return Ok(());
+            }
+            for (id, title) in actions {
+                items.push(MenuItemKind::MenuItem(MenuItem::with_id(
+                    app,
+                    id,
+                    title,
+                    true,
+                    None::<&str>,
+                )?));
+            }
+            items.push(MenuItemKind::Predefined(PredefinedMenuItem::separator(
+                app,
+            )?));
+            for (offset, item) in items.iter().enumerate() {
+                self.menu.insert(item, ACTIONS_POSITION + offset)?;
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            warn!(error = %e, "failed to update tray quick actions");
+        }
+    }
+}
+
+/// Create the tray icon; `on_action` receives the menu item id
+pub fn install<R: Runtime>(
+    app: &AppHandle<R>,
+    on_action: impl Fn(&AppHandle<R>, &str) + Send + Sync + 'static,
+) -> tauri::Result<TrayState<R>> {
+    let initial = TrayStatus {
+        kernel: KernelHealth::Down,
+        lock: LockStatus::SignedOut,
+    };
+    let kernel_item = MenuItem::new(app, initial.kernel_text(), false, None::<&str>)?;
+    let lock_item = MenuItem::new(app, initial.lock_text(), false, None::<&str>)?;
+    let show = MenuItem::with_id(app, MENU_SHOW, "Open", true, None::<&str>)?;
+    let lock_action = MenuItem::with_id(app, MENU_LOCK, "Lock", false, None::<&str>)?;
+    let restart = MenuItem::with_id(
+        app,
+        MENU_RESTART_KERNEL,
+        "Restart kernel",
+        true,
+        None::<&str>,
+    )?;
+    let quit = MenuItem::with_id(app, MENU_QUIT, "Quit", true, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &kernel_item,
+            &lock_item,
+            &PredefinedMenuItem::separator(app)?,
+            &show,
+            &lock_action,
+            &restart,
+            &PredefinedMenuItem::separator(app)?,
+            &quit,
+        ],
+    )?;
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu)
+        .tooltip(initial.tooltip())
+        .on_menu_event(move |app, event: MenuEvent| on_action(app, event.id().as_ref()));
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    let tray = builder.build(app)?;
+
+    Ok(TrayState {
+        tray,
+        kernel_item,
+        lock_item,
+        lock_action,
+        menu,
+        action_items: Mutex::new(Vec::new()),
+        restarting: AtomicBool::new(false),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_status_needs_every_session_locked() {
+        assert_eq!(LockStatus::from_sessions([]), LockStatus::SignedOut);
+        assert_eq!(LockStatus::from_sessions([true, true]), LockStatus::Locked);
+        assert_eq!(
+            LockStatus::from_sessions([true, false]),
+            LockStatus::Unlocked
+        );
+    }
+
+    #[test]
+    fn test_tooltip() {
+        let status = TrayStatus {
+            kernel: KernelHealth::Restarting,
+            lock: LockStatus::Locked,
+        };
+        assert_eq!(
+            status.tooltip(),
+            "Talking Rock — Kernel: restarting, Session: locked"
+        );
+    }
+}
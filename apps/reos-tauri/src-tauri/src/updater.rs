@@ -0,0 +1,523 @@
+//! Signed Application Updates for ReOS
+//!
+//! `update_check` fetches a JSON manifest from the HTTPS endpoint in
+//! `updater.json` (app data dir; no endpoint = updates disabled):
+//! ```json
+//! { "version": "1.4.0", "notes": "...", "url": "https://.../reos.AppImage",
+//!   "sha256": "<hex>", "rollout": 25, "signature": "<hex>" }
+//! ```
+//! The signature is Ed25519 over `signed_message()` (version, url, sha256
+//! and rollout), checked against the public key embedded at build time
+//! (`REOS_UPDATE_SIGNING_KEY`, hex); builds without one never offer
+//! updates. `update_download` then checks the artifact against `sha256`.
+//!
+//! Staged rollout: every install has a random id (`install-id` in the app
+//! data dir) and only takes an update when a hash of that id and the
+//! version lands below `rollout` percent.
+//!
+//! `update_install` asks the kernel to freeze migrations and encrypted
+//! writes (`update/prepare`) while holding the kernel lock, so nothing is
+//! mid-flight when the new build is swapped in; on failure the freeze is
+//! lifted again (`update/resume`). The staged artifact is hashed again
+//! right before it is used, in case it changed on disk since the download.
+//!
+//! Artifacts by platform: an AppImage (Linux, replaced in place), an `.msi`
+//! (Windows, handed to `msiexec`) or an `.app.tar.gz` (macOS, the bundle
+//! is swapped next to the running one).
+//!
+//! This isn't `tauri-plugin-updater` because that plugin installs as soon
+//! as a release verifies: it has no hook to hold the swap until the kernel
+//! is quiescent, no staged rollout, and it would need a second (minisign)
+//! key alongside the Ed25519 keys the kernel manifest already uses.
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use rand::RngCore;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::warn;
+
+use crate::kernel_integrity::parse_key;
+
+/// Update signing public key (hex), embedded by release builds
+const SIGNING_KEY: Option<&str> = option_env!("REOS_UPDATE_SIGNING_KEY");
+
+/// Domain separator (and version) of signed manifests
+const SIGNATURE_DOMAIN: &str = "reos-update-v1";
+
+/// Largest artifact accepted
+const MAX_DOWNLOAD_BYTES: u64 = 512 * 1024 * 1024;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Error)]
+pub enum UpdateError {
+    #[error("Updates are not configured")]
+    Disabled,
+    #[error("Update endpoint must use https")]
+    InsecureUrl,
+    #[error("Failed to fetch update manifest: {0}")]
+    Fetch(String),
+    #[error("Invalid update manifest: {0}")]
+    Malformed(String),
+    #[error("Update signature is invalid")]
+    BadSignature,
+    #[error("No update available")]
+    NoUpdate,
+    #[error("Failed to download update: {0}")]
+    Download(String),
+    #[error("Downloaded update doesn't match its checksum")]
+    ChecksumMismatch,
+    #[error("Kernel busy ({}), try again shortly", .0.join(", "))]
+    KernelBusy(Vec<String>),
+    #[error("Automatic install isn't supported for this build: {0}")]
+    Unsupported(String),
+    #[error("Failed to install update: {0}")]
+    Install(String),
+}
+
+/// Where to look for updates
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct UpdaterConfig {
+    pub endpoint: Option<String>,
+}
+
+impl UpdaterConfig {
+    /// Load the config from `path` (missing or invalid file = updates disabled)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+}
+
+fn full_rollout() -> u8 {
+    100
+}
+
+/// Signed description of a release
+#[derive(Deserialize, Clone, Debug)]
+pub struct UpdateManifest {
+    pub version: String,
+    #[serde(default)]
+    pub notes: String,
+    pub url: String,
+    pub sha256: String,
+    /// Percent of installs offered this release
+    #[serde(default = "full_rollout")]
+    pub rollout: u8,
+    pub signature: String,
+}
+
+impl UpdateManifest {
+    /// Bytes covered by `signature`
+    pub fn signed_message(&self) -> String {
+        format!(
+            "{SIGNATURE_DOMAIN}\n{}\n{}\n{}\n{}",
+            self.version,
+            self.url,
+            self.sha256.to_ascii_lowercase(),
+            self.rollout
+        )
+    }
+
+    pub fn verify(&self, key: &VerifyingKey) -> Result<(), UpdateError> {
+        let signature = hex::decode(self.signature.trim())
+            .ok()
+            .and_then(|sig| Signature::from_slice(&sig).ok())
+            .ok_or(UpdateError::BadSignature)?;
+        key.verify_strict(self.signed_message().as_bytes(), &signature)
+            .map_err(|_| UpdateError::BadSignature)
+    }
+}
+
+/// What the frontend is told about an update
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub notes: String,
+    /// Downloaded, verified and ready for `update_install`
+    pub staged: bool,
+}
+
+/// Whether `install_id` is among the first `rollout` percent for `version`
+pub fn in_rollout(install_id: &str, version: &str, rollout: u8) -> bool {
+    let digest = Sha256::new()
+        .chain_update(install_id.as_bytes())
+        .chain_update(b"\n")
+        .chain_update(version.as_bytes())
+        .finalize();
+    u16::from_be_bytes([digest[0], digest[1]]) % 100 < u16::from(rollout)
+}
+
+/// Accept `manifest` if it is signed, newer than `current` and includes this install
+pub fn evaluate(
+    manifest: &UpdateManifest,
+    key: &VerifyingKey,
+    current: &Version,
+    install_id: &str,
+) -> Result<bool, UpdateError> {
+    manifest.verify(key)?;
+    if !manifest.url.starts_with("https://") {
+        return Err(UpdateError::InsecureUrl);
+    }
+    let version = Version::parse(&manifest.version)
+        .map_err(|e| UpdateError::Malformed(format!("version: {e}")))?;
+    Ok(version > *current && in_rollout(install_id, &manifest.version, manifest.rollout))
+}
+
+/// Updater state: config, install id, and the offered/staged release
+pub struct Updater {
+    config: UpdaterConfig,
+    install_id: String,
+    available: Option<UpdateManifest>,
+    staged: Option<PathBuf>,
+}
+
+impl Updater {
+    /// `install_id_path` holds this install's rollout id (created on first use)
+    pub fn new(config: UpdaterConfig, install_id_path: Option<PathBuf>) -> Self {
+        Self {
+            config,
+            install_id: load_install_id(install_id_path.as_deref()),
+            available: None,
+            staged: None,
+        }
+    }
+
+    /// Endpoint and signing key, or why updates are off
+    pub fn source(&self) -> Result<(String, VerifyingKey), UpdateError> {
+        let endpoint = self.config.endpoint.clone().ok_or(UpdateError::Disabled)?;
+        if !endpoint.starts_with("https://") {
+            return Err(UpdateError::InsecureUrl);
+        }
+        let key = SIGNING_KEY
+            .and_then(parse_key)
+            .ok_or(UpdateError::Disabled)?;
+        Ok((endpoint, key))
+    }
+
+    pub fn install_id(&self) -> &str {
+        &self.install_id
+    }
+
+    /// Record the result of a check (clears a staged artifact for another version)
+    pub fn set_available(&mut self, manifest: Option<UpdateManifest>) {
+        let same = matches!(
+            (&self.available, &manifest),
+            (Some(a), Some(b)) if a.version == b.version && a.sha256 == b.sha256
+        );
+        if !same {
+            if let Some(staged) = self.staged.take() {
+                let _ = fs::remove_file(staged);
+            }
+        }
+        self.available = manifest;
+    }
+
+    pub fn available(&self) -> Option<&UpdateManifest> {
+        self.available.as_ref()
+    }
+
+    pub fn set_staged(&mut self, path: PathBuf) {
+        self.staged = Some(path);
+    }
+
+    pub fn staged(&self) -> Option<&Path> {
+        self.staged.as_deref()
+    }
+
+    pub fn info(&self, current: &Version) -> Option<UpdateInfo> {
+        self.available.as_ref().map(|m| UpdateInfo {
+            version: m.version.clone(),
+            current_version: current.to_string(),
+            notes: m.notes.clone(),
+            staged: self.staged.is_some(),
+        })
+    }
+}
+
+/// Updater state shared by the update commands
+pub struct UpdaterState(pub Mutex<Updater>);
+
+fn load_install_id(path: Option<&Path>) -> String {
+    if let Some(id) = path
+        .and_then(|p| fs::read_to_string(p).ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| s.len() == 32 && s.bytes().all(|b| b.is_ascii_hexdigit()))
+    {
+        return id;
+    }
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let id = hex::encode(bytes);
+    if let Some(path) = path {
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Err(e) = fs::write(path, &id) {
+            warn!(error = %e, "failed to save install id");
+        }
+    }
+    id
+}
+
+/// Fetch the release manifest (blocking)
+pub fn fetch_manifest(endpoint: &str) -> Result<UpdateManifest, UpdateError> {
+    let agent = ureq::AgentBuilder::new().timeout(HTTP_TIMEOUT).build();
+    agent
+        .get(endpoint)
+        .call()
+        .map_err(|e| UpdateError::Fetch(e.to_string()))?
+        .into_json()
+        .map_err(|e| UpdateError::Malformed(e.to_string()))
+}
+
+/// Download the artifact into `dir` and check its hash (blocking)
+pub fn download(manifest: &UpdateManifest, dir: &Path) -> Result<PathBuf, UpdateError> {
+    let download_err = |e: std::io::Error| UpdateError::Download(e.to_string());
+    fs::create_dir_all(dir).map_err(download_err)?;
+    let name = manifest
+        .url
+        .rsplit('/')
+        .next()
+        .filter(|n| !n.is_empty() && !n.contains(['\\', '?', '#']) && *n != "..")
+        .unwrap_or("update");
+    let target = dir.join(format!("{}-{name}", manifest.version));
+    let partial = target.with_extension("part");
+
+    let response = ureq::AgentBuilder::new()
+        .timeout_connect(HTTP_TIMEOUT)
+        .build()
+        .get(&manifest.url)
+        .call()
+        .map_err(|e| UpdateError::Download(e.to_string()))?;
+    let mut reader = response.into_reader().take(MAX_DOWNLOAD_BYTES + 1);
+    let mut file = File::create(&partial).map_err(download_err)?;
+    let mut hasher = Sha256::new();
+    let mut total = 0u64;
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf).map_err(download_err)?;
+        if n == 0 {
+            break;
+        }
+        total += n as u64;
+        if total > MAX_DOWNLOAD_BYTES {
+            let _ = fs::remove_file(&partial);
+            return Err(UpdateError::Download("artifact too large".to_string()));
+        }
+        hasher.update(&buf[..n]);
+        file.write_all(&buf[..n]).map_err(download_err)?;
+    }
+    file.sync_all().map_err(download_err)?;
+    drop(file);
+
+    if !hex::encode(hasher.finalize()).eq_ignore_ascii_case(manifest.sha256.trim()) {
+        let _ = fs::remove_file(&partial);
+        return Err(UpdateError::ChecksumMismatch);
+    }
+    fs::rename(&partial, &target).map_err(download_err)?;
+    Ok(target)
+}
+
+/// Check that the file at `path` still hashes to `sha256`
+pub fn verify_file(path: &Path, sha256: &str) -> Result<(), UpdateError> {
+    let mut file = File::open(path).map_err(|e| UpdateError::Install(e.to_string()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| UpdateError::Install(e.to_string()))?;
+    if !hex::encode(hasher.finalize()).eq_ignore_ascii_case(sha256.trim()) {
+        return Err(UpdateError::ChecksumMismatch);
+    }
+    Ok(())
+}
+
+/// Swap in the staged artifact once it matches `sha256` again; the caller
+/// restarts the app afterwards
+pub fn apply(staged: &Path, sha256: &str) -> Result<(), UpdateError> {
+    let install_err = |e: std::io::Error| UpdateError::Install(e.to_string());
+    if cfg!(target_os = "linux") {
+        // AppImage: replace the image in place (same filesystem rename)
+        let Some(appimage) = std::env::var_os("APPIMAGE").map(PathBuf::from) else {
+            return Err(UpdateError::Unsupported(
+                "not running from an AppImage".to_string(),
+            ));
+        };
+        let next = appimage.with_extension("next");
+        fs::copy(staged, &next).map_err(install_err)?;
+        // Check the copy that is about to be swapped in, not the staged file
+        if let Err(e) = verify_file(&next, sha256) {
+            let _ = fs::remove_file(&next);
+            return Err(e);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&next, fs::Permissions::from_mode(0o755)).map_err(install_err)?;
+        }
+        fs::rename(&next, &appimage).map_err(install_err)?;
+        Ok(())
+    } else if cfg!(target_os = "windows") && staged.extension().is_some_and(|ext| ext == "msi") {
+        verify_file(staged, sha256)?;
+        // The installer replaces the app once it has exited
+        Command::new("msiexec")
+            .arg("/i")
+            .arg(staged)
+            .arg("/passive")
+            .spawn()
+            .map(|_| ())
+            .map_err(install_err)
+    } else if cfg!(target_os = "macos")
+        && staged
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().ends_with(".app.tar.gz"))
+    {
+        verify_file(staged, sha256)?;
+        replace_bundle(staged)
+    } else {
+        Err(UpdateError::Unsupported(format!(
+            "{} artifacts on {}",
+            staged
+                .extension()
+                .map(|e| e.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            std::env::consts::OS
+        )))
+    }
+}
+
+/// Replace the running `.app` bundle with the one packed in `archive`
+/// (macOS); the old bundle is put back if the swap fails
+fn replace_bundle(archive: &Path) -> Result<(), UpdateError> {
+    let install_err = |e: std::io::Error| UpdateError::Install(e.to_string());
+    // .../ReOS.app/Contents/MacOS/<exe>
+    let exe = std::env::current_exe().map_err(install_err)?;
+    let bundle = exe
+        .ancestors()
+        .nth(3)
+        .filter(|p| p.extension().is_some_and(|ext| ext == "app"))
+        .ok_or_else(|| UpdateError::Unsupported("not running from an app bundle".to_string()))?
+        .to_path_buf();
+    let parent = bundle
+        .parent()
+        .ok_or_else(|| UpdateError::Unsupported("not running from an app bundle".to_string()))?;
+
+    // Unpack beside the bundle so the swap is a rename on one filesystem
+    let unpack = parent.join(".reos-update");
+    let previous = parent.join(".reos-previous.app");
+    let _ = fs::remove_dir_all(&unpack);
+    let _ = fs::remove_dir_all(&previous);
+    fs::create_dir_all(&unpack).map_err(install_err)?;
+    let swapped = (|| {
+        let status = Command::new("/usr/bin/tar")
+            .arg("-xzf")
+            .arg(archive)
+            .arg("-C")
+            .arg(&unpack)
+            .status()
+            .map_err(install_err)?;
+        if !status.success() {
+            return Err(UpdateError::Install(format!("tar: {status}")));
+        }
+        let mut bundles = fs::read_dir(&unpack)
+            .map_err(install_err)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "app"));
+        let (Some(next), None) = (bundles.next(), bundles.next()) else {
+            return Err(UpdateError::Malformed(
+                "archive must hold exactly one .app bundle".to_string(),
+            ));
+        };
+        fs::rename(&bundle, &previous).map_err(install_err)?;
+        if let Err(e) = fs::rename(&next, &bundle) {
+            let _ = fs::rename(&previous, &bundle);
+            return Err(install_err(e));
+        }
+        Ok(())
+    })();
+    let _ = fs::remove_dir_all(&unpack);
+    let _ = fs::remove_dir_all(&previous);
+    swapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn signed(version: &str, rollout: u8, key: &SigningKey) -> UpdateManifest {
+        let mut manifest = UpdateManifest {
+            version: version.to_string(),
+            notes: String::new(),
+            url: "https://updates.example/reos.AppImage".to_string(),
+            sha256: "AB".repeat(32),
+            rollout,
+            signature: String::new(),
+        };
+        manifest.signature = hex::encode(key.sign(manifest.signed_message().as_bytes()).to_bytes());
+        manifest
+    }
+
+    #[test]
+    fn test_evaluate_checks_signature_and_version() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let public = key.verifying_key();
+        let current = Version::parse("1.2.0").unwrap();
+
+        assert!(evaluate(&signed("1.3.0", 100, &key), &public, &current, "id").unwrap());
+        assert!(!evaluate(&signed("1.2.0", 100, &key), &public, &current, "id").unwrap());
+
+        let mut tampered = signed("1.3.0", 100, &key);
+        tampered.rollout = 50;
+        assert!(matches!(
+            evaluate(&tampered, &public, &current, "id"),
+            Err(UpdateError::BadSignature)
+        ));
+        let other = SigningKey::from_bytes(&[8u8; 32]);
+        assert!(evaluate(&signed("1.3.0", 100, &other), &public, &current, "id").is_err());
+    }
+
+    #[test]
+    fn test_rollout_buckets() {
+        let ids: Vec<String> = (0..1000).map(|i| format!("{i:032x}")).collect();
+        assert!(ids.iter().all(|id| !in_rollout(id, "1.3.0", 0)));
+        assert!(ids.iter().all(|id| in_rollout(id, "1.3.0", 100)));
+        let quarter = ids.iter().filter(|id| in_rollout(id, "1.3.0", 25)).count();
+        assert!((150..350).contains(&quarter), "{quarter}");
+    }
+
+    #[test]
+    fn test_staged_file_is_rehashed() {
+        let path = std::env::temp_dir().join(format!("reos-staged-{}", std::process::id()));
+        fs::write(&path, b"release").unwrap();
+        let sha256 = hex::encode(Sha256::digest(b"release"));
+        assert!(verify_file(&path, &sha256.to_ascii_uppercase()).is_ok());
+
+        fs::write(&path, b"swapped").unwrap();
+        assert!(matches!(
+            verify_file(&path, &sha256),
+            Err(UpdateError::ChecksumMismatch)
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_install_id_is_persisted() {
+        let path = std::env::temp_dir().join(format!("reos-install-id-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let first = load_install_id(Some(&path));
+        assert_eq!(first.len(), 32);
+        assert_eq!(load_install_id(Some(&path)), first);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,168 @@
+//! Username Validation for ReOS
+//!
+//! Every login path checks the username before anything reaches the
+//! kernel: it must look like a POSIX account name (by default) and pass
+//! the deployment's allow/deny lists. Rejections carry a stable code so
+//! the UI can explain what is wrong.
+//!
+//! Configurable via `username-policy.json` in the app data dir:
+//! `{ "pattern": "^[a-z][a-z0-9]*$", "deny": ["backup"], "forbid_root": true }`
+
+use regex::Regex;
+use serde::Deserialize;
+use std::path::Path;
+use thiserror::Error;
+use tracing::warn;
+
+/// POSIX portable account names (as accepted by `useradd` by default)
+const DEFAULT_PATTERN: &str = r"^[a-z_][a-z0-9_-]*\$?$";
+/// Linux `LOGIN_NAME_MAX` minus the terminating NUL
+const DEFAULT_MAX_LENGTH: usize = 32;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum UsernameError {
+    #[error("Username is required")]
+    Empty,
+    #[error("Username is too long")]
+    TooLong,
+    #[error("Username contains invalid characters")]
+    InvalidFormat,
+    #[error("Logging in as root is not allowed")]
+    RootForbidden,
+    #[error("This account may not log in")]
+    Denied,
+    #[error("This account is not allowed to log in here")]
+    NotAllowed,
+}
+
+impl UsernameError {
+    /// Stable identifier for the UI and the audit log
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Empty => "username_empty",
+            Self::TooLong => "username_too_long",
+            Self::InvalidFormat => "username_invalid",
+            Self::RootForbidden => "username_root_forbidden",
+            Self::Denied => "username_denied",
+            Self::NotAllowed => "username_not_allowed",
+        }
+    }
+}
+
+/// Deployment policy for login usernames
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct UsernamePolicy {
+    /// Regex a username must match
+    pub pattern: String,
+    pub max_length: usize,
+    /// If non-empty, only these accounts may log in
+    pub allow: Vec<String>,
+    /// Accounts that may never log in
+    pub deny: Vec<String>,
+    pub forbid_root: bool,
+}
+
+impl Default for UsernamePolicy {
+    fn default() -> Self {
+        Self {
+            pattern: DEFAULT_PATTERN.to_string(),
+            max_length: DEFAULT_MAX_LENGTH,
+            allow: Vec::new(),
+            deny: Vec::new(),
+            forbid_root: true,
+        }
+    }
+}
+
+impl UsernamePolicy {
+    /// Load the policy from `path` (missing or invalid file = defaults)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Compiled username policy
+pub struct UsernameState {
+    policy: UsernamePolicy,
+    pattern: Regex,
+}
+
+impl UsernameState {
+    pub fn new(policy: UsernamePolicy) -> Self {
+        // A broken custom pattern must not lock everyone out of the app
+        let pattern = Regex::new(&policy.pattern).unwrap_or_else(|e| {
+            warn!(error = %e, "invalid username pattern, using default");
+            Regex::new(DEFAULT_PATTERN).expect("default pattern compiles")
+        });
+        Self { policy, pattern }
+    }
+
+    /// Check `username` against the policy
+    pub fn validate(&self, username: &str) -> Result<(), UsernameError> {
+        let policy = &self.policy;
+        if username.is_empty() {
+            return Err(UsernameError::Empty);
+        }
+        if username.len() > policy.max_length {
+            return Err(UsernameError::TooLong);
+        }
+        if !self.pattern.is_match(username) {
+            return Err(UsernameError::InvalidFormat);
+        }
+        if policy.forbid_root && username == "root" {
+            return Err(UsernameError::RootForbidden);
+        }
+        if policy.deny.iter().any(|d| d == username) {
+            return Err(UsernameError::Denied);
+        }
+        if !policy.allow.is_empty() && !policy.allow.iter().any(|a| a == username) {
+            return Err(UsernameError::NotAllowed);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_policy() {
+        let state = UsernameState::new(UsernamePolicy::default());
+        assert!(state.validate("alice").is_ok());
+        assert!(state.validate("_svc-build$").is_ok());
+        assert_eq!(state.validate(""), Err(UsernameError::Empty));
+        assert_eq!(state.validate(&"a".repeat(33)), Err(UsernameError::TooLong));
+        assert_eq!(state.validate("Alice"), Err(UsernameError::InvalidFormat));
+        assert_eq!(
+            state.validate("a;rm -rf"),
+            Err(UsernameError::InvalidFormat)
+        );
+        assert_eq!(state.validate("root"), Err(UsernameError::RootForbidden));
+    }
+
+    #[test]
+    fn test_allow_and_deny_lists() {
+        let state = UsernameState::new(UsernamePolicy {
+            allow: vec!["alice".into(), "bob".into()],
+            deny: vec!["bob".into()],
+            ..UsernamePolicy::default()
+        });
+        assert!(state.validate("alice").is_ok());
+        assert_eq!(state.validate("bob"), Err(UsernameError::Denied));
+        assert_eq!(state.validate("carol"), Err(UsernameError::NotAllowed));
+    }
+
+    #[test]
+    fn test_invalid_pattern_falls_back() {
+        let state = UsernameState::new(UsernamePolicy {
+            pattern: "([".into(),
+            ..UsernamePolicy::default()
+        });
+        assert!(state.validate("alice").is_ok());
+        assert_eq!(state.validate("Alice"), Err(UsernameError::InvalidFormat));
+    }
+}
@@ -0,0 +1,70 @@
+//! Secrets Vault
+//!
+//! The blessed path for the frontend to keep API keys and similar secrets.
+//! `vault_*` commands check the session and role in Rust, enforce the
+//! limits below, then store entries in the user's encrypted kernel storage
+//! through `vault/*` kernel methods (never forwarded by `kernel_request`).
+//! Entries are per user by construction: the kernel resolves the session
+//! token to that user's key material and data directory.
+
+use thiserror::Error;
+
+/// Longest secret name
+pub const MAX_NAME_LEN: usize = 128;
+/// Largest secret value (64 KiB)
+pub const MAX_VALUE_BYTES: usize = 64 * 1024;
+/// Most secrets one user can keep
+pub const MAX_ENTRIES: usize = 256;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VaultError {
+    #[error("Secret names are 1-{MAX_NAME_LEN} characters of letters, digits, '.', '_' or '-', not starting with '.'")]
+    InvalidName,
+    #[error("Secret is larger than {MAX_VALUE_BYTES} bytes")]
+    TooLarge,
+    #[error("Guest sessions have no vault")]
+    GuestSession,
+}
+
+/// Names become file names in the kernel (mirrored by `reos.vault`)
+pub fn validate_name(name: &str) -> Result<(), VaultError> {
+    let valid_chars = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if name.is_empty() || name.len() > MAX_NAME_LEN || name.starts_with('.') || !valid_chars {
+        return Err(VaultError::InvalidName);
+    }
+    Ok(())
+}
+
+pub fn validate_value(value: &str) -> Result<(), VaultError> {
+    if value.len() > MAX_VALUE_BYTES {
+        return Err(VaultError::TooLarge);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names() {
+        assert!(validate_name("openai.api_key").is_ok());
+        assert!(validate_name("github-token_2").is_ok());
+        assert!(validate_name(&"a".repeat(MAX_NAME_LEN)).is_ok());
+        for bad in ["", ".hidden", "../escape", "a/b", "ключ", "a b"] {
+            assert_eq!(validate_name(bad), Err(VaultError::InvalidName), "{bad}");
+        }
+        assert!(validate_name(&"a".repeat(MAX_NAME_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_value_size() {
+        assert!(validate_value(&"x".repeat(MAX_VALUE_BYTES)).is_ok());
+        assert_eq!(
+            validate_value(&"x".repeat(MAX_VALUE_BYTES + 1)),
+            Err(VaultError::TooLarge)
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! Post-Login Warmup
+//!
+//! A fresh session's first screen pays for opening the encrypted store,
+//! loading the system index and filling the kernel's caches. Once a login
+//! succeeds, the shell sends a few requests on the user's behalf in the
+//! background, at `Priority::Background` in the scheduler, so that cost is
+//! paid while the user is still looking at the first screen:
+//!
+//! - `warmup/hint` (the shell's own method, with the kernel session token):
+//!   `store`, `index` or `caches`
+//! - any other method, only if the frontend could call it for this
+//!   session without a prompt: allowlisted, permitted for the role, no
+//!   consent rule and no recent-password requirement
+//!
+//! Requests are sent one at a time after `delay_ms`, and stop as soon as
+//! the session ends or locks. Results are dropped; failures are logged.
+//! Guest sessions get no warmup.
+//!
+//! `warmup.json` in the app data dir (missing or invalid file = defaults):
+//! `{ "enabled": true, "delay_ms": 1500, "requests": [{ "method": "warmup/hint", "params": { "hint": "store" } }] }`
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+
+/// The shell's own warmup method (never on the frontend allowlist)
+pub const HINT_METHOD: &str = "warmup/hint";
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct WarmupRequest {
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+impl WarmupRequest {
+    fn hint(hint: &str) -> Self {
+        Self {
+            method: HINT_METHOD.to_string(),
+            params: json!({ "hint": hint }),
+        }
+    }
+
+    /// Whether this is a `warmup/hint` (sent with the kernel session token)
+    pub fn is_hint(&self) -> bool {
+        self.method == HINT_METHOD
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct WarmupConfig {
+    pub enabled: bool,
+    /// Wait after login before the first request (the first screen's own
+    /// requests go first)
+    pub delay_ms: u64,
+    pub requests: Vec<WarmupRequest>,
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            delay_ms: 1500,
+            requests: vec![
+                WarmupRequest::hint("store"),
+                WarmupRequest::hint("index"),
+                WarmupRequest::hint("caches"),
+            ],
+        }
+    }
+}
+
+impl WarmupConfig {
+    /// Load from `path` (missing or invalid file = defaults)
+    pub fn load(path: Option<&Path>) -> Self {
+        path.and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay_ms)
+    }
+
+    /// Whether there is anything to send
+    pub fn active(&self) -> bool {
+        self.enabled && !self.requests.is_empty()
+    }
+}
+
+/// Loaded once at startup
+pub struct WarmupState(pub WarmupConfig);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_defaults_and_file() {
+        let config = WarmupConfig::load(None);
+        assert!(config.active());
+        assert!(config.requests.iter().all(WarmupRequest::is_hint));
+
+        let dir = std::env::temp_dir().join(format!("reos-warmup-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("warmup.json");
+        std::fs::write(&path, r#"{ "requests": [{ "method": "personas/list" }] }"#).unwrap();
+        let config = WarmupConfig::load(Some(&path));
+        assert_eq!(config.delay(), Duration::from_millis(1500));
+        assert_eq!(config.requests.len(), 1);
+        assert!(!config.requests[0].is_hint());
+        assert_eq!(config.requests[0].params, Value::Null);
+
+        std::fs::write(&path, r#"{ "enabled": false }"#).unwrap();
+        assert!(!WarmupConfig::load(Some(&path)).active());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
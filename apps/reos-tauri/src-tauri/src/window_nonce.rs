@@ -0,0 +1,164 @@
+//! Per-Window Command Nonces
+//!
+//! Each webview gets a random nonce when its page loads; state-changing
+//! commands require it next to the session token and check it against the
+//! invoking window's label. An invocation captured in one window (or from
+//! an earlier page load) can't be replayed from another. Nonces rotate
+//! every `NONCE_ROTATE_AFTER`; the previous one stays valid for
+//! `NONCE_GRACE` so in-flight calls don't fail. The new nonce is sent only
+//! to its own window (`window-nonce-rotated`).
+
+use rand::RngCore;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::secret::constant_time_eq;
+
+/// Rotate a window's nonce after this long
+pub const NONCE_ROTATE_AFTER: Duration = Duration::from_secs(5 * 60);
+/// How long the previous nonce is still accepted after rotation
+pub const NONCE_GRACE: Duration = Duration::from_secs(60);
+/// Event carrying a window's new nonce (emitted to that window only)
+pub const NONCE_ROTATED_EVENT: &str = "window-nonce-rotated";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum NonceError {
+    #[error("Invalid window nonce")]
+    Invalid,
+}
+
+struct WindowNonce {
+    current: String,
+    previous: Option<String>,
+    issued_at: Instant,
+}
+
+fn new_nonce() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+#[derive(Default)]
+pub struct WindowNonces {
+    windows: HashMap<String, WindowNonce>,
+}
+
+impl WindowNonces {
+    /// Fresh nonce for `label` (page load); invalidates earlier ones
+    pub fn issue(&mut self, label: &str) -> String {
+        let nonce = new_nonce();
+        self.windows.insert(
+            label.to_string(),
+            WindowNonce {
+                current: nonce.clone(),
+                previous: None,
+                issued_at: Instant::now(),
+            },
+        );
+        nonce
+    }
+
+    /// Current nonce for `label`, issuing one if the window has none yet
+    pub fn current(&mut self, label: &str) -> String {
+        match self.windows.get(label) {
+            Some(entry) => entry.current.clone(),
+            None => self.issue(label),
+        }
+    }
+
+    pub fn verify(&self, label: &str, nonce: &str) -> Result<(), NonceError> {
+        let entry = self.windows.get(label).ok_or(NonceError::Invalid)?;
+        let matches = |candidate: &str| constant_time_eq(candidate.as_bytes(), nonce.as_bytes());
+        let previous_ok = entry
+            .previous
+            .as_deref()
+            .is_some_and(|prev| entry.issued_at.elapsed() < NONCE_GRACE && matches(prev));
+        if matches(&entry.current) || previous_ok {
+            Ok(())
+        } else {
+            Err(NonceError::Invalid)
+        }
+    }
+
+    /// Rotate nonces older than `NONCE_ROTATE_AFTER`; returns (label, new nonce)
+    pub fn rotate_due(&mut self) -> Vec<(String, String)> {
+        self.rotate_older_than(NONCE_ROTATE_AFTER)
+    }
+
+    fn rotate_older_than(&mut self, age: Duration) -> Vec<(String, String)> {
+        let mut rotated = Vec::new();
+        for (label, entry) in self.windows.iter_mut() {
+            if entry.issued_at.elapsed() >= age {
+                let nonce = new_nonce();
+                entry.previous = Some(std::mem::replace(&mut entry.current, nonce.clone()));
+                entry.issued_at = Instant::now();
+                rotated.push((label.clone(), nonce));
+            }
+        }
+        rotated
+    }
+
+    /// Forget a closed window
+    pub fn remove(&mut self, label: &str) {
+        self.windows.remove(label);
+    }
+}
+
+/// Thread-safe nonce table
+pub struct WindowNonceState(pub Mutex<WindowNonces>);
+
+impl WindowNonceState {
+    pub fn new() -> Self {
+        Self(Mutex::new(WindowNonces::default()))
+    }
+}
+
+impl Default for WindowNonceState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonce_is_bound_to_window() {
+        let mut nonces = WindowNonces::default();
+        let main = nonces.issue("main");
+        let other = nonces.issue("settings");
+        assert_eq!(nonces.verify("main", &main), Ok(()));
+        assert_eq!(nonces.verify("settings", &main), Err(NonceError::Invalid));
+        assert_eq!(nonces.verify("main", &other), Err(NonceError::Invalid));
+        assert_eq!(nonces.verify("unknown", &main), Err(NonceError::Invalid));
+    }
+
+    #[test]
+    fn test_page_load_invalidates_old_nonce() {
+        let mut nonces = WindowNonces::default();
+        let first = nonces.issue("main");
+        let second = nonces.issue("main");
+        assert_eq!(nonces.verify("main", &first), Err(NonceError::Invalid));
+        assert_eq!(nonces.current("main"), second);
+    }
+
+    #[test]
+    fn test_rotation_keeps_previous_for_grace() {
+        let mut nonces = WindowNonces::default();
+        let old = nonces.issue("main");
+        let rotated = nonces.rotate_older_than(Duration::ZERO);
+        assert_eq!(rotated.len(), 1);
+        let (label, new) = &rotated[0];
+        assert_eq!(label, "main");
+        assert_eq!(nonces.verify("main", new), Ok(()));
+        assert_eq!(nonces.verify("main", &old), Ok(()));
+
+        // A second rotation drops the original
+        nonces.rotate_older_than(Duration::ZERO);
+        assert_eq!(nonces.verify("main", &old), Err(NonceError::Invalid));
+    }
+}
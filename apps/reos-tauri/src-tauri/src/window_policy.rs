@@ -0,0 +1,326 @@
+//! Per-Window Command Policy for ReOS
+//!
+//! Every command invocation passes through `WindowPolicy::check` before it
+//! reaches its handler: the invoking webview's label must be allowed for the
+//! command's group and the page it has loaded must come from an app origin.
+//! This keeps auxiliary or embedded webviews (and anything navigated away
+//! from the bundled frontend) away from login, credential and admin commands.
+//!
+//! Configurable via `window-policy.json` in the app data dir; groups left
+//! out keep their defaults, `"*"` allows any window and `"prefix-*"` any
+//! window whose label starts with `prefix-`:
+//! ```json
+//! { "origins": ["tauri://localhost", "http://tauri.localhost"],
+//!   "groups": { "kernel": ["main", "dashboard"], "vault": ["main", "me"] } }
+//! ```
+//!
+//! Commands missing from `command_group` are denied.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::Url;
+use thiserror::Error;
+
+use crate::windows::SECONDARY_PREFIX;
+
+/// Wildcard window label
+const ANY_WINDOW: &str = "*";
+
+/// Origins the bundled frontend is served from
+const APP_ORIGINS: &[&str] = &[
+    "tauri://localhost",
+    "http://tauri.localhost",
+    "https://tauri.localhost",
+];
+
+/// Vite dev server (`build.devUrl`), accepted in debug builds only
+const DEV_ORIGIN: &str = "http://localhost:1420";
+
+/// Commands grouped by what they can reach
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandGroup {
+    /// Starting a session (password, OS session, OIDC, guest, TOTP) and
+    /// the first-run setup that precedes it
+    Login,
+    /// Changing stored credentials (password, PIN, TOTP, biometrics)
+    Credentials,
+    /// Working with an existing session (validate, lock/unlock, nonces)
+    Session,
+    /// Kernel lifecycle and requests
+    Kernel,
+    /// User secrets vault
+    Vault,
+    /// Admin-only operations
+    Admin,
+}
+
+/// Group of each registered command (`None` = unknown, denied)
+pub fn command_group(command: &str) -> Option<CommandGroup> {
+    use CommandGroup::*;
+    let group = match command {
+        "auth_login"
+        | "auth_login_os_session"
+        | "auth_login_oidc"
+        | "auth_guest_login"
+        | "auth_verify_totp"
+        | "get_system_username"
+        | "setup_status"
+        | "setup_run_step"
+        | "remote_kernel_pair" => Login,
+        "auth_change_password"
+        | "auth_pin_set"
+        | "auth_pin_clear"
+        | "auth_totp_enroll"
+        | "auth_totp_confirm"
+        | "auth_totp_disable"
+        | "auth_biometric_enable"
+        | "auth_biometric_disable" => Credentials,
+        "auth_pin_unlock"
+        | "auth_lock"
+        | "auth_unlock"
+        | "auth_step_up"
+        | "auth_biometric_status"
+        | "auth_biometric_unlock"
+        | "auth_logout"
+        | "auth_validate"
+        | "auth_refresh"
+        | "auth_get_session"
+        | "window_nonce"
+        | "update_check"
+        | "deep_link_take"
+        | "window_open"
+        | "window_close"
+        | "window_handoff"
+        | "clipboard_write"
+        | "clipboard_read"
+        | "autostart_enable"
+        | "autostart_disable"
+        | "autostart_status"
+        | "appearance_get"
+        | "config_path"
+        | "features_get"
+        | "settings_get"
+        | "settings_set"
+        | "power_status"
+        | "open_external"
+        | "log_get_config"
+        | "log_set_level"
+        | "frontend_log"
+        | "metrics_snapshot"
+        | "health_check"
+        | "debug_request_history"
+        | "crash_report_list"
+        | "crash_report_get"
+        | "crash_report_submit"
+        | "diagnostics_export" => Session,
+        "kernel_start"
+        | "kernel_request"
+        | "kernel_integrity_status"
+        | "kernel_status"
+        | "kernel_list_methods"
+        | "kernel_blob_read"
+        | "kernel_blob_release"
+        | "kernel_stream_read"
+        | "kernel_stream_release"
+        | "kernel_logs_subscribe"
+        | "kernel_logs_unsubscribe"
+        | "file_ingest"
+        | "export_file"
+        | "tray_action_run"
+        | "app_menu_run"
+        | "print_document"
+        | "print_capabilities" => Kernel,
+        "vault_set" | "vault_get" | "vault_delete" | "vault_list" | "clipboard_copy_secret" => {
+            Vault
+        }
+        "auth_revoke_user"
+        | "audit_verify_requests"
+        | "state_reset"
+        | "kernel_hot_swap"
+        | "update_download"
+        | "update_install" => Admin,
+        _ => return None,
+    };
+    Some(group)
+}
+
+/// Why an invocation was refused
+#[derive(Debug, Error, PartialEq)]
+pub enum WindowDenied {
+    #[error("Unknown command {0}")]
+    UnknownCommand(String),
+    #[error("Command {command} is not allowed from window {window}")]
+    Window { command: String, window: String },
+    #[error("Command {command} is not allowed from origin {origin}")]
+    Origin { command: String, origin: String },
+}
+
+/// Allowed windows per command group, plus allowed page origins
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct WindowPolicy {
+    pub origins: Vec<String>,
+    pub groups: HashMap<CommandGroup, Vec<String>>,
+}
+
+impl Default for WindowPolicy {
+    fn default() -> Self {
+        let mut origins: Vec<String> = APP_ORIGINS.iter().map(|o| o.to_string()).collect();
+        if cfg!(debug_assertions) {
+            origins.push(DEV_ORIGIN.to_string());
+        }
+        Self {
+            origins,
+            groups: default_groups(),
+        }
+    }
+}
+
+fn default_groups() -> HashMap<CommandGroup, Vec<String>> {
+    let main = || vec!["main".to_string()];
+    let app_windows = || {
+        vec![
+            "main".to_string(),
+            "me".to_string(),
+            "dashboard".to_string(),
+            format!("{SECONDARY_PREFIX}{ANY_WINDOW}"),
+        ]
+    };
+    HashMap::from([
+        (CommandGroup::Login, main()),
+        (CommandGroup::Credentials, main()),
+        (CommandGroup::Session, app_windows()),
+        (CommandGroup::Kernel, app_windows()),
+        (CommandGroup::Vault, main()),
+        (CommandGroup::Admin, main()),
+    ])
+}
+
+impl WindowPolicy {
+    /// Load the policy from `path` (missing or invalid file = defaults)
+    pub fn load(path: Option<&Path>) -> Self {
+        let mut policy: Self = path
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        for (group, windows) in default_groups() {
+            policy.groups.entry(group).or_insert(windows);
+        }
+        policy
+    }
+
+    /// Whether the webview `window`, showing `url`, may invoke `command`
+    pub fn check(
+        &self,
+        command: &str,
+        window: &str,
+        url: Option<&Url>,
+    ) -> Result<(), WindowDenied> {
+        let group =
+            command_group(command).ok_or_else(|| WindowDenied::UnknownCommand(command.into()))?;
+        let allowed = self
+            .groups
+            .get(&group)
+            .is_some_and(|w| w.iter().any(|l| label_matches(l, window)));
+        if !allowed {
+            return Err(WindowDenied::Window {
+                command: command.into(),
+                window: window.into(),
+            });
+        }
+        let origin = url.map(origin_of).unwrap_or_default();
+        if !self.origins.contains(&origin) {
+            return Err(WindowDenied::Origin {
+                command: command.into(),
+                origin,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Whether policy entry `pattern` covers `window`
+fn label_matches(pattern: &str, window: &str) -> bool {
+    match pattern.strip_suffix(ANY_WINDOW) {
+        Some(prefix) => window.starts_with(prefix),
+        None => pattern == window,
+    }
+}
+
+/// `scheme://host[:port]` (custom schemes have opaque `Url::origin`s)
+fn origin_of(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{}://{host}:{port}", url.scheme()),
+        None => format!("{}://{host}", url.scheme()),
+    }
+}
+
+/// Window policy state (read-only after startup)
+pub struct WindowPolicyState(pub WindowPolicy);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_default_policy_keeps_login_in_main_window() {
+        let policy = WindowPolicy::default();
+        let app = url("tauri://localhost/index.html");
+        assert!(policy.check("auth_login", "main", Some(&app)).is_ok());
+        assert!(matches!(
+            policy.check("auth_login", "dashboard", Some(&app)),
+            Err(WindowDenied::Window { .. })
+        ));
+        assert!(policy
+            .check("kernel_request", "dashboard", Some(&app))
+            .is_ok());
+        assert!(policy.check("state_reset", "me", Some(&app)).is_err());
+        assert!(policy
+            .check("kernel_request", "doc-notes", Some(&app))
+            .is_ok());
+        assert!(policy.check("vault_get", "doc-notes", Some(&app)).is_err());
+    }
+
+    #[test]
+    fn test_foreign_origin_and_unknown_command_denied() {
+        let policy = WindowPolicy::default();
+        let foreign = url("https://example.com/");
+        assert_eq!(
+            policy.check("auth_validate", "main", Some(&foreign)),
+            Err(WindowDenied::Origin {
+                command: "auth_validate".into(),
+                origin: "https://example.com".into(),
+            })
+        );
+        assert!(policy.check("auth_validate", "main", None).is_err());
+        assert_eq!(
+            policy.check("debug_dump", "main", Some(&url("tauri://localhost/"))),
+            Err(WindowDenied::UnknownCommand("debug_dump".into()))
+        );
+    }
+
+    #[test]
+    fn test_partial_file_keeps_default_groups() {
+        let dir = std::env::temp_dir().join(format!("reos-window-policy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("window-policy.json");
+        std::fs::write(
+            &path,
+            r#"{ "origins": ["http://localhost:5173"], "groups": { "vault": ["*"] } }"#,
+        )
+        .unwrap();
+        let policy = WindowPolicy::load(Some(&path));
+        let dev = url("http://localhost:5173/");
+        assert!(policy.check("vault_get", "embedded", Some(&dev)).is_ok());
+        assert!(policy.check("auth_login", "embedded", Some(&dev)).is_err());
+        assert!(policy.check("auth_login", "main", Some(&dev)).is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,321 @@
+//! Window Size and Position Persistence for ReOS
+//!
+//! Each window's size, position, maximized state and monitor are recorded
+//! by label as it moves and resizes, written to `window-state.json` in the
+//! app config dir when a window closes or the app exits, and applied again
+//! when a window with the same label opens (the main window before it is
+//! first shown).
+//!
+//! Saved geometry is checked against the monitors connected now: a window
+//! goes back to its monitor if that's still attached, is shrunk to fit,
+//! and is centered when its title bar would end up off screen (e.g. the
+//! monitor it was on has been unplugged).
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::storage::write_private_file;
+
+/// Most windows remembered; the least recently used are dropped
+const MAX_WINDOWS: usize = 32;
+/// Smallest size restored
+const MIN_WIDTH: u32 = 320;
+const MIN_HEIGHT: u32 = 240;
+/// How much of the top edge must be on screen to keep a position
+const MIN_VISIBLE_WIDTH: i32 = 100;
+const MIN_VISIBLE_HEIGHT: i32 = 40;
+
+/// Outer position and inner size, in physical pixels
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SavedWindow {
+    /// Last geometry while not maximized
+    pub geometry: Geometry,
+    pub maximized: bool,
+    /// Name of the monitor the window was on
+    pub monitor: Option<String>,
+    /// Unix seconds of the last update
+    pub last_used: u64,
+}
+
+/// A connected monitor's work area, in physical pixels
+#[derive(Clone, Debug)]
+pub struct MonitorArea {
+    pub name: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl MonitorArea {
+    /// Whether enough of the top edge of `g` is on this monitor to grab it
+    fn shows_title_bar(&self, g: &Geometry) -> bool {
+        let overlap_x = (g.x + g.width as i32).min(self.x + self.width as i32) - g.x.max(self.x);
+        let title_on_screen =
+            g.y >= self.y && g.y + MIN_VISIBLE_HEIGHT <= self.y + self.height as i32;
+        overlap_x >= MIN_VISIBLE_WIDTH && title_on_screen
+    }
+
+    fn contains_center(&self, g: &Geometry) -> bool {
+        let cx = g.x + g.width as i32 / 2;
+        let cy = g.y + g.height as i32 / 2;
+        cx >= self.x
+            && cx < self.x + self.width as i32
+            && cy >= self.y
+            && cy < self.y + self.height as i32
+    }
+}
+
+/// Where to put a saved window given the monitors connected now
+///
+/// `monitors[0]` is used as the fallback (pass the primary monitor
+/// first); `None` when no monitor is known.
+pub fn place(saved: &SavedWindow, monitors: &[MonitorArea]) -> Option<Geometry> {
+    let g = saved.geometry;
+    let target = saved
+        .monitor
+        .as_ref()
+        .and_then(|name| monitors.iter().find(|m| m.name.as_ref() == Some(name)))
+        .or_else(|| monitors.iter().find(|m| m.contains_center(&g)))
+        .or_else(|| monitors.first())?;
+
+    let width = g.width.clamp(MIN_WIDTH.min(target.width), target.width);
+    let height = g.height.clamp(MIN_HEIGHT.min(target.height), target.height);
+    let mut placed = Geometry {
+        x: g.x,
+        y: g.y,
+        width,
+        height,
+    };
+    if !target.shows_title_bar(&placed) {
+        placed.x = target.x + (target.width - width) as i32 / 2;
+        placed.y = target.y + (target.height - height) as i32 / 2;
+    }
+    Some(placed)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct WindowStateFile {
+    windows: HashMap<String, SavedWindow>,
+}
+
+/// Saved windows by label, backed by `window-state.json`
+pub struct WindowStateStore {
+    path: Option<PathBuf>,
+    windows: HashMap<String, SavedWindow>,
+    dirty: bool,
+}
+
+impl WindowStateStore {
+    /// Load from `path` (missing or invalid file = nothing saved)
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let windows = path
+            .as_ref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str::<WindowStateFile>(&s).ok())
+            .map(|f| f.windows)
+            .unwrap_or_default();
+        Self {
+            path,
+            windows,
+            dirty: false,
+        }
+    }
+
+    pub fn get(&self, label: &str) -> Option<&SavedWindow> {
+        self.windows.get(label)
+    }
+
+    /// Record `label`'s state; `geometry` is ignored while maximized so
+    /// un-maximizing after a restart returns to the normal size
+    pub fn update(
+        &mut self,
+        label: &str,
+        geometry: Geometry,
+        maximized: bool,
+        monitor: Option<String>,
+    ) {
+        let last_used = now();
+        match self.windows.get_mut(label) {
+            Some(saved) => {
+                if !maximized {
+                    saved.geometry = geometry;
+                }
+                saved.maximized = maximized;
+                saved.monitor = monitor;
+                saved.last_used = last_used;
+            }
+            None => {
+                if self.windows.len() >= MAX_WINDOWS {
+                    let oldest = self
+                        .windows
+                        .iter()
+                        .min_by_key(|(_, saved)| saved.last_used)
+                        .map(|(label, _)| label.clone());
+                    if let Some(oldest) = oldest {
+                        self.windows.remove(&oldest);
+                    }
+                }
+                self.windows.insert(
+                    label.to_string(),
+                    SavedWindow {
+                        geometry,
+                        maximized,
+                        monitor,
+                        last_used,
+                    },
+                );
+            }
+        }
+        self.dirty = true;
+    }
+
+    /// Write to disk if anything changed since the last save
+    pub fn save(&mut self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+        let file = WindowStateFile {
+            windows: self.windows.clone(),
+        };
+        let data = serde_json::to_vec_pretty(&file)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        write_private_file(path, &data)?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+pub struct WindowStateState(pub Mutex<WindowStateStore>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, x: i32, width: u32) -> MonitorArea {
+        MonitorArea {
+            name: Some(name.to_string()),
+            x,
+            y: 0,
+            width,
+            height: 1080,
+        }
+    }
+
+    fn saved(monitor: &str, x: i32, y: i32, width: u32, height: u32) -> SavedWindow {
+        SavedWindow {
+            geometry: Geometry {
+                x,
+                y,
+                width,
+                height,
+            },
+            maximized: false,
+            monitor: Some(monitor.to_string()),
+            last_used: 0,
+        }
+    }
+
+    #[test]
+    fn test_place_keeps_valid_geometry() {
+        let monitors = [monitor("DP-1", 0, 1920), monitor("HDMI-1", 1920, 1920)];
+        let window = saved("HDMI-1", 2000, 100, 1200, 800);
+        assert_eq!(place(&window, &monitors), Some(window.geometry));
+    }
+
+    #[test]
+    fn test_place_recenters_after_monitor_unplugged() {
+        let monitors = [monitor("DP-1", 0, 1920)];
+        let window = saved("HDMI-1", 2000, 100, 1200, 800);
+        assert_eq!(
+            place(&window, &monitors),
+            Some(Geometry {
+                x: 360,
+                y: 140,
+                width: 1200,
+                height: 800
+            })
+        );
+    }
+
+    #[test]
+    fn test_place_shrinks_to_fit_and_rescues_title_bar() {
+        let monitors = [monitor("eDP-1", 0, 1366)];
+        let window = saved("eDP-1", 0, -50, 2560, 1440);
+        let placed = place(&window, &monitors).unwrap();
+        assert_eq!((placed.width, placed.height), (1366, 1080));
+        assert_eq!((placed.x, placed.y), (0, 0));
+        assert_eq!(place(&window, &[]), None);
+    }
+
+    #[test]
+    fn test_maximized_keeps_normal_geometry() {
+        let mut store = WindowStateStore::load(None);
+        let normal = Geometry {
+            x: 10,
+            y: 20,
+            width: 800,
+            height: 600,
+        };
+        store.update("main", normal, false, None);
+        let full = Geometry {
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1080,
+        };
+        store.update("main", full, true, Some("DP-1".into()));
+        let saved = store.get("main").unwrap();
+        assert!(saved.maximized);
+        assert_eq!(saved.geometry, normal);
+    }
+
+    #[test]
+    fn test_round_trip_and_cap() {
+        let path =
+            std::env::temp_dir().join(format!("reos-window-state-{}.json", std::process::id()));
+        let mut store = WindowStateStore::load(Some(path.clone()));
+        let g = Geometry {
+            x: 1,
+            y: 2,
+            width: 640,
+            height: 480,
+        };
+        for i in 0..MAX_WINDOWS + 3 {
+            store.update(&format!("doc-{i}"), g, false, None);
+        }
+        assert_eq!(store.windows.len(), MAX_WINDOWS);
+        store.save().unwrap();
+        let reloaded = WindowStateStore::load(Some(path.clone()));
+        assert_eq!(reloaded.windows.len(), MAX_WINDOWS);
+        assert_eq!(
+            reloaded
+                .get(&format!("doc-{}", MAX_WINDOWS + 2))
+                .unwrap()
+                .geometry,
+            g
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,216 @@
+//! Secondary App Windows for ReOS
+//!
+//! `window_open` opens another webview on the bundled frontend (e.g. a
+//! document at `/conversation/42`) backed by the same kernel process and
+//! session store. Secondary windows are labelled `doc-<name>` so the window
+//! policy can grant them session and kernel commands as a group.
+//!
+//! Session tokens live in each window's `sessionStorage`, so a new window
+//! starts without one. The opener's token is held for the new label and
+//! handed over once, to the first `window_handoff` call from that window
+//! within `HANDOFF_TTL`; it never appears in the window's URL.
+//!
+//! Focus changes are tracked here and broadcast to every window
+//! (`window-focus-changed`).
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::secret::SecretString;
+
+/// Label prefix of windows opened with `window_open`
+pub const SECONDARY_PREFIX: &str = "doc-";
+
+/// Most secondary windows open at once
+pub const MAX_SECONDARY_WINDOWS: usize = 8;
+
+/// How long a new window has to claim the opener's session
+pub const HANDOFF_TTL: Duration = Duration::from_secs(30);
+
+/// Event carrying the label of the window that gained focus
+pub const WINDOW_FOCUS_EVENT: &str = "window-focus-changed";
+
+const MAX_NAME_LEN: usize = 32;
+const MAX_ROUTE_LEN: usize = 512;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum WindowError {
+    #[error("Invalid window name")]
+    InvalidName,
+    #[error("Invalid window route")]
+    InvalidRoute,
+    #[error("Window already open")]
+    AlreadyOpen,
+    #[error("Too many windows open")]
+    TooMany,
+    #[error("Not a secondary window")]
+    NotSecondary,
+    #[error("No session to hand over to this window")]
+    NoHandoff,
+}
+
+/// Check that `label` is `doc-` followed by a name of `[a-z0-9-]`
+pub fn check_label(label: &str) -> Result<(), WindowError> {
+    let name = label
+        .strip_prefix(SECONDARY_PREFIX)
+        .ok_or(WindowError::NotSecondary)?;
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-');
+    if valid {
+        Ok(())
+    } else {
+        Err(WindowError::InvalidName)
+    }
+}
+
+pub fn is_secondary(label: &str) -> bool {
+    label.starts_with(SECONDARY_PREFIX)
+}
+
+/// Check that `route` is a path within the bundled frontend
+pub fn check_route(route: &str) -> Result<(), WindowError> {
+    let path = route.split(['?', '#']).next().unwrap_or_default();
+    let valid = route.len() <= MAX_ROUTE_LEN
+        && route.starts_with('/')
+        && !route.starts_with("//")
+        && !path.split('/').any(|segment| segment == "..")
+        && route
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"/-_.~?=&#".contains(&b));
+    if valid {
+        Ok(())
+    } else {
+        Err(WindowError::InvalidRoute)
+    }
+}
+
+struct Handoff {
+    session_token: SecretString,
+    issued_at: Instant,
+}
+
+/// Pending session handoffs and window focus
+#[derive(Default)]
+pub struct WindowRegistry {
+    handoffs: HashMap<String, Handoff>,
+    focused: Option<String>,
+}
+
+impl WindowRegistry {
+    /// Hold `session_token` for the window about to open as `label`
+    pub fn offer(&mut self, label: &str, session_token: SecretString) {
+        self.handoffs.insert(
+            label.to_string(),
+            Handoff {
+                session_token,
+                issued_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Take the token held for `label` (once, within `HANDOFF_TTL`)
+    pub fn claim(&mut self, label: &str) -> Result<SecretString, WindowError> {
+        self.handoffs
+            .remove(label)
+            .filter(|h| h.issued_at.elapsed() < HANDOFF_TTL)
+            .map(|h| h.session_token)
+            .ok_or(WindowError::NoHandoff)
+    }
+
+    /// Record that `label` gained focus (false if it already had it)
+    pub fn focus(&mut self, label: &str) -> bool {
+        if self.focused.as_deref() == Some(label) {
+            return false;
+        }
+        self.focused = Some(label.to_string());
+        true
+    }
+
+    pub fn focused(&self) -> Option<&str> {
+        self.focused.as_deref()
+    }
+
+    /// Forget a closed window
+    pub fn remove(&mut self, label: &str) {
+        self.handoffs.remove(label);
+        if self.focused.as_deref() == Some(label) {
+            self.focused = None;
+        }
+    }
+}
+
+pub struct WindowsState(pub Mutex<WindowRegistry>);
+
+/// Session handed to a new window
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionHandoff {
+    pub session_token: String,
+    pub username: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_label() {
+        assert_eq!(check_label("doc-notes-2"), Ok(()));
+        assert_eq!(check_label("main"), Err(WindowError::NotSecondary));
+        for name in ["", "Main", "a/b", "a b", &"x".repeat(33)] {
+            let label = format!("doc-{name}");
+            assert_eq!(check_label(&label), Err(WindowError::InvalidName));
+        }
+        assert!(is_secondary("doc-notes-2"));
+        assert!(!is_secondary("main"));
+    }
+
+    #[test]
+    fn test_routes_stay_in_app() {
+        assert!(check_route("/").is_ok());
+        assert!(check_route("/conversation/42?view=full#end").is_ok());
+        for route in [
+            "conversation/42",
+            "//evil.example/x",
+            "/a/../b",
+            "https://evil.example",
+            "/a\\b",
+            "/a b",
+            "/%2e%2e/x",
+        ] {
+            assert_eq!(
+                check_route(route),
+                Err(WindowError::InvalidRoute),
+                "{route}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_handoff_is_claimed_once() {
+        let mut registry = WindowRegistry::default();
+        registry.offer("doc-a", SecretString::new("token".to_string()));
+        assert!(registry.claim("doc-b").is_err());
+        assert_eq!(registry.claim("doc-a").unwrap().expose(), "token");
+        assert_eq!(
+            registry.claim("doc-a").map(|_| ()),
+            Err(WindowError::NoHandoff)
+        );
+    }
+
+    #[test]
+    fn test_focus_tracking() {
+        let mut registry = WindowRegistry::default();
+        assert!(registry.focus("main"));
+        assert!(!registry.focus("main"));
+        assert!(registry.focus("doc-a"));
+        registry.remove("doc-a");
+        assert_eq!(registry.focused(), None);
+    }
+}
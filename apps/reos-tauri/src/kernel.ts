@@ -8,6 +8,8 @@
  * - Tokens are 256-bit CSPRNG, validated by Rust on every request
  */
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import { getCurrentWindow } from '@tauri-apps/api/window';
 import { JsonRpcResponseSchema } from './types';
 
 // Session token storage
@@ -24,6 +26,70 @@ export class KernelError extends Error {
   }
 }
 
+/** JSON-RPC error code used by Rust when the session's role may not call a method */
+export const METHOD_DENIED_CODE = -32403;
+
+export class MethodDeniedError extends KernelError {
+  method: string;
+  role: string;
+
+  constructor(message: string, method: string, role: string) {
+    super(message, METHOD_DENIED_CODE);
+    this.name = 'MethodDeniedError';
+    this.method = method;
+    this.role = role;
+  }
+}
+
+/** JSON-RPC error code used by Rust when the user declines a consent prompt */
+export const CONSENT_DENIED_CODE = -32406;
+
+/** JSON-RPC error code for rate-limited requests (Rust shell and kernel) */
+export const RATE_LIMITED_CODE = -32429;
+
+export class RateLimitedError extends KernelError {
+  retryAfterMs: number;
+
+  constructor(message: string, retryAfterMs: number) {
+    super(message, RATE_LIMITED_CODE);
+    this.name = 'RateLimitedError';
+    this.retryAfterMs = retryAfterMs;
+  }
+}
+
+/** Error returned by Rust when a command's window nonce is stale or foreign */
+const WINDOW_NONCE_INVALID = 'Invalid window nonce';
+
+/** This window's nonce for state-changing commands (rotated by Rust) */
+let windowNonce: string | null = null;
+let nonceListener: Promise<UnlistenFn> | null = null;
+
+async function getWindowNonce(): Promise<string> {
+  if (!nonceListener) {
+    nonceListener = getCurrentWindow().listen<string>('window-nonce-rotated', (event) => {
+      windowNonce = event.payload;
+    });
+  }
+  if (!windowNonce) {
+    windowNonce = await invoke<string>('window_nonce');
+  }
+  return windowNonce;
+}
+
+/**
+ * Invoke a state-changing command with this window's nonce, fetching a
+ * fresh one once if the cached nonce was rotated out.
+ */
+async function invokeWithNonce<T>(command: string, args: Record<string, unknown>): Promise<T> {
+  try {
+    return await invoke<T>(command, { ...args, windowNonce: await getWindowNonce() });
+  } catch (e) {
+    if (String(e) !== WINDOW_NONCE_INVALID) throw e;
+    windowNonce = null;
+    return await invoke<T>(command, { ...args, windowNonce: await getWindowNonce() });
+  }
+}
+
 export class AuthenticationError extends Error {
   constructor(message: string) {
     super(message);
@@ -81,8 +147,26 @@ export interface AuthResult {
   session_token?: string;
   username?: string;
   error?: string;
+  /** Stable failure code, e.g. 'username_invalid' or 'username_root_forbidden' */
+  error_code?: string;
+  /** PAM succeeded but a TOTP code is required (see verifyTotp) */
+  needs_second_factor?: boolean;
+  challenge_id?: string;
+  /** New password rejected by the password policy */
+  violations?: PasswordViolation[];
 }
 
+/** One broken password-policy rule (see password-policy.json) */
+export type PasswordViolation =
+  | { code: 'too_short'; min: number }
+  | { code: 'too_long'; max: number }
+  | { code: 'missing_lowercase' }
+  | { code: 'missing_uppercase' }
+  | { code: 'missing_digit' }
+  | { code: 'missing_symbol' }
+  | { code: 'contains_username' }
+  | { code: 'breached' };
+
 /**
  * Get the current system username.
  * @returns System username or null if unavailable
@@ -96,13 +180,65 @@ export async function getSystemUsername(): Promise<string | null> {
 }
 
 /**
- * Authenticate user via Polkit (native system dialog).
+ * Authenticate user via the configured login backend (default: Polkit,
+ * which shows a native system dialog).
  * @param username - Linux username
- * @param _password - Ignored, kept for API compatibility
+ * @param password - Required by the LDAP and password-file backends,
+ *   ignored by Polkit
+ * @returns Authentication result
+ */
+export async function login(username: string, password?: string | null): Promise<AuthResult> {
+  const result = await invoke<AuthResult>('auth_login', { username, password: password ?? null });
+
+  if (result.success && result.session_token && result.username) {
+    setSession(result.session_token, result.username);
+  }
+
+  return result;
+}
+
+/**
+ * Log in using the current desktop session instead of a PAM prompt
+ * (only when enabled in os-login.json). The passphrase unlocks the
+ * encryption key; TOTP may still be required afterwards.
+ * @param username - Must match the desktop session's user
+ * @param passphrase - Encryption passphrase
+ * @returns Authentication result
+ */
+export async function loginWithOsSession(username: string, passphrase: string): Promise<AuthResult> {
+  const result = await invoke<AuthResult>('auth_login_os_session', { username, passphrase });
+
+  if (result.success && result.session_token && result.username) {
+    setSession(result.session_token, result.username);
+  }
+
+  return result;
+}
+
+/**
+ * Log in through the organization's identity provider (only when
+ * configured in oidc.json). Opens the system browser; resolves once the
+ * provider redirects back or the attempt times out.
+ * @param passphrase - Encryption passphrase for the mapped local user
+ */
+export async function loginWithOidc(passphrase: string): Promise<AuthResult> {
+  const result = await invoke<AuthResult>('auth_login_oidc', { passphrase });
+
+  if (result.success && result.session_token && result.username) {
+    setSession(result.session_token, result.username);
+  }
+
+  return result;
+}
+
+/**
+ * Complete a login that returned needs_second_factor.
+ * @param challengeId - challenge_id from the login result
+ * @param code - 6-digit code from the authenticator app
  * @returns Authentication result
  */
-export async function login(username: string, _password?: string | null): Promise<AuthResult> {
-  const result = await invoke<AuthResult>('auth_login', { username });
+export async function verifyTotp(challengeId: string, code: string): Promise<AuthResult> {
+  const result = await invoke<AuthResult>('auth_verify_totp', { challengeId, code });
 
   if (result.success && result.session_token && result.username) {
     setSession(result.session_token, result.username);
@@ -111,6 +247,16 @@ export async function login(username: string, _password?: string | null): Promis
   return result;
 }
 
+/**
+ * Turn off TOTP for the current user (needs a recent password, see stepUp).
+ * @returns True if TOTP was enrolled
+ */
+export async function disableTotp(): Promise<boolean> {
+  const token = getSessionToken();
+  if (!token) return false;
+  return await invokeWithNonce<boolean>('auth_totp_disable', { sessionToken: token });
+}
+
 /**
  * Logout and destroy session.
  * @returns True if logout succeeded
@@ -120,7 +266,7 @@ export async function logout(): Promise<boolean> {
   if (!token) return false;
 
   try {
-    await invoke('auth_logout', { sessionToken: token });
+    await invokeWithNonce('auth_logout', { sessionToken: token });
   } catch {
     // Ignore errors, clear local session anyway
   }
@@ -129,6 +275,551 @@ export async function logout(): Promise<boolean> {
   return true;
 }
 
+export interface RevokeOutcome {
+  /** Shell sessions revoked */
+  revoked: number;
+  /** Kernel sessions dropped with their key material (null if the kernel failed) */
+  kernel_revoked: number | null;
+  /** Why the kernel failed: the user's key material may still be live */
+  kernel_error: string | null;
+}
+
+/**
+ * Revoke every session of a user and drop their key material (admin only).
+ * @param username - Account to revoke
+ * @returns What was revoked; check `kernel_error` for a partial failure
+ */
+export async function revokeUser(username: string): Promise<RevokeOutcome> {
+  const token = getSessionToken();
+  if (!token) throw new Error('Not logged in');
+  return await invokeWithNonce<RevokeOutcome>('auth_revoke_user', {
+    sessionToken: token,
+    username,
+  });
+}
+
+/**
+ * Secrets vault: API keys and similar secrets, encrypted with the user's
+ * session key. Names are 1-128 chars of letters, digits, '.', '_' or '-';
+ * values up to 64 KiB. Not available to guest sessions.
+ */
+function vaultToken(): string {
+  const token = getSessionToken();
+  if (!token) throw new AuthenticationError('Not authenticated');
+  return token;
+}
+
+export async function vaultSet(name: string, value: string): Promise<void> {
+  await invokeWithNonce('vault_set', { sessionToken: vaultToken(), name, value });
+}
+
+/** @returns The secret, or null if it isn't stored */
+export async function vaultGet(name: string): Promise<string | null> {
+  return await invoke<string | null>('vault_get', { sessionToken: vaultToken(), name });
+}
+
+/** @returns Whether the secret existed */
+export async function vaultDelete(name: string): Promise<boolean> {
+  return await invokeWithNonce<boolean>('vault_delete', { sessionToken: vaultToken(), name });
+}
+
+/** @returns Names of the stored secrets (never their values) */
+export async function vaultList(): Promise<string[]> {
+  return await invoke<string[]>('vault_list', { sessionToken: vaultToken() });
+}
+
+/**
+ * Copy a secret to the clipboard from Rust (the value never reaches the
+ * webview). It's cleared again after a timeout if nothing replaced it.
+ */
+export async function vaultCopy(name: string): Promise<void> {
+  await invokeWithNonce('clipboard_copy_secret', { sessionToken: vaultToken(), name });
+}
+
+/**
+ * Open a URL (https, http, mailto by default) or an absolute file path with
+ * the system's default app. Rust checks it against the open policy and
+ * audits the attempt; programs and scripts are refused.
+ */
+export async function openExternal(target: string): Promise<void> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  await invokeWithNonce('open_external', { sessionToken, target });
+}
+
+export interface LogLevels {
+  /** Level for targets not listed */
+  default: string;
+  targets: Record<string, string>;
+}
+
+export interface LogConfig {
+  levels: LogLevels;
+  /** Active log file, or null if file logging is unavailable */
+  file: string | null;
+  max_bytes: number | null;
+  keep: number | null;
+}
+
+/** @returns The shell's log levels and log file (for support) */
+export async function getLogConfig(): Promise<LogConfig> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return await invoke<LogConfig>('log_get_config', { sessionToken });
+}
+
+/**
+ * Change a log level until the app exits, e.g. `setLogLevel('kernel', 'debug')`.
+ * Shell modules can be named without the `reos_tauri::` prefix; `*` is the
+ * default level and a null level resets the target.
+ */
+export async function setLogLevel(target: string, level: string | null): Promise<LogLevels> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return await invokeWithNonce<LogLevels>('log_set_level', { sessionToken, target, level });
+}
+
+export type FrontendLogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+/**
+ * Write an entry to the shell's log files, next to Rust and kernel logs.
+ * Best effort: failures are swallowed so logging never breaks the UI.
+ */
+export async function frontendLog(
+  level: FrontendLogLevel,
+  message: string,
+  context?: Record<string, unknown>,
+): Promise<void> {
+  try {
+    await invoke('frontend_log', { level, message, context: context ?? null });
+  } catch {
+    // Nowhere better to report it
+  }
+}
+
+/** Record a user action, to see what led up to an error */
+export function breadcrumb(action: string, context?: Record<string, unknown>): void {
+  void frontendLog('info', action, { breadcrumb: true, ...context });
+}
+
+function describeArgs(args: unknown[]): string {
+  return args
+    .map((arg) => (arg instanceof Error ? `${arg.name}: ${arg.message}` : String(arg)))
+    .join(' ');
+}
+
+/**
+ * Send console errors and warnings, uncaught errors and unhandled
+ * rejections to the shell's log files (they still reach the console).
+ */
+export function forwardConsoleToLog(): void {
+  for (const level of ['error', 'warn'] as const) {
+    const original = console[level].bind(console);
+    console[level] = (...args: unknown[]) => {
+      original(...args);
+      void frontendLog(level, describeArgs(args));
+    };
+  }
+  window.addEventListener('error', (event) => {
+    void frontendLog('error', event.message, {
+      source: event.filename,
+      line: event.lineno,
+      stack: event.error instanceof Error ? event.error.stack : undefined,
+    });
+  });
+  window.addEventListener('unhandledrejection', (event) => {
+    const reason: unknown = event.reason;
+    void frontendLog('error', `Unhandled rejection: ${describeArgs([reason])}`, {
+      stack: reason instanceof Error ? reason.stack : undefined,
+    });
+  });
+}
+
+export interface KernelOutputLine {
+  /** Increases by one per line, across kernel restarts */
+  seq: number;
+  stream: 'stdout' | 'stderr';
+  line: string;
+}
+
+/**
+ * Follow kernel output (redacted stderr, plus one stdout line per response
+ * and pushed notification) for a debug console. `callback` first gets the
+ * buffered lines after `after`, then each new line in order.
+ * @returns Function that stops following
+ */
+export async function followKernelOutput(
+  callback: (line: KernelOutputLine) => void,
+  after?: number,
+): Promise<() => Promise<void>> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  let last = after ?? 0;
+  const deliver = (line: KernelOutputLine) => {
+    // A line recorded while subscribing arrives both ways
+    if (line.seq <= last) return;
+    last = line.seq;
+    callback(line);
+  };
+  const pending: KernelOutputLine[] = [];
+  let replayed = false;
+  const unlisten = await getCurrentWindow().listen<KernelOutputLine>('kernel-output', (event) => {
+    if (replayed) deliver(event.payload);
+    else pending.push(event.payload);
+  });
+  try {
+    const backlog = await invokeWithNonce<KernelOutputLine[]>('kernel_logs_subscribe', {
+      sessionToken,
+      after: after ?? null,
+    });
+    backlog.forEach(deliver);
+    pending.forEach(deliver);
+    replayed = true;
+  } catch (e) {
+    unlisten();
+    throw e;
+  }
+  return async () => {
+    unlisten();
+    await invokeWithNonce('kernel_logs_unsubscribe', {});
+  };
+}
+
+export interface MethodMetrics {
+  count: number;
+  errors: number;
+  /** Latency percentiles (bucket upper bounds), null before any request */
+  p50_ms: number | null;
+  p90_ms: number | null;
+  p99_ms: number | null;
+  mean_ms: number | null;
+}
+
+export interface MetricsSnapshot {
+  requests: Record<string, MethodMetrics>;
+  kernel_starts: number;
+  kernel_restarts: number;
+  kernel_exits: number;
+  queue_depth: number;
+  queue_peak: number;
+  auth_failures: Record<string, number>;
+  kernel_resources: KernelResourceUsage | null;
+}
+
+/** @returns Shell metrics since the app started */
+export async function getMetrics(): Promise<MetricsSnapshot> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return await invoke<MetricsSnapshot>('metrics_snapshot', { sessionToken });
+}
+
+export interface KernelResourceUsage {
+  pid: number;
+  rss_bytes: number;
+  /** Percent of one core since the previous sample */
+  cpu_percent: number | null;
+  /** Handles on Windows */
+  open_fds: number | null;
+  children: number | null;
+  /** Unix milliseconds */
+  sampled_at: number;
+}
+
+export interface KernelStatus {
+  status: 'not_started' | 'running' | 'busy' | 'exited';
+  /** Backend of the current kernel; null if none is started or it is busy */
+  backend: KernelBackend | null;
+  pid: number | null;
+  version: string | null;
+  protocol: string;
+  /** Unix milliseconds of the last message from the kernel */
+  last_heartbeat_ms: number | null;
+  since_heartbeat_ms: number | null;
+  /** null before the first sample or on unsupported platforms */
+  resources: KernelResourceUsage | null;
+}
+
+/** @returns Kernel process state and latest resource usage */
+export async function getKernelStatus(): Promise<KernelStatus> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return await invoke<KernelStatus>('kernel_status', { sessionToken });
+}
+
+export interface KernelResourceWarning {
+  resource: 'rss' | 'cpu' | 'open_fds';
+  value: number;
+  threshold: number;
+  usage: KernelResourceUsage;
+}
+
+/**
+ * Subscribe to the kernel going over a resource threshold (once per crossing).
+ * @returns Function that removes the listener
+ */
+export async function onKernelResourceWarning(
+  callback: (warning: KernelResourceWarning) => void,
+): Promise<UnlistenFn> {
+  return listen<KernelResourceWarning>('kernel-resource-warning', (event) => {
+    callback(event.payload);
+  });
+}
+
+export interface HealthReport {
+  /** false if the kernel died, disk space is low or a config file is invalid */
+  ok: boolean;
+  app_version: string;
+  /** `--profile` name, 'default' without one */
+  profile: string;
+  /** Data dir beside the executable when running portable, else null */
+  portable: string | null;
+  kernel: KernelStatus;
+  sessions: { active: number; locked: number; guests: number };
+  queue_depth: number;
+  disk: { path: string; available_bytes: number; total_bytes: number; low: boolean } | null;
+  /**
+   * Config files that exist but don't parse (defaults are used instead),
+   * and files whose startup migration failed (naming the backup)
+   */
+  config_problems: { file: string; error: string }[];
+  /**
+   * `REOS_*` variables and command-line flags layered over config.toml
+   * (`source` is the variable or flag); `error` if ignored
+   */
+  overrides: { source: string; key: string; value: string; error: string | null }[];
+  /** false when no platform watcher locks sessions on screen lock (idle lock still applies) */
+  screen_lock_watch: boolean;
+}
+
+/** @returns Everything an "About / System status" screen shows */
+export async function healthCheck(): Promise<HealthReport> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return await invoke<HealthReport>('health_check', { sessionToken });
+}
+
+export interface RequestHistoryEntry {
+  seq: number;
+  /** Unix milliseconds */
+  ts: number;
+  correlation_id: string;
+  method: string;
+  /** null for the shell's own requests */
+  username: string | null;
+  /** Redacted, truncated params */
+  params: string;
+  duration_ms: number;
+  outcome: 'ok' | 'error' | 'failed';
+  code: number | null;
+}
+
+export interface RequestHistory {
+  /** false unless enabled in request-history.json (on in debug builds) */
+  enabled: boolean;
+  entries: RequestHistoryEntry[];
+}
+
+/**
+ * Recent kernel requests for the devtools panel (admins see every session's).
+ * @param after - Only entries with a greater seq (for polling)
+ */
+export async function getRequestHistory(after?: number): Promise<RequestHistory> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return await invoke<RequestHistory>('debug_request_history', {
+    sessionToken,
+    after: after ?? null,
+  });
+}
+
+export interface CrashSummary {
+  id: string;
+  /** Unix seconds */
+  created_at: number;
+  app_version: string;
+  /** First line of the (redacted) panic message */
+  message: string;
+  submitted_at: number | null;
+}
+
+export interface CrashReport {
+  id: string;
+  created_at: number;
+  app_version: string;
+  kernel_version: string | null;
+  os: string;
+  thread: string;
+  location: string;
+  message: string;
+  backtrace: string;
+  /** Last redacted log lines before the crash */
+  log_lines: string[];
+  submitted_at: number | null;
+}
+
+/** @returns Crash reports saved on this machine, newest first */
+export async function listCrashReports(): Promise<CrashSummary[]> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return await invoke<CrashSummary[]>('crash_report_list', { sessionToken });
+}
+
+/** @returns A crash report exactly as it would be sent */
+export async function getCrashReport(id: string): Promise<CrashReport> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return await invoke<CrashReport>('crash_report_get', { sessionToken, id });
+}
+
+/**
+ * Send a crash report to the configured endpoint. Rust asks the user in a
+ * native dialog first; nothing is sent if they decline.
+ */
+export async function submitCrashReport(id: string): Promise<CrashSummary> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return await invokeWithNonce<CrashSummary>('crash_report_submit', { sessionToken, id });
+}
+
+/**
+ * Copy plain text to the clipboard. Control and invisible characters and
+ * URL tracking parameters are stripped; oversized text is refused.
+ */
+export async function clipboardWrite(text: string): Promise<void> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  await invokeWithNonce('clipboard_write', { sessionToken, text });
+}
+
+/** @returns Sanitized clipboard text, or null if it holds no text */
+export async function clipboardRead(): Promise<string | null> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return await invoke<string | null>('clipboard_read', { sessionToken });
+}
+
+export interface AutostartStatus {
+  enabled: boolean;
+  /** Starts hidden in the tray with the kernel running and sessions locked */
+  minimized: boolean;
+}
+
+/** Launch ReOS when the user logs in to the desktop. */
+export async function enableAutostart(minimized: boolean): Promise<AutostartStatus> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return await invokeWithNonce<AutostartStatus>('autostart_enable', { sessionToken, minimized });
+}
+
+export async function disableAutostart(): Promise<void> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  await invokeWithNonce('autostart_disable', { sessionToken });
+}
+
+export async function getAutostartStatus(): Promise<AutostartStatus> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return await invoke<AutostartStatus>('autostart_status', { sessionToken });
+}
+
+/** Result of checking the kernel request audit chain */
+export interface RequestAuditReport {
+  /** Lines whose hash and link checked out */
+  verified: number;
+  /** First broken line as `file:line`, absent when the chain is intact */
+  broken_at?: string;
+}
+
+/**
+ * Verify the hash chain of the Rust-side kernel request log (admin only).
+ */
+export async function verifyRequestAudit(): Promise<RequestAuditReport> {
+  const token = getSessionToken();
+  if (!token) throw new Error('Not logged in');
+  return await invoke<RequestAuditReport>('audit_verify_requests', { sessionToken: token });
+}
+
+export interface UpdateInfo {
+  version: string;
+  current_version: string;
+  notes: string;
+  /** Downloaded and verified, ready to install */
+  staged: boolean;
+}
+
+/**
+ * Look for a newer signed release (null when up to date or not yet offered
+ * to this install by a staged rollout).
+ */
+export async function checkForUpdate(): Promise<UpdateInfo | null> {
+  const token = getSessionToken();
+  if (!token) throw new Error('Not logged in');
+  return await invoke<UpdateInfo | null>('update_check', { sessionToken: token });
+}
+
+/** Download and verify the release found by checkForUpdate (admin only). */
+export async function downloadUpdate(): Promise<UpdateInfo> {
+  const token = getSessionToken();
+  if (!token) throw new Error('Not logged in');
+  return await invokeWithNonce<UpdateInfo>('update_download', { sessionToken: token });
+}
+
+/**
+ * Install the downloaded release and restart (admin only). Fails with
+ * "Kernel busy" while a migration or encrypted write is in flight.
+ */
+export async function installUpdate(): Promise<void> {
+  const token = getSessionToken();
+  if (!token) throw new Error('Not logged in');
+  await invokeWithNonce<void>('update_install', { sessionToken: token });
+}
+
+/**
+ * Stop the kernel and clear request rate limits (admin only). The kernel
+ * restarts on the next request; sessions are kept.
+ */
+export async function resetState(): Promise<void> {
+  const token = getSessionToken();
+  if (!token) throw new Error('Not logged in');
+  await invokeWithNonce<void>('state_reset', { sessionToken: token });
+}
+
+/** Outcome of `hotSwapKernel` */
+export interface KernelSwap {
+  /** Kernel-side sessions carried over to the new kernel */
+  sessions: number;
+  /** Whether a running kernel was replaced */
+  replaced: boolean;
+}
+
+/**
+ * Start a new kernel beside the running one and switch to it, keeping
+ * every session (admin only). On failure the running kernel keeps serving.
+ */
+export async function hotSwapKernel(): Promise<KernelSwap> {
+  const token = getSessionToken();
+  if (!token) throw new Error('Not logged in');
+  return invokeWithNonce<KernelSwap>('kernel_hot_swap', { sessionToken: token });
+}
+
+/** This shell's client certificate, for pairing with a remote kernel */
+export interface RemoteKernelPairing {
+  /** SHA-256 of the certificate (hex), to compare on both ends */
+  fingerprint: string;
+  /** The certificate (PEM) for the kernel's operator */
+  certificate: string;
+}
+
+/**
+ * The client certificate a remote kernel's operator pairs with
+ * (`python -m reos.kernel_pairing add`), created on first use. Works before
+ * login, since logging in needs the remote kernel.
+ */
+export async function remoteKernelPairing(): Promise<RemoteKernelPairing> {
+  return invokeWithNonce<RemoteKernelPairing>('remote_kernel_pair', {});
+}
+
 /**
  * Validate current session.
  * @returns True if session is valid
@@ -158,7 +849,7 @@ export async function refreshSession(): Promise<boolean> {
   if (!token) return false;
 
   try {
-    await invoke('auth_refresh', { sessionToken: token });
+    await invokeWithNonce('auth_refresh', { sessionToken: token });
     return true;
   } catch {
     return false;
@@ -166,23 +857,891 @@ export async function refreshSession(): Promise<boolean> {
 }
 
 /**
- * Send a JSON-RPC request to the Python kernel.
- * Requires an authenticated session.
- *
- * @param method - The RPC method name (e.g., 'chat/respond', 'tools/call')
- * @param params - The parameters for the method
- * @returns The result from the kernel
- * @throws AuthenticationError if not authenticated
- * @throws KernelError if the kernel returns an error
+ * Lock the current session (lock screen). The token stays stored so
+ * `unlockSession` can resume it.
  */
-export async function kernelRequest(method: string, params: unknown): Promise<unknown> {
-  const sessionToken = getSessionToken();
+export async function lockSession(): Promise<void> {
+  const token = getSessionToken();
+  if (!token) return;
+  await invokeWithNonce('auth_lock', { sessionToken: token });
+}
 
-  if (!sessionToken) {
-    throw new AuthenticationError('Not authenticated. Please login first.');
-  }
+/**
+ * Resume a locked session with a PIN or the account password.
+ * @throws Error with the reason if verification fails
+ */
+export async function unlockSession(secret: { pin: string } | { password: string }): Promise<void> {
+  const token = getSessionToken();
+  if (!token) throw new AuthenticationError('Not authenticated');
+  await invokeWithNonce('auth_unlock', { sessionToken: token, ...secret });
+}
 
-  const raw = await invoke('kernel_request', { sessionToken, method, params });
+/**
+ * Re-enter the account password so sensitive kernel methods (which fail
+ * with "Re-authentication required") are allowed for a few minutes.
+ */
+export async function stepUp(password: string): Promise<void> {
+  const token = getSessionToken();
+  if (!token) throw new AuthenticationError('Not authenticated');
+  await invokeWithNonce('auth_step_up', { sessionToken: token, password });
+}
+
+/** Session lifecycle changes broadcast by the Rust side */
+export type SessionEventKind = 'created' | 'refreshed' | 'locked' | 'unlocked' | 'expired' | 'revoked';
+
+export interface SessionEventPayload {
+  /** Truncated session id (first 16 chars of the token) */
+  session_id: string;
+  username?: string;
+  /** What caused the change ('logout', 'suspend', 'pin', ...) */
+  reason?: string;
+}
+
+/** Why a session was locked */
+export type LockReason =
+  | 'suspend'
+  | 'screen_lock'
+  | 'manual'
+  | 'tray'
+  | 'shortcut'
+  | 'autostart'
+  | 'start_locked';
+
+/** Whether an event concerns this window's session */
+function isCurrentSession(payload: SessionEventPayload): boolean {
+  const token = getSessionToken();
+  return token !== null && token.startsWith(payload.session_id);
+}
+
+/**
+ * Subscribe to a session lifecycle event for any session.
+ * @returns Function that removes the listener
+ */
+export async function onSessionEvent(
+  kind: SessionEventKind,
+  callback: (payload: SessionEventPayload) => void,
+): Promise<UnlistenFn> {
+  return listen<SessionEventPayload>(`session-${kind}`, (event) => {
+    callback(event.payload);
+  });
+}
+
+/**
+ * Subscribe to locks of the current session (suspend, screen lock, or
+ * another window calling lockSession). The session token stays valid for
+ * unlock, so callers should show the lock screen rather than clearing it.
+ * @returns Function that removes the listener
+ */
+export async function onSessionLocked(
+  callback: (reason: LockReason) => void,
+): Promise<UnlistenFn> {
+  return onSessionEvent('locked', (payload) => {
+    if (isCurrentSession(payload)) {
+      callback(payload.reason as LockReason);
+    }
+  });
+}
+
+/** Why the kernel failed its pre-launch integrity check */
+export interface KernelIntegrityFailure {
+  reason:
+    | 'manifest_missing'
+    | 'bad_signature'
+    | 'malformed'
+    | 'file_missing'
+    | 'file_modified'
+    | 'unlisted'
+    | 'unsigned';
+  /** Offending file, relative to the kernel root */
+  path?: string;
+}
+
+/**
+ * Check the kernel against the manifest signed for this build.
+ * @returns The failure, or null when the kernel verifies (or a debug build has no signing key)
+ */
+export async function getKernelIntegrity(): Promise<KernelIntegrityFailure | null> {
+  return await invoke<KernelIntegrityFailure | null>('kernel_integrity_status');
+}
+
+/**
+ * Subscribe to refusals to start a kernel that failed verification.
+ * @returns Function that removes the listener
+ */
+export async function onKernelIntegrityFailed(
+  callback: (failure: KernelIntegrityFailure) => void,
+): Promise<UnlistenFn> {
+  return listen<KernelIntegrityFailure>('kernel-integrity-failed', (event) => {
+    callback(event.payload);
+  });
+}
+
+export interface SlowRequest {
+  /** Also in the kernel's logs for this request */
+  correlation_id: string;
+  method: string;
+  elapsed_ms: number;
+  /** Typical latency of the method, once it has enough samples */
+  baseline_ms: number | null;
+  threshold_ms: number;
+  /** false while the request is still running */
+  finished: boolean;
+}
+
+/**
+ * Subscribe to slow kernel requests (reported once while running and again
+ * when they finish).
+ * @returns Function that removes the listener
+ */
+export async function onSlowRequest(
+  callback: (slow: SlowRequest) => void,
+): Promise<UnlistenFn> {
+  return listen<SlowRequest>('kernel-slow-request', (event) => {
+    callback(event.payload);
+  });
+}
+
+export interface DesktopNotification {
+  category: 'kernel' | 'session_expiry' | 'crash';
+  title: string;
+  body: string;
+}
+
+/**
+ * Subscribe to notifications the desktop couldn't show natively (no
+ * notification service); muted categories never arrive.
+ * @returns Function that removes the listener
+ */
+export async function onNotification(
+  callback: (notification: DesktopNotification) => void,
+): Promise<UnlistenFn> {
+  return listen<DesktopNotification>('notification', (event) => {
+    callback(event.payload);
+  });
+}
+
+export interface DeepLink {
+  url: string;
+  method: string;
+  params: Record<string, string>;
+}
+
+/**
+ * Run tray quick actions in this window's session when the tray asks for
+ * them (main window only). Rust picks the method from the kernel's
+ * manifest and shows the result as a notification; `callback` gets the
+ * action id and any error.
+ * @returns Function that removes the listener
+ */
+export async function onTrayQuickAction(
+  callback?: (actionId: string, error?: unknown) => void,
+): Promise<UnlistenFn> {
+  return listen<string>('tray-quick-action', async (event) => {
+    const actionId = event.payload;
+    const sessionToken = getSessionToken();
+    try {
+      if (!sessionToken) throw new AuthenticationError('Not authenticated');
+      await invokeWithNonce('tray_action_run', { sessionToken, actionId });
+      callback?.(actionId);
+    } catch (error) {
+      callback?.(actionId, error);
+    }
+  });
+}
+
+/**
+ * Run kernel-declared app menu items in this window's session when one is
+ * chosen while this window has focus (or, with none focused, in the main
+ * window). The method comes from the kernel's menu manifest; `callback`
+ * gets the item id with the method's result, or the error.
+ * @returns Function that removes the listener
+ */
+export async function onAppMenuAction(
+  callback: (actionId: string, result?: unknown, error?: unknown) => void,
+): Promise<UnlistenFn> {
+  return listen<string>('app-menu-action', async (event) => {
+    const actionId = event.payload;
+    const sessionToken = getSessionToken();
+    try {
+      if (!sessionToken) throw new AuthenticationError('Not authenticated');
+      const result = await invokeWithNonce('app_menu_run', { sessionToken, actionId });
+      callback(actionId, result);
+    } catch (error) {
+      callback(actionId, undefined, error);
+    }
+  });
+}
+
+/**
+ * Subscribe to `reos://` links. Each arrives once a session is active and
+ * runs through `kernelRequest`, so the usual policy checks apply; the
+ * callback gets the link with its result. Links opened before this window
+ * loaded are taken when the callback is registered.
+ * @returns Function that removes the listener
+ */
+export async function onDeepLink(
+  callback: (link: DeepLink, result: unknown) => void,
+): Promise<UnlistenFn> {
+  const dispatch = async (link: DeepLink) => {
+    callback(link, await kernelRequest(link.method, link.params));
+  };
+  const unlisten = await listen<DeepLink>('deep-link', (event) => {
+    void dispatch(event.payload);
+  });
+  const sessionToken = getSessionToken();
+  if (sessionToken) {
+    const held = await invokeWithNonce<DeepLink | null>('deep_link_take', { sessionToken });
+    if (held) await dispatch(held);
+  }
+  return unlisten;
+}
+
+/**
+ * Subscribe to links that arrived with no active session; the link is
+ * dispatched through `onDeepLink` after login.
+ * @returns Function that removes the listener
+ */
+export async function onDeepLinkLoginRequired(
+  callback: (url: string) => void,
+): Promise<UnlistenFn> {
+  return listen<string>('deep-link-login-required', (event) => {
+    callback(event.payload);
+  });
+}
+
+/**
+ * Subscribe to later launches of the app (main window only). The app has
+ * already been brought to the front and any `reos://` link among the
+ * arguments routed; the callback gets the launch's command-line arguments.
+ * @returns Function that removes the listener
+ */
+export async function onSecondInstance(
+  callback: (args: string[]) => void,
+): Promise<UnlistenFn> {
+  return listen<string[]>('second-instance', (event) => {
+    callback(event.payload);
+  });
+}
+
+export type SetupStep = 'directories' | 'kernel' | 'venv';
+
+export interface SetupStepStatus {
+  step: SetupStep;
+  /** Optional steps don't block completion */
+  required: boolean;
+  state: 'done' | 'pending' | 'failed';
+  /** Present when state is 'failed' */
+  error?: string;
+  /** What was found, e.g. the interpreter and its version */
+  detail: string | null;
+}
+
+export interface SetupStatus {
+  /** Every required step is done */
+  complete: boolean;
+  steps: SetupStepStatus[];
+}
+
+/** First-run steps for the onboarding wizard (works before login) */
+export async function getSetupStatus(): Promise<SetupStatus> {
+  return await invoke<SetupStatus>('setup_status');
+}
+
+/**
+ * Run one first-run step. Needs no session until setup is complete, an
+ * admin session afterwards. 'venv' can take minutes.
+ */
+export async function runSetupStep(step: SetupStep): Promise<SetupStepStatus> {
+  return await invokeWithNonce<SetupStepStatus>('setup_run_step', {
+    sessionToken: getSessionToken(),
+    step,
+  });
+}
+
+/** Where the user's `config.toml` lives */
+export interface ConfigLocation {
+  /** null if the platform has no config dir */
+  path: string | null;
+  exists: boolean;
+  /** Why the file is being ignored (defaults in use), naming the key */
+  error: string | null;
+}
+
+export async function getConfigPath(): Promise<ConfigLocation> {
+  return await invoke<ConfigLocation>('config_path');
+}
+
+/** When the shell starts the kernel ahead of its first request */
+export type KernelStartPolicy = 'eager' | 'on_login' | 'on_first_request';
+
+/** How the shell talks to the kernel (from its next start) */
+export type KernelTransport = 'stdio' | 'unix' | 'pipe' | 'remote';
+
+/** What answers kernel requests (from its next start) */
+export type KernelBackend = 'process' | 'mock' | 'embedded';
+
+/** User settings (`config.toml` plus launch at login) */
+export interface Settings {
+  kernel: {
+    python?: string;
+    start: KernelStartPolicy;
+    transport: KernelTransport;
+    backend: KernelBackend;
+  };
+  session: { idle_timeout_secs: number };
+  logging: { level?: string };
+  ui: { theme: 'system' | 'light' | 'dark' };
+  /** Feature flag overrides: on/off for everyone, or the users it is on for */
+  features: Record<string, boolean | string[]>;
+  /** null where it can't be read */
+  autostart: AutostartStatus | null;
+  /** Keys set by `REOS_*` environment variables (can't be changed) */
+  overridden: (keyof SettingValues)[];
+}
+
+/** Values accepted by `setSetting`, per key */
+export interface SettingValues {
+  'kernel.python': string | null;
+  'kernel.start': KernelStartPolicy;
+  'kernel.transport': KernelTransport;
+  'kernel.backend': KernelBackend;
+  'session.idle_timeout_secs': number;
+  'logging.level': string | null;
+  'ui.theme': Settings['ui']['theme'];
+  autostart: AutostartStatus;
+}
+
+export async function getSettings(): Promise<Settings> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return await invoke<Settings>('settings_get', { sessionToken });
+}
+
+/**
+ * Change one setting. It is validated and saved by the shell; the error
+ * names the key when the value is rejected. `kernel.*` and `session.*`
+ * need an admin session and a recent password (see stepUp).
+ * @returns All settings after the change
+ */
+export async function setSetting<K extends keyof SettingValues>(
+  key: K,
+  value: SettingValues[K],
+): Promise<Settings> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return await invokeWithNonce<Settings>('settings_set', { sessionToken, key, value });
+}
+
+/** config.toml or rate-limits.json was edited while the app runs */
+export interface ConfigChange {
+  /** Keys with a new value ('rate_limits' for rate-limits.json) */
+  changed: string[];
+  /** Changed keys that only take effect after a restart */
+  restart_required: string[];
+  /** Why the edited config.toml was rejected (previous settings kept) */
+  error: string | null;
+}
+
+/**
+ * Subscribe to outside edits of the config files.
+ * @returns Function that removes the listener
+ */
+export async function onConfigChanged(
+  callback: (change: ConfigChange) => void,
+): Promise<UnlistenFn> {
+  return listen<ConfigChange>('config://changed', (event) => {
+    callback(event.payload);
+  });
+}
+
+/**
+ * Subscribe to settings changes made from any window.
+ * @returns Function that removes the listener
+ */
+export async function onSettingsChanged(
+  callback: (settings: Settings) => void,
+): Promise<UnlistenFn> {
+  return listen<Settings>('settings-changed', (event) => {
+    callback(event.payload);
+  });
+}
+
+/** A feature flag as it applies to the current user */
+export interface FeatureStatus {
+  name: string;
+  enabled: boolean;
+  /** Where the value came from (config.toml wins over the kernel) */
+  source: 'default' | 'kernel' | 'config';
+  /** null for flags declared by the kernel */
+  description: string | null;
+}
+
+/** @returns Every known feature flag for this session's user */
+export async function getFeatures(): Promise<FeatureStatus[]> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return await invoke<FeatureStatus[]>('features_get', { sessionToken });
+}
+
+/**
+ * Subscribe to feature flag changes (kernel restart, config.toml edit);
+ * call `getFeatures` again to see them.
+ * @returns Function that removes the listener
+ */
+export async function onFeaturesChanged(callback: () => void): Promise<UnlistenFn> {
+  return listen('features://changed', () => {
+    callback();
+  });
+}
+
+/** A method the running kernel answers */
+export interface KernelMethod {
+  name: string;
+  /** Whether this session may send it (allowlist, role, guest policy) */
+  allowed: boolean;
+}
+
+/**
+ * Methods from the running kernel's manifest, cached by the shell (no
+ * kernel round trip).
+ * @returns null before the kernel's handshake, or if it sent no manifest
+ */
+export async function listKernelMethods(): Promise<KernelMethod[] | null> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return await invoke<KernelMethod[] | null>('kernel_list_methods', { sessionToken });
+}
+
+/**
+ * Subscribe to method manifest changes (kernel handshake, exit or restart);
+ * call `listKernelMethods` again to see them.
+ * @returns Function that removes the listener
+ */
+export async function onKernelMethodsChanged(callback: () => void): Promise<UnlistenFn> {
+  return listen('kernel://methods-changed', () => {
+    callback();
+  });
+}
+
+/** System light/dark scheme, accent color and text scaling */
+export interface Appearance {
+  scheme: 'light' | 'dark';
+  /** `#rrggbb`, or null when the desktop doesn't set one */
+  accent: string | null;
+  /** Text size relative to the platform default */
+  font_scale: number;
+}
+
+export async function getAppearance(): Promise<Appearance> {
+  return await invoke<Appearance>('appearance_get');
+}
+
+/**
+ * Subscribe to system appearance changes.
+ * @returns Function that removes the listener
+ */
+export async function onAppearanceChanged(
+  callback: (appearance: Appearance) => void,
+): Promise<UnlistenFn> {
+  return listen<Appearance>('appearance-changed', (event) => {
+    callback(event.payload);
+  });
+}
+
+export interface Battery {
+  on_battery: boolean;
+  /** Charge in percent, or null without a battery */
+  percent: number | null;
+  low: boolean;
+}
+
+export type PowerEvent =
+  | { kind: 'suspend' }
+  | { kind: 'resume' }
+  | ({ kind: 'battery' } & Battery);
+
+/** @returns The last battery state, or null if it isn't known */
+export async function getPowerStatus(): Promise<Battery | null> {
+  return await invoke<Battery | null>('power_status');
+}
+
+/**
+ * Subscribe to suspend, resume and battery changes. Sessions are already
+ * locked when `suspend` arrives.
+ * @returns Function that removes the listener
+ */
+export async function onPowerChanged(callback: (event: PowerEvent) => void): Promise<UnlistenFn> {
+  return listen<PowerEvent>('power-changed', (event) => {
+    callback(event.payload);
+  });
+}
+
+/**
+ * Open a secondary window (label `doc-<name>`) on an app route, e.g.
+ * `/conversation/42`. It shares this window's session and kernel.
+ */
+export async function openWindow(label: string, route: string): Promise<void> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  await invokeWithNonce('window_open', { sessionToken, label, route });
+}
+
+/**
+ * Close a secondary window opened with `openWindow`.
+ */
+export async function closeWindow(label: string): Promise<void> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  await invokeWithNonce('window_close', { sessionToken, label });
+}
+
+/**
+ * In a window opened with `openWindow`, take over the opener's session.
+ * @returns true if a session was handed over
+ */
+export async function claimWindowSession(): Promise<boolean> {
+  try {
+    const handoff = await invokeWithNonce<{ sessionToken: string; username: string }>(
+      'window_handoff',
+      {},
+    );
+    setSession(handoff.sessionToken, handoff.username);
+    return true;
+  } catch {
+    return false;
+  }
+}
+
+/**
+ * Subscribe to focus changes across app windows.
+ * @returns Function that removes the listener
+ */
+export async function onWindowFocusChanged(
+  callback: (label: string) => void,
+): Promise<UnlistenFn> {
+  return listen<string>('window-focus-changed', (event) => {
+    callback(event.payload);
+  });
+}
+
+export interface DroppedFile {
+  id: string;
+  name: string;
+  size: number;
+}
+
+export interface IngestedFile {
+  /** Kernel-side handle of the stored file */
+  handle: string;
+  name: string;
+  size: number;
+}
+
+/**
+ * Subscribe to files dropped on this window. Paths are checked against the
+ * drop allowlist in Rust; only accepted files arrive, as opaque drop ids.
+ * @returns Function that removes the listener
+ */
+export async function onFileDropped(
+  callback: (file: DroppedFile) => void,
+): Promise<UnlistenFn> {
+  return listen<DroppedFile>('file-dropped', (event) => {
+    callback(event.payload);
+  });
+}
+
+/**
+ * Subscribe to dropped files that failed the allowlist (outside the allowed
+ * folders, wrong type, too large).
+ * @returns Function that removes the listener
+ */
+export async function onFileDropRejected(
+  callback: (rejected: { name: string; reason: string }) => void,
+): Promise<UnlistenFn> {
+  return listen<{ name: string; reason: string }>('file-drop-rejected', (event) => {
+    callback(event.payload);
+  });
+}
+
+/**
+ * Stream a dropped file to the kernel; the contents go from Rust to the
+ * kernel directly. Progress arrives through `onIngestProgress`.
+ */
+export async function ingestDroppedFile(dropId: string): Promise<IngestedFile> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return invokeWithNonce<IngestedFile>('file_ingest', { sessionToken, dropId });
+}
+
+/**
+ * Subscribe to bytes sent while dropped files are ingested.
+ * @returns Function that removes the listener
+ */
+export async function onIngestProgress(
+  callback: (progress: { id: string; sent: number; total: number }) => void,
+): Promise<UnlistenFn> {
+  return listen<{ id: string; sent: number; total: number }>('file-ingest-progress', (event) => {
+    callback(event.payload);
+  });
+}
+
+export interface ExportedFile {
+  path: string;
+  size: number;
+}
+
+/**
+ * Run a kernel method and save its result to a file chosen in the native
+ * save dialog. Rust writes the file; the result never passes through the
+ * webview. Progress arrives through `onExportProgress`.
+ * @returns The saved file, or null if the dialog was cancelled
+ */
+export async function exportFile(
+  method: string,
+  params: unknown,
+  fileName?: string,
+): Promise<ExportedFile | null> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return invokeWithNonce<ExportedFile | null>('export_file', {
+    sessionToken,
+    method,
+    params,
+    fileName,
+  });
+}
+
+/**
+ * Save a diagnostics bundle (logs, redacted config, kernel stderr,
+ * versions and metrics) as a zip chosen in the native save dialog, to
+ * attach to bug reports.
+ * @returns The saved file, or null if the dialog was cancelled
+ */
+export async function exportDiagnostics(): Promise<ExportedFile | null> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return invokeWithNonce<ExportedFile | null>('diagnostics_export', { sessionToken });
+}
+
+/**
+ * Subscribe to bytes written while exports are saved.
+ * @returns Function that removes the listener
+ */
+export async function onExportProgress(
+  callback: (progress: { id: string; written: number; total: number }) => void,
+): Promise<UnlistenFn> {
+  return listen<{ id: string; written: number; total: number }>('file-export-progress', (event) => {
+    callback(event.payload);
+  });
+}
+
+export interface PrintOptions {
+  paper?: 'a4' | 'letter';
+  landscape?: boolean;
+  copies?: number;
+  header?: boolean;
+  dialog?: boolean;
+}
+
+/**
+ * Print a kernel document (`conversation:<id>`, `archive:<id>` or `me`)
+ * through the native print dialog. Rust fetches and lays out the content.
+ * @returns False if the dialog was cancelled
+ */
+export async function printDocument(handle: string, options?: PrintOptions): Promise<boolean> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  return invokeWithNonce<boolean>('print_document', { sessionToken, handle, options });
+}
+
+export interface PrintCapabilities {
+  /** false: printDocument always fails on this platform */
+  supported: boolean;
+  /** false: `dialog` is ignored and documents go straight to the default printer */
+  dialog: boolean;
+}
+
+/**
+ * What printing supports on this platform.
+ */
+export async function getPrintCapabilities(): Promise<PrintCapabilities> {
+  return await invoke<PrintCapabilities>('print_capabilities');
+}
+
+export interface KernelStartupProgress {
+  stage: 'verifying' | 'spawning' | 'handshake' | 'initializing' | 'ready';
+  /** 1-based */
+  step: number;
+  total: number;
+}
+
+/**
+ * Subscribe to kernel start stages (at launch, and after a kernel restart).
+ * @returns Function that removes the listener
+ */
+export async function onKernelStartupProgress(
+  callback: (progress: KernelStartupProgress) => void,
+): Promise<UnlistenFn> {
+  return listen<KernelStartupProgress>('kernel-startup-progress', (event) => {
+    callback(event.payload);
+  });
+}
+
+/**
+ * Start the kernel ahead of the first request (requires a session;
+ * logging in starts it anyway).
+ */
+export async function startKernel(): Promise<void> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  await invokeWithNonce('kernel_start', { sessionToken });
+}
+
+/** A large result the kernel handed over out of band, read in segments */
+export interface KernelBlob {
+  handle: string;
+  /** Bytes of serialized JSON */
+  size: number;
+  segments: number;
+  segment_size: number;
+}
+
+function asKernelBlob(result: unknown): KernelBlob | null {
+  if (typeof result !== 'object' || result === null || !('__blob' in result)) return null;
+  return (result as { __blob: KernelBlob }).__blob;
+}
+
+/**
+ * Stream a large result's bytes, one decrypted segment at a time; the
+ * handle is released once done (or on error).
+ */
+export async function* readKernelBlob(blob: KernelBlob): AsyncGenerator<Uint8Array> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  try {
+    for (let index = 0; index < blob.segments; index++) {
+      const segment = await invoke<ArrayBuffer>('kernel_blob_read', {
+        sessionToken,
+        handle: blob.handle,
+        index,
+      });
+      yield new Uint8Array(segment);
+    }
+  } finally {
+    await invokeWithNonce<boolean>('kernel_blob_release', { sessionToken, handle: blob.handle });
+  }
+}
+
+/** Read a large result whole and parse it */
+async function parseKernelBlob(blob: KernelBlob): Promise<unknown> {
+  const data = new Uint8Array(blob.size);
+  let offset = 0;
+  for await (const segment of readKernelBlob(blob)) {
+    data.set(segment, offset);
+    offset += segment.length;
+  }
+  return JSON.parse(new TextDecoder().decode(data));
+}
+
+/** A large result array kept by the shell, read in chunks of items */
+export interface KernelArrayStream {
+  handle: string;
+  items: number;
+  chunks: number;
+}
+
+/** The stream a result (or a field of it) was replaced by, if any */
+export function asKernelArrayStream(value: unknown): KernelArrayStream | null {
+  if (typeof value !== 'object' || value === null || !('__stream' in value)) return null;
+  return (value as { __stream: KernelArrayStream }).__stream;
+}
+
+/**
+ * Stream a large result array, one chunk of items at a time; the handle
+ * is released once done (or on error).
+ */
+export async function* readKernelArray(stream: KernelArrayStream): AsyncGenerator<unknown[]> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) throw new AuthenticationError('Not authenticated');
+  const decoder = new TextDecoder();
+  try {
+    for (let index = 0; index < stream.chunks; index++) {
+      const chunk = await invoke<ArrayBuffer>('kernel_stream_read', {
+        sessionToken,
+        handle: stream.handle,
+        index,
+      });
+      yield JSON.parse(decoder.decode(chunk)) as unknown[];
+    }
+  } finally {
+    await invokeWithNonce<boolean>('kernel_stream_release', {
+      sessionToken,
+      handle: stream.handle,
+    });
+  }
+}
+
+async function collectKernelArray(stream: KernelArrayStream): Promise<unknown[]> {
+  const items: unknown[] = [];
+  for await (const chunk of readKernelArray(stream)) {
+    for (const item of chunk) items.push(item);
+  }
+  return items;
+}
+
+/** Replace streamed arrays (the result itself, or its fields) with their items */
+async function materializeKernelArrays(result: unknown): Promise<unknown> {
+  const stream = asKernelArrayStream(result);
+  if (stream) return collectKernelArray(stream);
+  if (typeof result !== 'object' || result === null || Array.isArray(result)) return result;
+  const fields = result as Record<string, unknown>;
+  for (const [key, value] of Object.entries(fields)) {
+    const field = asKernelArrayStream(value);
+    if (field) fields[key] = await collectKernelArray(field);
+  }
+  return fields;
+}
+
+/**
+ * Send a JSON-RPC request to the Python kernel.
+ * Requires an authenticated session.
+ *
+ * Large results the kernel moved out of band are read back and parsed,
+ * and large arrays the shell streams are collected; use
+ * `kernelRequestStream` to stream them instead.
+ *
+ * @param method - The RPC method name (e.g., 'chat/respond', 'tools/call')
+ * @param params - The parameters for the method
+ * @returns The result from the kernel
+ * @throws AuthenticationError if not authenticated
+ * @throws KernelError if the kernel returns an error
+ * @throws MethodDeniedError if the session's role may not call the method
+ * @throws RateLimitedError if the session exceeded its request budget
+ */
+export async function kernelRequest(method: string, params: unknown): Promise<unknown> {
+  const result = await kernelRequestStream(method, params);
+  if ('blob' in result) return parseKernelBlob(result.blob);
+  return materializeKernelArrays(result.result);
+}
+
+/**
+ * `kernelRequest`, leaving a large result as a `KernelBlob` to read with
+ * `readKernelBlob`, and large arrays (the result or its fields) as
+ * `__stream` references to read with `readKernelArray` (see
+ * `asKernelArrayStream`). Unread blobs and streams expire after ten
+ * minutes.
+ */
+export async function kernelRequestStream(
+  method: string,
+  params: unknown,
+): Promise<{ result: unknown } | { blob: KernelBlob }> {
+  const sessionToken = getSessionToken();
+
+  if (!sessionToken) {
+    throw new AuthenticationError('Not authenticated. Please login first.');
+  }
+
+  const raw = await invokeWithNonce('kernel_request', { sessionToken, method, params });
   const parsed = JsonRpcResponseSchema.parse(raw);
 
   if (parsed.error) {
@@ -191,8 +1750,17 @@ export async function kernelRequest(method: string, params: unknown): Promise<un
       clearSession();
       throw new AuthenticationError('Session expired. Please login again.');
     }
+    if (parsed.error.code === RATE_LIMITED_CODE) {
+      const data = (parsed.error.data ?? {}) as { retry_after_ms?: number };
+      throw new RateLimitedError(parsed.error.message, data.retry_after_ms ?? 1000);
+    }
+    if (parsed.error.code === METHOD_DENIED_CODE) {
+      const data = (parsed.error.data ?? {}) as { method?: string; role?: string };
+      throw new MethodDeniedError(parsed.error.message, data.method ?? method, data.role ?? 'unknown');
+    }
     throw new KernelError(parsed.error.message, parsed.error.code);
   }
 
-  return parsed.result;
+  const blob = asKernelBlob(parsed.result);
+  return blob ? { blob } : { result: parsed.result };
 }
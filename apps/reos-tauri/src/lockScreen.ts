@@ -8,7 +8,14 @@
  * - Smartcards
  * - Any other PAM-configured auth method
  */
-import { login, getSessionUsername, isAuthenticated, validateSession, getSystemUsername } from './kernel';
+import {
+  login,
+  getSessionUsername,
+  isAuthenticated,
+  validateSession,
+  getSystemUsername,
+  claimWindowSession,
+} from './kernel';
 import { el } from './dom';
 
 export interface LockScreenOptions {
@@ -350,8 +357,8 @@ export async function checkSessionOrLogin(
   root: HTMLElement,
   onLogin: (username: string) => void
 ): Promise<boolean> {
-  // Check if we have a session token
-  if (!isAuthenticated()) {
+  // Check if we have a session token (or one handed over by our opener)
+  if (!isAuthenticated() && !(await claimWindowSession())) {
     await showLockScreen(root, { onLogin });
     return false;
   }
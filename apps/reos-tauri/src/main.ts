@@ -18,6 +18,7 @@ import {
   validateSession,
   logout,
   getSessionUsername,
+  forwardConsoleToLog,
 } from './kernel';
 import { checkSessionOrLogin, showLockOverlay } from './lockScreen';
 import { el, rowHeader, label, textInput, textArea, smallButton } from './dom';
@@ -3370,6 +3371,7 @@ function setupSessionMonitoring(): void {
 }
 
 // Initialize app on load
+forwardConsoleToLog();
 initializeApp().catch((err) => {
   console.error('Failed to initialize app:', err);
   // Show error prominently in the UI
@@ -0,0 +1,24 @@
+/**
+ * Startup splash: shows kernel start progress until Rust swaps in the
+ * main window. Deliberately imports nothing but the event API.
+ */
+import { listen } from '@tauri-apps/api/event';
+
+import type { KernelStartupProgress } from './kernel';
+
+const STAGE_TEXT: Record<KernelStartupProgress['stage'], string> = {
+  verifying: 'Verifying kernel…',
+  spawning: 'Starting kernel…',
+  handshake: 'Securing channel…',
+  initializing: 'Initializing…',
+  ready: 'Ready',
+};
+
+const fill = document.getElementById('fill');
+const stage = document.getElementById('stage');
+
+void listen<KernelStartupProgress>('kernel-startup-progress', (event) => {
+  const { step, total } = event.payload;
+  if (fill) fill.style.width = `${Math.round((step / total) * 100)}%`;
+  if (stage) stage.textContent = STAGE_TEXT[event.payload.stage];
+});
@@ -8,5 +8,14 @@ export default defineConfig({
       'Cache-Control': 'no-store'
     }
   },
+  build: {
+    rollupOptions: {
+      // The splash window loads its own small page
+      input: {
+        main: 'index.html',
+        splash: 'splash.html'
+      }
+    }
+  },
   clearScreen: false
 });
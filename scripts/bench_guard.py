@@ -0,0 +1,76 @@
+#!/usr/bin/env python3
+"""Compare RPC benchmark results against recorded baselines.
+
+Run after `cargo bench --features bench` in apps/reos-tauri/src-tauri:
+    python scripts/bench_guard.py            # fail on regressions
+    python scripts/bench_guard.py --record   # accept the current results
+
+Reads criterion's mean estimate for every benchmark under
+target/criterion and compares it with bench-baselines.json next to the
+benchmarks. A benchmark more than --tolerance slower than its baseline
+fails the check (exit status 1); new benchmarks without a baseline are
+listed but don't fail it. Baselines are only comparable on the machine they
+were recorded on, so record them on the machine that runs the check.
+
+The benchmarks live in apps/reos-tauri/src-tauri/benches/rpc.rs.
+"""
+
+from __future__ import annotations
+
+import argparse
+import json
+import sys
+from pathlib import Path
+
+CRATE = Path(__file__).resolve().parent.parent / "apps" / "reos-tauri" / "src-tauri"
+BASELINES = CRATE / "benches" / "bench-baselines.json"
+DEFAULT_TOLERANCE = 0.25
+
+
+def current_results(criterion_dir: Path) -> dict[str, float]:
+    """Mean time per iteration (ns) by benchmark id, from criterion's output."""
+    results = {}
+    for estimates in sorted(criterion_dir.glob("**/new/estimates.json")):
+        bench = estimates.parent.parent.relative_to(criterion_dir).as_posix()
+        data = json.loads(estimates.read_text())
+        results[bench] = round(data["mean"]["point_estimate"], 1)
+    return results
+
+
+def main() -> int:
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("--record", action="store_true", help="write the current results")
+    parser.add_argument("--tolerance", type=float, default=DEFAULT_TOLERANCE)
+    parser.add_argument("--criterion-dir", type=Path, default=CRATE / "target" / "criterion")
+    args = parser.parse_args()
+
+    results = current_results(args.criterion_dir)
+    if not results:
+        print(f"no benchmark results under {args.criterion_dir}", file=sys.stderr)
+        return 2
+
+    if args.record:
+        BASELINES.write_text(json.dumps(results, indent=2, sort_keys=True) + "\n")
+        print(f"recorded {len(results)} baselines in {BASELINES}")
+        return 0
+
+    if not BASELINES.exists():
+        print(f"no baselines yet: run with --record to create {BASELINES}", file=sys.stderr)
+        return 2
+    baselines: dict[str, float] = json.loads(BASELINES.read_text())
+
+    regressions = 0
+    for bench, mean in results.items():
+        baseline = baselines.get(bench)
+        if baseline is None:
+            print(f"NEW   {bench}: {mean:.0f} ns")
+            continue
+        change = mean / baseline - 1
+        slower = change > args.tolerance
+        regressions += slower
+        print(f"{'SLOW' if slower else 'ok':<5} {bench}: {mean:.0f} ns ({change:+.1%})")
+    return 1 if regressions else 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
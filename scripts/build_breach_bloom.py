@@ -0,0 +1,68 @@
+#!/usr/bin/env python3
+"""Build the breached-password bloom filter bundled with the Tauri app.
+
+Run with: python scripts/build_breach_bloom.py <passwords.txt> [output]
+
+The input is one password per line (e.g. a SecLists or HIBP top-N list).
+Passwords are lowercased before insertion, matching the lookup in
+apps/reos-tauri/src-tauri/src/password_policy.rs.
+
+File format (little endian):
+    b"RBF1" | k: u8 | m: u32 (bits) | ceil(m / 8) bytes of bits
+Bit i of the filter is byte i // 8, bit i % 8. Probe j of a password is
+(h1 + j * h2) mod m, where h1/h2 are the first two u64s of its SHA-256
+(h2 forced odd).
+"""
+
+from __future__ import annotations
+
+import hashlib
+import math
+import struct
+import sys
+from pathlib import Path
+
+DEFAULT_OUTPUT = (
+    Path(__file__).resolve().parent.parent
+    / "apps/reos-tauri/src-tauri/assets/breached-passwords.bloom"
+)
+FALSE_POSITIVE_RATE = 1e-6
+MASK64 = (1 << 64) - 1
+
+
+def probes(password: str, k: int, m: int) -> list[int]:
+    digest = hashlib.sha256(password.lower().encode()).digest()
+    h1, h2 = struct.unpack_from("<QQ", digest)
+    h2 |= 1
+    return [((h1 + j * h2) & MASK64) % m for j in range(k)]
+
+
+def build(passwords: list[str]) -> bytes:
+    n = max(len(passwords), 1)
+    m = math.ceil(-n * math.log(FALSE_POSITIVE_RATE) / math.log(2) ** 2)
+    m = (m + 7) // 8 * 8
+    k = max(1, round(m / n * math.log(2)))
+    bits = bytearray(m // 8)
+    for password in passwords:
+        for i in probes(password, k, m):
+            bits[i // 8] |= 1 << (i % 8)
+    return b"RBF1" + struct.pack("<BI", k, m) + bytes(bits)
+
+
+def main() -> int:
+    if len(sys.argv) not in (2, 3):
+        print(__doc__)
+        return 1
+    source = Path(sys.argv[1])
+    output = Path(sys.argv[2]) if len(sys.argv) == 3 else DEFAULT_OUTPUT
+    passwords = sorted(
+        {line.strip() for line in source.read_text(errors="ignore").splitlines() if line.strip()}
+    )
+    output.parent.mkdir(parents=True, exist_ok=True)
+    output.write_bytes(build(passwords))
+    print(f"wrote {len(passwords)} passwords to {output}")
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -0,0 +1,85 @@
+#!/usr/bin/env python3
+"""Sign the kernel manifest checked by the Tauri shell before launch.
+
+Run with:
+    python scripts/sign_kernel_manifest.py keygen <private.pem>
+    python scripts/sign_kernel_manifest.py sign <private.pem> [kernel_root]
+
+`keygen` writes a new Ed25519 key and prints the public key; build the app
+with it in REOS_KERNEL_SIGNING_KEY. `sign` lists every loadable file under
+<kernel_root>/reos (default: src/) with its SHA-256 in kernel-manifest.json
+and writes the hex signature to kernel-manifest.json.sig. Bytecode caches
+are skipped: the shell rejects unlisted .pyc files, so ship without them.
+
+Verification lives in apps/reos-tauri/src-tauri/src/kernel_integrity.rs.
+"""
+
+from __future__ import annotations
+
+import hashlib
+import json
+import sys
+from pathlib import Path
+
+from cryptography.hazmat.primitives.asymmetric.ed25519 import Ed25519PrivateKey
+from cryptography.hazmat.primitives.serialization import (
+    Encoding,
+    NoEncryption,
+    PrivateFormat,
+    PublicFormat,
+    load_pem_private_key,
+)
+
+DEFAULT_ROOT = Path(__file__).resolve().parent.parent / "src"
+MANIFEST_FILE = "kernel-manifest.json"
+SIGNATURE_FILE = "kernel-manifest.json.sig"
+# Keep in sync with LOADABLE_EXTENSIONS in kernel_integrity.rs
+LOADABLE_SUFFIXES = {".py", ".pth", ".so", ".pyd"}
+
+
+def public_hex(key: Ed25519PrivateKey) -> str:
+    return key.public_key().public_bytes(Encoding.Raw, PublicFormat.Raw).hex()
+
+
+def keygen(private_path: Path) -> None:
+    key = Ed25519PrivateKey.generate()
+    private_path.write_bytes(
+        key.private_bytes(Encoding.PEM, PrivateFormat.PKCS8, NoEncryption())
+    )
+    private_path.chmod(0o600)
+    print(public_hex(key))
+
+
+def manifest(root: Path) -> bytes:
+    files = {
+        path.relative_to(root).as_posix(): hashlib.sha256(path.read_bytes()).hexdigest()
+        for path in sorted((root / "reos").rglob("*"))
+        if path.is_file() and path.suffix in LOADABLE_SUFFIXES and "__pycache__" not in path.parts
+    }
+    return json.dumps({"version": 1, "files": files}, indent=2).encode()
+
+
+def sign(private_path: Path, root: Path) -> None:
+    key = load_pem_private_key(private_path.read_bytes(), password=None)
+    if not isinstance(key, Ed25519PrivateKey):
+        raise SystemExit("not an Ed25519 private key")
+    body = manifest(root)
+    (root / MANIFEST_FILE).write_bytes(body)
+    (root / SIGNATURE_FILE).write_text(key.sign(body).hex() + "\n")
+    print(f"Signed {len(json.loads(body)['files'])} files for key {public_hex(key)}")
+
+
+def main() -> int:
+    args = sys.argv[1:]
+    if len(args) == 2 and args[0] == "keygen":
+        keygen(Path(args[1]))
+        return 0
+    if len(args) in (2, 3) and args[0] == "sign":
+        sign(Path(args[1]), Path(args[2]) if len(args) == 3 else DEFAULT_ROOT)
+        return 0
+    print(__doc__)
+    return 1
+
+
+if __name__ == "__main__":
+    sys.exit(main())
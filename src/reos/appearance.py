@@ -0,0 +1,52 @@
+"""The desktop appearance, as reported by the Rust shell.
+
+``appearance/set`` is sent by the shell when the kernel starts and whenever
+the user's light/dark scheme, accent color or text scaling changes; anything
+the kernel renders itself reads it with ``current()`` to match the app.
+"""
+
+from __future__ import annotations
+
+import re
+import threading
+from typing import Any
+
+MIN_FONT_SCALE = 0.5
+MAX_FONT_SCALE = 3.0
+
+_ACCENT = re.compile(r"#[0-9a-f]{6}")
+
+_lock = threading.Lock()
+_current: dict[str, Any] = {"scheme": "light", "accent": None, "font_scale": 1.0}
+
+
+class AppearanceError(Exception):
+    """Malformed appearance."""
+
+
+def set_appearance(params: dict[str, Any]) -> dict[str, Any]:
+    """Validate and store the shell's appearance."""
+    global _current
+    scheme = params.get("scheme")
+    if scheme not in ("light", "dark"):
+        raise AppearanceError("scheme must be light or dark")
+    accent = params.get("accent")
+    if accent is not None and not (isinstance(accent, str) and _ACCENT.fullmatch(accent)):
+        raise AppearanceError("accent must be #rrggbb")
+    font_scale = params.get("font_scale", 1.0)
+    if (
+        isinstance(font_scale, bool)
+        or not isinstance(font_scale, (int, float))
+        or not MIN_FONT_SCALE <= font_scale <= MAX_FONT_SCALE
+    ):
+        raise AppearanceError("font_scale is out of range")
+    appearance = {"scheme": scheme, "accent": accent, "font_scale": float(font_scale)}
+    with _lock:
+        _current = appearance
+    return {"ok": True}
+
+
+def current() -> dict[str, Any]:
+    """The last appearance the shell reported (light, no accent, scale 1 until then)."""
+    with _lock:
+        return dict(_current)
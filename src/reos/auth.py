@@ -17,6 +17,7 @@ Security Notes:
 from __future__ import annotations
 
 import hashlib
+import hmac
 import os
 import secrets
 import subprocess
@@ -32,6 +33,9 @@ from cryptography.hazmat.primitives.kdf.scrypt import Scrypt
 # Session idle timeout (15 minutes)
 SESSION_IDLE_TIMEOUT_SECONDS = 15 * 60
 
+# PAM service for password checks
+PAM_SERVICE = "login"
+
 
 @dataclass
 class Session:
@@ -91,6 +95,18 @@ class SessionStore:
                 return True
             return False
 
+    def remove_user(self, username: str) -> int:
+        """Remove every session of `username` (zeroizing keys). Returns the count."""
+        with self._lock:
+            tokens = [t for t, s in self._sessions.items() if s.username == username]
+            for token in tokens:
+                session = self._sessions[token]
+                if session.key_material:
+                    zeros = bytes(len(session.key_material))
+                    session.key_material = zeros
+                del self._sessions[token]
+            return len(tokens)
+
     def refresh(self, token: str) -> bool:
         """Refresh a session's activity timestamp."""
         with self._lock:
@@ -100,6 +116,20 @@ class SessionStore:
                 return True
             return False
 
+    def snapshot(self) -> list[Session]:
+        """Live (unexpired) sessions."""
+        with self._lock:
+            return [s for s in self._sessions.values() if not s.is_expired()]
+
+    def insert_all(self, sessions: list[Session]) -> bool:
+        """Store `sessions` if the store is still empty (a fresh kernel)."""
+        with self._lock:
+            if self._sessions:
+                return False
+            for session in sessions:
+                self._sessions[session.token] = session
+            return True
+
     def cleanup_expired(self) -> int:
         """Remove all expired sessions. Returns count of removed sessions."""
         with self._lock:
@@ -122,6 +152,13 @@ def get_session_store() -> SessionStore:
     return _session_store
 
 
+def is_valid_username(username: str) -> bool:
+    """Check a username against the Linux username rules we accept."""
+    if not username or len(username) > 32:
+        return False
+    return all(c.isalnum() or c in "_-" for c in username)
+
+
 def authenticate_polkit(username: str) -> bool:
     """Authenticate user via Polkit (native system dialog).
 
@@ -184,6 +221,29 @@ def authenticate_polkit(username: str) -> bool:
         return False
 
 
+def authenticate_pam(username: str, password: str) -> bool:
+    """Check a user's account password through PAM.
+
+    Args:
+        username: Linux username
+        password: Password to check
+
+    Returns:
+        True if PAM accepted the password, False otherwise (including
+        when python-pam isn't installed)
+    """
+    if not password:
+        return False
+    try:
+        import pam
+    except ImportError:
+        return False
+    try:
+        return bool(pam.authenticate(username, password, service=PAM_SERVICE))
+    except Exception:
+        return False
+
+
 def derive_encryption_key(username: str, password: str) -> bytes:
     """Derive a 256-bit encryption key from username and password.
 
@@ -310,6 +370,119 @@ def login_polkit(username: str) -> dict[str, Any]:
     }
 
 
+def open_session(username: str, passphrase: str) -> dict[str, Any]:
+    """Open a session for a user someone else already authenticated.
+
+    For logins vouched for outside this kernel (the desktop session, OIDC,
+    LDAP, a password file): only the key is derived here, from `passphrase`.
+
+    Args:
+        username: Linux username
+        passphrase: Secret the encryption key is derived from
+
+    Returns:
+        Dict with success status, session_token, username, or error
+    """
+    if not is_valid_username(username):
+        return {"success": False, "error": "Invalid username"}
+    if not passphrase:
+        return {"success": False, "error": "Passphrase is required"}
+
+    now = datetime.now(timezone.utc)
+    session = Session(
+        token=generate_session_token(),
+        username=username,
+        created_at=now,
+        last_activity=now,
+        key_material=derive_encryption_key(username, passphrase),
+    )
+    _session_store.insert(session)
+    session.get_user_data_root().mkdir(parents=True, exist_ok=True)
+    return {
+        "success": True,
+        "session_token": session.token,
+        "username": session.username,
+    }
+
+
+def login_ldap(
+    username: str, password: str, url: str, user_dn: str, ca_cert: str | None = None
+) -> dict[str, Any]:
+    """Authenticate by binding to an LDAP directory, then open a session.
+
+    Args:
+        username: Linux username (escaped into `user_dn`)
+        password: Directory password, also the encryption key's source
+        url: ldaps:// or ldap:// (StartTLS) URL of the directory
+        user_dn: DN template containing "{username}"
+        ca_cert: CA bundle to verify the server with (system store if None)
+
+    Returns:
+        Dict with success status, session_token, username, or error
+    """
+    from . import ldap_auth
+
+    if not is_valid_username(username):
+        return {"success": False, "error": "Invalid username"}
+    try:
+        if not ldap_auth.bind(url, user_dn, username, password, ca_cert):
+            return {"success": False, "error": "Invalid username or password"}
+    except ldap_auth.LdapError as exc:
+        return {"success": False, "error": str(exc)}
+    return open_session(username, password)
+
+
+def os_session_owner(os_session_id: str) -> str | None:
+    """Owner of an active, local logind session (None if it isn't one).
+
+    Args:
+        os_session_id: logind session id, e.g. "2" or "c1"
+
+    Returns:
+        The session's username, or None if it's remote, inactive, unknown
+        or loginctl isn't available
+    """
+    if not os_session_id or not os_session_id.isalnum():
+        return None
+    command = ["loginctl", "show-session", os_session_id]
+    for prop in ("Name", "Active", "Remote"):
+        command += ["-p", prop]
+    try:
+        result = subprocess.run(
+            command,
+            capture_output=True,
+            text=True,
+            timeout=5,
+        )
+    except (OSError, subprocess.TimeoutExpired):
+        return None
+    if result.returncode != 0:
+        return None
+    properties = dict(line.split("=", 1) for line in result.stdout.splitlines() if "=" in line)
+    if properties.get("Active") != "yes" or properties.get("Remote") != "no":
+        return None
+    return properties.get("Name") or None
+
+
+def login_os_session(username: str, os_session_id: str, passphrase: str) -> dict[str, Any]:
+    """Open a session vouched for by the user's desktop login session.
+
+    The Rust shell checked the session through logind already; it's checked
+    again here so a request naming someone else's session is refused.
+
+    Args:
+        username: Linux username
+        os_session_id: logind session the shell runs in
+        passphrase: Secret the encryption key is derived from
+
+    Returns:
+        Dict with success status, session_token, username, or error
+    """
+    if os_session_owner(os_session_id) != username:
+        return {"success": False, "error": "Not in this user's desktop session"}
+    return open_session(username, passphrase)
+
+
 # Keep old function name for compatibility
 def login(username: str, password: str | None = None) -> dict[str, Any]:
     """Authenticate and create a session.
@@ -340,6 +513,92 @@ def logout(session_token: str) -> dict[str, Any]:
     return {"success": False, "error": "Session not found"}
 
 
+def verify_password(session_token: str, username: str, password: str) -> dict[str, Any]:
+    """Check the password of a session's user (unlock, step-up).
+
+    The Rust shell has matched the session to `username` already, and may
+    have kept it locked past this kernel's idle timeout; a session this
+    kernel still holds must belong to `username`, and is refreshed on
+    success. Its key material doesn't change.
+
+    Args:
+        session_token: A session of `username`
+        username: Linux username
+        password: The user's account password
+
+    Returns:
+        Dict with success status and username, or error
+    """
+    session = _session_store.get(session_token)
+    if session is not None and session.username != username:
+        return {"success": False, "error": "Invalid session"}
+    if not authenticate_pam(username, password):
+        return {"success": False, "error": "Incorrect password"}
+    _session_store.refresh(session_token)
+    return {"success": True, "username": username}
+
+
+def change_password(
+    session_token: str, username: str, current_password: str, new_password: str
+) -> dict[str, Any]:
+    """Change a user's account password and rotate their session.
+
+    Checks the current password, changes it through PAM, re-encrypts the
+    user's data under a key derived from the new password, then replaces
+    every session of the user with a single new one.
+
+    Args:
+        session_token: A live session of `username` (its key decrypts the data)
+        username: Linux username
+        current_password: The user's current account password
+        new_password: The password to set
+
+    Returns:
+        Dict with success status, the new session_token and username, or error
+    """
+    from . import pam_password
+    from .crypto_storage import CryptoStorage
+
+    session = _session_store.get(session_token)
+    if session is None or session.username != username:
+        return {"success": False, "error": "Invalid session"}
+    if not authenticate_pam(username, current_password):
+        return {"success": False, "error": "Incorrect password"}
+    try:
+        pam_password.change_password(username, current_password, new_password)
+    except pam_password.PamError as exc:
+        return {"success": False, "error": str(exc)}
+
+    now = datetime.now(timezone.utc)
+    rotated = Session(
+        token=generate_session_token(),
+        username=username,
+        created_at=now,
+        last_activity=now,
+        key_material=derive_encryption_key(username, new_password),
+    )
+    CryptoStorage(session).rekey(CryptoStorage(rotated))
+    _session_store.remove_user(username)
+    _session_store.insert(rotated)
+    return {
+        "success": True,
+        "session_token": rotated.token,
+        "username": username,
+    }
+
+
+def revoke_user(username: str) -> dict[str, Any]:
+    """Destroy every session of a user (zeroizes their key material).
+
+    Args:
+        username: Linux username
+
+    Returns:
+        Dict with the number of sessions revoked
+    """
+    return {"revoked": _session_store.remove_user(username)}
+
+
 def validate_session(session_token: str) -> dict[str, Any]:
     """Validate a session token.
 
@@ -358,6 +617,29 @@ def validate_session(session_token: str) -> dict[str, Any]:
     return {"valid": False}
 
 
+def validate_session_hash(token_sha256: str) -> dict[str, Any]:
+    """Validate a session by the SHA-256 of its token.
+
+    The Rust shell persists only token hashes, so this is how it checks
+    which saved sessions this kernel still holds before restoring them.
+
+    Args:
+        token_sha256: Hex SHA-256 of the session token
+
+    Returns:
+        Dict with valid status and session info if valid
+    """
+    wanted = token_sha256.lower()
+    for session in _session_store.snapshot():
+        digest = hashlib.sha256(session.token.encode()).hexdigest()
+        if hmac.compare_digest(digest, wanted):
+            return {
+                "valid": True,
+                "username": session.username,
+            }
+    return {"valid": False}
+
+
 def get_session(session_token: str) -> Session | None:
     """Get a session by token.
 
@@ -382,6 +664,63 @@ def refresh_session(session_token: str) -> bool:
     return _session_store.refresh(session_token)
 
 
+def export_sessions() -> list[dict[str, Any]]:
+    """Live sessions with their key material, for a replacement kernel.
+
+    Only ever sent to the Rust shell over the encrypted channel, during a
+    hot swap (`session/export`).
+
+    Returns:
+        One dict per session, `key_material` hex-encoded
+    """
+    return [
+        {
+            "token": s.token,
+            "username": s.username,
+            "created_at": s.created_at.isoformat(),
+            "last_activity": s.last_activity.isoformat(),
+            "key_material": s.key_material.hex(),
+        }
+        for s in _session_store.snapshot()
+    ]
+
+
+def import_sessions(sessions: list[Any]) -> int:
+    """Take over sessions exported by the kernel this one replaces.
+
+    Accepted once, before any session exists, so it can't be used to add
+    sessions to a kernel already in use.
+
+    Args:
+        sessions: Output of `export_sessions`
+
+    Returns:
+        Number of sessions imported
+
+    Raises:
+        ValueError: If an entry is malformed or sessions already exist
+    """
+    parsed = []
+    for entry in sessions:
+        if not isinstance(entry, dict):
+            raise ValueError("session entries must be objects")
+        try:
+            parsed.append(
+                Session(
+                    token=str(entry["token"]),
+                    username=str(entry["username"]),
+                    created_at=datetime.fromisoformat(entry["created_at"]),
+                    last_activity=datetime.fromisoformat(entry["last_activity"]),
+                    key_material=bytes.fromhex(entry["key_material"]),
+                )
+            )
+        except (KeyError, TypeError, ValueError) as exc:
+            raise ValueError(f"malformed session entry: {exc}") from None
+    if not _session_store.insert_all(parsed):
+        raise ValueError("sessions already exist")
+    return len(parsed)
+
+
 # Encryption utilities using session key
 
 
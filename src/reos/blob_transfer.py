@@ -0,0 +1,153 @@
+"""Out-of-band transfer of large results to the Rust shell.
+
+A result of hundreds of megabytes shouldn't travel as one JSON line through
+the pipe. When the shell offers it in ``initialize`` (``blobs``: a private
+directory and a size threshold), a response whose serialized result is
+larger than the threshold is written elsewhere and replaced by a
+reference::
+
+    {"result": {"__blob": {"kind": "memfd", "fd": 9, "size": 524288000,
+                           "key": "<hex>", "encoding": "json"}}}
+
+- ``memfd`` (Linux): an anonymous, sealed memory file the shell (our
+  parent) opens through ``/proc/<pid>/fd/<fd>``
+- ``file`` elsewhere, or if memfd is unavailable: a 0600 file in the
+  offered directory, which the shell unlinks once opened
+
+The data is sealed with ChaCha20-Poly1305 under a fresh key that only
+travels in the (encrypted) response frame, in ``SEGMENT_SIZE`` plaintext
+segments whose nonce is the segment index, so the shell can read it
+segment by segment and anything else with access to the file can neither
+read nor alter it undetected.
+
+The kernel answers one request at a time, so a blob is released (memfd
+closed, file removed if still there) when the next request is read: by then
+the shell has opened its own handle.
+"""
+
+from __future__ import annotations
+
+import json
+import os
+import secrets
+import tempfile
+from pathlib import Path
+from typing import Any
+
+from cryptography.hazmat.primitives.ciphers.aead import ChaCha20Poly1305
+
+# Plaintext bytes per sealed segment (mirrors blobs.rs)
+SEGMENT_SIZE = 1024 * 1024
+# Smallest threshold honored, whatever the shell offers
+MIN_THRESHOLD = 64 * 1024
+
+_threshold: int | None = None
+_dir: Path | None = None
+# Blob resources of the last response: memfds to close, files to remove
+_pending_fds: list[int] = []
+_pending_files: list[Path] = []
+
+
+def configure(offer: Any) -> bool:
+    """Accept the shell's ``blobs`` offer from ``initialize``; whether it was usable."""
+    global _threshold, _dir
+    _threshold, _dir = None, None
+    if not isinstance(offer, dict):
+        return False
+    threshold = offer.get("threshold")
+    directory = offer.get("dir")
+    if not isinstance(threshold, int) or isinstance(threshold, bool):
+        return False
+    if not isinstance(directory, str) or not Path(directory).is_dir():
+        return False
+    _threshold = max(threshold, MIN_THRESHOLD)
+    _dir = Path(directory)
+    return True
+
+
+def _nonce(index: int) -> bytes:
+    return b"\x00" * 4 + index.to_bytes(8, "little")
+
+
+def seal(data: bytes, key: bytes) -> bytes:
+    """``data`` sealed segment by segment (the layout blobs.rs reads)."""
+    aead = ChaCha20Poly1305(key)
+    return b"".join(
+        aead.encrypt(_nonce(index), data[offset : offset + SEGMENT_SIZE], None)
+        for index, offset in enumerate(range(0, len(data), SEGMENT_SIZE))
+    )
+
+
+def _write_memfd(sealed: bytes) -> dict[str, Any] | None:
+    create = getattr(os, "memfd_create", None)
+    if create is None:
+        return None
+    try:
+        fd = create("reos-blob", os.MFD_CLOEXEC | os.MFD_ALLOW_SEALING)
+    except OSError:
+        return None
+    try:
+        view = memoryview(sealed)
+        while view:
+            view = view[os.write(fd, view) :]
+        try:
+            import fcntl
+
+            fcntl.fcntl(
+                fd,
+                fcntl.F_ADD_SEALS,
+                fcntl.F_SEAL_SHRINK | fcntl.F_SEAL_GROW | fcntl.F_SEAL_WRITE | fcntl.F_SEAL_SEAL,
+            )
+        except (ImportError, AttributeError, OSError):
+            pass
+    except OSError:
+        os.close(fd)
+        return None
+    _pending_fds.append(fd)
+    return {"kind": "memfd", "fd": fd}
+
+
+def _write_file(sealed: bytes) -> dict[str, Any] | None:
+    if _dir is None:
+        return None
+    try:
+        fd, name = tempfile.mkstemp(prefix="blob-", suffix=".blob", dir=_dir)
+    except OSError:
+        return None
+    path = Path(name)
+    try:
+        with os.fdopen(fd, "wb") as handle:
+            handle.write(sealed)
+    except OSError:
+        path.unlink(missing_ok=True)
+        return None
+    _pending_files.append(path)
+    return {"kind": "file", "path": str(path)}
+
+
+def offload(resp: dict[str, Any]) -> dict[str, Any]:
+    """``resp`` with a large result moved out of band (unchanged otherwise)."""
+    if _threshold is None or "result" not in resp:
+        return resp
+    data = json.dumps(resp["result"], ensure_ascii=False).encode("utf-8")
+    if len(data) <= _threshold:
+        return resp
+    key = secrets.token_bytes(32)
+    sealed = seal(data, key)
+    blob = _write_memfd(sealed) or _write_file(sealed)
+    if blob is None:
+        # Nowhere to put it: the pipe still works, just slowly
+        return resp
+    blob.update({"size": len(data), "key": key.hex(), "encoding": "json"})
+    return {**resp, "result": {"__blob": blob}}
+
+
+def release_previous() -> None:
+    """Drop the last response's blobs (the shell holds its own handles now)."""
+    while _pending_fds:
+        try:
+            os.close(_pending_fds.pop())
+        except OSError:
+            pass
+    while _pending_files:
+        _pending_files.pop().unlink(missing_ok=True)
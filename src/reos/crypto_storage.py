@@ -18,6 +18,8 @@ from typing import TYPE_CHECKING
 
 from cryptography.hazmat.primitives.ciphers.aead import AESGCM
 
+from . import update_guard
+
 if TYPE_CHECKING:
     from .auth import Session
 
@@ -170,14 +172,15 @@ class CryptoStorage:
         """
         target = self._resolve_path(rel_path)
 
-        # Ensure parent directories exist
-        target.parent.mkdir(parents=True, exist_ok=True)
+        with update_guard.operation("encrypted_write"):
+            # Ensure parent directories exist
+            target.parent.mkdir(parents=True, exist_ok=True)
 
-        encrypted = self.encrypt(data)
-        target.write_bytes(encrypted)
+            encrypted = self.encrypt(data)
+            target.write_bytes(encrypted)
 
-        # Set restrictive permissions
-        target.chmod(0o600)
+            # Set restrictive permissions
+            target.chmod(0o600)
 
     def write_text(self, rel_path: str, text: str, encoding: str = "utf-8") -> None:
         """Encrypt and write text to a file.
@@ -245,6 +248,27 @@ class CryptoStorage:
 
         return sorted(files)
 
+    def rekey(self, new: CryptoStorage) -> int:
+        """Re-encrypt every file under this key with `new`'s (password change).
+
+        Files this key can't decrypt (left by an earlier key) are left alone.
+
+        Args:
+            new: Storage of the same user under the new key
+
+        Returns:
+            Number of files re-encrypted
+        """
+        rekeyed = 0
+        for rel_path in self.list_files():
+            try:
+                data = self.read(rel_path)
+            except CryptoStorageError:
+                continue
+            new.write(rel_path, data)
+            rekeyed += 1
+        return rekeyed
+
     def ensure_dir(self, rel_dir: str) -> Path:
         """Ensure a directory exists within user data.
 
@@ -466,9 +466,11 @@ class Database:
     def _run_migrations(self) -> None:
         """Run pending schema migrations."""
         from .migrations import run_migrations
+        from .update_guard import operation
 
         try:
-            count = run_migrations(self)
+            with operation("migration"):
+                count = run_migrations(self)
             if count > 0:
                 import logging
 
@@ -0,0 +1,69 @@
+"""Chunked results for the Rust shell's streaming file export.
+
+``export/begin`` runs an ordinary kernel method (the shell has already
+applied its allowlist, role and consent checks) and keeps the serialized
+result here; ``export/chunk`` hands it out piece by piece and
+``export/end`` drops it. Text results are written as-is, anything else as
+pretty-printed JSON.
+"""
+
+from __future__ import annotations
+
+import json
+import secrets
+import threading
+import time
+from typing import Any
+
+# Largest chunk handed out per export/chunk call
+MAX_CHUNK_BYTES = 1024 * 1024
+MAX_OPEN_EXPORTS = 4
+# Exports the shell never ended are dropped after this long (seconds)
+EXPORT_TTL = 600.0
+
+
+class ExportError(Exception):
+    """Unknown export or invalid range."""
+
+
+def serialize(result: Any) -> bytes:
+    """File contents for a method result."""
+    if isinstance(result, str):
+        return result.encode("utf-8")
+    return json.dumps(result, indent=2, ensure_ascii=False).encode("utf-8")
+
+
+class ExportStore:
+    """Serialized results waiting to be fetched, by export id."""
+
+    def __init__(self) -> None:
+        self._lock = threading.Lock()
+        self._exports: dict[str, tuple[bytes, float]] = {}
+
+    def begin(self, data: bytes) -> dict[str, object]:
+        with self._lock:
+            now = time.monotonic()
+            self._exports = {
+                export_id: entry
+                for export_id, entry in self._exports.items()
+                if now - entry[1] <= EXPORT_TTL
+            }
+            if len(self._exports) >= MAX_OPEN_EXPORTS:
+                raise ExportError("too many exports in progress")
+            export_id = secrets.token_hex(16)
+            self._exports[export_id] = (data, now)
+        return {"export_id": export_id, "size": len(data)}
+
+    def chunk(self, export_id: str, offset: int, length: int) -> bytes:
+        with self._lock:
+            entry = self._exports.get(export_id)
+        if entry is None:
+            raise ExportError("unknown export")
+        data = entry[0]
+        if offset < 0 or offset > len(data) or length <= 0:
+            raise ExportError("invalid range")
+        return data[offset : offset + min(length, MAX_CHUNK_BYTES)]
+
+    def end(self, export_id: str) -> None:
+        with self._lock:
+            self._exports.pop(export_id, None)
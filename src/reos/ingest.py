@@ -0,0 +1,138 @@
+"""Streamed file ingestion behind the Rust shell's drag-and-drop pipeline.
+
+The shell validates dropped files against its allowlist and sends them
+in chunks (``ingest/begin``, ``ingest/chunk``..., ``ingest/finish``), so
+file contents never pass through the webview. Each chunk is stored as its
+own encrypted part under ``ingest/<handle>/`` in the user's data root and
+``finish`` checks the total size and SHA-256 before writing the manifest.
+The handle it returns is how the frontend refers to the file afterwards.
+"""
+
+from __future__ import annotations
+
+import hashlib
+import json
+import secrets
+import threading
+import time
+from dataclasses import dataclass, field
+from typing import Any
+
+from .crypto_storage import CryptoStorage
+
+INGEST_DIR = "ingest"
+MANIFEST = "manifest.json"
+# Mirrors file_drop::CHUNK_SIZE in the Tauri shell, with headroom
+MAX_CHUNK_BYTES = 1024 * 1024
+# Per user
+MAX_OPEN_UPLOADS = 8
+# Unfinished uploads are dropped after this long (seconds)
+UPLOAD_TTL = 600.0
+MAX_NAME_CHARS = 255
+
+
+class IngestError(Exception):
+    """Invalid, unknown or corrupted upload."""
+
+
+@dataclass
+class _Upload:
+    username: str
+    name: str
+    size: int
+    started: float = field(default_factory=time.monotonic)
+    received: int = 0
+    parts: int = 0
+    digest: Any = field(default_factory=hashlib.sha256)
+
+
+def _part_path(handle: str, index: int) -> str:
+    return f"{INGEST_DIR}/{handle}/part-{index:06d}"
+
+
+class IngestManager:
+    """Uploads in progress, keyed by upload id (which becomes the handle)."""
+
+    def __init__(self) -> None:
+        self._lock = threading.Lock()
+        self._uploads: dict[str, _Upload] = {}
+
+    def _get(self, storage: CryptoStorage, upload_id: str) -> _Upload:
+        upload = self._uploads.get(upload_id)
+        if upload is None or upload.username != storage.username:
+            raise IngestError("unknown upload")
+        return upload
+
+    def _discard(self, storage: CryptoStorage, upload_id: str, upload: _Upload) -> None:
+        self._uploads.pop(upload_id, None)
+        for index in range(upload.parts):
+            storage.delete(_part_path(upload_id, index))
+
+    def begin(self, storage: CryptoStorage, *, name: str, size: int) -> str:
+        """Start an upload of ``size`` bytes; returns its id."""
+        name = name.replace("\\", "/").rsplit("/", 1)[-1]
+        if not name or len(name) > MAX_NAME_CHARS or size < 0:
+            raise IngestError("invalid file name or size")
+        with self._lock:
+            now = time.monotonic()
+            for upload_id, upload in list(self._uploads.items()):
+                if now - upload.started > UPLOAD_TTL and upload.username == storage.username:
+                    self._discard(storage, upload_id, upload)
+            open_uploads = sum(u.username == storage.username for u in self._uploads.values())
+            if open_uploads >= MAX_OPEN_UPLOADS:
+                raise IngestError("too many uploads in progress")
+            upload_id = secrets.token_hex(16)
+            self._uploads[upload_id] = _Upload(username=storage.username, name=name, size=size)
+            return upload_id
+
+    def chunk(self, storage: CryptoStorage, upload_id: str, data: bytes) -> int:
+        """Store the next chunk; returns the bytes received so far."""
+        if len(data) > MAX_CHUNK_BYTES:
+            raise IngestError("chunk too large")
+        with self._lock:
+            upload = self._get(storage, upload_id)
+            if upload.received + len(data) > upload.size:
+                self._discard(storage, upload_id, upload)
+                raise IngestError("more data than announced")
+            storage.write(_part_path(upload_id, upload.parts), data)
+            upload.parts += 1
+            upload.received += len(data)
+            upload.digest.update(data)
+            return upload.received
+
+    def finish(self, storage: CryptoStorage, upload_id: str, sha256: str) -> dict[str, object]:
+        """Check size and hash and record the file; returns its handle."""
+        with self._lock:
+            upload = self._get(storage, upload_id)
+            if upload.received != upload.size or not secrets.compare_digest(
+                upload.digest.hexdigest(), sha256.lower()
+            ):
+                self._discard(storage, upload_id, upload)
+                raise IngestError("upload incomplete or corrupted")
+            del self._uploads[upload_id]
+        manifest = {
+            "name": upload.name,
+            "size": upload.size,
+            "sha256": sha256.lower(),
+            "parts": upload.parts,
+        }
+        storage.write_text(f"{INGEST_DIR}/{upload_id}/{MANIFEST}", json.dumps(manifest))
+        return {"handle": upload_id, "name": upload.name, "size": upload.size}
+
+    def abort(self, storage: CryptoStorage, upload_id: str) -> None:
+        """Drop an unfinished upload and its stored parts."""
+        with self._lock:
+            upload = self._uploads.get(upload_id)
+            if upload is not None and upload.username == storage.username:
+                self._discard(storage, upload_id, upload)
+
+
+def read(storage: CryptoStorage, handle: str) -> bytes:
+    """Contents of an ingested file."""
+    if len(handle) != 32 or not all(c in "0123456789abcdef" for c in handle):
+        raise IngestError("invalid handle")
+    manifest_path = f"{INGEST_DIR}/{handle}/{MANIFEST}"
+    if not storage.exists(manifest_path):
+        raise IngestError("unknown handle")
+    manifest = json.loads(storage.read_text(manifest_path))
+    return b"".join(storage.read(_part_path(handle, i)) for i in range(manifest["parts"]))
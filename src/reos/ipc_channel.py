@@ -0,0 +1,99 @@
+"""Encrypted stdio channel between the Rust shell and the UI kernel.
+
+The first line the Rust shell writes is a handshake hello carrying an
+ephemeral X25519 public key; the kernel answers with its own. Both sides
+derive per-direction ChaCha20-Poly1305 keys (HKDF-SHA256 over the shared
+secret, salted with the transcript hash), and every later line is one
+base64 frame whose nonce is an implicit per-direction counter.
+
+This keeps credentials and user data in the pipe confidential and
+tamper-evident against same-user processes that can read or inject into
+it after startup. Like Noise NN it is unauthenticated: a process already
+sitting between the two ends at spawn time is out of scope.
+
+Mirrors apps/reos-tauri/src-tauri/src/ipc.rs.
+"""
+
+from __future__ import annotations
+
+import base64
+import json
+from hashlib import sha256
+from typing import Any
+
+from cryptography.hazmat.primitives.asymmetric.x25519 import (
+    X25519PrivateKey,
+    X25519PublicKey,
+)
+from cryptography.hazmat.primitives.ciphers.aead import ChaCha20Poly1305
+from cryptography.hazmat.primitives.hashes import SHA256
+from cryptography.hazmat.primitives.kdf.hkdf import HKDF
+from cryptography.hazmat.primitives.serialization import Encoding, PublicFormat
+
+PROTOCOL = "reos-ipc-v1"
+
+
+class ChannelError(Exception):
+    """Handshake or frame could not be processed."""
+
+
+def _nonce(counter: int) -> bytes:
+    return b"\x00" * 4 + counter.to_bytes(8, "little")
+
+
+def derive_keys(shared: bytes, rust_public: bytes, kernel_public: bytes) -> tuple[bytes, bytes]:
+    """Return (rust->kernel key, kernel->rust key)."""
+    transcript = sha256(PROTOCOL.encode() + rust_public + kernel_public).digest()
+    okm = HKDF(
+        algorithm=SHA256(), length=64, salt=transcript, info=f"{PROTOCOL} keys".encode()
+    ).derive(shared)
+    return okm[:32], okm[32:]
+
+
+class SecureChannel:
+    """Per-direction AEAD state after a completed handshake."""
+
+    def __init__(self, recv_key: bytes, send_key: bytes) -> None:
+        self._recv = ChaCha20Poly1305(recv_key)
+        self._send = ChaCha20Poly1305(send_key)
+        self._recv_counter = 0
+        self._send_counter = 0
+
+    def seal(self, plaintext: str) -> str:
+        frame = self._send.encrypt(_nonce(self._send_counter), plaintext.encode(), None)
+        self._send_counter += 1
+        return base64.b64encode(frame).decode()
+
+    def open(self, line: str) -> str:
+        try:
+            frame = base64.b64decode(line, validate=True)
+            plaintext = self._recv.decrypt(_nonce(self._recv_counter), frame, None)
+        except Exception as exc:  # InvalidTag, binascii.Error
+            raise ChannelError("invalid frame") from exc
+        self._recv_counter += 1
+        return plaintext.decode()
+
+
+def parse_hello(line: str) -> bytes | None:
+    """Return the peer's public key if `line` is a handshake hello."""
+    try:
+        msg: Any = json.loads(line)
+    except json.JSONDecodeError:
+        return None
+    if not isinstance(msg, dict) or msg.get("channel") != PROTOCOL:
+        return None
+    try:
+        public = bytes.fromhex(str(msg.get("e", "")))
+    except ValueError:
+        return None
+    return public if len(public) == 32 else None
+
+
+def accept(rust_public: bytes) -> tuple[SecureChannel, str]:
+    """Kernel side of the handshake: returns the channel and the reply line."""
+    private = X25519PrivateKey.generate()
+    kernel_public = private.public_key().public_bytes(Encoding.Raw, PublicFormat.Raw)
+    shared = private.exchange(X25519PublicKey.from_public_bytes(rust_public))
+    to_kernel, to_rust = derive_keys(shared, rust_public, kernel_public)
+    reply = json.dumps({"channel": PROTOCOL, "e": kernel_public.hex()})
+    return SecureChannel(recv_key=to_kernel, send_key=to_rust), reply
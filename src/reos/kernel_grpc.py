@@ -0,0 +1,372 @@
+"""gRPC listener for a remote kernel (REOS_KERNEL_GRPC_LISTEN).
+
+Serves ``reos.kernel.v1.Kernel`` from ``proto/kernel.proto`` for sites that
+standardize on gRPC; the shell side is ``remote_grpc.rs`` (feature
+``grpc``). Callers present the shared token as ``authorization: Bearer
+<token>`` metadata; anything else ends with UNAUTHENTICATED.
+
+One ``Connect`` call is one shell connection, and a second one while the
+first is open gets UNAVAILABLE. Each frame carries one line: the channel
+hello, an encrypted frame (raw bytes rather than base64), or, for clients
+without the channel, a typed plain JSON-RPC message.
+
+The schema is small enough that frames are encoded here by hand instead
+of through generated code, which leaves ``grpcio`` the only dependency.
+"""
+
+from __future__ import annotations
+
+import base64
+import binascii
+import hmac
+import json
+import logging
+import queue
+import threading
+from concurrent.futures import ThreadPoolExecutor
+from pathlib import Path
+from typing import Any
+
+import grpc
+
+logger = logging.getLogger(__name__)
+
+SERVICE = "reos.kernel.v1.Kernel"
+
+# Largest message accepted, mirroring MAX_FRAME_LEN in kernel.rs
+MAX_MESSAGE_SIZE = 256 * 1024 * 1024
+
+# Frame body field numbers (proto/kernel.proto)
+_HELLO, _SEALED, _REQUEST, _RESPONSE, _NOTIFICATION = 1, 2, 3, 4, 5
+
+_VARINT, _LENGTH_DELIMITED = 0, 2
+
+
+class FrameError(ValueError):
+    """Bytes that aren't a kernel frame."""
+
+
+def _varint(value: int) -> bytes:
+    value &= (1 << 64) - 1
+    out = bytearray()
+    while True:
+        byte = value & 0x7F
+        value >>= 7
+        if value:
+            out.append(byte | 0x80)
+        else:
+            out.append(byte)
+            return bytes(out)
+
+
+def _field(number: int, payload: bytes | str | int) -> bytes:
+    if isinstance(payload, int):
+        return _varint(number << 3 | _VARINT) + _varint(payload)
+    if isinstance(payload, str):
+        payload = payload.encode()
+    return _varint(number << 3 | _LENGTH_DELIMITED) + _varint(len(payload)) + payload
+
+
+def _message(**fields: tuple[int, Any]) -> bytes:
+    """Encode the non-default fields (proto3 leaves defaults out)."""
+    return b"".join(
+        _field(number, value) for number, value in fields.values() if value not in ("", 0, None)
+    )
+
+
+def _read_varint(data: bytes, pos: int) -> tuple[int, int]:
+    value = shift = 0
+    while True:
+        if pos >= len(data) or shift > 63:
+            raise FrameError("truncated varint")
+        byte = data[pos]
+        pos += 1
+        value |= (byte & 0x7F) << shift
+        shift += 7
+        if not byte & 0x80:
+            return value, pos
+
+
+def _fields(data: bytes) -> dict[int, bytes | int]:
+    """Field number to (last) value; unknown wire types are rejected."""
+    out: dict[int, bytes | int] = {}
+    pos = 0
+    while pos < len(data):
+        key, pos = _read_varint(data, pos)
+        number, wire = key >> 3, key & 7
+        if wire == _VARINT:
+            out[number], pos = _read_varint(data, pos)
+        elif wire == _LENGTH_DELIMITED:
+            length, pos = _read_varint(data, pos)
+            if pos + length > len(data):
+                raise FrameError("truncated field")
+            out[number] = data[pos : pos + length]
+            pos += length
+        else:
+            raise FrameError(f"unexpected wire type {wire}")
+    return out
+
+
+def _text(fields: dict[int, bytes | int], number: int) -> str:
+    value = fields.get(number, b"")
+    if not isinstance(value, bytes):
+        raise FrameError(f"field {number} is not text")
+    return value.decode("utf-8", errors="replace")
+
+
+def _json(text: str) -> Any:
+    try:
+        return json.loads(text) if text else None
+    except json.JSONDecodeError as exc:
+        raise FrameError("invalid JSON value") from exc
+
+
+def decode_frame(data: bytes) -> str:
+    """The line (without newline) carried by a serialized ``Frame``."""
+    fields = _fields(data)
+    if _HELLO in fields:
+        return _text(fields, _HELLO)
+    if _SEALED in fields:
+        sealed = fields[_SEALED]
+        if not isinstance(sealed, bytes):
+            raise FrameError("sealed frame is not bytes")
+        return base64.b64encode(sealed).decode()
+    message: dict[str, Any] = {"jsonrpc": "2.0"}
+    if _REQUEST in fields or _NOTIFICATION in fields:
+        request = _REQUEST in fields
+        body = fields[_REQUEST if request else _NOTIFICATION]
+        if not isinstance(body, bytes):
+            raise FrameError("message is not embedded")
+        inner = _fields(body)
+        method_field, params_field = (2, 3) if request else (1, 2)
+        message["method"] = _text(inner, method_field)
+        if request and _text(inner, 1):
+            message["id"] = _json(_text(inner, 1))
+        params = _text(inner, params_field)
+        if params:
+            message["params"] = _json(params)
+        return json.dumps(message)
+    if _RESPONSE in fields:
+        body = fields[_RESPONSE]
+        if not isinstance(body, bytes):
+            raise FrameError("message is not embedded")
+        inner = _fields(body)
+        message["id"] = _json(_text(inner, 1))
+        error = inner.get(3)
+        if isinstance(error, bytes):
+            err = _fields(error)
+            code = err.get(1, 0)
+            if not isinstance(code, int):
+                raise FrameError("error code is not a number")
+            # int32 travels as a 64-bit two's complement varint
+            code = code - (1 << 64) if code >= 1 << 63 else code
+            message["error"] = {"code": code, "message": _text(err, 2)}
+            if _text(err, 3):
+                message["error"]["data"] = _json(_text(err, 3))
+        else:
+            message["result"] = _json(_text(inner, 2))
+        return json.dumps(message)
+    raise FrameError("empty frame")
+
+
+def encode_frame(line: str) -> bytes:
+    """The serialized ``Frame`` for a line: a hello or plain JSON-RPC
+    message if it is a JSON object, an encrypted frame otherwise."""
+    if not line.startswith("{"):
+        try:
+            return _field(_SEALED, base64.b64decode(line, validate=True))
+        except binascii.Error as exc:
+            raise FrameError("neither JSON nor an encrypted frame") from exc
+    message = json.loads(line)
+    if "channel" in message:
+        return _field(_HELLO, line)
+
+    def dump(value: Any) -> str:
+        return "" if value is None else json.dumps(value)
+
+    if "method" in message and "id" in message:
+        return _field(
+            _REQUEST,
+            _message(
+                id=(1, dump(message["id"])),
+                method=(2, message["method"]),
+                params=(3, dump(message.get("params"))),
+            ),
+        )
+    if "method" in message:
+        return _field(
+            _NOTIFICATION,
+            _message(method=(1, message["method"]), params=(2, dump(message.get("params")))),
+        )
+    error = message.get("error")
+    error_bytes = None
+    if isinstance(error, dict):
+        error_bytes = _field(
+            3,
+            _message(
+                code=(1, int(error.get("code", 0))),
+                message=(2, str(error.get("message", ""))),
+                data=(3, dump(error.get("data"))),
+            ),
+        )
+    response = _message(id=(1, dump(message.get("id"))), result=(2, dump(message.get("result"))))
+    return _field(_RESPONSE, response + (error_bytes or b""))
+
+
+class _LineReader:
+    """Received frames as lines; "" once the call has ended."""
+
+    def __init__(self, connection: GrpcConnection) -> None:
+        self._connection = connection
+
+    def readline(self) -> str:
+        line = self._connection.inbound.get()
+        if line is None:
+            # Keep the end in place for any later read
+            self._connection.inbound.put(None)
+            return ""
+        return line + "\n"
+
+    def close(self) -> None:
+        pass
+
+
+class _LineWriter:
+    """Each written line goes out as one frame."""
+
+    def __init__(self, connection: GrpcConnection) -> None:
+        self._connection = connection
+        self._pending = ""
+
+    def write(self, text: str) -> int:
+        self._pending += text
+        while "\n" in self._pending:
+            line, _, self._pending = self._pending.partition("\n")
+            if self._connection.closed.is_set():
+                raise BrokenPipeError("shell connection closed")
+            self._connection.outbound.put(line)
+        return len(text)
+
+    def flush(self) -> None:
+        pass
+
+    def close(self) -> None:
+        pass
+
+
+class GrpcConnection:
+    """One ``Connect`` call, with the socket-like ``makefile`` and ``close``."""
+
+    def __init__(self) -> None:
+        self.inbound: queue.Queue[str | None] = queue.Queue()
+        self.outbound: queue.Queue[str | None] = queue.Queue()
+        self.closed = threading.Event()
+
+    def makefile(self, mode: str, encoding: str = "utf-8", newline: str | None = None) -> Any:
+        if mode == "r":
+            return _LineReader(self)
+        return _LineWriter(self)
+
+    def close(self) -> None:
+        if self.closed.is_set():
+            return
+        self.closed.set()
+        self.inbound.put(None)
+        self.outbound.put(None)
+
+
+class GrpcListener:
+    """gRPC server on ``host``:``port`` for token holders; TLS when
+    ``certfile`` and ``keyfile`` are given (and then, with ``clients``,
+    only for the paired shells)."""
+
+    def __init__(
+        self,
+        host: str,
+        port: int,
+        certfile: str | None,
+        keyfile: str | None,
+        token: str,
+        *,
+        clients: str | None = None,
+    ) -> None:
+        self._token = token.encode()
+        self._busy = threading.Lock()
+        self._pending: queue.Queue[GrpcConnection] = queue.Queue()
+        self._server = grpc.server(
+            ThreadPoolExecutor(max_workers=4, thread_name_prefix="kernel-grpc"),
+            options=[
+                ("grpc.max_receive_message_length", MAX_MESSAGE_SIZE),
+                ("grpc.max_send_message_length", MAX_MESSAGE_SIZE),
+            ],
+        )
+        connect = grpc.stream_stream_rpc_method_handler(
+            self._connect, request_deserializer=decode_frame, response_serializer=encode_frame
+        )
+        self._server.add_generic_rpc_handlers(
+            (grpc.method_handlers_generic_handler(SERVICE, {"Connect": connect}),)
+        )
+        address = f"[{host}]:{port}" if ":" in host else f"{host}:{port}"
+        if certfile and keyfile:
+            with open(keyfile, "rb") as key, open(certfile, "rb") as cert:
+                pair = (key.read(), cert.read())
+            if clients:
+                credentials = grpc.ssl_server_credentials(
+                    [pair],
+                    root_certificates=Path(clients).read_bytes(),
+                    require_client_auth=True,
+                )
+            else:
+                credentials = grpc.ssl_server_credentials([pair])
+            self._port = self._server.add_secure_port(address, credentials)
+        else:
+            self._port = self._server.add_insecure_port(address)
+        self._server.start()
+
+    def _authorized(self, context: grpc.ServicerContext) -> bool:
+        metadata = dict(context.invocation_metadata())
+        scheme, _, presented = str(metadata.get("authorization", "")).partition(" ")
+        return scheme == "Bearer" and hmac.compare_digest(presented.encode(), self._token)
+
+    def _connect(self, frames: Any, context: grpc.ServicerContext) -> Any:
+        if not self._authorized(context):
+            logger.warning("Rejected remote kernel call from %s", context.peer())
+            context.abort(grpc.StatusCode.UNAUTHENTICATED, "token rejected")
+        if not self._busy.acquire(blocking=False):
+            context.abort(grpc.StatusCode.UNAVAILABLE, "kernel busy with another shell")
+        connection = GrpcConnection()
+        context.add_callback(connection.close)
+
+        def receive() -> None:
+            try:
+                for line in frames:
+                    if line.strip():
+                        connection.inbound.put(line)
+            except grpc.RpcError:
+                pass
+            finally:
+                connection.inbound.put(None)
+
+        threading.Thread(target=receive, name="kernel-grpc-receive", daemon=True).start()
+        self._pending.put(connection)
+        try:
+            # The connection is served by the kernel's main loop
+            while (line := connection.outbound.get()) is not None:
+                yield line
+        finally:
+            connection.close()
+            self._busy.release()
+
+    @property
+    def port(self) -> int:
+        return self._port
+
+    def accept(self, timeout: float | None = None) -> GrpcConnection:
+        """Wait for an authorized shell; TimeoutError after ``timeout`` seconds."""
+        try:
+            return self._pending.get(timeout=timeout)
+        except queue.Empty:
+            raise TimeoutError("no shell connected") from None
+
+    def close(self) -> None:
+        self._server.stop(grace=1).wait(5)
@@ -0,0 +1,357 @@
+"""HTTP(S) listener for a remote kernel (REOS_KERNEL_HTTP_LISTEN).
+
+The fallback to ``kernel_websocket`` for networks whose proxies only pass
+plain requests, and handy for poking a kernel with curl. The shell side is
+``remote_http.rs``. Every request must carry the shared token as
+``Authorization: Bearer <token>``; anything else gets a 401.
+
+- ``POST connect``: ``{"connection": "<id>"}``, or 503 while another shell
+  is connected
+- ``POST frames?connection=<id>``: frame lines for the kernel
+- ``GET events?connection=<id>``: the kernel's lines as server-sent events
+- ``GET poll?connection=<id>``: the same by long polling (empty after
+  ``POLL_WAIT`` seconds without any)
+- ``POST close?connection=<id>``
+
+An unknown or closed connection gets a 410. A connection that is neither
+streamed nor polled for ``IDLE_TIMEOUT`` seconds is closed, so a shell
+that vanished doesn't keep the kernel busy. TLS is up to the operator
+(``run_http_server`` only allows plain HTTP on a loopback address).
+"""
+
+from __future__ import annotations
+
+import hmac
+import json
+import logging
+import queue
+import secrets
+import ssl
+import threading
+import time
+from http import HTTPStatus
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from typing import Any
+from urllib.parse import parse_qs, urlsplit
+
+from . import kernel_pairing
+
+logger = logging.getLogger(__name__)
+
+# Largest request body accepted, mirroring MAX_FRAME_LEN in kernel.rs
+MAX_BODY_SIZE = 256 * 1024 * 1024
+
+# How long a poll waits for lines (the shell gives up after 60 seconds)
+POLL_WAIT = 25.0
+
+# Most bytes returned by one poll, once at least one line is in
+POLL_BATCH = 1024 * 1024
+
+# Comment sent on an idle event stream, so proxies don't time it out
+KEEP_ALIVE_INTERVAL = 15.0
+
+# Seconds a connection may go without being streamed or polled
+IDLE_TIMEOUT = 60.0
+
+
+class _LineReader:
+    """Posted lines; "" once the connection is closed."""
+
+    def __init__(self, connection: HttpConnection) -> None:
+        self._connection = connection
+
+    def readline(self) -> str:
+        line = self._connection.inbound.get()
+        if line is None:
+            # Keep the end in place for any later read
+            self._connection.inbound.put(None)
+            return ""
+        return line + "\n"
+
+    def close(self) -> None:
+        pass
+
+
+class _LineWriter:
+    """Each written line is queued for the shell's stream or next poll."""
+
+    def __init__(self, connection: HttpConnection) -> None:
+        self._connection = connection
+        self._pending = ""
+
+    def write(self, text: str) -> int:
+        self._pending += text
+        while "\n" in self._pending:
+            line, _, self._pending = self._pending.partition("\n")
+            if self._connection.closed.is_set():
+                raise BrokenPipeError("shell connection closed")
+            self._connection.outbound.put(line)
+        return len(text)
+
+    def flush(self) -> None:
+        pass
+
+    def close(self) -> None:
+        pass
+
+
+class HttpConnection:
+    """One shell connection, with the socket-like ``makefile`` and ``close``."""
+
+    def __init__(self, connection_id: str) -> None:
+        self.id = connection_id
+        self.inbound: queue.Queue[str | None] = queue.Queue()
+        self.outbound: queue.Queue[str | None] = queue.Queue()
+        self.closed = threading.Event()
+        self.last_seen = time.monotonic()
+        # Open event streams; the connection isn't idle while one is
+        self.streaming = 0
+
+    def touch(self) -> None:
+        self.last_seen = time.monotonic()
+
+    def makefile(self, mode: str, encoding: str = "utf-8", newline: str | None = None) -> Any:
+        if mode == "r":
+            return _LineReader(self)
+        return _LineWriter(self)
+
+    def close(self) -> None:
+        if self.closed.is_set():
+            return
+        self.closed.set()
+        self.inbound.put(None)
+        self.outbound.put(None)
+
+
+class _Handler(BaseHTTPRequestHandler):
+    protocol_version = "HTTP/1.1"
+    server: _Server
+
+    def log_message(self, format: str, *args: Any) -> None:  # noqa: A002
+        logger.debug("%s %s", self.address_string(), format % args)
+
+    def do_GET(self) -> None:  # noqa: N802
+        self._dispatch({"events": self._events, "poll": self._poll})
+
+    def do_POST(self) -> None:  # noqa: N802
+        self._dispatch({"connect": self._connect, "frames": self._frames, "close": self._close})
+
+    def _dispatch(self, routes: dict[str, Any]) -> None:
+        if not self._authorized():
+            logger.warning("Rejected remote kernel request from %s", self.client_address[0])
+            self._respond(HTTPStatus.UNAUTHORIZED, headers={"WWW-Authenticate": "Bearer"})
+            return
+        url = urlsplit(self.path)
+        route = routes.get(url.path.rstrip("/").rpartition("/")[2])
+        if route is None:
+            self._respond(HTTPStatus.NOT_FOUND)
+            return
+        if route == self._connect:
+            route()
+            return
+        connection_id = parse_qs(url.query).get("connection", [""])[0]
+        connection = self.server.listener._find(connection_id)
+        if connection is None:
+            self._respond(HTTPStatus.GONE)
+            return
+        connection.touch()
+        route(connection)
+
+    def _authorized(self) -> bool:
+        scheme, _, presented = self.headers.get("Authorization", "").partition(" ")
+        token = self.server.listener._token
+        return scheme == "Bearer" and hmac.compare_digest(presented.encode(), token)
+
+    def _respond(
+        self,
+        status: HTTPStatus,
+        body: bytes = b"",
+        content_type: str = "text/plain; charset=utf-8",
+        headers: dict[str, str] | None = None,
+    ) -> None:
+        self.send_response(status)
+        self.send_header("Content-Type", content_type)
+        self.send_header("Content-Length", str(len(body)))
+        self.send_header("Cache-Control", "no-store")
+        for name, value in (headers or {}).items():
+            self.send_header(name, value)
+        self.end_headers()
+        if body:
+            self.wfile.write(body)
+
+    def _connect(self) -> None:
+        self._discard_body()
+        connection = self.server.listener._open()
+        if connection is None:
+            self._respond(HTTPStatus.SERVICE_UNAVAILABLE, headers={"Retry-After": "5"})
+            return
+        body = json.dumps({"connection": connection.id}).encode()
+        self._respond(HTTPStatus.OK, body, "application/json")
+
+    def _frames(self, connection: HttpConnection) -> None:
+        length = int(self.headers.get("Content-Length") or 0)
+        if length > MAX_BODY_SIZE:
+            self.close_connection = True
+            self._respond(HTTPStatus.REQUEST_ENTITY_TOO_LARGE)
+            return
+        body = self.rfile.read(length).decode("utf-8", errors="replace")
+        if connection.closed.is_set():
+            self._respond(HTTPStatus.GONE)
+            return
+        for line in body.split("\n"):
+            if line.strip():
+                connection.inbound.put(line)
+        self._respond(HTTPStatus.NO_CONTENT)
+
+    def _close(self, connection: HttpConnection) -> None:
+        self._discard_body()
+        connection.close()
+        self._respond(HTTPStatus.NO_CONTENT)
+
+    def _events(self, connection: HttpConnection) -> None:
+        self.close_connection = True
+        self.send_response(HTTPStatus.OK)
+        self.send_header("Content-Type", "text/event-stream")
+        self.send_header("Cache-Control", "no-store")
+        self.send_header("Connection", "close")
+        # Tell buffering proxies (nginx) to pass events on as they come
+        self.send_header("X-Accel-Buffering", "no")
+        self.end_headers()
+        connection.streaming += 1
+        try:
+            while True:
+                try:
+                    line = connection.outbound.get(timeout=KEEP_ALIVE_INTERVAL)
+                except queue.Empty:
+                    self.wfile.write(b": keep-alive\n\n")
+                    self.wfile.flush()
+                    continue
+                if line is None:
+                    connection.outbound.put(None)
+                    return
+                self.wfile.write(b"data: " + line.encode() + b"\n\n")
+                self.wfile.flush()
+        except OSError:
+            # The shell dropped the stream; it reconnects or the connection idles out
+            return
+        finally:
+            connection.streaming -= 1
+            connection.touch()
+
+    def _poll(self, connection: HttpConnection) -> None:
+        lines: list[str] = []
+        size = 0
+        try:
+            line = connection.outbound.get(timeout=POLL_WAIT)
+        except queue.Empty:
+            line = ""
+        while line is not None and size < POLL_BATCH:
+            if line:
+                lines.append(line)
+                size += len(line) + 1
+            try:
+                line = connection.outbound.get_nowait()
+            except queue.Empty:
+                break
+        if line is None:
+            connection.outbound.put(None)
+            if not lines:
+                self._respond(HTTPStatus.GONE)
+                return
+        connection.touch()
+        body = "".join(f"{line}\n" for line in lines).encode()
+        self._respond(HTTPStatus.OK, body, "text/plain; charset=utf-8")
+
+    def _discard_body(self) -> None:
+        length = int(self.headers.get("Content-Length") or 0)
+        if 0 < length <= MAX_BODY_SIZE:
+            self.rfile.read(length)
+
+
+class _Server(ThreadingHTTPServer):
+    daemon_threads = True
+    listener: HttpListener
+
+
+class HttpListener:
+    """HTTP(S) server on ``host``:``port`` for token holders; TLS when
+    ``certfile`` and ``keyfile`` are given (and then, with ``clients``,
+    only for the paired shells)."""
+
+    def __init__(
+        self,
+        host: str,
+        port: int,
+        certfile: str | None,
+        keyfile: str | None,
+        token: str,
+        *,
+        clients: str | None = None,
+    ) -> None:
+        self._token = token.encode()
+        self._lock = threading.Lock()
+        self._current: HttpConnection | None = None
+        self._pending: queue.Queue[HttpConnection] = queue.Queue()
+        self._stopped = threading.Event()
+        self._server = _Server((host, port), _Handler)
+        self._server.listener = self
+        if certfile and keyfile:
+            context = ssl.SSLContext(ssl.PROTOCOL_TLS_SERVER)
+            context.minimum_version = ssl.TLSVersion.TLSv1_2
+            context.load_cert_chain(certfile, keyfile)
+            kernel_pairing.require_client_certificates(context, clients)
+            self._server.socket = context.wrap_socket(self._server.socket, server_side=True)
+        self._thread = threading.Thread(
+            target=self._server.serve_forever, name="kernel-http", daemon=True
+        )
+        self._thread.start()
+        self._reaper = threading.Thread(target=self._reap, name="kernel-http-reaper", daemon=True)
+        self._reaper.start()
+
+    def _open(self) -> HttpConnection | None:
+        """A new connection, or None while the current one is still open."""
+        with self._lock:
+            if self._current is not None and not self._current.closed.is_set():
+                return None
+            self._current = HttpConnection(secrets.token_urlsafe(24))
+            self._pending.put(self._current)
+            return self._current
+
+    def _find(self, connection_id: str) -> HttpConnection | None:
+        with self._lock:
+            current = self._current
+        if current is None or not hmac.compare_digest(current.id, connection_id):
+            return None
+        if current.closed.is_set():
+            return None
+        return current
+
+    def _reap(self) -> None:
+        while not self._stopped.wait(IDLE_TIMEOUT / 4):
+            with self._lock:
+                current = self._current
+            if current is None or current.closed.is_set() or current.streaming:
+                continue
+            if time.monotonic() - current.last_seen > IDLE_TIMEOUT:
+                logger.info("Remote shell went quiet; closing its connection")
+                current.close()
+
+    @property
+    def port(self) -> int:
+        return int(self._server.server_address[1])
+
+    def accept(self, timeout: float | None = None) -> HttpConnection:
+        """Wait for a connected shell; TimeoutError after ``timeout`` seconds."""
+        try:
+            return self._pending.get(timeout=timeout)
+        except queue.Empty:
+            raise TimeoutError("no shell connected") from None
+
+    def close(self) -> None:
+        self._stopped.set()
+        with self._lock:
+            if self._current is not None:
+                self._current.close()
+        self._server.shutdown()
+        self._server.server_close()
+        self._thread.join(5)
@@ -0,0 +1,138 @@
+"""Paired shells for a remote kernel (mutual TLS).
+
+A shell pairs by handing over its client certificate (``remote_kernel_pair``
+in the desktop app creates one and shows it with its fingerprint). The
+operator adds it here; from then on the kernel's TLS listeners
+(``kernel_websocket``, ``kernel_http``, ``kernel_grpc``) require a client
+certificate and accept only the paired ones, before any token is looked at.
+
+    python -m reos.kernel_pairing add shell.pem
+    python -m reos.kernel_pairing list
+    python -m reos.kernel_pairing remove <fingerprint>
+    python -m reos.kernel_pairing fingerprint server-cert.pem
+
+``fingerprint`` prints what goes in a shell's ``server_cert_sha256`` to pin
+this kernel's certificate. The paired certificates live in one PEM file
+(``REOS_KERNEL_PAIRED_CLIENTS``, by default ``paired-clients.pem`` in the
+data dir); with none paired, the token alone gets a shell in.
+"""
+
+from __future__ import annotations
+
+import argparse
+import contextlib
+import os
+import ssl
+import sys
+from hashlib import sha256
+from pathlib import Path
+
+from cryptography import x509
+from cryptography.hazmat.primitives.serialization import Encoding
+
+from .settings import settings
+
+
+def paired_clients_path() -> Path:
+    override = os.environ.get("REOS_KERNEL_PAIRED_CLIENTS")
+    return Path(override) if override else settings.data_dir / "paired-clients.pem"
+
+
+def fingerprint(cert: x509.Certificate) -> str:
+    """SHA-256 of the certificate's DER, in hex (as the shell shows it)."""
+    return sha256(cert.public_bytes(Encoding.DER)).hexdigest()
+
+
+def load_paired(path: Path | None = None) -> list[x509.Certificate]:
+    path = path or paired_clients_path()
+    try:
+        data = path.read_bytes()
+    except FileNotFoundError:
+        return []
+    return x509.load_pem_x509_certificates(data) if data.strip() else []
+
+
+def _save(certs: list[x509.Certificate], path: Path) -> None:
+    path.parent.mkdir(parents=True, exist_ok=True)
+    temp = path.with_name(path.name + ".tmp")
+    with contextlib.suppress(FileNotFoundError):
+        temp.unlink()
+    fd = os.open(temp, os.O_WRONLY | os.O_CREAT | os.O_EXCL, 0o600)
+    with os.fdopen(fd, "wb") as out:
+        for cert in certs:
+            out.write(cert.public_bytes(Encoding.PEM))
+    os.replace(temp, path)
+
+
+def add(cert: x509.Certificate, path: Path | None = None) -> bool:
+    """Pair the shell with `cert`; False if it already was."""
+    path = path or paired_clients_path()
+    certs = load_paired(path)
+    if any(fingerprint(known) == fingerprint(cert) for known in certs):
+        return False
+    _save([*certs, cert], path)
+    return True
+
+
+def remove(print_: str, path: Path | None = None) -> bool:
+    """Unpair the shell whose certificate has fingerprint `print_`."""
+    path = path or paired_clients_path()
+    wanted = print_.replace(":", "").lower()
+    certs = load_paired(path)
+    kept = [cert for cert in certs if fingerprint(cert) != wanted]
+    if len(kept) == len(certs):
+        return False
+    _save(kept, path)
+    return True
+
+
+def paired_clients_file() -> str | None:
+    """The paired certificates' file, or None while no shell is paired."""
+    path = paired_clients_path()
+    return str(path) if load_paired(path) else None
+
+
+def require_client_certificates(context: ssl.SSLContext, clients: str | None) -> None:
+    """Make a server `context` accept only the certificates in `clients`."""
+    if clients:
+        context.verify_mode = ssl.CERT_REQUIRED
+        context.load_verify_locations(cafile=clients)
+
+
+def _load_cert(path: str) -> x509.Certificate:
+    return x509.load_pem_x509_certificate(Path(path).read_bytes())
+
+
+def main(argv: list[str] | None = None) -> int:
+    parser = argparse.ArgumentParser(
+        prog="python -m reos.kernel_pairing",
+        description="Pair desktop shells with this remote kernel (mutual TLS)",
+    )
+    commands = parser.add_subparsers(dest="command", required=True)
+    commands.add_parser("add", help="pair the shell with this certificate").add_argument("cert")
+    commands.add_parser("list", help="list paired shells")
+    commands.add_parser("remove", help="unpair a shell").add_argument("fingerprint")
+    commands.add_parser(
+        "fingerprint", help="print a certificate's SHA-256 (for server_cert_sha256)"
+    ).add_argument("cert")
+    args = parser.parse_args(argv)
+
+    if args.command == "add":
+        cert = _load_cert(args.cert)
+        added = add(cert)
+        print(f"{'Paired' if added else 'Already paired'}: {fingerprint(cert)}")
+    elif args.command == "list":
+        for cert in load_paired():
+            print(f"{fingerprint(cert)}  {cert.subject.rfc4514_string()}")
+    elif args.command == "remove":
+        if not remove(args.fingerprint):
+            print(f"Not paired: {args.fingerprint}", file=sys.stderr)
+            return 1
+        print(f"Unpaired: {args.fingerprint}")
+    else:
+        print(fingerprint(_load_cert(args.cert)))
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -0,0 +1,339 @@
+"""Windows named pipe listener for the kernel (REOS_KERNEL_PIPE).
+
+The Windows counterpart of the Unix socket in ``run_socket_server``. The
+pipe is created with a security descriptor whose DACL grants access to the
+current user only (protected, so nothing is inherited), rejects remote
+clients, and is the first and only instance of its name: a process that
+created the name before us makes creation fail instead of sharing it.
+
+One shell connection at a time. Reads and writes use overlapped I/O so a
+push from a background job can be written while the main loop is blocked
+reading the next request.
+"""
+
+from __future__ import annotations
+
+import ctypes
+import io
+import sys
+from ctypes import wintypes
+from typing import Any
+
+if sys.platform != "win32":  # pragma: no cover - imported on Windows only
+    raise ImportError("named pipes are only available on Windows")
+
+_kernel32 = ctypes.WinDLL("kernel32", use_last_error=True)
+_advapi32 = ctypes.WinDLL("advapi32", use_last_error=True)
+
+PIPE_ACCESS_DUPLEX = 0x00000003
+FILE_FLAG_FIRST_PIPE_INSTANCE = 0x00080000
+FILE_FLAG_OVERLAPPED = 0x40000000
+PIPE_TYPE_BYTE = 0x00000000
+PIPE_READMODE_BYTE = 0x00000000
+PIPE_WAIT = 0x00000000
+PIPE_REJECT_REMOTE_CLIENTS = 0x00000008
+BUFFER_SIZE = 64 * 1024
+
+ERROR_BROKEN_PIPE = 109
+ERROR_PIPE_NOT_CONNECTED = 233
+ERROR_PIPE_CONNECTED = 535
+ERROR_IO_PENDING = 997
+WAIT_TIMEOUT = 0x00000102
+TOKEN_QUERY = 0x0008
+TOKEN_USER_CLASS = 1
+SDDL_REVISION_1 = 1
+INVALID_HANDLE_VALUE = ctypes.c_void_p(-1).value
+
+
+class _Overlapped(ctypes.Structure):
+    _fields_ = [
+        ("Internal", ctypes.c_void_p),
+        ("InternalHigh", ctypes.c_void_p),
+        ("Offset", wintypes.DWORD),
+        ("OffsetHigh", wintypes.DWORD),
+        ("hEvent", wintypes.HANDLE),
+    ]
+
+
+class _SecurityAttributes(ctypes.Structure):
+    _fields_ = [
+        ("nLength", wintypes.DWORD),
+        ("lpSecurityDescriptor", wintypes.LPVOID),
+        ("bInheritHandle", wintypes.BOOL),
+    ]
+
+
+def _fn(dll: Any, name: str, restype: Any, *argtypes: Any) -> Any:
+    fn = getattr(dll, name)
+    fn.restype = restype
+    fn.argtypes = argtypes
+    return fn
+
+
+_LPOVERLAPPED = ctypes.POINTER(_Overlapped)
+_GetCurrentProcess = _fn(_kernel32, "GetCurrentProcess", wintypes.HANDLE)
+_CloseHandle = _fn(_kernel32, "CloseHandle", wintypes.BOOL, wintypes.HANDLE)
+_LocalFree = _fn(_kernel32, "LocalFree", wintypes.HLOCAL, wintypes.HLOCAL)
+_CreateEventW = _fn(
+    _kernel32,
+    "CreateEventW",
+    wintypes.HANDLE,
+    wintypes.LPVOID,
+    wintypes.BOOL,
+    wintypes.BOOL,
+    wintypes.LPCWSTR,
+)
+_WaitForSingleObject = _fn(
+    _kernel32, "WaitForSingleObject", wintypes.DWORD, wintypes.HANDLE, wintypes.DWORD
+)
+_CreateNamedPipeW = _fn(
+    _kernel32,
+    "CreateNamedPipeW",
+    wintypes.HANDLE,
+    wintypes.LPCWSTR,
+    wintypes.DWORD,
+    wintypes.DWORD,
+    wintypes.DWORD,
+    wintypes.DWORD,
+    wintypes.DWORD,
+    wintypes.DWORD,
+    ctypes.POINTER(_SecurityAttributes),
+)
+_ConnectNamedPipe = _fn(
+    _kernel32, "ConnectNamedPipe", wintypes.BOOL, wintypes.HANDLE, _LPOVERLAPPED
+)
+_DisconnectNamedPipe = _fn(_kernel32, "DisconnectNamedPipe", wintypes.BOOL, wintypes.HANDLE)
+_ReadFile = _fn(
+    _kernel32,
+    "ReadFile",
+    wintypes.BOOL,
+    wintypes.HANDLE,
+    wintypes.LPVOID,
+    wintypes.DWORD,
+    ctypes.POINTER(wintypes.DWORD),
+    _LPOVERLAPPED,
+)
+_WriteFile = _fn(
+    _kernel32,
+    "WriteFile",
+    wintypes.BOOL,
+    wintypes.HANDLE,
+    wintypes.LPCVOID,
+    wintypes.DWORD,
+    ctypes.POINTER(wintypes.DWORD),
+    _LPOVERLAPPED,
+)
+_GetOverlappedResult = _fn(
+    _kernel32,
+    "GetOverlappedResult",
+    wintypes.BOOL,
+    wintypes.HANDLE,
+    _LPOVERLAPPED,
+    ctypes.POINTER(wintypes.DWORD),
+    wintypes.BOOL,
+)
+_CancelIoEx = _fn(_kernel32, "CancelIoEx", wintypes.BOOL, wintypes.HANDLE, _LPOVERLAPPED)
+_OpenProcessToken = _fn(
+    _advapi32,
+    "OpenProcessToken",
+    wintypes.BOOL,
+    wintypes.HANDLE,
+    wintypes.DWORD,
+    ctypes.POINTER(wintypes.HANDLE),
+)
+_GetTokenInformation = _fn(
+    _advapi32,
+    "GetTokenInformation",
+    wintypes.BOOL,
+    wintypes.HANDLE,
+    ctypes.c_int,
+    wintypes.LPVOID,
+    wintypes.DWORD,
+    ctypes.POINTER(wintypes.DWORD),
+)
+_ConvertSidToStringSidW = _fn(
+    _advapi32,
+    "ConvertSidToStringSidW",
+    wintypes.BOOL,
+    wintypes.LPVOID,
+    ctypes.POINTER(wintypes.LPWSTR),
+)
+_ConvertStringSecurityDescriptorToSecurityDescriptorW = _fn(
+    _advapi32,
+    "ConvertStringSecurityDescriptorToSecurityDescriptorW",
+    wintypes.BOOL,
+    wintypes.LPCWSTR,
+    wintypes.DWORD,
+    ctypes.POINTER(wintypes.LPVOID),
+    ctypes.POINTER(wintypes.ULONG),
+)
+
+
+def _error() -> OSError:
+    return ctypes.WinError(ctypes.get_last_error())
+
+
+def _user_sid() -> str:
+    """The current user's SID, as a string (S-1-5-21-...)."""
+    token = wintypes.HANDLE()
+    if not _OpenProcessToken(_GetCurrentProcess(), TOKEN_QUERY, ctypes.byref(token)):
+        raise _error()
+    try:
+        size = wintypes.DWORD()
+        _GetTokenInformation(token, TOKEN_USER_CLASS, None, 0, ctypes.byref(size))
+        info = ctypes.create_string_buffer(size.value)
+        if not _GetTokenInformation(token, TOKEN_USER_CLASS, info, size, ctypes.byref(size)):
+            raise _error()
+        # TOKEN_USER starts with the SID pointer
+        sid = ctypes.cast(info, ctypes.POINTER(ctypes.c_void_p))[0]
+        text = wintypes.LPWSTR()
+        if not _ConvertSidToStringSidW(sid, ctypes.byref(text)):
+            raise _error()
+        try:
+            return str(text.value)
+        finally:
+            _LocalFree(ctypes.cast(text, wintypes.HLOCAL))
+    finally:
+        _CloseHandle(token)
+
+
+def owner_only_sddl() -> str:
+    """Protected DACL allowing only the current user (GENERIC_ALL)."""
+    return f"D:P(A;;GA;;;{_user_sid()})"
+
+
+def _wait(handle: int, overlapped: _Overlapped) -> int:
+    """Bytes transferred by a pending operation once it completes."""
+    done = wintypes.DWORD()
+    if not _GetOverlappedResult(handle, ctypes.byref(overlapped), ctypes.byref(done), True):
+        raise _error()
+    return done.value
+
+
+class _PipeIO(io.RawIOBase):
+    """The connected pipe as a raw stream (closing it leaves the pipe open)."""
+
+    def __init__(self, handle: int) -> None:
+        self._handle = handle
+        self._read_event = _CreateEventW(None, True, False, None)
+        self._write_event = _CreateEventW(None, True, False, None)
+        if not self._read_event or not self._write_event:
+            raise _error()
+
+    def readable(self) -> bool:
+        return True
+
+    def writable(self) -> bool:
+        return True
+
+    def readinto(self, buffer: Any) -> int:
+        view = memoryview(buffer).cast("B")
+        chunk = ctypes.create_string_buffer(len(view))
+        overlapped = _Overlapped(hEvent=self._read_event)
+        if not _ReadFile(self._handle, chunk, len(view), None, ctypes.byref(overlapped)):
+            error = ctypes.get_last_error()
+            if error in (ERROR_BROKEN_PIPE, ERROR_PIPE_NOT_CONNECTED):
+                return 0
+            if error != ERROR_IO_PENDING:
+                raise ctypes.WinError(error)
+        try:
+            count = _wait(self._handle, overlapped)
+        except OSError as exc:
+            if exc.winerror in (ERROR_BROKEN_PIPE, ERROR_PIPE_NOT_CONNECTED):
+                return 0
+            raise
+        view[:count] = chunk.raw[:count]
+        return count
+
+    def write(self, data: Any) -> int:
+        source = bytes(data)
+        overlapped = _Overlapped(hEvent=self._write_event)
+        if not _WriteFile(self._handle, source, len(source), None, ctypes.byref(overlapped)):
+            error = ctypes.get_last_error()
+            if error != ERROR_IO_PENDING:
+                raise ctypes.WinError(error)
+        return _wait(self._handle, overlapped)
+
+    def close(self) -> None:
+        if not self.closed:
+            _CloseHandle(self._read_event)
+            _CloseHandle(self._write_event)
+        super().close()
+
+
+class PipeConnection:
+    """One shell connection, with the socket-like ``makefile`` and ``close``."""
+
+    def __init__(self, listener: PipeListener) -> None:
+        self._listener = listener
+        self._raw = _PipeIO(listener.handle)
+
+    def makefile(self, mode: str, encoding: str = "utf-8", newline: str | None = None) -> Any:
+        if mode == "r":
+            buffered: Any = io.BufferedReader(self._raw, BUFFER_SIZE)
+        else:
+            buffered = io.BufferedWriter(self._raw, BUFFER_SIZE)
+        return io.TextIOWrapper(buffered, encoding=encoding, newline=newline)
+
+    def close(self) -> None:
+        self._raw.close()
+        # Ready for the next ConnectNamedPipe; anything unread is discarded
+        _DisconnectNamedPipe(self._listener.handle)
+
+
+class PipeListener:
+    """The kernel's end of ``name`` (``\\\\.\\pipe\\...``), owner-only."""
+
+    def __init__(self, name: str) -> None:
+        descriptor = wintypes.LPVOID()
+        if not _ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            owner_only_sddl(), SDDL_REVISION_1, ctypes.byref(descriptor), None
+        ):
+            raise _error()
+        try:
+            attributes = _SecurityAttributes(
+                ctypes.sizeof(_SecurityAttributes), descriptor, False
+            )
+            handle = _CreateNamedPipeW(
+                name,
+                PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE | FILE_FLAG_OVERLAPPED,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                1,
+                BUFFER_SIZE,
+                BUFFER_SIZE,
+                0,
+                ctypes.byref(attributes),
+            )
+        finally:
+            _LocalFree(ctypes.cast(descriptor, wintypes.HLOCAL))
+        if handle is None or handle == INVALID_HANDLE_VALUE:
+            raise _error()
+        self.handle: int = handle
+        self._event = _CreateEventW(None, True, False, None)
+        if not self._event:
+            _CloseHandle(handle)
+            raise _error()
+
+    def accept(self, timeout: float) -> PipeConnection:
+        """Wait for a shell to connect; TimeoutError after ``timeout`` seconds."""
+        overlapped = _Overlapped(hEvent=self._event)
+        if not _ConnectNamedPipe(self.handle, ctypes.byref(overlapped)):
+            error = ctypes.get_last_error()
+            if error == ERROR_PIPE_CONNECTED:
+                return PipeConnection(self)
+            if error != ERROR_IO_PENDING:
+                raise ctypes.WinError(error)
+            if _WaitForSingleObject(self._event, int(timeout * 1000)) == WAIT_TIMEOUT:
+                _CancelIoEx(self.handle, ctypes.byref(overlapped))
+                try:
+                    # A shell that connected as the wait ended still counts
+                    _wait(self.handle, overlapped)
+                except OSError:
+                    raise TimeoutError("no shell connected") from None
+            else:
+                _wait(self.handle, overlapped)
+        return PipeConnection(self)
+
+    def close(self) -> None:
+        _CloseHandle(self._event)
+        _CloseHandle(self.handle)
@@ -0,0 +1,167 @@
+"""WebSocket listener for a remote kernel (REOS_KERNEL_WS_LISTEN).
+
+Lets a shell on another machine use this kernel (``remote_kernel.rs``):
+a thin client talking to a home server. TLS only (``wss://``), with the
+certificate and key given by the operator, and a shared token the shell
+must present as ``Authorization: Bearer <token>`` in the upgrade request;
+anything else gets a 401 before a single frame is read.
+
+Each frame line is one text message. One shell connection at a time: a
+second one while the first is still open is closed with 1013 (try again
+later). The kernel itself keeps running between connections.
+"""
+
+from __future__ import annotations
+
+import hmac
+import logging
+import queue
+import ssl
+import threading
+from http import HTTPStatus
+from typing import Any
+
+from websockets.exceptions import ConnectionClosed
+from websockets.sync.server import ServerConnection, serve
+
+from . import kernel_pairing
+
+logger = logging.getLogger(__name__)
+
+# Largest message accepted, mirroring MAX_FRAME_LEN in kernel.rs
+MAX_MESSAGE_SIZE = 256 * 1024 * 1024
+
+# Close code for a shell arriving while another is connected
+TRY_AGAIN_LATER = 1013
+
+
+class _MessageReader:
+    """Text messages as lines; "" once the connection is closed."""
+
+    def __init__(self, websocket: ServerConnection) -> None:
+        self._websocket = websocket
+
+    def readline(self) -> str:
+        try:
+            message = self._websocket.recv()
+        except ConnectionClosed:
+            return ""
+        if isinstance(message, bytes):
+            message = message.decode("utf-8", errors="replace")
+        return message + "\n"
+
+    def close(self) -> None:
+        pass
+
+
+class _MessageWriter:
+    """Each written line goes out as one text message."""
+
+    def __init__(self, websocket: ServerConnection) -> None:
+        self._websocket = websocket
+        self._pending = ""
+
+    def write(self, text: str) -> int:
+        self._pending += text
+        while "\n" in self._pending:
+            line, _, self._pending = self._pending.partition("\n")
+            try:
+                self._websocket.send(line)
+            except ConnectionClosed as exc:
+                raise BrokenPipeError(str(exc)) from exc
+        return len(text)
+
+    def flush(self) -> None:
+        pass
+
+    def close(self) -> None:
+        pass
+
+
+class WebSocketConnection:
+    """One shell connection, with the socket-like ``makefile`` and ``close``."""
+
+    def __init__(self, websocket: ServerConnection) -> None:
+        self._websocket = websocket
+        self.closed = threading.Event()
+
+    def makefile(self, mode: str, encoding: str = "utf-8", newline: str | None = None) -> Any:
+        if mode == "r":
+            return _MessageReader(self._websocket)
+        return _MessageWriter(self._websocket)
+
+    def close(self) -> None:
+        self._websocket.close()
+        self.closed.set()
+
+
+class WebSocketListener:
+    """TLS WebSocket server on ``host``:``port`` for token holders (and,
+    with ``clients``, only the paired shells; see ``kernel_pairing``)."""
+
+    def __init__(
+        self,
+        host: str,
+        port: int,
+        certfile: str,
+        keyfile: str,
+        token: str,
+        *,
+        clients: str | None = None,
+    ) -> None:
+        context = ssl.SSLContext(ssl.PROTOCOL_TLS_SERVER)
+        context.minimum_version = ssl.TLSVersion.TLSv1_2
+        context.load_cert_chain(certfile, keyfile)
+        kernel_pairing.require_client_certificates(context, clients)
+        self._token = token.encode()
+        self._pending: queue.Queue[WebSocketConnection] = queue.Queue()
+        self._busy = threading.Lock()
+        self._server = serve(
+            self._handle,
+            host,
+            port,
+            ssl=context,
+            process_request=self._authorize,
+            max_size=MAX_MESSAGE_SIZE,
+            # Frames are encrypted already; compressing them gains nothing
+            compression=None,
+        )
+        self._thread = threading.Thread(
+            target=self._server.serve_forever, name="kernel-websocket", daemon=True
+        )
+        self._thread.start()
+
+    def _authorize(self, connection: ServerConnection, request: Any) -> Any:
+        header = request.headers.get("Authorization", "")
+        scheme, _, presented = header.partition(" ")
+        if scheme == "Bearer" and hmac.compare_digest(presented.encode(), self._token):
+            return None
+        logger.warning("Rejected remote kernel connection from %s", connection.remote_address)
+        return connection.respond(HTTPStatus.UNAUTHORIZED, "Unauthorized\n")
+
+    def _handle(self, websocket: ServerConnection) -> None:
+        if not self._busy.acquire(blocking=False):
+            websocket.close(TRY_AGAIN_LATER, "kernel busy with another shell")
+            return
+        try:
+            connection = WebSocketConnection(websocket)
+            self._pending.put(connection)
+            # The connection is served by the kernel's main loop
+            connection.closed.wait()
+        finally:
+            self._busy.release()
+
+    @property
+    def port(self) -> int:
+        return int(self._server.socket.getsockname()[1])
+
+    def accept(self, timeout: float | None = None) -> WebSocketConnection:
+        """Wait for an authorized shell; TimeoutError after ``timeout`` seconds."""
+        try:
+            return self._pending.get(timeout=timeout)
+        except queue.Empty:
+            raise TimeoutError("no shell connected") from None
+
+    def close(self) -> None:
+        self._server.shutdown()
+        self._thread.join(5)
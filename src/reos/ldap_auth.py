@@ -0,0 +1,62 @@
+"""Password checks against an LDAP directory (``auth/login_ldap``).
+
+A user is authenticated by binding as their own entry: the Rust shell
+passes the server URL and a DN template from ``auth-backend.json``
+(``uid={username},ou=people,dc=example,dc=org``), and the username is
+escaped into it. The password never crosses the network in the clear:
+``ldaps://`` URLs use TLS from the start, ``ldap://`` ones must upgrade
+with StartTLS, and the server certificate is always verified.
+
+Needs the optional ``ldap`` extra (ldap3).
+"""
+
+from __future__ import annotations
+
+import ssl
+
+CONNECT_TIMEOUT_SECONDS = 10
+
+
+class LdapError(Exception):
+    """Directory unreachable, misconfigured, or ldap3 isn't installed."""
+
+
+def bind(url: str, user_dn: str, username: str, password: str, ca_cert: str | None) -> bool:
+    """Whether the directory accepts `password` for `username`'s entry.
+
+    Raises:
+        LdapError: If the directory can't be asked (bad configuration,
+            connection or TLS failure, ldap3 missing)
+    """
+    if not password:
+        # An empty password would be an unauthenticated bind, which succeeds
+        return False
+    if not url.startswith(("ldaps://", "ldap://")):
+        raise LdapError("LDAP URL must start with ldaps:// or ldap://")
+    if "{username}" not in user_dn:
+        raise LdapError("LDAP user DN must contain {username}")
+    try:
+        import ldap3
+        from ldap3.core.exceptions import LDAPException
+        from ldap3.utils.dn import escape_rdn
+    except ImportError:
+        raise LdapError("LDAP support is not installed (the reos[ldap] extra)") from None
+
+    tls = ldap3.Tls(validate=ssl.CERT_REQUIRED, ca_certs_file=ca_cert)
+    use_ssl = url.startswith("ldaps://")
+    server = ldap3.Server(url, use_ssl=use_ssl, tls=tls, connect_timeout=CONNECT_TIMEOUT_SECONDS)
+    conn = ldap3.Connection(
+        server,
+        user=user_dn.replace("{username}", escape_rdn(username)),
+        password=password,
+        raise_exceptions=False,
+    )
+    try:
+        conn.open()
+        if not use_ssl and not conn.start_tls():
+            raise LdapError("LDAP server refused StartTLS")
+        return bool(conn.bind())
+    except LDAPException as exc:
+        raise LdapError(f"LDAP server unavailable: {exc}") from exc
+    finally:
+        conn.unbind()
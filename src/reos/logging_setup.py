@@ -4,6 +4,7 @@ import logging
 from logging.handlers import RotatingFileHandler
 from pathlib import Path
 
+from .redaction import install_log_filter
 from .settings import settings
 
 _CONFIGURED = False
@@ -70,4 +71,5 @@ def configure_logging(*, log_path: Path | None = None) -> None:
             file=sys.stderr,
         )
 
+    install_log_filter()
     _CONFIGURED = True
@@ -0,0 +1,145 @@
+"""Account password changes through PAM (``pam_chauthtok``).
+
+python-pam only authenticates, so this drives libpam through ctypes with a
+conversation that answers the module's prompts: the current password for
+prompts that ask for it ("Current password:", "(current) UNIX password:",
+"Old password:"), the new password for the others ("New password:",
+"Retype new password:"). Informational messages are ignored and error
+messages become the failure reason.
+
+Runs as the user whose password changes, so modules such as pam_unix ask
+for the current password themselves (through its setuid helpers).
+"""
+
+from __future__ import annotations
+
+import ctypes
+import ctypes.util
+from ctypes import (
+    CFUNCTYPE,
+    POINTER,
+    Structure,
+    byref,
+    c_char_p,
+    c_int,
+    c_size_t,
+    c_void_p,
+    cast,
+)
+
+# The stack passwd(1) uses, with the system's password quality rules
+PAM_SERVICE = "passwd"
+
+PAM_SUCCESS = 0
+PAM_CONV_ERR = 19
+PAM_PROMPT_ECHO_OFF = 1
+PAM_PROMPT_ECHO_ON = 2
+PAM_ERROR_MSG = 3
+
+_CURRENT_PROMPT_WORDS = ("current", "old")
+
+
+class PamError(Exception):
+    """Password change refused or PAM unavailable."""
+
+
+class _PamHandle(Structure):
+    _fields_ = [("handle", c_void_p)]
+
+
+class _PamMessage(Structure):
+    _fields_ = [("msg_style", c_int), ("msg", c_char_p)]
+
+
+class _PamResponse(Structure):
+    # Allocated with strdup: PAM frees it
+    _fields_ = [("resp", c_void_p), ("resp_retcode", c_int)]
+
+
+_ConvFunc = CFUNCTYPE(
+    c_int,
+    c_int,
+    POINTER(POINTER(_PamMessage)),
+    POINTER(POINTER(_PamResponse)),
+    c_void_p,
+)
+
+
+class _PamConv(Structure):
+    _fields_ = [("conv", _ConvFunc), ("appdata_ptr", c_void_p)]
+
+
+def _load() -> tuple[ctypes.CDLL, ctypes.CDLL]:
+    libpam_name = ctypes.util.find_library("pam")
+    libc_name = ctypes.util.find_library("c")
+    if libpam_name is None or libc_name is None:
+        raise PamError("PAM is not available")
+    libpam = ctypes.CDLL(libpam_name)
+    libc = ctypes.CDLL(libc_name)
+
+    libpam.pam_start.argtypes = [c_char_p, c_char_p, POINTER(_PamConv), POINTER(_PamHandle)]
+    libpam.pam_start.restype = c_int
+    libpam.pam_chauthtok.argtypes = [_PamHandle, c_int]
+    libpam.pam_chauthtok.restype = c_int
+    libpam.pam_end.argtypes = [_PamHandle, c_int]
+    libpam.pam_end.restype = c_int
+    libpam.pam_strerror.argtypes = [_PamHandle, c_int]
+    libpam.pam_strerror.restype = c_char_p
+    libc.calloc.argtypes = [c_size_t, c_size_t]
+    libc.calloc.restype = c_void_p
+    libc.strdup.argtypes = [c_char_p]
+    libc.strdup.restype = c_void_p
+    return libpam, libc
+
+
+def answer_for(prompt: str, current_password: str, new_password: str) -> str:
+    """The password a hidden PAM prompt asks for."""
+    lowered = prompt.lower()
+    if any(word in lowered for word in _CURRENT_PROMPT_WORDS):
+        return current_password
+    return new_password
+
+
+def change_password(username: str, current_password: str, new_password: str) -> None:
+    """Change `username`'s account password.
+
+    Raises:
+        PamError: If PAM is unavailable or refused the change (weak or
+            reused password, wrong current password, ...)
+    """
+    libpam, libc = _load()
+    errors: list[str] = []
+
+    def conversation(n_messages, messages, responses, _appdata):  # type: ignore[no-untyped-def]
+        # PAM frees the array and each response with free()
+        block = libc.calloc(n_messages, ctypes.sizeof(_PamResponse))
+        if not block:
+            return PAM_CONV_ERR
+        responses[0] = cast(block, POINTER(_PamResponse))
+        for i in range(n_messages):
+            message = messages[i].contents
+            text = (message.msg or b"").decode(errors="replace")
+            if message.msg_style == PAM_PROMPT_ECHO_OFF:
+                answer = answer_for(text, current_password, new_password)
+            elif message.msg_style == PAM_PROMPT_ECHO_ON:
+                answer = username
+            else:
+                if message.msg_style == PAM_ERROR_MSG:
+                    errors.append(text)
+                continue
+            responses[0][i].resp = libc.strdup(answer.encode())
+            responses[0][i].resp_retcode = 0
+        return PAM_SUCCESS
+
+    conv = _PamConv(_ConvFunc(conversation), 0)
+    handle = _PamHandle()
+    code = libpam.pam_start(PAM_SERVICE.encode(), username.encode(), byref(conv), byref(handle))
+    if code != PAM_SUCCESS:
+        raise PamError("PAM could not start")
+    try:
+        code = libpam.pam_chauthtok(handle, 0)
+        if code != PAM_SUCCESS:
+            reason = errors[-1] if errors else libpam.pam_strerror(handle, code).decode()
+            raise PamError(reason or "Password change failed")
+    finally:
+        libpam.pam_end(handle, code)
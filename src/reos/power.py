@@ -0,0 +1,63 @@
+"""Power state reported by the Rust shell.
+
+``power/event`` arrives when the machine suspends or resumes and when the
+battery changes (``{"kind": "suspend" | "resume" | "battery", ...}``).
+Background jobs call ``wait_until_resumed`` between steps so they hold off
+while the machine sleeps; the first report of a low battery tells the
+caller to flush its caches, so little is lost if the battery runs out.
+"""
+
+from __future__ import annotations
+
+import threading
+from typing import Any
+
+# A job never waits longer than this, in case the resume is never reported
+MAX_PAUSE_SECONDS = 300.0
+
+_lock = threading.Lock()
+_awake = threading.Event()
+_awake.set()
+_battery: dict[str, Any] | None = None
+
+
+class PowerError(Exception):
+    """Malformed power event."""
+
+
+def handle_event(params: dict[str, Any]) -> bool:
+    """Apply a power event; True if the battery just became low."""
+    global _battery
+    kind = params.get("kind")
+    if kind == "suspend":
+        _awake.clear()
+        return False
+    if kind == "resume":
+        _awake.set()
+        return False
+    if kind != "battery":
+        raise PowerError("kind must be suspend, resume or battery")
+
+    on_battery, low = params.get("on_battery"), params.get("low")
+    percent = params.get("percent")
+    if not isinstance(on_battery, bool) or not isinstance(low, bool):
+        raise PowerError("on_battery and low are required")
+    if percent is not None and (
+        isinstance(percent, bool) or not isinstance(percent, int) or not 0 <= percent <= 100
+    ):
+        raise PowerError("percent must be between 0 and 100")
+    with _lock:
+        was_low = _battery is not None and _battery["low"]
+        _battery = {"on_battery": on_battery, "percent": percent, "low": low}
+    return low and not was_low
+
+
+def battery() -> dict[str, Any] | None:
+    """The last battery state reported (None until the shell reports one)."""
+    with _lock:
+        return dict(_battery) if _battery is not None else None
+
+
+def wait_until_resumed(timeout: float = MAX_PAUSE_SECONDS) -> bool:
+    """Block while the machine is suspended; False if the wait timed out."""
+    return _awake.wait(timeout)
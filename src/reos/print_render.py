@@ -0,0 +1,94 @@
+"""Printable documents for the Rust shell's native printing.
+
+``print/render`` runs an ordinary read method (the shell has already
+applied its allowlist, role and consent checks) and turns the result into
+a title plus a list of blocks (``heading``, ``text`` or ``code``); the
+shell lays the blocks out into pages and drives the platform print dialog.
+Results without a dedicated layout print as pretty-printed JSON.
+"""
+
+from __future__ import annotations
+
+import json
+from typing import Any
+
+# Caps on what one document may hold; the shell enforces its own page cap
+MAX_BLOCKS = 5000
+MAX_TEXT_CHARS = 2_000_000
+
+
+class PrintRenderError(Exception):
+    """The document is too large to print."""
+
+
+def _block(kind: str, text: Any) -> dict[str, str]:
+    return {"kind": kind, "text": text if isinstance(text, str) else str(text or "")}
+
+
+def _messages(messages: Any) -> list[dict[str, str]]:
+    blocks: list[dict[str, str]] = []
+    for message in messages if isinstance(messages, list) else []:
+        if not isinstance(message, dict):
+            continue
+        role = str(message.get("role") or "message").capitalize()
+        created_at = message.get("created_at")
+        blocks.append(_block("heading", f"{role} · {created_at}" if created_at else role))
+        blocks.append(_block("text", message.get("content")))
+    return blocks
+
+
+def _markdown(markdown: str) -> list[dict[str, str]]:
+    blocks: list[dict[str, str]] = []
+    paragraph: list[str] = []
+    code: list[str] | None = None
+
+    def flush() -> None:
+        if paragraph:
+            blocks.append(_block("text", "\n".join(paragraph)))
+            paragraph.clear()
+
+    for line in markdown.splitlines():
+        if line.startswith("```"):
+            if code is None:
+                flush()
+                code = []
+            else:
+                blocks.append(_block("code", "\n".join(code)))
+                code = None
+        elif code is not None:
+            code.append(line)
+        elif line.startswith("#"):
+            flush()
+            blocks.append(_block("heading", line.lstrip("#").strip()))
+        elif not line.strip():
+            flush()
+        else:
+            paragraph.append(line)
+    if code is not None:
+        blocks.append(_block("code", "\n".join(code)))
+    flush()
+    return blocks
+
+
+def render(method: str, result: Any) -> dict[str, Any]:
+    """Title and blocks for ``method``'s result."""
+    if method == "conversation/get_messages" and isinstance(result, dict):
+        document = {"title": "Conversation", "blocks": _messages(result.get("messages"))}
+    elif method == "archive/get" and isinstance(result, dict):
+        blocks = []
+        if result.get("summary"):
+            blocks.append(_block("text", result["summary"]))
+        blocks.extend(_messages(result.get("messages")))
+        document = {"title": str(result.get("title") or "Archive"), "blocks": blocks}
+    elif method == "play/me/read" and isinstance(result, dict):
+        document = {"title": "Me", "blocks": _markdown(str(result.get("markdown") or ""))}
+    elif isinstance(result, str):
+        document = {"title": method, "blocks": [_block("text", result)]}
+    else:
+        text = json.dumps(result, indent=2, ensure_ascii=False)
+        document = {"title": method, "blocks": [_block("code", text)]}
+
+    blocks = document["blocks"]
+    if len(blocks) > MAX_BLOCKS or sum(len(b["text"]) for b in blocks) > MAX_TEXT_CHARS:
+        raise PrintRenderError("document is too large to print")
+    return document
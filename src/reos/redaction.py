@@ -0,0 +1,146 @@
+"""Redaction of secrets in logs and error responses.
+
+Mirrors apps/reos-tauri/src-tauri/src/redact.rs: values of sensitive keys
+(passwords, tokens, ``__session``, ...) and secret-shaped text (bearer
+headers, session tokens, common API key formats) become ``[REDACTED]``,
+so logs users paste into issues don't leak credentials.
+
+The Rust shell sends deployment rules from ``redaction.json`` in the
+``initialize`` handshake (see ``configure``).
+"""
+
+from __future__ import annotations
+
+import logging
+import re
+from typing import Any
+
+REDACTED = "[REDACTED]"
+
+DEFAULT_KEYS = (
+    "password",
+    "passphrase",
+    "passwd",
+    "secret",
+    "token",
+    "api_key",
+    "apikey",
+    "authorization",
+    "cookie",
+    "private_key",
+    "__session",
+    "pin",
+)
+
+DEFAULT_PATTERNS = (
+    # Authorization header values
+    r"(?i)\b(?:bearer|basic)\s+[A-Za-z0-9._~+/=-]{8,}",
+    # 256-bit hex session tokens
+    r"\b[0-9a-fA-F]{64}\b",
+    # Provider API keys (OpenAI/Anthropic, GitHub, Slack)
+    r"\bsk-[A-Za-z0-9_-]{16,}",
+    r"\bgh[pousr]_[A-Za-z0-9]{20,}",
+    r"\bxox[abprs]-[A-Za-z0-9-]{10,}",
+)
+
+
+class Redactor:
+    """Compiled redaction rules."""
+
+    def __init__(self, keys: list[str] | None = None, patterns: list[str] | None = None) -> None:
+        self._keys = list(DEFAULT_KEYS) + [k.lower() for k in keys or []]
+        alternation = "|".join(re.escape(k) for k in self._keys)
+        self._assignments = re.compile(
+            rf"""(?i)([\w-]*(?:{alternation})[\w-]*["']?\s*[:=]\s*)("[^"]*"|'[^']*'|[^\s,;&}}\]]+)"""
+        )
+        self._patterns: list[re.Pattern[str]] = []
+        for pattern in list(DEFAULT_PATTERNS) + list(patterns or []):
+            try:
+                self._patterns.append(re.compile(pattern))
+            except re.error:
+                logging.getLogger(__name__).warning("Ignoring invalid redaction pattern")
+
+    def is_sensitive_key(self, key: str) -> bool:
+        key = key.lower()
+        for k in self._keys:
+            if len(k) >= 6:
+                if k in key:
+                    return True
+            # Short names only as a whole word (`pin`, not `spinner`)
+            elif key == k or key.endswith(f"_{k}") or key.startswith(f"{k}_"):
+                return True
+        return False
+
+    def text(self, text: str) -> str:
+        # Patterns first: `Authorization: Bearer x` must lose the whole credential
+        for pattern in self._patterns:
+            text = pattern.sub(REDACTED, text)
+
+        def replace(match: re.Match[str]) -> str:
+            value = match.group(2)
+            # Counts like `max_tokens: 512` aren't secrets
+            if value.isdigit() or value.startswith("[REDACTED"):
+                return match.group(0)
+            return match.group(1) + REDACTED
+
+        return self._assignments.sub(replace, text)
+
+    def value(self, value: Any) -> Any:
+        """Return a copy with sensitive keys' values and secret-shaped text redacted."""
+        if isinstance(value, str):
+            return self.text(value)
+        if isinstance(value, list):
+            return [self.value(v) for v in value]
+        if isinstance(value, dict):
+            return {
+                k: REDACTED
+                if isinstance(v, (str, list, dict)) and self.is_sensitive_key(str(k))
+                else self.value(v)
+                for k, v in value.items()
+            }
+        return value
+
+
+_redactor = Redactor()
+_configured = False
+
+
+def configure(rules: Any) -> None:
+    """Add deployment rules from the Rust shell (first call only)."""
+    global _redactor, _configured
+    if _configured or not isinstance(rules, dict):
+        return
+    keys = [k for k in rules.get("keys") or [] if isinstance(k, str)]
+    patterns = [p for p in rules.get("patterns") or [] if isinstance(p, str)]
+    _redactor = Redactor(keys, patterns)
+    _configured = True
+
+
+def redact_text(text: str) -> str:
+    return _redactor.text(text)
+
+
+def redact_value(value: Any) -> Any:
+    return _redactor.value(value)
+
+
+class RedactingFilter(logging.Filter):
+    """Logging filter that redacts the formatted message."""
+
+    def filter(self, record: logging.LogRecord) -> bool:
+        message = record.getMessage()
+        redacted = redact_text(message)
+        if redacted != message:
+            record.msg = redacted
+            record.args = None
+        return True
+
+
+def install_log_filter() -> None:
+    """Attach RedactingFilter to the root handlers and the last-resort handler."""
+    handlers = list(logging.getLogger().handlers)
+    if logging.lastResort is not None:
+        handlers.append(logging.lastResort)
+    for handler in handlers:
+        if not any(isinstance(f, RedactingFilter) for f in handler.filters):
+            handler.addFilter(RedactingFilter())
@@ -474,6 +474,7 @@ class AuditEventType(Enum):
     AUTH_LOGIN_FAILED = "auth_login_failed"
     AUTH_LOGOUT = "auth_logout"
     AUTH_SESSION_EXPIRED = "auth_session_expired"
+    AUTH_PASSWORD_CHANGED = "auth_password_changed"
 
     # Command execution events
     COMMAND_EXECUTED = "command_executed"
@@ -16,14 +16,104 @@ Usage:
 from __future__ import annotations
 
 import contextvars
+import hmac
+import time
 from contextlib import contextmanager
 from dataclasses import dataclass
+from hashlib import sha256
 from typing import TYPE_CHECKING, Any, Iterator
 
 if TYPE_CHECKING:
     from .crypto_storage import CryptoStorage
 
 
+# Domain separator (and version) of signed __session claims; mirrors kernel.rs
+SESSION_SIGNATURE_VERSION = "reos-session-v1"
+# Domain separator (and version) of signed __shell proofs; mirrors kernel.rs
+SHELL_SIGNATURE_VERSION = "reos-shell-v1"
+# Signed claims older than this are rejected (seconds)
+SESSION_CLAIMS_MAX_AGE = 120
+
+
+def session_claims_message(
+    username: str, session_id: str, role: str, method: str, iat: int
+) -> bytes:
+    """Canonical byte string the Rust shell signs for __session."""
+    return "\n".join(
+        [SESSION_SIGNATURE_VERSION, username, session_id, role, method, str(iat)]
+    ).encode()
+
+
+def verify_session_claims(
+    claims: dict[str, Any],
+    method: str,
+    key: bytes,
+    *,
+    now: float | None = None,
+    max_age: int = SESSION_CLAIMS_MAX_AGE,
+) -> bool:
+    """Check that __session claims were signed by the Rust shell for `method`.
+
+    Args:
+        claims: The __session object (with ``iat`` and ``sig``)
+        method: The JSON-RPC method the claims arrived with
+        key: Per-boot key received in the ``initialize`` handshake
+        now: Current unix time (for tests)
+        max_age: Maximum accepted age of ``iat`` in seconds
+
+    Returns:
+        True if the signature is valid and fresh
+    """
+    iat = claims.get("iat")
+    sig = claims.get("sig")
+    if not isinstance(iat, int) or not isinstance(sig, str):
+        return False
+    now = time.time() if now is None else now
+    if abs(now - iat) > max_age:
+        return False
+
+    fields = [claims.get(name) for name in ("username", "session_id", "role")]
+    if not all(isinstance(f, str) for f in fields):
+        return False
+    message = session_claims_message(*fields, method=method, iat=iat)
+    expected = hmac.new(key, message, sha256).hexdigest()
+    return hmac.compare_digest(expected, sig)
+
+
+def shell_claims_message(method: str, iat: int) -> bytes:
+    """Canonical byte string the Rust shell signs for __shell."""
+    return "\n".join([SHELL_SIGNATURE_VERSION, method, str(iat)]).encode()
+
+
+def verify_shell_claims(
+    claims: dict[str, Any],
+    method: str,
+    key: bytes,
+    *,
+    now: float | None = None,
+    max_age: int = SESSION_CLAIMS_MAX_AGE,
+) -> bool:
+    """Check that a __shell proof was signed by the Rust shell for `method`.
+
+    The shell adds it to calls it makes on its own behalf (never to a
+    window's request), so methods that trust the shell's authentication
+    can tell them apart from anything else able to reach the kernel.
+
+    Returns:
+        True if the signature is valid and fresh
+    """
+    iat = claims.get("iat")
+    sig = claims.get("sig")
+    if not isinstance(iat, int) or not isinstance(sig, str):
+        return False
+    now = time.time() if now is None else now
+    if abs(now - iat) > max_age:
+        return False
+
+    expected = hmac.new(key, shell_claims_message(method, iat), sha256).hexdigest()
+    return hmac.compare_digest(expected, sig)
+
+
 @dataclass
 class SessionInfo:
     """Session info extracted from RPC request.
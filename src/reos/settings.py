@@ -25,7 +25,12 @@ class Settings:
     """
 
     root_dir: Path = Path(__file__).resolve().parents[2]
-    data_dir: Path = root_dir / ".reos-data"
+    # REOS_DATA_DIR: set by the desktop shell for named profiles
+    data_dir: Path = (
+        Path(os.environ["REOS_DATA_DIR"])
+        if os.environ.get("REOS_DATA_DIR")
+        else root_dir / ".reos-data"
+    )
     events_path: Path = data_dir / "events.jsonl"
     audit_path: Path = data_dir / "audit.log"
     log_path: Path = data_dir / "reos.log"
@@ -15,21 +15,41 @@ catalog so the UI can reuse those capabilities.
 
 from __future__ import annotations
 
+import binascii
+import contextlib
 import hashlib
+import hmac
+import inspect
+import ipaddress
 import json
 import logging
+import os
+import re
+import socket
 import sys
 import threading
+import time
 import uuid
+from collections.abc import Callable
 from pathlib import Path
 from typing import Any
 
 logger = logging.getLogger(__name__)
 
-from . import auth
+from . import appearance, auth, blob_transfer, ipc_channel, power, redaction, update_guard
+from .appearance import AppearanceError
+from .power import PowerError
+from .export_stream import ExportError, ExportStore
+from .export_stream import serialize as serialize_export
+from .print_render import PrintRenderError
+from .print_render import render as render_print
+from .ingest import IngestError, IngestManager
+from .crypto_storage import CryptoStorage
+from .vault import Vault, VaultError
 from .agent import ChatAgent
 from .db import Database, get_db
 from .mcp_tools import ToolError, call_tool, list_tools
+from .session import session_context, verify_session_claims, verify_shell_claims
 from .security import (
     ValidationError,
     validate_service_name,
@@ -72,10 +92,17 @@ from .play_fs import update_scene as play_update_scene
 from .play_fs import assign_repo_to_act as play_assign_repo_to_act
 from .context_meter import calculate_context_stats, estimate_tokens
 from .knowledge_store import KnowledgeStore
+from .system_index import get_or_refresh_context
 from .compact_extractor import extract_knowledge_from_messages, generate_archive_summary
 
 _JSON = dict[str, Any]
 
+# Session token hashes the Rust shell persists (hex SHA-256)
+_SHA256_HEX_RE = re.compile(r"[0-9a-f]{64}")
+
+# Correlation IDs the Rust shell sends with each request (12 hex chars)
+_CORRELATION_ID_RE = re.compile(r"[0-9a-f]{12}")
+
 
 class RpcError(RuntimeError):
     def __init__(self, code: int, message: str, data: Any | None = None) -> None:
@@ -86,9 +113,10 @@ class RpcError(RuntimeError):
 
 
 def _jsonrpc_error(*, req_id: Any, code: int, message: str, data: Any | None = None) -> _JSON:
-    err: _JSON = {"code": code, "message": message}
+    # Error text often echoes params or exception details
+    err: _JSON = {"code": code, "message": redaction.redact_text(message)}
     if data is not None:
-        err["data"] = data
+        err["data"] = redaction.redact_value(data)
     return {"jsonrpc": "2.0", "id": req_id, "error": err}
 
 
@@ -96,22 +124,307 @@ def _jsonrpc_result(*, req_id: Any, result: Any) -> _JSON:
     return {"jsonrpc": "2.0", "id": req_id, "result": result}
 
 
+# Per-boot key for verifying __session claims, set once by the initialize
+# handshake from the Rust shell (see reos.session.verify_session_claims)
+_session_key: bytes | None = None
+
+# Tray quick actions announced in the `initialize` result. The shell runs
+# them with the main window's session, through the usual method checks,
+# and shows `summary` / `message` / `error` from the result.
+_QUICK_ACTIONS: list[dict[str, str]] = [
+    {"id": "system-status", "title": "System status", "method": "system/live_state"},
+    {"id": "open-terminal", "title": "Open terminal", "method": "system/open-terminal"},
+]
+
+# Feature flags announced in the `initialize` result, over the shell's
+# compiled defaults (config.toml `[features]` still wins). Names are
+# lowercase letters, digits and `_`.
+_FEATURES: dict[str, bool] = {}
+
+# App menu sections the Tauri shell adds to the native menu; items run their
+# method (without params) through the same checks as any kernel request
+_APP_MENU: list[dict[str, Any]] = [
+    {
+        "title": "Conversation",
+        "items": [
+            {
+                "id": "new-conversation",
+                "title": "New conversation",
+                "accelerator": "CmdOrCtrl+Shift+N",
+                "method": "conversation/start",
+            },
+            {
+                "id": "pending-approvals",
+                "title": "Pending approvals",
+                "method": "approval/pending",
+            },
+        ],
+    },
+    {
+        "title": "System",
+        "items": [
+            {
+                "id": "system-status",
+                "title": "System status",
+                "accelerator": "CmdOrCtrl+Shift+S",
+                "method": "system/live_state",
+            },
+            {"id": "hardware", "title": "Hardware", "method": "system/hardware"},
+            {"id": "model-status", "title": "Model status", "method": "ollama/status"},
+            {
+                "id": "open-terminal",
+                "title": "Open terminal",
+                "accelerator": "CmdOrCtrl+Alt+T",
+                "method": "system/open-terminal",
+            },
+        ],
+    },
+]
+
+# Shown while the Thunderbird integration is active
+_CALENDAR_MENU: dict[str, Any] = {
+    "title": "Calendar",
+    "items": [
+        {
+            "id": "calendar-status",
+            "title": "Calendar status",
+            "method": "cairn/thunderbird/status",
+        },
+    ],
+}
+
+
+def _accept_session_key(params: Any) -> bool:
+    """Take the Rust shell's __session signing key (first handshake only).
+
+    Later handshakes (a shell reconnecting over the socket) must present the
+    same key; returns False if one doesn't.
+    """
+    global _session_key
+    key = params.get("session_key") if isinstance(params, dict) else None
+    if _session_key is not None:
+        try:
+            offered = bytes.fromhex(key) if isinstance(key, str) else b""
+        except ValueError:
+            offered = b""
+        return hmac.compare_digest(offered, _session_key)
+    if not isinstance(key, str):
+        return True
+    try:
+        _session_key = bytes.fromhex(key)
+    except ValueError:
+        logger.warning("Ignoring malformed session key in initialize")
+    return True
+
+
+# auth/* methods that trust the shell's own authentication: only with a
+# signed __shell proof (see reos.session.verify_shell_claims)
+_SHELL_SIGNED_METHODS = frozenset({"auth/login_verified", "auth/revoke_user"})
+
+
+def _take_spawn_key(line: str) -> None:
+    """Install the session key the shell wrote to stdin when it spawned us.
+
+    A listening kernel gets its key this way rather than in `initialize`:
+    a same-user process connecting to the endpoint before the shell would
+    otherwise be the first to hand over a key, and keep it.
+    """
+    global _session_key
+    try:
+        key = bytes.fromhex(line.strip())
+    except ValueError:
+        key = b""
+    if len(key) != 32:
+        raise SystemExit("Malformed session key on stdin")
+    _session_key = key
+
+
+def _handle_with_session(db: Database, req: dict[str, Any]) -> dict[str, Any] | None:
+    """Verify and strip __session, then dispatch inside its session context.
+
+    Claims must carry a valid signature from the Rust shell; anything else
+    that can write to stdin can't impersonate a user. auth/* belongs to the
+    shell: a call with claims came from a window and is refused.
+    """
+    params = req.get("params")
+    claims = params.pop("__session", None) if isinstance(params, dict) else None
+    shell = params.pop("__shell", None) if isinstance(params, dict) else None
+    method = req.get("method")
+    if isinstance(method, str) and method.startswith("auth/"):
+        if claims is not None:
+            logger.warning("Rejected auth request made for a window session: %s", method)
+            return _jsonrpc_error(
+                req_id=req.get("id"), code=-32003, message="Method reserved for the shell"
+            )
+        if method in _SHELL_SIGNED_METHODS and (
+            _session_key is None
+            or not isinstance(shell, dict)
+            or not verify_shell_claims(shell, method, _session_key)
+        ):
+            logger.warning("Rejected request without a valid __shell proof: %s", method)
+            return _jsonrpc_error(
+                req_id=req.get("id"), code=-32003, message="Invalid shell signature"
+            )
+    if claims is not None:
+        if (
+            _session_key is None
+            or not isinstance(claims, dict)
+            or not isinstance(method, str)
+            or not verify_session_claims(claims, method, _session_key)
+        ):
+            logger.warning("Rejected request with unsigned or invalid __session: %s", method)
+            return _jsonrpc_error(
+                req_id=req.get("id"), code=-32003, message="Invalid session signature"
+            )
+
+    with session_context(claims):
+        return _handle_jsonrpc_request(db, req)
+
+
+# Encrypted channel to the Rust shell, once its handshake hello arrived
+# (over stdio, plain JSON lines otherwise, e.g. when driven by hand)
+_channel: ipc_channel.SecureChannel | None = None
+
+# Over a Unix socket (REOS_KERNEL_SOCKET), named pipe (REOS_KERNEL_PIPE),
+# WebSocket (REOS_KERNEL_WS_LISTEN), HTTP (REOS_KERNEL_HTTP_LISTEN) or gRPC
+# (REOS_KERNEL_GRPC_LISTEN), the current shell connection's streams;
+# None between connections. Over stdio, frames use stdin/stdout.
+_socket_mode = False
+_conn_in: Any = None
+_conn_out: Any = None
+
+# Embedded in the shell's process (see `embedded_start`): lines the kernel
+# pushes go to this callback instead of a stream
+_push_sink: Callable[[str], None] | None = None
+
+# Set by `kernel/shutdown`: exit rather than wait for the shell to reconnect
+_shutdown = threading.Event()
+
+# Set when a reconnecting shell fails the key check, or a connection skips
+# the handshake: drop it (the shell that started us may still connect)
+_drop_connection = threading.Event()
+
+
 def _readline() -> str | None:
-    line = sys.stdin.readline()
+    line = (_conn_in if _socket_mode else sys.stdin).readline()
     if not line:
         return None
+    if _channel is not None and line.strip():
+        try:
+            return _channel.open(line.strip())
+        except ipc_channel.ChannelError:
+            # Counters are out of sync after a bad frame: nothing later can be trusted
+            logger.error("Rejected tampered or corrupt IPC frame; shutting down")
+            return None
     return line
 
 
+# Background jobs push progress events; frames must not interleave and the
+# channel's counters must advance in the order lines are written
+_write_lock = threading.Lock()
+
+
 def _write(obj: Any) -> None:
+    line = json.dumps(obj, ensure_ascii=False)
+    with _write_lock:
+        if _channel is not None:
+            line = _channel.seal(line)
+        _write_line(line)
+
+
+def _write_line(line: str) -> None:
+    if _push_sink is not None:
+        _push_sink(line)
+        return
+    if _socket_mode:
+        if _conn_out is None:
+            # No shell connected: pushes until the next one are dropped
+            return
+        try:
+            _conn_out.write(line + "\n")
+            _conn_out.flush()
+        except OSError:
+            # The shell went away; the read loop sees it next
+            pass
+        return
     try:
-        sys.stdout.write(json.dumps(obj, ensure_ascii=False) + "\n")
+        sys.stdout.write(line + "\n")
         sys.stdout.flush()
     except BrokenPipeError:
         # Client closed the pipe (e.g., UI exited). Treat as a clean shutdown.
         raise SystemExit(0) from None
 
 
+def push_notification(title: str, body: str = "") -> None:
+    """Ask the Tauri shell to show a desktop notification.
+
+    Sent as a JSON-RPC notification (no id) between responses; the shell
+    applies the user's mute settings for the ``kernel`` category.
+    """
+    _write(
+        {
+            "jsonrpc": "2.0",
+            "method": "event",
+            "params": {"tag": "notify", "title": title, "body": body},
+        }
+    )
+
+
+def push_progress(job: str, done: int, total: int, *, failed: bool = False) -> None:
+    """Report progress of a long-running job for the taskbar.
+
+    ``total`` of 0 means unknown; the job ends once ``done`` reaches
+    ``total`` or with ``failed``. Safe to call from background threads.
+    """
+    _write(
+        {
+            "jsonrpc": "2.0",
+            "method": "event",
+            "params": {
+                "tag": "progress",
+                "job": job,
+                "done": done,
+                "total": total,
+                "failed": failed,
+            },
+        }
+    )
+
+
+def _thunderbird_active(db: Database) -> bool:
+    try:
+        from .cairn.store import CairnStore
+        from .rpc.handlers.cairn import get_current_play_path
+
+        play_path = get_current_play_path(db)
+        if not play_path:
+            return False
+        store = CairnStore(Path(play_path) / ".cairn" / "cairn.db")
+        stored_state = store.get_integration_state("thunderbird")
+    except Exception:
+        return False
+    return bool(stored_state and stored_state["state"] == "active")
+
+
+def _app_menu(db: Database) -> list[dict[str, Any]]:
+    """App menu sections for the features available now."""
+    menu = list(_APP_MENU)
+    if _thunderbird_active(db):
+        menu.append(_CALENDAR_MENU)
+    return menu
+
+
+def push_capabilities(db: Database) -> None:
+    """Send the shell a new app menu after features were turned on or off."""
+    _write(
+        {
+            "jsonrpc": "2.0",
+            "method": "event",
+            "params": {"tag": "capabilities", "menu": _app_menu(db)},
+        }
+    )
+
+
 # -------------------------------------------------------------------------
 # Authentication handlers (PAM + session management)
 # -------------------------------------------------------------------------
@@ -150,6 +463,86 @@ def _handle_auth_login(
     return result
 
 
+# Who may vouch for a user in auth/login_verified (the shell's backends)
+_LOGIN_VERIFIERS = ("password_file", "oidc")
+
+
+def _audit_login(username: str, result: dict[str, Any], details: dict[str, Any]) -> None:
+    """Audit a login attempt made through one of the shell's backends."""
+    if result.get("success"):
+        audit_log(AuditEventType.AUTH_LOGIN_SUCCESS, {"username": username, **details})
+    else:
+        audit_log(AuditEventType.AUTH_LOGIN_FAILED, {
+            "username": username,
+            "error": result.get("error", "unknown"),
+            **details,
+        })
+
+
+def _handle_auth_login_ldap(
+    *,
+    username: str,
+    password: str,
+    url: str,
+    user_dn: str,
+    ca_cert: str | None,
+) -> dict[str, Any]:
+    """Authenticate against an LDAP directory and create a session."""
+    try:
+        check_rate_limit("auth")
+    except RateLimitExceeded as e:
+        audit_log(AuditEventType.RATE_LIMIT_EXCEEDED, {"category": "auth", "username": username})
+        return {"success": False, "error": str(e)}
+
+    result = auth.login_ldap(username, password, url, user_dn, ca_cert)
+    _audit_login(username, result, {"verified_by": "ldap"})
+    return result
+
+
+def _handle_auth_login_verified(
+    *,
+    username: str,
+    password: str,
+    verified_by: str,
+    subject: str | None,
+) -> dict[str, Any]:
+    """Create a session for a user the shell already authenticated.
+
+    `subject` is the identity provider's id for the user (OIDC), kept in
+    the audit log to trace a session back to the account that vouched.
+    """
+    try:
+        check_rate_limit("auth")
+    except RateLimitExceeded as e:
+        audit_log(AuditEventType.RATE_LIMIT_EXCEEDED, {"category": "auth", "username": username})
+        return {"success": False, "error": str(e)}
+
+    result = auth.open_session(username, password)
+    details: dict[str, Any] = {"verified_by": verified_by}
+    if subject is not None:
+        details["subject"] = subject
+    _audit_login(username, result, details)
+    return result
+
+
+def _handle_auth_login_os_session(
+    *,
+    username: str,
+    os_session_id: str,
+    passphrase: str,
+) -> dict[str, Any]:
+    """Open a session vouched for by the desktop login session (no PAM prompt)."""
+    try:
+        check_rate_limit("auth")
+    except RateLimitExceeded as e:
+        audit_log(AuditEventType.RATE_LIMIT_EXCEEDED, {"category": "auth", "username": username})
+        return {"success": False, "error": str(e)}
+
+    result = auth.login_os_session(username, os_session_id, passphrase)
+    _audit_login(username, result, {"verified_by": "os_session"})
+    return result
+
+
 def _handle_auth_logout(
     *,
     session_token: str,
@@ -163,12 +556,53 @@ def _handle_auth_logout(
     return result
 
 
-def _handle_auth_validate(
+def _handle_auth_change_password(
     *,
     session_token: str,
+    username: str,
+    current_password: str,
+    new_password: str,
 ) -> dict[str, Any]:
-    """Validate a session token."""
-    return auth.validate_session(session_token)
+    """Change a user's password via PAM and rotate their session and key."""
+    try:
+        check_rate_limit("auth")
+    except RateLimitExceeded as e:
+        audit_log(AuditEventType.RATE_LIMIT_EXCEEDED, {"category": "auth", "username": username})
+        return {"success": False, "error": str(e)}
+
+    result = auth.change_password(session_token, username, current_password, new_password)
+    if result.get("success"):
+        audit_log(AuditEventType.AUTH_PASSWORD_CHANGED, {"username": username})
+    else:
+        audit_log(AuditEventType.AUTH_LOGIN_FAILED, {
+            "username": username,
+            "error": result.get("error", "unknown"),
+            "check": "change_password",
+        })
+    return result
+
+
+def _handle_auth_revoke_user(
+    *,
+    username: str,
+) -> dict[str, Any]:
+    """Destroy every session of a user (admin revocation from the shell)."""
+    if not auth.is_valid_username(username):
+        raise RpcError(code=-32602, message="Invalid username")
+    result = auth.revoke_user(username)
+    audit_log(AuditEventType.AUTH_LOGOUT, {"username": username, "revoked": result["revoked"]})
+    return result
+
+
+def _handle_auth_validate(
+    *,
+    session_token: str | None = None,
+    session_token_sha256: str | None = None,
+) -> dict[str, Any]:
+    """Validate a session token, or the hash of one (sessions the shell restores)."""
+    if session_token is not None:
+        return auth.validate_session(session_token)
+    return auth.validate_session_hash(session_token_sha256 or "")
 
 
 def _handle_auth_refresh(
@@ -180,6 +614,210 @@ def _handle_auth_refresh(
     return {"success": refreshed}
 
 
+def _handle_auth_verify_password(
+    *,
+    session_token: str,
+    username: str,
+    password: str,
+    purpose: str,
+) -> dict[str, Any]:
+    """Check a logged-in user's password via PAM (unlock and step-up)."""
+    try:
+        check_rate_limit("auth")
+    except RateLimitExceeded as e:
+        audit_log(AuditEventType.RATE_LIMIT_EXCEEDED, {"category": "auth", "username": username})
+        return {"success": False, "error": str(e)}
+
+    result = auth.verify_password(session_token, username, password)
+    if not result.get("success"):
+        audit_log(AuditEventType.AUTH_LOGIN_FAILED, {
+            "username": username,
+            "error": result.get("error", "unknown"),
+            "check": purpose,
+        })
+    return result
+
+
+def _handle_auth_groups(
+    *,
+    username: str,
+) -> dict[str, Any]:
+    """Unix groups of a user, for the shell's role mapping."""
+    from .linux_tools import _get_user_groups
+
+    if not auth.is_valid_username(username):
+        raise RpcError(code=-32602, message="Invalid username")
+    return {"groups": _get_user_groups(username)}
+
+
+def _vault_for(session_token: str) -> Vault:
+    """Vault of the user behind a kernel session token."""
+    session = auth.get_session(session_token)
+    if session is None:
+        raise RpcError(code=-32003, message="Invalid session")
+    return Vault(CryptoStorage(session))
+
+
+def _handle_vault(method: str, params: dict[str, Any]) -> dict[str, Any]:
+    """vault/set, vault/get, vault/delete, vault/list (called by the Rust shell only)."""
+    session_token = params.get("session_token")
+    if not isinstance(session_token, str) or not session_token:
+        raise RpcError(code=-32602, message="session_token is required")
+    vault = _vault_for(session_token)
+    if method == "vault/list":
+        return {"names": vault.list()}
+
+    name = params.get("name")
+    if not isinstance(name, str):
+        raise RpcError(code=-32602, message="name is required")
+    try:
+        if method == "vault/get":
+            return {"value": vault.get(name)}
+        if method == "vault/delete":
+            return {"deleted": vault.delete(name)}
+        value = params.get("value")
+        max_entries = params.get("max_entries")
+        if not isinstance(value, str) or not isinstance(max_entries, int):
+            raise RpcError(code=-32602, message="value and max_entries are required")
+        vault.set(name, value, max_entries=max_entries)
+        return {"ok": True}
+    except VaultError as exc:
+        raise RpcError(code=-32602, message=str(exc)) from exc
+
+
+_ingest = IngestManager()
+
+
+def _handle_ingest(method: str, params: dict[str, Any]) -> dict[str, Any]:
+    """ingest/begin, ingest/chunk, ingest/finish, ingest/abort (called by the Rust shell only)."""
+    session_token = params.get("session_token")
+    if not isinstance(session_token, str) or not session_token:
+        raise RpcError(code=-32602, message="session_token is required")
+    session = auth.get_session(session_token)
+    if session is None:
+        raise RpcError(code=-32003, message="Invalid session")
+    storage = CryptoStorage(session)
+    try:
+        if method == "ingest/begin":
+            name, size = params.get("name"), params.get("size")
+            if not isinstance(name, str) or not isinstance(size, int):
+                raise RpcError(code=-32602, message="name and size are required")
+            return {"upload_id": _ingest.begin(storage, name=name, size=size)}
+
+        upload_id = params.get("upload_id")
+        if not isinstance(upload_id, str):
+            raise RpcError(code=-32602, message="upload_id is required")
+        if method == "ingest/chunk":
+            data = params.get("data")
+            if not isinstance(data, str):
+                raise RpcError(code=-32602, message="data is required")
+            try:
+                chunk = binascii.a2b_base64(data, strict_mode=True)
+            except binascii.Error as exc:
+                raise RpcError(code=-32602, message="data must be base64") from exc
+            return {"received": _ingest.chunk(storage, upload_id, chunk)}
+        if method == "ingest/finish":
+            sha256 = params.get("sha256")
+            if not isinstance(sha256, str):
+                raise RpcError(code=-32602, message="sha256 is required")
+            return _ingest.finish(storage, upload_id, sha256)
+        _ingest.abort(storage, upload_id)
+        return {"ok": True}
+    except IngestError as exc:
+        raise RpcError(code=-32602, message=str(exc)) from exc
+
+
+_exports = ExportStore()
+
+
+def _handle_export(db: Database, req_id: Any, method: str, params: dict[str, Any]) -> Any:
+    """export/begin, export/chunk, export/end (called by the Rust shell only)."""
+    try:
+        if method == "export/begin":
+            inner = params.get("method")
+            if not isinstance(inner, str) or inner.startswith("export/"):
+                raise RpcError(code=-32602, message="method is required")
+            # Runs in the caller's session context, set up from the signed claims
+            inner_params = params.get("params")
+            inner_req = {"jsonrpc": "2.0", "id": req_id, "method": inner, "params": inner_params}
+            response = _handle_jsonrpc_request(db, inner_req)
+            if response is None or "error" in response:
+                error = (response or {}).get("error") or {}
+                raise RpcError(
+                    code=error.get("code", -32603), message=error.get("message", "export failed")
+                )
+            return _exports.begin(serialize_export(response.get("result")))
+
+        export_id = params.get("export_id")
+        if not isinstance(export_id, str):
+            raise RpcError(code=-32602, message="export_id is required")
+        if method == "export/chunk":
+            offset, length = params.get("offset"), params.get("length")
+            if not isinstance(offset, int) or not isinstance(length, int):
+                raise RpcError(code=-32602, message="offset and length are required")
+            data = _exports.chunk(export_id, offset, length)
+            return {"data": binascii.b2a_base64(data, newline=False).decode("ascii")}
+        _exports.end(export_id)
+        return {"ok": True}
+    except ExportError as exc:
+        raise RpcError(code=-32602, message=str(exc)) from exc
+
+
+def _flush_caches(db: Database) -> None:
+    """Move the database's write-ahead log into the main file (low battery)."""
+    try:
+        db.connect().execute("PRAGMA wal_checkpoint(TRUNCATE)")
+    except Exception as exc:  # noqa: BLE001
+        logger.warning("Failed to checkpoint the database: %s", exc)
+
+
+# Work `warmup/hint` can do ahead of a new session's first real request
+WARMUP_HINTS = ("store", "index", "caches")
+
+
+def _handle_warmup_hint(db: Database, params: dict[str, Any]) -> dict[str, Any]:
+    """warmup/hint (called by the Rust shell only, in the background after login).
+
+    - ``store``: derive the user's encrypted store and read its index
+    - ``index``: load (or capture, once a day) the system snapshot the agent uses
+    - ``caches``: read the lists the first screens show
+    """
+    hint = params.get("hint")
+    if hint not in WARMUP_HINTS:
+        raise RpcError(code=-32602, message=f"hint must be one of {', '.join(WARMUP_HINTS)}")
+    started = time.monotonic()
+    if hint == "store":
+        session_token = params.get("session_token")
+        if not isinstance(session_token, str) or not session_token:
+            raise RpcError(code=-32602, message="session_token is required")
+        _vault_for(session_token).list()
+    elif hint == "index":
+        get_or_refresh_context(db)
+    else:
+        _handle_personas_list(db)
+        _handle_play_acts_list(db)
+        _handle_conversation_list(db)
+    return {"hint": hint, "ms": round((time.monotonic() - started) * 1000)}
+
+
+def _handle_print_render(db: Database, req_id: Any, params: dict[str, Any]) -> Any:
+    """print/render (called by the Rust shell only)."""
+    inner = params.get("method")
+    if not isinstance(inner, str) or inner.startswith(("export/", "print/")):
+        raise RpcError(code=-32602, message="method is required")
+    # Runs in the caller's session context, set up from the signed claims
+    inner_params = params.get("params")
+    inner_req = {"jsonrpc": "2.0", "id": req_id, "method": inner, "params": inner_params}
+    response = _handle_jsonrpc_request(db, inner_req)
+    if response is None or "error" in response:
+        error = (response or {}).get("error") or {}
+        raise RpcError(code=error.get("code", -32603), message=error.get("message", "print failed"))
+    try:
+        return render_print(inner, response.get("result"))
+    except PrintRenderError as exc:
+        raise RpcError(code=-32602, message=str(exc)) from exc
+
+
 def _tools_list() -> dict[str, Any]:
     return {
         "tools": [
@@ -2127,6 +2765,12 @@ def _handle_system_live_state(db: Database) -> dict[str, Any]:
         logger.debug("Failed to get network traffic: %s", e)
         result["_errors"] = result.get("_errors", []) + ["traffic"]
 
+    # One line for the tray quick action notification
+    disk = result["disks"][0]["percent"] if result["disks"] else 0.0
+    result["summary"] = (
+        f"CPU {result['cpu_percent']:.0f}%, memory {result['memory']['percent']:.0f}%, "
+        f"disk {disk:.0f}%"
+    )
     return result
 
 
@@ -2727,12 +3371,16 @@ def _handle_ollama_pull_start(db: Database, *, model: str) -> dict[str, Any]:
     def do_pull() -> None:
         import httpx
 
+        job = f"model-pull:{pull_id}"
+        last_percent = -1
         try:
             with httpx.Client(timeout=None) as client:
                 # Stream the pull to get progress updates
                 with client.stream("POST", pull_url, json={"name": model, "stream": True}) as response:
                     response.raise_for_status()
                     for line in response.iter_lines():
+                        # Hold off while the machine sleeps
+                        power.wait_until_resumed()
                         if not line:
                             continue
                         try:
@@ -2761,8 +3409,14 @@ def _handle_ollama_pull_start(db: Database, *, model: str) -> dict[str, Any]:
                                 if "error" in data:
                                     pull_state["error"] = data["error"]
                                     pull_state["done"] = True
+                                percent = pull_state["progress"]
+                                done = pull_state["done"]
                         except json.JSONDecodeError:
                             continue
+                        # Only whole-percent steps reach the taskbar
+                        if percent != last_percent and not done:
+                            last_percent = percent
+                            push_progress(job, percent, 100)
 
             # Mark as done if we exit cleanly
             with _pull_lock:
@@ -2770,12 +3424,14 @@ def _handle_ollama_pull_start(db: Database, *, model: str) -> dict[str, Any]:
                     _active_pulls[pull_id]["done"] = True
                     if _active_pulls[pull_id]["progress"] == 0:
                         _active_pulls[pull_id]["progress"] = 100
+            push_progress(job, 100, 100)
 
         except Exception as e:
             with _pull_lock:
                 if pull_id in _active_pulls:
                     _active_pulls[pull_id]["error"] = str(e)
                     _active_pulls[pull_id]["done"] = True
+            push_progress(job, 0, 100, failed=True)
 
     # Start pull in background thread
     thread = threading.Thread(target=do_pull, daemon=True)
@@ -4359,13 +5015,40 @@ def _handle_handoff_validate_all(_db: Database) -> dict[str, Any]:
     return validate_all_manifests()
 
 
+_METHOD_EQ_RE = re.compile(r'method == "([^"]+)"')
+_METHOD_IN_RE = re.compile(r"method in \(([^)]*)\)")
+_method_names: list[str] | None = None
+
+
+def _method_manifest() -> list[str]:
+    """Methods `_handle_jsonrpc_request` answers, for the `initialize` result.
+
+    Read from its own dispatch so the list can't drift from it; empty when the
+    source isn't shipped (the shell then has no manifest to check against).
+    """
+    global _method_names
+    if _method_names is None:
+        try:
+            source = inspect.getsource(_handle_jsonrpc_request)
+        except (OSError, TypeError):
+            source = ""
+        names = set(_METHOD_EQ_RE.findall(source))
+        for group in _METHOD_IN_RE.findall(source):
+            names.update(re.findall(r'"([^"]+)"', group))
+        _method_names = sorted(names)
+    return _method_names
+
+
 def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any] | None:
     method = req.get("method")
     req_id = req.get("id")
     params = req.get("params")
 
-    # Generate correlation ID for request tracing
-    correlation_id = uuid.uuid4().hex[:12]
+    # Correlation ID for request tracing: the shell's when it sent one (so
+    # its slow-request warnings match these logs), otherwise a fresh one
+    correlation_id = req.get("correlation_id")
+    if not isinstance(correlation_id, str) or not _CORRELATION_ID_RE.fullmatch(correlation_id):
+        correlation_id = uuid.uuid4().hex[:12]
 
     # Log request entry (DEBUG level for normal requests, skip ping/initialize for noise reduction)
     if method not in ("ping", "initialize"):
@@ -4378,12 +5061,31 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
 
     try:
         if method == "initialize":
+            if not _accept_session_key(params):
+                logger.warning("Rejected initialize with a different session key")
+                if _socket_mode:
+                    _drop_connection.set()
+                raise RpcError(code=-32003, message="Session key mismatch")
+            if isinstance(params, dict):
+                redaction.configure(params.get("redaction"))
+                blob_transfer.configure(params.get("blobs"))
             result = {
                 "protocolVersion": "jsonrpc-2.0",
                 "serverInfo": {"name": "reos-ui-kernel", "version": "0.1.0"},
+                "quick_actions": _QUICK_ACTIONS,
+                "menu": _app_menu(db),
+                "features": _FEATURES,
+                "methods": _method_manifest(),
+                "pid": os.getpid(),
             }
             return _jsonrpc_result(req_id=req_id, result=result)
 
+        if method == "kernel/shutdown":
+            # From the Rust shell (a notification) when it stops a socket
+            # kernel for good
+            _shutdown.set()
+            return None
+
         # Notifications can omit id; ignore.
         if req_id is None:
             return None
@@ -4405,6 +5107,84 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
                 result=_handle_auth_login(username=username, password=password),
             )
 
+        if method == "auth/login_ldap":
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            username = params.get("username")
+            password = params.get("password")
+            url = params.get("url")
+            user_dn = params.get("user_dn")
+            ca_cert = params.get("ca_cert")
+            if not isinstance(username, str) or not username:
+                raise RpcError(code=-32602, message="username is required")
+            if not isinstance(password, str):
+                raise RpcError(code=-32602, message="password is required")
+            if not isinstance(url, str) or not isinstance(user_dn, str):
+                raise RpcError(code=-32602, message="url and user_dn are required")
+            if ca_cert is not None and not isinstance(ca_cert, str):
+                raise RpcError(code=-32602, message="ca_cert must be a path")
+            return _jsonrpc_result(
+                req_id=req_id,
+                result=_handle_auth_login_ldap(
+                    username=username,
+                    password=password,
+                    url=url,
+                    user_dn=user_dn,
+                    ca_cert=ca_cert,
+                ),
+            )
+
+        if method == "auth/login_verified":
+            # Called by the Rust shell only, once its backend checked the user
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            username = params.get("username")
+            password = params.get("password")
+            verified_by = params.get("verified_by")
+            subject = params.get("subject")
+            if not isinstance(username, str) or not username:
+                raise RpcError(code=-32602, message="username is required")
+            if not isinstance(password, str):
+                raise RpcError(code=-32602, message="password is required")
+            if verified_by not in _LOGIN_VERIFIERS:
+                raise RpcError(
+                    code=-32602,
+                    message=f"verified_by must be one of {', '.join(_LOGIN_VERIFIERS)}",
+                )
+            if verified_by == "oidc" and (not isinstance(subject, str) or not subject):
+                raise RpcError(code=-32602, message="subject is required for oidc")
+            if subject is not None and not isinstance(subject, str):
+                raise RpcError(code=-32602, message="subject must be a string")
+            return _jsonrpc_result(
+                req_id=req_id,
+                result=_handle_auth_login_verified(
+                    username=username,
+                    password=password,
+                    verified_by=verified_by,
+                    subject=subject,
+                ),
+            )
+
+        if method == "auth/login_os_session":
+            # Called by the Rust shell only, after it checked the session with logind
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            username = params.get("username")
+            os_session_id = params.get("os_session_id")
+            passphrase = params.get("passphrase")
+            if not isinstance(username, str) or not username:
+                raise RpcError(code=-32602, message="username is required")
+            if not isinstance(os_session_id, str) or not os_session_id:
+                raise RpcError(code=-32602, message="os_session_id is required")
+            if not isinstance(passphrase, str):
+                raise RpcError(code=-32602, message="passphrase is required")
+            return _jsonrpc_result(
+                req_id=req_id,
+                result=_handle_auth_login_os_session(
+                    username=username, os_session_id=os_session_id, passphrase=passphrase
+                ),
+            )
+
         if method == "auth/logout":
             if not isinstance(params, dict):
                 raise RpcError(code=-32602, message="params must be an object")
@@ -4416,17 +5196,60 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
                 result=_handle_auth_logout(session_token=session_token),
             )
 
-        if method == "auth/validate":
+        if method == "auth/change_password":
             if not isinstance(params, dict):
                 raise RpcError(code=-32602, message="params must be an object")
             session_token = params.get("session_token")
+            username = params.get("username")
+            current_password = params.get("current_password")
+            new_password = params.get("new_password")
             if not isinstance(session_token, str) or not session_token:
                 raise RpcError(code=-32602, message="session_token is required")
+            if not isinstance(username, str) or not username:
+                raise RpcError(code=-32602, message="username is required")
+            if not isinstance(current_password, str) or not isinstance(new_password, str):
+                raise RpcError(
+                    code=-32602, message="current_password and new_password are required"
+                )
+            if not new_password:
+                raise RpcError(code=-32602, message="new_password must not be empty")
+            return _jsonrpc_result(
+                req_id=req_id,
+                result=_handle_auth_change_password(
+                    session_token=session_token,
+                    username=username,
+                    current_password=current_password,
+                    new_password=new_password,
+                ),
+            )
+
+        if method == "auth/revoke_user":
+            # Called by the Rust shell only, for an admin's session
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            username = params.get("username")
+            if not isinstance(username, str) or not username:
+                raise RpcError(code=-32602, message="username is required")
             return _jsonrpc_result(
                 req_id=req_id,
-                result=_handle_auth_validate(session_token=session_token),
+                result=_handle_auth_revoke_user(username=username),
             )
 
+        if method == "auth/validate":
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            session_token = params.get("session_token")
+            token_sha256 = params.get("session_token_sha256")
+            if isinstance(session_token, str) and session_token:
+                result = _handle_auth_validate(session_token=session_token)
+            elif isinstance(token_sha256, str) and _SHA256_HEX_RE.fullmatch(token_sha256):
+                result = _handle_auth_validate(session_token_sha256=token_sha256)
+            else:
+                raise RpcError(
+                    code=-32602, message="session_token or session_token_sha256 is required"
+                )
+            return _jsonrpc_result(req_id=req_id, result=result)
+
         if method == "auth/refresh":
             if not isinstance(params, dict):
                 raise RpcError(code=-32602, message="params must be an object")
@@ -4438,6 +5261,110 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
                 result=_handle_auth_refresh(session_token=session_token),
             )
 
+        if method == "auth/verify_password":
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            session_token = params.get("session_token")
+            username = params.get("username")
+            password = params.get("password")
+            if not isinstance(session_token, str) or not session_token:
+                raise RpcError(code=-32602, message="session_token is required")
+            if not isinstance(username, str) or not username:
+                raise RpcError(code=-32602, message="username is required")
+            if not isinstance(password, str):
+                raise RpcError(code=-32602, message="password is required")
+            purpose = params.get("purpose", "unlock")
+            if purpose not in ("unlock", "step_up"):
+                raise RpcError(code=-32602, message="purpose must be unlock or step_up")
+            return _jsonrpc_result(
+                req_id=req_id,
+                result=_handle_auth_verify_password(
+                    session_token=session_token,
+                    username=username,
+                    password=password,
+                    purpose=purpose,
+                ),
+            )
+
+        if method == "auth/groups":
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            username = params.get("username")
+            if not isinstance(username, str) or not username:
+                raise RpcError(code=-32602, message="username is required")
+            return _jsonrpc_result(
+                req_id=req_id,
+                result=_handle_auth_groups(username=username),
+            )
+
+        if method == "update/prepare":
+            # Called by the Rust shell only, right before installing an update
+            return _jsonrpc_result(req_id=req_id, result=update_guard.prepare())
+
+        if method == "update/resume":
+            update_guard.resume()
+            return _jsonrpc_result(req_id=req_id, result={"ok": True})
+
+        if method == "session/export":
+            # Called by the Rust shell only, while swapping in a new kernel
+            return _jsonrpc_result(req_id=req_id, result={"sessions": auth.export_sessions()})
+
+        if method == "session/import":
+            # Called by the Rust shell only, on the kernel being swapped in
+            if not isinstance(params, dict) or not isinstance(params.get("sessions"), list):
+                raise RpcError(code=-32602, message="sessions must be a list")
+            try:
+                imported = auth.import_sessions(params["sessions"])
+            except ValueError as exc:
+                raise RpcError(code=-32602, message=str(exc)) from None
+            return _jsonrpc_result(req_id=req_id, result={"imported": imported})
+
+        if method in ("export/begin", "export/chunk", "export/end"):
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            return _jsonrpc_result(req_id=req_id, result=_handle_export(db, req_id, method, params))
+
+        if method == "appearance/set":
+            # Called by the Rust shell only, at startup and on theme changes
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            try:
+                return _jsonrpc_result(req_id=req_id, result=appearance.set_appearance(params))
+            except AppearanceError as exc:
+                raise RpcError(code=-32602, message=str(exc)) from exc
+
+        if method == "power/event":
+            # Called by the Rust shell only, on suspend, resume and battery changes
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            try:
+                battery_low = power.handle_event(params)
+            except PowerError as exc:
+                raise RpcError(code=-32602, message=str(exc)) from exc
+            if battery_low:
+                _flush_caches(db)
+            return _jsonrpc_result(req_id=req_id, result={"ok": True})
+
+        if method == "warmup/hint":
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            return _jsonrpc_result(req_id=req_id, result=_handle_warmup_hint(db, params))
+
+        if method == "print/render":
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            return _jsonrpc_result(req_id=req_id, result=_handle_print_render(db, req_id, params))
+
+        if method in ("ingest/begin", "ingest/chunk", "ingest/finish", "ingest/abort"):
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            return _jsonrpc_result(req_id=req_id, result=_handle_ingest(method, params))
+
+        if method in ("vault/set", "vault/get", "vault/delete", "vault/list"):
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            return _jsonrpc_result(req_id=req_id, result=_handle_vault(method, params))
+
         if method == "tools/list":
             return _jsonrpc_result(req_id=req_id, result=_tools_list())
 
@@ -5848,18 +6775,19 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
                 raise RpcError(code=-32602, message="active_profiles must be a list")
             active_accounts = params.get("active_accounts")
             all_active = params.get("all_active", False)
-            return _jsonrpc_result(
-                req_id=req_id,
-                result=_handle_thunderbird_configure(
-                    db,
-                    active_profiles=active_profiles,
-                    active_accounts=active_accounts,
-                    all_active=all_active,
-                ),
+            result = _handle_thunderbird_configure(
+                db,
+                active_profiles=active_profiles,
+                active_accounts=active_accounts,
+                all_active=all_active,
             )
+            push_capabilities(db)
+            return _jsonrpc_result(req_id=req_id, result=result)
 
         if method == "thunderbird/decline":
-            return _jsonrpc_result(req_id=req_id, result=_handle_thunderbird_decline(db))
+            result = _handle_thunderbird_decline(db)
+            push_capabilities(db)
+            return _jsonrpc_result(req_id=req_id, result=result)
 
         if method == "thunderbird/reset":
             return _jsonrpc_result(req_id=req_id, result=_handle_thunderbird_reset(db))
@@ -6066,13 +6994,18 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
         )
 
 
-def run_stdio_server() -> None:
-    """Run the UI kernel server over stdio."""
+def _serve(db: Database) -> None:
+    """Answer the shell's requests until its connection closes.
 
-    db = get_db()
-    db.migrate()
+    Over stdio the first line may skip the channel handshake (a kernel
+    driven by hand); a connection to a socket, pipe or remote listener
+    that doesn't start with a valid hello is dropped.
+    """
+    global _channel
 
-    while True:
+    # The handshake is only accepted as the very first line
+    first_line = True
+    while not _shutdown.is_set() and not _drop_connection.is_set():
         line = _readline()
         if line is None:
             return
@@ -6081,6 +7014,20 @@ def run_stdio_server() -> None:
         if not line:
             continue
 
+        if first_line:
+            first_line = False
+            rust_public = ipc_channel.parse_hello(line)
+            if rust_public is not None:
+                channel, reply = ipc_channel.accept(rust_public)
+                with _write_lock:
+                    _write_line(reply)
+                _channel = channel
+                continue
+            if _socket_mode:
+                logger.warning("Dropped a connection that didn't start with a handshake")
+                _drop_connection.set()
+                return
+
         try:
             req = json.loads(line)
         except json.JSONDecodeError:
@@ -6089,13 +7036,301 @@ def run_stdio_server() -> None:
         if not isinstance(req, dict):
             continue
 
-        resp = _handle_jsonrpc_request(db, req)
+        # The shell has opened the last response's blobs by now
+        blob_transfer.release_previous()
+        resp = _handle_with_session(db, req)
         if resp is not None:
-            _write(resp)
+            _write(blob_transfer.offload(resp))
+
+
+def run_stdio_server() -> None:
+    """Run the UI kernel server over stdio."""
+    db = get_db()
+    db.migrate()
+    _serve(db)
+
+
+def run_socket_server(path: str, linger: float) -> None:
+    """Run the UI kernel server on a Unix socket at `path` (0600).
+
+    One shell connection at a time, each with its own channel handshake. A
+    shell that goes away leaves the kernel running: the next one has
+    `linger` seconds to connect and present the same session key in
+    `initialize` before the kernel exits. `kernel/shutdown` ends it at once.
+    """
+    db = get_db()
+    db.migrate()
+
+    with contextlib.suppress(FileNotFoundError):
+        os.unlink(path)
+    listener = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+    old_umask = os.umask(0o177)
+    try:
+        listener.bind(path)
+    finally:
+        os.umask(old_umask)
+    listener.listen(1)
+
+    def accept() -> socket.socket:
+        listener.settimeout(linger)
+        conn, _ = listener.accept()
+        conn.settimeout(None)
+        return conn
+
+    try:
+        _serve_connections(db, accept, linger)
+    finally:
+        listener.close()
+        with contextlib.suppress(FileNotFoundError):
+            os.unlink(path)
+
+
+def run_pipe_server(name: str, linger: float) -> None:
+    """Run the UI kernel server on a Windows named pipe (owner-only).
+
+    Same lifecycle as `run_socket_server`; see `kernel_pipe` for the pipe's
+    security descriptor.
+    """
+    from . import kernel_pipe
+
+    db = get_db()
+    db.migrate()
+
+    listener = kernel_pipe.PipeListener(name)
+    try:
+        _serve_connections(db, lambda: listener.accept(linger), linger)
+    finally:
+        listener.close()
+
+
+def run_websocket_server(listen: str, certfile: str, keyfile: str, token: str) -> None:
+    """Run the UI kernel server for a remote shell on a TLS WebSocket.
+
+    `listen` is `host:port`. Only shells presenting `token` get in (see
+    `kernel_websocket`); like a socket kernel it takes one connection at a
+    time and keeps the session key of its first `initialize`. It waits for
+    shells until stopped on this machine, and its output stays on the
+    console.
+    """
+    from . import kernel_pairing, kernel_websocket
+
+    host, _, port = listen.rpartition(":")
+    db = get_db()
+    db.migrate()
+
+    listener = kernel_websocket.WebSocketListener(
+        host.strip("[]") or "0.0.0.0",
+        int(port),
+        certfile,
+        keyfile,
+        token,
+        clients=kernel_pairing.paired_clients_file(),
+    )
+    logger.info("Remote kernel listening on wss://%s", listen)
+    try:
+        _serve_connections(db, listener.accept, None, silence_output=False)
+    finally:
+        listener.close()
+
+
+def run_http_server(listen: str, certfile: str | None, keyfile: str | None, token: str) -> None:
+    """Run the UI kernel server for a remote shell over HTTP(S).
+
+    The fallback to `run_websocket_server` where WebSockets are blocked (see
+    `kernel_http`), with the same lifecycle. Without a certificate it
+    serves plain HTTP, which `_remote_credentials` only allows on loopback.
+    """
+    from . import kernel_http, kernel_pairing
+
+    host, _, port = listen.rpartition(":")
+    db = get_db()
+    db.migrate()
+
+    listener = kernel_http.HttpListener(
+        host.strip("[]") or "0.0.0.0",
+        int(port),
+        certfile,
+        keyfile,
+        token,
+        clients=kernel_pairing.paired_clients_file(),
+    )
+    scheme = "https" if certfile else "http"
+    logger.info("Remote kernel listening on %s://%s", scheme, listen)
+    try:
+        _serve_connections(db, listener.accept, None, silence_output=False)
+    finally:
+        listener.close()
+
+
+def run_grpc_server(listen: str, certfile: str | None, keyfile: str | None, token: str) -> None:
+    """Run the UI kernel server for a remote shell over gRPC.
+
+    Serves `proto/kernel.proto` (see `kernel_grpc`, which needs the `grpc`
+    extra) with the same lifecycle as `run_websocket_server`; plain
+    (non-TLS) gRPC only on a loopback address.
+    """
+    from . import kernel_grpc, kernel_pairing
+
+    host, _, port = listen.rpartition(":")
+    db = get_db()
+    db.migrate()
+
+    listener = kernel_grpc.GrpcListener(
+        host.strip("[]") or "0.0.0.0",
+        int(port),
+        certfile,
+        keyfile,
+        token,
+        clients=kernel_pairing.paired_clients_file(),
+    )
+    scheme = "grpcs" if certfile else "grpc"
+    logger.info("Remote kernel listening on %s://%s", scheme, listen)
+    try:
+        _serve_connections(db, listener.accept, None, silence_output=False)
+    finally:
+        listener.close()
+
+
+def _serve_connections(
+    db: Database,
+    accept: Callable[[], Any],
+    linger: float | None,
+    *,
+    silence_output: bool = True,
+) -> None:
+    """Serve one shell connection after another from `accept` (which raises
+    TimeoutError after `linger`) until shut down or nobody reconnects."""
+    global _channel, _conn_in, _conn_out, _socket_mode
+
+    _socket_mode = True
+    while not _shutdown.is_set():
+        try:
+            conn = accept()
+        except TimeoutError:
+            logger.info("No shell reconnected within %.0fs; exiting", linger)
+            return
+        _drop_connection.clear()
+        _channel = None
+        _conn_in = conn.makefile("r", encoding="utf-8", newline="\n")
+        with _write_lock:
+            _conn_out = conn.makefile("w", encoding="utf-8", newline="\n")
+        try:
+            _serve(db)
+        finally:
+            with _write_lock:
+                for stream in (_conn_in, _conn_out):
+                    with contextlib.suppress(OSError):
+                        stream.close()
+                _conn_in = _conn_out = None
+                _channel = None
+            conn.close()
+        if silence_output and not _drop_connection.is_set():
+            # Only the shell that started us reads our output, and it's gone
+            _silence_output()
+
+
+def _silence_output() -> None:
+    devnull = os.open(os.devnull, os.O_WRONLY)
+    try:
+        os.dup2(devnull, 1)
+        os.dup2(devnull, 2)
+    finally:
+        os.close(devnull)
+
+
+# Shortest accepted remote kernel token
+MIN_REMOTE_TOKEN_LEN = 32
+
+
+def _remote_credentials(prefix: str, listen: str, tls_required: bool) -> tuple[Any, Any, str]:
+    """Certificate, key and token for a remote kernel listening on `listen`,
+    from `<prefix>_CERT`, `<prefix>_KEY` and `<prefix>_TOKEN_FILE` (a file,
+    so the token doesn't sit in the environment). Without `tls_required`,
+    the certificate may be left out for a loopback address."""
+    certfile = os.environ.get(f"{prefix}_CERT")
+    keyfile = os.environ.get(f"{prefix}_KEY")
+    token_file = os.environ.get(f"{prefix}_TOKEN_FILE")
+    host = listen.rpartition(":")[0].strip("[]")
+    if not tls_required and not certfile and not keyfile and _is_loopback(host):
+        certfile = keyfile = None
+    elif not certfile or not keyfile:
+        raise SystemExit(f"{prefix}_LISTEN needs {prefix}_CERT and {prefix}_KEY")
+    if not token_file:
+        raise SystemExit(f"{prefix}_LISTEN needs {prefix}_TOKEN_FILE")
+    token = Path(token_file).read_text(encoding="utf-8").strip()
+    if len(token) < MIN_REMOTE_TOKEN_LEN:
+        raise SystemExit(f"Remote kernel token must be at least {MIN_REMOTE_TOKEN_LEN} characters")
+    return certfile, keyfile, token
+
+
+def _is_loopback(host: str) -> bool:
+    if host == "localhost":
+        return True
+    try:
+        return ipaddress.ip_address(host).is_loopback
+    except ValueError:
+        return False
+
+
+# Database of the embedded kernel, once `embedded_start` opened it
+_embedded_db: Database | None = None
+
+
+def embedded_start(push: Callable[[str], None]) -> None:
+    """Serve a shell that loaded this interpreter in-process (PyO3).
+
+    Requests come in through `embedded_handle`, on the shell's threads, and
+    pushes go to `push` as JSON text: nothing crosses a process boundary, so
+    there is no channel to encrypt. Called again whenever the shell starts
+    its kernel anew, with a new session key to follow in `initialize`.
+    """
+    global _embedded_db, _push_sink, _session_key, _channel
+    if _embedded_db is None:
+        redaction.install_log_filter()
+        _embedded_db = get_db()
+        _embedded_db.migrate()
+    _session_key = None
+    _channel = None
+    _push_sink = push
+
+
+def embedded_handle(line: str) -> str | None:
+    """Answer one JSON-RPC request given as JSON text (None for a notification)."""
+    if _embedded_db is None:
+        raise RuntimeError("embedded kernel not started")
+    req = json.loads(line)
+    if not isinstance(req, dict):
+        raise ValueError("request must be a JSON object")
+    resp = _handle_with_session(_embedded_db, req)
+    return None if resp is None else json.dumps(resp, ensure_ascii=False)
 
 
 def main() -> None:
-    run_stdio_server()
+    redaction.install_log_filter()
+    socket_path = os.environ.get("REOS_KERNEL_SOCKET")
+    pipe_name = os.environ.get("REOS_KERNEL_PIPE")
+    ws_listen = os.environ.get("REOS_KERNEL_WS_LISTEN")
+    http_listen = os.environ.get("REOS_KERNEL_HTTP_LISTEN")
+    grpc_listen = os.environ.get("REOS_KERNEL_GRPC_LISTEN")
+    linger = float(os.environ.get("REOS_KERNEL_LINGER_SECS") or 300)
+    if os.environ.get("REOS_KERNEL_KEY_STDIN") == "1":
+        _take_spawn_key(sys.stdin.readline())
+    if socket_path:
+        run_socket_server(socket_path, linger)
+    elif pipe_name:
+        run_pipe_server(pipe_name, linger)
+    elif ws_listen:
+        run_websocket_server(ws_listen, *_remote_credentials("REOS_KERNEL_WS", ws_listen, True))
+    elif http_listen:
+        run_http_server(
+            http_listen, *_remote_credentials("REOS_KERNEL_HTTP", http_listen, False)
+        )
+    elif grpc_listen:
+        run_grpc_server(
+            grpc_listen, *_remote_credentials("REOS_KERNEL_GRPC", grpc_listen, False)
+        )
+    else:
+        run_stdio_server()
 
 
 if __name__ == "__main__":
@@ -0,0 +1,64 @@
+"""Keep app updates from landing mid-write.
+
+Before installing an update the Rust shell calls ``update/prepare``: new
+migrations and encrypted writes are refused from then on, and the call
+waits (briefly) for the ones in flight to finish. If they don't, the
+freeze is lifted again and the shell reports the kernel as busy.
+``update/resume`` lifts the freeze when an install is abandoned.
+
+Guarded code wraps its critical section in ``operation(kind)``.
+"""
+
+from __future__ import annotations
+
+import threading
+from collections import Counter
+from contextlib import contextmanager
+from typing import Iterator
+
+# How long update/prepare waits for in-flight operations by default (seconds)
+DEFAULT_PREPARE_TIMEOUT = 10.0
+
+_cond = threading.Condition()
+_inflight: Counter[str] = Counter()
+_frozen = False
+
+
+class UpdatePending(Exception):
+    """Raised when guarded work starts while an update is being installed."""
+
+
+@contextmanager
+def operation(kind: str) -> Iterator[None]:
+    """Mark a migration or encrypted write as in flight."""
+    with _cond:
+        if _frozen:
+            raise UpdatePending(f"{kind} refused: an update is being installed")
+        _inflight[kind] += 1
+    try:
+        yield
+    finally:
+        with _cond:
+            _inflight[kind] -= 1
+            if not _inflight[kind]:
+                del _inflight[kind]
+            _cond.notify_all()
+
+
+def prepare(timeout: float = DEFAULT_PREPARE_TIMEOUT) -> dict[str, object]:
+    """Freeze guarded work and wait for in-flight operations to drain."""
+    global _frozen
+    with _cond:
+        _frozen = True
+        _cond.wait_for(lambda: not _inflight, timeout=timeout)
+        busy = sorted(_inflight)
+        if busy:
+            _frozen = False
+        return {"ready": not busy, "busy": busy}
+
+
+def resume() -> None:
+    """Lift a freeze left by ``prepare`` (install abandoned or failed)."""
+    global _frozen
+    with _cond:
+        _frozen = False
@@ -0,0 +1,59 @@
+"""Per-user secrets vault on top of CryptoStorage.
+
+Backs the Rust shell's vault_* commands (API keys and similar secrets).
+Each secret is one encrypted file under ``vault/`` in the user's data
+root, so entries are namespaced per user by the session's key material.
+The Rust shell checks sessions, roles and sizes first; names are
+validated again here because they become file names.
+"""
+
+from __future__ import annotations
+
+import re
+
+from .crypto_storage import CryptoStorage
+
+VAULT_DIR = "vault"
+SECRET_SUFFIX = ".secret"
+# Mirrors vault::validate_name in the Tauri shell
+NAME_PATTERN = re.compile(r"^[A-Za-z0-9_-][A-Za-z0-9._-]{0,127}$")
+
+
+class VaultError(Exception):
+    """Invalid secret name or full vault."""
+
+
+class Vault:
+    """Named secrets for one user."""
+
+    def __init__(self, storage: CryptoStorage) -> None:
+        self._storage = storage
+
+    @staticmethod
+    def _path(name: str) -> str:
+        if not NAME_PATTERN.match(name):
+            raise VaultError(f"invalid secret name: {name!r}")
+        return f"{VAULT_DIR}/{name}{SECRET_SUFFIX}"
+
+    def list(self) -> list[str]:
+        prefix = f"{VAULT_DIR}/"
+        return [
+            rel[len(prefix) : -len(SECRET_SUFFIX)]
+            for rel in self._storage.list_files(VAULT_DIR)
+            if rel.startswith(prefix) and rel.endswith(SECRET_SUFFIX)
+        ]
+
+    def set(self, name: str, value: str, *, max_entries: int) -> None:
+        path = self._path(name)
+        if not self._storage.exists(path) and len(self.list()) >= max_entries:
+            raise VaultError(f"vault is full ({max_entries} secrets)")
+        self._storage.write_text(path, value)
+
+    def get(self, name: str) -> str | None:
+        path = self._path(name)
+        if not self._storage.exists(path):
+            return None
+        return self._storage.read_text(path)
+
+    def delete(self, name: str) -> bool:
+        return self._storage.delete(self._path(name))
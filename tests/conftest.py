@@ -1,8 +1,10 @@
 from __future__ import annotations
 
+import json
 import subprocess
 from collections.abc import Iterator
 from pathlib import Path
+from typing import Any
 
 import pytest
 
@@ -250,3 +252,38 @@ def session_log_dir(tmp_path: Path) -> Iterator[Path]:
     # Restore
     if original_base is not None:
         sl_mod._SESSION_LOG_BASE = original_base
+
+
+class ShellChannel:
+    """The Rust shell's side of the kernel channel handshake (ipc.rs).
+
+    Send `hello` as the first line, pass the kernel's reply to `finish`,
+    then `seal` requests and `open` responses.
+    """
+
+    def __init__(self) -> None:
+        from cryptography.hazmat.primitives.asymmetric.x25519 import X25519PrivateKey
+        from cryptography.hazmat.primitives.serialization import Encoding, PublicFormat
+
+        from reos.ipc_channel import PROTOCOL
+
+        self._private = X25519PrivateKey.generate()
+        self._public = self._private.public_key().public_bytes(Encoding.Raw, PublicFormat.Raw)
+        self.hello = json.dumps({"channel": PROTOCOL, "e": self._public.hex()})
+        self._channel: Any = None
+
+    def finish(self, reply: str) -> None:
+        from cryptography.hazmat.primitives.asymmetric.x25519 import X25519PublicKey
+
+        from reos.ipc_channel import SecureChannel, derive_keys
+
+        kernel_public = bytes.fromhex(json.loads(reply)["e"])
+        shared = self._private.exchange(X25519PublicKey.from_public_bytes(kernel_public))
+        to_kernel, to_rust = derive_keys(shared, self._public, kernel_public)
+        self._channel = SecureChannel(recv_key=to_rust, send_key=to_kernel)
+
+    def seal(self, message: dict[str, Any]) -> str:
+        return str(self._channel.seal(json.dumps(message)))
+
+    def open(self, line: str) -> dict[str, Any]:
+        return dict(json.loads(self._channel.open(line.strip())))
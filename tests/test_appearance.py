@@ -0,0 +1,30 @@
+"""Tests for the desktop appearance reported by the Rust shell."""
+
+from __future__ import annotations
+
+import pytest
+
+from reos import appearance
+from reos.appearance import AppearanceError, current, set_appearance
+
+
+def test_set_appearance_is_returned_by_current(monkeypatch):
+    monkeypatch.setattr(appearance, "_current", dict(appearance._current))
+    set_appearance({"scheme": "dark", "accent": "#3584e4", "font_scale": 1.25})
+    assert current() == {"scheme": "dark", "accent": "#3584e4", "font_scale": 1.25}
+
+
+@pytest.mark.parametrize(
+    "params",
+    [
+        {"scheme": "sepia"},
+        {"scheme": "dark", "accent": "blue"},
+        {"scheme": "dark", "font_scale": 10},
+        {"scheme": "dark", "font_scale": True},
+    ],
+)
+def test_rejects_malformed_appearance(monkeypatch, params):
+    monkeypatch.setattr(appearance, "_current", dict(appearance._current))
+    with pytest.raises(AppearanceError):
+        set_appearance(params)
+    assert current()["scheme"] == "light"
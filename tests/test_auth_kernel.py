@@ -0,0 +1,413 @@
+"""Tests for the kernel's auth/* methods called by the Rust shell."""
+
+from __future__ import annotations
+
+from pathlib import Path
+from typing import Any
+
+import pytest
+
+from reos import auth
+from reos.security import get_rate_limiter
+
+
+@pytest.fixture(autouse=True)
+def reset_rate_limiter() -> None:
+    get_rate_limiter()._requests.clear()
+
+
+@pytest.fixture
+def fresh_store(monkeypatch: pytest.MonkeyPatch) -> auth.SessionStore:
+    store = auth.SessionStore()
+    monkeypatch.setattr(auth, "_session_store", store)
+    return store
+
+
+def _session(token: str, username: str = "alice") -> auth.Session:
+    now = auth.datetime.now(auth.timezone.utc)
+    return auth.Session(
+        token=token,
+        username=username,
+        created_at=now,
+        last_activity=now,
+        key_material=bytes(range(32)),
+    )
+
+
+def _rpc(method: str, params: dict[str, Any]) -> dict[str, Any]:
+    import reos.ui_rpc_server as ui
+
+    resp = ui._handle_jsonrpc_request(
+        None,  # type: ignore[arg-type]  # auth/* never touch the db
+        {"jsonrpc": "2.0", "id": 1, "method": method, "params": params},
+    )
+    assert resp is not None
+    return resp
+
+
+def test_auth_change_password(
+    fresh_store: auth.SessionStore, monkeypatch: pytest.MonkeyPatch, tmp_path: Path
+) -> None:
+    from reos import pam_password
+    from reos.crypto_storage import CryptoStorage
+
+    monkeypatch.setattr(auth.Path, "home", lambda: tmp_path)
+    monkeypatch.setattr(auth, "authenticate_pam", lambda u, p: p == "old-pass")
+    changed: list[tuple[str, str, str]] = []
+    monkeypatch.setattr(pam_password, "change_password", lambda *args: changed.append(args))
+
+    old = _session("tok")
+    fresh_store.insert(old)
+    fresh_store.insert(_session("other"))
+    fresh_store.insert(_session("bob", username="bob"))
+    CryptoStorage(old).write("notes/a.txt", b"secret")
+
+    def change(current: str, new: str) -> dict[str, Any]:
+        params = {
+            "session_token": "tok",
+            "username": "alice",
+            "current_password": current,
+            "new_password": new,
+        }
+        return _rpc("auth/change_password", params)["result"]
+
+    assert change("wrong", "new-pass") == {"success": False, "error": "Incorrect password"}
+    assert changed == []
+
+    result = change("old-pass", "new-pass")
+    assert result["success"] is True and result["username"] == "alice"
+    assert changed == [("alice", "old-pass", "new-pass")]
+    # Only the rotated session is left for alice, keyed from the new password
+    assert auth.get_session("tok") is None and auth.get_session("other") is None
+    assert auth.get_session("bob") is not None
+    rotated = auth.get_session(result["session_token"])
+    assert rotated is not None
+    assert rotated.key_material == auth.derive_encryption_key("alice", "new-pass")
+    assert CryptoStorage(rotated).read("notes/a.txt") == b"secret"
+
+
+def test_auth_change_password_refused_by_pam(
+    fresh_store: auth.SessionStore, monkeypatch: pytest.MonkeyPatch, tmp_path: Path
+) -> None:
+    from reos import pam_password
+
+    def refuse(*_args: str) -> None:
+        raise pam_password.PamError("BAD PASSWORD: it is too short")
+
+    monkeypatch.setattr(auth.Path, "home", lambda: tmp_path)
+    monkeypatch.setattr(auth, "authenticate_pam", lambda u, p: True)
+    monkeypatch.setattr(pam_password, "change_password", refuse)
+    fresh_store.insert(_session("tok"))
+
+    params = {
+        "session_token": "tok",
+        "username": "alice",
+        "current_password": "old-pass",
+        "new_password": "x",
+    }
+    result = _rpc("auth/change_password", params)["result"]
+    assert result == {"success": False, "error": "BAD PASSWORD: it is too short"}
+    assert auth.get_session("tok") is not None
+
+
+def test_pam_prompt_answers() -> None:
+    from reos.pam_password import answer_for
+
+    assert answer_for("(current) UNIX password: ", "old", "new") == "old"
+    assert answer_for("Current password: ", "old", "new") == "old"
+    assert answer_for("New password: ", "old", "new") == "new"
+    assert answer_for("Retype new password: ", "old", "new") == "new"
+
+
+def test_auth_groups(monkeypatch: pytest.MonkeyPatch) -> None:
+    from reos import linux_tools
+
+    asked: list[str] = []
+
+    def fake_groups(username: str) -> list[str]:
+        asked.append(username)
+        return ["alice", "wheel"]
+
+    monkeypatch.setattr(linux_tools, "_get_user_groups", fake_groups)
+    resp = _rpc("auth/groups", {"username": "alice"})
+    assert resp["result"] == {"groups": ["alice", "wheel"]}
+    assert asked == ["alice"]
+
+
+def test_auth_groups_rejects_bad_usernames() -> None:
+    assert _rpc("auth/groups", {"username": "alice; id"})["error"]["code"] == -32602
+    assert _rpc("auth/groups", {})["error"]["code"] == -32602
+
+
+def test_auth_verify_password(
+    fresh_store: auth.SessionStore, monkeypatch: pytest.MonkeyPatch
+) -> None:
+    checked: list[tuple[str, str]] = []
+
+    def fake_pam(username: str, password: str) -> bool:
+        checked.append((username, password))
+        return password == "hunter2"
+
+    monkeypatch.setattr(auth, "authenticate_pam", fake_pam)
+    fresh_store.insert(_session("tok"))
+
+    def verify(username: str, password: str, token: str = "tok") -> dict[str, Any]:
+        params = {"session_token": token, "username": username, "password": password}
+        return _rpc("auth/verify_password", params)["result"]
+
+    assert verify("alice", "hunter2") == {"success": True, "username": "alice"}
+    assert verify("alice", "wrong")["success"] is False
+    # Someone else's session never gets as far as PAM
+    assert verify("bob", "hunter2")["error"] == "Invalid session"
+    # One this kernel let expire (the shell kept it locked) still unlocks
+    assert verify("alice", "hunter2", token="expired")["success"] is True
+    assert checked == [("alice", "hunter2"), ("alice", "wrong"), ("alice", "hunter2")]
+    # The session and its key are untouched
+    session = auth.get_session("tok")
+    assert session is not None and session.key_material == bytes(range(32))
+
+
+def test_auth_verify_password_step_up(
+    fresh_store: auth.SessionStore, monkeypatch: pytest.MonkeyPatch
+) -> None:
+    import reos.ui_rpc_server as ui
+
+    audited: list[dict[str, Any]] = []
+    monkeypatch.setattr(auth, "authenticate_pam", lambda username, password: False)
+    monkeypatch.setattr(ui, "audit_log", lambda event, details: audited.append(details))
+    fresh_store.insert(_session("tok"))
+
+    params = {"session_token": "tok", "username": "alice", "password": "x"}
+    resp = _rpc("auth/verify_password", {**params, "purpose": "step_up"})
+    assert resp["result"]["success"] is False
+    assert audited[-1]["check"] == "step_up"
+
+    assert _rpc("auth/verify_password", {**params, "purpose": "sudo"})["error"]["code"] == -32602
+
+
+def test_auth_validate_by_token_hash(fresh_store: auth.SessionStore) -> None:
+    # The hash the Rust shell persists, pinned in session_vault.rs as well
+    token_sha256 = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    assert _rpc("auth/validate", {"session_token_sha256": token_sha256})["result"] == {
+        "valid": False
+    }
+
+    fresh_store.insert(_session("abc"))
+    resp = _rpc("auth/validate", {"session_token_sha256": token_sha256})
+    assert resp["result"] == {"valid": True, "username": "alice"}
+    assert _rpc("auth/validate", {"session_token": "abc"})["result"]["valid"] is True
+
+    assert _rpc("auth/validate", {"session_token_sha256": "abc"})["error"]["code"] == -32602
+
+
+def test_auth_login_os_session(
+    fresh_store: auth.SessionStore, monkeypatch: pytest.MonkeyPatch, tmp_path: Path
+) -> None:
+    import subprocess
+
+    monkeypatch.setattr(auth.Path, "home", lambda: tmp_path)
+    owners = {
+        "2": "Name=alice\nActive=yes\nRemote=no\n",
+        "3": "Name=alice\nActive=yes\nRemote=yes\n",
+    }
+
+    def fake_run(command: list[str], **_kwargs: Any) -> subprocess.CompletedProcess[str]:
+        assert command[:2] == ["loginctl", "show-session"]
+        stdout = owners.get(command[2])
+        return subprocess.CompletedProcess(command, 0 if stdout else 1, stdout or "", "")
+
+    monkeypatch.setattr(auth.subprocess, "run", fake_run)
+
+    def login(username: str, os_session_id: str) -> dict[str, Any]:
+        params = {"username": username, "os_session_id": os_session_id, "passphrase": "pass"}
+        return _rpc("auth/login_os_session", params)["result"]
+
+    result = login("alice", "2")
+    assert result["success"] is True
+    session = auth.get_session(result["session_token"])
+    assert session is not None
+    assert session.key_material == auth.derive_encryption_key("alice", "pass")
+
+    # Someone else's, a remote or an unknown session is refused
+    assert login("bob", "2")["success"] is False
+    assert login("alice", "3")["success"] is False
+    assert login("alice", "9")["success"] is False
+    assert login("alice", "2 --all")["success"] is False
+    assert len(fresh_store.snapshot()) == 1
+
+
+def test_auth_login_verified(
+    fresh_store: auth.SessionStore, monkeypatch: pytest.MonkeyPatch, tmp_path: Path
+) -> None:
+    monkeypatch.setattr(auth.Path, "home", lambda: tmp_path)
+    params = {"username": "alice", "password": "hunter2", "verified_by": "password_file"}
+
+    result = _rpc("auth/login_verified", params)["result"]
+    assert result["success"] is True
+    session = auth.get_session(result["session_token"])
+    assert session is not None
+    assert session.key_material == auth.derive_encryption_key("alice", "hunter2")
+
+    resp = _rpc("auth/login_verified", {**params, "verified_by": "anyone"})
+    assert resp["error"]["code"] == -32602
+    assert _rpc("auth/login_verified", {**params, "password": ""})["result"]["success"] is False
+
+
+def _shell_rpc(
+    method: str, params: dict[str, Any], monkeypatch: pytest.MonkeyPatch
+) -> dict[str, Any]:
+    """Send `method` through the kernel's entry point, as the shell would."""
+    import hmac
+    import time
+    from hashlib import sha256
+
+    import reos.ui_rpc_server as ui
+    from reos.session import shell_claims_message
+
+    key = bytes(range(32))
+    monkeypatch.setattr(ui, "_session_key", key)
+    iat = int(time.time())
+    sig = hmac.new(key, shell_claims_message(method, iat), sha256).hexdigest()
+    resp = ui._handle_with_session(
+        None,  # type: ignore[arg-type]
+        {
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": {**params, "__shell": {"iat": iat, "sig": sig}},
+        },
+    )
+    assert resp is not None
+    return resp
+
+
+def test_shell_only_methods_need_the_shell_proof(
+    fresh_store: auth.SessionStore, monkeypatch: pytest.MonkeyPatch, tmp_path: Path
+) -> None:
+    import reos.ui_rpc_server as ui
+
+    monkeypatch.setattr(auth.Path, "home", lambda: tmp_path)
+    monkeypatch.setattr(ui, "_session_key", bytes(range(32)))
+    params = {"username": "alice", "password": "hunter2", "verified_by": "password_file"}
+
+    def unsigned(method: str, params: dict[str, Any]) -> dict[str, Any]:
+        req = {"jsonrpc": "2.0", "id": 1, "method": method, "params": params}
+        resp = ui._handle_with_session(None, req)  # type: ignore[arg-type]
+        assert resp is not None
+        return resp
+
+    assert unsigned("auth/login_verified", params)["error"]["code"] == -32003
+    forged = {**params, "__shell": {"iat": 0, "sig": "00"}}
+    assert unsigned("auth/login_verified", forged)["error"]["code"] == -32003
+    assert unsigned("auth/revoke_user", {"username": "alice"})["error"]["code"] == -32003
+    assert fresh_store.snapshot() == []
+
+    # Signed by the shell, both go through
+    result = _shell_rpc("auth/login_verified", params, monkeypatch)["result"]
+    assert result["success"] is True
+    assert _shell_rpc("auth/revoke_user", {"username": "alice"}, monkeypatch)["result"] == {
+        "revoked": 1
+    }
+
+
+def test_auth_methods_refuse_window_sessions(
+    fresh_store: auth.SessionStore, monkeypatch: pytest.MonkeyPatch
+) -> None:
+    import reos.ui_rpc_server as ui
+
+    monkeypatch.setattr(ui, "_session_key", bytes(range(32)))
+    fresh_store.insert(_session("a1"))
+    claims = {"username": "alice", "session_id": "a1", "role": "admin"}
+    for method, params in [
+        ("auth/revoke_user", {"username": "alice"}),
+        ("auth/logout", {"session_token": "a1"}),
+    ]:
+        req = {
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": {**params, "__session": claims},
+        }
+        resp = ui._handle_with_session(None, req)  # type: ignore[arg-type]
+        assert resp is not None and resp["error"]["code"] == -32003
+    assert auth.get_session("a1") is not None
+
+
+def test_auth_login_verified_is_rate_limited(
+    fresh_store: auth.SessionStore, monkeypatch: pytest.MonkeyPatch, tmp_path: Path
+) -> None:
+    monkeypatch.setattr(auth.Path, "home", lambda: tmp_path)
+    params = {"username": "alice", "password": "hunter2", "verified_by": "password_file"}
+    results = [_rpc("auth/login_verified", params)["result"] for _ in range(6)]
+    assert all(r["success"] for r in results[:5])
+    assert results[5]["success"] is False and len(fresh_store.snapshot()) == 5
+
+
+def test_auth_login_ldap(
+    fresh_store: auth.SessionStore, monkeypatch: pytest.MonkeyPatch, tmp_path: Path
+) -> None:
+    from reos import ldap_auth
+
+    monkeypatch.setattr(auth.Path, "home", lambda: tmp_path)
+    binds: list[tuple[Any, ...]] = []
+
+    def fake_bind(*args: Any) -> bool:
+        binds.append(args)
+        return args[3] == "hunter2"
+
+    monkeypatch.setattr(ldap_auth, "bind", fake_bind)
+    params = {
+        "username": "alice",
+        "password": "hunter2",
+        "url": "ldaps://ldap.example.org",
+        "user_dn": "uid={username},ou=people,dc=example,dc=org",
+    }
+
+    result = _rpc("auth/login_ldap", params)["result"]
+    assert result["success"] is True and auth.get_session(result["session_token"]) is not None
+    assert binds[0] == (params["url"], params["user_dn"], "alice", "hunter2", None)
+    failed = _rpc("auth/login_ldap", {**params, "password": "wrong"})["result"]
+    assert failed == {"success": False, "error": "Invalid username or password"}
+
+
+def test_ldap_bind_refuses_unsafe_requests() -> None:
+    from reos import ldap_auth
+
+    dn = "uid={username},dc=example,dc=org"
+    # An empty password would make an anonymous bind succeed
+    assert ldap_auth.bind("ldaps://ldap.example.org", dn, "alice", "", None) is False
+    with pytest.raises(ldap_auth.LdapError):
+        ldap_auth.bind("http://ldap.example.org", dn, "alice", "hunter2", None)
+    with pytest.raises(ldap_auth.LdapError):
+        ldap_auth.bind("ldaps://ldap.example.org", "dc=example,dc=org", "alice", "hunter2", None)
+
+
+def test_auth_login_verified_by_oidc(
+    fresh_store: auth.SessionStore, monkeypatch: pytest.MonkeyPatch, tmp_path: Path
+) -> None:
+    import reos.ui_rpc_server as ui
+
+    audited: list[dict[str, Any]] = []
+    monkeypatch.setattr(auth.Path, "home", lambda: tmp_path)
+    monkeypatch.setattr(ui, "audit_log", lambda event, details: audited.append(details))
+    params = {"username": "alice", "password": "passphrase", "verified_by": "oidc"}
+
+    # The identity provider's subject is required, and audited
+    assert _rpc("auth/login_verified", params)["error"]["code"] == -32602
+    result = _rpc("auth/login_verified", {**params, "subject": "sub-123"})["result"]
+    assert result["success"] is True
+    assert audited == [{"username": "alice", "verified_by": "oidc", "subject": "sub-123"}]
+
+
+def test_auth_revoke_user(fresh_store: auth.SessionStore) -> None:
+    fresh_store.insert(_session("a1"))
+    fresh_store.insert(_session("a2"))
+    fresh_store.insert(_session("b1", username="bob"))
+    kept = auth.get_session("a1")
+
+    assert _rpc("auth/revoke_user", {"username": "alice"})["result"] == {"revoked": 2}
+    assert auth.get_session("a1") is None and auth.get_session("a2") is None
+    assert auth.get_session("b1") is not None
+    # Key material is zeroized, not just dropped
+    assert kept is not None and kept.key_material == bytes(32)
+    assert _rpc("auth/revoke_user", {"username": "alice"})["result"] == {"revoked": 0}
@@ -0,0 +1,53 @@
+"""Tests for out-of-band transfer of large results to the Rust shell."""
+
+from __future__ import annotations
+
+import json
+import os
+from pathlib import Path
+
+import pytest
+from cryptography.hazmat.primitives.ciphers.aead import ChaCha20Poly1305
+
+from reos import blob_transfer
+
+
+@pytest.fixture(autouse=True)
+def _reset():
+    yield
+    blob_transfer.release_previous()
+    blob_transfer.configure(None)
+
+
+def _open(sealed: bytes, key: bytes, size: int) -> bytes:
+    aead = ChaCha20Poly1305(key)
+    step = blob_transfer.SEGMENT_SIZE + 16
+    return b"".join(
+        aead.decrypt(blob_transfer._nonce(index), sealed[offset : offset + step], None)
+        for index, offset in enumerate(range(0, len(sealed), step))
+    )[:size]
+
+
+def test_small_results_and_no_offer_stay_inline(tmp_path: Path):
+    resp = {"jsonrpc": "2.0", "id": 1, "result": {"ok": True}}
+    assert blob_transfer.offload(resp) is resp
+    assert blob_transfer.configure({"threshold": 1, "dir": str(tmp_path)})
+    assert blob_transfer.offload(resp) is resp
+    assert not blob_transfer.configure({"threshold": 1, "dir": str(tmp_path / "missing")})
+
+
+def test_large_result_sealed_out_of_band(tmp_path: Path, monkeypatch):
+    # The file path works everywhere; memfd is Linux-only
+    monkeypatch.delattr(os, "memfd_create", raising=False)
+    assert blob_transfer.configure({"threshold": 0, "dir": str(tmp_path)})
+    result = {"rows": ["x" * 1000] * 3000}
+    resp = blob_transfer.offload({"jsonrpc": "2.0", "id": 2, "result": result})
+    blob = resp["result"]["__blob"]
+    assert blob["kind"] == "file" and blob["encoding"] == "json"
+    sealed = Path(blob["path"]).read_bytes()
+    assert len(sealed) > blob_transfer.SEGMENT_SIZE + 16
+    data = _open(sealed, bytes.fromhex(blob["key"]), blob["size"])
+    assert json.loads(data) == result
+
+    blob_transfer.release_previous()
+    assert not Path(blob["path"]).exists()
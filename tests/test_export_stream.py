@@ -0,0 +1,27 @@
+"""Tests for chunked results behind the export_file command."""
+
+from __future__ import annotations
+
+import pytest
+
+from reos.export_stream import ExportError, ExportStore, serialize
+
+
+def test_text_results_are_written_as_is():
+    assert serialize("# Notes\n") == b"# Notes\n"
+    assert serialize({"a": "é"}) == b'{\n  "a": "\xc3\xa9"\n}'
+
+
+def test_chunks_cover_the_result_then_end():
+    store = ExportStore()
+    begun = store.begin(b"0123456789")
+    export_id = begun["export_id"]
+    assert begun["size"] == 10
+    assert store.chunk(export_id, 0, 4) == b"0123"
+    assert store.chunk(export_id, 8, 4) == b"89"
+    assert store.chunk(export_id, 10, 4) == b""
+    with pytest.raises(ExportError):
+        store.chunk(export_id, 11, 4)
+    store.end(export_id)
+    with pytest.raises(ExportError):
+        store.chunk(export_id, 0, 4)
@@ -0,0 +1,65 @@
+"""Tests for streamed file ingestion behind the file_ingest command."""
+
+from __future__ import annotations
+
+import hashlib
+from dataclasses import dataclass
+from pathlib import Path
+
+import pytest
+
+from reos import ingest
+from reos.crypto_storage import CryptoStorage
+from reos.ingest import IngestError, IngestManager
+
+
+@dataclass
+class _Session:
+    username: str
+    key_material: bytes
+    root: Path
+
+    def get_user_data_root(self) -> Path:
+        return self.root
+
+
+def _storage(tmp_path: Path, username: str = "alice") -> CryptoStorage:
+    session = _Session(username, bytes(range(32)), tmp_path / username)
+    return CryptoStorage(session)  # type: ignore[arg-type]
+
+
+def test_chunks_are_stored_encrypted_and_read_back(tmp_path: Path):
+    storage = _storage(tmp_path)
+    manager = IngestManager()
+    data = b"secret notes " * 100
+    upload_id = manager.begin(storage, name="/home/alice/notes.md", size=len(data))
+    assert manager.chunk(storage, upload_id, data[:600]) == 600
+    assert manager.chunk(storage, upload_id, data[600:]) == len(data)
+    result = manager.finish(storage, upload_id, hashlib.sha256(data).hexdigest())
+
+    assert result == {"handle": upload_id, "name": "notes.md", "size": len(data)}
+    assert ingest.read(storage, upload_id) == data
+    for part in (tmp_path / "alice" / "ingest" / upload_id).iterdir():
+        assert b"secret notes" not in part.read_bytes()
+
+
+def test_hash_mismatch_discards_upload(tmp_path: Path):
+    storage = _storage(tmp_path)
+    manager = IngestManager()
+    upload_id = manager.begin(storage, name="a.txt", size=3)
+    manager.chunk(storage, upload_id, b"abc")
+    with pytest.raises(IngestError):
+        manager.finish(storage, upload_id, hashlib.sha256(b"abd").hexdigest())
+    assert storage.list_files("ingest") == []
+    with pytest.raises(IngestError):
+        manager.chunk(storage, upload_id, b"x")
+
+
+def test_uploads_belong_to_their_user(tmp_path: Path):
+    alice, bob = _storage(tmp_path, "alice"), _storage(tmp_path, "bob")
+    manager = IngestManager()
+    upload_id = manager.begin(alice, name="a.txt", size=2)
+    with pytest.raises(IngestError):
+        manager.chunk(bob, upload_id, b"hi")
+    with pytest.raises(IngestError):
+        manager.chunk(alice, upload_id, b"too long")
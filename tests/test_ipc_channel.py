@@ -0,0 +1,61 @@
+"""Tests for the encrypted stdio channel shared with the Rust shell."""
+
+from __future__ import annotations
+
+import json
+
+import pytest
+from cryptography.hazmat.primitives.asymmetric.x25519 import (
+    X25519PrivateKey,
+    X25519PublicKey,
+)
+from cryptography.hazmat.primitives.serialization import Encoding, PublicFormat
+
+from reos.ipc_channel import (
+    PROTOCOL,
+    ChannelError,
+    SecureChannel,
+    accept,
+    derive_keys,
+    parse_hello,
+)
+
+
+def test_matches_rust_test_vector():
+    """Same vector as ipc.rs test_shared_vector."""
+    to_kernel, to_rust = derive_keys(bytes([1] * 32), bytes([2] * 32), bytes([3] * 32))
+    assert to_kernel.hex() == "d28833923e1ced9d90511bf3e5f4725dcff9b57aa97a9ca4da9498750f3b9257"
+    assert to_rust.hex() == "2ee84077c81cab7e0ea8037948f0570664ec15c874db2e95668ba7741f666a79"
+    rust = SecureChannel(recv_key=to_rust, send_key=to_kernel)
+    assert rust.seal("ping") == "J4GQ+TYk3b9HCIFDDWVY3FcwteM="
+
+
+def _handshake() -> tuple[SecureChannel, SecureChannel]:
+    private = X25519PrivateKey.generate()
+    rust_public = private.public_key().public_bytes(Encoding.Raw, PublicFormat.Raw)
+    hello = json.dumps({"channel": PROTOCOL, "e": rust_public.hex()})
+    kernel, reply = accept(parse_hello(hello))
+    kernel_public = bytes.fromhex(json.loads(reply)["e"])
+    shared = private.exchange(X25519PublicKey.from_public_bytes(kernel_public))
+    to_kernel, to_rust = derive_keys(shared, rust_public, kernel_public)
+    return SecureChannel(recv_key=to_rust, send_key=to_kernel), kernel
+
+
+def test_round_trip():
+    rust, kernel = _handshake()
+    assert kernel.open(rust.seal('{"method":"ping"}')) == '{"method":"ping"}'
+    assert rust.open(kernel.seal('{"result":true}')) == '{"result":true}'
+
+
+def test_replayed_frame_rejected():
+    rust, kernel = _handshake()
+    frame = rust.seal("first")
+    kernel.open(frame)
+    with pytest.raises(ChannelError):
+        kernel.open(frame)
+
+
+def test_parse_hello_rejects_non_handshake():
+    assert parse_hello('{"jsonrpc":"2.0","id":1,"method":"ping"}') is None
+    assert parse_hello(json.dumps({"channel": PROTOCOL, "e": "00"})) is None
+    assert parse_hello("not json") is None
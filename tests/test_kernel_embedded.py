@@ -0,0 +1,48 @@
+"""Tests for the kernel embedded in the shell's process (embedded_start/embedded_handle)."""
+
+from __future__ import annotations
+
+import json
+
+import pytest
+
+
+def _initialize(key: str) -> str:
+    return json.dumps(
+        {"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"session_key": key}}
+    )
+
+
+def test_embedded_requests_and_pushes(
+    monkeypatch: pytest.MonkeyPatch, isolated_db_singleton: object
+) -> None:
+    import reos.ui_rpc_server as ui
+
+    monkeypatch.setattr(ui, "_embedded_db", None)
+    monkeypatch.setattr(ui, "_push_sink", None)
+    monkeypatch.setattr(ui, "_session_key", None)
+    monkeypatch.setattr(ui, "_channel", None)
+    pushed: list[str] = []
+    ui.embedded_start(pushed.append)
+
+    first = ui.embedded_handle(_initialize("aa" * 32))
+    assert first is not None and "result" in json.loads(first)
+    pong = ui.embedded_handle(json.dumps({"jsonrpc": "2.0", "id": 2, "method": "ping"}))
+    assert pong is not None and json.loads(pong)["id"] == 2
+    assert ui.embedded_handle(json.dumps({"jsonrpc": "2.0", "method": "ping"})) is None
+
+    ui.push_notification("Done", "all of it")
+    assert json.loads(pushed[-1])["params"]["tag"] == "notify"
+
+    # A new start in the same interpreter takes a new key
+    ui.embedded_start(pushed.append)
+    again = ui.embedded_handle(_initialize("bb" * 32))
+    assert again is not None and "result" in json.loads(again)
+
+
+def test_embedded_needs_start(monkeypatch: pytest.MonkeyPatch) -> None:
+    import reos.ui_rpc_server as ui
+
+    monkeypatch.setattr(ui, "_embedded_db", None)
+    with pytest.raises(RuntimeError):
+        ui.embedded_handle(json.dumps({"jsonrpc": "2.0", "id": 1, "method": "ping"}))
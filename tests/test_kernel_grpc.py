@@ -0,0 +1,133 @@
+"""Tests for the remote kernel's gRPC transport (REOS_KERNEL_GRPC_LISTEN)."""
+
+from __future__ import annotations
+
+import base64
+import json
+import queue
+import threading
+from pathlib import Path
+from typing import Any
+
+import pytest
+
+grpc = pytest.importorskip("grpc")
+
+TOKEN = "t" * 32
+
+
+def test_frames_round_trip() -> None:
+    from reos.kernel_grpc import FrameError, decode_frame, encode_frame
+
+    hello = json.dumps({"channel": "reos-ipc-v1", "e": "00" * 32})
+    assert decode_frame(encode_frame(hello)) == hello
+
+    sealed = base64.b64encode(bytes(range(256))).decode()
+    assert decode_frame(encode_frame(sealed)) == sealed
+
+    for message in (
+        {"jsonrpc": "2.0", "id": 7, "method": "ping", "params": {"a": [1, "b"]}},
+        {"jsonrpc": "2.0", "id": "x", "method": "ping"},
+        {"jsonrpc": "2.0", "method": "event", "params": {"n": 1}},
+        {"jsonrpc": "2.0", "id": 7, "result": {"ok": True}},
+        {"jsonrpc": "2.0", "id": 0, "result": None},
+        {"jsonrpc": "2.0", "id": 7, "error": {"code": -32601, "message": "nope"}},
+        {"jsonrpc": "2.0", "id": 7, "error": {"code": -32099, "message": "x", "data": [1]}},
+    ):
+        assert json.loads(decode_frame(encode_frame(json.dumps(message)))) == message
+
+    with pytest.raises(FrameError):
+        encode_frame("not base64!")
+    with pytest.raises(FrameError):
+        decode_frame(b"")
+    with pytest.raises(FrameError):
+        decode_frame(b"\x0a\x05ab")
+
+
+def _connect(port: int, token: str, outbound: queue.Queue):
+    from reos.kernel_grpc import SERVICE, decode_frame, encode_frame
+
+    channel = grpc.insecure_channel(f"127.0.0.1:{port}")
+    call = channel.stream_stream(
+        f"/{SERVICE}/Connect",
+        request_serializer=encode_frame,
+        response_deserializer=decode_frame,
+    )
+
+    def frames():
+        while (line := outbound.get()) is not None:
+            yield line
+
+    return channel, call(frames(), metadata=(("authorization", f"Bearer {token}"),))
+
+
+def _handshake(outbound: queue.Queue, responses: Any) -> Any:
+    from tests.conftest import ShellChannel
+
+    channel = ShellChannel()
+    outbound.put(channel.hello)
+    channel.finish(next(responses))
+    return channel
+
+
+def test_remote_kernel_over_grpc_requires_token_and_serves_one_shell(
+    tmp_path: Path, monkeypatch: pytest.MonkeyPatch, isolated_db_singleton: object
+) -> None:
+    import reos.ui_rpc_server as ui
+    from reos import kernel_grpc
+    from reos.db import get_db
+
+    monkeypatch.setattr(ui, "_session_key", None)
+    monkeypatch.setattr(ui, "_socket_mode", False)
+    ui._shutdown.clear()
+    listener = kernel_grpc.GrpcListener("127.0.0.1", 0, None, None, TOKEN)
+    server = threading.Thread(
+        target=ui._serve_connections,
+        args=(get_db(), listener.accept, None),
+        kwargs={"silence_output": False},
+        daemon=True,
+    )
+    server.start()
+    request = {"jsonrpc": "2.0", "id": 1, "method": "initialize"}
+    request["params"] = {"session_key": "aa" * 32}
+    try:
+        rejected: queue.Queue = queue.Queue()
+        channel, responses = _connect(listener.port, "x" * 32, rejected)
+        with pytest.raises(grpc.RpcError) as denied:
+            next(responses)
+        assert denied.value.code() == grpc.StatusCode.UNAUTHENTICATED
+        rejected.put(None)
+        channel.close()
+
+        outbound: queue.Queue = queue.Queue()
+        channel, responses = _connect(listener.port, TOKEN, outbound)
+        shell = _handshake(outbound, responses)
+        outbound.put(shell.seal(request))
+        assert "result" in shell.open(next(responses))
+
+        # A second shell is turned away while the first is connected
+        second: queue.Queue = queue.Queue()
+        other, busy = _connect(listener.port, TOKEN, second)
+        with pytest.raises(grpc.RpcError) as turned_away:
+            next(busy)
+        assert turned_away.value.code() == grpc.StatusCode.UNAVAILABLE
+        second.put(None)
+        other.close()
+
+        # The kernel outlives the call: the same key gets back in
+        outbound.put(None)
+        assert list(responses) == []
+        channel.close()
+        outbound = queue.Queue()
+        channel, responses = _connect(listener.port, TOKEN, outbound)
+        shell = _handshake(outbound, responses)
+        outbound.put(shell.seal(request))
+        assert "result" in shell.open(next(responses))
+        outbound.put(shell.seal({"jsonrpc": "2.0", "method": "kernel/shutdown"}))
+        server.join(5)
+        assert not server.is_alive()
+        outbound.put(None)
+        channel.close()
+    finally:
+        ui._shutdown.set()
+        listener.close()
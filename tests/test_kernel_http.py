@@ -0,0 +1,125 @@
+"""Tests for the remote kernel's HTTP transport (REOS_KERNEL_HTTP_LISTEN)."""
+
+from __future__ import annotations
+
+import json
+import threading
+import urllib.error
+import urllib.request
+from pathlib import Path
+from typing import Any
+
+import pytest
+
+TOKEN = "t" * 32
+
+
+def _call(port: int, method: str, path: str, body: bytes | None = None, token: str = TOKEN):
+    request = urllib.request.Request(
+        f"http://127.0.0.1:{port}/kernel/{path}",
+        data=body if body is not None else (b"" if method == "POST" else None),
+        method=method,
+        headers={"Authorization": f"Bearer {token}"},
+    )
+    with urllib.request.urlopen(request, timeout=30) as response:
+        return response.status, response.read()
+
+
+def _status(port: int, method: str, path: str, token: str = TOKEN) -> int:
+    try:
+        return _call(port, method, path, token=token)[0]
+    except urllib.error.HTTPError as exc:
+        return exc.code
+
+
+def _send(port: int, connection: str, line: str) -> None:
+    _call(port, "POST", f"frames?connection={connection}", line.encode())
+
+
+def _receive(port: int, connection: str) -> str:
+    while True:
+        _, body = _call(port, "GET", f"poll?connection={connection}")
+        if body:
+            return body.decode().splitlines()[0]
+
+
+def _connect(port: int) -> tuple[str, Any]:
+    """A connection with the channel handshake done, and its shell side."""
+    from tests.conftest import ShellChannel
+
+    status, body = _call(port, "POST", "connect")
+    assert status == 200
+    connection = str(json.loads(body)["connection"])
+    shell = ShellChannel()
+    _send(port, connection, shell.hello)
+    shell.finish(_receive(port, connection))
+    return connection, shell
+
+
+def _exchange(port: int, connection: str, shell: Any, request: dict) -> dict:
+    _send(port, connection, shell.seal(request))
+    return dict(shell.open(_receive(port, connection)))
+
+
+def test_remote_kernel_over_http_requires_token_and_serves_one_shell(
+    tmp_path: Path, monkeypatch: pytest.MonkeyPatch, isolated_db_singleton: object
+) -> None:
+    import reos.ui_rpc_server as ui
+    from reos import kernel_http
+    from reos.db import get_db
+
+    monkeypatch.setattr(ui, "_session_key", None)
+    monkeypatch.setattr(ui, "_socket_mode", False)
+    ui._shutdown.clear()
+    listener = kernel_http.HttpListener("127.0.0.1", 0, None, None, TOKEN)
+    server = threading.Thread(
+        target=ui._serve_connections,
+        args=(get_db(), listener.accept, None),
+        kwargs={"silence_output": False},
+        daemon=True,
+    )
+    server.start()
+    port = listener.port
+    try:
+        assert _status(port, "POST", "connect", token="x" * 32) == 401
+
+        connection, shell = _connect(port)
+        request = {"jsonrpc": "2.0", "id": 1, "method": "initialize"}
+        request["params"] = {"session_key": "aa" * 32}
+        assert "result" in _exchange(port, connection, shell, request)
+
+        # A second shell is turned away while the first is connected
+        assert _status(port, "POST", "connect") == 503
+        assert _status(port, "GET", "poll?connection=nope") == 410
+
+        # The kernel outlives the connection: the same key gets back in
+        assert _status(port, "POST", f"close?connection={connection}") == 204
+        assert _status(port, "GET", f"poll?connection={connection}") == 410
+        connection, shell = _connect(port)
+        assert "result" in _exchange(port, connection, shell, request)
+        _send(port, connection, shell.seal({"jsonrpc": "2.0", "method": "kernel/shutdown"}))
+        server.join(5)
+        assert not server.is_alive()
+    finally:
+        ui._shutdown.set()
+        listener.close()
+
+
+def test_plain_http_only_on_loopback(tmp_path: Path, monkeypatch: pytest.MonkeyPatch) -> None:
+    import reos.ui_rpc_server as ui
+
+    token_file = tmp_path / "token"
+    token_file.write_text(TOKEN, encoding="utf-8")
+    monkeypatch.setenv("REOS_KERNEL_HTTP_TOKEN_FILE", str(token_file))
+    monkeypatch.delenv("REOS_KERNEL_HTTP_CERT", raising=False)
+    monkeypatch.delenv("REOS_KERNEL_HTTP_KEY", raising=False)
+
+    assert ui._remote_credentials("REOS_KERNEL_HTTP", "127.0.0.1:8443", False) == (
+        None,
+        None,
+        TOKEN,
+    )
+    with pytest.raises(SystemExit):
+        ui._remote_credentials("REOS_KERNEL_HTTP", "0.0.0.0:8443", False)
+    with pytest.raises(SystemExit):
+        ui._remote_credentials("REOS_KERNEL_WS", "127.0.0.1:8443", True)
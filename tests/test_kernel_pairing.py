@@ -0,0 +1,107 @@
+"""Tests for pairing shells with a remote kernel (mutual TLS)."""
+
+from __future__ import annotations
+
+import datetime
+import http.client
+import ssl
+from pathlib import Path
+
+import pytest
+from cryptography import x509
+from cryptography.hazmat.primitives import hashes, serialization
+from cryptography.hazmat.primitives.asymmetric import ec
+from cryptography.x509.oid import NameOID
+
+TOKEN = "t" * 32
+
+
+def _certificate(tmp_path: Path, name: str, dns: str | None = None) -> tuple[Path, Path]:
+    key = ec.generate_private_key(ec.SECP256R1())
+    subject = x509.Name([x509.NameAttribute(NameOID.COMMON_NAME, name)])
+    now = datetime.datetime.now(datetime.UTC)
+    builder = (
+        x509.CertificateBuilder()
+        .subject_name(subject)
+        .issuer_name(subject)
+        .public_key(key.public_key())
+        .serial_number(x509.random_serial_number())
+        .not_valid_before(now - datetime.timedelta(minutes=1))
+        .not_valid_after(now + datetime.timedelta(hours=1))
+    )
+    if dns:
+        builder = builder.add_extension(
+            x509.SubjectAlternativeName([x509.DNSName(dns)]), critical=False
+        )
+    cert = builder.sign(key, hashes.SHA256())
+    certfile = tmp_path / f"{name}.pem"
+    keyfile = tmp_path / f"{name}.key"
+    certfile.write_bytes(cert.public_bytes(serialization.Encoding.PEM))
+    keyfile.write_bytes(
+        key.private_bytes(
+            serialization.Encoding.PEM,
+            serialization.PrivateFormat.PKCS8,
+            serialization.NoEncryption(),
+        )
+    )
+    return certfile, keyfile
+
+
+def test_add_list_remove(tmp_path: Path, monkeypatch: pytest.MonkeyPatch) -> None:
+    from reos import kernel_pairing
+
+    clients = tmp_path / "paired-clients.pem"
+    monkeypatch.setenv("REOS_KERNEL_PAIRED_CLIENTS", str(clients))
+    assert kernel_pairing.paired_clients_file() is None
+
+    certfile, _ = _certificate(tmp_path, "laptop")
+    cert = x509.load_pem_x509_certificate(certfile.read_bytes())
+    print_ = kernel_pairing.fingerprint(cert)
+    assert kernel_pairing.main(["add", str(certfile)]) == 0
+    assert not kernel_pairing.add(cert)
+    assert kernel_pairing.paired_clients_file() == str(clients)
+    assert clients.stat().st_mode & 0o777 == 0o600
+
+    other, _ = _certificate(tmp_path, "desktop")
+    assert kernel_pairing.add(x509.load_pem_x509_certificate(other.read_bytes()))
+    assert len(kernel_pairing.load_paired()) == 2
+
+    colons = ":".join(print_[i : i + 2] for i in range(0, len(print_), 2)).upper()
+    assert kernel_pairing.remove(colons)
+    assert kernel_pairing.main(["remove", print_]) == 1
+    assert print_ not in [kernel_pairing.fingerprint(c) for c in kernel_pairing.load_paired()]
+    assert len(kernel_pairing.load_paired()) == 1
+
+
+def test_only_paired_shells_complete_the_handshake(tmp_path: Path) -> None:
+    from reos import kernel_http, kernel_pairing
+
+    server_cert, server_key = _certificate(tmp_path, "server", dns="localhost")
+    paired_cert, paired_key = _certificate(tmp_path, "paired")
+    stranger_cert, stranger_key = _certificate(tmp_path, "stranger")
+    clients = tmp_path / "paired-clients.pem"
+    kernel_pairing.add(x509.load_pem_x509_certificate(paired_cert.read_bytes()), clients)
+
+    listener = kernel_http.HttpListener(
+        "127.0.0.1", 0, str(server_cert), str(server_key), TOKEN, clients=str(clients)
+    )
+
+    def connect(identity: tuple[Path, Path] | None) -> int:
+        context = ssl.create_default_context(cafile=str(server_cert))
+        if identity:
+            context.load_cert_chain(*map(str, identity))
+        conn = http.client.HTTPSConnection("localhost", listener.port, context=context)
+        try:
+            headers = {"Authorization": f"Bearer {TOKEN}"}
+            conn.request("GET", "/poll?connection=none", headers=headers)
+            return conn.getresponse().status
+        finally:
+            conn.close()
+
+    try:
+        assert connect((paired_cert, paired_key)) == 410
+        for identity in (None, (stranger_cert, stranger_key)):
+            with pytest.raises((ssl.SSLError, ConnectionError)):
+                connect(identity)
+    finally:
+        listener.close()
@@ -0,0 +1,99 @@
+"""Tests for the kernel's Windows named pipe transport (REOS_KERNEL_PIPE)."""
+
+from __future__ import annotations
+
+import os
+import secrets
+import sys
+import threading
+import time
+from pathlib import Path
+
+import pytest
+
+pytestmark = pytest.mark.skipif(sys.platform != "win32", reason="Windows named pipes only")
+
+
+def _open(name: str):
+    for _ in range(100):
+        try:
+            return open(name, "r+b", buffering=0)
+        except FileNotFoundError:
+            time.sleep(0.05)
+    raise AssertionError(f"{name} never appeared")
+
+
+def _read_line(pipe) -> str:
+    line = b""
+    while not line.endswith(b"\n"):
+        chunk = pipe.read(1)
+        if not chunk:
+            break
+        line += chunk
+    return line.decode()
+
+
+def _call(name: str, request: dict) -> dict | None:
+    from tests.conftest import ShellChannel
+
+    with _open(name) as pipe:
+        shell = ShellChannel()
+        pipe.write((shell.hello + "\n").encode())
+        shell.finish(_read_line(pipe))
+        pipe.write((shell.seal(request) + "\n").encode())
+        if "id" not in request:
+            return None
+        line = _read_line(pipe)
+        return shell.open(line) if line else None
+
+
+def _initialize(key: str) -> dict:
+    return {"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"session_key": key}}
+
+
+def test_pipe_reconnect_requires_same_key(
+    tmp_path: Path, monkeypatch: pytest.MonkeyPatch, isolated_db_singleton: object
+) -> None:
+    import reos.ui_rpc_server as ui
+
+    monkeypatch.setattr(ui, "_session_key", None)
+    monkeypatch.setattr(ui, "_silence_output", lambda: None)
+    monkeypatch.setattr(ui, "_socket_mode", False)
+    ui._shutdown.clear()
+    name = rf"\\.\pipe\reos-kernel-test-{secrets.token_hex(8)}"
+    server = threading.Thread(target=ui.run_pipe_server, args=(name, 10), daemon=True)
+    server.start()
+
+    first = _call(name, _initialize("aa" * 32))
+    assert first is not None and first["result"]["pid"] == os.getpid()
+    refused = _call(name, _initialize("bb" * 32))
+    assert refused is not None and refused["error"]["code"] == -32003
+    again = _call(name, _initialize("aa" * 32))
+    assert again is not None and "result" in again
+
+    _call(name, {"jsonrpc": "2.0", "method": "kernel/shutdown"})
+    server.join(5)
+    assert not server.is_alive()
+
+
+def test_pipe_name_is_first_instance_only() -> None:
+    from reos import kernel_pipe
+
+    name = rf"\\.\pipe\reos-kernel-test-{secrets.token_hex(8)}"
+    listener = kernel_pipe.PipeListener(name)
+    try:
+        # Someone else's listener on the same name can't be created
+        with pytest.raises(OSError):
+            kernel_pipe.PipeListener(name)
+        with pytest.raises(TimeoutError):
+            listener.accept(0.1)
+    finally:
+        listener.close()
+
+
+def test_owner_only_sddl() -> None:
+    from reos import kernel_pipe
+
+    sddl = kernel_pipe.owner_only_sddl()
+    assert sddl.startswith("D:P(A;;GA;;;S-1-")
+    assert sddl.count("(") == 1
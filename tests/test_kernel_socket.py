@@ -0,0 +1,109 @@
+"""Tests for the kernel's Unix socket transport (REOS_KERNEL_SOCKET)."""
+
+from __future__ import annotations
+
+import json
+import os
+import socket
+import stat
+import sys
+import threading
+from pathlib import Path
+
+import pytest
+
+pytestmark = pytest.mark.skipif(sys.platform == "win32", reason="Unix sockets only")
+
+
+def _call(path: Path, request: dict) -> dict | None:
+    from tests.conftest import ShellChannel
+
+    with socket.socket(socket.AF_UNIX, socket.SOCK_STREAM) as conn:
+        conn.connect(str(path))
+        lines = conn.makefile("r")
+        shell = ShellChannel()
+        conn.sendall((shell.hello + "\n").encode())
+        shell.finish(lines.readline())
+        conn.sendall((shell.seal(request) + "\n").encode())
+        if "id" not in request:
+            return None
+        line = lines.readline()
+        return shell.open(line) if line else None
+
+
+def _start(path: Path) -> threading.Thread:
+    import reos.ui_rpc_server as ui
+
+    server = threading.Thread(target=ui.run_socket_server, args=(str(path), 10), daemon=True)
+    server.start()
+    for _ in range(100):
+        if path.exists():
+            break
+        threading.Event().wait(0.05)
+    return server
+
+
+def _shutdown(path: Path, server: threading.Thread) -> None:
+    import reos.ui_rpc_server as ui
+
+    _call(path, {"jsonrpc": "2.0", "method": "kernel/shutdown"})
+    server.join(5)
+    assert not server.is_alive()
+    assert not path.exists()
+    ui._shutdown.clear()
+
+
+def _initialize(key: str) -> dict:
+    return {"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"session_key": key}}
+
+
+def test_reconnect_requires_same_key(
+    tmp_path: Path, monkeypatch: pytest.MonkeyPatch, isolated_db_singleton: object
+) -> None:
+    import reos.ui_rpc_server as ui
+
+    monkeypatch.setattr(ui, "_session_key", None)
+    monkeypatch.setattr(ui, "_silence_output", lambda: None)
+    monkeypatch.setattr(ui, "_socket_mode", False)
+    ui._shutdown.clear()
+    path = tmp_path / "kernel.sock"
+    server = _start(path)
+    assert stat.S_IMODE(os.stat(path).st_mode) == 0o600
+
+    first = _call(path, _initialize("aa" * 32))
+    assert first is not None and first["result"]["pid"] == os.getpid()
+    # A later connection: the kernel is still there, but only for its key
+    refused = _call(path, _initialize("bb" * 32))
+    assert refused is not None and refused["error"]["code"] == -32003
+    again = _call(path, _initialize("aa" * 32))
+    assert again is not None and "result" in again
+    _shutdown(path, server)
+
+
+def test_spawn_key_and_handshake_required(
+    tmp_path: Path, monkeypatch: pytest.MonkeyPatch, isolated_db_singleton: object
+) -> None:
+    import reos.ui_rpc_server as ui
+
+    monkeypatch.setattr(ui, "_session_key", None)
+    monkeypatch.setattr(ui, "_silence_output", lambda: None)
+    monkeypatch.setattr(ui, "_socket_mode", False)
+    ui._shutdown.clear()
+    # What main() reads from stdin when the shell spawned us
+    ui._take_spawn_key("cc" * 32 + "\n")
+    path = tmp_path / "kernel.sock"
+    server = _start(path)
+
+    # Plain JSON without the channel handshake: the connection is dropped
+    with socket.socket(socket.AF_UNIX, socket.SOCK_STREAM) as conn:
+        conn.connect(str(path))
+        conn.sendall((json.dumps(_initialize("aa" * 32)) + "\n").encode())
+        assert conn.makefile("r").readline() == ""
+    # Connecting first doesn't install a key: only the spawn key is accepted
+    first = _call(path, _initialize("aa" * 32))
+    assert first is not None and first["error"]["code"] == -32003
+    shell = _call(path, _initialize("cc" * 32))
+    assert shell is not None and "result" in shell
+    _shutdown(path, server)
+    with pytest.raises(SystemExit):
+        ui._take_spawn_key("not hex")
@@ -0,0 +1,121 @@
+"""Tests for the remote kernel's WebSocket transport (REOS_KERNEL_WS_LISTEN)."""
+
+from __future__ import annotations
+
+import datetime
+import ssl
+import threading
+from pathlib import Path
+from typing import Any
+
+import pytest
+
+websockets = pytest.importorskip("websockets")
+
+TOKEN = "t" * 32
+
+
+def _certificate(tmp_path: Path) -> tuple[Path, Path]:
+    from cryptography import x509
+    from cryptography.hazmat.primitives import hashes, serialization
+    from cryptography.hazmat.primitives.asymmetric import ec
+    from cryptography.x509.oid import NameOID
+
+    key = ec.generate_private_key(ec.SECP256R1())
+    name = x509.Name([x509.NameAttribute(NameOID.COMMON_NAME, "localhost")])
+    now = datetime.datetime.now(datetime.UTC)
+    cert = (
+        x509.CertificateBuilder()
+        .subject_name(name)
+        .issuer_name(name)
+        .public_key(key.public_key())
+        .serial_number(x509.random_serial_number())
+        .not_valid_before(now - datetime.timedelta(minutes=1))
+        .not_valid_after(now + datetime.timedelta(hours=1))
+        .add_extension(x509.SubjectAlternativeName([x509.DNSName("localhost")]), critical=False)
+        .sign(key, hashes.SHA256())
+    )
+    certfile = tmp_path / "cert.pem"
+    keyfile = tmp_path / "key.pem"
+    certfile.write_bytes(cert.public_bytes(serialization.Encoding.PEM))
+    keyfile.write_bytes(
+        key.private_bytes(
+            serialization.Encoding.PEM,
+            serialization.PrivateFormat.PKCS8,
+            serialization.NoEncryption(),
+        )
+    )
+    return certfile, keyfile
+
+
+def _connect(port: int, certfile: Path, token: str):
+    from websockets.sync.client import connect
+
+    context = ssl.create_default_context(cafile=str(certfile))
+    return connect(
+        f"wss://localhost:{port}/",
+        ssl=context,
+        additional_headers={"Authorization": f"Bearer {token}"},
+    )
+
+
+def _handshake(shell: Any) -> Any:
+    from tests.conftest import ShellChannel
+
+    channel = ShellChannel()
+    shell.send(channel.hello)
+    channel.finish(shell.recv(timeout=10))
+    return channel
+
+
+def test_remote_kernel_requires_token_and_serves_one_shell(
+    tmp_path: Path, monkeypatch: pytest.MonkeyPatch, isolated_db_singleton: object
+) -> None:
+    import reos.ui_rpc_server as ui
+    from reos import kernel_websocket
+    from reos.db import get_db
+
+    monkeypatch.setattr(ui, "_session_key", None)
+    monkeypatch.setattr(ui, "_socket_mode", False)
+    ui._shutdown.clear()
+    certfile, keyfile = _certificate(tmp_path)
+    listener = kernel_websocket.WebSocketListener(
+        "127.0.0.1", 0, str(certfile), str(keyfile), TOKEN
+    )
+    server = threading.Thread(
+        target=ui._serve_connections,
+        args=(get_db(), listener.accept, None),
+        kwargs={"silence_output": False},
+        daemon=True,
+    )
+    server.start()
+    try:
+        with pytest.raises(websockets.exceptions.InvalidStatus):
+            _connect(listener.port, certfile, "x" * 32)
+
+        with _connect(listener.port, certfile, TOKEN) as shell:
+            channel = _handshake(shell)
+            request = {"jsonrpc": "2.0", "id": 1, "method": "initialize"}
+            request["params"] = {"session_key": "aa" * 32}
+            shell.send(channel.seal(request))
+            response = channel.open(shell.recv(timeout=10))
+            assert "result" in response
+
+            # A second shell is turned away while the first is connected
+            with _connect(listener.port, certfile, TOKEN) as second:
+                with pytest.raises(websockets.exceptions.ConnectionClosed) as closed:
+                    second.recv(timeout=10)
+                assert closed.value.rcvd is not None
+                assert closed.value.rcvd.code == kernel_websocket.TRY_AGAIN_LATER
+
+        # The kernel outlives the connection: the same key gets back in
+        with _connect(listener.port, certfile, TOKEN) as shell:
+            channel = _handshake(shell)
+            shell.send(channel.seal(request))
+            assert "result" in channel.open(shell.recv(timeout=10))
+            shell.send(channel.seal({"jsonrpc": "2.0", "method": "kernel/shutdown"}))
+        server.join(5)
+        assert not server.is_alive()
+    finally:
+        ui._shutdown.set()
+        listener.close()
@@ -0,0 +1,13 @@
+"""Tests for the method manifest the kernel announces in `initialize`."""
+
+from __future__ import annotations
+
+from reos import ui_rpc_server
+
+
+def test_manifest_lists_dispatched_methods():
+    methods = ui_rpc_server._method_manifest()
+    assert methods == sorted(set(methods))
+    for name in ("ping", "chat/respond", "vault/list", "export/end", "ingest/abort"):
+        assert name in methods
+    assert not any(" " in name for name in methods)
@@ -0,0 +1,46 @@
+"""Tests for power events forwarded by the Rust shell."""
+
+from __future__ import annotations
+
+import threading
+
+import pytest
+
+from reos import power
+from reos.power import PowerError, handle_event, wait_until_resumed
+
+
+@pytest.fixture(autouse=True)
+def _reset_power(monkeypatch):
+    monkeypatch.setattr(power, "_awake", threading.Event())
+    power._awake.set()
+    monkeypatch.setattr(power, "_battery", None)
+
+
+def test_suspend_pauses_until_resume():
+    handle_event({"kind": "suspend"})
+    assert not wait_until_resumed(timeout=0.01)
+    handle_event({"kind": "resume"})
+    assert wait_until_resumed(timeout=0.01)
+
+
+def test_low_battery_is_reported_once():
+    discharging = {"kind": "battery", "on_battery": True, "percent": 40, "low": False}
+    low = {"kind": "battery", "on_battery": True, "percent": 9, "low": True}
+    assert not handle_event(discharging)
+    assert handle_event(low)
+    assert not handle_event({**low, "percent": 8})
+    assert power.battery() == {"on_battery": True, "percent": 8, "low": True}
+
+
+@pytest.mark.parametrize(
+    "params",
+    [
+        {"kind": "hibernate"},
+        {"kind": "battery", "on_battery": True},
+        {"kind": "battery", "on_battery": True, "low": False, "percent": 101},
+    ],
+)
+def test_rejects_malformed_events(params):
+    with pytest.raises(PowerError):
+        handle_event(params)
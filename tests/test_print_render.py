@@ -0,0 +1,51 @@
+"""Tests for printable documents behind the print_document command."""
+
+from __future__ import annotations
+
+import pytest
+
+from reos import print_render
+from reos.print_render import PrintRenderError, render
+
+
+def test_conversation_prints_one_heading_per_message():
+    result = {
+        "messages": [
+            {"role": "user", "content": "Hi", "created_at": "2026-01-02"},
+            {"role": "assistant", "content": "Hello"},
+        ]
+    }
+    document = render("conversation/get_messages", result)
+    assert document["title"] == "Conversation"
+    assert document["blocks"] == [
+        {"kind": "heading", "text": "User · 2026-01-02"},
+        {"kind": "text", "text": "Hi"},
+        {"kind": "heading", "text": "Assistant"},
+        {"kind": "text", "text": "Hello"},
+    ]
+
+
+def test_markdown_headings_paragraphs_and_code():
+    markdown = "# Me\n\nLine one\nline two\n\n```\nx = 1\n```\n## Goals\nShip"
+    document = render("play/me/read", {"markdown": markdown})
+    assert document["blocks"] == [
+        {"kind": "heading", "text": "Me"},
+        {"kind": "text", "text": "Line one\nline two"},
+        {"kind": "code", "text": "x = 1"},
+        {"kind": "heading", "text": "Goals"},
+        {"kind": "text", "text": "Ship"},
+    ]
+
+
+def test_other_results_print_as_json():
+    document = render("system/live_state", {"cpu": 3})
+    assert document == {
+        "title": "system/live_state",
+        "blocks": [{"kind": "code", "text": '{\n  "cpu": 3\n}'}],
+    }
+
+
+def test_rejects_oversized_documents(monkeypatch):
+    monkeypatch.setattr(print_render, "MAX_TEXT_CHARS", 10)
+    with pytest.raises(PrintRenderError):
+        render("play/me/read", {"markdown": "x" * 11})
@@ -0,0 +1,48 @@
+"""Tests for session/export and session/import (kernel hot swap)."""
+
+from __future__ import annotations
+
+import pytest
+
+from reos import auth
+
+
+@pytest.fixture
+def fresh_store(monkeypatch: pytest.MonkeyPatch) -> auth.SessionStore:
+    store = auth.SessionStore()
+    monkeypatch.setattr(auth, "_session_store", store)
+    return store
+
+
+def _session(token: str) -> auth.Session:
+    now = auth.datetime.now(auth.timezone.utc)
+    return auth.Session(
+        token=token,
+        username="alice",
+        created_at=now,
+        last_activity=now,
+        key_material=bytes(range(32)),
+    )
+
+
+def test_sessions_round_trip(
+    fresh_store: auth.SessionStore, monkeypatch: pytest.MonkeyPatch
+) -> None:
+    fresh_store.insert(_session("tok"))
+    exported = auth.export_sessions()
+
+    monkeypatch.setattr(auth, "_session_store", auth.SessionStore())
+    assert auth.import_sessions(exported) == 1
+    session = auth.get_session("tok")
+    assert session is not None
+    assert session.username == "alice"
+    assert session.key_material == bytes(range(32))
+
+
+def test_import_refused_once_sessions_exist(fresh_store: auth.SessionStore) -> None:
+    fresh_store.insert(_session("tok"))
+    exported = auth.export_sessions()
+    with pytest.raises(ValueError):
+        auth.import_sessions(exported)
+    with pytest.raises(ValueError):
+        auth.import_sessions([{"token": "x"}])
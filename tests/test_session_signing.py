@@ -0,0 +1,89 @@
+"""Tests for __session claim signatures shared with the Rust shell."""
+
+from __future__ import annotations
+
+import hmac
+from hashlib import sha256
+
+from reos.session import (
+    session_claims_message,
+    shell_claims_message,
+    verify_session_claims,
+    verify_shell_claims,
+)
+
+KEY = bytes([7] * 32)
+IAT = 1_700_000_000
+
+
+def _signed(method: str = "chat/respond", **overrides: object) -> dict[str, object]:
+    claims: dict[str, object] = {
+        "username": "alice",
+        "session_id": "0123abcd",
+        "role": "user",
+        "iat": IAT,
+    }
+    claims.update(overrides)
+    message = session_claims_message(
+        str(claims["username"]), str(claims["session_id"]), str(claims["role"]), method, IAT
+    )
+    claims["sig"] = hmac.new(KEY, message, sha256).hexdigest()
+    return claims
+
+
+def test_matches_rust_test_vector():
+    """Same vector as kernel.rs test_session_signature_vector."""
+    message = session_claims_message("alice", "0123abcd", "user", "chat/respond", IAT)
+    assert (
+        hmac.new(KEY, message, sha256).hexdigest()
+        == "29aa3bf2878080adec4c653eac86184e9721cecd362275b7336ac433ef9bfe16"
+    )
+
+
+def test_valid_claims_verify():
+    assert verify_session_claims(_signed(), "chat/respond", KEY, now=IAT + 5)
+
+
+def test_tampered_claims_are_rejected():
+    claims = _signed()
+    claims["username"] = "root"
+    assert not verify_session_claims(claims, "chat/respond", KEY, now=IAT)
+
+
+def test_signature_is_bound_to_method():
+    assert not verify_session_claims(_signed(), "tools/call", KEY, now=IAT)
+
+
+def test_wrong_key_and_stale_claims_are_rejected():
+    assert not verify_session_claims(_signed(), "chat/respond", bytes(32), now=IAT)
+    assert not verify_session_claims(_signed(), "chat/respond", KEY, now=IAT + 3600)
+
+
+def test_missing_signature_is_rejected():
+    claims = _signed()
+    del claims["sig"]
+    assert not verify_session_claims(claims, "chat/respond", KEY, now=IAT)
+
+
+def _shell_signed(method: str = "auth/revoke_user") -> dict[str, object]:
+    sig = hmac.new(KEY, shell_claims_message(method, IAT), sha256).hexdigest()
+    return {"iat": IAT, "sig": sig}
+
+
+def test_shell_proof_matches_rust_test_vector():
+    """Same vector as kernel.rs test_shell_signature_vector."""
+    assert _shell_signed()["sig"] == (
+        "a80d81e1fb59acee74b6fea3cc2514201df93365b3d77c939a72aa539534b9d2"
+    )
+
+
+def test_shell_proof_verifies_for_its_method_only():
+    assert verify_shell_claims(_shell_signed(), "auth/revoke_user", KEY, now=IAT + 5)
+    assert not verify_shell_claims(_shell_signed(), "auth/login_verified", KEY, now=IAT)
+    assert not verify_shell_claims(_shell_signed(), "auth/revoke_user", bytes(32), now=IAT)
+    assert not verify_shell_claims(_shell_signed(), "auth/revoke_user", KEY, now=IAT + 3600)
+
+
+def test_session_claims_are_not_a_shell_proof():
+    claims = _signed("auth/revoke_user")
+    assert not verify_shell_claims(claims, "auth/revoke_user", KEY, now=IAT)
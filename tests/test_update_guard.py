@@ -0,0 +1,43 @@
+"""Tests for the update freeze behind update/prepare and update/resume."""
+
+from __future__ import annotations
+
+import threading
+
+import pytest
+
+from reos import update_guard
+
+
+def test_prepare_freezes_until_resume():
+    assert update_guard.prepare(timeout=0.1) == {"ready": True, "busy": []}
+    with pytest.raises(update_guard.UpdatePending):
+        with update_guard.operation("encrypted_write"):
+            pass
+    update_guard.resume()
+    with update_guard.operation("encrypted_write"):
+        pass
+
+
+def test_prepare_reports_busy_and_unfreezes():
+    started = threading.Event()
+    release = threading.Event()
+
+    def write() -> None:
+        with update_guard.operation("migration"):
+            started.set()
+            release.wait(5)
+
+    worker = threading.Thread(target=write)
+    worker.start()
+    started.wait(5)
+    try:
+        assert update_guard.prepare(timeout=0.05) == {"ready": False, "busy": ["migration"]}
+        # Not frozen after a failed prepare
+        with update_guard.operation("encrypted_write"):
+            pass
+    finally:
+        release.set()
+        worker.join()
+    assert update_guard.prepare(timeout=1) == {"ready": True, "busy": []}
+    update_guard.resume()
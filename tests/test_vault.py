@@ -0,0 +1,56 @@
+"""Tests for the per-user secrets vault behind the vault_* commands."""
+
+from __future__ import annotations
+
+from dataclasses import dataclass
+from pathlib import Path
+
+import pytest
+
+from reos.crypto_storage import CryptoStorage
+from reos.vault import Vault, VaultError
+
+
+@dataclass
+class _Session:
+    username: str
+    key_material: bytes
+    root: Path
+
+    def get_user_data_root(self) -> Path:
+        return self.root
+
+
+def _vault(tmp_path: Path) -> Vault:
+    session = _Session("alice", bytes(range(32)), tmp_path / "alice")
+    return Vault(CryptoStorage(session))  # type: ignore[arg-type]
+
+
+def test_set_get_list_delete(tmp_path: Path):
+    vault = _vault(tmp_path)
+    vault.set("openai.api_key", "sk-test", max_entries=10)
+    assert vault.get("openai.api_key") == "sk-test"
+    assert vault.list() == ["openai.api_key"]
+    # Stored encrypted
+    raw = (tmp_path / "alice" / "vault" / "openai.api_key.secret").read_bytes()
+    assert b"sk-test" not in raw
+
+    assert vault.delete("openai.api_key")
+    assert vault.get("openai.api_key") is None
+    assert not vault.delete("openai.api_key")
+
+
+def test_rejects_path_like_names(tmp_path: Path):
+    vault = _vault(tmp_path)
+    for name in ("../escape", ".hidden", "a/b", ""):
+        with pytest.raises(VaultError):
+            vault.set(name, "x", max_entries=10)
+
+
+def test_max_entries(tmp_path: Path):
+    vault = _vault(tmp_path)
+    vault.set("a", "1", max_entries=1)
+    # Overwriting an existing entry is still allowed
+    vault.set("a", "2", max_entries=1)
+    with pytest.raises(VaultError):
+        vault.set("b", "1", max_entries=1)
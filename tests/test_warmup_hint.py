@@ -0,0 +1,34 @@
+"""Tests for the `warmup/hint` requests the shell sends after login."""
+
+from __future__ import annotations
+
+
+def _rpc(db: object, *, req_id: int, params: dict) -> dict:
+    import reos.ui_rpc_server as ui
+
+    req = {"jsonrpc": "2.0", "id": req_id, "method": "warmup/hint", "params": params}
+    resp = ui._handle_jsonrpc_request(db, req)
+    assert resp is not None
+    return resp
+
+
+def test_caches_hint_reports_time(tmp_path, monkeypatch, isolated_db_singleton: object) -> None:
+    monkeypatch.setenv("REOS_DATA_DIR", str(tmp_path / "data"))
+
+    from reos.db import get_db
+
+    resp = _rpc(get_db(), req_id=1, params={"hint": "caches"})
+    assert resp["result"]["hint"] == "caches"
+    assert resp["result"]["ms"] >= 0
+
+
+def test_unknown_hint_and_missing_token_are_invalid(
+    tmp_path, monkeypatch, isolated_db_singleton: object
+) -> None:
+    monkeypatch.setenv("REOS_DATA_DIR", str(tmp_path / "data"))
+
+    from reos.db import get_db
+
+    db = get_db()
+    assert _rpc(db, req_id=1, params={"hint": "everything"})["error"]["code"] == -32602
+    assert _rpc(db, req_id=2, params={"hint": "store"})["error"]["code"] == -32602